@@ -47,6 +47,19 @@ fn main() {
                 .arg(Arg::new("workspace").long("workspace").takes_value(false).help("Build all members in the workspace."))
                 .arg(Arg::new("simd128").long("simd128").takes_value(false).help("Use 128-bit SIMD instruction set for WASM")),
         )
+        .subcommand(
+            App::new("check-shaders")
+                .about("Run tests while validating every shader that gets used, for all platforms.")
+                .long_about(
+                    "Run tests while validating shaders for all platforms: every shader that gets drawn, and every \
+                     shader that is registered using `register_shader!` once a test creates a `Cx`. Code gets \
+                     generated for every platform, but only the WGSL output gets validated (using naga). The GLSL ES \
+                     1.00 (WebGL and OpenGL), HLSL and Metal output is not validated, since there is no offline \
+                     validator for them.",
+                )
+                .arg(Arg::new("package").short('p').long("package").takes_value(true).help("Test only the specified package."))
+                .arg(Arg::new("features").long("features").takes_value(true).help("Specify additional feature flags.")),
+        )
         .get_matches();
 
     if let Some(cmd) = matches.subcommand_matches("build") {
@@ -60,6 +73,10 @@ fn main() {
         });
     }
 
+    if let Some(cmd) = matches.subcommand_matches("check-shaders") {
+        check_shaders(cmd.value_of("package").unwrap_or("").to_string(), cmd.value_of("features").unwrap_or("").to_string());
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     if let Some(cmd) = matches.subcommand_matches("install-deps") {
         if cmd.is_present("ci") {
@@ -124,3 +141,22 @@ fn build(opts: BuildOpts) {
     println!("{}", std::str::from_utf8(&out.stdout).ok().unwrap());
     println!("{}", std::str::from_utf8(&out.stderr).ok().unwrap());
 }
+
+/// Runs `cargo test` with the `shader-validation` feature of `wrflib`, which validates every shader when it first
+/// gets used, and every shader that is registered using `register_shader!` when the first `Cx` gets created.
+fn check_shaders(package: String, features: String) {
+    println!("    Running cargo test with shader validation");
+
+    let mut features = if features.is_empty() { vec![] } else { vec![features] };
+    features.push("wrflib/shader-validation".to_string());
+    let features = features.join(",");
+
+    let mut args = vec!["test", "--features", &features];
+    if !package.is_empty() {
+        args.push("-p");
+        args.push(&package);
+    }
+
+    let status = Command::new("cargo").args(args).status().expect("Failed to execute command");
+    std::process::exit(status.code().unwrap_or(1));
+}
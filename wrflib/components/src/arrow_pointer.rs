@@ -57,6 +57,7 @@ static MAIN_SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(MAIN_SHADER);
 
 impl ArrowPointerIns {
     pub fn draw(cx: &mut Cx, pos: Vec2, color: Vec4, direction: ArrowPointerDirection, size: Vec2) {
@@ -50,6 +50,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 #[derive(Default)]
 pub struct Button {
@@ -19,6 +19,7 @@ pub struct ChartTooltip {
     target_pos: Vec2,
     value: f32,
     axis: f32,
+    time: Option<f64>,
     dataset: usize,
 }
 
@@ -27,6 +28,7 @@ impl ChartTooltip {
         self.target_pos = current_element.normalized_data_point;
         self.value = current_element.data_point.y;
         self.axis = current_element.data_point.x;
+        self.time = current_element.time;
         self.dataset = current_element.dataset_index;
     }
 
@@ -40,7 +42,9 @@ impl ChartTooltip {
         let mut background_color = vec4(1., 1., 1., 1.) - text_color;
         background_color.w = 1.; // Keep a valid alpha color.
 
-        let size = config.tooltip.size.max(&vec2(130., 50.));
+        // Leave room for the date and time when using a time axis
+        let min_size = if config.time_axis.is_some() { vec2(190., 50.) } else { vec2(130., 50.) };
+        let size = config.tooltip.size.max(&min_size);
         let arrow_pointer_size = vec2(10., 10.);
 
         // Center the tooltip horizontally, always on top of the current element
@@ -78,7 +82,9 @@ impl ChartTooltip {
             let text_props = TextInsProps { text_style: TEXT_STYLE_MONO, color: text_color, ..TextInsProps::DEFAULT };
 
             let path = {
-                if (self.axis as usize) < config.labels.len() {
+                if let (Some(time), Some(time_axis)) = (self.time, &config.time_axis) {
+                    format_date_time(time * time_axis.unit.in_seconds() + time_axis.utc_offset)
+                } else if (self.axis as usize) < config.labels.len() {
                     config.labels[self.axis as usize].to_string()
                 } else {
                     format!("{}", self.axis)
@@ -91,9 +97,21 @@ impl ChartTooltip {
     }
 }
 
-/// TODO(hernan): Implement other chart types like bar, pie, etc...
+/// TODO(hernan): Implement other chart types like pie, etc...
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum ChartType {
+    /// Points connected by straight lines.
+    #[default]
     Line,
+    /// One bar per datum. Datasets are grouped side by side, or stacked when
+    /// [`ChartConfig::stacked`] is set.
+    Bar,
+    /// Points only, without any lines. Use [`ChartDataset::point_background_colors`] and
+    /// [`ChartDataset::point_radii`] to style each point individually.
+    Scatter,
+    /// Like [`ChartType::Line`], but the area between the line and the x axis (or
+    /// the dataset below when stacked) is filled with [`ChartDataset::background_color`].
+    Area,
 }
 
 /// Contains the data is going to be used to render the chart
@@ -105,6 +123,12 @@ pub enum ChartData<'a> {
     Empty,
     Values(&'a [f32]),
     Pairs(&'a [Vec2]),
+    /// Timestamps and their values, meant to be used with [`ChartConfig::time_axis`].
+    /// Both slices must have the same length.
+    TimeSeries {
+        times: &'a [f64],
+        values: &'a [f32],
+    },
 }
 
 impl<'a> ChartData<'a> {
@@ -116,10 +140,16 @@ impl<'a> ChartData<'a> {
         ChartData::Pairs(data)
     }
 
+    pub fn from_time_series(times: &'a [f64], values: &'a [f32]) -> ChartData<'a> {
+        debug_assert_eq!(times.len(), values.len());
+        ChartData::TimeSeries { times, values }
+    }
+
     pub fn len(&self) -> usize {
         match self {
             ChartData::Values(data) => data.len(),
             ChartData::Pairs(data) => data.len(),
+            ChartData::TimeSeries { times, values } => times.len().min(values.len()),
             ChartData::Empty => 0,
         }
     }
@@ -128,6 +158,7 @@ impl<'a> ChartData<'a> {
         match self {
             ChartData::Values(data) => data.is_empty(),
             ChartData::Pairs(data) => data.is_empty(),
+            ChartData::TimeSeries { .. } => self.len() == 0,
             ChartData::Empty => true,
         }
     }
@@ -154,23 +185,44 @@ impl<'a> ChartData<'a> {
                 }
                 (min, max)
             }
+            ChartData::TimeSeries { .. } => {
+                let mut min = lo;
+                let mut max = hi;
+                for i in 0..self.len() {
+                    let p = self.value_at(i);
+                    min = min.min(&p);
+                    max = max.max(&p);
+                }
+                (min, max)
+            }
             ChartData::Empty => (lo, hi),
         }
     }
 
+    /// Note that timestamps lose precision when converted to `f32`; see [`ChartData::time_at`].
     pub fn value_at(&self, i: usize) -> Vec2 {
         match self {
             ChartData::Values(data) => vec2(i as f32, data[i]),
             ChartData::Pairs(data) => data[i],
+            ChartData::TimeSeries { times, values } => vec2(times[i] as f32, values[i]),
             ChartData::Empty => vec2(INFINITY, INFINITY),
         }
     }
 
+    /// Full precision timestamp at the given index, for [`ChartData::TimeSeries`].
+    pub fn time_at(&self, i: usize) -> Option<f64> {
+        match self {
+            ChartData::TimeSeries { times, .. } => Some(times[i]),
+            _ => None,
+        }
+    }
+
     // TODO(hernan): Prevent copying data
     pub fn points(&self) -> Vec<Vec2> {
         match self {
             ChartData::Values(data) => data.iter().enumerate().map(|(x, y)| vec2(x as f32, *y)).collect(),
             ChartData::Pairs(data) => data.to_vec(),
+            ChartData::TimeSeries { .. } => (0..self.len()).map(|i| self.value_at(i)).collect(),
             ChartData::Empty => vec![],
         }
     }
@@ -180,12 +232,27 @@ impl<'a> ChartData<'a> {
 pub struct ChartDataset<'a> {
     pub label: String,
     pub data: ChartData<'a>,
+    /// Fill color used for bars and areas
+    pub background_color: Vec4,
     pub point_background_color: Vec4,
+    /// Per-point colors. When present, they override [`ChartDataset::point_background_color`]
+    /// for the points at the same index.
+    pub point_background_colors: &'a [Vec4],
     pub point_radius: f32,
+    /// Per-point radii. When present, they override [`ChartDataset::point_radius`]
+    /// for the points at the same index.
+    pub point_radii: &'a [f32],
     pub point_style: DrawPoints3dStyle,
     pub border_color: Vec4,
     pub border_width: f32,
     pub show_line: bool,
+    /// Key into [`ChartConfig::scales`] of the y axis this dataset is plotted against.
+    /// Datasets bound to different axes are scaled independently.
+    pub y_axis_id: &'a str,
+    /// Version of [`ChartDataset::data`]. The chart caches its layout of the data, and only recomputes it when the
+    /// revision or the length of the data changes, so increment this whenever the values change. The default of 0
+    /// means that the data isn't versioned, in which case the layout gets recomputed on every draw.
+    pub revision: u64,
 }
 
 impl<'a> Default for ChartDataset<'a> {
@@ -193,19 +260,47 @@ impl<'a> Default for ChartDataset<'a> {
         Self {
             label: String::new(),
             data: ChartData::Empty,
+            background_color: vec4(1., 1., 1., 0.5),
             point_background_color: COLOR_WHITE,
+            point_background_colors: &[],
             point_radius: 10.,
+            point_radii: &[],
             point_style: DrawPoints3dStyle::Circle,
             border_color: COLOR_WHITE,
             border_width: 2.,
             show_line: true,
+            y_axis_id: "y",
+            revision: 0,
         }
     }
 }
 
+/// Side of the chart where a y axis is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartAxisPosition {
+    Left,
+    Right,
+}
+
+/// Settings of an axis, keyed by its id in [`ChartConfig::scales`]. The x axis uses the
+/// `"x"` id, and y axes use the ids that datasets refer to in [`ChartDataset::y_axis_id`].
+///
+/// The range, in data units, is always visible and gets extended to fit the data. Use the
+/// default (empty) range to just fit the data. With a [`ChartConfig::time_axis`], the x range
+/// is in seconds relative to the earliest timestamp in the data.
+#[derive(Debug, Clone)]
 pub struct ChartScale {
     pub min: f32,
     pub max: f32,
+    /// Only used by y axes. Without a scale, the first y axis is drawn on the left and
+    /// the rest on the right.
+    pub position: ChartAxisPosition,
+}
+
+impl Default for ChartScale {
+    fn default() -> Self {
+        Self { min: INFINITY, max: NEG_INFINITY, position: ChartAxisPosition::Left }
+    }
 }
 
 #[derive(Clone)]
@@ -267,6 +362,32 @@ pub struct ChartTooltipConfig {
     pub renderer: Option<Arc<RwLock<dyn ChartTooltipRenderer>>>,
 }
 
+/// Side of the chart where the legend is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartLegendPosition {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Legend configuration
+///
+/// The legend shows the label of each dataset next to a swatch of its color. Clicking
+/// on an item hides or shows its dataset (see [`ChartEvent::LegendClick`]), and hovering
+/// over it dims the other datasets.
+#[derive(Debug, Clone)]
+pub struct ChartLegendConfig {
+    pub display: bool,
+    pub position: ChartLegendPosition,
+}
+
+impl Default for ChartLegendConfig {
+    fn default() -> Self {
+        Self { display: false, position: ChartLegendPosition::Top }
+    }
+}
+
 /// These options are based on the ones provided by ChartJS
 pub struct ChartConfig<'a> {
     pub chart_type: ChartType,
@@ -274,9 +395,16 @@ pub struct ChartConfig<'a> {
     /// it has to contain the same amount of elements as the dataset with the most values.
     pub labels: Vec<String>,
     pub datasets: Vec<ChartDataset<'a>>,
+    /// Stack datasets on top of each other instead of drawing them independently.
+    /// Values are stacked by datum index, and only apply to [`ChartType::Bar`] and
+    /// [`ChartType::Area`].
+    pub stacked: bool,
     pub scales: HashMap<String, ChartScale>,
+    /// Use timestamps for the x axis instead of plain numbers.
+    pub time_axis: Option<ChartTimeAxis>,
     pub style: ChartStyle,
     pub tooltip: ChartTooltipConfig,
+    pub legend: ChartLegendConfig,
     pub zoom_enabled: bool,
     pub pan_enabled: bool,
 }
@@ -287,9 +415,12 @@ impl<'a> Default for ChartConfig<'a> {
             chart_type: ChartType::Line,
             labels: Vec::<String>::default(),
             datasets: vec![],
+            stacked: false,
             scales: HashMap::new(),
+            time_axis: None,
             style: CHART_STYLE_DARK,
             tooltip: ChartTooltipConfig::default(),
+            legend: ChartLegendConfig::default(),
             pan_enabled: false,
             zoom_enabled: false,
         }
@@ -302,6 +433,10 @@ pub struct ChartCurrentElement {
     pub datum_index: usize,
     pub data_point: Vec2,
     pub normalized_data_point: Vec2,
+    /// Full precision timestamp of the element, when using [`ChartData::TimeSeries`].
+    /// With a [`ChartConfig::time_axis`], `data_point.x` is relative to the earliest
+    /// timestamp instead.
+    pub time: Option<f64>,
 }
 
 #[derive(Debug, Clone)]
@@ -314,14 +449,219 @@ pub enum ChartEvent {
         cursor: Vec2,
         /// The value at the current mouse position
         /// This value might not be part of the input data. Instead, it's interpolated
-        /// based on normalized values.
+        /// based on normalized values. With multiple y axes, `y` is in units of the first one.
         cursor_value: Vec2,
         /// If exists, we also retreive the element in the input data that is closest
         /// to the current mouse position
         current_element: Option<ChartCurrentElement>,
     },
+    /// A legend item was clicked, which toggled the visibility of its dataset.
+    LegendClick {
+        dataset_index: usize,
+        /// Whether the dataset is visible now.
+        visible: bool,
+    },
+    /// The PNG file requested using [`Chart::request_png`], or an error if it couldn't be encoded.
+    Png(Result<Vec<u8>, String>),
 }
 
+/// Minimum distance between labels of a time axis, in pixels. Larger than the regular
+/// minimum cell size, since time labels are wider.
+const MIN_TIME_LABEL_SPACING: f32 = 100.;
+
+/// Horizontal space taken by the labels of each y axis, in pixels.
+const AXIS_WIDTH: f32 = 50.;
+
+/// Height of each legend item, in pixels.
+const LEGEND_ITEM_HEIGHT: f32 = 20.;
+
+/// Size of the color swatch of each legend item, in pixels.
+const LEGEND_SWATCH_SIZE: f32 = 12.;
+
+/// Space between the swatch and the label of a legend item, in pixels.
+const LEGEND_LABEL_OFFSET: f32 = 6.;
+
+/// Space between legend items, and around the legend, in pixels.
+const LEGEND_SPACING: f32 = 15.;
+
+/// How much datasets are blended into the background when dimmed, from 0 to 1.
+const DIM_AMOUNT: f32 = 0.75;
+
+/// Fraction of the space available for each datum that is taken by bars.
+const BAR_PERCENTAGE: f32 = 0.8;
+
+/// A datum after laying out the chart, in data coordinates.
+#[derive(Debug, Clone)]
+struct ChartElement {
+    /// The value as provided in the dataset.
+    data_point: Vec2,
+    /// Where the element is drawn. For bars this is the center of its top edge.
+    /// For stacked charts, it includes the values of the datasets below it.
+    position: Vec2,
+    /// Where the bar or the filled area starts.
+    base: f32,
+    /// Width of the bar, if any.
+    width: f32,
+    /// Full precision timestamp, when using [`ChartData::TimeSeries`].
+    time: Option<f64>,
+    /// Full precision `position.x`, since converting timestamps into `f32` rounds them to the
+    /// same value when they are close together but far from [`Chart::time_origin`]. This
+    /// only gets converted into `f32` once projected onto the screen.
+    x: f64,
+}
+
+/// Identifies the data and settings that a [`ChartLayout`] was computed from.
+///
+/// Data is identified by the [`ChartDataset::revision`] and length of each dataset, which is
+/// cheap to check on every draw. Datasets with revision 0 aren't versioned, so a key that
+/// contains them never matches, see [`ChartLayoutKey::is_versioned`].
+#[derive(Debug, Clone, PartialEq)]
+struct ChartLayoutKey {
+    /// Revision and length of each dataset.
+    data: Vec<(u64, usize)>,
+    chart_type: ChartType,
+    stacked: bool,
+    time_unit: Option<ChartTimeUnit>,
+    hidden: Vec<bool>,
+    y_axis_ids: Vec<String>,
+    /// Sorted by id, since the order of a `HashMap` isn't stable.
+    scales: Vec<(String, f32, f32, ChartAxisPosition)>,
+}
+
+impl ChartLayoutKey {
+    fn new(config: &ChartConfig, hidden: &[bool]) -> Self {
+        let data = config.datasets.iter().map(|dataset| (dataset.revision, dataset.data.len())).collect();
+        let mut scales: Vec<_> =
+            config.scales.iter().map(|(id, scale)| (id.clone(), scale.min, scale.max, scale.position)).collect();
+        scales.sort_by(|a, b| a.0.cmp(&b.0));
+        Self {
+            data,
+            chart_type: config.chart_type,
+            stacked: config.stacked,
+            time_unit: config.time_axis.as_ref().map(|time_axis| time_axis.unit),
+            hidden: (0..config.datasets.len()).map(|index| hidden.get(index).copied().unwrap_or(false)).collect(),
+            y_axis_ids: config.datasets.iter().map(|dataset| dataset.y_axis_id.to_string()).collect(),
+            scales,
+        }
+    }
+
+    /// Whether every dataset has a [`ChartDataset::revision`], so that this key identifies the data.
+    fn is_versioned(&self) -> bool {
+        self.data.iter().all(|&(revision, _)| revision != 0)
+    }
+}
+
+/// A y axis that datasets are bound to, see [`ChartDataset::y_axis_id`].
+#[derive(Debug, Clone)]
+struct ChartYAxis {
+    id: String,
+    position: ChartAxisPosition,
+    min: f32,
+    max: f32,
+    /// Set once the axis is zoomed or panned on its own, which stops it from autoscaling.
+    zoomed: bool,
+    /// Where the labels of the axis are drawn, which is also where it can be zoomed and panned.
+    rect: Rect,
+}
+
+impl ChartYAxis {
+    /// Horizontal position of the axis line, on the side of `rect` facing the chart.
+    fn edge(&self) -> f32 {
+        match self.position {
+            ChartAxisPosition::Left => self.rect.pos.x + self.rect.size.x,
+            ChartAxisPosition::Right => self.rect.pos.x,
+        }
+    }
+}
+
+/// Elements of every dataset, and the bounds of the data. Only recomputed when the
+/// [`ChartLayoutKey`] changes, since it's proportional to the size of the data.
+#[derive(Default)]
+struct ChartLayout {
+    key: Option<ChartLayoutKey>,
+    elements: Vec<Vec<ChartElement>>,
+    /// Whether the elements of each dataset are sorted horizontally, which is needed
+    /// for decimation.
+    sorted: Vec<bool>,
+    /// Index into `y_axes` of each dataset.
+    axes: Vec<usize>,
+    min_x: f32,
+    max_x: f32,
+    /// Autoscaled y axes, with the first one used for the grid. There is always at least one.
+    y_axes: Vec<ChartYAxis>,
+}
+
+/// Identifies the view that [`ChartLod`] was computed for.
+#[derive(Debug, Clone, PartialEq)]
+struct ChartLodKey {
+    layout: Option<ChartLayoutKey>,
+    min_x: f32,
+    max_x: f32,
+    bounds: Rect,
+    zoom_pan: Option<Rect>,
+    dpi: f32,
+}
+
+/// Indices of the elements to draw for each dataset, which for large datasets is a small
+/// subset of them. Recomputed when either the data or the view changes.
+#[derive(Default)]
+struct ChartLod {
+    key: Option<ChartLodKey>,
+    indices: Vec<Vec<usize>>,
+}
+
+/// Datasets with more than this many visible elements per horizontal pixel get decimated.
+/// Decimation keeps up to 4 elements per pixel, so this keeps them from being decimated
+/// for no gain.
+const LOD_MAX_ELEMENTS_PER_PIXEL: usize = 4;
+
+/// Where a legend item is drawn, which is also where it can be clicked.
+#[derive(Debug, Clone)]
+struct ChartLegendItem {
+    dataset_index: usize,
+    rect: Rect,
+}
+
+/// Fills the region between two line segments that share the same horizontal range.
+/// Both bars and areas are drawn using this, with `rect` being the visible part of it.
+#[derive(Clone, Default)]
+#[repr(C)]
+pub(crate) struct ChartFillIns {
+    pub(crate) quad: QuadIns,
+    pub(crate) color: Vec4,
+    pub(crate) x_range: Vec2,
+    pub(crate) top: Vec2,
+    pub(crate) base: Vec2,
+}
+
+pub(crate) static FILL_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            instance color: vec4;
+            instance x_range: vec2;
+            instance top: vec2;
+            instance base: vec2;
+
+            fn pixel() -> vec4 {
+                let p = rect_pos + pos * rect_size;
+                let t = (p.x - x_range.x) / (x_range.y - x_range.x);
+                let top_y = mix(top.x, top.y, t);
+                let base_y = mix(base.x, base.y, t);
+                if p.y < min(top_y, base_y) || p.y > max(top_y, base_y) {
+                    return vec4(0.);
+                }
+                return vec4(color.rgb*color.a, color.a);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+register_shader!(FILL_SHADER);
+
 #[derive(Default)]
 pub struct Chart {
     bounds: Rect,
@@ -331,12 +671,30 @@ pub struct Chart {
     texture_area: Area,
     pass: Pass,
     color_texture: Texture,
-    min: Vec2,
-    max: Vec2,
+    min_x: f32,
+    max_x: f32,
+    y_axes: Vec<ChartYAxis>,
+    /// Earliest timestamp when using a time axis, in its unit. The x coordinates of the chart
+    /// are the seconds since this time, which are kept in `f64` until they are projected onto
+    /// the screen (see [`ChartElement::x`]).
+    time_origin: f64,
     background: Background,
-    areas: Vec<Area>,
+    chart_type: ChartType,
+    layout: ChartLayout,
+    lod: ChartLod,
     tooltip: ChartTooltip,
     tooltip_visible: bool,
+    /// Whether each dataset was hidden using the legend or [`Chart::set_dataset_visible`].
+    hidden_datasets: Vec<bool>,
+    legend_items: Vec<ChartLegendItem>,
+    /// Dataset whose legend item is being hovered, which dims the rest.
+    highlighted_dataset: Option<usize>,
+    /// Dataset whose legend item got a [`Event::PointerDown`], to toggle it on [`Event::PointerUp`].
+    pressed_legend_item: Option<usize>,
+    /// What got drawn during the last draw, used for exporting the chart.
+    scene: ChartScene,
+    /// Whether [`Chart::request_png`] is waiting for a readback of [`Chart::pass`].
+    png_requested: bool,
     // Keep an Arc<RwLock> to plugins since most of them are maybe owned
     // by other components. But we also need to call them here.
     pub plugins: Vec<Arc<RwLock<dyn ChartPlugin>>>,
@@ -344,6 +702,8 @@ pub struct Chart {
     zoom_enabled: bool,
     pan_enabled: bool,
     panning: bool,
+    /// Index into `y_axes` of the axis being panned on its own, if any.
+    panning_axis: Option<usize>,
 
     /// Set to `None` when we're viewing the whole chart (e.g. initial state,
     /// and when clicking the "reset view" button) which means that the axes
@@ -357,18 +717,35 @@ pub struct Chart {
 
 impl Chart {
     pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ChartEvent {
+        if let Event::PassReadback(readback) = event {
+            if self.png_requested && Some(readback.pass_id) == self.pass.pass_id {
+                self.png_requested = false;
+                return ChartEvent::Png(encode_png(readback.width, readback.height, &readback.pixels));
+            }
+            return ChartEvent::None;
+        }
+
         match event.hits_pointer(cx, self.component_id, self.texture_area.get_rect_for_first_instance(cx)) {
             Event::PointerDown(pd) => {
-                if self.pan_enabled {
+                if let Some(dataset_index) = self.get_legend_item_at(pd.rel) {
+                    self.pressed_legend_item = Some(dataset_index);
+                } else if self.pan_enabled {
                     self.last_pointer_pos = pd.rel;
                     self.panning = true;
+                    self.panning_axis = self.get_y_axis_at(pd.rel);
                 }
             }
             Event::PointerMove(pm) => {
-                if self.pan_enabled {
+                if self.panning {
                     let delta_pan = pm.rel - self.last_pointer_pos;
                     self.last_pointer_pos = pm.rel;
 
+                    if let Some(axis) = self.panning_axis {
+                        self.pan_y_axis(axis, delta_pan.y);
+                        cx.request_draw();
+                        return ChartEvent::None;
+                    }
+
                     if self.zoom_pan.is_none() {
                         self.zoom_pan = Some(self.bounds);
                     }
@@ -380,13 +757,30 @@ impl Chart {
                     cx.request_draw();
                 }
             }
-            Event::PointerUp(_) => {
+            Event::PointerUp(pu) => {
                 if self.pan_enabled {
                     self.panning = false;
+                    self.panning_axis = None;
+                }
+
+                if let Some(dataset_index) = self.pressed_legend_item.take() {
+                    if self.get_legend_item_at(pu.rel) == Some(dataset_index) {
+                        let visible = !self.is_dataset_visible(dataset_index);
+                        self.set_dataset_visible(dataset_index, visible);
+                        self.highlighted_dataset = if visible { Some(dataset_index) } else { None };
+                        cx.request_draw();
+                        return ChartEvent::LegendClick { dataset_index, visible };
+                    }
                 }
             }
             Event::PointerScroll(ps) => {
                 if self.zoom_enabled {
+                    if let Some(axis) = self.get_y_axis_at(ps.rel) {
+                        self.zoom_y_axis(axis, ps.rel.y, ps.scroll.y);
+                        cx.request_draw();
+                        return ChartEvent::None;
+                    }
+
                     if self.zoom_pan.is_none() {
                         self.zoom_pan = Some(self.bounds);
                     }
@@ -409,15 +803,23 @@ impl Chart {
                 }
             }
             Event::PointerHover(pe) => {
-                if self.panning || pe.hover_state == HoverState::Out {
+                let legend_item = if pe.hover_state == HoverState::Out { None } else { self.get_legend_item_at(pe.rel) };
+                // Hidden datasets can't be highlighted
+                let highlighted_dataset = legend_item.filter(|&dataset_index| self.is_dataset_visible(dataset_index));
+                if highlighted_dataset != self.highlighted_dataset {
+                    self.highlighted_dataset = highlighted_dataset;
+                    cx.request_draw();
+                }
+
+                if self.panning || pe.hover_state == HoverState::Out || legend_item.is_some() {
                     self.tooltip_visible = false;
                     return ChartEvent::PointerOut;
                 }
 
                 let mouse_pos_rel = pe.rel;
                 let cursor = mouse_pos_rel.clamp(&self.bounds.pos, &(self.bounds.pos + self.bounds.size));
-                let cursor_value = self.denormalize_data_point(cursor);
-                let current_element = self.get_element_at(cx, cursor_value);
+                let cursor_value = self.denormalize_data_point(cursor, 0);
+                let current_element = self.get_element_at(cursor);
                 if let Some(current_element) = &current_element {
                     self.tooltip.update(current_element);
                     self.tooltip_visible = true;
@@ -435,29 +837,73 @@ impl Chart {
         ChartEvent::None
     }
 
+    /// Whether a dataset is drawn. Datasets are hidden by clicking on their legend item,
+    /// or using [`Chart::set_dataset_visible`].
+    pub fn is_dataset_visible(&self, dataset_index: usize) -> bool {
+        !self.hidden_datasets.get(dataset_index).copied().unwrap_or(false)
+    }
+
+    /// Hides or shows a dataset, without changing the [`ChartConfig`]. Hidden datasets
+    /// are not taken into account for stacking and autoscaling either.
+    pub fn set_dataset_visible(&mut self, dataset_index: usize, visible: bool) {
+        if self.hidden_datasets.len() <= dataset_index {
+            self.hidden_datasets.resize(dataset_index + 1, false);
+        }
+        self.hidden_datasets[dataset_index] = !visible;
+    }
+
+    /// Whether a dataset is dimmed because another one is highlighted.
+    fn is_dataset_dimmed(&self, dataset_index: usize) -> bool {
+        matches!(self.highlighted_dataset, Some(highlighted_dataset) if highlighted_dataset != dataset_index)
+    }
+
+    /// Index of the dataset whose legend item is at `pos`, if any.
+    fn get_legend_item_at(&self, pos: Vec2) -> Option<usize> {
+        self.legend_items.iter().find(|item| item.rect.contains(pos)).map(|item| item.dataset_index)
+    }
+
+    /// Index of the y axis whose labels are at `pos`, if any.
+    fn get_y_axis_at(&self, pos: Vec2) -> Option<usize> {
+        self.y_axes.iter().position(|axis| axis.rect.contains(pos))
+    }
+
+    /// Moves the range of a y axis by `delta` pixels.
+    fn pan_y_axis(&mut self, axis: usize, delta: f32) {
+        let (_, scale) = self.get_offset_scale();
+        let y_axis = &mut self.y_axes[axis];
+        let delta = delta * (y_axis.max - y_axis.min) / (self.bounds.size.y * scale.y);
+        y_axis.min += delta;
+        y_axis.max += delta;
+        y_axis.zoomed = true;
+    }
+
+    /// Scales the range of a y axis around the value at `y`, in the same direction that
+    /// scrolling zooms the whole chart.
+    fn zoom_y_axis(&mut self, axis: usize, y: f32, scroll: f32) {
+        let value = self.denormalize_data_point(vec2(self.bounds.pos.x, y), axis).y;
+        let zoom_factor = (1. + scroll / self.bounds.size.y).max(0.1);
+        let y_axis = &mut self.y_axes[axis];
+        y_axis.min = value - (value - y_axis.min) * zoom_factor;
+        y_axis.max = value + (y_axis.max - value) * zoom_factor;
+        y_axis.zoomed = true;
+    }
+
     /// Gets the nearest element for a given value
-    /// `stride` is used to jump between elements, in case they are represented by
-    /// more than one value.
-    fn get_nearest_element_index(data: &[DrawPoints3dInstance], value: f32) -> Option<usize> {
-        if data.is_empty() {
+    /// Elements are assumed to be sorted by their horizontal position.
+    fn get_nearest_element_index(elements: &[ChartElement], value: f64) -> Option<usize> {
+        if elements.is_empty() {
             return None;
         }
 
-        let partition_index = {
-            let mut i = 0;
-            // find the first element higher than the given value.
-            while i < data.len() && data[i].user_info.x <= value {
-                i += 1;
-            }
-            i
-        };
+        // find the first element higher than the given value.
+        let partition_index = elements.partition_point(|element| element.x <= value);
 
-        if partition_index >= data.len() {
+        if partition_index >= elements.len() {
             // The dataset cannot be partitioned, meaning the reference time is beyond
             // the available data (which might happen because the data is being loaded).
             // In this case, just return the last element, which should be the nearest
             // one since data is assumed to be sorted.
-            return Some(data.len() - 1);
+            return Some(elements.len() - 1);
         }
 
         if partition_index == 0 {
@@ -467,31 +913,69 @@ impl Chart {
         }
 
         // Compare values with the previous one
-        if (data[partition_index].user_info.x - value).abs() >= (data[partition_index - 1].user_info.x - value).abs() {
+        if (elements[partition_index].x - value).abs() >= (elements[partition_index - 1].x - value).abs() {
             return Some(partition_index - 1);
         }
 
         Some(partition_index)
     }
 
-    fn get_element_at(&self, cx: &mut Cx, cursor: Vec2) -> Option<ChartCurrentElement> {
+    /// Distance in pixels between an element and the cursor, or `None` if the element
+    /// is outside the chart boundaries.
+    fn get_element_distance(&self, element: &ChartElement, axis: usize, cursor: Vec2) -> Option<f32> {
+        let position = self.normalize_element(element, axis);
+        if self.chart_type == ChartType::Bar {
+            let corner = vec2(
+                self.normalize_x(element.x - 0.5 * element.width as f64),
+                self.normalize_data_point(vec2(element.position.x, element.base), axis).y,
+            );
+            let (min, max) = (corner.min(&position), corner.max(&position));
+            if !self.bounds.intersects(Rect { pos: min, size: max - min }) {
+                return None;
+            }
+            // Zero when hovering the bar itself
+            let delta = (min - cursor).max(&(cursor - max)).max(&vec2(0., 0.));
+            return Some(delta.distance(&vec2(0., 0.)));
+        }
+
+        if !self.bounds.contains(position) {
+            return None;
+        }
+        if self.chart_type == ChartType::Scatter {
+            Some(position.distance(&cursor))
+        } else {
+            Some((position.y - cursor.y).abs())
+        }
+    }
+
+    fn get_element_at(&self, cursor: Vec2) -> Option<ChartCurrentElement> {
         let mut ret: Option<ChartCurrentElement> = None;
         let mut min_distance = INFINITY;
 
-        for dataset_index in 0..self.areas.len() {
-            let area = &self.areas[dataset_index];
-            let points = area.get_slice::<DrawPoints3dInstance>(cx);
-            if let Some(datum_index) = Self::get_nearest_element_index(points, cursor.x) {
-                let data_point = points[datum_index].user_info;
-                let distance = (data_point.y - cursor.y).abs();
-                if distance < min_distance {
-                    ret = Some(ChartCurrentElement {
-                        dataset_index,
-                        datum_index,
-                        data_point,
-                        normalized_data_point: self.normalize_data_point(data_point),
-                    });
-                    min_distance = distance;
+        for (dataset_index, (elements, &axis)) in self.layout.elements.iter().zip(&self.layout.axes).enumerate() {
+            // Scatter data doesn't need to be sorted, and we care about both axes
+            // anyway, so look at every point.
+            let candidates = if self.chart_type == ChartType::Scatter {
+                0..elements.len()
+            } else if let Some(datum_index) = Self::get_nearest_element_index(elements, self.denormalize_x(cursor.x)) {
+                datum_index..datum_index + 1
+            } else {
+                continue;
+            };
+
+            for datum_index in candidates {
+                let element = &elements[datum_index];
+                if let Some(distance) = self.get_element_distance(element, axis, cursor) {
+                    if distance < min_distance {
+                        ret = Some(ChartCurrentElement {
+                            dataset_index,
+                            datum_index,
+                            data_point: element.data_point,
+                            normalized_data_point: self.normalize_element(element, axis),
+                            time: element.time,
+                        });
+                        min_distance = distance;
+                    }
                 }
             }
         }
@@ -503,7 +987,7 @@ impl Chart {
         lo1 + (value - lo0) / (hi0 - lo0) * (hi1 - lo1)
     }
 
-    fn draw_grid(&mut self, cx: &mut Cx, config: &ChartConfig) {
+    fn draw_grid(&self, scene: &mut ChartScene, config: &ChartConfig) {
         let min_x = self.bounds.pos.x;
         let max_x = min_x + self.bounds.size.x;
         let min_y = self.bounds.pos.y;
@@ -512,18 +996,29 @@ impl Chart {
         // Minimum size of a cell, in pixels.
         let min_cell_size = 50.;
 
-        let data_min = self.min;
-        let data_max = self.denormalize_data_point(vec2(max_x, max_y));
+        // Bars are padded horizontally, so start from the first whole value to keep
+        // labels aligned with them.
+        let is_bar = self.chart_type == ChartType::Bar;
+        let data_min = vec2(if is_bar { self.min_x.ceil() } else { self.min_x }, self.y_axes[0].min);
+        let data_max = self.denormalize_data_point(vec2(max_x, max_y), 0);
 
-        let first = self.normalize_data_point(data_min);
-        let last = self.normalize_data_point(data_max);
+        let first = self.normalize_data_point(data_min, 0);
+        let last = self.normalize_data_point(data_max, 0);
 
         let max_lines = (data_max.x - data_min.x).abs();
         let step_size = (last.x - first.x).abs() / max_lines;
 
         let mut lines = vec![];
 
-        let mut draw_vertical_line = |x, round_op: &dyn Fn(f32) -> f32| {
+        // TODO(hernan): Render text labels if provided in config
+        let column_label = |x, round_op: &dyn Fn(f32) -> f32| {
+            let col_value = round_op(self.denormalize_data_point(vec2(x, min_y), 0).x);
+            // Values that round to zero can be slightly negative, which shouldn't show up as "-0".
+            let col_value = if col_value.abs() < 0.5 { 0. } else { col_value };
+            Some(format!("{:.0}", col_value))
+        };
+
+        let mut draw_vertical_line = |x, label: Option<String>| {
             lines.push(DrawLines3dInstance::from_segment(
                 vec3(x, min_y, 0.),
                 vec3(x, max_y + 10., 0.),
@@ -531,50 +1026,78 @@ impl Chart {
                 1.,
             ));
 
-            // TODO(hernan): Render text labels if provided in config
-            let label = {
-                let col_value = round_op(self.denormalize_data_point(vec2(x, min_y)).x);
-                format!("{:.0}", col_value)
+            let label = match label {
+                Some(label) => label,
+                None => return,
             };
 
-            TextIns::draw_str(
-                cx,
-                &label,
-                Vec2 { x, y: max_y + 10. },
-                &TextInsProps {
-                    position_anchoring: TEXT_ANCHOR_CENTER_H,
-                    color: config.style.label_color,
-                    ..TextInsProps::DEFAULT
-                },
-            );
+            scene.add_text(label, Vec2 { x, y: max_y + 10. }, TEXT_ANCHOR_CENTER_H, config.style.label_color);
         };
 
-        // Lines are rendered in reversed order first
-        // This prevents jumping when panning and zooming
-        let mut x = first.x;
-        let mut last_x = x + min_cell_size;
-        while x > min_x {
-            // Skip some lines in order to ensure there is enough
-            // space between them
-            if x < max_x && (last_x - x >= min_cell_size) {
-                draw_vertical_line(x, &|x| x);
-                last_x = x;
+        if let Some(time_axis) = &config.time_axis {
+            // Ticks are computed using absolute (local) times in f64, and only then converted
+            // back into chart coordinates, so they land exactly on round times.
+            let start = self.denormalize_data_point(vec2(min_x, min_y), 0).x;
+            let end = self.denormalize_data_point(vec2(max_x, min_y), 0).x;
+            let pixels_per_second = (max_x - min_x) / (end - start);
+            if pixels_per_second.is_finite() && pixels_per_second > 0. {
+                let origin = self.time_origin * time_axis.unit.in_seconds() + time_axis.utc_offset;
+                let interval = get_time_interval((MIN_TIME_LABEL_SPACING / pixels_per_second) as f64);
+                let first_tick = ((origin + start as f64) / interval).ceil();
+                let last_tick = ((origin + end as f64) / interval).floor();
+                let mut tick = first_tick;
+                while tick <= last_tick {
+                    let time = tick * interval;
+                    let x = self.normalize_x(time - origin);
+                    draw_vertical_line(x, Some(format_time(time, interval)));
+                    tick += 1.;
+                }
+            }
+            draw_vertical_line(min_x, None);
+            draw_vertical_line(max_x, None);
+        } else {
+            // Lines are rendered in reversed order first
+            // This prevents jumping when panning and zooming
+            let mut x = first.x;
+            let mut last_x = x + min_cell_size;
+            while x > min_x {
+                // Skip some lines in order to ensure there is enough
+                // space between them
+                if x < max_x && (last_x - x >= min_cell_size) {
+                    draw_vertical_line(x, column_label(x, &|x| x));
+                    last_x = x;
+                }
+                x -= step_size;
             }
-            x -= step_size;
-        }
 
-        let mut x = first.x;
-        let mut last_x = x - min_cell_size;
-        while x < max_x {
-            if min_x < x && (x - last_x >= min_cell_size) {
-                draw_vertical_line(x, &|x| x);
-                last_x = x;
+            let mut x = first.x;
+            let mut last_x = x - min_cell_size;
+            while x < max_x {
+                if min_x < x && (x - last_x >= min_cell_size) {
+                    draw_vertical_line(x, column_label(x, &|x| x));
+                    last_x = x;
+                }
+                x += step_size;
+            }
+
+            if is_bar {
+                // Bars don't line up with the boundaries, so labelling them would be misleading
+                draw_vertical_line(min_x, None);
+                draw_vertical_line(max_x, None);
+            } else {
+                draw_vertical_line(min_x, column_label(min_x, &|x| x.floor()));
+                draw_vertical_line(max_x, column_label(max_x, &|x| x.ceil()));
             }
-            x += step_size;
         }
 
-        draw_vertical_line(min_x, &|x| x.floor());
-        draw_vertical_line(max_x, &|x| x.ceil());
+        // Rows are placed using the first y axis, and every axis labels them with its own values.
+        let precisions: Vec<usize> = (0..self.y_axes.len())
+            .map(|axis| {
+                let range = self.denormalize_data_point(vec2(min_x, min_y), axis).y
+                    - self.denormalize_data_point(vec2(min_x, max_y), axis).y;
+                Self::get_label_precision(range.abs())
+            })
+            .collect();
 
         let mut draw_horizontal_line = |y, round_op: &dyn Fn(f32) -> f32| {
             lines.push(DrawLines3dInstance::from_segment(
@@ -584,19 +1107,33 @@ impl Chart {
                 1.,
             ));
 
-            // Flip min_y/max_y since y coordinate is inverted
-            let row_value = round_op(self.denormalize_data_point(vec2(min_x, y)).y);
-
-            TextIns::draw_str(
-                cx,
-                &format!("{:.0}", row_value),
-                Vec2 { x: min_x - 15., y },
-                &TextInsProps {
-                    position_anchoring: TEXT_ANCHOR_RIGHT + TEXT_ANCHOR_CENTER_V,
-                    color: config.style.label_color,
-                    ..TextInsProps::DEFAULT
-                },
-            );
+            for (axis_index, axis) in self.y_axes.iter().enumerate() {
+                let edge = axis.edge();
+                let (tick, anchoring) = match axis.position {
+                    ChartAxisPosition::Left => (-10., TEXT_ANCHOR_RIGHT),
+                    ChartAxisPosition::Right => (10., TEXT_ANCHOR_LEFT),
+                };
+                // The row itself already reaches into the labels of the first axis
+                if edge != min_x {
+                    lines.push(DrawLines3dInstance::from_segment(
+                        vec3(edge, y, 0.),
+                        vec3(edge + tick, y, 0.),
+                        config.style.grid_color,
+                        1.,
+                    ));
+                }
+
+                let precision = precisions[axis_index];
+                let factor = 10_f32.powi(precision as i32);
+                let row_value = round_op(self.denormalize_data_point(vec2(min_x, y), axis_index).y * factor) / factor;
+
+                scene.add_text(
+                    format!("{:.*}", precision, row_value),
+                    Vec2 { x: edge + 1.5 * tick, y },
+                    anchoring + TEXT_ANCHOR_CENTER_V,
+                    config.style.label_color,
+                );
+            }
         };
 
         // See comments above for rendering vertical lines
@@ -626,14 +1163,17 @@ impl Chart {
         // Draw axes a bit brighter than columns/rows
         let axis_color = vec4(0.5, 0.5, 0.5, 1.);
         lines.push(DrawLines3dInstance::from_segment(vec3(min_x, max_y, 0.), vec3(max_x, max_y, 0.), axis_color, 1.));
-        lines.push(DrawLines3dInstance::from_segment(vec3(min_x, min_y, 0.), vec3(min_x, max_y, 0.), axis_color, 1.));
+        for axis in &self.y_axes {
+            let edge = axis.edge();
+            lines.push(DrawLines3dInstance::from_segment(vec3(edge, min_y, 0.), vec3(edge, max_y, 0.), axis_color, 1.));
+        }
 
-        DrawLines3d::draw(cx, &lines, Default::default());
+        scene.add_lines(lines);
     }
 
     /// Use Liang-Barsky algorithm to clip line its points are both inside
     /// the chart boundaries (see: <https://en.wikipedia.org/wiki/Liang%E2%80%93Barsky_algorithm>)
-    fn draw_lines(&mut self, cx: &mut Cx, data: &[Vec2], color: Vec4, scale: f32) {
+    fn draw_lines(&self, scene: &mut ChartScene, data: &[Vec2], color: Vec4, scale: f32) {
         let min_x = self.bounds.pos.x;
         let max_x = min_x + self.bounds.size.x;
         let min_y = self.bounds.pos.y;
@@ -691,40 +1231,246 @@ impl Chart {
             }
         }
 
-        DrawLines3d::draw(cx, &lines, Default::default());
+        scene.add_lines(lines);
     }
 
+    /// Draws the points at `indices`, with `normalized_data` containing their screen positions.
+    /// Colors are passed through `dim`, see [`Chart::dim_color`].
     fn draw_points(
-        &mut self,
-        cx: &mut Cx,
+        &self,
+        scene: &mut ChartScene,
         normalized_data: &[Vec2],
-        original_data: &[Vec2],
+        elements: &[ChartElement],
+        indices: &[usize],
+        dataset: &ChartDataset,
+        dim: &dyn Fn(Vec4) -> Vec4,
+    ) {
+        let dpi = scene.dpi;
+        let mut points = Vec::<DrawPoints3dInstance>::with_capacity(normalized_data.len());
+        for (p, &datum_index) in normalized_data.iter().zip(indices) {
+            // Check if point is inside the chart boundaries before drawing
+            if self.bounds.contains(*p) {
+                let color = dataset.point_background_colors.get(datum_index).unwrap_or(&dataset.point_background_color);
+                let radius = dataset.point_radii.get(datum_index).unwrap_or(&dataset.point_radius);
+                points.push(DrawPoints3dInstance {
+                    position: p.to_vec3(),
+                    color: dim(*color).to_vec3(),
+                    size: radius * dpi,
+                    user_info: elements[datum_index].data_point,
+                });
+            }
+        }
+
+        scene.add_points(points, dataset.point_style.clone());
+    }
+
+    /// Draws one bar per element, from its base up to its position.
+    fn draw_bars(&self, scene: &mut ChartScene, elements: &[ChartElement], axis: usize, color: Vec4) {
+        let fills: Vec<ChartFillIns> = elements
+            .iter()
+            .filter_map(|element| {
+                let half_width = 0.5 * element.width as f64;
+                let top_left =
+                    vec2(self.normalize_x(element.x - half_width), self.normalize_data_point(element.position, axis).y);
+                let bottom_right = vec2(
+                    self.normalize_x(element.x + half_width),
+                    self.normalize_data_point(vec2(element.position.x, element.base), axis).y,
+                );
+                self.get_fill(
+                    vec2(top_left.x, bottom_right.x),
+                    vec2(top_left.y, top_left.y),
+                    vec2(bottom_right.y, bottom_right.y),
+                    color,
+                )
+            })
+            .collect();
+        scene.add_fills(fills);
+    }
+
+    /// Fills the area between consecutive elements at `indices` and their bases.
+    fn draw_area(
+        &self,
+        scene: &mut ChartScene,
+        normalized_data: &[Vec2],
+        elements: &[ChartElement],
+        indices: &[usize],
+        axis: usize,
         color: Vec4,
-        scale: f32,
-        point_style: DrawPoints3dStyle,
-    ) -> Area {
-        let min_x = self.bounds.pos.x;
-        let max_x = min_x + self.bounds.size.x;
-        let min_y = self.bounds.pos.y;
-        let max_y = min_y + self.bounds.size.y;
+    ) {
+        let mut fills = Vec::with_capacity(indices.len());
+        for i in 1..indices.len() {
+            let (a, b) = (normalized_data[i - 1], normalized_data[i]);
+            let (element_a, element_b) = (&elements[indices[i - 1]], &elements[indices[i]]);
+            let base_a = self.normalize_data_point(vec2(element_a.position.x, element_a.base), axis).y;
+            let base_b = self.normalize_data_point(vec2(element_b.position.x, element_b.base), axis).y;
+            fills.extend(self.get_fill(vec2(a.x, b.x), vec2(a.y, b.y), vec2(base_a, base_b), color));
+        }
+        scene.add_fills(fills);
+    }
 
-        let color = color.to_vec3();
-        let size = scale;
-        let mut points = Vec::<DrawPoints3dInstance>::with_capacity(normalized_data.len());
-        for i in 0..normalized_data.len() {
-            let p = normalized_data[i];
+    /// Builds a fill between the `top` and `base` segments (in screen coordinates) spanning
+    /// `x_range`, restricted to the chart boundaries. Returns `None` if nothing is visible.
+    fn get_fill(&self, x_range: Vec2, top: Vec2, base: Vec2, color: Vec4) -> Option<ChartFillIns> {
+        if x_range.y - x_range.x < EPSILON {
+            return None;
+        }
 
-            // Check if point is inside the chart boundaries before drawing
-            if min_x <= p.x && p.x <= max_x && min_y <= p.y && p.y <= max_y {
-                points.push(DrawPoints3dInstance { position: p.to_vec3(), color, size, user_info: original_data[i] });
+        let min = vec2(x_range.x, top.x.min(top.y).min(base.x).min(base.y)).max(&self.bounds.pos);
+        let max = vec2(x_range.y, top.x.max(top.y).max(base.x).max(base.y)).min(&(self.bounds.pos + self.bounds.size));
+        if min.x >= max.x || min.y >= max.y {
+            return None;
+        }
+
+        Some(ChartFillIns { quad: QuadIns::from_rect(Rect { pos: min, size: max - min }), color, x_range, top, base })
+    }
+
+    /// Blends a color into the background, keeping its opacity. Used to make datasets
+    /// stand out less.
+    fn dim_color(color: Vec4, background_color: Vec4) -> Vec4 {
+        let dimmed = Vec4::mix(color, background_color, DIM_AMOUNT);
+        vec4(dimmed.x, dimmed.y, dimmed.z, color.w)
+    }
+
+    /// Width of `text` when drawn using `props`, in pixels.
+    fn get_text_width(cx: &Cx, text: &str, props: &TextInsProps) -> f32 {
+        let mut width = 0.;
+        TextIns::generate_2d_glyphs(
+            &props.text_style,
+            &cx.fonts_data,
+            cx.current_dpi_factor,
+            props.font_scale,
+            props.draw_depth,
+            props.color,
+            vec2(0., 0.),
+            0,
+            &text.chars().collect::<Vec<char>>(),
+            |_, _, x, advance| {
+                width = x + advance;
+                0.
+            },
+        );
+        width
+    }
+
+    /// Computes where each legend item goes, storing them in [`Chart::legend_items`].
+    /// Returns the part of `rect` that is left for the chart itself.
+    fn layout_legend(&mut self, cx: &Cx, config: &ChartConfig, rect: Rect) -> Rect {
+        self.legend_items.clear();
+        if !config.legend.display || config.datasets.is_empty() {
+            return rect;
+        }
+
+        let widths: Vec<f32> = config
+            .datasets
+            .iter()
+            .map(|dataset| {
+                LEGEND_SWATCH_SIZE + LEGEND_LABEL_OFFSET + Self::get_text_width(cx, &dataset.label, &TextInsProps::DEFAULT)
+            })
+            .collect();
+
+        match config.legend.position {
+            ChartLegendPosition::Top | ChartLegendPosition::Bottom => {
+                // Items are laid out in centered rows, wrapping when they don't fit
+                let max_row_width = rect.size.x - 2. * LEGEND_SPACING;
+                let mut rows: Vec<(Vec<usize>, f32)> = vec![];
+                for (dataset_index, &width) in widths.iter().enumerate() {
+                    match rows.last_mut() {
+                        Some((row, row_width)) if *row_width + LEGEND_SPACING + width <= max_row_width => {
+                            row.push(dataset_index);
+                            *row_width += LEGEND_SPACING + width;
+                        }
+                        _ => rows.push((vec![dataset_index], width)),
+                    }
+                }
+
+                let height = rows.len() as f32 * LEGEND_ITEM_HEIGHT + LEGEND_SPACING;
+                let top = match config.legend.position {
+                    ChartLegendPosition::Top => rect.pos.y + 0.5 * LEGEND_SPACING,
+                    _ => rect.pos.y + rect.size.y - height + 0.5 * LEGEND_SPACING,
+                };
+                for (row_index, (row, row_width)) in rows.iter().enumerate() {
+                    let mut x = rect.pos.x + 0.5 * (rect.size.x - row_width);
+                    let y = top + row_index as f32 * LEGEND_ITEM_HEIGHT;
+                    for &dataset_index in row {
+                        let size = vec2(widths[dataset_index], LEGEND_ITEM_HEIGHT);
+                        self.legend_items.push(ChartLegendItem { dataset_index, rect: Rect { pos: vec2(x, y), size } });
+                        x += widths[dataset_index] + LEGEND_SPACING;
+                    }
+                }
+
+                let size = rect.size - vec2(0., height);
+                match config.legend.position {
+                    ChartLegendPosition::Top => Rect { pos: rect.pos + vec2(0., height), size },
+                    _ => Rect { pos: rect.pos, size },
+                }
+            }
+            ChartLegendPosition::Left | ChartLegendPosition::Right => {
+                // Items are laid out in a column, starting from the top
+                let width = widths.iter().fold(0_f32, |max, width| max.max(*width)) + 2. * LEGEND_SPACING;
+                let left = match config.legend.position {
+                    ChartLegendPosition::Left => rect.pos.x + LEGEND_SPACING,
+                    _ => rect.pos.x + rect.size.x - width + LEGEND_SPACING,
+                };
+                for (dataset_index, &item_width) in widths.iter().enumerate() {
+                    let pos = vec2(left, rect.pos.y + 0.5 * LEGEND_SPACING + dataset_index as f32 * LEGEND_ITEM_HEIGHT);
+                    let size = vec2(item_width, LEGEND_ITEM_HEIGHT);
+                    self.legend_items.push(ChartLegendItem { dataset_index, rect: Rect { pos, size } });
+                }
+
+                let size = rect.size - vec2(width, 0.);
+                match config.legend.position {
+                    ChartLegendPosition::Left => Rect { pos: rect.pos + vec2(width, 0.), size },
+                    _ => Rect { pos: rect.pos, size },
+                }
             }
         }
+    }
 
-        DrawPoints3d::draw(
-            cx,
-            &points,
-            DrawPoints3dOptions { use_screen_space: true, point_style, ..DrawPoints3dOptions::default() },
-        )
+    /// Draws a color swatch and the label of each dataset, dimming hidden datasets and the
+    /// ones that are not highlighted. Labels of hidden datasets are also struck through.
+    fn draw_legend(&self, scene: &mut ChartScene, config: &ChartConfig) {
+        let mut swatches = Vec::with_capacity(self.legend_items.len());
+        let mut strikethroughs = vec![];
+        for item in &self.legend_items {
+            let dataset = &config.datasets[item.dataset_index];
+            let color = match config.chart_type {
+                ChartType::Bar | ChartType::Area => dataset.background_color,
+                ChartType::Scatter => dataset.point_background_color,
+                ChartType::Line => dataset.border_color,
+            };
+            let hidden = !self.is_dataset_visible(item.dataset_index);
+            let dimmed = hidden || self.is_dataset_dimmed(item.dataset_index);
+            let dim = |color| if dimmed { Self::dim_color(color, config.style.background_color) } else { color };
+
+            let pos = item.rect.pos + vec2(0., 0.5 * (LEGEND_ITEM_HEIGHT - LEGEND_SWATCH_SIZE));
+            let (min, max) = (pos, pos + vec2(LEGEND_SWATCH_SIZE, LEGEND_SWATCH_SIZE));
+            swatches.push(ChartFillIns {
+                quad: QuadIns::from_rect(Rect { pos: min, size: max - min }),
+                color: dim(color),
+                x_range: vec2(min.x, max.x),
+                top: vec2(min.y, min.y),
+                base: vec2(max.y, max.y),
+            });
+
+            let label_pos = item.rect.pos + vec2(LEGEND_SWATCH_SIZE + LEGEND_LABEL_OFFSET, 0.5 * LEGEND_ITEM_HEIGHT);
+            if hidden {
+                strikethroughs.push(DrawLines3dInstance::from_segment(
+                    label_pos.to_vec3(),
+                    vec3(item.rect.pos.x + item.rect.size.x, label_pos.y, 0.),
+                    dim(config.style.label_color),
+                    1.,
+                ));
+            }
+
+            scene.add_text(
+                dataset.label.clone(),
+                label_pos,
+                TEXT_ANCHOR_LEFT + TEXT_ANCHOR_CENTER_V,
+                dim(config.style.label_color),
+            );
+        }
+        scene.add_fills(swatches);
+        scene.add_lines(strikethroughs);
     }
 
     /// Compute offset and scaling based on zoom/pan values
@@ -738,46 +1484,57 @@ impl Chart {
         }
     }
 
-    /// Transform a data point from data coordinates to normalized screen coordinates
-    fn normalize_data_point(&self, data_point: Vec2) -> Vec2 {
+    /// Transform a data point from data coordinates to normalized screen coordinates,
+    /// using the range of the y axis at index `axis`.
+    fn normalize_data_point(&self, data_point: Vec2, axis: usize) -> Vec2 {
         let (offset, scale) = self.get_offset_scale();
-        offset
-            + scale
-                * vec2(
-                    // For x axis, we want values to be in the range [bounds.pos.x, bounds.pos.x + bounds.size.x],
-                    // using (p.x - min.x) / (max.x - min.x) for interpolation.
-                    self.bounds.pos.x + (data_point.x - self.min.x) / (self.max.x - self.min.x) * self.bounds.size.x,
-                    // For y axis, it's a similar process except that we want charts to start at the bottom instead.
-                    // Then, we add bounds.size.y and subtract the interpolated value.
-                    (self.bounds.pos.y + self.bounds.size.y)
-                        - (data_point.y - self.min.y) / (self.max.y - self.min.y) * self.bounds.size.y,
-                )
+        let y_axis = &self.y_axes[axis];
+        vec2(
+            self.normalize_x(data_point.x as f64),
+            // For y axis, we want charts to start at the bottom instead. So we add bounds.size.y
+            // and subtract the interpolated value.
+            offset.y
+                + scale.y
+                    * ((self.bounds.pos.y + self.bounds.size.y)
+                        - (data_point.y - y_axis.min) / (y_axis.max - y_axis.min) * self.bounds.size.y),
+        )
+    }
+
+    /// Transform an x coordinate from data coordinates to normalized screen coordinates. This is
+    /// computed in `f64`, so that [`ChartElement::x`] keeps its precision until it's on the screen.
+    fn normalize_x(&self, x: f64) -> f32 {
+        let (offset, scale) = self.get_offset_scale();
+        let (min_x, max_x) = (self.min_x as f64, self.max_x as f64);
+        // We want values to be in the range [bounds.pos.x, bounds.pos.x + bounds.size.x],
+        // using (p.x - min.x) / (max.x - min.x) for interpolation.
+        let x = self.bounds.pos.x as f64 + (x - min_x) / (max_x - min_x) * self.bounds.size.x as f64;
+        (offset.x as f64 + scale.x as f64 * x) as f32
+    }
+
+    /// Like [`Chart::normalize_data_point`] for the position of an element, using its full
+    /// precision [`ChartElement::x`].
+    fn normalize_element(&self, element: &ChartElement, axis: usize) -> Vec2 {
+        vec2(self.normalize_x(element.x), self.normalize_data_point(element.position, axis).y)
     }
 
-    /// Transform a normalized data point from screen coordinates to data coordinates
-    fn denormalize_data_point(&self, normalized_data_point: Vec2) -> Vec2 {
+    /// Transform a normalized data point from screen coordinates to data coordinates,
+    /// using the range of the y axis at index `axis`.
+    fn denormalize_data_point(&self, normalized_data_point: Vec2, axis: usize) -> Vec2 {
         let (offset, scale) = self.get_offset_scale();
-        let normalized_data_point = (normalized_data_point - offset) / scale;
+        let y_axis = &self.y_axes[axis];
+        let normalized_y = (normalized_data_point.y - offset.y) / scale.y;
         vec2(
-            Self::remap(
-                normalized_data_point.x,
-                self.bounds.pos.x,
-                self.bounds.pos.x + self.bounds.size.x,
-                self.min.x,
-                self.max.x,
-            ),
-            Self::remap(
-                normalized_data_point.y,
-                self.bounds.pos.y,
-                self.bounds.pos.y + self.bounds.size.y,
-                self.max.y,
-                self.min.y,
-            ),
+            self.denormalize_x(normalized_data_point.x) as f32,
+            Self::remap(normalized_y, self.bounds.pos.y, self.bounds.pos.y + self.bounds.size.y, y_axis.max, y_axis.min),
         )
     }
 
-    fn normalize(&self, data: &[Vec2]) -> Vec<Vec2> {
-        data.iter().map(|p| self.normalize_data_point(*p)).collect()
+    /// Inverse of [`Chart::normalize_x`].
+    fn denormalize_x(&self, normalized_x: f32) -> f64 {
+        let (offset, scale) = self.get_offset_scale();
+        let (min_x, max_x) = (self.min_x as f64, self.max_x as f64);
+        let x = (normalized_x as f64 - offset.x as f64) / scale.x as f64;
+        min_x + (x - self.bounds.pos.x as f64) / self.bounds.size.x as f64 * (max_x - min_x)
     }
 
     /// Rounds a number to the closest power of 10, rounded up
@@ -800,33 +1557,352 @@ impl Chart {
         vec2(value.x, if value.y < 0. { -Self::round_up_to_10s(value.y.abs()) } else { Self::round_down_to_10s(value.y) })
     }
 
-    fn get_min_max(config: &ChartConfig) -> (Vec2, Vec2) {
-        let mut min = vec2(INFINITY, INFINITY);
-        let mut max = vec2(NEG_INFINITY, NEG_INFINITY);
+    /// Number of decimals needed to tell apart the labels of an axis spanning `range`.
+    fn get_label_precision(range: f32) -> usize {
+        if range >= 10. {
+            0
+        } else {
+            ((1. - range.log10()).ceil().max(0.) as usize).min(6)
+        }
+    }
 
-        if let Some(x_scale) = config.scales.get("x") {
-            min.x = x_scale.min;
-            max.x = x_scale.max;
+    /// Horizontal space available for each bar group, which is the smallest distance between
+    /// two consecutive values.
+    fn get_bar_slot_width(config: &ChartConfig, time_origin: f64) -> f32 {
+        let mut slot_width = INFINITY;
+        for dataset in &config.datasets {
+            for i in 1..dataset.data.len() {
+                let distance = (Self::get_data_point(config, &dataset.data, i, time_origin).x
+                    - Self::get_data_point(config, &dataset.data, i - 1, time_origin).x)
+                    .abs();
+                if distance > EPSILON {
+                    slot_width = slot_width.min(distance);
+                }
+            }
         }
+        if slot_width == INFINITY {
+            1.
+        } else {
+            slot_width
+        }
+    }
 
+    /// Earliest timestamp in the data, or zero if there are none.
+    fn get_time_origin(config: &ChartConfig) -> f64 {
+        let mut time_origin = f64::INFINITY;
         for dataset in &config.datasets {
-            let (lo, hi) = dataset.data.min_max(min, max);
-            min = lo;
-            max = hi;
+            for i in 0..dataset.data.len() {
+                if let Some(time) = dataset.data.time_at(i) {
+                    time_origin = time_origin.min(time);
+                }
+            }
+        }
+        if time_origin.is_finite() {
+            time_origin
+        } else {
+            0.
+        }
+    }
+
+    /// Value of a datum in chart coordinates. When using a time axis, timestamps are
+    /// converted into seconds since `time_origin`.
+    fn get_data_point(config: &ChartConfig, data: &ChartData, index: usize, time_origin: f64) -> Vec2 {
+        vec2(Self::get_data_x(config, data, index, time_origin) as f32, data.value_at(index).y)
+    }
+
+    /// Full precision x coordinate of [`Chart::get_data_point`].
+    fn get_data_x(config: &ChartConfig, data: &ChartData, index: usize, time_origin: f64) -> f64 {
+        match (&config.time_axis, data.time_at(index)) {
+            (Some(time_axis), Some(time)) => (time - time_origin) * time_axis.unit.in_seconds(),
+            _ => data.value_at(index).x as f64,
+        }
+    }
+
+    /// Index of the y axis of each dataset, and the ids of the y axes in order of appearance.
+    /// There is always at least one y axis, even without datasets.
+    fn get_y_axis_ids<'a>(config: &ChartConfig<'a>) -> (Vec<usize>, Vec<&'a str>) {
+        let mut ids = vec![];
+        let axes = config
+            .datasets
+            .iter()
+            .map(|dataset| {
+                ids.iter().position(|id| *id == dataset.y_axis_id).unwrap_or_else(|| {
+                    ids.push(dataset.y_axis_id);
+                    ids.len() - 1
+                })
+            })
+            .collect();
+        if ids.is_empty() {
+            ids.push("y");
         }
+        (axes, ids)
+    }
+
+    /// Computes where each datum is drawn, taking into account grouping and stacking.
+    /// Datasets that are `hidden` get no elements. Also returns the slot width for bar charts.
+    fn layout_elements(config: &ChartConfig, hidden: &[bool], time_origin: f64) -> (Vec<Vec<ChartElement>>, f32) {
+        let is_hidden = |dataset_index: usize| hidden.get(dataset_index).copied().unwrap_or(false);
+        let visible_datasets = (0..config.datasets.len()).filter(|&dataset_index| !is_hidden(dataset_index)).count();
+
+        let is_bar = config.chart_type == ChartType::Bar;
+        let stacked = config.stacked && matches!(config.chart_type, ChartType::Bar | ChartType::Area);
+
+        let slot_width = if is_bar { Self::get_bar_slot_width(config, time_origin) } else { 0. };
+        let group_width = BAR_PERCENTAGE * slot_width;
+        let width = if stacked { group_width } else { group_width / visible_datasets.max(1) as f32 };
+
+        // Positive and negative values are stacked separately, so they grow away from zero.
+        // Datasets are only stacked with the ones bound to the same y axis.
+        let (axes, y_axis_ids) = Self::get_y_axis_ids(config);
+        let mut positive_stacks = vec![vec![]; y_axis_ids.len()];
+        let mut negative_stacks = vec![vec![]; y_axis_ids.len()];
+
+        let mut ret = Vec::with_capacity(config.datasets.len());
+        let mut group_index = 0;
+        for (dataset_index, (dataset, &axis)) in config.datasets.iter().zip(&axes).enumerate() {
+            if is_hidden(dataset_index) {
+                ret.push(vec![]);
+                continue;
+            }
 
-        // Force either bound to be zero (but not both)
-        if max.y < 0. {
-            max.y = 0.;
-        } else if min.y > 0. {
-            min.y = 0.;
+            let (positive_stack, negative_stack) = (&mut positive_stacks[axis], &mut negative_stacks[axis]);
+            let offset = if is_bar && !stacked { (group_index as f32 + 0.5) * width - 0.5 * group_width } else { 0. };
+            group_index += 1;
+
+            let mut elements = Vec::with_capacity(dataset.data.len());
+            for datum_index in 0..dataset.data.len() {
+                let data_point = Self::get_data_point(config, &dataset.data, datum_index, time_origin);
+                let mut base = 0.;
+                if stacked {
+                    if positive_stack.len() <= datum_index {
+                        positive_stack.resize(datum_index + 1, 0.);
+                        negative_stack.resize(datum_index + 1, 0.);
+                    }
+                    let stack =
+                        if data_point.y < 0. { &mut negative_stack[datum_index] } else { &mut positive_stack[datum_index] };
+                    base = *stack;
+                    *stack += data_point.y;
+                }
+
+                elements.push(ChartElement {
+                    data_point,
+                    position: vec2(data_point.x + offset, base + data_point.y),
+                    base,
+                    width: if is_bar { width } else { 0. },
+                    time: dataset.data.time_at(datum_index),
+                    x: Self::get_data_x(config, &dataset.data, datum_index, time_origin) + offset as f64,
+                });
+            }
+            ret.push(elements);
         }
 
-        (Self::round_down(min), Self::round_up(max))
+        (ret, slot_width)
     }
 
+    /// Range of the x axis, and of each y axis in `y_axis_ids`, with `axes` being the index
+    /// of the y axis of each dataset.
+    fn get_min_max(
+        config: &ChartConfig,
+        elements: &[Vec<ChartElement>],
+        axes: &[usize],
+        y_axis_ids: &[&str],
+        slot_width: f32,
+    ) -> ((f32, f32), Vec<(f32, f32)>) {
+        let get_scale_range = |id: &str| config.scales.get(id).map_or((INFINITY, NEG_INFINITY), |scale| (scale.min, scale.max));
+        let (mut min_x, mut max_x) = get_scale_range("x");
+        let mut y_ranges: Vec<(f32, f32)> = y_axis_ids.iter().map(|id| get_scale_range(id)).collect();
+
+        // Leave room for half a bar group on each side, so the first and last bars
+        // are not cut in half.
+        let padding = if config.scales.contains_key("x") { 0. } else { 0.5 * slot_width };
+
+        for (elements, &axis) in elements.iter().zip(axes) {
+            let (min_y, max_y) = &mut y_ranges[axis];
+            for element in elements {
+                let x = element.data_point.x;
+                min_x = min_x.min(x - padding);
+                max_x = max_x.max(x + padding);
+                *min_y = min_y.min(element.position.y.min(element.base));
+                *max_y = max_y.max(element.position.y.max(element.base));
+            }
+        }
+
+        for (min_y, max_y) in &mut y_ranges {
+            // E.g. when all datasets of an axis are hidden
+            if *min_y > *max_y {
+                *min_y = 0.;
+                *max_y = 1.;
+            }
+            // Force either bound to be zero (but not both)
+            if *max_y < 0. {
+                *max_y = 0.;
+            } else if *min_y > 0. {
+                *min_y = 0.;
+            }
+            *min_y = Self::round_down(vec2(0., *min_y)).y;
+            *max_y = Self::round_up(vec2(0., *max_y)).y;
+        }
+
+        ((min_x, max_x), y_ranges)
+    }
+
+    /// Shows the whole chart again, after zooming or panning it or any of its y axes.
     pub fn reset_zoom_pan(&mut self) {
         self.zoom_pan = None;
+        for axis in &mut self.y_axes {
+            axis.zoomed = false;
+        }
+    }
+
+    /// Forces the chart to process the data again on the next draw.
+    ///
+    /// Processed data is cached across draws, and only updated when the [`ChartDataset::revision`]
+    /// or size of datasets changes, or on every draw for datasets without a revision. Call this after
+    /// modifying data without changing revisions.
+    pub fn invalidate_cache(&mut self) {
+        self.layout.key = None;
+        self.lod.key = None;
+    }
+
+    /// Serializes the chart as it was last drawn (including the current zoom and pan) as an SVG
+    /// document, in logical pixels. Tooltips and [`ChartPlugin`]s are not included.
+    ///
+    /// Returns an empty document if the chart hasn't been drawn yet.
+    pub fn to_svg(&self) -> String {
+        self.scene.to_svg()
+    }
+
+    /// Requests a PNG file of the chart as it gets rendered next, in physical pixels, which gets
+    /// returned as a [`ChartEvent::Png`] by [`Chart::handle`]. Unlike [`Chart::to_svg`], this
+    /// includes tooltips and [`ChartPlugin`]s.
+    ///
+    /// This reads the chart's texture back from the GPU using [`Pass::request_readback`], so the
+    /// event arrives after the next paint.
+    pub fn request_png(&mut self, cx: &mut Cx) {
+        self.png_requested = true;
+        cx.request_draw();
+    }
+
+    /// Recomputes [`Chart::layout`] if the data changed since the last draw.
+    fn update_layout(&mut self, config: &ChartConfig) {
+        let key = ChartLayoutKey::new(config, &self.hidden_datasets);
+        if key.is_versioned() && self.layout.key.as_ref() == Some(&key) {
+            return;
+        }
+
+        if self.zoom_pan.is_none() {
+            // Like min/max, keep the origin while panning/zooming so the view doesn't jump
+            self.time_origin = Self::get_time_origin(config);
+        }
+        let (axes, y_axis_ids) = Self::get_y_axis_ids(config);
+        let (elements, slot_width) = Self::layout_elements(config, &self.hidden_datasets, self.time_origin);
+        let ((min_x, max_x), y_ranges) = Self::get_min_max(config, &elements, &axes, &y_axis_ids, slot_width);
+        let y_axes = y_axis_ids
+            .iter()
+            .zip(y_ranges)
+            .enumerate()
+            .map(|(index, (id, (min, max)))| {
+                let position = match config.scales.get(*id) {
+                    Some(scale) => scale.position,
+                    None if index == 0 => ChartAxisPosition::Left,
+                    None => ChartAxisPosition::Right,
+                };
+                ChartYAxis { id: id.to_string(), position, min, max, zoomed: false, rect: Rect::default() }
+            })
+            .collect();
+        let sorted =
+            elements.iter().map(|elements| elements.windows(2).all(|pair| pair[0].position.x <= pair[1].position.x)).collect();
+        if !key.is_versioned() {
+            // The key can't tell if the data changed, so neither can the key of the level of detail.
+            self.lod.key = None;
+        }
+        self.layout = ChartLayout { key: Some(key), elements, sorted, axes, min_x, max_x, y_axes };
+    }
+
+    /// Recomputes [`Chart::lod`] if the data or the view changed since the last draw.
+    fn update_lod(&mut self, dpi: f32) {
+        let key = ChartLodKey {
+            layout: self.layout.key.clone(),
+            min_x: self.min_x,
+            max_x: self.max_x,
+            bounds: self.bounds,
+            zoom_pan: self.zoom_pan,
+            dpi,
+        };
+        if self.lod.key.as_ref() == Some(&key) {
+            return;
+        }
+
+        let decimate = matches!(self.chart_type, ChartType::Line | ChartType::Area);
+        let min_x = self.denormalize_data_point(self.bounds.pos, 0).x;
+        let max_x = self.denormalize_data_point(self.bounds.pos + self.bounds.size, 0).x;
+        let columns = (self.bounds.size.x * dpi).max(1.) as usize;
+
+        let indices = self
+            .layout
+            .elements
+            .iter()
+            .zip(&self.layout.sorted)
+            .map(|(elements, sorted)| {
+                if decimate && *sorted {
+                    Self::decimate(elements, min_x, max_x, columns)
+                } else {
+                    (0..elements.len()).collect()
+                }
+            })
+            .collect();
+        self.lod = ChartLod { key: Some(key), indices };
+    }
+
+    /// Picks which elements to draw when there are many more of them than horizontal pixels.
+    ///
+    /// Only elements between `min_x` and `max_x` (plus one on each side, so lines reach the
+    /// edges) are considered. These are grouped into `columns` buckets, keeping the first, last,
+    /// lowest and highest element of each one (a.k.a. M4 aggregation), which draws the same
+    /// lines as using every element. Returns indices into `elements`, which must be sorted
+    /// horizontally.
+    fn decimate(elements: &[ChartElement], min_x: f32, max_x: f32, columns: usize) -> Vec<usize> {
+        let start = elements.partition_point(|element| element.position.x < min_x).saturating_sub(1);
+        let end = (elements.partition_point(|element| element.position.x <= max_x) + 1).min(elements.len());
+        if end <= start || end - start <= LOD_MAX_ELEMENTS_PER_PIXEL * columns {
+            return (start..end).collect();
+        }
+
+        let mut ret = Vec::with_capacity(LOD_MAX_ELEMENTS_PER_PIXEL * columns + 2);
+        let mut push_bucket = |bucket: [usize; 4]| {
+            let mut bucket = bucket;
+            bucket.sort_unstable();
+            for (i, index) in bucket.iter().enumerate() {
+                if i == 0 || *index != bucket[i - 1] {
+                    ret.push(*index);
+                }
+            }
+        };
+
+        let column_width = (max_x - min_x) / columns as f32;
+        // first, last, lowest and highest element of the current column
+        let mut bucket = [start; 4];
+        let mut column = ((elements[start].position.x - min_x) / column_width).floor();
+        for index in start + 1..end {
+            let element_column = ((elements[index].position.x - min_x) / column_width).floor();
+            if element_column != column {
+                push_bucket(bucket);
+                bucket = [index; 4];
+                column = element_column;
+                continue;
+            }
+
+            bucket[1] = index;
+            if elements[index].position.y < elements[bucket[2]].position.y {
+                bucket[2] = index;
+            }
+            if elements[index].position.y > elements[bucket[3]].position.y {
+                bucket[3] = index;
+            }
+        }
+        push_bucket(bucket);
+
+        ret
     }
 
     fn draw_chart(&mut self, cx: &mut Cx, config: &ChartConfig) {
@@ -837,44 +1913,96 @@ impl Chart {
         let current_dpi = cx.current_dpi_factor;
 
         self.background.draw(cx, rect, config.style.background_color);
+        let mut scene = ChartScene::new(rect, config.style.background_color, current_dpi);
 
         self.zoom_enabled = config.zoom_enabled;
         self.pan_enabled = config.pan_enabled;
 
-        // Compute the rect where the chart will be rendered. The offsets below
-        // add some marging so we can also render labels for each axis.
-        // TODO(Hernan): should this be customizable?
-        self.bounds = Rect { pos: rect.pos + vec2(60., 5.), size: rect.size - vec2(80., 40.) };
+        self.chart_type = config.chart_type;
+        self.update_layout(config);
 
         if self.zoom_pan.is_none() {
-            // Compute min/max for all datasets before rendering
             // Only update min/max values if we're not panning/zooming
-            let (data_min, data_max) = Self::get_min_max(config);
-            self.min = data_min;
-            self.max = data_max;
+            self.min_x = self.layout.min_x;
+            self.max_x = self.layout.max_x;
         }
+        // Same for y axes, which can also be zoomed and panned on their own
+        self.y_axes = self
+            .layout
+            .y_axes
+            .iter()
+            .map(|axis| match self.y_axes.iter().find(|current| current.id == axis.id) {
+                Some(current) if self.zoom_pan.is_some() || current.zoomed => {
+                    ChartYAxis { position: axis.position, ..current.clone() }
+                }
+                _ => axis.clone(),
+            })
+            .collect();
 
-        self.areas = vec![];
+        // Compute the rect where the chart will be rendered. The offsets below
+        // add some marging so we can also render labels for each axis.
+        // TODO(Hernan): should this be customizable?
+        let left_axes = self.y_axes.iter().filter(|axis| axis.position == ChartAxisPosition::Left).count();
+        let right_axes = self.y_axes.len() - left_axes;
+        let left_margin = 60. + AXIS_WIDTH * left_axes.saturating_sub(1) as f32;
+        let right_margin = 20. + AXIS_WIDTH * right_axes as f32;
+        let rect = self.layout_legend(cx, config, rect);
+        self.bounds = Rect { pos: rect.pos + vec2(left_margin, 5.), size: rect.size - vec2(left_margin + right_margin, 40.) };
+
+        // Axes on the same side are placed next to each other, starting from the chart
+        let (mut left_index, mut right_index) = (0., 0.);
+        for axis in &mut self.y_axes {
+            let x = match axis.position {
+                ChartAxisPosition::Left => {
+                    left_index += 1.;
+                    self.bounds.pos.x - left_index * AXIS_WIDTH
+                }
+                ChartAxisPosition::Right => {
+                    right_index += 1.;
+                    self.bounds.pos.x + self.bounds.size.x + (right_index - 1.) * AXIS_WIDTH
+                }
+            };
+            axis.rect = Rect { pos: vec2(x, self.bounds.pos.y), size: vec2(AXIS_WIDTH, self.bounds.size.y) };
+        }
 
-        self.draw_grid(cx, config);
+        self.update_lod(current_dpi);
 
-        for dataset in &config.datasets {
-            let points = dataset.data.points();
-            let normalized_data = self.normalize(&points);
-            if !normalized_data.is_empty() {
-                self.draw_lines(cx, &normalized_data, dataset.border_color, dataset.border_width * current_dpi);
-                let area = self.draw_points(
-                    cx,
-                    &normalized_data,
-                    &points,
-                    dataset.point_background_color,
-                    dataset.point_radius * current_dpi,
-                    dataset.point_style.clone(),
-                );
-                self.areas.push(area);
+        self.draw_grid(&mut scene, config);
+
+        for (dataset_index, (((dataset, elements), indices), &axis)) in
+            config.datasets.iter().zip(&self.layout.elements).zip(&self.lod.indices).zip(&self.layout.axes).enumerate()
+        {
+            if elements.is_empty() {
+                continue;
+            }
+
+            let dimmed = self.is_dataset_dimmed(dataset_index);
+            let dim = |color| if dimmed { Self::dim_color(color, config.style.background_color) } else { color };
+
+            if config.chart_type == ChartType::Bar {
+                self.draw_bars(&mut scene, elements, axis, dim(dataset.background_color));
+                continue;
+            }
+
+            let normalized_data: Vec<Vec2> =
+                indices.iter().map(|&datum_index| self.normalize_element(&elements[datum_index], axis)).collect();
+            if normalized_data.is_empty() {
+                continue;
+            }
+            if config.chart_type == ChartType::Area {
+                self.draw_area(&mut scene, &normalized_data, elements, indices, axis, dim(dataset.background_color));
+            }
+            if config.chart_type != ChartType::Scatter && dataset.show_line {
+                self.draw_lines(&mut scene, &normalized_data, dim(dataset.border_color), dataset.border_width * current_dpi);
             }
+            self.draw_points(&mut scene, &normalized_data, elements, indices, dataset, &dim);
         }
 
+        self.draw_legend(&mut scene, config);
+
+        scene.draw(cx);
+        self.scene = scene;
+
         for plugin in &mut self.plugins {
             plugin.write().unwrap().draw(cx, config, &self.bounds)
         }
@@ -902,6 +2030,9 @@ impl Chart {
         let color_texture_handle = self.color_texture.get_color(cx);
         self.pass.set_size(cx, rect.size);
         self.pass.add_color_texture(cx, color_texture_handle, ClearColor::default());
+        if self.png_requested {
+            self.pass.request_readback(cx);
+        }
 
         self.draw_chart(cx, config);
 
@@ -911,9 +2042,14 @@ impl Chart {
 
 #[cfg(test)]
 mod tests {
-    use wrflib::vec2;
+    use std::collections::HashMap;
 
-    use crate::Chart;
+    use wrflib::{vec2, Rect};
+
+    use super::ChartElement;
+    use crate::{
+        Chart, ChartAxisPosition, ChartConfig, ChartData, ChartDataset, ChartScale, ChartTimeAxis, ChartTimeUnit, ChartType,
+    };
 
     #[test]
     fn it_rounds_up() {
@@ -950,4 +2086,174 @@ mod tests {
         assert_eq!(Chart::round_down(vec2(10., 1001.)), vec2(10., 1000.));
         assert_eq!(Chart::round_down(vec2(10., -1001.)), vec2(10., -10000.));
     }
+
+    #[test]
+    fn it_lays_out_stacked_bars() {
+        let first = [1., -2., 3.];
+        let second = [4., -5., 6.];
+        let config = ChartConfig {
+            chart_type: ChartType::Bar,
+            stacked: true,
+            datasets: vec![
+                ChartDataset { data: ChartData::from_values(&first), ..ChartDataset::default() },
+                ChartDataset { data: ChartData::from_values(&second), ..ChartDataset::default() },
+            ],
+            ..ChartConfig::default()
+        };
+
+        let (elements, slot_width) = Chart::layout_elements(&config, &[], 0.);
+        assert_eq!(slot_width, 1.);
+        assert_eq!(elements[1][0].position, vec2(0., 5.));
+        assert_eq!(elements[1][0].base, 1.);
+        assert_eq!(elements[1][1].position, vec2(1., -7.));
+        assert_eq!(elements[1][1].base, -2.);
+        assert_eq!(elements[1][2].data_point, vec2(2., 6.));
+    }
+
+    #[test]
+    fn it_lays_out_grouped_bars() {
+        let first = [1., 2.];
+        let second = [3., 4.];
+        let config = ChartConfig {
+            chart_type: ChartType::Bar,
+            datasets: vec![
+                ChartDataset { data: ChartData::from_values(&first), ..ChartDataset::default() },
+                ChartDataset { data: ChartData::from_values(&second), ..ChartDataset::default() },
+            ],
+            ..ChartConfig::default()
+        };
+
+        let (elements, _) = Chart::layout_elements(&config, &[], 0.);
+        assert_eq!(elements[0][1].position, vec2(0.8, 2.));
+        assert_eq!(elements[1][1].position, vec2(1.2, 4.));
+        assert_eq!(elements[0][1].base, 0.);
+        assert_eq!(elements[0][1].width, 0.4);
+    }
+
+    #[test]
+    fn it_scales_y_axes_independently() {
+        let velocity = [1., 20., 30.];
+        let acceleration = [-0.5, 0.2, 0.05];
+        let mut scales = HashMap::new();
+        scales.insert("acceleration".to_string(), ChartScale { position: ChartAxisPosition::Left, ..ChartScale::default() });
+        let config = ChartConfig {
+            datasets: vec![
+                ChartDataset { data: ChartData::from_values(&velocity), y_axis_id: "velocity", ..ChartDataset::default() },
+                ChartDataset {
+                    data: ChartData::from_values(&acceleration),
+                    y_axis_id: "acceleration",
+                    ..ChartDataset::default()
+                },
+                ChartDataset { data: ChartData::from_values(&velocity), y_axis_id: "velocity", ..ChartDataset::default() },
+            ],
+            scales,
+            ..ChartConfig::default()
+        };
+
+        let mut chart = Chart::default();
+        chart.update_layout(&config);
+        assert_eq!(chart.layout.axes, vec![0, 1, 0]);
+        let axes: Vec<_> = chart.layout.y_axes.iter().map(|axis| (axis.id.as_str(), axis.position, axis.min, axis.max)).collect();
+        assert_eq!(
+            axes,
+            vec![("velocity", ChartAxisPosition::Left, 0., 100.), ("acceleration", ChartAxisPosition::Left, -1., 1.)]
+        );
+    }
+
+    #[test]
+    fn it_caches_the_layout_until_the_revision_changes() {
+        let mut chart = Chart::default();
+        let config = |data, revision| ChartConfig {
+            datasets: vec![ChartDataset { data: ChartData::from_values(data), revision, ..ChartDataset::default() }],
+            ..ChartConfig::default()
+        };
+        chart.update_layout(&config(&[1., 2., 3.], 1));
+        assert_eq!(chart.layout.elements[0][2].data_point, vec2(2., 3.));
+        chart.update_layout(&config(&[4., 5., 6.], 1));
+        assert_eq!(chart.layout.elements[0][2].data_point, vec2(2., 3.));
+        chart.update_layout(&config(&[4., 5., 6.], 2));
+        assert_eq!(chart.layout.elements[0][2].data_point, vec2(2., 6.));
+        chart.update_layout(&config(&[4., 5., 6., 7.], 2));
+        assert_eq!(chart.layout.elements[0][3].data_point, vec2(3., 7.));
+        // Without a revision, the layout is recomputed every time.
+        chart.update_layout(&config(&[1., 2., 3.], 0));
+        assert_eq!(chart.layout.elements[0][2].data_point, vec2(2., 3.));
+        chart.update_layout(&config(&[4., 5., 6.], 0));
+        assert_eq!(chart.layout.elements[0][2].data_point, vec2(2., 6.));
+    }
+
+    #[test]
+    fn it_keeps_timestamps_precise_until_projected() {
+        // Values 10ms apart, ten days after the first one, where `f32` seconds are only precise up to 62.5ms.
+        let times = [0., 864e12, 864e12 + 1e7, 864e12 + 2e7];
+        let values = [0., 1., 2., 3.];
+        let config = ChartConfig {
+            datasets: vec![ChartDataset { data: ChartData::from_time_series(&times, &values), ..ChartDataset::default() }],
+            time_axis: Some(ChartTimeAxis { unit: ChartTimeUnit::Nanoseconds, ..ChartTimeAxis::default() }),
+            ..ChartConfig::default()
+        };
+
+        let mut chart = Chart::default();
+        chart.update_layout(&config);
+        chart.bounds = Rect { pos: vec2(0., 0.), size: vec2(1000., 100.) };
+        chart.min_x = chart.layout.min_x;
+        chart.max_x = chart.layout.max_x;
+        chart.y_axes = chart.layout.y_axes.clone();
+        // Zoom in on the end of the data, so that 10ms are about 11 pixels wide.
+        chart.zoom_pan = Some(Rect { pos: vec2(500. - 1e9, 0.), size: vec2(1e9, 100.) });
+
+        let elements = &chart.layout.elements[0];
+        let xs: Vec<f32> = elements[1..].iter().map(|element| chart.normalize_element(element, 0).x).collect();
+        assert!(xs[1] - xs[0] > 10.);
+        assert!(((xs[2] - xs[1]) - (xs[1] - xs[0])).abs() < 0.1);
+        assert_eq!(Chart::get_nearest_element_index(elements, chart.denormalize_x(xs[1] + 2.)), Some(2));
+        assert_eq!(Chart::get_nearest_element_index(elements, chart.denormalize_x(xs[1] + 8.)), Some(3));
+    }
+
+    fn elements_from_values(values: &[f32]) -> Vec<ChartElement> {
+        let data = ChartData::from_values(values);
+        (0..data.len())
+            .map(|i| ChartElement {
+                data_point: data.value_at(i),
+                position: data.value_at(i),
+                base: 0.,
+                width: 0.,
+                time: None,
+                x: data.value_at(i).x as f64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_decimates_elements() {
+        let values: Vec<f32> = (0..10_000).map(|i| ((i * 7919) % 1000) as f32).collect();
+        let elements = elements_from_values(&values);
+
+        let indices = Chart::decimate(&elements, 0., 10_000., 10);
+        assert!(indices.len() <= 4 * 10);
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+        // Each column keeps its first, last, lowest and highest values.
+        for column in 0..10 {
+            let range = column * 1000..(column + 1) * 1000;
+            assert!(indices.contains(&range.start));
+            assert!(indices.contains(&(range.end - 1)));
+            let min = range.clone().map(|i| values[i]).fold(f32::INFINITY, f32::min);
+            let max = range.clone().map(|i| values[i]).fold(f32::NEG_INFINITY, f32::max);
+            assert!(indices.iter().any(|&i| range.contains(&i) && values[i] == min));
+            assert!(indices.iter().any(|&i| range.contains(&i) && values[i] == max));
+        }
+    }
+
+    #[test]
+    fn it_only_decimates_visible_elements() {
+        let values: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+        let elements = elements_from_values(&values);
+
+        // Few visible elements are kept as is, including one on each side of the view.
+        assert_eq!(Chart::decimate(&elements, 100.5, 110., 100), (100..=111).collect::<Vec<_>>());
+
+        let indices = Chart::decimate(&elements, 100.5, 900., 10);
+        assert_eq!(indices.first(), Some(&100));
+        assert_eq!(indices.last(), Some(&901));
+    }
 }
@@ -0,0 +1,323 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Exporting [`crate::Chart`]s as images: the chart is first drawn into a [`ChartScene`], which
+//! then either gets drawn on screen or serialized as SVG.
+
+use std::fmt::Write;
+
+use crate::*;
+use wrflib::*;
+
+/// Something drawn by the chart, which maps one to one to a call that adds instances.
+pub(crate) enum ChartSceneItem {
+    Lines(Vec<DrawLines3dInstance>),
+    Fills(Vec<ChartFillIns>),
+    Points { points: Vec<DrawPoints3dInstance>, point_style: DrawPoints3dStyle },
+    Text { text: String, pos: Vec2, anchoring: Vec2, color: Vec4 },
+}
+
+/// Everything drawn by the chart during the last draw, in order, except for its tooltip and plugins.
+/// Keeping it around makes it possible to export exactly what is on screen.
+#[derive(Default)]
+pub(crate) struct ChartScene {
+    pub(crate) rect: Rect,
+    pub(crate) background_color: Vec4,
+    pub(crate) dpi: f32,
+    items: Vec<ChartSceneItem>,
+}
+
+impl ChartScene {
+    pub(crate) fn new(rect: Rect, background_color: Vec4, dpi: f32) -> Self {
+        Self { rect, background_color, dpi, items: vec![] }
+    }
+
+    pub(crate) fn add_lines(&mut self, lines: Vec<DrawLines3dInstance>) {
+        if !lines.is_empty() {
+            self.items.push(ChartSceneItem::Lines(lines));
+        }
+    }
+
+    pub(crate) fn add_fills(&mut self, fills: Vec<ChartFillIns>) {
+        if !fills.is_empty() {
+            self.items.push(ChartSceneItem::Fills(fills));
+        }
+    }
+
+    pub(crate) fn add_points(&mut self, points: Vec<DrawPoints3dInstance>, point_style: DrawPoints3dStyle) {
+        if !points.is_empty() {
+            self.items.push(ChartSceneItem::Points { points, point_style });
+        }
+    }
+
+    pub(crate) fn add_text(&mut self, text: String, pos: Vec2, anchoring: Vec2, color: Vec4) {
+        self.items.push(ChartSceneItem::Text { text, pos, anchoring, color });
+    }
+
+    /// Draws every item, in the order they were added. The background is drawn separately.
+    pub(crate) fn draw(&self, cx: &mut Cx) {
+        for item in &self.items {
+            match item {
+                ChartSceneItem::Lines(lines) => DrawLines3d::draw(cx, lines, Default::default()),
+                ChartSceneItem::Fills(fills) => {
+                    cx.add_instances(&FILL_SHADER, fills);
+                }
+                ChartSceneItem::Points { points, point_style } => {
+                    DrawPoints3d::draw(
+                        cx,
+                        points,
+                        DrawPoints3dOptions {
+                            use_screen_space: true,
+                            point_style: point_style.clone(),
+                            ..DrawPoints3dOptions::default()
+                        },
+                    );
+                }
+                ChartSceneItem::Text { text, pos, anchoring, color } => {
+                    TextIns::draw_str(
+                        cx,
+                        text,
+                        *pos,
+                        &TextInsProps { position_anchoring: *anchoring, color: *color, ..TextInsProps::DEFAULT },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Serializes every item as an SVG document, in logical pixels.
+    ///
+    /// Line widths and point sizes are scaled by the dpi factor when drawing, so they are divided
+    /// by it here. Text is written as SVG text using the same font, so it might render slightly
+    /// differently.
+    pub(crate) fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        let Rect { pos, size } = self.rect;
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+            fmt_number(size.x),
+            fmt_number(size.y),
+            fmt_number(pos.x),
+            fmt_number(pos.y),
+            fmt_number(size.x),
+            fmt_number(size.y)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+            fmt_number(pos.x),
+            fmt_number(pos.y),
+            fmt_number(size.x),
+            fmt_number(size.y),
+            fmt_paint("fill", self.background_color)
+        )
+        .unwrap();
+
+        for item in &self.items {
+            match item {
+                ChartSceneItem::Lines(lines) => self.write_lines(&mut svg, lines),
+                ChartSceneItem::Fills(fills) => Self::write_fills(&mut svg, fills),
+                ChartSceneItem::Points { points, point_style } => self.write_points(&mut svg, points, point_style),
+                ChartSceneItem::Text { text, pos, anchoring, color } => {
+                    Self::write_text(&mut svg, text, *pos, *anchoring, *color)
+                }
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Writes lines as paths, joining consecutive segments that share an end point and a style.
+    fn write_lines(&self, svg: &mut String, lines: &[DrawLines3dInstance]) {
+        let mut start = 0;
+        while start < lines.len() {
+            let first = &lines[start];
+            let mut path = format!(
+                "M{} {}L{} {}",
+                fmt_number(first.position_start.x),
+                fmt_number(first.position_start.y),
+                fmt_number(first.position_end.x),
+                fmt_number(first.position_end.y)
+            );
+            let mut end = start + 1;
+            while end < lines.len()
+                && lines[end].position_start == lines[end - 1].position_end
+                && lines[end].color_start == first.color_start
+                && lines[end].scale == first.scale
+            {
+                write!(path, "L{} {}", fmt_number(lines[end].position_end.x), fmt_number(lines[end].position_end.y)).unwrap();
+                end += 1;
+            }
+
+            writeln!(
+                svg,
+                r#"<path d="{}" fill="none" {} stroke-width="{}"/>"#,
+                path,
+                fmt_paint("stroke", first.color_start),
+                fmt_number(first.scale / self.dpi)
+            )
+            .unwrap();
+            start = end;
+        }
+    }
+
+    /// Writes fills as polygons, clipped to their quads like in [`FILL_SHADER`].
+    fn write_fills(svg: &mut String, fills: &[ChartFillIns]) {
+        for fill in fills {
+            let polygon = [
+                vec2(fill.x_range.x, fill.top.x),
+                vec2(fill.x_range.y, fill.top.y),
+                vec2(fill.x_range.y, fill.base.y),
+                vec2(fill.x_range.x, fill.base.x),
+            ];
+            let min = fill.quad.rect_pos;
+            let max = fill.quad.rect_pos + fill.quad.rect_size;
+            let polygon = clip_polygon(&polygon, true, min.x, 1.);
+            let polygon = clip_polygon(&polygon, true, max.x, -1.);
+            let polygon = clip_polygon(&polygon, false, min.y, 1.);
+            let polygon = clip_polygon(&polygon, false, max.y, -1.);
+            if polygon.len() < 3 {
+                continue;
+            }
+
+            let points: Vec<String> =
+                polygon.iter().map(|point| format!("{},{}", fmt_number(point.x), fmt_number(point.y))).collect();
+            writeln!(svg, r#"<polygon points="{}" {}/>"#, points.join(" "), fmt_paint("fill", fill.color)).unwrap();
+        }
+    }
+
+    fn write_points(&self, svg: &mut String, points: &[DrawPoints3dInstance], point_style: &DrawPoints3dStyle) {
+        for point in points {
+            let radius = point.size / self.dpi;
+            let color = vec4(point.color.x, point.color.y, point.color.z, 1.);
+            match point_style {
+                DrawPoints3dStyle::Circle => writeln!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="{}" {}/>"#,
+                    fmt_number(point.position.x),
+                    fmt_number(point.position.y),
+                    fmt_number(radius),
+                    fmt_paint("fill", color)
+                ),
+                DrawPoints3dStyle::Quad => writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                    fmt_number(point.position.x - radius),
+                    fmt_number(point.position.y - radius),
+                    fmt_number(2. * radius),
+                    fmt_number(2. * radius),
+                    fmt_paint("fill", color)
+                ),
+            }
+            .unwrap();
+        }
+    }
+
+    /// Writes text aligned the same way as [`TextInsProps::position_anchoring`] does.
+    fn write_text(svg: &mut String, text: &str, pos: Vec2, anchoring: Vec2, color: Vec4) {
+        let text_anchor = match anchoring.x {
+            x if x < 0.25 => "start",
+            x if x < 0.75 => "middle",
+            _ => "end",
+        };
+        let dominant_baseline = match anchoring.y {
+            y if y < 0.25 => "hanging",
+            y if y < 0.75 => "central",
+            _ => "text-after-edge",
+        };
+        // Font sizes are in points, at 96 pixels per inch.
+        let font_size = TextInsProps::DEFAULT.text_style.font_size * 96. / 72.;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-family="Ubuntu, sans-serif" font-size="{}" text-anchor="{}" dominant-baseline="{}" {}>{}</text>"#,
+            fmt_number(pos.x),
+            fmt_number(pos.y),
+            fmt_number(font_size),
+            text_anchor,
+            dominant_baseline,
+            fmt_paint("fill", color),
+            escape_xml(text)
+        )
+        .unwrap();
+    }
+}
+
+/// Sutherland–Hodgman clipping of a polygon against a single boundary, keeping the side where
+/// `sign * (coordinate - bound) >= 0`. Uses x coordinates if `vertical`, y coordinates otherwise.
+fn clip_polygon(polygon: &[Vec2], vertical: bool, bound: f32, sign: f32) -> Vec<Vec2> {
+    let coordinate = |point: Vec2| if vertical { point.x } else { point.y };
+    let inside = |point: Vec2| sign * (coordinate(point) - bound) >= 0.;
+
+    let mut ret = Vec::with_capacity(polygon.len() + 1);
+    for (index, &current) in polygon.iter().enumerate() {
+        let previous = polygon[(index + polygon.len() - 1) % polygon.len()];
+        if inside(current) != inside(previous) {
+            let t = (bound - coordinate(previous)) / (coordinate(current) - coordinate(previous));
+            ret.push(previous + (current - previous) * t);
+        }
+        if inside(current) {
+            ret.push(current);
+        }
+    }
+    ret
+}
+
+/// Formats a coordinate with at most two decimals, which is plenty for pixels.
+fn fmt_number(value: f32) -> String {
+    format!("{}", (value * 100.).round() / 100.)
+}
+
+/// Formats a color as an SVG paint `attribute` (e.g. `fill`), adding its opacity if it's not opaque.
+fn fmt_paint(attribute: &str, color: Vec4) -> String {
+    let to_byte = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
+    let paint = format!(r##"{}="#{:02x}{:02x}{:02x}""##, attribute, to_byte(color.x), to_byte(color.y), to_byte(color.z));
+    if color.w < 1. {
+        format!(r#"{} {}-opacity="{}""#, paint, attribute, fmt_number(color.w))
+    } else {
+        paint
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Encodes RGBA pixels, like [`PassReadbackEvent::pixels`], as a PNG file.
+pub(crate) fn encode_png(width: usize, height: usize, pixels: &[u32]) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    let data: Vec<u8> = pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+    writer.write_image_data(&data).map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use wrflib::vec2;
+
+    use super::{clip_polygon, escape_xml, fmt_number};
+
+    #[test]
+    fn it_clips_polygons() {
+        let square = [vec2(0., 0.), vec2(2., 0.), vec2(2., 2.), vec2(0., 2.)];
+        assert_eq!(clip_polygon(&square, true, 1., -1.), vec![vec2(0., 0.), vec2(1., 0.), vec2(1., 2.), vec2(0., 2.)]);
+        assert_eq!(clip_polygon(&square, false, 3., 1.), vec![]);
+    }
+
+    #[test]
+    fn it_formats_svg_values() {
+        assert_eq!(fmt_number(12.), "12");
+        assert_eq!(fmt_number(1.23456), "1.23");
+        assert_eq!(escape_xml("a < b & \"c\""), "a &lt; b &amp; &quot;c&quot;");
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Time axis support for [`crate::Chart`]: choosing tick intervals and formatting timestamps.
+
+/// Unit of the timestamps plotted on a time axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartTimeUnit {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+impl ChartTimeUnit {
+    /// Length of one unit, in seconds.
+    pub fn in_seconds(&self) -> f64 {
+        match self {
+            ChartTimeUnit::Seconds => 1.,
+            ChartTimeUnit::Milliseconds => 1e-3,
+            ChartTimeUnit::Microseconds => 1e-6,
+            ChartTimeUnit::Nanoseconds => 1e-9,
+        }
+    }
+}
+
+/// Treats x values as Unix timestamps, placing ticks at "nice" intervals (e.g. every
+/// 5 minutes) with date/time labels.
+///
+/// Use [`crate::ChartData::TimeSeries`] to provide the timestamps. They are kept as `f64`,
+/// and made relative to the earliest one before being converted to chart coordinates,
+/// so precision is preserved when zooming in.
+#[derive(Debug, Clone)]
+pub struct ChartTimeAxis {
+    pub unit: ChartTimeUnit,
+    /// Offset from UTC for labels, in seconds. Labels are in UTC by default.
+    pub utc_offset: f64,
+}
+
+impl Default for ChartTimeAxis {
+    fn default() -> Self {
+        Self { unit: ChartTimeUnit::Seconds, utc_offset: 0. }
+    }
+}
+
+const MINUTE: f64 = 60.;
+const HOUR: f64 = 60. * MINUTE;
+const DAY: f64 = 24. * HOUR;
+
+/// Intervals between one second and a year, in seconds. Smaller and bigger intervals
+/// are multiples of 1, 2 and 5.
+const TIME_INTERVALS: &[f64] = &[
+    1.,
+    2.,
+    5.,
+    10.,
+    15.,
+    30.,
+    MINUTE,
+    2. * MINUTE,
+    5. * MINUTE,
+    10. * MINUTE,
+    15. * MINUTE,
+    30. * MINUTE,
+    HOUR,
+    2. * HOUR,
+    3. * HOUR,
+    6. * HOUR,
+    12. * HOUR,
+    DAY,
+    2. * DAY,
+    7. * DAY,
+    14. * DAY,
+    30. * DAY,
+    60. * DAY,
+    90. * DAY,
+    180. * DAY,
+    365. * DAY,
+];
+
+/// Smallest multiple of 1, 2 or 5 times a power of 10 (times `unit`) that is at least `min_interval`.
+fn get_decimal_interval(min_interval: f64, unit: f64) -> f64 {
+    let base = 10_f64.powf((min_interval / unit).log10().floor()) * unit;
+    for factor in [1., 2., 5.] {
+        if base * factor >= min_interval {
+            return base * factor;
+        }
+    }
+    base * 10.
+}
+
+/// Picks the smallest "nice" interval, in seconds, that is at least `min_interval` seconds long.
+pub(crate) fn get_time_interval(min_interval: f64) -> f64 {
+    let min_interval = min_interval.max(1e-9);
+    if min_interval < 1. {
+        return get_decimal_interval(min_interval, 1.);
+    }
+    if let Some(interval) = TIME_INTERVALS.iter().find(|interval| **interval >= min_interval) {
+        return *interval;
+    }
+    get_decimal_interval(min_interval, 365. * DAY)
+}
+
+/// Converts days since 1970-01-01 into a (year, month, day) date.
+/// See: <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Formats a timestamp (in seconds since 1970-01-01) with just enough precision to tell
+/// apart timestamps that are `interval` seconds away from each other.
+///
+/// Subsecond digits are capped at microseconds, which is about as precise as `f64` gets
+/// for current timestamps.
+pub(crate) fn format_time(seconds: f64, interval: f64) -> String {
+    let digits = if interval < 1. { ((-interval.log10()).ceil() as u32).clamp(1, 6) } else { 0 };
+    let scale = 10_i64.pow(digits);
+
+    // Round once in integer space, so e.g. 59.9999 doesn't end up as "00:00:60".
+    let ticks = (seconds * scale as f64).round() as i64;
+    let days = ticks.div_euclid(DAY as i64 * scale);
+    let ticks_of_day = ticks.rem_euclid(DAY as i64 * scale);
+    let fraction = ticks_of_day % scale;
+    let seconds_of_day = ticks_of_day / scale;
+    let (hours, minutes, seconds) = (seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60);
+
+    if interval >= DAY {
+        let (year, month, day) = civil_from_days(days);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    } else if interval >= MINUTE {
+        format!("{:02}:{:02}", hours, minutes)
+    } else if interval >= 1. {
+        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}:{:02}.{:0width$}", hours, minutes, seconds, fraction, width = digits as usize)
+    }
+}
+
+/// Formats a timestamp (in seconds since 1970-01-01) including its date, down to milliseconds.
+pub(crate) fn format_date_time(seconds: f64) -> String {
+    format!("{} {}", format_time(seconds, DAY), format_time(seconds, 1e-3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_picks_nice_intervals() {
+        assert_eq!(get_time_interval(0.0003), 0.0005);
+        assert_eq!(get_time_interval(0.04), 0.05);
+        assert_eq!(get_time_interval(0.7), 1.);
+        assert_eq!(get_time_interval(3.), 5.);
+        assert_eq!(get_time_interval(40.), 60.);
+        assert_eq!(get_time_interval(1000.), 30. * 60.);
+        assert_eq!(get_time_interval(5. * 3600.), 6. * 3600.);
+        assert_eq!(get_time_interval(3. * 86400.), 7. * 86400.);
+        assert_eq!(get_time_interval(400. * 86400.), 2. * 365. * 86400.);
+    }
+
+    #[test]
+    fn it_converts_days_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(18993), (2022, 1, 1));
+    }
+
+    #[test]
+    fn it_formats_times() {
+        // 2021-10-12 01:46:40 UTC
+        let time = 1_634_003_200.;
+        assert_eq!(format_time(time, DAY), "2021-10-12");
+        assert_eq!(format_time(time, HOUR), "01:46");
+        assert_eq!(format_time(time + 5., 5.), "01:46:45");
+        assert_eq!(format_time(time + 0.25, 0.05), "01:46:40.25");
+        assert_eq!(format_time(time + 0.000_125, 0.000_005), "01:46:40.000125");
+        assert_eq!(format_time(time + 59.9999, 1.), "01:47:40");
+        assert_eq!(format_date_time(time + 0.5), "2021-10-12 01:46:40.500");
+    }
+}
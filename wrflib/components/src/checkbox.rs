@@ -79,6 +79,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 #[derive(Default)]
 pub struct Checkbox {
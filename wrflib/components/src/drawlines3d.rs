@@ -255,3 +255,4 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
@@ -4,54 +4,64 @@
 // found in the LICENSE-APACHE file in the root directory of this source tree.
 // You may not use this file except in compliance with the License.
 
+use crate::viewport3d::*;
 use wrflib::*;
 
+/// Shared by [`SHADER`] and [`PICKING_SHADER`], which have to declare `in_pos` and `in_size`.
+const VERTEX_SHADER: CodeFragment = code_fragment!(
+    r#"
+        uniform rect_size: vec2;
+        uniform use_screen_space: float;
+        uniform point_style: float;
+        uniform vertex_transform: mat4;
+
+        geometry geom: vec2;
+
+        // Transforms a vertex to clip space, accounting for aspect ratio
+        fn to_clip_space(v: vec4) -> vec4 {
+            let w = draw_clip.z - draw_clip.x;
+            let h = draw_clip.w - draw_clip.y;
+            let aspect = w / h;
+            return v / v.w * aspect;
+        }
+
+        fn vertex() -> vec4 {
+            if use_screen_space == 1. {
+                let projected_pos = camera_projection * camera_view * vertex_transform * vec4(in_pos, 1.0);
+                let point_size = in_size * dpi_factor;
+                let offset = point_size * vec4((geom - vec2(0.5, 0.5))/rect_size, 0, 0);
+
+                // When rendering screen space points, we convert the projected point to clip space
+                // and then apply the offset.
+                return to_clip_space(projected_pos) + offset;
+            } else {
+                let view_pos = camera_view * vertex_transform * vec4(in_pos, 1.0);
+                let point_size = in_size;
+                let offset = point_size * vec4(geom - vec2(0.5, 0.5), 0, 0);
+
+                // For world space points, we apply the offset in view space so they always
+                // face to the camera.
+                return camera_projection * (view_pos + offset);
+            }
+        }
+    "#
+);
+
 static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
         code_fragment!(
             r#"
-            uniform rect_size: vec2;
-            uniform use_screen_space: float;
-            uniform point_style: float;
-            uniform vertex_transform: mat4;
-
-            geometry geom: vec2;
-
             instance in_pos: vec3;
             instance in_color: vec3;
             instance in_size: float;
             instance in_user_info: vec2;
-
-            // Transforms a vertex to clip space, accounting for aspect ratio
-            fn to_clip_space(v: vec4) -> vec4 {
-                let w = draw_clip.z - draw_clip.x;
-                let h = draw_clip.w - draw_clip.y;
-                let aspect = w / h;
-                return v / v.w * aspect;
-            }
-
-            fn vertex() -> vec4 {
-                if use_screen_space == 1. {
-                    let projected_pos = camera_projection * camera_view * vertex_transform * vec4(in_pos, 1.0);
-                    let point_size = in_size * dpi_factor;
-                    let offset = point_size * vec4((geom - vec2(0.5, 0.5))/rect_size, 0, 0);
-
-                    // When rendering screen space points, we convert the projected point to clip space
-                    // and then apply the offset.
-                    return to_clip_space(projected_pos) + offset;
-                } else {
-                    let view_pos = camera_view * vertex_transform * vec4(in_pos, 1.0);
-                    let point_size = in_size;
-                    let offset = point_size * vec4(geom - vec2(0.5, 0.5), 0, 0);
-
-                    // For world space points, we apply the offset in view space so they always
-                    // face to the camera.
-                    return camera_projection * (view_pos + offset);
-                }
-            }
-
+            "#
+        ),
+        VERTEX_SHADER,
+        code_fragment!(
+            r#"
             fn pixel() -> vec4 {
                 if point_style == 1. {
                     let df = Df::viewport(geom);
@@ -66,6 +76,34 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
+
+static PICKING_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        Viewport3D::PICKING_SHADER,
+        code_fragment!(
+            r#"
+            instance in_pos: vec3;
+            instance in_size: float;
+            instance in_pick_id: float;
+            "#
+        ),
+        VERTEX_SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                if point_style == 1. && length(geom - vec2(0.5)) > 0.5 {
+                    return vec4(0.);
+                }
+                return pick_id_to_color(in_pick_id);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+register_shader!(PICKING_SHADER);
 
 #[repr(C)]
 #[derive(Debug, Clone)]
@@ -78,6 +116,13 @@ pub struct DrawPoints3dInstance {
     pub user_info: Vec2,
 }
 
+#[repr(C)]
+struct DrawPoints3dPickingInstance {
+    position: Vec3,
+    size: f32,
+    pick_id: f32,
+}
+
 #[repr(C)]
 struct DrawPoints3dUniforms {
     rect_size: Vec2,
@@ -95,6 +140,7 @@ pub enum DrawPoints3dStyle {
 const POINT_STYLE_QUAD: f32 = 0.0;
 const POINT_STYLE_CIRCLE: f32 = 1.0;
 
+#[derive(Clone)]
 pub struct DrawPoints3dOptions {
     pub use_screen_space: bool,
     pub point_style: DrawPoints3dStyle,
@@ -116,7 +162,35 @@ impl DrawPoints3d {
     /// flag. Regardless of the render space, all points are rendered as billboards, facing the camera.
     pub fn draw(cx: &mut Cx, data: &[DrawPoints3dInstance], options: DrawPoints3dOptions) -> Area {
         let area = cx.add_instances(&SHADER, data);
+        Self::write_uniforms(cx, area, options);
+        area
+    }
+
+    /// Draw the same points into the picking pass of `viewport`, so that [`Viewport3DEvent::Pick`] returns `area`
+    /// (as returned by [`DrawPoints3d::draw`]) and the index into `data`. Call this between
+    /// [`Viewport3D::begin_picking_draw`] and [`Viewport3D::end_picking_draw`].
+    pub fn draw_picking(
+        cx: &mut Cx,
+        viewport: &mut Viewport3D,
+        area: Area,
+        data: &[DrawPoints3dInstance],
+        options: DrawPoints3dOptions,
+    ) {
+        let first_pick_id = viewport.add_pickable(area, data.len());
+        let picking_data: Vec<DrawPoints3dPickingInstance> = data
+            .iter()
+            .enumerate()
+            .map(|(index, instance)| DrawPoints3dPickingInstance {
+                position: instance.position,
+                size: instance.size,
+                pick_id: (first_pick_id as usize + index) as f32,
+            })
+            .collect();
+        let picking_area = cx.add_instances(&PICKING_SHADER, &picking_data);
+        Self::write_uniforms(cx, picking_area, options);
+    }
 
+    fn write_uniforms(cx: &mut Cx, area: Area, options: DrawPoints3dOptions) {
         let rect = cx.get_box_rect();
         area.write_user_uniforms(
             cx,
@@ -130,7 +204,5 @@ impl DrawPoints3d {
                 vertex_transform: options.vertex_transform,
             },
         );
-
-        area
     }
 }
@@ -33,6 +33,7 @@ static BACKGROUND_SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(BACKGROUND_SHADER);
 static KNOB_SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
@@ -70,6 +71,7 @@ static KNOB_SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(KNOB_SHADER);
 
 #[derive(Clone, Default)]
 #[repr(C)]
@@ -131,6 +131,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 #[derive(Default)]
 pub struct FoldCaption {
@@ -53,6 +53,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 #[derive(Default)]
 pub struct FpsCounter {
@@ -1,18 +1,50 @@
 use std::f32::consts::PI;
 
+use crate::viewport3d::*;
 use wrflib::*;
 
 /// Represents a single vertex used in 3d objects.
 #[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Geometry3d {
     /// Vertex position.
-    geom_pos: Vec3,
+    pub geom_pos: Vec3,
     /// Some sort of identifier (dependent on the type of object).
-    geom_id: f32,
+    pub geom_id: f32,
     /// Vertex normal vector (perpendicular to the surface).
-    geom_normal: Vec3,
+    pub geom_normal: Vec3,
     /// 2d coordinates for mapping textures (dependent on the type of object).
-    geom_uv: Vec2,
+    pub geom_uv: Vec2,
+}
+
+static PICKING_SHADER: Shader = Shader {
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        Geometry3d::SHADER,
+        Viewport3D::PICKING_SHADER,
+        code_fragment!(
+            r#"
+            instance in_transform: mat4;
+            instance in_pick_id: float;
+
+            fn vertex() -> vec4 {
+                return camera_projection * camera_view * in_transform * vec4(geom_pos, 1.);
+            }
+
+            fn pixel() -> vec4 {
+                return pick_id_to_color(in_pick_id);
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+register_shader!(PICKING_SHADER);
+
+#[repr(C)]
+struct Geometry3dPickingInstance {
+    transform: Mat4,
+    pick_id: f32,
 }
 
 #[derive(Clone, Copy)]
@@ -36,6 +68,21 @@ impl Geometry3d {
     "#
     );
 
+    /// Draw `gpu_geometry` into the picking pass of `viewport` once for every transform in `transforms`, so
+    /// that [`Viewport3DEvent::Pick`] returns `area` and the index into `transforms`. The vertices are positioned
+    /// with `camera_projection * camera_view * transform * vec4(geom_pos, 1.)`, which should match how the
+    /// mesh was drawn into `area`. Call this between [`Viewport3D::begin_picking_draw`] and
+    /// [`Viewport3D::end_picking_draw`].
+    pub fn draw_picking(cx: &mut Cx, viewport: &mut Viewport3D, area: Area, gpu_geometry: GpuGeometry, transforms: &[Mat4]) {
+        let first_pick_id = viewport.add_pickable(area, transforms.len());
+        let picking_data: Vec<Geometry3dPickingInstance> = transforms
+            .iter()
+            .enumerate()
+            .map(|(index, &transform)| Geometry3dPickingInstance { transform, pick_id: (first_pick_id as usize + index) as f32 })
+            .collect();
+        cx.add_mesh_instances(&PICKING_SHADER, &picking_data, gpu_geometry);
+    }
+
     /// 3d cube.
     pub fn cube(
         width: f32,
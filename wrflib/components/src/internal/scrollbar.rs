@@ -52,6 +52,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 #[derive(Debug)]
 pub(crate) struct ScrollBar {
@@ -61,9 +61,17 @@ mod fps_counter;
 pub use crate::fps_counter::*;
 mod geometry3d;
 pub use crate::geometry3d::*;
+mod model3d;
+pub use crate::model3d::*;
+mod model3d_gltf;
+mod model3d_obj;
 
 mod chart;
 pub use crate::chart::*;
+mod chart_time;
+pub use crate::chart_time::*;
+mod chart_export;
+pub(crate) use crate::chart_export::*;
 mod drawlines3d;
 pub use crate::drawlines3d::*;
 mod drawpoints3d;
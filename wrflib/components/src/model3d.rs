@@ -0,0 +1,94 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! 3d models loaded from files, see [`Model3d::from_gltf`] and [`Model3d::from_obj`].
+
+use crate::*;
+use wrflib::*;
+
+/// A 3d model, consisting of meshes that are placed in the scene by [`Model3d::instances`].
+///
+/// The vertices use [`Geometry3d`], so you can render them with a shader that includes
+/// [`Geometry3d::SHADER`]. Texture coordinates have their origin at the top left of the texture.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Model3d {
+    pub meshes: Vec<Mesh3d>,
+    pub materials: Vec<Material3d>,
+    /// Where to draw the meshes. The same mesh can be drawn multiple times, e.g. when multiple glTF nodes refer
+    /// to it, so create a single [`GpuGeometry`] per mesh and draw it once per instance.
+    pub instances: Vec<Model3dInstance>,
+}
+
+/// A set of triangles with a single material; one glTF primitive, or one material of an OBJ file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh3d {
+    pub vertices: Vec<Geometry3d>,
+    pub triangle_indices: Vec<[u32; 3]>,
+    /// Index into [`Model3d::materials`].
+    pub material_index: Option<usize>,
+}
+
+impl Mesh3d {
+    pub fn to_geometry(&self) -> Geometry {
+        Geometry::new(self.vertices.clone(), self.triangle_indices.clone())
+    }
+
+    pub fn to_gpu_geometry(&self, cx: &mut Cx) -> GpuGeometry {
+        GpuGeometry::new(cx, self.to_geometry())
+    }
+
+    /// Sets smooth vertex normals based on the triangles, for files that don't contain normals.
+    pub(crate) fn compute_normals(&mut self) {
+        for vertex in &mut self.vertices {
+            vertex.geom_normal = Vec3::default();
+        }
+        for &[a, b, c] in &self.triangle_indices {
+            let [pos_a, pos_b, pos_c] = [a, b, c].map(|index| self.vertices[index as usize].geom_pos);
+            // Not normalized, so bigger triangles weigh more.
+            let normal = Vec3::cross(pos_b - pos_a, pos_c - pos_a);
+            for index in [a, b, c] {
+                self.vertices[index as usize].geom_normal += normal;
+            }
+        }
+        for vertex in &mut self.vertices {
+            if vertex.geom_normal != Vec3::default() {
+                vertex.geom_normal = vertex.geom_normal.normalize();
+            }
+        }
+    }
+}
+
+/// Places [`Model3d::meshes`]`[mesh_index]` in the scene.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Model3dInstance {
+    pub mesh_index: usize,
+    /// Transforms the mesh from its own coordinates into the coordinates of the model, e.g. the combined glTF
+    /// node transforms.
+    pub transform: Mat4,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material3d {
+    pub name: String,
+    /// Multiplied with [`Material3d::base_color_texture`] if there is one.
+    pub base_color: Vec4,
+    pub base_color_texture: Option<Material3dTexture>,
+}
+
+impl Default for Material3d {
+    fn default() -> Self {
+        Self { name: String::new(), base_color: Vec4::all(1.), base_color_texture: None }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Material3dTexture {
+    /// An image embedded in the file; use [`Texture::from_decoded_image`] to upload it.
+    Image(DecodedImage),
+    /// A reference to an external file, relative to the model file. Load it yourself, e.g. using
+    /// [`TextureLoader`].
+    Uri(String),
+}
@@ -0,0 +1,495 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Loading [`Model3d`]s from glTF 2.0 files. See the
+//! [specification](https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html).
+
+use base64::Engine;
+use serde_json::Value;
+
+use crate::*;
+use wrflib::*;
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4e4f_534a;
+const GLB_CHUNK_BIN: u32 = 0x004e_4942;
+
+const MODE_TRIANGLES: u64 = 4;
+const MODE_TRIANGLE_STRIP: u64 = 5;
+const MODE_TRIANGLE_FAN: u64 = 6;
+
+impl Model3d {
+    /// Parses a glTF 2.0 file: either a binary `.glb` file, or a `.gltf` JSON file with its buffers embedded as
+    /// `data:` URIs.
+    ///
+    /// Every primitive of every mesh becomes a [`Mesh3d`], and every node of the default scene that has a mesh
+    /// becomes a [`Model3dInstance`] for each of its primitives, with the transforms of its parent nodes applied.
+    /// Only triangles are supported. Materials only use the base color of the metallic-roughness model; missing
+    /// normals are computed from the triangles.
+    pub fn from_gltf(bytes: &[u8]) -> Result<Self, String> {
+        let (json, bin) = if bytes.starts_with(GLB_MAGIC) { parse_glb(bytes)? } else { (bytes, None) };
+        let json: Value = serde_json::from_slice(json).map_err(|err| format!("Invalid glTF JSON: {}", err))?;
+        GltfParser::new(&json, bin)?.parse()
+    }
+}
+
+/// Splits a `.glb` file into its JSON and binary chunks.
+fn parse_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let read_u32 = |offset: usize| -> Result<u32, String> {
+        let word = bytes.get(offset..offset + 4).ok_or_else(|| "Unexpected end of GLB file".to_string())?;
+        Ok(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+    };
+    if read_u32(4)? != 2 {
+        return Err("Unsupported GLB version; only glTF 2.0 is supported".to_string());
+    }
+    let length = (read_u32(8)? as usize).min(bytes.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(offset)? as usize;
+        let chunk_type = read_u32(offset + 4)?;
+        let chunk = bytes.get(offset + 8..offset + 8 + chunk_length).ok_or_else(|| "Unexpected end of GLB file".to_string())?;
+        match chunk_type {
+            GLB_CHUNK_JSON if json.is_none() => json = Some(chunk),
+            GLB_CHUNK_BIN if bin.is_none() => bin = Some(chunk),
+            // Unknown chunks must be ignored.
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+    Ok((json.ok_or_else(|| "GLB file has no JSON chunk".to_string())?, bin))
+}
+
+struct GltfParser<'a> {
+    json: &'a Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> GltfParser<'a> {
+    fn new(json: &'a Value, bin: Option<&[u8]>) -> Result<Self, String> {
+        let buffers = array(json, "buffers")
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| match buffer["uri"].as_str() {
+                Some(uri) => decode_data_uri(uri),
+                // Only the first buffer can refer to the binary chunk of a GLB file.
+                None if index == 0 => {
+                    bin.map(|bin| bin.to_vec()).ok_or_else(|| "Buffer without a URI outside of a GLB file".to_string())
+                }
+                None => Err("Buffer without a URI".to_string()),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { json, buffers })
+    }
+
+    fn parse(&self) -> Result<Model3d, String> {
+        let materials =
+            array(self.json, "materials").iter().map(|material| self.parse_material(material)).collect::<Result<_, _>>()?;
+
+        // Meshes can have multiple primitives, which all become separate `Mesh3d`s.
+        let mut meshes = vec![];
+        let mut mesh_ranges = vec![];
+        for mesh in array(self.json, "meshes") {
+            let start = meshes.len();
+            for primitive in array(mesh, "primitives") {
+                meshes.push(self.parse_primitive(primitive)?);
+            }
+            mesh_ranges.push(start..meshes.len());
+        }
+
+        // Without scenes, all root nodes get rendered.
+        let root_nodes: Vec<usize> = match self.json["scenes"].get(self.json["scene"].as_u64().unwrap_or(0) as usize) {
+            Some(scene) => array(scene, "nodes").iter().filter_map(Value::as_u64).map(|node| node as usize).collect(),
+            None => {
+                let nodes = array(self.json, "nodes");
+                let children: Vec<u64> =
+                    nodes.iter().flat_map(|node| array(node, "children")).filter_map(Value::as_u64).collect();
+                (0..nodes.len()).filter(|node| !children.contains(&(*node as u64))).collect()
+            }
+        };
+
+        let mut instances = vec![];
+        let mut stack: Vec<(usize, Mat4, usize)> = root_nodes.into_iter().map(|node| (node, Mat4::identity(), 0)).collect();
+        while let Some((node_index, parent_transform, depth)) = stack.pop() {
+            let node = self.json["nodes"].get(node_index).ok_or_else(|| format!("Invalid node index {}", node_index))?;
+            // Nodes must form a tree, but guard against cycles in broken files.
+            if depth > 1000 {
+                return Err("glTF node hierarchy is too deep".to_string());
+            }
+            let transform = Mat4::mul(&node_transform(node)?, &parent_transform);
+            if let Some(mesh) = node["mesh"].as_u64() {
+                let range = mesh_ranges.get(mesh as usize).ok_or_else(|| format!("Invalid mesh index {}", mesh))?;
+                instances.extend(range.clone().map(|mesh_index| Model3dInstance { mesh_index, transform }));
+            }
+            for child in array(node, "children").iter().filter_map(Value::as_u64) {
+                stack.push((child as usize, transform, depth + 1));
+            }
+        }
+
+        Ok(Model3d { meshes, materials, instances })
+    }
+
+    fn parse_primitive(&self, primitive: &Value) -> Result<Mesh3d, String> {
+        let attributes = &primitive["attributes"];
+        let positions = match attributes["POSITION"].as_u64() {
+            Some(accessor) => self.read_accessor_f32(accessor, 3)?,
+            None => return Err("Primitive without POSITION attribute".to_string()),
+        };
+        let vertex_count = positions.len() / 3;
+        let normals = attributes["NORMAL"].as_u64().map(|accessor| self.read_accessor_f32(accessor, 3)).transpose()?;
+        let uvs = attributes["TEXCOORD_0"].as_u64().map(|accessor| self.read_accessor_f32(accessor, 2)).transpose()?;
+        if normals.as_ref().is_some_and(|normals| normals.len() != vertex_count * 3)
+            || uvs.as_ref().is_some_and(|uvs| uvs.len() != vertex_count * 2)
+        {
+            return Err("Primitive attributes have different counts".to_string());
+        }
+
+        let vertices = (0..vertex_count)
+            .map(|i| Geometry3d {
+                geom_pos: vec3(positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]),
+                geom_id: 0.,
+                geom_normal: normals.as_ref().map_or(Vec3::default(), |n| vec3(n[i * 3], n[i * 3 + 1], n[i * 3 + 2])),
+                geom_uv: uvs.as_ref().map_or(Vec2::default(), |uv| vec2(uv[i * 2], uv[i * 2 + 1])),
+            })
+            .collect();
+
+        let indices = match primitive["indices"].as_u64() {
+            Some(accessor) => self.read_accessor_indices(accessor)?,
+            None => (0..vertex_count as u32).collect(),
+        };
+        if let Some(&index) = indices.iter().find(|&&index| index as usize >= vertex_count) {
+            return Err(format!("Vertex index {} out of bounds", index));
+        }
+        let triangle_indices = match primitive["mode"].as_u64().unwrap_or(MODE_TRIANGLES) {
+            MODE_TRIANGLES => indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+            MODE_TRIANGLE_STRIP => (2..indices.len())
+                .map(|i| {
+                    if i % 2 == 0 {
+                        [indices[i - 2], indices[i - 1], indices[i]]
+                    } else {
+                        [indices[i - 1], indices[i - 2], indices[i]]
+                    }
+                })
+                .collect(),
+            MODE_TRIANGLE_FAN => (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+            mode => return Err(format!("Unsupported primitive mode {}; only triangles are supported", mode)),
+        };
+
+        let mut mesh = Mesh3d { vertices, triangle_indices, material_index: primitive["material"].as_u64().map(|m| m as usize) };
+        if normals.is_none() {
+            mesh.compute_normals();
+        }
+        Ok(mesh)
+    }
+
+    fn parse_material(&self, material: &Value) -> Result<Material3d, String> {
+        let pbr = &material["pbrMetallicRoughness"];
+        let base_color = match array(pbr, "baseColorFactor").iter().map(Value::as_f64).collect::<Option<Vec<f64>>>() {
+            Some(factor) if factor.len() == 4 => vec4(factor[0] as f32, factor[1] as f32, factor[2] as f32, factor[3] as f32),
+            _ => Vec4::all(1.),
+        };
+        let base_color_texture = match pbr["baseColorTexture"]["index"].as_u64() {
+            Some(texture) => self.parse_texture(texture as usize)?,
+            None => None,
+        };
+        Ok(Material3d { name: material["name"].as_str().unwrap_or_default().to_string(), base_color, base_color_texture })
+    }
+
+    fn parse_texture(&self, texture: usize) -> Result<Option<Material3dTexture>, String> {
+        let image = match self.json["textures"][texture]["source"].as_u64() {
+            Some(source) => &self.json["images"][source as usize],
+            // Textures without a source use an extension we don't support.
+            None => return Ok(None),
+        };
+        if let Some(uri) = image["uri"].as_str() {
+            if uri.starts_with("data:") {
+                Ok(Some(Material3dTexture::Image(decode_image(&decode_data_uri(uri)?)?)))
+            } else {
+                Ok(Some(Material3dTexture::Uri(uri.to_string())))
+            }
+        } else if let Some(buffer_view) = image["bufferView"].as_u64() {
+            let (bytes, _stride) = self.buffer_view(buffer_view)?;
+            Ok(Some(Material3dTexture::Image(decode_image(bytes)?)))
+        } else {
+            Err("Image without a URI or buffer view".to_string())
+        }
+    }
+
+    /// Returns the bytes of a buffer view, and its stride if it has one.
+    fn buffer_view(&self, buffer_view: u64) -> Result<(&[u8], Option<usize>), String> {
+        let view =
+            self.json["bufferViews"].get(buffer_view as usize).ok_or_else(|| format!("Invalid buffer view {}", buffer_view))?;
+        let buffer = self.buffers.get(view["buffer"].as_u64().unwrap_or(u64::MAX) as usize).ok_or("Invalid buffer index")?;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+        let bytes = buffer.get(offset..offset + length).ok_or("Buffer view out of bounds")?;
+        Ok((bytes, view["byteStride"].as_u64().map(|stride| stride as usize)))
+    }
+
+    /// Calls `element` with the bytes of every element of an accessor, and returns the accessor's component type.
+    fn read_accessor(&self, accessor: u64, components: usize, mut element: impl FnMut(&[u8])) -> Result<u64, String> {
+        let accessor = self.json["accessors"].get(accessor as usize).ok_or_else(|| format!("Invalid accessor {}", accessor))?;
+        if !accessor["sparse"].is_null() {
+            return Err("Sparse accessors are not supported".to_string());
+        }
+        let expected_type = match components {
+            1 => "SCALAR",
+            2 => "VEC2",
+            _ => "VEC3",
+        };
+        if accessor["type"].as_str() != Some(expected_type) {
+            return Err(format!("Expected accessor of type {}, got {}", expected_type, accessor["type"]));
+        }
+        let component_type = accessor["componentType"].as_u64().unwrap_or(0);
+        let component_size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(format!("Invalid component type {}", component_type)),
+        };
+        let count = accessor["count"].as_u64().unwrap_or(0) as usize;
+        let buffer_view = match accessor["bufferView"].as_u64() {
+            Some(buffer_view) => buffer_view,
+            // Accessors without a buffer view are all zeros.
+            None => {
+                let zeros = vec![0; components * component_size];
+                (0..count).for_each(|_| element(&zeros));
+                return Ok(component_type);
+            }
+        };
+        let (bytes, stride) = self.buffer_view(buffer_view)?;
+        let element_size = components * component_size;
+        let stride = stride.unwrap_or(element_size);
+        let offset = accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        if count > 0 && offset + (count - 1) * stride + element_size > bytes.len() {
+            return Err("Accessor out of bounds".to_string());
+        }
+        for index in 0..count {
+            let start = offset + index * stride;
+            element(&bytes[start..start + element_size]);
+        }
+        Ok(component_type)
+    }
+
+    /// Reads a float accessor, converting normalized integers to floats.
+    fn read_accessor_f32(&self, accessor: u64, components: usize) -> Result<Vec<f32>, String> {
+        let mut values = vec![];
+        let mut elements = vec![];
+        let component_type = self.read_accessor(accessor, components, |bytes| elements.extend_from_slice(bytes))?;
+        match component_type {
+            5126 => values.extend(elements.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))),
+            5121 => values.extend(elements.iter().map(|&b| b as f32 / 255.)),
+            5123 => values.extend(elements.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.)),
+            5120 => values.extend(elements.iter().map(|&b| (b as i8 as f32 / 127.).max(-1.))),
+            5122 => values.extend(elements.chunks_exact(2).map(|b| (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.).max(-1.))),
+            _ => return Err(format!("Invalid component type {} for float attribute", component_type)),
+        }
+        Ok(values)
+    }
+
+    fn read_accessor_indices(&self, accessor: u64) -> Result<Vec<u32>, String> {
+        let mut elements = vec![];
+        let component_type = self.read_accessor(accessor, 1, |bytes| elements.extend_from_slice(bytes))?;
+        match component_type {
+            5121 => Ok(elements.iter().map(|&b| b as u32).collect()),
+            5123 => Ok(elements.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32).collect()),
+            5125 => Ok(elements.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()),
+            _ => Err(format!("Invalid component type {} for indices", component_type)),
+        }
+    }
+}
+
+/// Returns `value[key]` as a slice, or an empty slice if it's missing.
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value[key].as_array().map_or(&[], Vec::as_slice)
+}
+
+fn floats<const N: usize>(value: &Value) -> Result<Option<[f32; N]>, String> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let values: Vec<f32> = value.as_array().into_iter().flatten().filter_map(Value::as_f64).map(|v| v as f32).collect();
+    values.try_into().map(Some).map_err(|_| format!("Expected {} numbers, got {}", N, value))
+}
+
+/// The local transform of a node, either from its `matrix` or from its translation, rotation and scale.
+fn node_transform(node: &Value) -> Result<Mat4, String> {
+    if let Some(matrix) = floats::<16>(&node["matrix"])? {
+        // glTF matrices are column-major, just like [`Mat4`].
+        return Ok(Mat4 { v: matrix });
+    }
+    let [tx, ty, tz] = floats::<3>(&node["translation"])?.unwrap_or([0., 0., 0.]);
+    let [x, y, z, w] = floats::<4>(&node["rotation"])?.unwrap_or([0., 0., 0., 1.]);
+    let [sx, sy, sz] = floats::<3>(&node["scale"])?.unwrap_or([1., 1., 1.]);
+    Ok(Mat4 {
+        v: [
+            (1. - 2. * (y * y + z * z)) * sx,
+            (2. * (x * y + z * w)) * sx,
+            (2. * (x * z - y * w)) * sx,
+            0.,
+            (2. * (x * y - z * w)) * sy,
+            (1. - 2. * (x * x + z * z)) * sy,
+            (2. * (y * z + x * w)) * sy,
+            0.,
+            (2. * (x * z + y * w)) * sz,
+            (2. * (y * z - x * w)) * sz,
+            (1. - 2. * (x * x + y * y)) * sz,
+            0.,
+            tx,
+            ty,
+            tz,
+            1.,
+        ],
+    })
+}
+
+/// Decodes a base64 `data:` URI; other URIs are not supported.
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>, String> {
+    let data = match uri.strip_prefix("data:").and_then(|rest| rest.split_once(";base64,")) {
+        Some((_mime_type, data)) => data,
+        None => return Err(format!("Unsupported URI {:?}; only embedded base64 data URIs are supported", uri)),
+    };
+    base64::engine::general_purpose::STANDARD.decode(data).map_err(|err| format!("Invalid base64 data: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base64(bytes: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+
+    /// A triangle, followed by its `u16` indices.
+    fn triangle_buffer() -> Vec<u8> {
+        let positions: [f32; 9] = [0., 0., 0., 1., 0., 0., 0., 1., 0.];
+        let indices: [u16; 3] = [0, 1, 2];
+        let mut buffer: Vec<u8> = positions.iter().flat_map(|v| v.to_le_bytes()).collect();
+        buffer.extend(indices.iter().flat_map(|i| i.to_le_bytes()));
+        buffer
+    }
+
+    fn red_png() -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, 1, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[255, 0, 0, 255]).unwrap();
+        bytes
+    }
+
+    /// Node 0 translates node 1, which draws the triangle scaled. Node 2 draws the triangle rotated.
+    fn gltf_json(buffer: Value) -> Value {
+        serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0, 2] }],
+            "nodes": [
+                { "translation": [1, 0, 0], "children": [1] },
+                { "mesh": 0, "scale": [2, 2, 2] },
+                { "mesh": 0, "rotation": [0, 0, std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2] },
+            ],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+            "materials": [
+                { "name": "red", "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 0.5], "baseColorTexture": { "index": 0 } } },
+                { "name": "external", "pbrMetallicRoughness": { "baseColorTexture": { "index": 1 } } },
+            ],
+            "textures": [{ "source": 0 }, { "source": 1 }],
+            "images": [{ "uri": format!("data:image/png;base64,{}", base64(&red_png())) }, { "uri": "wheel.png" }],
+            "buffers": [buffer],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 6 },
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+                { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" },
+            ],
+        })
+    }
+
+    fn transform_point(transform: Mat4, point: Vec3) -> Vec3 {
+        transform.transform_vec4(vec4(point.x, point.y, point.z, 1.)).to_vec3()
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn it_parses_gltf() {
+        let buffer = triangle_buffer();
+        let json = gltf_json(serde_json::json!({
+            "uri": format!("data:application/octet-stream;base64,{}", base64(&buffer)),
+            "byteLength": buffer.len(),
+        }));
+        let model = Model3d::from_gltf(json.to_string().as_bytes()).unwrap();
+
+        assert_eq!(model.meshes.len(), 1);
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.triangle_indices, vec![[0, 1, 2]]);
+        assert_eq!(mesh.material_index, Some(0));
+        assert_eq!(mesh.vertices[1].geom_pos, vec3(1., 0., 0.));
+        // Normals are missing from the file, so they get computed.
+        assert!(mesh.vertices.iter().all(|vertex| vertex.geom_normal == vec3(0., 0., 1.)));
+
+        assert_eq!(model.instances.len(), 2);
+        let translated_scaled = model.instances.iter().find(|i| i.transform.v[12] != 0.).unwrap();
+        assert_close(transform_point(translated_scaled.transform, vec3(1., 0., 0.)), vec3(3., 0., 0.));
+        let rotated = model.instances.iter().find(|i| i.transform.v[12] == 0.).unwrap();
+        assert_close(transform_point(rotated.transform, vec3(1., 0., 0.)), vec3(0., 1., 0.));
+
+        assert_eq!(model.materials[0].name, "red");
+        assert_eq!(model.materials[0].base_color, vec4(1., 0., 0., 0.5));
+        assert_eq!(
+            model.materials[0].base_color_texture,
+            Some(Material3dTexture::Image(DecodedImage { width: 1, height: 1, pixels: vec![0xff0000ff] }))
+        );
+        assert_eq!(model.materials[1].base_color, Vec4::all(1.));
+        assert_eq!(model.materials[1].base_color_texture, Some(Material3dTexture::Uri("wheel.png".to_string())));
+    }
+
+    #[test]
+    fn it_parses_glb() {
+        let buffer = triangle_buffer();
+        let json = gltf_json(serde_json::json!({ "byteLength": buffer.len() }));
+        let mut json_chunk = json.to_string().into_bytes();
+        // Chunks are padded to 4 bytes; JSON with spaces and binary data with zeros.
+        json_chunk.resize(json_chunk.len().next_multiple_of(4), b' ');
+        let mut bin_chunk = buffer.clone();
+        bin_chunk.resize(bin_chunk.len().next_multiple_of(4), 0);
+
+        let mut glb = b"glTF".to_vec();
+        glb.extend(2u32.to_le_bytes());
+        glb.extend(((12 + 8 + json_chunk.len() + 8 + bin_chunk.len()) as u32).to_le_bytes());
+        for (chunk_type, chunk) in [(GLB_CHUNK_JSON, &json_chunk), (GLB_CHUNK_BIN, &bin_chunk)] {
+            glb.extend((chunk.len() as u32).to_le_bytes());
+            glb.extend(chunk_type.to_le_bytes());
+            glb.extend(chunk);
+        }
+
+        let embedded = gltf_json(serde_json::json!({
+            "uri": format!("data:application/octet-stream;base64,{}", base64(&buffer)),
+            "byteLength": buffer.len(),
+        }));
+        assert_eq!(Model3d::from_gltf(&glb).unwrap(), Model3d::from_gltf(embedded.to_string().as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_invalid_gltf() {
+        assert!(Model3d::from_gltf(b"{").unwrap_err().starts_with("Invalid glTF JSON"));
+        let json = gltf_json(serde_json::json!({ "uri": "car.bin", "byteLength": 42 }));
+        assert!(Model3d::from_gltf(json.to_string().as_bytes()).unwrap_err().starts_with("Unsupported URI"));
+        let mut json = gltf_json(serde_json::json!({ "byteLength": 42 }));
+        assert_eq!(Model3d::from_gltf(json.to_string().as_bytes()).unwrap_err(), "Buffer without a URI outside of a GLB file");
+        json["meshes"][0]["primitives"][0]["mode"] = serde_json::json!(1);
+        let buffer = triangle_buffer();
+        json["buffers"][0]["uri"] = format!("data:application/octet-stream;base64,{}", base64(&buffer)).into();
+        assert!(Model3d::from_gltf(json.to_string().as_bytes()).unwrap_err().starts_with("Unsupported primitive mode 1"));
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Loading [`Model3d`]s from Wavefront OBJ files, with materials from MTL files.
+
+use std::collections::HashMap;
+
+use crate::*;
+use wrflib::*;
+
+/// The position, texture coordinate, and normal indices of a face vertex.
+type ObjVertex = (usize, Option<usize>, Option<usize>);
+
+impl Model3d {
+    /// Parses an OBJ file, with materials from the contents of the MTL file that it refers to (`mtllib`), if any.
+    ///
+    /// Faces get split into a [`Mesh3d`] per material (`usemtl`), which all get a single [`Model3dInstance`] with
+    /// an identity transform. Polygons get triangulated as fans; points, lines, and free-form geometry are ignored.
+    /// Texture coordinates get flipped vertically, since OBJ has their origin at the bottom left. Missing
+    /// normals are computed from the triangles.
+    pub fn from_obj(obj: &str, mtl: Option<&str>) -> Result<Self, String> {
+        let materials = match mtl {
+            Some(mtl) => parse_mtl(mtl)?,
+            None => vec![],
+        };
+
+        let mut positions: Vec<Vec3> = vec![];
+        let mut uvs: Vec<Vec2> = vec![];
+        let mut normals: Vec<Vec3> = vec![];
+        let mut meshes: Vec<Mesh3d> = vec![];
+        // For each mesh, which vertices already have been added to it.
+        let mut vertex_maps: Vec<HashMap<ObjVertex, u32>> = vec![];
+        let mut has_normals: Vec<bool> = vec![];
+        let mut current_mesh = None;
+        let mut current_material = None;
+
+        for (line_index, line) in obj.lines().enumerate() {
+            let error = |message: &str| format!("Line {}: {}", line_index + 1, message);
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("v") => positions.push(parse_vec3(words).map_err(|err| error(&err))?),
+                Some("vt") => {
+                    let uv = parse_floats(words).map_err(|err| error(&err))?;
+                    // The second coordinate is optional.
+                    uvs.push(vec2(
+                        *uv.first().ok_or_else(|| error("Expected a texture coordinate"))?,
+                        1. - uv.get(1).unwrap_or(&0.),
+                    ));
+                }
+                Some("vn") => normals.push(parse_vec3(words).map_err(|err| error(&err))?),
+                Some("usemtl") => {
+                    let name = words.next().unwrap_or_default();
+                    current_material = materials.iter().position(|material: &Material3d| material.name == name);
+                    current_mesh = None;
+                }
+                Some("f") => {
+                    let mesh_index = *current_mesh.get_or_insert_with(|| {
+                        meshes.push(Mesh3d { material_index: current_material, ..Mesh3d::default() });
+                        vertex_maps.push(HashMap::new());
+                        has_normals.push(true);
+                        meshes.len() - 1
+                    });
+                    let mesh = &mut meshes[mesh_index];
+                    let mut face = vec![];
+                    for word in words {
+                        let mut parts = word.split('/');
+                        let position = resolve_index(parts.next(), positions.len()).map_err(|err| error(&err))?;
+                        let uv = resolve_index(parts.next(), uvs.len()).map_err(|err| error(&err))?;
+                        let normal = resolve_index(parts.next(), normals.len()).map_err(|err| error(&err))?;
+                        let position = position.ok_or_else(|| error("Face vertex without a position"))?;
+                        has_normals[mesh_index] &= normal.is_some();
+
+                        let index = *vertex_maps[mesh_index].entry((position, uv, normal)).or_insert_with(|| {
+                            mesh.vertices.push(Geometry3d {
+                                geom_pos: positions[position],
+                                geom_id: 0.,
+                                geom_normal: normal.map_or(Vec3::default(), |normal| normals[normal]),
+                                geom_uv: uv.map_or(Vec2::default(), |uv| uvs[uv]),
+                            });
+                            (mesh.vertices.len() - 1) as u32
+                        });
+                        face.push(index);
+                    }
+                    if face.len() < 3 {
+                        return Err(error("Face with less than 3 vertices"));
+                    }
+                    mesh.triangle_indices.extend((2..face.len()).map(|i| [face[0], face[i - 1], face[i]]));
+                }
+                _ => {}
+            }
+        }
+
+        for (mesh, has_normals) in meshes.iter_mut().zip(has_normals) {
+            if !has_normals {
+                mesh.compute_normals();
+            }
+        }
+        let instances = (0..meshes.len()).map(|mesh_index| Model3dInstance { mesh_index, transform: Mat4::identity() }).collect();
+        Ok(Model3d { meshes, materials, instances })
+    }
+}
+
+/// Parses the materials in an MTL file. Only the diffuse color (`Kd`), opacity (`d` or `Tr`), and diffuse texture
+/// (`map_Kd`) are used.
+fn parse_mtl(mtl: &str) -> Result<Vec<Material3d>, String> {
+    let mut materials: Vec<Material3d> = vec![];
+    for (line_index, line) in mtl.lines().enumerate() {
+        let error = |message: &str| format!("MTL line {}: {}", line_index + 1, message);
+        let mut words = line.split_whitespace();
+        let keyword = words.next();
+        if keyword == Some("newmtl") {
+            materials.push(Material3d { name: words.collect::<Vec<_>>().join(" "), ..Material3d::default() });
+            continue;
+        }
+        let material = match (keyword, materials.last_mut()) {
+            (Some("Kd" | "d" | "Tr" | "map_Kd"), Some(material)) => material,
+            (Some("Kd" | "d" | "Tr" | "map_Kd"), None) => return Err(error("Material property before newmtl")),
+            _ => continue,
+        };
+        match keyword {
+            Some("Kd") => {
+                let color = parse_vec3(words).map_err(|err| error(&err))?;
+                material.base_color = vec4(color.x, color.y, color.z, material.base_color.w);
+            }
+            Some("d") => material.base_color.w = parse_float(words.next()).map_err(|err| error(&err))?,
+            Some("Tr") => material.base_color.w = 1. - parse_float(words.next()).map_err(|err| error(&err))?,
+            _ => {
+                // The file name comes last, after any options.
+                let uri = words.last().ok_or_else(|| error("Expected a file name"))?;
+                material.base_color_texture = Some(Material3dTexture::Uri(uri.to_string()));
+            }
+        }
+    }
+    Ok(materials)
+}
+
+fn parse_float(word: Option<&str>) -> Result<f32, String> {
+    let word = word.ok_or_else(|| "Expected a number".to_string())?;
+    word.parse().map_err(|_| format!("Invalid number {:?}", word))
+}
+
+fn parse_floats<'a>(words: impl Iterator<Item = &'a str>) -> Result<Vec<f32>, String> {
+    words.map(|word| parse_float(Some(word))).collect()
+}
+
+fn parse_vec3<'a>(mut words: impl Iterator<Item = &'a str>) -> Result<Vec3, String> {
+    Ok(vec3(parse_float(words.next())?, parse_float(words.next())?, parse_float(words.next())?))
+}
+
+/// Turns a 1-based (or negative, counting from the end) OBJ index into a 0-based index. Empty indices (like in
+/// `1//2`) become `None`.
+fn resolve_index(word: Option<&str>, len: usize) -> Result<Option<usize>, String> {
+    let word = match word {
+        Some(word) if !word.is_empty() => word,
+        _ => return Ok(None),
+    };
+    let index: isize = word.parse().map_err(|_| format!("Invalid index {:?}", word))?;
+    let resolved = if index < 0 { len as isize + index } else { index - 1 };
+    if resolved < 0 || resolved as usize >= len {
+        return Err(format!("Index {} out of bounds", index));
+    }
+    Ok(Some(resolved as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MTL: &str = "
+        # Two materials
+        newmtl body
+        Kd 0.5 0.5 1.0
+        d 0.5
+        newmtl glass
+        Kd 0 0 0
+        map_Kd -s 1 1 1 textures/glass.png
+    ";
+
+    #[test]
+    fn it_parses_obj() {
+        let obj = "
+            mtllib car.mtl
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0.25
+            vn 0 0 1
+            usemtl body
+            f 1/1/1 2/2/1 3/1/1 4/1/1
+            usemtl glass
+            f -4 -2 -1
+            usemtl unknown
+            l 1 2
+        ";
+        let model = Model3d::from_obj(obj, Some(MTL)).unwrap();
+
+        assert_eq!(model.meshes.len(), 2);
+        let quad = &model.meshes[0];
+        assert_eq!(quad.material_index, Some(0));
+        // The quad gets triangulated, and the 4th vertex is the same as the 3rd except for the position.
+        assert_eq!(quad.vertices.len(), 4);
+        assert_eq!(quad.triangle_indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(
+            quad.vertices[1],
+            Geometry3d { geom_pos: vec3(1., 0., 0.), geom_id: 0., geom_normal: vec3(0., 0., 1.), geom_uv: vec2(1., 0.75) }
+        );
+
+        let triangle = &model.meshes[1];
+        assert_eq!(triangle.material_index, Some(1));
+        assert_eq!(
+            triangle.vertices.iter().map(|v| v.geom_pos).collect::<Vec<_>>(),
+            vec![vec3(0., 0., 0.), vec3(1., 1., 0.), vec3(0., 1., 0.)]
+        );
+        // Normals are missing from these faces, so they get computed.
+        assert!(triangle.vertices.iter().all(|vertex| vertex.geom_normal == vec3(0., 0., 1.)));
+
+        assert_eq!(
+            model.instances,
+            vec![
+                Model3dInstance { mesh_index: 0, transform: Mat4::identity() },
+                Model3dInstance { mesh_index: 1, transform: Mat4::identity() }
+            ]
+        );
+        assert_eq!(model.materials.len(), 2);
+        assert_eq!(model.materials[0].base_color, vec4(0.5, 0.5, 1., 0.5));
+        assert_eq!(model.materials[0].base_color_texture, None);
+        assert_eq!(model.materials[1].name, "glass");
+        assert_eq!(model.materials[1].base_color_texture, Some(Material3dTexture::Uri("textures/glass.png".to_string())));
+    }
+
+    #[test]
+    fn it_rejects_invalid_obj() {
+        assert_eq!(Model3d::from_obj("v 0 0\n", None).unwrap_err(), "Line 1: Expected a number");
+        assert_eq!(Model3d::from_obj("v 0 0 0\nf 1 2 3\n", None).unwrap_err(), "Line 2: Index 2 out of bounds");
+        assert_eq!(Model3d::from_obj("v 0 0 0\nf 1 1\n", None).unwrap_err(), "Line 2: Face with less than 3 vertices");
+        assert_eq!(Model3d::from_obj("", Some("Kd 1 1 1")).unwrap_err(), "MTL line 1: Material property before newmtl");
+    }
+}
@@ -44,6 +44,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 pub struct ScrollShadow;
 
@@ -76,6 +76,39 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
+
+static TEXTURED_SHADER: Shader = Shader {
+    build_geom: Some(build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        Geometry3d::SHADER,
+        DRAWCUBE_SHADER_PRELUDE,
+        code_fragment!(
+            r#"
+            texture texture: textureCube;
+
+            varying dir: vec3;
+            fn vertex() -> vec4 {
+                // The cube is centered around the origin, so the geometry position doubles as the view direction.
+                dir = geom_pos;
+                let model_view = camera_view * transform ;
+                return camera_projection * (model_view * vec4(
+                    geom_pos.x * cube_size.x + cube_pos.x,
+                    geom_pos.y * cube_size.y + cube_pos.y,
+                    geom_pos.z * cube_size.z + cube_pos.z + draw_zbias,
+                    1.
+                ));
+            }
+
+            fn pixel() -> vec4 {
+                return sample_cube(texture, dir);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+register_shader!(TEXTURED_SHADER);
 
 pub struct SkyBox;
 
@@ -89,4 +122,12 @@ impl SkyBox {
 
         cx.add_instances(&SHADER, &[cube]);
     }
+
+    /// Draws a sky using a cube map, e.g. one made with [`Texture::from_cube_faces`].
+    pub fn draw_with_texture(cx: &mut Cx, world_origin: Vec3, texture_handle: TextureHandle) {
+        let cube = CubeIns { cube_size: vec3(200., 200., 200.), cube_pos: world_origin, transform: Mat4::identity() };
+
+        let area = cx.add_instances(&TEXTURED_SHADER, &[cube]);
+        area.write_texture_2d(cx, "texture", texture_handle);
+    }
 }
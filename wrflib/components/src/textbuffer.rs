@@ -4,11 +4,16 @@
 // found in the LICENSE-APACHE file in the root directory of this source tree.
 // You may not use this file except in compliance with the License.
 
+use unicode_segmentation::UnicodeSegmentation;
 use wrflib::*;
 
 use crate::textcursor::*;
 use crate::tokentype::*;
 
+/// Number of characters that [`TextBuffer::prev_grapheme_offset`] and [`TextBuffer::next_grapheme_offset`] look
+/// at. Longer grapheme clusters (which don't occur in practice) get split.
+const GRAPHEME_WINDOW: usize = 32;
+
 #[derive(Clone, Default)]
 pub struct TextBuffer {
     // Vec<Vec<char>> was chosen because, for all practical use (code) most lines are short
@@ -372,6 +377,33 @@ impl TextBuffer {
         }
     }
 
+    /// The offset of the grapheme cluster boundary before `offset`, so that moving the cursor doesn't split
+    /// characters like "é" written with a combining accent. Line breaks count as a single cluster.
+    ///
+    /// Only a few characters around `offset` get segmented, so that this stays cheap on long lines.
+    pub fn prev_grapheme_offset(&self, offset: usize) -> usize {
+        let pos = self.offset_to_text_pos(offset);
+        match self.lines.get(pos.row) {
+            Some(line) if pos.col > 0 => {
+                let line_start = line[pos.col.saturating_sub(GRAPHEME_WINDOW)..pos.col].iter().collect::<String>();
+                offset - line_start.graphemes(true).next_back().map_or(1, |cluster| cluster.chars().count())
+            }
+            _ => offset.saturating_sub(1),
+        }
+    }
+
+    /// The offset of the grapheme cluster boundary after `offset`, see [`TextBuffer::prev_grapheme_offset`].
+    pub fn next_grapheme_offset(&self, offset: usize) -> usize {
+        let pos = self.offset_to_text_pos(offset);
+        match self.lines.get(pos.row) {
+            Some(line) if pos.col < line.len() => {
+                let line_end = line[pos.col..line.len().min(pos.col + GRAPHEME_WINDOW)].iter().collect::<String>();
+                offset + line_end.graphemes(true).next().map_or(1, |cluster| cluster.chars().count())
+            }
+            _ => offset + 1,
+        }
+    }
+
     pub fn get_char(&self, start: usize) -> char {
         let pos = self.offset_to_text_pos(start);
         let line = &self.lines[pos.row];
@@ -702,3 +734,37 @@ impl<'a> LineTokenizer<'a> {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_moves_by_grapheme_clusters() {
+        // "e" followed by a combining acute accent, and a flag made of two regional indicators.
+        let text_buffer = TextBuffer::from_utf8("ae\u{301}b\n🇳🇱");
+        assert_eq!(text_buffer.next_grapheme_offset(0), 1);
+        assert_eq!(text_buffer.next_grapheme_offset(1), 3);
+        assert_eq!(text_buffer.next_grapheme_offset(3), 4);
+        assert_eq!(text_buffer.next_grapheme_offset(4), 5);
+        assert_eq!(text_buffer.next_grapheme_offset(5), 7);
+        assert_eq!(text_buffer.prev_grapheme_offset(7), 5);
+        assert_eq!(text_buffer.prev_grapheme_offset(5), 4);
+        assert_eq!(text_buffer.prev_grapheme_offset(3), 1);
+        assert_eq!(text_buffer.prev_grapheme_offset(1), 0);
+        assert_eq!(text_buffer.prev_grapheme_offset(0), 0);
+
+        let mut cursor = TextCursor { head: 0, tail: 0, max: 0 };
+        cursor.move_right(2, text_buffer.calc_char_count(), &text_buffer);
+        assert_eq!(cursor.head, 3);
+        cursor.move_right(10, text_buffer.calc_char_count(), &text_buffer);
+        assert_eq!(cursor.head, 7);
+        cursor.move_left(3, &text_buffer);
+        assert_eq!(cursor.head, 3);
+
+        let text_buffer = TextBuffer::from_utf8(&format!("{}e\u{301}{}", "a".repeat(1000), "b".repeat(1000)));
+        assert_eq!(text_buffer.prev_grapheme_offset(1002), 1000);
+        assert_eq!(text_buffer.next_grapheme_offset(1000), 1002);
+        assert_eq!(text_buffer.next_grapheme_offset(1002), 1003);
+    }
+}
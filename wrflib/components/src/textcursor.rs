@@ -84,19 +84,17 @@ impl TextCursor {
         self.head = text_buffer.text_pos_to_offset(TextPos { row: pos.row, col: text_buffer.lines[pos.row].len() });
     }
 
-    pub fn move_left(&mut self, char_count: usize, _text_buffer: &TextBuffer) {
-        if self.head >= char_count {
-            self.head -= char_count;
-        } else {
-            self.head = 0;
+    /// Moves the head by `char_count` grapheme clusters, see [`TextBuffer::prev_grapheme_offset`].
+    pub fn move_left(&mut self, char_count: usize, text_buffer: &TextBuffer) {
+        for _ in 0..char_count {
+            self.head = text_buffer.prev_grapheme_offset(self.head);
         }
     }
 
-    pub fn move_right(&mut self, char_count: usize, total_char_count: usize, _text_buffer: &TextBuffer) {
-        if self.head + char_count < total_char_count {
-            self.head += char_count;
-        } else {
-            self.head = total_char_count;
+    /// Moves the head by `char_count` grapheme clusters, see [`TextBuffer::next_grapheme_offset`].
+    pub fn move_right(&mut self, char_count: usize, total_char_count: usize, text_buffer: &TextBuffer) {
+        for _ in 0..char_count {
+            self.head = text_buffer.next_grapheme_offset(self.head).min(total_char_count);
         }
     }
 
@@ -35,6 +35,7 @@ static SHADER_INDENT_LINES: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER_INDENT_LINES);
 static SHADER_CURSOR: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
@@ -56,6 +57,7 @@ static SHADER_CURSOR: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER_CURSOR);
 static SHADER_SELECTION: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
@@ -109,6 +111,7 @@ static SHADER_SELECTION: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER_SELECTION);
 static SHADER_PAREN_PAIR: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
@@ -126,6 +129,7 @@ static SHADER_PAREN_PAIR: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER_PAREN_PAIR);
 static SHADER_SEARCH_MARKER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
@@ -138,13 +142,14 @@ static SHADER_SEARCH_MARKER: Shader = Shader {
                 let pos2 = vec2(pos.x, pos.y + 0.03 * sin(pos.x * rect_size.x));
                 let df = Df::viewport(pos2 * rect_size);
                 df.move_to(vec2(0., rect_size.y - 1.));
-                df.line_to(rect_size.x, rect_size.y - 1.);
+                df.line_to(vec2(rect_size.x, rect_size.y - 1.));
                 return df.stroke(vec4(171.0/255.0,99.0/255.0,99.0/255.0,1.0), 0.8);
             }"#
         ),
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER_SEARCH_MARKER);
 static SHADER_MESSAGE_MARKER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
@@ -157,13 +162,14 @@ static SHADER_MESSAGE_MARKER: Shader = Shader {
                 let pos2 = vec2(pos.x, pos.y + 0.03 * sin(pos.x * rect_size.x));
                 let df = Df::viewport(pos2 * rect_size);
                 df.move_to(vec2(0., rect_size.y - 1.));
-                df.line_to(rect_size.x, rect_size.y - 1.);
+                df.line_to(vec2(rect_size.x, rect_size.y - 1.));
                 return df.stroke(color, 0.8);
             }"#
         ),
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER_MESSAGE_MARKER);
 
 /// Convenient type of [`QuadIns`] which has a single `color` field, which is
 /// drawn as the background by default. You pass in your own [`Shader`].
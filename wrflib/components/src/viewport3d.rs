@@ -67,6 +67,8 @@ pub struct Viewport3DProps {
     pub camera_target: Vec3,
     /// Represents if panning should move camera vertically.
     pub vertical_panning_enabled: bool,
+    /// Enables GPU picking; see [`Viewport3D::begin_picking_draw`].
+    pub picking_enabled: bool,
 }
 
 impl Viewport3DProps {
@@ -77,6 +79,7 @@ impl Viewport3DProps {
         camera_target: Vec3::all(0.),
         panning_enabled: true,
         vertical_panning_enabled: true,
+        picking_enabled: false,
     };
 }
 
@@ -86,6 +89,45 @@ impl Default for Viewport3DProps {
     }
 }
 
+/// A pickable instance that was hit; see [`Viewport3DPointerPick`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport3DPick {
+    /// The [`Area`] that was passed to [`Viewport3D::add_pickable`].
+    pub area: Area,
+    /// Index of the instance within `area`.
+    pub instance_index: usize,
+}
+
+/// What was picked at a [`PointerDownEvent`]; see [`Viewport3DEvent::Pick`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport3DPointerPick {
+    /// [`PointerDownEvent::abs`] of the pointer down.
+    pub abs: Vec2,
+    /// The picked instance, or `None` if nothing pickable is there.
+    pub pick: Option<Viewport3DPick>,
+}
+
+#[derive(Clone)]
+pub enum Viewport3DEvent {
+    None,
+    /// The camera moved, and the scene should be drawn with this projection.
+    CameraMove(PassMatrixMode),
+    /// Picking results for pointer downs on the viewport, in order. There is usually one, but pointer downs
+    /// that happen before the picking pass gets painted are answered together. See
+    /// [`Viewport3D::begin_picking_draw`].
+    Pick(Vec<Viewport3DPointerPick>),
+}
+
+#[derive(Clone)]
+struct Pickable {
+    area: Area,
+    first_pick_id: u32,
+    instance_count: usize,
+}
+
+/// Largest pick id that still fits in the RGB channels of the picking texture.
+const MAX_PICK_ID: u32 = (1 << 24) - 1;
+
 pub struct Viewport3D {
     component_id: ComponentId,
     area: Area,
@@ -102,6 +144,14 @@ pub struct Viewport3D {
     camera_target_offset_start: Option<Vec3>,
     props: Viewport3DProps,
     has_read_props: bool,
+    picking_pass: Pass,
+    picking_view: View,
+    picking_texture: Texture,
+    picking_depth_texture: Texture,
+    /// Pickables of the current picking draw.
+    pickables: Vec<Pickable>,
+    /// Positions of pointer downs that wait for the readback of the picking pass.
+    pending_picks: Vec<Vec2>,
 }
 
 impl Default for Viewport3D {
@@ -124,12 +174,45 @@ impl Default for Viewport3D {
             view_2d: Default::default(),
             has_read_props: Default::default(),
             props: Default::default(),
+            picking_pass: Default::default(),
+            picking_view: Default::default(),
+            picking_texture: Default::default(),
+            picking_depth_texture: Default::default(),
+            pickables: Default::default(),
+            pending_picks: Default::default(),
         }
     }
 }
 
 impl Viewport3D {
-    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> Option<PassMatrixMode> {
+    /// Shader helpers for drawing into the picking pass; see [`Viewport3D::begin_picking_draw`].
+    ///
+    /// Pick ids are encoded in the RGB channels of a regular RGBA8 texture, rather than rendered into an
+    /// integer texture. Shaders only output floats (GLSL ES 1.00 has no integer outputs), and RGBA8 is the one
+    /// color format that every platform can render into and read back. This limits pick ids to 2^24 - 1, with 0
+    /// reserved for the background.
+    pub const PICKING_SHADER: CodeFragment = code_fragment!(
+        r#"
+        // Encodes a pick id as an opaque color, with the most significant byte in red.
+        fn pick_id_to_color(pick_id: float) -> vec4 {
+            let id = floor(pick_id + 0.5);
+            return vec4(floor(id / 65536.), mod(floor(id / 256.), 256.), mod(id, 256.), 255.) / 255.;
+        }
+    "#
+    );
+
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> Viewport3DEvent {
+        if let Event::PassReadback(readback) = event {
+            if Some(readback.pass_id) == self.picking_pass.pass_id && !self.pending_picks.is_empty() {
+                let picks = std::mem::take(&mut self.pending_picks)
+                    .into_iter()
+                    .map(|abs| Viewport3DPointerPick { abs, pick: self.pick(cx, readback, abs) })
+                    .collect();
+                return Viewport3DEvent::Pick(picks);
+            }
+            return Viewport3DEvent::None;
+        }
+
         match event.hits_pointer(cx, self.component_id, self.area.get_rect_for_first_instance(cx)) {
             Event::PointerHover(_pe) => {
                 // cx.set_hover_mouse_cursor(MouseCursor::Move);
@@ -137,6 +220,10 @@ impl Viewport3D {
             // traditional mouse down
             Event::PointerDown(pe) => {
                 // cx.set_down_mouse_cursor(MouseCursor::Move);
+                if self.props.picking_enabled && self.picking_pass.pass_id.is_some() {
+                    self.pending_picks.push(pe.abs);
+                    self.picking_pass.request_readback(cx);
+                }
                 if self.props.panning_enabled && pe.button == MouseButton::Left {
                     self.camera_target_offset_start = Some(self.camera_target_offset);
                 } else if pe.button == MouseButton::Right {
@@ -154,7 +241,7 @@ impl Viewport3D {
                 let zoom_speed = (self.camera_position.radius * (PI / 4.) / max_distance).sin().abs() / 2.0;
                 self.camera_position.radius =
                     (self.camera_position.radius + pe.scroll.y * zoom_speed).max(min_distance).min(max_distance);
-                return Some(self.pass_set_matrix_mode(cx));
+                return Viewport3DEvent::CameraMove(self.pass_set_matrix_mode(cx));
             }
             Event::PointerMove(pe) => {
                 // Using standard makeSafe approach to clamp to slightly less than the limits for phi/theta
@@ -167,7 +254,7 @@ impl Viewport3D {
                         phi: (phi - (pe.abs.y - pe.abs_start.y) * rotate_speed).clamp(EPSILON, PI - EPSILON),
                         radius,
                     };
-                    return Some(self.pass_set_matrix_mode(cx));
+                    return Viewport3DEvent::CameraMove(self.pass_set_matrix_mode(cx));
                 } else if let Some(camera_target_offset_start) = self.camera_target_offset_start {
                     // TODO(Shobhit): Whenever we do Orthographic view properly, we need to adjust the panning accordingly
                     // We would need to consider viewable area's width and height into consideration just like how
@@ -193,13 +280,13 @@ impl Viewport3D {
                         .to_vec3()
                         + camera_target_offset_start;
 
-                    return Some(self.pass_set_matrix_mode(cx));
+                    return Viewport3DEvent::CameraMove(self.pass_set_matrix_mode(cx));
                 }
             }
             _ => (),
         }
 
-        None
+        Viewport3DEvent::None
     }
 
     fn get_matrix_projection(&self) -> PassMatrixMode {
@@ -221,6 +308,9 @@ impl Viewport3D {
     fn pass_set_matrix_mode(&mut self, cx: &mut Cx) -> PassMatrixMode {
         let matrix_mode = self.get_matrix_projection();
         self.pass.set_matrix_mode(cx, matrix_mode.clone());
+        if self.props.picking_enabled {
+            self.picking_pass.set_matrix_mode(cx, matrix_mode.clone());
+        }
         matrix_mode
     }
 
@@ -278,4 +368,106 @@ impl Viewport3D {
 
         self.view_2d.end_view(cx);
     }
+
+    /// Starts drawing into the picking pass, if [`Viewport3DProps::picking_enabled`] is set. Returns `false`
+    /// otherwise, in which case you should skip the picking draw altogether. Call this after
+    /// [`Viewport3D::end_draw`], draw the pickable objects again using [`Viewport3D::add_pickable`], and
+    /// finish with [`Viewport3D::end_picking_draw`]. For [`DrawPoints3d`](crate::DrawPoints3d) this is done by
+    /// [`DrawPoints3d::draw_picking`](crate::DrawPoints3d::draw_picking), and for meshes of
+    /// [`Geometry3d`](crate::Geometry3d) vertices by [`Geometry3d::draw_picking`](crate::Geometry3d::draw_picking).
+    ///
+    /// Instead of their regular color, objects in the picking pass output
+    /// `pick_id_to_color(pick_id)` from [`Viewport3D::PICKING_SHADER`], where `pick_id` is the value returned
+    /// by [`Viewport3D::add_pickable`] plus the instance index. Everything else is left out of the picking
+    /// pass, so it can't be picked, but it doesn't block picking either.
+    ///
+    /// When the pointer goes down over the viewport, the picking pass gets painted and read back from the GPU,
+    /// after which [`Viewport3D::handle`] returns a [`Viewport3DEvent::Pick`] with what is under the pointer.
+    #[must_use]
+    pub fn begin_picking_draw(&mut self, cx: &mut Cx) -> bool {
+        if !self.props.picking_enabled {
+            return false;
+        }
+        self.picking_pass.begin_pass_without_textures(cx);
+        self.picking_pass.set_size(cx, self.measured_size);
+        let picking_texture_handle = self.picking_texture.get_color(cx);
+        self.picking_pass.add_color_texture(cx, picking_texture_handle, ClearColor::ClearWith(Vec4::default()));
+        let depth_texture_handle = self.picking_depth_texture.get_depth(cx);
+        self.picking_pass.set_depth_texture(cx, depth_texture_handle, ClearDepth::ClearWith(1.0));
+
+        self.picking_view.begin_view(cx, LayoutSize::FILL);
+        self.pickables.clear();
+        true
+    }
+
+    /// Registers `instance_count` instances of `area` for picking, and returns the pick id of the first
+    /// instance. Must be called between [`Viewport3D::begin_picking_draw`] and
+    /// [`Viewport3D::end_picking_draw`].
+    ///
+    /// Panics if there are more than 2^24 - 1 pickable instances in total, since their ids wouldn't fit in the
+    /// picking texture; see [`Viewport3D::PICKING_SHADER`].
+    pub fn add_pickable(&mut self, area: Area, instance_count: usize) -> u32 {
+        // Pick id 0 is reserved for the clear color.
+        let first_pick_id = self.pickables.last().map_or(1, |last| last.first_pick_id as usize + last.instance_count);
+        assert!(
+            first_pick_id + instance_count <= MAX_PICK_ID as usize + 1,
+            "Too many pickable instances in Viewport3D: {} exceeds the maximum of {}",
+            first_pick_id + instance_count - 1,
+            MAX_PICK_ID
+        );
+        let first_pick_id = first_pick_id as u32;
+        self.pickables.push(Pickable { area, first_pick_id, instance_count });
+        first_pick_id
+    }
+
+    pub fn end_picking_draw(&mut self, cx: &mut Cx) {
+        let matrix_mode = self.get_matrix_projection();
+        self.picking_pass.set_matrix_mode(cx, matrix_mode);
+
+        self.picking_view.end_view(cx);
+        self.picking_pass.end_pass(cx);
+    }
+
+    /// Returns the picked instance at absolute position `abs` in `readback` of the picking pass, or `None` if
+    /// nothing pickable is there.
+    fn pick(&self, cx: &Cx, readback: &PassReadbackEvent, abs: Vec2) -> Option<Viewport3DPick> {
+        let rect = self.area.get_rect_for_first_instance(cx)?;
+        if !rect.contains(abs) || readback.pixels.is_empty() {
+            return None;
+        }
+        let x = ((abs.x - rect.pos.x) / rect.size.x * readback.width as f32) as usize;
+        let y = ((abs.y - rect.pos.y) / rect.size.y * readback.height as f32) as usize;
+        let pixel = *readback.pixels.get(y.min(readback.height - 1) * readback.width + x.min(readback.width - 1))?;
+        let [r, g, b, _a] = pixel.to_le_bytes();
+        let pick_id = u32::from_be_bytes([0, r, g, b]);
+
+        self.pickables.iter().find_map(|pickable| {
+            let instance_index = pick_id.checked_sub(pickable.first_pick_id)? as usize;
+            if instance_index < pickable.instance_count {
+                Some(Viewport3DPick { area: pickable.area, instance_index })
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_pickable_accepts_ids_up_to_the_maximum() {
+        let mut viewport = Viewport3D::default();
+        assert_eq!(viewport.add_pickable(Area::default(), 10), 1);
+        assert_eq!(viewport.add_pickable(Area::default(), MAX_PICK_ID as usize - 10), 11);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many pickable instances in Viewport3D: 16777216 exceeds the maximum of 16777215")]
+    fn add_pickable_rejects_ids_above_the_maximum() {
+        let mut viewport = Viewport3D::default();
+        viewport.add_pickable(Area::default(), 10);
+        viewport.add_pickable(Area::default(), MAX_PICK_ID as usize - 9);
+    }
 }
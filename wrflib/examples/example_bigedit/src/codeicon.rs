@@ -104,6 +104,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 pub enum CodeIconType {
     Panic,
@@ -98,6 +98,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 pub enum ColorPickerEvent {
     Change { hsva: Vec4 },
@@ -54,6 +54,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 pub struct FileTree {
     pub view: ScrollView,
@@ -6,6 +6,7 @@ use crate::ChartExample;
 pub(crate) struct LinesBasic {
     pub(crate) chart: Chart,
     pub(crate) datasets: Vec<Vec<f32>>,
+    pub(crate) data_revision: u64,
     pub(crate) randomize_btn: Button,
     pub(crate) add_dataset_btn: Button,
     pub(crate) add_data_btn: Button,
@@ -16,6 +17,8 @@ pub(crate) struct LinesBasic {
     pub(crate) tooltip: ChartTooltipConfig,
     pub(crate) pan_enabled: bool,
     pub(crate) zoom_enabled: bool,
+    pub(crate) chart_type: ChartType,
+    pub(crate) stacked: bool,
 }
 
 impl Default for LinesBasic {
@@ -23,6 +26,7 @@ impl Default for LinesBasic {
         let mut ret = Self {
             chart: Chart::default(),
             datasets: vec![],
+            data_revision: 1,
             randomize_btn: Button::default(),
             add_dataset_btn: Button::default(),
             add_data_btn: Button::default(),
@@ -33,6 +37,8 @@ impl Default for LinesBasic {
             tooltip: Default::default(),
             pan_enabled: false,
             zoom_enabled: false,
+            chart_type: ChartType::Line,
+            stacked: false,
         };
 
         // Add two initial datasets
@@ -60,6 +66,10 @@ impl LinesBasic {
         Self { zoom_enabled: true, pan_enabled: true, ..Self::default() }
     }
 
+    pub(crate) fn with_chart_type(chart_type: ChartType, stacked: bool) -> Self {
+        Self { chart_type, stacked, ..Self::default() }
+    }
+
     fn get_random_data(count: usize) -> Vec<f32> {
         if count == 0 {
             vec![]
@@ -77,6 +87,7 @@ impl LinesBasic {
         for data in &mut self.datasets {
             *data = Self::get_random_data(data_count);
         }
+        self.data_revision += 1;
     }
 
     fn add_dataset(&mut self) {
@@ -137,10 +148,12 @@ impl LinesBasic {
             .map(|(i, data)| ChartDataset {
                 label: format!("Dataset {}", i),
                 data: ChartData::from_values(data),
+                background_color: vec4(1., 1., 1., 0.5) * colors[i % colors.len()],
                 point_background_color: colors[i % colors.len()],
                 point_radius: 4.,
                 border_color: colors[i % colors.len()],
                 border_width: 2.,
+                revision: self.data_revision,
                 ..ChartDataset::default()
             })
             .collect();
@@ -155,10 +168,12 @@ impl LinesBasic {
 
         let config = ChartConfig {
             labels,
-            chart_type: ChartType::Line,
+            chart_type: self.chart_type,
+            stacked: self.stacked,
             datasets,
             style: self.style.clone(),
             tooltip: self.tooltip.clone(),
+            legend: ChartLegendConfig { display: true, ..ChartLegendConfig::default() },
             zoom_enabled: self.zoom_enabled,
             pan_enabled: self.pan_enabled,
             ..ChartConfig::default()
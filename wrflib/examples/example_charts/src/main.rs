@@ -6,6 +6,10 @@ use chart_list::*;
 
 mod lines_basic;
 use lines_basic::*;
+mod multiple_axes;
+use multiple_axes::*;
+mod time_series;
+use time_series::*;
 mod tooltip_custom;
 use tooltip_custom::*;
 
@@ -36,6 +40,13 @@ impl ChartsExampleApp {
                 "Lines",
                 "Lines - Dark",
                 "Lines - Styling (TODO)",
+                "Bars",
+                "Bars - Stacked",
+                "Scatter",
+                "Area",
+                "Area - Stacked",
+                "Time Series",
+                "Multiple Axes",
                 "Tooltip - Custom",
                 "Interaction - Zoom",
                 "Interaction - Pan",
@@ -57,6 +68,13 @@ impl ChartsExampleApp {
             match selected {
                 "Lines" => self.chart = Box::new(LinesBasic::default()),
                 "Lines - Dark" => self.chart = Box::new(LinesBasic::with_dark_style()),
+                "Bars" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Bar, false)),
+                "Bars - Stacked" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Bar, true)),
+                "Scatter" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Scatter, false)),
+                "Area" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Area, false)),
+                "Area - Stacked" => self.chart = Box::new(LinesBasic::with_chart_type(ChartType::Area, true)),
+                "Time Series" => self.chart = Box::new(TimeSeriesExample::default()),
+                "Multiple Axes" => self.chart = Box::new(MultipleAxesExample::default()),
                 "Tooltip - Custom" => self.chart = Box::new(TooltipCustomExample::default()),
                 "Interaction - Zoom" => self.chart = Box::new(LinesBasic::with_zoom()),
                 "Interaction - Pan" => self.chart = Box::new(LinesBasic::with_pan()),
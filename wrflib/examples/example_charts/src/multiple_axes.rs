@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use wrflib::*;
+use wrflib_components::*;
+
+use crate::ChartExample;
+
+/// Plots velocity and acceleration against their own y axes, since they have very
+/// different ranges. Scroll or drag over the labels of an axis to zoom or pan only that axis.
+pub(crate) struct MultipleAxesExample {
+    pub(crate) chart: Chart,
+    pub(crate) velocity: Vec<f32>,
+    pub(crate) acceleration: Vec<f32>,
+    pub(crate) reset_view_btn: Button,
+}
+
+impl Default for MultipleAxesExample {
+    fn default() -> Self {
+        let time_step = 0.1;
+
+        let mut velocity = vec![];
+        let mut acceleration = vec![];
+        for i in 0..100 {
+            let t = i as f32 * time_step;
+            velocity.push(20. + 15. * (t * 0.5).sin());
+            acceleration.push(7.5 * (t * 0.5).cos());
+        }
+
+        Self { chart: Chart::default(), velocity, acceleration, reset_view_btn: Button::default() }
+    }
+}
+
+impl ChartExample for MultipleAxesExample {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ChartEvent {
+        if let ButtonEvent::Clicked = self.reset_view_btn.handle(cx, event) {
+            self.chart.reset_zoom_pan();
+        }
+
+        self.chart.handle(cx, event)
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        cx.begin_column(Width::Fill, Height::Fill);
+
+        cx.begin_row(Width::Fill, Height::Fix(cx.get_height_left() - 70.));
+        cx.begin_padding_box(Padding::top(20.));
+
+        let mut scales = HashMap::new();
+        scales.insert("acceleration".to_string(), ChartScale { position: ChartAxisPosition::Right, ..ChartScale::default() });
+
+        let config = ChartConfig {
+            datasets: vec![
+                ChartDataset {
+                    label: "Velocity (m/s)".to_string(),
+                    data: ChartData::from_values(&self.velocity),
+                    point_background_color: COLOR_BLUE,
+                    point_radius: 2.,
+                    border_color: COLOR_BLUE,
+                    border_width: 2.,
+                    y_axis_id: "velocity",
+                    ..ChartDataset::default()
+                },
+                ChartDataset {
+                    label: "Acceleration (m/s²)".to_string(),
+                    data: ChartData::from_values(&self.acceleration),
+                    point_background_color: COLOR_RED,
+                    point_radius: 2.,
+                    border_color: COLOR_RED,
+                    border_width: 2.,
+                    y_axis_id: "acceleration",
+                    ..ChartDataset::default()
+                },
+            ],
+            scales,
+            style: CHART_STYLE_LIGHT,
+            legend: ChartLegendConfig { display: true, position: ChartLegendPosition::Bottom },
+            zoom_enabled: true,
+            pan_enabled: true,
+            ..ChartConfig::default()
+        };
+        self.chart.draw(cx, &config);
+
+        cx.end_padding_box();
+        cx.end_row();
+
+        cx.begin_row(Width::Fill, Height::Fix(50.));
+        self.reset_view_btn.draw(cx, "Reset View");
+        cx.end_row();
+
+        cx.end_column();
+    }
+}
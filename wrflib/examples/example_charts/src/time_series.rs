@@ -0,0 +1,74 @@
+use wrflib::*;
+use wrflib_components::*;
+
+use crate::ChartExample;
+
+/// Plots a signal sampled every 100ms against wall-clock time, using nanosecond
+/// timestamps like the ones found in robot logs.
+pub(crate) struct TimeSeriesExample {
+    pub(crate) chart: Chart,
+    pub(crate) times: Vec<f64>,
+    pub(crate) values: Vec<f32>,
+    pub(crate) reset_view_btn: Button,
+}
+
+impl Default for TimeSeriesExample {
+    fn default() -> Self {
+        // 2021-10-12 01:46:40 UTC
+        let start_time = 1_634_003_200e9;
+        let sample_period = 100e6;
+
+        let mut times = vec![];
+        let mut values = vec![];
+        for i in 0..600 {
+            times.push(start_time + i as f64 * sample_period);
+            values.push(50. * (i as f32 * 0.05).sin() + 20. * (i as f32 * 0.31).sin());
+        }
+
+        Self { chart: Chart::default(), times, values, reset_view_btn: Button::default() }
+    }
+}
+
+impl ChartExample for TimeSeriesExample {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> ChartEvent {
+        if let ButtonEvent::Clicked = self.reset_view_btn.handle(cx, event) {
+            self.chart.reset_zoom_pan();
+        }
+
+        self.chart.handle(cx, event)
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        cx.begin_column(Width::Fill, Height::Fill);
+
+        cx.begin_row(Width::Fill, Height::Fix(cx.get_height_left() - 70.));
+        cx.begin_padding_box(Padding::top(20.));
+
+        let config = ChartConfig {
+            datasets: vec![ChartDataset {
+                label: "Signal".to_string(),
+                data: ChartData::from_time_series(&self.times, &self.values),
+                point_background_color: COLOR_BLUE,
+                point_radius: 2.,
+                border_color: COLOR_BLUE,
+                border_width: 2.,
+                ..ChartDataset::default()
+            }],
+            time_axis: Some(ChartTimeAxis { unit: ChartTimeUnit::Nanoseconds, ..ChartTimeAxis::default() }),
+            style: CHART_STYLE_LIGHT,
+            zoom_enabled: true,
+            pan_enabled: true,
+            ..ChartConfig::default()
+        };
+        self.chart.draw(cx, &config);
+
+        cx.end_padding_box();
+        cx.end_row();
+
+        cx.begin_row(Width::Fill, Height::Fix(50.));
+        self.reset_view_btn.draw(cx, "Reset View");
+        cx.end_row();
+
+        cx.end_column();
+    }
+}
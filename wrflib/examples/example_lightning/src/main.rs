@@ -114,6 +114,7 @@ static SHADER: Shader = Shader {
 
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 struct LogoApp {
     window: Window,
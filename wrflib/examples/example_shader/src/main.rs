@@ -153,6 +153,7 @@ return fbm(pos + q) > 0. ? primary_color : secondary_color;
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 struct ColorSliders {
     label: String,
     slider_r: FloatSlider,
@@ -32,6 +32,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 struct MultithreadExampleApp {
     window: Window,
@@ -90,6 +90,7 @@ static MAIN_SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(MAIN_SHADER);
 
 #[derive(Clone)]
 #[repr(C)]
@@ -8,7 +8,7 @@ use crate::builtin::Builtin;
 use crate::const_eval::ConstEvaluator;
 use crate::dep_analyse::DepAnalyser;
 use crate::env::{Env, Sym, VarKind};
-use crate::error::ParseError;
+use crate::error::{ErrorCode, ParseError, ParseErrors};
 use crate::ident::{Ident, IdentPath};
 use crate::shaderast::*;
 use crate::span::Span;
@@ -17,7 +17,7 @@ use crate::ty_check::TyChecker;
 use std::cell::RefCell;
 use std::collections::{BTreeSet, HashMap, HashSet};
 
-pub(crate) fn analyse_shader<'a>(builtins: &'a HashMap<Ident, Builtin>, shader: &'a ShaderAst) -> Result<(), ParseError> {
+pub(crate) fn analyse_shader<'a>(builtins: &'a HashMap<Ident, Builtin>, shader: &'a ShaderAst) -> Result<(), ParseErrors> {
     ShaderAnalyser { builtins, shader, env: &mut Env::default() }.analyse_shader()
 }
 
@@ -37,24 +37,42 @@ impl<'a> ShaderAnalyser<'a> {
         ConstEvaluator { shader: self.shader }
     }
 
-    fn analyse_shader(&mut self) -> Result<(), ParseError> {
+    fn analyse_shader(&mut self) -> Result<(), ParseErrors> {
         self.env.push_scope();
         for &ident in self.builtins.keys() {
             let _ = self.env.insert_sym(Span::default(), IdentPath::from_ident(ident), Sym::Builtin);
         }
+        let mut errors = vec![];
         for decl in &self.shader.decls {
-            self.analyse_decl(decl)?;
+            if let Err(err) = self.analyse_decl(decl) {
+                errors.push(err);
+            }
         }
-        for decl in &self.shader.decls {
-            match decl {
-                Decl::Fn(decl) => {
-                    FnDefAnalyser { builtins: self.builtins, shader: self.shader, decl, env: self.env, is_inside_loop: false }
-                        .analyse_fn_def()?;
+        // Function bodies can refer to any declaration, so after an invalid declaration we'd mostly report
+        // follow-up errors for them.
+        if errors.is_empty() {
+            for decl in &self.shader.decls {
+                if let Decl::Fn(decl) = decl {
+                    let scope_count = self.env.scope_count();
+                    let result = FnDefAnalyser {
+                        builtins: self.builtins,
+                        shader: self.shader,
+                        decl,
+                        env: self.env,
+                        is_inside_loop: false,
+                    }
+                    .analyse_fn_def();
+                    if let Err(err) = result {
+                        self.env.truncate_scopes(scope_count);
+                        errors.push(err);
+                    }
                 }
-                _ => {}
             }
         }
         self.env.pop_scope();
+        if !errors.is_empty() {
+            return Err(ParseErrors { errors });
+        }
         for decl in &self.shader.decls {
             match decl {
                 Decl::Geometry(decl) => {
@@ -91,9 +109,43 @@ impl<'a> ShaderAnalyser<'a> {
         for &instance_dep in fragment_decl.instance_deps.borrow().as_ref().unwrap() {
             self.shader.find_instance_decl(instance_dep).unwrap().is_used_in_fragment_shader.set(Some(true));
         }
+        self.mark_used_structs(vertex_decl, fragment_decl);
         Ok(())
     }
 
+    /// Sets [`StructDecl::is_used`], so that structs that are only used by unused functions (e.g. in a module
+    /// of which only a few functions get used) don't end up in the generated code.
+    fn mark_used_structs(&self, vertex_decl: &FnDecl, fragment_decl: &FnDecl) {
+        for decl in &self.shader.decls {
+            if let Decl::Struct(decl) = decl {
+                decl.is_used.set(Some(false));
+            }
+        }
+        let mut struct_deps: Vec<Ident> = vertex_decl
+            .struct_deps
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .union(fragment_decl.struct_deps.borrow().as_ref().unwrap())
+            .copied()
+            .collect();
+        while let Some(struct_dep) = struct_deps.pop() {
+            let decl = self.shader.find_struct_decl(struct_dep).unwrap();
+            if decl.is_used.replace(Some(true)) == Some(true) {
+                continue;
+            }
+            for field in &decl.fields {
+                let mut ty = field.ty_expr.ty.borrow().as_ref().unwrap().clone();
+                while let Ty::Array { elem_ty, .. } = ty {
+                    ty = (*elem_ty).clone();
+                }
+                if let Ty::Struct { ident } = ty {
+                    struct_deps.push(ident);
+                }
+            }
+        }
+    }
+
     fn analyse_decl(&mut self, decl: &Decl) -> Result<(), ParseError> {
         match decl {
             Decl::Geometry(decl) => self.analyse_geometry_decl(decl),
@@ -112,10 +164,11 @@ impl<'a> ShaderAnalyser<'a> {
         match ty {
             Ty::Float | Ty::Vec2 | Ty::Vec3 | Ty::Vec4 | Ty::Mat4 => {}
             _ => {
-                return Err(ParseError {
-                    span: decl.span,
-                    message: String::from("attribute must be either a floating-point scalar or vector or mat4"),
-                })
+                return Err(ParseError::new(
+                    ErrorCode::InvalidDeclaration,
+                    decl.span,
+                    "attribute must be either a floating-point scalar or vector or mat4",
+                ))
             }
         }
         self.env.insert_sym(decl.span, IdentPath::from_ident(decl.ident), Sym::Var { is_mut: false, ty, kind: VarKind::Geometry })
@@ -123,7 +176,11 @@ impl<'a> ShaderAnalyser<'a> {
 
     fn analyse_const_decl(&mut self, decl: &ConstDecl) -> Result<(), ParseError> {
         let expected_ty = self.ty_checker().ty_check_ty_expr(&decl.ty_expr)?;
-        let actual_ty = self.ty_checker().ty_check_expr_with_expected_ty(decl.span, &decl.expr, &expected_ty)?;
+        let actual_ty = self.ty_checker().ty_check_expr_with_expected_ty(
+            &decl.expr,
+            &expected_ty,
+            Some((decl.ty_expr.span(), "expected due to this type")),
+        )?;
         self.const_evaluator().const_eval_expr(&decl.expr)?;
         self.env.insert_sym(
             decl.span,
@@ -146,26 +203,28 @@ impl<'a> ShaderAnalyser<'a> {
             match return_ty {
                 Ty::Vec4 => {}
                 _ => {
-                    return Err(ParseError {
-                        span: decl.span,
-                        message: String::from("function `vertex` must return a value of type `vec4`"),
-                    })
+                    return Err(ParseError::new(
+                        ErrorCode::InvalidDeclaration,
+                        decl.span,
+                        "function `vertex` must return a value of type `vec4`",
+                    ))
                 }
             }
         } else if decl.ident_path == IdentPath::from_str("pixel") {
             match return_ty {
                 Ty::Vec4 => {}
                 _ => {
-                    return Err(ParseError {
-                        span: decl.span,
-                        message: String::from("function `fragment` must return a value of type `vec4`"),
-                    })
+                    return Err(ParseError::new(
+                        ErrorCode::InvalidDeclaration,
+                        decl.span,
+                        "function `fragment` must return a value of type `vec4`",
+                    ))
                 }
             }
         } else {
             match return_ty {
                 Ty::Array { .. } => {
-                    return Err(ParseError { span: decl.span, message: String::from("functions can't return arrays") })
+                    return Err(ParseError::new(ErrorCode::InvalidDeclaration, decl.span, "functions can't return arrays"))
                 }
                 _ => {}
             }
@@ -181,10 +240,11 @@ impl<'a> ShaderAnalyser<'a> {
         match ty {
             Ty::Float | Ty::Vec2 | Ty::Vec3 | Ty::Vec4 | Ty::Mat4 => {}
             _ => {
-                return Err(ParseError {
-                    span: decl.span,
-                    message: String::from("attribute must be either a floating-point scalar or vector or mat4"),
-                })
+                return Err(ParseError::new(
+                    ErrorCode::InvalidDeclaration,
+                    decl.span,
+                    "attribute must be either a floating-point scalar or vector or mat4",
+                ))
             }
         }
         self.env.insert_sym(decl.span, IdentPath::from_ident(decl.ident), Sym::Var { is_mut: false, ty, kind: VarKind::Instance })
@@ -199,9 +259,12 @@ impl<'a> ShaderAnalyser<'a> {
 
     fn analyse_texture_decl(&mut self, decl: &TextureDecl) -> Result<(), ParseError> {
         let ty = self.ty_checker().ty_check_ty_expr(&decl.ty_expr)?;
-        match ty {
-            Ty::Texture2D => {}
-            _ => return Err(ParseError { span: decl.span, message: String::from("texture must be a texture2D") }),
+        if !ty.is_texture() {
+            return Err(ParseError::new(
+                ErrorCode::InvalidDeclaration,
+                decl.span,
+                "texture must be a texture2D, textureCube, or texture3D",
+            ));
         }
         self.env.insert_sym(decl.span, IdentPath::from_ident(decl.ident), Sym::Var { is_mut: false, ty, kind: VarKind::Texture })
     }
@@ -216,10 +279,11 @@ impl<'a> ShaderAnalyser<'a> {
         match ty {
             Ty::Float | Ty::Vec2 | Ty::Vec3 | Ty::Vec4 => {}
             _ => {
-                return Err(ParseError {
-                    span: decl.span,
-                    message: String::from("varying must be either a floating-point scalar or vector"),
-                })
+                return Err(ParseError::new(
+                    ErrorCode::InvalidDeclaration,
+                    decl.span,
+                    "varying must be either a floating-point scalar or vector",
+                ))
             }
         }
         self.env.insert_sym(decl.span, IdentPath::from_ident(decl.ident), Sym::Var { is_mut: true, ty, kind: VarKind::Varying })
@@ -236,10 +300,13 @@ impl<'a> ShaderAnalyser<'a> {
                 continue;
             }
             if call_stack.contains(&callee) {
-                return Err(ParseError {
-                    span: decl.span,
-                    message: format!("function `{}` recursively calls `{}`", decl.ident_path, callee),
-                });
+                return Err(ParseError::new(
+                    ErrorCode::InvalidShaderStage,
+                    decl.span,
+                    format!("function `{}` recursively calls `{}`", decl.ident_path, callee),
+                )
+                .with_label(callee_decl.span, format!("`{}` defined here", callee))
+                .with_help("shaders don't support recursion"));
             }
             self.analyse_call_tree(kind, call_stack, callee_decl)?;
         }
@@ -272,34 +339,41 @@ impl<'a> ShaderAnalyser<'a> {
                 .as_mut()
                 .unwrap()
                 .extend(callee_decl.cons_fn_deps.borrow().as_ref().unwrap().iter().cloned());
+            decl.struct_deps.borrow_mut().as_mut().unwrap().extend(callee_decl.struct_deps.borrow().as_ref().unwrap());
         }
         if decl.is_used_in_vertex_shader.get().unwrap() && decl.is_used_in_fragment_shader.get().unwrap() {
             if !decl.geometry_deps.borrow().as_ref().unwrap().is_empty() {
-                return Err(ParseError {
-                    span: decl.span,
-                    message: format!(
+                return Err(ParseError::new(
+                    ErrorCode::InvalidShaderStage,
+                    decl.span,
+                    format!(
                         "function `{}` can't access any geometries, since it's used in both the vertex and fragment shader",
                         decl.ident_path
                     ),
-                });
+                )
+                .with_help(STAGE_HELP));
             }
             if !decl.instance_deps.borrow().as_ref().unwrap().is_empty() {
-                return Err(ParseError {
-                    span: decl.span,
-                    message: format!(
+                return Err(ParseError::new(
+                    ErrorCode::InvalidShaderStage,
+                    decl.span,
+                    format!(
                         "function `{}` can't access any instances, since it's used in both the vertex and fragment shader",
                         decl.ident_path
                     ),
-                });
+                )
+                .with_help(STAGE_HELP));
             }
             if decl.has_varying_deps.get().unwrap() {
-                return Err(ParseError {
-                    span: decl.span,
-                    message: format!(
+                return Err(ParseError::new(
+                    ErrorCode::InvalidShaderStage,
+                    decl.span,
+                    format!(
                         "function `{}` can't access any varyings, since it's used in both the vertex and fragment shader",
                         decl.ident_path
                     ),
-                });
+                )
+                .with_help(STAGE_HELP));
             }
         }
         visited.insert(decl.ident_path);
@@ -307,6 +381,9 @@ impl<'a> ShaderAnalyser<'a> {
     }
 }
 
+/// Help for functions that are used in both the vertex and fragment shader, but access something only one of them can.
+const STAGE_HELP: &str = "split the function up, so that the part that accesses them is only called from `vertex` or `pixel`";
+
 #[derive(Debug)]
 struct FnDefAnalyser<'a> {
     builtins: &'a HashMap<Ident, Builtin>,
@@ -352,6 +429,11 @@ impl<'a> FnDefAnalyser<'a> {
         *self.decl.instance_deps.borrow_mut() = Some(BTreeSet::new());
         self.decl.has_varying_deps.set(Some(false));
         *self.decl.cons_fn_deps.borrow_mut() = Some(BTreeSet::new());
+        *self.decl.struct_deps.borrow_mut() = Some(BTreeSet::new());
+        for param in &self.decl.params {
+            self.dep_analyser().dep_analyse_ty(param.ty_expr.ty.borrow().as_ref().unwrap());
+        }
+        self.dep_analyser().dep_analyse_ty(self.decl.return_ty.borrow().as_ref().unwrap());
         self.analyse_block(&self.decl.block)?;
         self.env.pop_scope();
         Ok(())
@@ -375,21 +457,24 @@ impl<'a> FnDefAnalyser<'a> {
                 self.analyse_if_stmt(span, expr, block_if_true, block_if_false)
             }
             Stmt::Let { span, ref ty, ident, ref ty_expr, ref expr } => self.analyse_let_stmt(span, ty, ident, ty_expr, expr),
+            Stmt::Loop { span, ref block } => self.analyse_loop_stmt(span, block),
+            Stmt::Match { span, ref expr, ref arms } => self.analyse_match_stmt(span, expr, arms),
             Stmt::Return { span, ref expr } => self.analyse_return_stmt(span, expr),
+            Stmt::While { span, ref expr, ref block } => self.analyse_while_stmt(span, expr, block),
             Stmt::Expr { span, ref expr } => self.analyse_expr_stmt(span, expr),
         }
     }
 
     fn analyse_break_stmt(&self, span: Span) -> Result<(), ParseError> {
         if !self.is_inside_loop {
-            return Err(ParseError { span, message: String::from("break outside loop") });
+            return Err(ParseError::new(ErrorCode::InvalidControlFlow, span, "break outside loop"));
         }
         Ok(())
     }
 
     fn analyse_continue_stmt(&self, span: Span) -> Result<(), ParseError> {
         if !self.is_inside_loop {
-            return Err(ParseError { span, message: String::from("continue outside loop") });
+            return Err(ParseError::new(ErrorCode::InvalidControlFlow, span, "continue outside loop"));
         }
         Ok(())
     }
@@ -403,23 +488,23 @@ impl<'a> FnDefAnalyser<'a> {
         step_expr: &Option<Expr>,
         block: &Block,
     ) -> Result<(), ParseError> {
-        self.ty_checker().ty_check_expr_with_expected_ty(span, from_expr, &Ty::Int)?;
+        self.ty_checker().ty_check_expr_with_expected_ty(from_expr, &Ty::Int, None)?;
         let from = self.const_evaluator().const_eval_expr(from_expr)?.to_int().unwrap();
         self.dep_analyser().dep_analyse_expr(from_expr);
-        self.ty_checker().ty_check_expr_with_expected_ty(span, to_expr, &Ty::Int)?;
+        self.ty_checker().ty_check_expr_with_expected_ty(to_expr, &Ty::Int, None)?;
         let to = self.const_evaluator().const_eval_expr(to_expr)?.to_int().unwrap();
         self.dep_analyser().dep_analyse_expr(to_expr);
         if let Some(step_expr) = step_expr {
-            self.ty_checker().ty_check_expr_with_expected_ty(span, step_expr, &Ty::Int)?;
+            self.ty_checker().ty_check_expr_with_expected_ty(step_expr, &Ty::Int, None)?;
             let step = self.const_evaluator().const_eval_expr(step_expr)?.to_int().unwrap();
             if step == 0 {
-                return Err(ParseError { span, message: String::from("step must not be zero") });
+                return Err(ParseError::new(ErrorCode::InvalidControlFlow, span, "step must not be zero"));
             }
             if from < to && step < 0 {
-                return Err(ParseError { span, message: String::from("step must not be positive") });
+                return Err(ParseError::new(ErrorCode::InvalidControlFlow, span, "step must not be positive"));
             }
             if from > to && step > 0 {
-                return Err(ParseError { span, message: String::from("step must not be negative") });
+                return Err(ParseError::new(ErrorCode::InvalidControlFlow, span, "step must not be negative"));
             }
             self.dep_analyser().dep_analyse_expr(step_expr);
         }
@@ -435,12 +520,12 @@ impl<'a> FnDefAnalyser<'a> {
 
     fn analyse_if_stmt(
         &mut self,
-        span: Span,
+        _span: Span,
         expr: &Expr,
         block_if_true: &Block,
         block_if_false: &Option<Box<Block>>,
     ) -> Result<(), ParseError> {
-        self.ty_checker().ty_check_expr_with_expected_ty(span, expr, &Ty::Bool)?;
+        self.ty_checker().ty_check_expr_with_expected_ty(expr, &Ty::Bool, None)?;
         self.const_evaluator().try_const_eval_expr(expr);
         self.dep_analyser().dep_analyse_expr(expr);
         self.env.push_scope();
@@ -465,7 +550,11 @@ impl<'a> FnDefAnalyser<'a> {
         *ty.borrow_mut() = Some(if let Some(ty_expr) = ty_expr {
             let expected_ty = self.ty_checker().ty_check_ty_expr(ty_expr)?;
             if let Some(expr) = expr {
-                let actual_ty = self.ty_checker().ty_check_expr_with_expected_ty(span, expr, &expected_ty)?;
+                let actual_ty = self.ty_checker().ty_check_expr_with_expected_ty(
+                    expr,
+                    &expected_ty,
+                    Some((ty_expr.span(), "expected due to this type")),
+                )?;
                 self.dep_analyser().dep_analyse_expr(expr);
                 actual_ty
             } else {
@@ -474,14 +563,15 @@ impl<'a> FnDefAnalyser<'a> {
         } else if let Some(expr) = expr {
             let ty = self.ty_checker().ty_check_expr(expr)?;
             if ty == Ty::Void {
-                return Err(ParseError { span, message: String::from("init expression cannot be void") });
+                return Err(ParseError::new(ErrorCode::TypeMismatch, span, "init expression cannot be void"));
             }
             self.const_evaluator().try_const_eval_expr(expr);
             self.dep_analyser().dep_analyse_expr(expr);
             ty
         } else {
-            return Err(ParseError { span, message: format!("can't infer type of variable `{}`", ident) });
+            return Err(ParseError::new(ErrorCode::TypeMismatch, span, format!("can't infer type of variable `{}`", ident)));
         });
+        self.dep_analyser().dep_analyse_ty(ty.borrow().as_ref().unwrap());
         self.env.insert_sym(
             span,
             IdentPath::from_ident(ident),
@@ -489,18 +579,80 @@ impl<'a> FnDefAnalyser<'a> {
         )
     }
 
+    fn analyse_loop_stmt(&mut self, _span: Span, block: &Block) -> Result<(), ParseError> {
+        self.analyse_loop_block(block)
+    }
+
+    fn analyse_match_stmt(&mut self, _span: Span, expr: &Expr, arms: &[MatchArm]) -> Result<(), ParseError> {
+        self.ty_checker().ty_check_expr_with_expected_ty(expr, &Ty::Int, None)?;
+        self.const_evaluator().try_const_eval_expr(expr);
+        self.dep_analyser().dep_analyse_expr(expr);
+        let mut wildcard_span = None;
+        let mut matched_vals: Vec<(i32, Span)> = Vec::new();
+        for arm in arms {
+            for pattern in &arm.patterns {
+                let previous_span = match *pattern {
+                    Pattern::Int { span, val } => {
+                        let previous_span = matched_vals.iter().find(|(other_val, _)| *other_val == val).map(|(_, span)| *span);
+                        matched_vals.push((val, span));
+                        previous_span
+                    }
+                    Pattern::Wildcard { span } => {
+                        let previous_span = wildcard_span;
+                        wildcard_span = Some(span);
+                        previous_span
+                    }
+                };
+                if let Some(previous_span) = previous_span.or(wildcard_span.filter(|span| *span != pattern.span())) {
+                    return Err(ParseError::new(ErrorCode::InvalidControlFlow, pattern.span(), "unreachable pattern")
+                        .with_label(previous_span, "matches any value that this pattern would match"));
+                }
+            }
+            self.env.push_scope();
+            self.analyse_block(&arm.block)?;
+            self.env.pop_scope();
+        }
+        Ok(())
+    }
+
     fn analyse_return_stmt(&mut self, span: Span, expr: &Option<Expr>) -> Result<(), ParseError> {
         if let Some(expr) = expr {
-            self.ty_checker().ty_check_expr_with_expected_ty(span, expr, self.decl.return_ty.borrow().as_ref().unwrap())?;
+            let expected_because = self
+                .decl
+                .return_ty_expr
+                .as_ref()
+                .map(|return_ty_expr| (return_ty_expr.span(), "expected because of this return type"));
+            self.ty_checker().ty_check_expr_with_expected_ty(
+                expr,
+                self.decl.return_ty.borrow().as_ref().unwrap(),
+                expected_because,
+            )?;
 
             self.const_evaluator().try_const_eval_expr(expr);
             self.dep_analyser().dep_analyse_expr(expr);
         } else if self.decl.return_ty.borrow().as_ref().unwrap() != &Ty::Void {
-            return Err(ParseError { span, message: String::from("missing return expression") });
+            return Err(ParseError::new(ErrorCode::InvalidControlFlow, span, "missing return expression"));
         }
         Ok(())
     }
 
+    fn analyse_while_stmt(&mut self, _span: Span, expr: &Expr, block: &Block) -> Result<(), ParseError> {
+        self.ty_checker().ty_check_expr_with_expected_ty(expr, &Ty::Bool, None)?;
+        self.const_evaluator().try_const_eval_expr(expr);
+        self.dep_analyser().dep_analyse_expr(expr);
+        self.analyse_loop_block(block)
+    }
+
+    fn analyse_loop_block(&mut self, block: &Block) -> Result<(), ParseError> {
+        self.env.push_scope();
+        let was_inside_loop = self.is_inside_loop;
+        self.is_inside_loop = true;
+        self.analyse_block(block)?;
+        self.is_inside_loop = was_inside_loop;
+        self.env.pop_scope();
+        Ok(())
+    }
+
     fn analyse_expr_stmt(&mut self, _span: Span, expr: &Expr) -> Result<(), ParseError> {
         self.ty_checker().ty_check_expr(expr)?;
         self.const_evaluator().try_const_eval_expr(expr);
@@ -325,6 +325,12 @@ pub(crate) fn generate_builtins() -> HashMap<Ident, Builtin> {
         builtin!(sample2d, [
             (Ty::Texture2D, Ty::Vec2) -> Ty::Vec4
         ]),
+        builtin!(sample3d, [
+            (Ty::Texture3D, Ty::Vec3) -> Ty::Vec4
+        ]),
+        builtin!(sample_cube, [
+            (Ty::TextureCube, Ty::Vec3) -> Ty::Vec4
+        ]),
         builtin!(sign, [
             (Ty::Float) -> Ty::Float,
             (Ty::Vec2) -> Ty::Vec2,
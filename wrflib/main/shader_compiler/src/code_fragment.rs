@@ -9,12 +9,18 @@ pub enum CodeFragment {
 impl CodeFragment {
     /// Offset the `line` and `col` fields by a certain number of characters.
     pub fn name_line_col_at_offset(&self, offset_chars: usize) -> String {
-        let (name, mut line, mut col, code) = match self {
-            CodeFragment::Static { filename, line, col, code } => (*filename, *line, *col, *code),
-            CodeFragment::Dynamic { name, code } => (name.as_str(), 0 as usize, 0 as usize, code.as_str()),
+        let (line, col) = self.line_col_at_offset(offset_chars);
+        format!("{}:{}:{}", self.name(), line, col)
+    }
+
+    /// Like [`CodeFragment::name_line_col_at_offset`], but returns just the `line` and `col`.
+    pub fn line_col_at_offset(&self, offset_chars: usize) -> (usize, usize) {
+        let (mut line, mut col) = match self {
+            CodeFragment::Static { line, col, .. } => (*line, *col),
+            CodeFragment::Dynamic { .. } => (0, 0),
         };
 
-        for (char_index, ch) in code.chars().enumerate() {
+        for (char_index, ch) in self.code().chars().enumerate() {
             if char_index == offset_chars {
                 break;
             }
@@ -25,7 +31,15 @@ impl CodeFragment {
                 col += 1;
             }
         }
-        format!("{}:{}:{}", name, line, col)
+        (line, col)
+    }
+
+    /// The filename or name of this fragment.
+    pub fn name(&self) -> &str {
+        match self {
+            CodeFragment::Static { filename, .. } => filename,
+            CodeFragment::Dynamic { name, .. } => name,
+        }
     }
 
     pub fn code(&self) -> &str {
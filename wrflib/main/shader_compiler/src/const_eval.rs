@@ -5,7 +5,7 @@
 // You may not use this file except in compliance with the License.
 
 use crate::env::VarKind;
-use crate::error::ParseError;
+use crate::error::{ErrorCode, ParseError};
 use crate::ident::{Ident, IdentPath};
 use crate::lit::Lit;
 use crate::shaderast::*;
@@ -21,8 +21,7 @@ pub(crate) struct ConstEvaluator<'a> {
 
 impl<'a> ConstEvaluator<'a> {
     pub(crate) fn const_eval_expr(&self, expr: &Expr) -> Result<Val, ParseError> {
-        self.try_const_eval_expr(expr)
-            .ok_or_else(|| ParseError { span: expr.span, message: String::from("expression is not const") })
+        self.try_const_eval_expr(expr).ok_or_else(|| ParseError::new(ErrorCode::NotConst, expr.span, "expression is not const"))
     }
 
     pub(crate) fn try_const_eval_expr(&self, expr: &Expr) -> Option<Val> {
@@ -121,6 +120,31 @@ impl<'a> ConstEvaluator<'a> {
                 (Val::Float(x), Val::Float(y)) => Some(Val::Float(x / y)),
                 _ => None,
             },
+            BinOp::Rem => match (&left_val, &right_val) {
+                (Val::Int(x), Val::Int(y)) => x.checked_rem(*y).map(Val::Int),
+                _ => None,
+            },
+            BinOp::BitOr => match (&left_val, &right_val) {
+                (Val::Int(x), Val::Int(y)) => Some(Val::Int(x | y)),
+                _ => None,
+            },
+            BinOp::BitXor => match (&left_val, &right_val) {
+                (Val::Int(x), Val::Int(y)) => Some(Val::Int(x ^ y)),
+                _ => None,
+            },
+            BinOp::BitAnd => match (&left_val, &right_val) {
+                (Val::Int(x), Val::Int(y)) => Some(Val::Int(x & y)),
+                _ => None,
+            },
+            // Shifting by 32 or more bits gives different results on different GPUs, so leave those to the GPU.
+            BinOp::Shl => match (&left_val, &right_val) {
+                (Val::Int(x), Val::Int(y)) => x.checked_shl(*y as u32).map(Val::Int),
+                _ => None,
+            },
+            BinOp::Shr => match (&left_val, &right_val) {
+                (Val::Int(x), Val::Int(y)) => x.checked_shr(*y as u32).map(Val::Int),
+                _ => None,
+            },
             _ => None,
         }
     }
@@ -131,6 +155,7 @@ impl<'a> ConstEvaluator<'a> {
         match op {
             UnOp::Not => match val {
                 Val::Bool(x) => Some(Val::Bool(!x)),
+                Val::Int(x) => Some(Val::Int(!x)),
                 _ => None,
             },
             UnOp::Neg => match val {
@@ -21,6 +21,9 @@ pub(crate) struct DepAnalyser<'a> {
 
 impl<'a> DepAnalyser<'a> {
     pub(crate) fn dep_analyse_expr(&mut self, expr: &Expr) {
+        if let Some(ty) = expr.ty.borrow().as_ref() {
+            self.dep_analyse_ty(ty);
+        }
         match expr.kind {
             ExprKind::Cond { span, ref expr, ref expr_if_true, ref expr_if_false } => {
                 self.dep_analyse_cond_expr(span, expr, expr_if_true, expr_if_false)
@@ -39,6 +42,18 @@ impl<'a> DepAnalyser<'a> {
         }
     }
 
+    /// Records the struct that `ty` refers to, if any. Structs used in the fields of other structs are found
+    /// later, for the structs that end up being used.
+    pub(crate) fn dep_analyse_ty(&mut self, ty: &Ty) {
+        match ty {
+            Ty::Array { elem_ty, .. } => self.dep_analyse_ty(elem_ty),
+            Ty::Struct { ident } => {
+                self.decl.struct_deps.borrow_mut().as_mut().unwrap().insert(*ident);
+            }
+            _ => {}
+        }
+    }
+
     fn dep_analyse_cond_expr(&mut self, _span: Span, expr: &Expr, expr_if_true: &Expr, expr_if_false: &Expr) {
         self.dep_analyse_expr(expr);
         self.dep_analyse_expr(expr_if_true);
@@ -4,7 +4,7 @@
 // found in the LICENSE-APACHE file in the root directory of this source tree.
 // You may not use this file except in compliance with the License.
 
-use crate::error::ParseError;
+use crate::error::{ErrorCode, ParseError};
 use crate::ident::{Ident, IdentPath};
 use crate::span::{CodeFragmentId, Span};
 use crate::token::{Token, TokenWithSpan};
@@ -61,14 +61,15 @@ impl<'a> DeTokParser for DeTokParserImpl<'a> {
     }
 
     fn error(&mut self, message: String) -> ParseError {
-        ParseError {
-            span: Span {
+        ParseError::new(
+            ErrorCode::Syntax,
+            Span {
                 code_fragment_id: self.token_with_span.span.code_fragment_id,
                 start: self.token_with_span.span.start,
                 end: self.token_with_span.span.end,
             },
             message,
-        }
+        )
     }
 
     fn error_missing_prop(&mut self, what: &str) -> ParseError {
@@ -189,6 +190,10 @@ impl SpanTracker {
     }
 
     pub(crate) fn error(&self, parser: &dyn DeTokParser, message: String) -> ParseError {
-        ParseError { span: Span { code_fragment_id: self.code_fragment_id, start: self.start, end: parser.token_end() }, message }
+        ParseError::new(
+            ErrorCode::Syntax,
+            Span { code_fragment_id: self.code_fragment_id, start: self.start, end: parser.token_end() },
+            message,
+        )
     }
 }
@@ -4,7 +4,7 @@
 // found in the LICENSE-APACHE file in the root directory of this source tree.
 // You may not use this file except in compliance with the License.
 
-use crate::error::ParseError;
+use crate::error::{ErrorCode, ParseError};
 use crate::ident::IdentPath;
 
 use crate::span::Span;
@@ -21,11 +21,26 @@ impl Env {
     pub(crate) fn find_sym(&self, ident_path: IdentPath) -> Option<Sym> {
         let ret = self.scopes.iter().rev().find_map(|scope| scope.get(&ident_path));
         if ret.is_some() {
-            return Some(ret.unwrap().clone());
+            return Some(ret.unwrap().1.clone());
         }
         None
     }
 
+    /// Where a symbol was defined, or [`None`] for builtins and undefined symbols.
+    pub(crate) fn find_sym_span(&self, ident_path: IdentPath) -> Option<Span> {
+        let (span, _) = self.scopes.iter().rev().find_map(|scope| scope.get(&ident_path))?;
+        Some(*span).filter(|span| *span != Span::default())
+    }
+
+    /// Depth of the scope stack, for restoring it using [`Env::truncate_scopes`] after an error.
+    pub(crate) fn scope_count(&self) -> usize {
+        self.scopes.len()
+    }
+
+    pub(crate) fn truncate_scopes(&mut self, scope_count: usize) {
+        self.scopes.truncate(scope_count);
+    }
+
     pub(crate) fn push_scope(&mut self) {
         self.scopes.push(Scope::new())
     }
@@ -37,10 +52,22 @@ impl Env {
     pub(crate) fn insert_sym(&mut self, span: Span, ident_path: IdentPath, sym: Sym) -> Result<(), ParseError> {
         match self.scopes.last_mut().unwrap().entry(ident_path) {
             Entry::Vacant(entry) => {
-                entry.insert(sym);
+                entry.insert((span, sym));
                 Ok(())
             }
-            Entry::Occupied(_) => Err(ParseError { span, message: format!("`{}` is already defined in this scope", ident_path) }),
+            Entry::Occupied(entry) => {
+                let err = ParseError::new(
+                    ErrorCode::AlreadyDefined,
+                    span,
+                    format!("`{}` is already defined in this scope", ident_path),
+                );
+                Err(match entry.get() {
+                    (_, Sym::Builtin) => {
+                        err.with_help(format!("`{}` is a builtin function, so pick a different name", ident_path))
+                    }
+                    (previous_span, _) => err.with_label(*previous_span, format!("previous definition of `{}` here", ident_path)),
+                })
+            }
         }
     }
 }
@@ -64,4 +91,19 @@ pub(crate) enum VarKind {
     Varying,
 }
 
-type Scope = HashMap<IdentPath, Sym>;
+impl VarKind {
+    /// For use in error messages, e.g. "instances can't be assigned to".
+    pub(crate) fn describe_plural(self) -> &'static str {
+        match self {
+            VarKind::Geometry => "geometries",
+            VarKind::Const => "constants",
+            VarKind::Instance => "instances",
+            VarKind::Local => "local variables",
+            VarKind::Texture => "textures",
+            VarKind::Uniform => "uniforms",
+            VarKind::Varying => "varyings",
+        }
+    }
+}
+
+type Scope = HashMap<IdentPath, (Span, Sym)>;
@@ -6,11 +6,48 @@
 
 use crate::{code_fragment::CodeFragment, span::Span};
 use std::fmt;
+use std::fmt::Write;
+
+/// The kind of a [`ParseError`], printed as e.g. `error[E0005]`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorCode {
+    /// Characters that don't form a valid token.
+    Lex = 1,
+    /// Tokens in an unexpected order.
+    Syntax = 2,
+    /// A name that is not defined, or that refers to the wrong kind of thing.
+    UndefinedName = 3,
+    /// A name that is defined twice in the same scope.
+    AlreadyDefined = 4,
+    /// A value of the wrong type.
+    TypeMismatch = 5,
+    /// Assigning to something that can't be assigned to.
+    InvalidAssignment = 6,
+    /// A declaration using a type that is not supported for it.
+    InvalidDeclaration = 7,
+    /// `break`, `continue`, `return`, `for` or `match` used incorrectly.
+    InvalidControlFlow = 8,
+    /// An expression that needs to be constant, but isn't.
+    NotConst = 9,
+    /// A function that can't be used in the vertex or fragment shader that calls it.
+    InvalidShaderStage = 10,
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "E{:04}", *self as u32)
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct ParseError {
+    pub code: ErrorCode,
     pub span: Span,
     pub message: String,
+    /// Other relevant locations, e.g. where a variable was declared, with an explanation for each.
+    pub labels: Vec<(Span, String)>,
+    /// Suggestion for how to fix the error.
+    pub help: Option<String>,
 }
 
 impl fmt::Display for ParseError {
@@ -20,17 +57,334 @@ impl fmt::Display for ParseError {
 }
 
 impl ParseError {
+    pub fn new(code: ErrorCode, span: Span, message: impl Into<String>) -> Self {
+        Self { code, span, message: message.into(), labels: vec![], help: None }
+    }
+
+    pub fn with_label(mut self, span: Span, label: impl Into<String>) -> Self {
+        self.labels.push((span, label.into()));
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Formats the error like `rustc` does, with the source code of every span underlined.
     pub fn format_for_console(&self, code_fragments: &[CodeFragment]) -> String {
-        let code_fragment = &code_fragments[self.span.code_fragment_id.0];
-        let pos = self.span.start;
-        // Hacky debugging: besides printing the file/line/col and so on, we also pull out a
-        // string (without newlines) from the actual code, and position it such that `pos`
-        // appears right under our "vvvvvv". :-)
-        format!(
-            "Error parsing shader at {} => {}\n\naround:             vvvvvv\n{}",
-            code_fragment.name_line_col_at_offset(pos),
-            self.message,
-            code_fragment.code().chars().skip(pos - 20).take(50).collect::<String>().replace('\n', " "),
-        )
+        // Group markers by line, so that e.g. a type annotation and the value that doesn't match it get shown together.
+        let mut lines: Vec<(SourceLine, Vec<Marker>)> = vec![];
+        let markers =
+            std::iter::once((self.span, '^', "")).chain(self.labels.iter().map(|(span, label)| (*span, '-', label.as_str())));
+        for (span, marker, label) in markers {
+            let line = SourceLine::new(&code_fragments[span.code_fragment_id.0], span);
+            match lines.iter_mut().find(|(other_line, _)| other_line.span == line.span) {
+                Some((_, line_markers)) => line_markers.push((span, marker, label)),
+                None => lines.push((line, vec![(span, marker, label)])),
+            }
+        }
+        let gutter_width = lines.iter().map(|(line, _)| line.number.to_string().len()).max().unwrap();
+
+        let mut out = format!("error[{}]: {}", self.code, self.message);
+        for (line, line_markers) in lines {
+            let code_fragment = &code_fragments[line.span.code_fragment_id.0];
+            let location = code_fragment.name_line_col_at_offset(line_markers[0].0.start.min(line.span.end));
+            write!(out, "\n{:w$}--> {}", "", location, w = gutter_width).unwrap();
+            write!(out, "\n{:w$} |", "", w = gutter_width).unwrap();
+            write!(out, "\n{:>w$} | {}", line.number, line.text.trim_end(), w = gutter_width).unwrap();
+            for (span, marker, label) in line_markers {
+                let start = span.start.clamp(line.span.start, line.span.end);
+                // Keep tabs, so that the markers line up with the code above them.
+                let indent = line
+                    .text
+                    .chars()
+                    .take(start - line.span.start)
+                    .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                    .collect::<String>();
+                let markers = marker.to_string().repeat(span.end.min(line.span.end).saturating_sub(start).max(1));
+                let marker_line = format!("{}{} {}", indent, markers, label);
+                write!(out, "\n{:w$} | {}", "", marker_line.trim_end(), w = gutter_width).unwrap();
+            }
+        }
+        if let Some(help) = &self.help {
+            write!(out, "\n{:w$} = help: {}", "", help, w = gutter_width).unwrap();
+        }
+        out
+    }
+}
+
+/// A [`Span`] to underline, the character to underline it with, and a label to put after it.
+type Marker<'a> = (Span, char, &'a str);
+
+/// The line of code that a [`Span`] starts on.
+struct SourceLine {
+    /// The whole line, without the newline.
+    span: Span,
+    number: usize,
+    text: String,
+}
+
+impl SourceLine {
+    fn new(code_fragment: &CodeFragment, span: Span) -> Self {
+        let chars = code_fragment.code().chars().collect::<Vec<_>>();
+        let start = span.start.min(chars.len());
+        let line_start = chars[..start].iter().rposition(|&ch| ch == '\n').map_or(0, |index| index + 1);
+        let line_end = chars[start..].iter().position(|&ch| ch == '\n').map_or(chars.len(), |index| start + index);
+        Self {
+            span: Span { code_fragment_id: span.code_fragment_id, start: line_start, end: line_end },
+            number: code_fragment.line_col_at_offset(start).0,
+            text: chars[line_start..line_end].iter().collect(),
+        }
+    }
+}
+
+/// All errors found while compiling a shader. Contains at least one error.
+#[derive(Clone, Debug)]
+pub struct ParseErrors {
+    pub errors: Vec<ParseError>,
+}
+
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let messages = self.errors.iter().map(|error| error.message.as_str()).collect::<Vec<_>>();
+        write!(f, "{}", messages.join("\n"))
+    }
+}
+
+impl From<ParseError> for ParseErrors {
+    fn from(error: ParseError) -> Self {
+        Self { errors: vec![error] }
+    }
+}
+
+impl ParseErrors {
+    /// Formats all errors using [`ParseError::format_for_console`].
+    pub fn format_for_console(&self, code_fragments: &[CodeFragment]) -> String {
+        let errors = self.errors.iter().map(|error| error.format_for_console(code_fragments)).collect::<Vec<_>>();
+        let summary = match errors.len() {
+            1 => String::from("could not compile shader due to previous error"),
+            len => format!("could not compile shader due to {} previous errors", len),
+        };
+        format!("{}\n\n{}", errors.join("\n\n"), summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_shader_ast::ShaderAstGenerator;
+
+    fn generate(code_fragments: &[CodeFragment]) -> ParseErrors {
+        ShaderAstGenerator::new().generate_shader_ast(code_fragments).unwrap_err()
+    }
+
+    fn main_fragment(code: &'static str) -> CodeFragment {
+        CodeFragment::Static { filename: "src/main.rs", line: 10, col: 20, code }
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let code_fragments = [main_fragment(
+            "
+            fn vertex() -> vec4 {
+                let x: float = 1;
+                return vec4(x);
+            }
+            fn pixel() -> vec4 {
+                return vec4(0.);
+            }",
+        )];
+        assert_eq!(
+            generate(&code_fragments).format_for_console(&code_fragments),
+            "error[E0005]: can't match expected type `float` with actual type `int`
+  --> src/main.rs:12:32
+   |
+12 |                 let x: float = 1;
+   |                                ^
+   |                        ----- expected due to this type
+   = help: use a float literal instead: `1.0`
+
+could not compile shader due to previous error"
+        );
+    }
+
+    #[test]
+    fn multiple_errors() {
+        let code_fragments = [
+            CodeFragment::Dynamic { name: "std".to_string(), code: "const ONE: int = 1;".to_string() },
+            main_fragment(
+                "
+            fn vertex() -> vec4 {
+                for i from 0 to 10 {
+                    i = 2;
+                }
+                return vec4(0.);
+            }
+            fn pixel() -> vec4 {
+                return foo(ONE);
+            }
+            fn foo(a: float) -> vec4 {
+                return vec4(a);
+            }",
+            ),
+        ];
+        let errors = generate(&code_fragments);
+        assert_eq!(
+            errors.errors.iter().map(|err| err.code).collect::<Vec<_>>(),
+            [ErrorCode::InvalidAssignment, ErrorCode::TypeMismatch]
+        );
+        assert_eq!(
+            errors.format_for_console(&code_fragments),
+            "error[E0006]: expression is not a valid left hand side
+  --> src/main.rs:13:21
+   |
+13 |                     i = 2;
+   |                     ^
+  --> src/main.rs:12:17
+   |
+12 |                 for i from 0 to 10 {
+   |                 -------------------- `i` declared here
+   = help: loop variables can't be assigned to; copy it into a `let` variable first
+
+error[E0005]: wrong type for argument 1 in call to function `foo`: expected `float`, got `int`
+  --> src/main.rs:18:28
+   |
+18 |                 return foo(ONE);
+   |                            ^^^
+  --> src/main.rs:20:20
+   |
+20 |             fn foo(a: float) -> vec4 {
+   |                    -------- parameter declared here
+
+could not compile shader due to 2 previous errors"
+        );
+    }
+
+    #[test]
+    fn already_defined_in_other_code_fragment() {
+        let code_fragments = [
+            CodeFragment::Dynamic { name: "std".to_string(), code: "\nuniform zoom: float;".to_string() },
+            main_fragment("instance zoom: float;"),
+        ];
+        let errors = generate(&code_fragments);
+        assert_eq!(errors.errors.len(), 1);
+        assert_eq!(errors.errors[0].code, ErrorCode::AlreadyDefined);
+        assert_eq!(
+            errors.format_for_console(&code_fragments),
+            "error[E0004]: `zoom` is already defined in this scope
+  --> src/main.rs:10:29
+   |
+10 | instance zoom: float;
+   |          ^^^^^^^^^^^^
+  --> std:1:9
+   |
+ 1 | uniform zoom: float;
+   |         ------------ previous definition of `zoom` here
+
+could not compile shader due to previous error"
+        );
+    }
+
+    #[test]
+    fn lex_error_in_every_code_fragment() {
+        let code_fragments = [
+            CodeFragment::Dynamic { name: "std".to_string(), code: "const A: float = 1.0 $ 2.0;".to_string() },
+            main_fragment("const B: float = 1.0 @ 2.0;"),
+        ];
+        let errors = generate(&code_fragments);
+        assert_eq!(errors.errors.iter().map(|err| err.code).collect::<Vec<_>>(), [ErrorCode::Lex, ErrorCode::Lex]);
+    }
+
+    #[test]
+    fn unreachable_pattern() {
+        let code_fragments = [main_fragment(
+            "
+            fn vertex() -> vec4 {
+                match 1 {
+                    0 | 1 => {}
+                    2 | 1 => {}
+                }
+                return vec4(0.);
+            }
+            fn pixel() -> vec4 {
+                return vec4(0.);
+            }",
+        )];
+        assert_eq!(
+            generate(&code_fragments).format_for_console(&code_fragments),
+            "error[E0008]: unreachable pattern
+  --> src/main.rs:14:25
+   |
+14 |                     2 | 1 => {}
+   |                         ^
+  --> src/main.rs:13:25
+   |
+13 |                     0 | 1 => {}
+   |                         - matches any value that this pattern would match
+
+could not compile shader due to previous error"
+        );
+    }
+
+    #[test]
+    fn ambiguous_glob_import() {
+        let code_fragments = [main_fragment(
+            "
+            mod a {
+                fn f() -> float { return 1.; }
+            }
+            mod b {
+                fn f() -> float { return 2.; }
+            }
+            use a::*;
+            use b::*;
+            fn vertex() -> vec4 {
+                return vec4(f());
+            }
+            fn pixel() -> vec4 {
+                return vec4(a::g());
+            }",
+        )];
+        assert_eq!(
+            generate(&code_fragments).format_for_console(&code_fragments),
+            "error[E0003]: `f` is ambiguous
+  --> src/main.rs:20:29
+   |
+20 |                 return vec4(f());
+   |                             ^^^
+  --> src/main.rs:17:17
+   |
+17 |             use a::*;
+   |                 ---- `f` could refer to `a::f`
+  --> src/main.rs:18:17
+   |
+18 |             use b::*;
+   |                 ---- `f` could also refer to `b::f`
+   = help: use a full path like `b::f`
+
+error[E0003]: cannot find `g` in module `a`
+  --> src/main.rs:23:29
+   |
+23 |                 return vec4(a::g());
+   |                             ^^^^^^
+
+could not compile shader due to 2 previous errors"
+        );
+    }
+
+    #[test]
+    fn reserved_prefix() {
+        let code_fragments = [main_fragment("fn mpsc_m1a1b() -> float { return 1.; }")];
+        assert_eq!(
+            generate(&code_fragments).format_for_console(&code_fragments),
+            "error[E0001]: `mpsc_m1a1b` starts with `mpsc_`, which is reserved
+  --> src/main.rs:10:23
+   |
+10 | fn mpsc_m1a1b() -> float { return 1.; }
+   |    ^^^^^^^^^^
+
+could not compile shader due to previous error"
+        );
     }
 }
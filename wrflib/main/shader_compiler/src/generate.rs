@@ -49,6 +49,36 @@ pub(crate) trait BackendWriter {
     fn needs_unpack_for_matrix_multiplication(&self) -> bool;
 
     fn use_cons_fn(&self, what: &str) -> bool;
+
+    /// Writes the declaration of a local variable, in a `let` statement or `for` loop.
+    fn write_local_var_decl(&self, string: &mut String, ident: Ident, ty: &Ty) {
+        self.write_var_decl(string, false, false, ident, ty);
+    }
+
+    /// Whether conditional expressions need to be written as `select(false_value, true_value, condition)`
+    /// instead of `condition ? true_value : false_value`.
+    fn needs_select_for_cond_expr(&self) -> bool {
+        false
+    }
+
+    /// Whether `inout` parameters are pointers, which means that we have to take the address of the
+    /// corresponding arguments.
+    fn needs_address_of_for_inout_args(&self) -> bool {
+        false
+    }
+
+    /// For integer operators that the backend doesn't support for the given argument types, writes the name of
+    /// a function that implements `op` (see [`int_op_name`]), and returns `true`. The function gets called with
+    /// the operands as arguments. Returns `false` if the operator can be written as is.
+    fn write_int_op_fn_ident(&self, _string: &mut String, _op: &'static str, _arg_tys: &[Ty]) -> bool {
+        false
+    }
+
+    /// The maximum number of iterations of `while` and `loop` statements, for backends that only support loops
+    /// with a fixed number of iterations. `None` if loops can run indefinitely.
+    fn max_loop_iterations(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub(crate) struct BlockGenerator<'a> {
@@ -86,7 +116,10 @@ impl<'a> BlockGenerator<'a> {
                 self.generate_if_stmt(span, expr, block_if_true, block_if_false)
             }
             Stmt::Let { span, ref ty, ident, ref ty_expr, ref expr } => self.generate_let_stmt(span, ty, ident, ty_expr, expr),
+            Stmt::Loop { span, ref block } => self.generate_loop_stmt(span, None, block),
+            Stmt::Match { span, ref expr, ref arms } => self.generate_match_stmt(span, expr, arms),
             Stmt::Return { span, ref expr } => self.generate_return_stmt(span, expr),
+            Stmt::While { span, ref expr, ref block } => self.generate_loop_stmt(span, Some(expr), block),
             Stmt::Expr { span, ref expr } => self.generate_expr_stmt(span, expr),
         }
     }
@@ -117,25 +150,21 @@ impl<'a> BlockGenerator<'a> {
         } else {
             -1
         };
-        write!(
-            self.string,
-            "for (int {0} = {1}; {0} {2} {3}; {0} {4} {5}) ",
-            ident,
-            if from <= to { from } else { from - 1 },
-            if from <= to { "<" } else { ">=" },
-            to,
-            if step > 0 { "+=" } else { "-=" },
-            step.abs()
-        )
-        .unwrap();
+        write!(self.string, "for (").unwrap();
+        self.backend_writer.write_local_var_decl(self.string, ident, &Ty::Int);
+        write!(self.string, " = {}; ", if from <= to { from } else { from - 1 }).unwrap();
+        self.backend_writer.write_ident(self.string, ident);
+        write!(self.string, " {} {}; ", if from <= to { "<" } else { ">=" }, to).unwrap();
+        self.backend_writer.write_ident(self.string, ident);
+        write!(self.string, " {} {}) ", if step > 0 { "+=" } else { "-=" }, step.abs()).unwrap();
         self.generate_block(block);
         writeln!(self.string).unwrap();
     }
 
     fn generate_if_stmt(&mut self, _span: Span, expr: &Expr, block_if_true: &Block, block_if_false: &Option<Box<Block>>) {
-        write!(self.string, "if").unwrap();
+        write!(self.string, "if (").unwrap();
         self.generate_expr(expr);
-        write!(self.string, " ").unwrap();
+        write!(self.string, ") ").unwrap();
         self.generate_block(block_if_true);
         if let Some(block_if_false) = block_if_false {
             write!(self.string, "else").unwrap();
@@ -152,7 +181,7 @@ impl<'a> BlockGenerator<'a> {
         _ty_expr: &Option<TyExpr>,
         expr: &Option<Expr>,
     ) {
-        self.write_var_decl(false, false, ident, ty.borrow().as_ref().unwrap());
+        self.backend_writer.write_local_var_decl(self.string, ident, ty.borrow().as_ref().unwrap());
         if let Some(expr) = expr {
             write!(self.string, " = ").unwrap();
             self.generate_expr(expr);
@@ -160,6 +189,84 @@ impl<'a> BlockGenerator<'a> {
         writeln!(self.string, ";").unwrap();
     }
 
+    /// Generates `while` loops, and `loop` loops if `expr` is `None`.
+    fn generate_loop_stmt(&mut self, _span: Span, expr: Option<&Expr>, block: &Block) {
+        if let Some(max_loop_iterations) = self.backend_writer.max_loop_iterations() {
+            // Use a `for` loop with a fixed number of iterations, and check the condition at the top of the body.
+            let counter_ident = Ident::new(format!("mpsc_loop_{}", self.indent_level));
+            write!(self.string, "for (").unwrap();
+            self.backend_writer.write_local_var_decl(self.string, counter_ident, &Ty::Int);
+            write!(self.string, " = 0; ").unwrap();
+            self.backend_writer.write_ident(self.string, counter_ident);
+            write!(self.string, " < {}; ", max_loop_iterations).unwrap();
+            self.backend_writer.write_ident(self.string, counter_ident);
+            writeln!(self.string, " += 1) {{").unwrap();
+            self.indent_level += 1;
+            if let Some(expr) = expr {
+                self.write_indent();
+                write!(self.string, "if (!").unwrap();
+                self.generate_parenthesized_expr(expr);
+                writeln!(self.string, ") {{ break; }}").unwrap();
+            }
+            for stmt in &block.stmts {
+                self.generate_stmt(stmt);
+            }
+            self.indent_level -= 1;
+            self.write_indent();
+            writeln!(self.string, "}}").unwrap();
+            return;
+        }
+        write!(self.string, "while ").unwrap();
+        match expr {
+            Some(expr) => self.generate_parenthesized_expr(expr),
+            None => write!(self.string, "(true)").unwrap(),
+        }
+        write!(self.string, " ").unwrap();
+        self.generate_block(block);
+        writeln!(self.string).unwrap();
+    }
+
+    /// Generates a chain of `if` statements, since `switch` statements aren't supported everywhere, and
+    /// fall through to the next case.
+    fn generate_match_stmt(&mut self, _span: Span, expr: &Expr, arms: &[MatchArm]) {
+        let match_ident = Ident::new(format!("mpsc_match_{}", self.indent_level));
+        writeln!(self.string, "{{").unwrap();
+        self.indent_level += 1;
+        self.write_indent();
+        self.backend_writer.write_local_var_decl(self.string, match_ident, &Ty::Int);
+        write!(self.string, " = ").unwrap();
+        self.generate_expr(expr);
+        writeln!(self.string, ";").unwrap();
+        self.write_indent();
+        for (arm_index, arm) in arms.iter().enumerate() {
+            if arm_index > 0 {
+                write!(self.string, " else ").unwrap();
+            }
+            if arm.patterns.iter().any(|pattern| matches!(pattern, Pattern::Wildcard { .. })) {
+                // Later arms are unreachable, so this is the last one.
+                self.generate_block(&arm.block);
+                break;
+            }
+            write!(self.string, "if (").unwrap();
+            for (pattern_index, pattern) in arm.patterns.iter().enumerate() {
+                if pattern_index > 0 {
+                    write!(self.string, " || ").unwrap();
+                }
+                if let Pattern::Int { val, .. } = pattern {
+                    write!(self.string, "(").unwrap();
+                    self.backend_writer.write_ident(self.string, match_ident);
+                    write!(self.string, " == {})", val).unwrap();
+                }
+            }
+            write!(self.string, ") ").unwrap();
+            self.generate_block(&arm.block);
+        }
+        writeln!(self.string).unwrap();
+        self.indent_level -= 1;
+        self.write_indent();
+        writeln!(self.string, "}}").unwrap();
+    }
+
     fn generate_return_stmt(&mut self, _span: Span, expr: &Option<Expr>) {
         write!(self.string, "return").unwrap();
         if let Some(expr) = expr {
@@ -170,7 +277,8 @@ impl<'a> BlockGenerator<'a> {
     }
 
     fn generate_expr_stmt(&mut self, _span: Span, expr: &Expr) {
-        self.generate_expr(expr);
+        ExprGenerator { shader: self.shader, decl: Some(self.decl), backend_writer: self.backend_writer, string: self.string }
+            .generate_stmt_expr(expr);
         writeln!(self.string, ";").unwrap();
     }
 
@@ -186,15 +294,24 @@ impl<'a> BlockGenerator<'a> {
         .generate_expr(expr)
     }
 
+    /// Writes an expression in parentheses, unless it already has them.
+    fn generate_parenthesized_expr(&mut self, expr: &Expr) {
+        let is_parenthesized = !matches!(*expr.const_val.borrow(), Some(Some(_)))
+            && matches!(expr.kind, ExprKind::Bin { op, .. } if !is_assign_op(op));
+        if !is_parenthesized {
+            write!(self.string, "(").unwrap();
+        }
+        self.generate_expr(expr);
+        if !is_parenthesized {
+            write!(self.string, ")").unwrap();
+        }
+    }
+
     fn write_indent(&mut self) {
         for _ in 0..self.indent_level {
             write!(self.string, "    ").unwrap();
         }
     }
-
-    fn write_var_decl(&mut self, is_inout: bool, is_packed: bool, ident: Ident, ty: &Ty) {
-        self.backend_writer.write_var_decl(self.string, is_inout, is_packed, ident, ty);
-    }
 }
 
 pub(crate) struct ExprGenerator<'a> {
@@ -231,7 +348,7 @@ impl<'a> ExprGenerator<'a> {
                     self.generate_cond_expr(span, expr, expr_if_true, expr_if_false)
                 }
                 ExprKind::Bin { span, op, ref left_expr, ref right_expr } => {
-                    self.generate_bin_expr(span, op, left_expr, right_expr)
+                    self.generate_bin_expr(span, op, left_expr, right_expr, true)
                 }
                 ExprKind::Un { span, op, ref expr } => self.generate_un_expr(span, op, expr),
                 ExprKind::MethodCall { span, ident, ref arg_exprs } => self.generate_method_call_expr(span, ident, arg_exprs),
@@ -245,7 +362,35 @@ impl<'a> ExprGenerator<'a> {
         }
     }
 
+    /// Like [`ExprGenerator::generate_expr`], but for expressions that are used as statements. We don't wrap
+    /// assignments in parentheses there, since some languages (e.g. WGSL) don't allow them as expressions.
+    pub(crate) fn generate_stmt_expr(&mut self, expr: &Expr) {
+        match expr.kind {
+            ExprKind::Bin { span, op, ref left_expr, ref right_expr } if is_assign_op(op) => match right_expr.kind {
+                // Not every backend supports assignments as expressions (e.g. WGSL), so write chained assignments
+                // like `a = b = c` as separate ones: `b = c; a = b`.
+                ExprKind::Bin { op: right_op, left_expr: ref right_left_expr, .. } if is_assign_op(right_op) => {
+                    self.generate_stmt_expr(right_expr);
+                    write!(self.string, "; ").unwrap();
+                    self.generate_bin_expr(span, op, left_expr, right_left_expr, false)
+                }
+                _ => self.generate_bin_expr(span, op, left_expr, right_expr, false),
+            },
+            _ => self.generate_expr(expr),
+        }
+    }
+
     fn generate_cond_expr(&mut self, _span: Span, expr: &Expr, expr_if_true: &Expr, expr_if_false: &Expr) {
+        if self.backend_writer.needs_select_for_cond_expr() {
+            write!(self.string, "select(").unwrap();
+            self.generate_expr(expr_if_false);
+            write!(self.string, ", ").unwrap();
+            self.generate_expr(expr_if_true);
+            write!(self.string, ", ").unwrap();
+            self.generate_expr(expr);
+            write!(self.string, ")").unwrap();
+            return;
+        }
         write!(self.string, "(").unwrap();
         self.generate_expr(expr);
         write!(self.string, " ? ").unwrap();
@@ -255,7 +400,7 @@ impl<'a> ExprGenerator<'a> {
         write!(self.string, ")").unwrap();
     }
 
-    fn generate_bin_expr(&mut self, _span: Span, op: BinOp, left_expr: &Expr, right_expr: &Expr) {
+    fn generate_bin_expr(&mut self, _span: Span, op: BinOp, left_expr: &Expr, right_expr: &Expr, parenthesize: bool) {
         // if left_expr or right_expr is a matrix, HLSL needs to use mul()
         let left_is_mat = match left_expr.ty.borrow().as_ref().unwrap() {
             Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => true,
@@ -327,15 +472,43 @@ impl<'a> ExprGenerator<'a> {
             }
         }
 
-        write!(self.string, "(").unwrap();
+        if op.is_int_only() {
+            let arg_tys = [left_expr.ty.borrow().clone().unwrap(), right_expr.ty.borrow().clone().unwrap()];
+            if self.backend_writer.write_int_op_fn_ident(self.string, int_op_name(op), &arg_tys) {
+                write!(self.string, "(").unwrap();
+                self.generate_expr(left_expr);
+                write!(self.string, ", ").unwrap();
+                self.generate_expr(right_expr);
+                write!(self.string, ")").unwrap();
+                return;
+            }
+        }
+
+        if parenthesize {
+            write!(self.string, "(").unwrap();
+        }
         self.generate_expr(left_expr);
         write!(self.string, " {} ", op).unwrap();
         self.generate_expr(right_expr);
-        write!(self.string, ")").unwrap();
+        if parenthesize {
+            write!(self.string, ")").unwrap();
+        }
     }
 
     fn generate_un_expr(&mut self, _span: Span, op: UnOp, expr: &Expr) {
-        write!(self.string, "{}", op).unwrap();
+        let ty = expr.ty.borrow().clone().unwrap();
+        match (op, &ty) {
+            (UnOp::Not, Ty::Int | Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4) => {
+                if self.backend_writer.write_int_op_fn_ident(self.string, "bitnot", &[ty]) {
+                    write!(self.string, "(").unwrap();
+                    self.generate_expr(expr);
+                    write!(self.string, ")").unwrap();
+                    return;
+                }
+                write!(self.string, "~").unwrap();
+            }
+            _ => write!(self.string, "{}", op).unwrap(),
+        }
         self.generate_expr(expr);
     }
 
@@ -367,9 +540,18 @@ impl<'a> ExprGenerator<'a> {
         self.backend_writer.write_call_ident(self.string, ident, arg_exprs);
 
         write!(self.string, "(").unwrap();
+        let inout_params = if self.backend_writer.needs_address_of_for_inout_args() {
+            self.shader.find_fn_decl(ident_path).map(|decl| decl.params.iter().map(|param| param.is_inout).collect())
+        } else {
+            None
+        }
+        .unwrap_or_else(Vec::new);
         let mut sep = "";
-        for arg_expr in arg_exprs {
+        for (index, arg_expr) in arg_exprs.iter().enumerate() {
             write!(self.string, "{}", sep).unwrap();
+            if inout_params.get(index) == Some(&true) {
+                write!(self.string, "&").unwrap();
+            }
 
             self.generate_expr(arg_expr);
 
@@ -417,3 +599,37 @@ impl<'a> ExprGenerator<'a> {
         self.backend_writer.write_ty_lit(self.string, ty_lit);
     }
 }
+
+fn is_assign_op(op: BinOp) -> bool {
+    matches!(op, BinOp::Assign | BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign)
+}
+
+/// The name of an integer operator, as passed to [`BackendWriter::write_int_op_fn_ident`]. Bitwise not is
+/// called `bitnot`.
+pub(crate) fn int_op_name(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Rem => "rem",
+        BinOp::BitOr => "bitor",
+        BinOp::BitXor => "bitxor",
+        BinOp::BitAnd => "bitand",
+        BinOp::Shl => "shl",
+        BinOp::Shr => "shr",
+        _ => panic!("`{}` is not an integer operator", op),
+    }
+}
+
+/// Writes the name of an item in a module or a method, like `df::circle` or `Df::circle`, as `mpsc_m2df6circle` or
+/// `mpsc_m2Df6circle`, since no platform allows `::` in identifiers. Every segment is prefixed with its length, so
+/// that different paths can't end up with the same name (like `a::b_c` and `a_b::c` would when just replacing `::`
+/// with `_`). User names can't start with `mpsc_`, so they can't clash with these either. Returns false for other
+/// identifiers, so that [`BackendWriter::write_ident`] can write those.
+pub(crate) fn write_module_item_ident(string: &mut String, ident_string: &str) -> bool {
+    if !ident_string.contains("::") {
+        return false;
+    }
+    write!(string, "mpsc_m").unwrap();
+    for segment in ident_string.split("::") {
+        write!(string, "{}{}", segment.len(), segment).unwrap();
+    }
+    true
+}
@@ -7,34 +7,34 @@
 use {
     crate::{
         env::VarKind,
-        generate::{BackendWriter, BlockGenerator, ExprGenerator},
+        generate::{write_module_item_ident, BackendWriter, BlockGenerator, ExprGenerator},
         ident::{Ident, IdentPath},
         shaderast::*,
         span::Span,
         swizzle::Swizzle,
         ty::{Ty, TyLit},
     },
-    std::cell::Cell,
-    std::collections::HashSet,
+    std::cell::{Cell, RefCell},
+    std::collections::{BTreeSet, HashSet},
     std::fmt::Write,
 };
 
 pub fn generate_vertex_shader(shader: &ShaderAst) -> String {
     let mut string = String::new();
-    ShaderGenerator { shader, string: &mut string, backend_writer: &GlslBackendWriter() }.generate_vertex_shader();
+    ShaderGenerator { shader, string: &mut string, backend_writer: &GlslBackendWriter::default() }.generate_vertex_shader();
     string
 }
 
 pub fn generate_fragment_shader(shader: &ShaderAst) -> String {
     let mut string = String::new();
-    ShaderGenerator { shader, string: &mut string, backend_writer: &GlslBackendWriter() }.generate_fragment_shader();
+    ShaderGenerator { shader, string: &mut string, backend_writer: &GlslBackendWriter::default() }.generate_fragment_shader();
     string
 }
 
 struct ShaderGenerator<'a> {
     shader: &'a ShaderAst,
     string: &'a mut String,
-    backend_writer: &'a dyn BackendWriter,
+    backend_writer: &'a GlslBackendWriter,
 }
 
 impl<'a, 'b> ShaderGenerator<'a> {
@@ -93,10 +93,12 @@ impl<'a, 'b> ShaderGenerator<'a> {
             }
         }
         let vertex_decl = self.shader.find_fn_decl(IdentPath::from_str("vertex")).unwrap();
+        let int_op_fns_position = self.string.len();
         for &(ty_lit, ref param_tys) in vertex_decl.cons_fn_deps.borrow_mut().as_ref().unwrap() {
             self.generate_cons_fn(ty_lit, param_tys);
         }
         self.generate_fn_decl(vertex_decl, self.backend_writer);
+        self.string.insert_str(int_op_fns_position, &self.backend_writer.generate_int_op_fns());
         writeln!(self.string, "void main() {{").unwrap();
         let mut geometry_unpacker = VarUnpacker::new("mpsc_packed_geometry", packed_geometries_size, self.string);
         for decl in &self.shader.decls {
@@ -163,10 +165,12 @@ impl<'a, 'b> ShaderGenerator<'a> {
         }
 
         let pixel_decl = self.shader.find_fn_decl(IdentPath::from_str("pixel")).unwrap();
+        let int_op_fns_position = self.string.len();
         for &(ty_lit, ref param_tys) in pixel_decl.cons_fn_deps.borrow_mut().as_ref().unwrap() {
             self.generate_cons_fn(ty_lit, param_tys);
         }
         self.generate_fn_decl(pixel_decl, self.backend_writer);
+        self.string.insert_str(int_op_fns_position, &self.backend_writer.generate_int_op_fns());
         writeln!(self.string, "void main() {{").unwrap();
         let mut varying_unpacker = VarUnpacker::new("mpsc_packed_varying", packed_varyings_size, self.string);
         for decl in &self.shader.decls {
@@ -195,7 +199,7 @@ impl<'a, 'b> ShaderGenerator<'a> {
     ) {
         for decl in &self.shader.decls {
             match decl {
-                Decl::Struct(decl) => self.generate_struct_decl(decl),
+                Decl::Struct(decl) if decl.is_used.get().unwrap() => self.generate_struct_decl(decl),
                 _ => {}
             }
         }
@@ -214,9 +218,20 @@ impl<'a, 'b> ShaderGenerator<'a> {
             }
         }
 
+        let mut has_texture_3d = false;
         for decl in &self.shader.decls {
             match decl {
-                Decl::Texture(decl) => self.generate_texture_decl(decl),
+                Decl::Texture(decl) => {
+                    let is_texture_3d = *decl.ty_expr.ty.borrow().as_ref().unwrap() == Ty::Texture3D;
+                    if is_texture_3d && !has_texture_3d {
+                        self.generate_sample_3d_fn();
+                        has_texture_3d = true;
+                    }
+                    self.generate_texture_decl(decl);
+                    if is_texture_3d {
+                        self.generate_texture_3d_decl(decl);
+                    }
+                }
                 _ => {}
             }
         }
@@ -233,7 +248,9 @@ impl<'a, 'b> ShaderGenerator<'a> {
     }
 
     fn generate_struct_decl(&mut self, decl: &StructDecl) {
-        write!(self.string, "struct {} {{", decl.ident).unwrap();
+        write!(self.string, "struct ").unwrap();
+        self.backend_writer.write_ident(self.string, decl.ident);
+        write!(self.string, " {{").unwrap();
         if !decl.fields.is_empty() {
             writeln!(self.string).unwrap();
             for field in &decl.fields {
@@ -265,6 +282,36 @@ impl<'a, 'b> ShaderGenerator<'a> {
         writeln!(self.string, ";").unwrap();
     }
 
+    /// GLSL ES 1.0 doesn't have 3D textures, so they get uploaded as a 2D texture with all slices stacked on top
+    /// of each other. `size` is the height of a slice and the number of slices. We interpolate between slices
+    /// ourselves, and clamp to the edges of a slice so that we don't bleed into the next one.
+    fn generate_sample_3d_fn(&mut self) {
+        writeln!(
+            self.string,
+            "vec4 mpsc_sample3d(sampler2D tex, vec2 size, vec3 pos) {{
+    float z = clamp(pos.z * size.y - 0.5, 0.0, size.y - 1.0);
+    float z0 = floor(z);
+    float z1 = min(z0 + 1.0, size.y - 1.0);
+    float y = clamp(pos.y * size.x, 0.5, size.x - 0.5) / size.x;
+    return mix(texture2D(tex, vec2(pos.x, (z0 + y) / size.y)), texture2D(tex, vec2(pos.x, (z1 + y) / size.y)), z - z0);
+}}"
+        )
+        .unwrap();
+    }
+
+    /// The size uniform and `sample3d` function for a `texture3D` declaration, see
+    /// [`ShaderGenerator::generate_sample_3d_fn`].
+    fn generate_texture_3d_decl(&mut self, decl: &TextureDecl) {
+        let size_ident = texture_3d_size_uniform_name(&decl.ident.to_string());
+        writeln!(self.string, "uniform vec2 {};", size_ident).unwrap();
+        writeln!(
+            self.string,
+            "vec4 mpsc_sample3d_{}(sampler2D tex, vec3 pos) {{ return mpsc_sample3d(tex, {}, pos); }}",
+            decl.ident, size_ident
+        )
+        .unwrap();
+    }
+
     fn compute_packed_geometries_size(&self) -> usize {
         let mut packed_attributes_size = 0;
         for decl in &self.shader.decls {
@@ -628,7 +675,71 @@ impl<'a> VarUnpacker<'a> {
     }
 }
 
-struct GlslBackendWriter();
+#[derive(Default)]
+struct GlslBackendWriter {
+    /// Functions that emulate integer operators, by operator name and argument types. See
+    /// [`GlslBackendWriter::generate_int_op_fns`].
+    int_op_fns: RefCell<BTreeSet<(&'static str, Vec<Ty>)>>,
+}
+
+impl GlslBackendWriter {
+    /// GLSL ES 1.0 has no bitwise operators and no `%` for integers, so we emulate those with functions. Integers
+    /// are often stored as floats on the GPU, so these are only exact for values in `-2^23..2^23`.
+    fn generate_int_op_fns(&self) -> String {
+        let mut string = String::new();
+        let int_op_fns = self.int_op_fns.borrow();
+        // Vector functions call the scalar ones, so generate those first.
+        let (scalar_fns, vector_fns): (Vec<_>, Vec<_>) =
+            int_op_fns.iter().partition(|(_, arg_tys)| arg_tys.iter().all(|arg_ty| *arg_ty == Ty::Int));
+        for (op, arg_tys) in scalar_fns.into_iter().chain(vector_fns) {
+            let return_ty = &arg_tys[0];
+            write!(string, "{} {}(", return_ty, int_op_fn_name(op, arg_tys)).unwrap();
+            let params = arg_tys.iter().enumerate().map(|(index, arg_ty)| format!("{} x{}", arg_ty, index)).collect::<Vec<_>>();
+            writeln!(string, "{}) {{", params.join(", ")).unwrap();
+            match (*op, return_ty) {
+                ("bitnot", _) => writeln!(string, "    return -x0 - 1;").unwrap(),
+                ("rem", Ty::Int) => writeln!(string, "    return x0 - x1 * (x0 / x1);").unwrap(),
+                ("shl", Ty::Int) => writeln!(string, "    return int(float(x0) * exp2(float(x1)));").unwrap(),
+                ("shr", Ty::Int) => writeln!(string, "    return int(floor(float(x0) / exp2(float(x1))));").unwrap(),
+                (_, Ty::Int) => {
+                    let combine_bits = match *op {
+                        "bitand" => "a_bit * b_bit",
+                        "bitor" => "max(a_bit, b_bit)",
+                        "bitxor" => "abs(a_bit - b_bit)",
+                        _ => panic!("unexpected integer operator `{}`", op),
+                    };
+                    // Go through the lowest 24 bits of the two's complement representation one by one.
+                    writeln!(string, "    float a = mod(float(x0), 16777216.0);").unwrap();
+                    writeln!(string, "    float b = mod(float(x1), 16777216.0);").unwrap();
+                    writeln!(string, "    float result = 0.0;").unwrap();
+                    writeln!(string, "    float bit = 1.0;").unwrap();
+                    writeln!(string, "    for (int i = 0; i < 24; i += 1) {{").unwrap();
+                    writeln!(string, "        float a_bit = mod(a, 2.0);").unwrap();
+                    writeln!(string, "        float b_bit = mod(b, 2.0);").unwrap();
+                    writeln!(string, "        result += bit * {};", combine_bits).unwrap();
+                    writeln!(string, "        a = floor(a / 2.0);").unwrap();
+                    writeln!(string, "        b = floor(b / 2.0);").unwrap();
+                    writeln!(string, "        bit *= 2.0;").unwrap();
+                    writeln!(string, "    }}").unwrap();
+                    writeln!(string, "    return int(result >= 8388608.0 ? result - 16777216.0 : result);").unwrap();
+                }
+                _ => {
+                    let scalar_fn_name = int_op_fn_name(op, &[Ty::Int, Ty::Int]);
+                    let components = ["x", "y", "z", "w"][..return_ty.size()]
+                        .iter()
+                        .map(|component| {
+                            let x1 = if arg_tys[1] == Ty::Int { String::from("x1") } else { format!("x1.{}", component) };
+                            format!("{}(x0.{}, {})", scalar_fn_name, component, x1)
+                        })
+                        .collect::<Vec<_>>();
+                    writeln!(string, "    return {}({});", return_ty, components.join(", ")).unwrap();
+                }
+            }
+            writeln!(string, "}}").unwrap();
+        }
+        string
+    }
+}
 
 impl BackendWriter for GlslBackendWriter {
     fn write_call_expr_hidden_args(&self, _string: &mut String, _ident_path: IdentPath, _shader: &ShaderAst, _sep: &str) {}
@@ -742,8 +853,8 @@ impl BackendWriter for GlslBackendWriter {
                 write!(string, " ").unwrap();
                 self.write_ident(string, ident);
             }
-            Ty::Texture2D => {
-                self.write_ty_lit(string, TyLit::Texture2D);
+            Ty::Texture2D | Ty::TextureCube | Ty::Texture3D => {
+                self.write_ty_lit(string, ty.maybe_ty_lit().unwrap());
                 write!(string, " ").unwrap();
                 self.write_ident(string, ident);
             }
@@ -752,7 +863,8 @@ impl BackendWriter for GlslBackendWriter {
                 write!(string, "[{}]", len).unwrap();
             }
             Ty::Struct { ident: struct_ident } => {
-                write!(string, "{} ", struct_ident).unwrap();
+                self.write_ident(string, struct_ident);
+                write!(string, " ").unwrap();
                 self.write_ident(string, ident);
             }
         }
@@ -779,17 +891,46 @@ impl BackendWriter for GlslBackendWriter {
                 TyLit::Mat3 => "mat3",
                 TyLit::Mat4 => "mat4",
                 TyLit::Texture2D => "sampler2D",
+                TyLit::TextureCube => "samplerCube",
+                // See `ShaderGenerator::generate_sample_3d_fn`.
+                TyLit::Texture3D => "sampler2D",
             }
         )
         .unwrap();
     }
 
-    fn write_call_ident(&self, string: &mut String, ident: Ident, _arg_exprs: &[Expr]) {
+    fn write_call_ident(&self, string: &mut String, ident: Ident, arg_exprs: &[Expr]) {
+        if ident == Ident::new("sample3d") {
+            // Every `texture3D` gets its own function, which passes along its size.
+            match &arg_exprs[0].kind {
+                ExprKind::Var { ident_path, .. } => write!(string, "mpsc_sample3d_{}", ident_path.get_single().unwrap()).unwrap(),
+                _ => panic!("the first argument of `sample3d` must be a texture declaration"),
+            }
+            return;
+        }
         self.write_ident(string, ident);
     }
 
+    fn write_int_op_fn_ident(&self, string: &mut String, op: &'static str, arg_tys: &[Ty]) -> bool {
+        let mut int_op_fns = self.int_op_fns.borrow_mut();
+        if op != "bitnot" && arg_tys[0] != Ty::Int {
+            int_op_fns.insert((op, vec![Ty::Int, Ty::Int]));
+        }
+        int_op_fns.insert((op, arg_tys.to_vec()));
+        write!(string, "{}", int_op_fn_name(op, arg_tys)).unwrap();
+        true
+    }
+
+    fn max_loop_iterations(&self) -> Option<usize> {
+        // GLSL ES 1.0 only guarantees support for `for` loops with constant bounds (see Appendix A of the spec).
+        Some(MAX_LOOP_ITERATIONS)
+    }
+
     fn write_ident(&self, string: &mut String, ident: Ident) {
         ident.with(|ident_string| {
+            if write_module_item_ident(string, ident_string) {
+                return;
+            }
             write!(
                 string,
                 "{}",
@@ -803,3 +944,29 @@ impl BackendWriter for GlslBackendWriter {
         })
     }
 }
+
+/// The name of the sampler uniform for a `texture` declaration, since some names get renamed to avoid clashes
+/// with GLSL keywords.
+pub fn texture_uniform_name(texture_name: &str) -> String {
+    let mut string = String::new();
+    GlslBackendWriter::default().write_ident(&mut string, Ident::new(texture_name));
+    string
+}
+
+/// The name of the `vec2` uniform that has to be set to the height and depth of the texture bound to a
+/// `texture3D` declaration. Its slices are expected to be stacked vertically in a single 2D texture.
+pub fn texture_3d_size_uniform_name(texture_name: &str) -> String {
+    format!("mpsc_texture3d_size_{}", texture_name)
+}
+
+/// The maximum number of iterations of `while` and `loop` statements, since GLSL ES 1.0 doesn't support
+/// unbounded loops. Loops that run longer than this exit as if they hit a `break`.
+const MAX_LOOP_ITERATIONS: usize = 65536;
+
+fn int_op_fn_name(op: &str, arg_tys: &[Ty]) -> String {
+    let mut int_op_fn_name = format!("mpsc_{}", op);
+    for arg_ty in arg_tys {
+        write!(int_op_fn_name, "_{}", arg_ty).unwrap();
+    }
+    int_op_fn_name
+}
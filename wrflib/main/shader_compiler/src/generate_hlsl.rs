@@ -7,7 +7,7 @@
 use {
     crate::{
         env::VarKind,
-        generate::{BackendWriter, BlockGenerator, ExprGenerator},
+        generate::{write_module_item_ident, BackendWriter, BlockGenerator, ExprGenerator},
         ident::{Ident, IdentPath},
         shaderast::*,
         span::Span,
@@ -38,16 +38,11 @@ struct ShaderGenerator<'a> {
 
 impl<'a> ShaderGenerator<'a> {
     fn generate_shader(&mut self) {
-        writeln!(
-            self.string,
-            "SamplerState mpsc_default_texture_sampler{{Filter=MIN_MAX_MIP_LINEAR;AddressU = Wrap;AddressV=Wrap;}};"
-        )
-        .unwrap();
-        writeln!(
-            self.string,
-            "float4 sample2d(Texture2D tex, float2 pos){{return tex.Sample(mpsc_default_texture_sampler,pos);}}"
-        )
-        .unwrap();
+        // Every texture comes with its own sampler, which gets passed along, see `generate_var_expr`.
+        writeln!(self.string, "float4 sample2d(Texture2D tex, SamplerState s, float2 pos){{return tex.Sample(s,pos);}}").unwrap();
+        writeln!(self.string, "float4 sample_cube(TextureCube tex, SamplerState s, float3 dir){{return tex.Sample(s,dir);}}")
+            .unwrap();
+        writeln!(self.string, "float4 sample3d(Texture3D tex, SamplerState s, float3 pos){{return tex.Sample(s,pos);}}").unwrap();
         self.generate_struct_decls();
         self.generate_uniform_structs();
         self.generate_texture_defs();
@@ -72,8 +67,10 @@ impl<'a> ShaderGenerator<'a> {
     fn generate_struct_decls(&mut self) {
         for decl in &self.shader.decls {
             match decl {
-                Decl::Struct(decl) => {
-                    write!(self.string, "struct {} {{", decl.ident).unwrap();
+                Decl::Struct(decl) if decl.is_used.get().unwrap() => {
+                    write!(self.string, "struct ").unwrap();
+                    self.backend_writer.write_ident(self.string, decl.ident);
+                    write!(self.string, " {{").unwrap();
                     if !decl.fields.is_empty() {
                         writeln!(self.string).unwrap();
                         for field in &decl.fields {
@@ -136,10 +133,17 @@ impl<'a> ShaderGenerator<'a> {
         for decl in &self.shader.decls {
             match decl {
                 Decl::Texture(decl) => {
-                    assert_eq!(*decl.ty_expr.ty.borrow().as_ref().unwrap(), Ty::Texture2D);
-                    write!(self.string, "Texture2D ").unwrap();
+                    match decl.ty_expr.ty.borrow().as_ref().unwrap() {
+                        Ty::Texture2D => write!(self.string, "Texture2D ").unwrap(),
+                        Ty::TextureCube => write!(self.string, "TextureCube ").unwrap(),
+                        Ty::Texture3D => write!(self.string, "Texture3D ").unwrap(),
+                        _ => panic!(),
+                    }
                     self.backend_writer.write_ident(self.string, decl.ident);
                     writeln!(self.string, ": register(t{});", index).unwrap();
+                    write!(self.string, "SamplerState ").unwrap();
+                    self.backend_writer.write_ident(self.string, decl.ident);
+                    writeln!(self.string, "_sampler: register(s{});", index).unwrap();
                     index += 1;
                 }
                 _ => {}
@@ -673,6 +677,12 @@ impl BackendWriter for HlslBackendWriter {
         }
 
         self.write_ident(string, ident_path.get_single().expect("unexpected"));
+        if let VarKind::Texture = kind.get().unwrap() {
+            // Textures can only be passed to the `sample*` functions, which also take the sampler.
+            write!(string, ", ").unwrap();
+            self.write_ident(string, ident_path.get_single().expect("unexpected"));
+            write!(string, "_sampler").unwrap();
+        }
     }
 
     fn needs_mul_fn_for_matrix_multiplication(&self) -> bool {
@@ -777,14 +787,14 @@ impl BackendWriter for HlslBackendWriter {
                 write!(string, " ").unwrap();
                 self.write_ident(string, ident);
             }
-            Ty::Texture2D => panic!(), // TODO
+            Ty::Texture2D | Ty::TextureCube | Ty::Texture3D => panic!(), // TODO
             Ty::Array { ref elem_ty, len } => {
                 self.write_var_decl(string, is_inout, is_packed, ident, elem_ty);
                 write!(string, " ").unwrap();
                 write!(string, "[{}]", len).unwrap();
             }
             Ty::Struct { ident: struct_ident } => {
-                write!(string, "{}", struct_ident).unwrap();
+                self.write_ident(string, struct_ident);
                 write!(string, " ").unwrap();
                 self.write_ident(string, ident);
             }
@@ -811,7 +821,7 @@ impl BackendWriter for HlslBackendWriter {
                 TyLit::Mat2 => "float2x2",
                 TyLit::Mat3 => "float3x3",
                 TyLit::Mat4 => "float4x4",
-                TyLit::Texture2D => panic!(), // TODO
+                TyLit::Texture2D | TyLit::TextureCube | TyLit::Texture3D => panic!(), // TODO
             }
         )
         .unwrap();
@@ -841,6 +851,9 @@ impl BackendWriter for HlslBackendWriter {
 
     fn write_ident(&self, string: &mut String, ident: Ident) {
         ident.with(|ident_string| {
+            if write_module_item_ident(string, ident_string) {
+                return;
+            }
             write!(
                 string,
                 "{}",
@@ -7,7 +7,7 @@
 use {
     crate::{
         env::VarKind,
-        generate::{BackendWriter, BlockGenerator, ExprGenerator},
+        generate::{write_module_item_ident, BackendWriter, BlockGenerator, ExprGenerator},
         ident::{Ident, IdentPath},
         shaderast::*,
         span::Span,
@@ -36,12 +36,13 @@ impl<'a> ShaderGenerator<'a> {
     fn generate_shader(&mut self) {
         writeln!(self.string, "#include <metal_stdlib>").unwrap();
         writeln!(self.string, "using namespace metal;").unwrap();
-        writeln!(
-            self.string,
-            "float4 sample2d(texture2d<float> tex, float2 pos){{return \
-             tex.sample(sampler(mag_filter::linear,min_filter::linear),pos);}}"
-        )
-        .unwrap();
+        // Every texture comes with its own sampler, which gets passed along, see `write_var`.
+        writeln!(self.string, "float4 sample2d(texture2d<float> tex, sampler s, float2 pos){{return tex.sample(s,pos);}}")
+            .unwrap();
+        writeln!(self.string, "float4 sample_cube(texturecube<float> tex, sampler s, float3 dir){{return tex.sample(s,dir);}}")
+            .unwrap();
+        writeln!(self.string, "float4 sample3d(texture3d<float> tex, sampler s, float3 pos){{return tex.sample(s,pos);}}")
+            .unwrap();
         self.generate_struct_decls();
         self.generate_uniform_structs();
         self.generate_texture_struct();
@@ -66,8 +67,10 @@ impl<'a> ShaderGenerator<'a> {
     fn generate_struct_decls(&mut self) {
         for decl in &self.shader.decls {
             match decl {
-                Decl::Struct(decl) => {
-                    write!(self.string, "struct {} {{", decl.ident).unwrap();
+                Decl::Struct(decl) if decl.is_used.get().unwrap() => {
+                    write!(self.string, "struct ").unwrap();
+                    self.backend_writer.write_ident(self.string, decl.ident);
+                    write!(self.string, " {{").unwrap();
                     if !decl.fields.is_empty() {
                         writeln!(self.string).unwrap();
                         for field in &decl.fields {
@@ -119,10 +122,17 @@ impl<'a> ShaderGenerator<'a> {
         for decl in &self.shader.decls {
             match decl {
                 Decl::Texture(decl) => {
-                    assert_eq!(*decl.ty_expr.ty.borrow().as_ref().unwrap(), Ty::Texture2D);
-                    write!(self.string, "    texture2d<float> ").unwrap();
+                    match decl.ty_expr.ty.borrow().as_ref().unwrap() {
+                        Ty::Texture2D => write!(self.string, "    texture2d<float> ").unwrap(),
+                        Ty::TextureCube => write!(self.string, "    texturecube<float> ").unwrap(),
+                        Ty::Texture3D => write!(self.string, "    texture3d<float> ").unwrap(),
+                        _ => panic!(),
+                    }
+                    self.backend_writer.write_ident(self.string, decl.ident);
+                    writeln!(self.string, " [[texture({})]];", index).unwrap();
+                    write!(self.string, "    sampler ").unwrap();
                     self.backend_writer.write_ident(self.string, decl.ident);
-                    write!(self.string, " [[texture({})]];", index).unwrap();
+                    writeln!(self.string, "_sampler [[sampler({})]];", index).unwrap();
                     index += 1;
                 }
                 _ => {}
@@ -569,7 +579,16 @@ impl BackendWriter for MetalBackendWriter {
                 )
                 .unwrap();
             }
-            VarKind::Texture => write!(string, "mpsc_textures.").unwrap(),
+            VarKind::Texture => {
+                // Textures can only be passed to the `sample*` functions, which also take the sampler.
+                let ident = ident_path.get_single().expect("unexpected");
+                write!(string, "mpsc_textures.").unwrap();
+                self.write_ident(string, ident);
+                write!(string, ", mpsc_textures.").unwrap();
+                self.write_ident(string, ident);
+                write!(string, "_sampler").unwrap();
+                return;
+            }
             _ => (),
         }
         self.write_ident(string, ident_path.get_single().expect("unexpected"));
@@ -692,13 +711,14 @@ impl BackendWriter for MetalBackendWriter {
                 write!(string, " {}", ref_prefix).unwrap();
                 self.write_ident(string, ident);
             }
-            Ty::Texture2D => panic!(), // TODO
+            Ty::Texture2D | Ty::TextureCube | Ty::Texture3D => panic!(), // TODO
             Ty::Array { ref elem_ty, len } => {
                 self.write_var_decl(string, is_inout, is_packed, ident, elem_ty);
                 write!(string, "[{}]", len).unwrap();
             }
             Ty::Struct { ident: struct_ident } => {
-                write!(string, "{} {}", struct_ident, ref_prefix).unwrap();
+                self.write_ident(string, struct_ident);
+                write!(string, " {}", ref_prefix).unwrap();
                 self.write_ident(string, ident);
             }
         }
@@ -724,7 +744,7 @@ impl BackendWriter for MetalBackendWriter {
                 TyLit::Mat2 => "float2x2",
                 TyLit::Mat3 => "float3x3",
                 TyLit::Mat4 => "float4x4",
-                TyLit::Texture2D => panic!(), // TODO
+                TyLit::Texture2D | TyLit::TextureCube | TyLit::Texture3D => panic!(), // TODO
             }
         )
         .unwrap();
@@ -750,6 +770,9 @@ impl BackendWriter for MetalBackendWriter {
 
     fn write_ident(&self, string: &mut String, ident: Ident) {
         ident.with(|ident_string| {
+            if write_module_item_ident(string, ident_string) {
+                return;
+            }
             // do a remapping
             write!(
                 string,
@@ -9,9 +9,10 @@ use crate::builtin::generate_builtins;
 use crate::builtin::Builtin;
 use crate::code_fragment::CodeFragment;
 use crate::detok::DeTokParserImpl;
-use crate::error::ParseError;
+use crate::error::ParseErrors;
 use crate::ident::Ident;
 use crate::lex::lex;
+use crate::resolve::resolve_shader;
 use crate::shaderast::ShaderAst;
 use crate::span::CodeFragmentId;
 use crate::token::{Token, TokenWithSpan};
@@ -29,19 +30,34 @@ impl ShaderAstGenerator {
     }
 
     /// Generate a complete [`ShaderAst`] from some code fragments.
-    pub fn generate_shader_ast(&self, code_fragments: &[CodeFragment]) -> Result<ShaderAst, ParseError> {
+    ///
+    /// Returns as many errors as we can find: the first one of every code fragment that can't be lexed, the first
+    /// syntax error, or any errors in declarations and in the bodies of functions.
+    pub fn generate_shader_ast(&self, code_fragments: &[CodeFragment]) -> Result<ShaderAst, ParseErrors> {
         let mut tokens: Vec<TokenWithSpan> = vec![];
+        let mut errors = vec![];
         let code_fragments_len = code_fragments.len();
         for (index, code_fragment) in code_fragments.iter().enumerate() {
             for token_result in lex(code_fragment.code().chars(), CodeFragmentId(index)) {
-                let token = token_result?;
-                // Skip intermediate `Eof` tokens, but keep the last one.
-                if token.token != Token::Eof || index == code_fragments_len - 1 {
-                    tokens.push(token);
+                match token_result {
+                    // Skip intermediate `Eof` tokens, but keep the last one.
+                    Ok(token) => {
+                        if token.token != Token::Eof || index == code_fragments_len - 1 {
+                            tokens.push(token);
+                        }
+                    }
+                    Err(err) => {
+                        errors.push(err);
+                        break;
+                    }
                 }
             }
         }
-        let shader_ast = DeTokParserImpl::new(&tokens).parse_shader()?;
+        if !errors.is_empty() {
+            return Err(ParseErrors { errors });
+        }
+        let mut shader_ast = DeTokParserImpl::new(&tokens).parse_shader()?;
+        resolve_shader(&mut shader_ast)?;
         analyse_shader(&self.builtins, &shader_ast)?;
         Ok(shader_ast)
     }
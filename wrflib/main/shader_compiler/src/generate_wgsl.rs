@@ -0,0 +1,1288 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Generates [WGSL](https://www.w3.org/TR/WGSL/), for WebGPU.
+//!
+//! Like [`crate::generate_metal`], this generates a single module, with a vertex entry point called
+//! `mpsc_vertex_main` and a fragment entry point called `mpsc_fragment_main`. Resources are laid out as follows:
+//! * Geometries and instances get packed into `vec4<f32>` vertex attributes in declaration order, just like in
+//!   [`crate::generate_glsl`]. The geometry attributes come first, followed by the instance attributes.
+//! * Uniform blocks are bound in group 0 as `array<vec4<f32>, N>`: `pass` at binding 0, `view` at binding 1,
+//!   `draw` at binding 2, and all other uniforms at binding 3. Uniforms are tightly packed floats, just like
+//!   on the other platforms, so the buffers have to be padded to a multiple of 16 bytes.
+//! * Textures are bound in group 1, with a linear sampler at binding 0 and the textures at binding 1 and up.
+
+use {
+    crate::{
+        env::VarKind,
+        generate::{write_module_item_ident, BackendWriter, BlockGenerator, ExprGenerator},
+        ident::{Ident, IdentPath},
+        shaderast::*,
+        span::Span,
+        swizzle::Swizzle,
+        ty::{Ty, TyLit},
+    },
+    std::{
+        cell::{Cell, RefCell},
+        collections::{BTreeMap, BTreeSet, HashSet},
+        fmt::Write,
+    },
+};
+
+pub fn generate_shader(shader: &ShaderAst) -> String {
+    let mut string = String::new();
+    ShaderGenerator { shader, string: &mut string, backend_writer: &WgslBackendWriter::default() }.generate_shader();
+    string
+}
+
+struct ShaderGenerator<'a> {
+    shader: &'a ShaderAst,
+    string: &'a mut String,
+    backend_writer: &'a WgslBackendWriter,
+}
+
+impl<'a> ShaderGenerator<'a> {
+    fn generate_shader(&mut self) {
+        self.generate_struct_decls();
+        self.generate_uniform_decls();
+        self.generate_texture_decls();
+        self.generate_private_var_decls();
+        self.generate_varying_struct();
+        self.generate_const_decls();
+        let vertex_decl = self.shader.find_fn_decl(IdentPath::from_str("vertex")).unwrap();
+        let fragment_decl = self.shader.find_fn_decl(IdentPath::from_str("pixel")).unwrap();
+        for &(ty_lit, ref param_tys) in
+            vertex_decl.cons_fn_deps.borrow().as_ref().unwrap().union(fragment_decl.cons_fn_deps.borrow().as_ref().unwrap())
+        {
+            self.generate_cons_fn(ty_lit, param_tys);
+        }
+        let mut visited = HashSet::new();
+        self.generate_fn_decl(vertex_decl, &mut visited);
+        self.generate_fn_decl(fragment_decl, &mut visited);
+        self.generate_vertex_main();
+        self.generate_fragment_main();
+        // The builtin helpers only get known while generating function bodies, so write them last. The order
+        // of declarations doesn't matter in WGSL.
+        self.generate_builtin_helper_fns();
+    }
+
+    fn generate_struct_decls(&mut self) {
+        for decl in &self.shader.decls {
+            if let Decl::Struct(decl) = decl {
+                if !decl.is_used.get().unwrap() {
+                    continue;
+                }
+                write!(self.string, "struct ").unwrap();
+                self.backend_writer.write_ident(self.string, decl.ident);
+                writeln!(self.string, " {{").unwrap();
+                for field in &decl.fields {
+                    // Field expressions don't remap identifiers, so don't do that here either.
+                    write!(self.string, "    {}: ", field.ident).unwrap();
+                    self.backend_writer.write_ty(self.string, field.ty_expr.ty.borrow().as_ref().unwrap());
+                    writeln!(self.string, ",").unwrap();
+                }
+                writeln!(self.string, "}}").unwrap();
+            }
+        }
+    }
+
+    fn generate_uniform_decls(&mut self) {
+        let mut uniform_block_sizes = BTreeMap::new();
+        for decl in &self.shader.decls {
+            if let Decl::Uniform(decl) = decl {
+                *uniform_block_sizes.entry(decl.block_ident.unwrap_or(Ident::new("default"))).or_insert(0) +=
+                    decl.ty_expr.ty.borrow().as_ref().unwrap().size();
+            }
+        }
+        for (ident, size) in uniform_block_sizes {
+            writeln!(
+                self.string,
+                "@group(0) @binding({}) var<uniform> mpsc_{}_uniforms: array<vec4<f32>, {}>;",
+                uniform_block_binding(ident),
+                ident,
+                size.div_ceil(4)
+            )
+            .unwrap();
+        }
+    }
+
+    fn generate_texture_decls(&mut self) {
+        let mut binding = 1;
+        for decl in &self.shader.decls {
+            if let Decl::Texture(decl) = decl {
+                write!(self.string, "@group(1) @binding({}) var ", binding).unwrap();
+                self.backend_writer.write_var_decl(self.string, false, false, decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                writeln!(self.string, ";").unwrap();
+                binding += 1;
+            }
+        }
+        if binding > 1 {
+            writeln!(self.string, "@group(1) @binding(0) var mpsc_sampler: sampler;").unwrap();
+            // Use `textureSampleLevel` instead of `textureSample`, since the latter is only allowed in uniform
+            // control flow in fragment shaders. We don't use mipmaps anyway.
+            writeln!(
+                self.string,
+                "fn sample2d(tex: texture_2d<f32>, pos: vec2<f32>) -> vec4<f32> {{ return textureSampleLevel(tex, \
+                 mpsc_sampler, pos, 0.0); }}"
+            )
+            .unwrap();
+            writeln!(
+                self.string,
+                "fn sample_cube(tex: texture_cube<f32>, dir: vec3<f32>) -> vec4<f32> {{ return textureSampleLevel(tex, \
+                 mpsc_sampler, dir, 0.0); }}"
+            )
+            .unwrap();
+            writeln!(
+                self.string,
+                "fn sample3d(tex: texture_3d<f32>, pos: vec3<f32>) -> vec4<f32> {{ return textureSampleLevel(tex, \
+                 mpsc_sampler, pos, 0.0); }}"
+            )
+            .unwrap();
+        }
+    }
+
+    /// Geometries, instances, and varyings are module-scope variables, just like in GLSL. They get filled in by
+    /// the entry points, so that we don't need to pass them around to every function.
+    fn generate_private_var_decls(&mut self) {
+        for decl in &self.shader.decls {
+            let (ident, ty) = match decl {
+                Decl::Geometry(decl) => (decl.ident, &decl.ty_expr.ty),
+                Decl::Instance(decl) => (decl.ident, &decl.ty_expr.ty),
+                Decl::Varying(decl) => (decl.ident, &decl.ty_expr.ty),
+                _ => continue,
+            };
+            write!(self.string, "var<private> ").unwrap();
+            self.backend_writer.write_var_decl(self.string, false, false, ident, ty.borrow().as_ref().unwrap());
+            writeln!(self.string, ";").unwrap();
+        }
+    }
+
+    fn generate_varying_struct(&mut self) {
+        writeln!(self.string, "struct mpsc_Varyings {{").unwrap();
+        writeln!(self.string, "    @builtin(position) mpsc_position: vec4<f32>,").unwrap();
+        for (index, packed_var_size) in
+            packed_var_sizes(self.packed_varyings().iter().map(|(_, ty)| ty.size()).sum()).into_iter().enumerate()
+        {
+            writeln!(self.string, "    @location({0}) mpsc_packed_varying_{0}: {1},", index, float_ty_name(packed_var_size))
+                .unwrap();
+        }
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_const_decls(&mut self) {
+        for decl in &self.shader.decls {
+            if let Decl::Const(decl) = decl {
+                write!(self.string, "const ").unwrap();
+                self.backend_writer.write_var_decl(
+                    self.string,
+                    false,
+                    false,
+                    decl.ident,
+                    decl.ty_expr.ty.borrow().as_ref().unwrap(),
+                );
+                write!(self.string, " = ").unwrap();
+                self.generate_expr(&decl.expr);
+                writeln!(self.string, ";").unwrap();
+            }
+        }
+    }
+
+    fn generate_cons_fn(&mut self, ty_lit: TyLit, param_tys: &[Ty]) {
+        let mut cons_name = format!("mpsc_{}", ty_lit);
+        for param_ty in param_tys {
+            write!(cons_name, "_{}", param_ty).unwrap();
+        }
+        if !self.backend_writer.use_cons_fn(&cons_name) {
+            return;
+        }
+
+        let param_idents: Vec<String> = if param_tys.len() == 1 {
+            vec!["x".to_string()]
+        } else {
+            (0..param_tys.len()).map(|index| format!("x{}", index)).collect()
+        };
+        write!(self.string, "fn {}(", cons_name).unwrap();
+        let mut sep = "";
+        for (param_ident, param_ty) in param_idents.iter().zip(param_tys) {
+            write!(self.string, "{}{}: ", sep, param_ident).unwrap();
+            self.backend_writer.write_ty(self.string, param_ty);
+            sep = ", ";
+        }
+        write!(self.string, ") -> ").unwrap();
+        self.backend_writer.write_ty_lit(self.string, ty_lit);
+        writeln!(self.string, " {{").unwrap();
+        write!(self.string, "    return ").unwrap();
+        self.backend_writer.write_ty_lit(self.string, ty_lit);
+        write!(self.string, "(").unwrap();
+        let ty = ty_lit.to_ty();
+        let components: Vec<String> = match param_tys {
+            // A matrix constructed from a single scalar has that scalar on its diagonal.
+            [param_ty] if ty.is_matrix() && param_ty.is_scalar() => {
+                let size = matrix_size(&ty);
+                (0..size * size).map(|index| if index % (size + 1) == 0 { "x".to_string() } else { "0.0".to_string() }).collect()
+            }
+            // A matrix constructed from a matrix of a different size gets truncated or extended with the
+            // identity matrix.
+            [param_ty] if ty.is_matrix() && param_ty.is_matrix() => {
+                let dst_size = matrix_size(&ty);
+                let src_size = matrix_size(param_ty);
+                let mut components = Vec::new();
+                for col_index in 0..dst_size {
+                    for row_index in 0..dst_size {
+                        components.push(if row_index < src_size && col_index < src_size {
+                            format!("x[{}][{}]", col_index, row_index)
+                        } else if col_index == row_index {
+                            "1.0".to_string()
+                        } else {
+                            "0.0".to_string()
+                        });
+                    }
+                }
+                components
+            }
+            _ => {
+                let mut components = Vec::new();
+                for (param_ident, param_ty) in param_idents.iter().zip(param_tys) {
+                    if param_ty.is_matrix() {
+                        let size = matrix_size(param_ty);
+                        for index in 0..size * size {
+                            components.push(format!("{}[{}][{}]", param_ident, index / size, index % size));
+                        }
+                    } else if param_ty.is_vector() {
+                        for index in 0..param_ty.size() {
+                            components.push(format!("{}[{}]", param_ident, index));
+                        }
+                    } else {
+                        components.push(param_ident.clone());
+                    }
+                }
+                components
+            }
+        };
+        write!(self.string, "{}", components.join(", ")).unwrap();
+        writeln!(self.string, ");").unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_fn_decl(&mut self, decl: &FnDecl, visited: &mut HashSet<IdentPath>) {
+        FnDeclGenerator { shader: self.shader, decl, visited, backend_writer: self.backend_writer, string: self.string }
+            .generate_fn_decl()
+    }
+
+    fn generate_vertex_main(&mut self) {
+        let decl = self.shader.find_fn_decl(IdentPath::from_str("vertex")).unwrap();
+        let geometries = self.shader_vars(|decl| match decl {
+            Decl::Geometry(decl) => Some((decl.ident, &decl.ty_expr.ty)),
+            _ => None,
+        });
+        let instances = self.shader_vars(|decl| match decl {
+            Decl::Instance(decl) => Some((decl.ident, &decl.ty_expr.ty)),
+            _ => None,
+        });
+        let geometry_sizes = packed_var_sizes(geometries.iter().map(|(_, ty)| ty.size()).sum());
+        let instance_sizes = packed_var_sizes(instances.iter().map(|(_, ty)| ty.size()).sum());
+
+        writeln!(self.string, "@vertex").unwrap();
+        write!(self.string, "fn mpsc_vertex_main(").unwrap();
+        let mut location = 0;
+        let mut sep = "";
+        for (packed_var_name, packed_var_sizes) in
+            [("mpsc_packed_geometry", &geometry_sizes), ("mpsc_packed_instance", &instance_sizes)]
+        {
+            for (index, &packed_var_size) in packed_var_sizes.iter().enumerate() {
+                write!(
+                    self.string,
+                    "{}@location({}) {}_{}: {}",
+                    sep,
+                    location,
+                    packed_var_name,
+                    index,
+                    float_ty_name(packed_var_size)
+                )
+                .unwrap();
+                location += 1;
+                sep = ", ";
+            }
+        }
+        writeln!(self.string, ") -> mpsc_Varyings {{").unwrap();
+        for (packed_var_name, vars, packed_var_sizes) in
+            [("mpsc_packed_geometry", &geometries, &geometry_sizes), ("mpsc_packed_instance", &instances, &instance_sizes)]
+        {
+            let mut offset = 0;
+            for (ident, ty) in vars {
+                write!(self.string, "    ").unwrap();
+                self.backend_writer.write_ident(self.string, *ident);
+                write!(self.string, " = ").unwrap();
+                write_unpacked_value(
+                    self.string,
+                    &|index| format!("{}_{}", packed_var_name, index),
+                    packed_var_sizes,
+                    offset,
+                    ty,
+                );
+                writeln!(self.string, ";").unwrap();
+                offset += ty.size();
+            }
+        }
+        writeln!(self.string, "    var mpsc_varyings: mpsc_Varyings;").unwrap();
+        write!(self.string, "    mpsc_varyings.mpsc_position = ").unwrap();
+        self.backend_writer.write_ident(self.string, decl.ident_path.get_single().expect("unexpected"));
+        writeln!(self.string, "();").unwrap();
+        let varyings: Vec<(String, Ty)> = self
+            .packed_varyings()
+            .into_iter()
+            .map(|(ident, ty)| {
+                let mut string = String::new();
+                self.backend_writer.write_ident(&mut string, ident);
+                (string, ty)
+            })
+            .collect();
+        let mut offset = 0;
+        for (index, packed_var_size) in packed_var_sizes(offset_end(&varyings)).into_iter().enumerate() {
+            let pieces = packed_pieces(&varyings, offset, offset + packed_var_size);
+            write!(self.string, "    mpsc_varyings.mpsc_packed_varying_{} = ", index).unwrap();
+            if pieces.len() == 1 {
+                write!(self.string, "{}", pieces[0]).unwrap();
+            } else {
+                write!(self.string, "{}({})", float_ty_name(packed_var_size), pieces.join(", ")).unwrap();
+            }
+            writeln!(self.string, ";").unwrap();
+            offset += packed_var_size;
+        }
+        writeln!(self.string, "    return mpsc_varyings;").unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_fragment_main(&mut self) {
+        let decl = self.shader.find_fn_decl(IdentPath::from_str("pixel")).unwrap();
+        let varyings = self.packed_varyings();
+        let packed_var_sizes = packed_var_sizes(varyings.iter().map(|(_, ty)| ty.size()).sum());
+        writeln!(self.string, "@fragment").unwrap();
+        writeln!(self.string, "fn mpsc_fragment_main(mpsc_varyings: mpsc_Varyings) -> @location(0) vec4<f32> {{").unwrap();
+        let mut offset = 0;
+        for (ident, ty) in &varyings {
+            write!(self.string, "    ").unwrap();
+            self.backend_writer.write_ident(self.string, *ident);
+            write!(self.string, " = ").unwrap();
+            write_unpacked_value(
+                self.string,
+                &|index| format!("mpsc_varyings.mpsc_packed_varying_{}", index),
+                &packed_var_sizes,
+                offset,
+                ty,
+            );
+            writeln!(self.string, ";").unwrap();
+            offset += ty.size();
+        }
+        write!(self.string, "    return ").unwrap();
+        self.backend_writer.write_ident(self.string, decl.ident_path.get_single().expect("unexpected"));
+        writeln!(self.string, "();").unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_builtin_helper_fns(&mut self) {
+        for (name, arg_tys) in self.backend_writer.builtin_helper_fns.borrow().iter() {
+            let return_ty = match name.as_str() {
+                "equal" | "notEqual" | "lessThan" | "lessThanEqual" | "greaterThan" | "greaterThanEqual" => {
+                    match arg_tys[0].size() {
+                        2 => Ty::Bvec2,
+                        3 => Ty::Bvec3,
+                        4 => Ty::Bvec4,
+                        _ => panic!(),
+                    }
+                }
+                "mod" | "not" | "matrixCompMult" | "inverse" | "shl" | "shr" | "bitor" | "bitxor" | "bitand" => {
+                    arg_tys[0].clone()
+                }
+                _ => arg_tys.iter().find(|arg_ty| arg_ty.is_vector()).unwrap().clone(),
+            };
+            write!(self.string, "fn {}(", builtin_helper_fn_name(name, arg_tys)).unwrap();
+            let mut sep = "";
+            for (index, arg_ty) in arg_tys.iter().enumerate() {
+                write!(self.string, "{}x{}: ", sep, index).unwrap();
+                self.backend_writer.write_ty(self.string, arg_ty);
+                sep = ", ";
+            }
+            write!(self.string, ") -> ").unwrap();
+            self.backend_writer.write_ty(self.string, &return_ty);
+            writeln!(self.string, " {{").unwrap();
+            match name.as_str() {
+                "mod" => writeln!(self.string, "    return x0 - x1 * floor(x0 / x1);").unwrap(),
+                "equal" => writeln!(self.string, "    return x0 == x1;").unwrap(),
+                "notEqual" => writeln!(self.string, "    return x0 != x1;").unwrap(),
+                "lessThan" => writeln!(self.string, "    return x0 < x1;").unwrap(),
+                "lessThanEqual" => writeln!(self.string, "    return x0 <= x1;").unwrap(),
+                "greaterThan" => writeln!(self.string, "    return x0 > x1;").unwrap(),
+                "greaterThanEqual" => writeln!(self.string, "    return x0 >= x1;").unwrap(),
+                "not" => writeln!(self.string, "    return !x0;").unwrap(),
+                "matrixCompMult" => {
+                    let size = matrix_size(&arg_tys[0]);
+                    let columns: Vec<String> = (0..size).map(|index| format!("x0[{0}] * x1[{0}]", index)).collect();
+                    writeln!(self.string, "    return mat{0}x{0}<f32>({1});", size, columns.join(", ")).unwrap();
+                }
+                "inverse" => self.generate_inverse_body(&arg_tys[0]),
+                "shl" | "shr" => {
+                    // The shift amount has to be unsigned, with as many components as the value being shifted.
+                    let shift_ty_name =
+                        if return_ty.is_scalar() { String::from("u32") } else { format!("vec{}<u32>", return_ty.size()) };
+                    let shift = if arg_tys[1].size() == return_ty.size() { "x1" } else { "u32(x1)" };
+                    let op = if name == "shl" { "<<" } else { ">>" };
+                    writeln!(self.string, "    return x0 {} {}({});", op, shift_ty_name, shift).unwrap();
+                }
+                "bitor" | "bitxor" | "bitand" => {
+                    // Bitwise operators don't accept a mix of scalars and vectors in WGSL.
+                    let op = match name.as_str() {
+                        "bitor" => "|",
+                        "bitxor" => "^",
+                        _ => "&",
+                    };
+                    write!(self.string, "    return x0 {} ", op).unwrap();
+                    self.backend_writer.write_ty(self.string, &return_ty);
+                    writeln!(self.string, "(x1);").unwrap();
+                }
+                _ => {
+                    // Builtins that take a mix of scalars and vectors in GLSL, but only vectors in WGSL.
+                    let args: Vec<String> = arg_tys
+                        .iter()
+                        .enumerate()
+                        .map(|(index, arg_ty)| {
+                            if arg_ty.is_scalar() {
+                                format!("{}(x{})", float_ty_name(return_ty.size()), index)
+                            } else {
+                                format!("x{}", index)
+                            }
+                        })
+                        .collect();
+                    writeln!(self.string, "    return {}({});", name, args.join(", ")).unwrap();
+                }
+            }
+            writeln!(self.string, "}}").unwrap();
+        }
+    }
+
+    /// WGSL doesn't have an `inverse` builtin, so compute it using the adjugate matrix. Only `mat4` is supported
+    /// by the shader language.
+    fn generate_inverse_body(&mut self, ty: &Ty) {
+        match ty {
+            Ty::Mat4 => {
+                for col_index in 0..4 {
+                    for row_index in 0..4 {
+                        writeln!(self.string, "    let a{0}{1} = x0[{0}][{1}];", col_index, row_index).unwrap();
+                    }
+                }
+                for (index, (a, b, c, d)) in [
+                    ("a00", "a11", "a01", "a10"),
+                    ("a00", "a12", "a02", "a10"),
+                    ("a00", "a13", "a03", "a10"),
+                    ("a01", "a12", "a02", "a11"),
+                    ("a01", "a13", "a03", "a11"),
+                    ("a02", "a13", "a03", "a12"),
+                    ("a20", "a31", "a21", "a30"),
+                    ("a20", "a32", "a22", "a30"),
+                    ("a20", "a33", "a23", "a30"),
+                    ("a21", "a32", "a22", "a31"),
+                    ("a21", "a33", "a23", "a31"),
+                    ("a22", "a33", "a23", "a32"),
+                ]
+                .iter()
+                .enumerate()
+                {
+                    writeln!(self.string, "    let b{:02} = {} * {} - {} * {};", index, a, b, c, d).unwrap();
+                }
+                writeln!(self.string, "    let det = b00 * b11 - b01 * b10 + b02 * b09 + b03 * b08 - b04 * b07 + b05 * b06;")
+                    .unwrap();
+                writeln!(self.string, "    return mat4x4<f32>(").unwrap();
+                for component in [
+                    "a11 * b11 - a12 * b10 + a13 * b09",
+                    "a02 * b10 - a01 * b11 - a03 * b09",
+                    "a31 * b05 - a32 * b04 + a33 * b03",
+                    "a22 * b04 - a21 * b05 - a23 * b03",
+                    "a12 * b08 - a10 * b11 - a13 * b07",
+                    "a00 * b11 - a02 * b08 + a03 * b07",
+                    "a32 * b02 - a30 * b05 - a33 * b01",
+                    "a20 * b05 - a22 * b02 + a23 * b01",
+                    "a10 * b10 - a11 * b08 + a13 * b06",
+                    "a01 * b08 - a00 * b10 - a03 * b06",
+                    "a30 * b04 - a31 * b02 + a33 * b00",
+                    "a21 * b02 - a20 * b04 - a23 * b00",
+                    "a11 * b07 - a10 * b09 - a12 * b06",
+                    "a00 * b09 - a01 * b07 + a02 * b06",
+                    "a31 * b01 - a30 * b03 - a32 * b00",
+                    "a20 * b03 - a21 * b01 + a22 * b00",
+                ] {
+                    writeln!(self.string, "        {},", component).unwrap();
+                }
+                writeln!(self.string, "    ) * (1.0 / det);").unwrap();
+            }
+            _ => panic!(),
+        }
+    }
+
+    /// All geometries, instances, and varyings that get passed from the vertex to the fragment shader.
+    fn packed_varyings(&self) -> Vec<(Ident, Ty)> {
+        self.shader_vars(|decl| match decl {
+            Decl::Geometry(decl) if decl.is_used_in_fragment_shader.get().unwrap() => Some((decl.ident, &decl.ty_expr.ty)),
+            Decl::Instance(decl) if decl.is_used_in_fragment_shader.get().unwrap() => Some((decl.ident, &decl.ty_expr.ty)),
+            Decl::Varying(decl) => Some((decl.ident, &decl.ty_expr.ty)),
+            _ => None,
+        })
+    }
+
+    fn shader_vars(&self, f: impl Fn(&Decl) -> Option<(Ident, &RefCell<Option<Ty>>)>) -> Vec<(Ident, Ty)> {
+        self.shader.decls.iter().filter_map(f).map(|(ident, ty)| (ident, ty.borrow().as_ref().unwrap().clone())).collect()
+    }
+
+    fn generate_expr(&mut self, expr: &Expr) {
+        ExprGenerator { shader: self.shader, decl: None, backend_writer: self.backend_writer, string: self.string }
+            .generate_expr(expr)
+    }
+}
+
+struct FnDeclGenerator<'a> {
+    shader: &'a ShaderAst,
+    decl: &'a FnDecl,
+    visited: &'a mut HashSet<IdentPath>,
+    string: &'a mut String,
+    backend_writer: &'a WgslBackendWriter,
+}
+
+impl<'a> FnDeclGenerator<'a> {
+    fn generate_fn_decl(&mut self) {
+        if self.visited.contains(&self.decl.ident_path) {
+            return;
+        }
+        for &callee in self.decl.callees.borrow().as_ref().unwrap().iter() {
+            FnDeclGenerator {
+                shader: self.shader,
+                backend_writer: self.backend_writer,
+                decl: self.shader.find_fn_decl(callee).unwrap(),
+                visited: self.visited,
+                string: self.string,
+            }
+            .generate_fn_decl()
+        }
+        write!(self.string, "fn ").unwrap();
+        self.backend_writer.write_ident(self.string, self.decl.ident_path.to_struct_fn_ident());
+        write!(self.string, "(").unwrap();
+        let mut sep = "";
+        for param in &self.decl.params {
+            write!(self.string, "{}", sep).unwrap();
+            if param.is_inout {
+                self.backend_writer.write_var_decl(
+                    self.string,
+                    true,
+                    false,
+                    param.ident,
+                    param.ty_expr.ty.borrow().as_ref().unwrap(),
+                );
+            } else {
+                write!(self.string, "mpsc_param_{}: ", param.ident).unwrap();
+                self.backend_writer.write_ty(self.string, param.ty_expr.ty.borrow().as_ref().unwrap());
+            }
+            sep = ", ";
+        }
+        write!(self.string, ") ").unwrap();
+        let return_ty = self.decl.return_ty.borrow();
+        if *return_ty.as_ref().unwrap() != Ty::Void {
+            write!(self.string, "-> ").unwrap();
+            self.backend_writer.write_ty(self.string, return_ty.as_ref().unwrap());
+            write!(self.string, " ").unwrap();
+        }
+        // Parameters are immutable in WGSL, so copy them into variables first. The body goes into a nested block,
+        // since those variables would otherwise conflict with any parameters that the body declares itself.
+        if self.decl.params.iter().any(|param| !param.is_inout) {
+            writeln!(self.string, "{{").unwrap();
+            for param in self.decl.params.iter().filter(|param| !param.is_inout) {
+                write!(self.string, "    ").unwrap();
+                self.backend_writer.write_local_var_decl(self.string, param.ident, param.ty_expr.ty.borrow().as_ref().unwrap());
+                writeln!(self.string, " = mpsc_param_{};", param.ident).unwrap();
+            }
+            write!(self.string, "    ").unwrap();
+            self.generate_block(&self.decl.block, 1);
+            writeln!(self.string).unwrap();
+            write!(self.string, "}}").unwrap();
+        } else {
+            self.generate_block(&self.decl.block, 0);
+        }
+        writeln!(self.string).unwrap();
+        self.visited.insert(self.decl.ident_path);
+    }
+
+    fn generate_block(&mut self, block: &Block, indent_level: usize) {
+        BlockGenerator {
+            shader: self.shader,
+            decl: self.decl,
+            backend_writer: self.backend_writer,
+            indent_level,
+            string: self.string,
+        }
+        .generate_block(block)
+    }
+}
+
+#[derive(Default)]
+struct WgslBackendWriter {
+    /// Helper functions for GLSL builtins that don't exist in WGSL, as (builtin name, argument types).
+    builtin_helper_fns: RefCell<BTreeSet<(String, Vec<Ty>)>>,
+}
+
+impl WgslBackendWriter {
+    fn write_ty(&self, string: &mut String, ty: &Ty) {
+        match ty {
+            Ty::Void => panic!(),
+            Ty::Array { elem_ty, len } => {
+                write!(string, "array<").unwrap();
+                self.write_ty(string, elem_ty);
+                write!(string, ", {}>", len).unwrap();
+            }
+            Ty::Struct { ident } => self.write_ident(string, *ident),
+            _ => self.write_ty_lit(string, ty.maybe_ty_lit().unwrap()),
+        }
+    }
+}
+
+impl BackendWriter for WgslBackendWriter {
+    fn write_call_expr_hidden_args(&self, _string: &mut String, _ident_path: IdentPath, _shader: &ShaderAst, _sep: &str) {}
+
+    fn generate_var_expr(
+        &self,
+        string: &mut String,
+        _span: Span,
+        ident_path: IdentPath,
+        kind: &Cell<Option<VarKind>>,
+        shader: &ShaderAst,
+        decl: &FnDecl,
+        _ty: &Option<Ty>,
+    ) {
+        let ident = ident_path.get_single().expect("unexpected");
+        match kind.get().unwrap() {
+            VarKind::Uniform => {
+                let uniform_decl = shader.find_uniform_decl(ident).unwrap();
+                let block_ident = uniform_decl.block_ident.unwrap_or(Ident::new("default"));
+                let mut offset = 0;
+                let mut block_size = 0;
+                for decl in &shader.decls {
+                    match decl {
+                        Decl::Uniform(decl) if decl.block_ident.unwrap_or(Ident::new("default")) == block_ident => {
+                            if decl.ident == ident {
+                                offset = block_size;
+                            }
+                            block_size += decl.ty_expr.ty.borrow().as_ref().unwrap().size();
+                        }
+                        _ => {}
+                    }
+                }
+                write_unpacked_value(
+                    string,
+                    &|index| format!("mpsc_{}_uniforms[{}]", block_ident, index),
+                    &packed_var_sizes(block_size.div_ceil(4) * 4),
+                    offset,
+                    uniform_decl.ty_expr.ty.borrow().as_ref().unwrap(),
+                );
+            }
+            VarKind::Local if decl.params.iter().any(|param| param.is_inout && param.ident == ident) => {
+                write!(string, "(*").unwrap();
+                self.write_ident(string, ident);
+                write!(string, ")").unwrap();
+            }
+            _ => self.write_ident(string, ident),
+        }
+    }
+
+    fn needs_mul_fn_for_matrix_multiplication(&self) -> bool {
+        false
+    }
+
+    fn needs_unpack_for_matrix_multiplication(&self) -> bool {
+        false
+    }
+
+    fn use_cons_fn(&self, what: &str) -> bool {
+        // Constructor names look like `mpsc_mat3_vec2_float_mat2`.
+        let mut tys = what.split('_').skip(1);
+        let ty = tys.next().unwrap();
+        let arg_tys: Vec<&str> = tys.collect();
+        let is_scalar = |ty: &str| matches!(ty, "bool" | "int" | "float");
+        let is_matrix = |ty: &str| ty.starts_with("mat");
+        if is_matrix(ty) {
+            // WGSL only has matrix constructors that take a matrix of the same size, all columns, or all scalars.
+            let column_ty = ty.replace("mat", "vec");
+            match arg_tys.as_slice() {
+                [arg_ty] if is_matrix(arg_ty) => *arg_ty != ty,
+                arg_tys => {
+                    !(arg_tys.len() > 1 && arg_tys.iter().all(|arg_ty| is_scalar(arg_ty))
+                        || arg_tys.iter().all(|arg_ty| *arg_ty == column_ty))
+                }
+            }
+        } else {
+            arg_tys.iter().any(|arg_ty| is_matrix(arg_ty))
+        }
+    }
+
+    fn write_var_decl(&self, string: &mut String, is_inout: bool, _is_packed: bool, ident: Ident, ty: &Ty) {
+        self.write_ident(string, ident);
+        write!(string, ": ").unwrap();
+        if is_inout {
+            write!(string, "ptr<function, ").unwrap();
+            self.write_ty(string, ty);
+            write!(string, ">").unwrap();
+        } else {
+            self.write_ty(string, ty);
+        }
+    }
+
+    fn write_local_var_decl(&self, string: &mut String, ident: Ident, ty: &Ty) {
+        write!(string, "var ").unwrap();
+        self.write_var_decl(string, false, false, ident, ty);
+    }
+
+    fn needs_select_for_cond_expr(&self) -> bool {
+        true
+    }
+
+    fn needs_address_of_for_inout_args(&self) -> bool {
+        true
+    }
+
+    fn write_ty_lit(&self, string: &mut String, ty_lit: TyLit) {
+        write!(
+            string,
+            "{}",
+            match ty_lit {
+                TyLit::Bool => "bool",
+                TyLit::Int => "i32",
+                TyLit::Float => "f32",
+                TyLit::Bvec2 => "vec2<bool>",
+                TyLit::Bvec3 => "vec3<bool>",
+                TyLit::Bvec4 => "vec4<bool>",
+                TyLit::Ivec2 => "vec2<i32>",
+                TyLit::Ivec3 => "vec3<i32>",
+                TyLit::Ivec4 => "vec4<i32>",
+                TyLit::Vec2 => "vec2<f32>",
+                TyLit::Vec3 => "vec3<f32>",
+                TyLit::Vec4 => "vec4<f32>",
+                TyLit::Mat2 => "mat2x2<f32>",
+                TyLit::Mat3 => "mat3x3<f32>",
+                TyLit::Mat4 => "mat4x4<f32>",
+                TyLit::Texture2D => "texture_2d<f32>",
+                TyLit::TextureCube => "texture_cube<f32>",
+                TyLit::Texture3D => "texture_3d<f32>",
+            }
+        )
+        .unwrap();
+    }
+
+    fn write_call_ident(&self, string: &mut String, ident: Ident, arg_exprs: &[Expr]) {
+        let name = ident.to_string();
+        let arg_tys: Vec<Ty> = arg_exprs.iter().map(|arg_expr| arg_expr.ty.borrow().as_ref().unwrap().clone()).collect();
+        let has_scalar_and_vector_args =
+            arg_tys.iter().any(|arg_ty| arg_ty.is_scalar()) && arg_tys.iter().any(|arg_ty| arg_ty.is_vector());
+        match name.as_str() {
+            "atan" if arg_tys.len() == 2 => write!(string, "atan2").unwrap(),
+            "dFdx" => write!(string, "dpdx").unwrap(),
+            "dFdy" => write!(string, "dpdy").unwrap(),
+            "faceforward" => write!(string, "faceForward").unwrap(),
+            "inversesqrt" => write!(string, "inverseSqrt").unwrap(),
+            "mod" | "equal" | "notEqual" | "lessThan" | "lessThanEqual" | "greaterThan" | "greaterThanEqual" | "not"
+            | "matrixCompMult" | "inverse" => {
+                write!(string, "{}", builtin_helper_fn_name(&name, &arg_tys)).unwrap();
+                self.builtin_helper_fns.borrow_mut().insert((name, arg_tys));
+            }
+            "clamp" | "max" | "min" | "smoothstep" | "step" if has_scalar_and_vector_args => {
+                write!(string, "{}", builtin_helper_fn_name(&name, &arg_tys)).unwrap();
+                self.builtin_helper_fns.borrow_mut().insert((name, arg_tys));
+            }
+            _ => self.write_ident(string, ident),
+        }
+    }
+
+    fn write_int_op_fn_ident(&self, string: &mut String, op: &'static str, arg_tys: &[Ty]) -> bool {
+        let needs_helper_fn = match op {
+            "shl" | "shr" => true,
+            "bitor" | "bitxor" | "bitand" => arg_tys[0] != arg_tys[1],
+            _ => false,
+        };
+        if needs_helper_fn {
+            write!(string, "{}", builtin_helper_fn_name(op, arg_tys)).unwrap();
+            self.builtin_helper_fns.borrow_mut().insert((op.to_string(), arg_tys.to_vec()));
+        }
+        needs_helper_fn
+    }
+
+    fn write_ident(&self, string: &mut String, ident: Ident) {
+        ident.with(|ident_string| {
+            if write_module_item_ident(string, ident_string) {
+                return;
+            }
+            if WGSL_RESERVED_WORDS.contains(&ident_string) {
+                write!(string, "mpsc_{}", ident_string).unwrap();
+            } else {
+                write!(string, "{}", ident_string).unwrap();
+            }
+        })
+    }
+}
+
+/// Keywords, reserved words, and predeclared types of WGSL, which we can't use as identifiers.
+const WGSL_RESERVED_WORDS: &[&str] = &[
+    "alias",
+    "array",
+    "atomic",
+    "bitcast",
+    "bool",
+    "break",
+    "case",
+    "const",
+    "const_assert",
+    "continue",
+    "continuing",
+    "default",
+    "diagnostic",
+    "discard",
+    "else",
+    "enable",
+    "f16",
+    "f32",
+    "fallthrough",
+    "false",
+    "filter",
+    "fn",
+    "for",
+    "i32",
+    "if",
+    "let",
+    "loop",
+    "mat2x2",
+    "mat3x3",
+    "mat4x4",
+    "mod",
+    "override",
+    "pass",
+    "private",
+    "ptr",
+    "requires",
+    "return",
+    "sampler",
+    "self",
+    "storage",
+    "struct",
+    "switch",
+    "target",
+    "texture",
+    "texture_2d",
+    "true",
+    "type",
+    "u32",
+    "uniform",
+    "var",
+    "vec2",
+    "vec3",
+    "vec4",
+    "while",
+    "workgroup",
+];
+
+fn builtin_helper_fn_name(name: &str, arg_tys: &[Ty]) -> String {
+    let mut helper_fn_name = format!("mpsc_{}", name);
+    for arg_ty in arg_tys {
+        write!(helper_fn_name, "_{}", arg_ty).unwrap();
+    }
+    helper_fn_name
+}
+
+fn uniform_block_binding(ident: Ident) -> usize {
+    if ident == Ident::new("pass") {
+        0
+    } else if ident == Ident::new("view") {
+        1
+    } else if ident == Ident::new("draw") {
+        2
+    } else if ident == Ident::new("default") {
+        3
+    } else {
+        panic!("extra uniform blocks not supported");
+    }
+}
+
+fn matrix_size(ty: &Ty) -> usize {
+    match ty {
+        Ty::Mat2 => 2,
+        Ty::Mat3 => 3,
+        Ty::Mat4 => 4,
+        _ => panic!(),
+    }
+}
+
+fn float_ty_name(size: usize) -> String {
+    if size == 1 {
+        "f32".to_string()
+    } else {
+        format!("vec{}<f32>", size)
+    }
+}
+
+/// Splits `packed_vars_size` floats into packed variables of 4 floats, except for possibly the last one.
+fn packed_var_sizes(mut packed_vars_size: usize) -> Vec<usize> {
+    let mut packed_var_sizes = Vec::new();
+    while packed_vars_size > 0 {
+        let packed_var_size = packed_vars_size.min(4);
+        packed_var_sizes.push(packed_var_size);
+        packed_vars_size -= packed_var_size;
+    }
+    packed_var_sizes
+}
+
+fn offset_end(vars: &[(String, Ty)]) -> usize {
+    vars.iter().map(|(_, ty)| ty.size()).sum()
+}
+
+/// Writes an expression that reads a value of type `ty` at `offset` floats into a list of packed variables,
+/// where `packed_var` gives the expression for the packed variable at some index.
+fn write_unpacked_value(
+    string: &mut String,
+    packed_var: &dyn Fn(usize) -> String,
+    packed_var_sizes: &[usize],
+    offset: usize,
+    ty: &Ty,
+) {
+    match ty {
+        Ty::Float | Ty::Vec2 | Ty::Vec3 | Ty::Vec4 => {
+            let mut pieces = Vec::new();
+            let mut piece_offset = offset;
+            while piece_offset < offset + ty.size() {
+                let index = piece_offset / 4;
+                let piece_end = (offset + ty.size()).min(index * 4 + 4);
+                if piece_offset == index * 4 && piece_end == index * 4 + packed_var_sizes[index] {
+                    pieces.push(packed_var(index));
+                } else {
+                    pieces.push(format!(
+                        "{}.{}",
+                        packed_var(index),
+                        Swizzle::from_range(piece_offset % 4, piece_end - index * 4)
+                    ));
+                }
+                piece_offset = piece_end;
+            }
+            if pieces.len() == 1 {
+                write!(string, "{}", pieces[0]).unwrap();
+            } else {
+                write!(string, "{}({})", float_ty_name(ty.size()), pieces.join(", ")).unwrap();
+            }
+        }
+        Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+            let size = matrix_size(ty);
+            let column_ty = match size {
+                2 => Ty::Vec2,
+                3 => Ty::Vec3,
+                _ => Ty::Vec4,
+            };
+            write!(string, "mat{0}x{0}<f32>(", size).unwrap();
+            for column_index in 0..size {
+                if column_index > 0 {
+                    write!(string, ", ").unwrap();
+                }
+                write_unpacked_value(string, packed_var, packed_var_sizes, offset + column_index * size, &column_ty);
+            }
+            write!(string, ")").unwrap();
+        }
+        _ => panic!("only float types can be packed"),
+    }
+}
+
+/// Returns expressions for the floats from `start` to `end` in `vars`, when packed together, grouped into
+/// swizzles where possible.
+fn packed_pieces(vars: &[(String, Ty)], start: usize, end: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut var_offset = 0;
+    for (name, ty) in vars {
+        let var_start = start.max(var_offset);
+        let var_end = end.min(var_offset + ty.size());
+        if var_start < var_end {
+            let (from, to) = (var_start - var_offset, var_end - var_offset);
+            match ty {
+                Ty::Float => pieces.push(name.clone()),
+                Ty::Vec2 | Ty::Vec3 | Ty::Vec4 if from == 0 && to == ty.size() => pieces.push(name.clone()),
+                Ty::Vec2 | Ty::Vec3 | Ty::Vec4 => pieces.push(format!("{}.{}", name, Swizzle::from_range(from, to))),
+                Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+                    let size = matrix_size(ty);
+                    let mut column_start = from;
+                    while column_start < to {
+                        let column_index = column_start / size;
+                        let column_end = to.min(column_index * size + size);
+                        if column_start == column_index * size && column_end == column_start + size {
+                            pieces.push(format!("{}[{}]", name, column_index));
+                        } else {
+                            pieces.push(format!(
+                                "{}[{}].{}",
+                                name,
+                                column_index,
+                                Swizzle::from_range(column_start % size, column_end - column_index * size)
+                            ));
+                        }
+                        column_start = column_end;
+                    }
+                }
+                _ => panic!("only float types can be packed"),
+            }
+        }
+        var_offset += ty.size();
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_fragment::CodeFragment;
+    use crate::generate_shader_ast::ShaderAstGenerator;
+
+    /// Generates WGSL for `code`, and checks that it's valid using naga.
+    fn generate_and_validate(code: &'static str) -> String {
+        let code_fragment = CodeFragment::Static { filename: "test.rs", line: 1, col: 1, code };
+        let shader_ast = ShaderAstGenerator::new().generate_shader_ast(&[code_fragment]).unwrap();
+        let wgsl = generate_shader(&shader_ast);
+        let module = naga::front::wgsl::parse_str(&wgsl).unwrap_or_else(|err| panic!("{}\n{}", err.emit_to_string(&wgsl), wgsl));
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+            .validate(&module)
+            .unwrap_or_else(|err| panic!("{:?}\n{}", err, wgsl));
+        wgsl
+    }
+
+    #[test]
+    fn vertex_and_pixel() {
+        let wgsl = generate_and_validate(
+            r#"
+            struct Scaler {
+                factor: float
+            }
+            impl Scaler {
+                fn apply(self, v: vec2) -> vec2 {
+                    return v * self.factor;
+                }
+            }
+            const OFFSET: float = 0.25;
+            geometry geom: vec2;
+            instance rect_pos: vec2;
+            instance color: vec4;
+            instance transform: mat4;
+            varying pos: vec2;
+            fn vertex() -> vec4 {
+                let scaler: Scaler;
+                scaler.factor = 2.0;
+                pos = scaler.apply(geom) + rect_pos + OFFSET;
+                return vec4(pos, 0., 1.);
+            }
+            fn pixel() -> vec4 {
+                return transform * color * pos.x;
+            }
+            "#,
+        );
+        // 2 floats of geometries, and 2 + 4 + 16 floats of instances.
+        assert!(wgsl.contains("@location(0) mpsc_packed_geometry_0: vec2<f32>"));
+        assert!(wgsl.contains("@location(6) mpsc_packed_instance_5: vec2<f32>"));
+        assert!(wgsl.contains("transform = mat4x4<f32>(vec4<f32>(mpsc_packed_instance_1.zw, mpsc_packed_instance_2.xy)"));
+        // Instances that are used in the pixel shader get passed along with the varyings.
+        assert!(wgsl.contains("mpsc_varyings.mpsc_packed_varying_5 = pos;"));
+    }
+
+    #[test]
+    fn uniforms_and_textures() {
+        let wgsl = generate_and_validate(
+            r#"
+            geometry geom: vec2;
+            uniform zoom: float in pass;
+            uniform offset: vec3;
+            uniform transform: mat4;
+            texture tex: texture2D;
+            texture texture: texture2D;
+            fn vertex() -> vec4 {
+                return transform * vec4(geom * zoom + offset.xy, offset.z, 1.);
+            }
+            fn pixel() -> vec4 {
+                return sample2d(tex, geom) + sample2d(texture, geom);
+            }
+            "#,
+        );
+        assert!(wgsl.contains("@group(0) @binding(0) var<uniform> mpsc_pass_uniforms: array<vec4<f32>, 1>;"));
+        assert!(wgsl.contains("@group(0) @binding(3) var<uniform> mpsc_default_uniforms: array<vec4<f32>, 5>;"));
+        assert!(wgsl.contains("@group(1) @binding(2) var mpsc_texture: texture_2d<f32>;"));
+    }
+
+    #[test]
+    fn cube_and_3d_textures() {
+        let wgsl = generate_and_validate(
+            r#"
+            geometry geom: vec2;
+            texture sky: textureCube;
+            texture volume: texture3D;
+            fn vertex() -> vec4 {
+                return vec4(geom, 0., 1.);
+            }
+            fn pixel() -> vec4 {
+                return sample_cube(sky, vec3(geom, 1.)) * sample3d(volume, vec3(geom, 0.5));
+            }
+            "#,
+        );
+        assert!(wgsl.contains("@group(1) @binding(1) var sky: texture_cube<f32>;"));
+        assert!(wgsl.contains("@group(1) @binding(2) var volume: texture_3d<f32>;"));
+    }
+
+    #[test]
+    fn statements_and_params() {
+        generate_and_validate(
+            r#"
+            geometry geom: vec2;
+            fn bump(inout value: float, amount: float) {
+                amount *= 2.;
+                value += amount;
+            }
+            fn vertex() -> vec4 {
+                let total = 0.;
+                for i from 0 to 10 step 2 {
+                    if i > 4 {
+                        break;
+                    }
+                    bump(total, float(i));
+                }
+                let sign = total > 1. ? 1. : -1.;
+                return vec4(geom * sign, 0., 1.);
+            }
+            fn pixel() -> vec4 {
+                return vec4(1.);
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn builtin_helpers() {
+        generate_and_validate(
+            r#"
+            geometry geom: vec2;
+            instance transform: mat4;
+            fn vertex() -> vec4 {
+                return mat4(geom.x) * vec4(geom, 0., 1.);
+            }
+            fn pixel() -> vec4 {
+                let m = inverse(transform) * matrixCompMult(transform, transform);
+                let n = mat2(transform);
+                let v = mod(m[0], 2.) + clamp(m[1], 0., 1.) + smoothstep(0., 1., m[2]) + step(0.5, m[3]);
+                let b = any(not(lessThan(v.xy, n[0]))) || all(equal(v.zw, n[1]));
+                return vec4(atan(v.x, v.y), inversesqrt(v.z), dFdx(v.w), b ? 1. : 0.);
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn int_ops_and_loops() {
+        let wgsl = generate_and_validate(
+            r#"
+            const MASK: int = 0xff;
+            instance flags: float;
+            varying hash: float;
+            fn vertex() -> vec4 {
+                let bits = int(flags);
+                let v = ivec2(bits >> 8, bits) & MASK;
+                let w = (v << 2) | (v ^ ivec2(1, 2)) | !v % 3;
+                let h = w.x ^ (w.y << v.x);
+                let i = 0;
+                while i < 10 {
+                    i += 1;
+                    if h % 2 == 0 {
+                        continue;
+                    }
+                    h = h >> 1;
+                }
+                loop {
+                    h += 1;
+                    if h > 100 {
+                        break;
+                    }
+                }
+                hash = float(h);
+                return vec4(hash, 0., 0., 1.);
+            }
+            fn pixel() -> vec4 {
+                match int(hash) & 3 {
+                    0 => {
+                        return vec4(1.);
+                    }
+                    1 | -1 => {
+                        return vec4(0.5);
+                    }
+                    _ => {}
+                }
+                return vec4(0.);
+            }
+            "#,
+        );
+        assert!(wgsl.contains("fn mpsc_shl_ivec2_int(x0: vec2<i32>, x1: i32) -> vec2<i32> {"));
+        assert!(wgsl.contains("fn mpsc_bitand_ivec2_int(x0: vec2<i32>, x1: i32) -> vec2<i32> {"));
+        assert!(wgsl.contains("while (i < 10) {"));
+    }
+
+    #[test]
+    fn modules() {
+        let wgsl = generate_and_validate(
+            r#"
+            mod shapes {
+                const RADIUS: float = 0.5;
+                struct Circle {
+                    center: vec2
+                }
+                struct Unused {
+                    x: float
+                }
+                impl Circle {
+                    fn new(center: vec2) -> Circle {
+                        let circle: Circle;
+                        circle.center = center;
+                        return circle;
+                    }
+                    fn distance(self, pos: vec2) -> float {
+                        return length(pos - self.center) - RADIUS;
+                    }
+                }
+                fn unused() -> Unused {
+                    let unused: Unused;
+                    unused.x = RADIUS;
+                    return unused;
+                }
+                mod util {
+                    fn scale(v: vec2) -> vec2 {
+                        return v * 2.0;
+                    }
+                }
+            }
+            mod color {
+                use shapes::util::*;
+                fn gray(v: float) -> vec4 {
+                    return vec4(v, v, v, 1.0);
+                }
+            }
+            use shapes::Circle;
+            use color::{gray};
+            geometry geom: vec2;
+            fn vertex() -> vec4 {
+                return vec4(shapes::util::scale(geom), 0., 1.);
+            }
+            fn pixel() -> vec4 {
+                let circle = Circle::new(vec2(0.5));
+                let RADIUS = circle.distance(geom);
+                return gray(RADIUS);
+            }
+            "#,
+        );
+        assert!(wgsl.contains("struct mpsc_m6shapes6Circle {"), "{}", wgsl);
+        assert!(wgsl.contains("fn mpsc_m6shapes6Circle8distance(mpsc_param_self: mpsc_m6shapes6Circle,"), "{}", wgsl);
+        assert!(wgsl.contains("fn mpsc_m5color4gray(mpsc_param_v: f32) -> vec4<f32> {"), "{}", wgsl);
+        assert!(wgsl.contains("var RADIUS: f32 = mpsc_m6shapes6Circle8distance(circle, geom);"), "{}", wgsl);
+        assert!(wgsl.contains("mpsc_m6shapes4util5scale(geom)"), "{}", wgsl);
+        assert!(!wgsl.contains("Unused"), "{}", wgsl);
+        assert!(!wgsl.contains("mpsc_m6shapes6unused"), "{}", wgsl);
+    }
+}
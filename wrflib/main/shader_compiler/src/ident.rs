@@ -39,6 +39,20 @@ impl Ident {
         })
     }
 
+    /// The name of an item called `self` inside of `module`, like `df::circle`.
+    pub(crate) fn in_module(self, module: Option<Ident>) -> Ident {
+        match module {
+            Some(module) => Ident::new(format!("{}::{}", module, self)),
+            None => self,
+        }
+    }
+
+    /// The module that an item called `self` is in, e.g. `df` for `df::circle`, or [`None`] for the top level.
+    pub(crate) fn module(self) -> Option<Ident> {
+        let string = self.to_string();
+        string.rfind("::").map(|index| Ident::new(&string[..index]))
+    }
+
     pub fn with<F, R>(self, f: F) -> R
     where
         F: FnOnce(&str) -> R,
@@ -109,27 +123,32 @@ impl Interner {
     }
 }
 
-/// Represents a path like `self::Something` or `Something::method`.
+/// Represents a path like `self::Something`, `Something::method` or `module::Something::method`.
+///
+/// After [`crate::resolve`], paths have at most two segments: the name of an item (where items in modules have
+/// names like `module::Something`), optionally followed by the name of a method.
 #[derive(Clone, Default, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub(crate) struct IdentPath {
-    segs: [Ident; 2],
+    segs: [Ident; 4],
     len: usize,
 }
 
 impl IdentPath {
     pub(crate) fn from_ident(ident: Ident) -> Self {
-        IdentPath { segs: [ident, Ident::default()], len: 1 }
+        IdentPath { segs: [ident, Ident::default(), Ident::default(), Ident::default()], len: 1 }
     }
 
     pub(crate) fn from_two_idents(ident1: Ident, ident2: Ident) -> Self {
-        IdentPath { segs: [ident1, ident2], len: 2 }
+        Self::from_two(ident1, ident2)
     }
 
+    /// The name of a function or method, like `Circle::distance`, or `shapes::Circle::distance` in a module.
+    /// Generators write names with `::` using [`crate::generate::write_module_item_ident`].
     pub(crate) fn to_struct_fn_ident(&self) -> Ident {
         let mut s = String::new();
         for i in 0..self.len {
             if i != 0 {
-                write!(s, "_").unwrap();
+                write!(s, "::").unwrap();
             }
             self.segs[i].with(|string| write!(s, "{}", string)).unwrap()
         }
@@ -137,11 +156,11 @@ impl IdentPath {
     }
 
     pub(crate) fn from_str(value: &str) -> Self {
-        IdentPath { segs: [Ident::new(value), Ident::default()], len: 1 }
+        Self::from_ident(Ident::new(value))
     }
 
     pub(crate) fn push(&mut self, ident: Ident) -> bool {
-        if self.len >= 4 {
+        if self.len >= self.segs.len() {
             return false;
         }
         self.segs[self.len] = ident;
@@ -150,7 +169,11 @@ impl IdentPath {
     }
 
     pub(crate) fn from_two(one: Ident, two: Ident) -> Self {
-        IdentPath { segs: [one, two], len: 2 }
+        IdentPath { segs: [one, two, Ident::default(), Ident::default()], len: 2 }
+    }
+
+    pub(crate) fn segs(&self) -> &[Ident] {
+        &self.segs[..self.len]
     }
 
     pub(crate) fn get_single(&self) -> Option<Ident> {
@@ -0,0 +1,1711 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Runs a [`ShaderAst`] directly on the CPU, instead of generating code for a GPU.
+//!
+//! This is used by the software rasterizer in `wrflib` (the `headless` feature). It follows the
+//! same conventions as [`crate::generate_glsl`]: geometries, instances, and uniforms are read from
+//! flat `f32` slices in declaration order, and whatever the pixel shader needs from the vertex
+//! shader is packed into a flat list of varyings (like `mpsc_packed_varying_N`).
+//!
+//! Matrices are column-major, just like [`crate::math::Mat4`].
+//!
+//! Note that this is a plain tree-walking interpreter, so it's slow. It's meant for tests and
+//! screenshots, not for running actual applications.
+
+use crate::env::VarKind;
+use crate::ident::{Ident, IdentPath};
+use crate::lit::Lit;
+use crate::shaderast::*;
+use crate::swizzle::Swizzle;
+use crate::ty::{Ty, TyLit};
+use crate::val::Val;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+/// Provides texture data to a [`ShaderInterpreter`] whenever a shader calls `sample2d`,
+/// `sample3d`, or `sample_cube`.
+pub trait TextureSampler {
+    /// Sample texture number `texture_index` (counting `texture` declarations in order) at
+    /// normalized coordinates `(x, y)`, where `(0, 0)` is the start of the first row of the
+    /// image. Should return RGBA.
+    fn sample_2d(&self, texture_index: usize, x: f32, y: f32) -> [f32; 4];
+
+    /// Like [`TextureSampler::sample_2d`], but for a `texture3D`, where `z` selects the slice.
+    /// Returns transparent black by default.
+    fn sample_3d(&self, _texture_index: usize, _x: f32, _y: f32, _z: f32) -> [f32; 4] {
+        [0.0; 4]
+    }
+
+    /// Sample a `textureCube` in direction `(x, y, z)`, which doesn't have to be normalized.
+    /// Returns transparent black by default.
+    fn sample_cube(&self, _texture_index: usize, _x: f32, _y: f32, _z: f32) -> [f32; 4] {
+        [0.0; 4]
+    }
+}
+
+/// Runs the `vertex()` and `pixel()` functions of a [`ShaderAst`].
+///
+/// Set the uniforms using [`ShaderInterpreter::set_uniforms`], and then call
+/// [`ShaderInterpreter::vertex`] for every vertex, and [`ShaderInterpreter::pixel`] for every
+/// pixel.
+pub struct ShaderInterpreter {
+    program: Program,
+    globals: Vec<Value>,
+    locals: Vec<(Ident, Value)>,
+    swizzles: IdentMap<Ident, Vec<usize>>,
+    recorded_dx: Vec<Value>,
+    recorded_dy: Vec<Value>,
+}
+
+impl ShaderInterpreter {
+    /// Prepares a [`ShaderAst`] for interpretation. The [`ShaderAst`] must have been analysed
+    /// already, which is always the case when it comes out of
+    /// [`crate::generate_shader_ast::ShaderAstGenerator::generate_shader_ast`].
+    pub fn new(shader: ShaderAst) -> Self {
+        let program = Program::new(shader);
+        let mut interpreter = Self {
+            globals: vec![Value::Void; program.global_slots.len()],
+            program,
+            locals: Vec::new(),
+            swizzles: IdentMap::default(),
+            recorded_dx: Vec::new(),
+            recorded_dy: Vec::new(),
+        };
+        for (slot, decl_index) in interpreter.program.const_slots.clone() {
+            let expr = match &interpreter.program.shader.decls[decl_index] {
+                Decl::Const(decl) => decl.expr.clone(),
+                _ => panic!(),
+            };
+            interpreter.globals[slot] = interpreter.executor(&NoTextures, Derivatives::Ignore).eval_expr(&expr);
+        }
+        for slot in interpreter.program.texture_slots.clone() {
+            interpreter.globals[slot.0] = Value::Texture(slot.1);
+        }
+        for (slot, ty) in interpreter.program.uniform_slots.iter().flatten() {
+            interpreter.globals[*slot] = interpreter.program.zero_value(ty);
+        }
+        interpreter
+    }
+
+    /// The number of floats that [`ShaderInterpreter::vertex`] writes, and that
+    /// [`ShaderInterpreter::pixel`] expects.
+    pub fn packed_varyings_size(&self) -> usize {
+        self.program.packed_varyings_size
+    }
+
+    /// Set all uniforms, per block. Each slice must contain the uniforms of that block in
+    /// declaration order, just like how the GPU backends pack them.
+    pub fn set_uniforms(&mut self, pass_uniforms: &[f32], view_uniforms: &[f32], draw_uniforms: &[f32], user_uniforms: &[f32]) {
+        for (block_index, data) in [pass_uniforms, view_uniforms, draw_uniforms, user_uniforms].iter().enumerate() {
+            unpack_slots(&mut self.globals, &self.program.uniform_slots[block_index], data);
+        }
+    }
+
+    /// Run `vertex()` for the given (packed) geometry and instance data. Writes the varyings
+    /// that [`ShaderInterpreter::pixel`] needs into `varyings`, and returns the clip space
+    /// position.
+    pub fn vertex(&mut self, geometry: &[f32], instance: &[f32], sampler: &dyn TextureSampler, varyings: &mut [f32]) -> [f32; 4] {
+        for slot in 0..self.program.varying_decl_slots.len() {
+            let (slot, ty) = &self.program.varying_decl_slots[slot];
+            self.globals[*slot] = self.program.zero_value(ty);
+        }
+        unpack_slots(&mut self.globals, &self.program.geometry_slots, geometry);
+        unpack_slots(&mut self.globals, &self.program.instance_slots, instance);
+
+        let vertex_fn = self.program.vertex_fn;
+        let position = self.executor(sampler, Derivatives::Ignore).call_fn(vertex_fn, Vec::new()).0;
+
+        let mut offset = 0;
+        for (slot, _ty) in &self.program.packed_varying_slots {
+            offset += self.globals[*slot].write_components(&mut varyings[offset..]);
+        }
+        position.to_vec4()
+    }
+
+    /// Run `pixel()` for the given (interpolated) varyings, and return the resulting color.
+    ///
+    /// In order to support `dFdx` and `dFdy`, you also have to pass in the varyings for the
+    /// pixel to the right, and for the pixel above (in the same coordinate system as OpenGL,
+    /// where y points up). Like on GPUs, these derivatives are only accurate when control flow
+    /// is the same for all three pixels.
+    pub fn pixel(
+        &mut self,
+        varyings: &[f32],
+        varyings_dx: &[f32],
+        varyings_dy: &[f32],
+        sampler: &dyn TextureSampler,
+    ) -> [f32; 4] {
+        let pixel_fn = self.program.pixel_fn;
+        if self.program.pixel_uses_derivatives {
+            let mut recorded_dx = std::mem::take(&mut self.recorded_dx);
+            let mut recorded_dy = std::mem::take(&mut self.recorded_dy);
+            recorded_dx.clear();
+            recorded_dy.clear();
+
+            self.unpack_varyings(varyings_dx);
+            self.executor(sampler, Derivatives::Record(&mut recorded_dx)).call_fn(pixel_fn, Vec::new());
+            self.unpack_varyings(varyings_dy);
+            self.executor(sampler, Derivatives::Record(&mut recorded_dy)).call_fn(pixel_fn, Vec::new());
+
+            self.unpack_varyings(varyings);
+            let color = self
+                .executor(sampler, Derivatives::Replay { dx: &recorded_dx, dy: &recorded_dy, index: 0 })
+                .call_fn(pixel_fn, Vec::new())
+                .0;
+
+            self.recorded_dx = recorded_dx;
+            self.recorded_dy = recorded_dy;
+            color.to_vec4()
+        } else {
+            self.unpack_varyings(varyings);
+            self.executor(sampler, Derivatives::Ignore).call_fn(pixel_fn, Vec::new()).0.to_vec4()
+        }
+    }
+
+    fn unpack_varyings(&mut self, varyings: &[f32]) {
+        unpack_slots(&mut self.globals, &self.program.packed_varying_slots, varyings);
+    }
+
+    fn executor<'a>(&'a mut self, sampler: &'a dyn TextureSampler, derivatives: Derivatives<'a>) -> Executor<'a> {
+        self.locals.clear();
+        Executor {
+            program: &self.program,
+            globals: &mut self.globals,
+            locals: &mut self.locals,
+            frame_base: 0,
+            swizzles: &mut self.swizzles,
+            sampler,
+            derivatives,
+        }
+    }
+}
+
+fn unpack_slots(globals: &mut [Value], slots: &[(usize, Ty)], data: &[f32]) {
+    let mut offset = 0;
+    for (slot, ty) in slots {
+        let size = ty.size();
+        if offset + size > data.len() {
+            break;
+        }
+        globals[*slot] = Value::from_components(ty, &data[offset..offset + size]);
+        offset += size;
+    }
+}
+
+struct NoTextures;
+
+impl TextureSampler for NoTextures {
+    fn sample_2d(&self, _texture_index: usize, _x: f32, _y: f32) -> [f32; 4] {
+        [0.0; 4]
+    }
+}
+
+/// [`Ident`]s are just indices, so we don't need [`HashMap`]'s default (DoS-resistant, but slow)
+/// hasher for them. Lookups by [`Ident`] are in the hottest paths of the interpreter.
+#[derive(Default)]
+struct IdentHasher(u64);
+
+impl Hasher for IdentHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
+
+type IdentMap<K, V> = HashMap<K, V, BuildHasherDefault<IdentHasher>>;
+
+/// Everything about a [`ShaderAst`] that doesn't change between invocations.
+struct Program {
+    shader: ShaderAst,
+    fn_decls: IdentMap<IdentPath, usize>,
+    struct_decls: IdentMap<Ident, usize>,
+    builtins: IdentMap<Ident, BuiltinFn>,
+    global_slots: IdentMap<Ident, usize>,
+    const_slots: Vec<(usize, usize)>,
+    texture_slots: Vec<(usize, usize)>,
+    /// Uniform slots for the "pass", "view", and "draw" blocks, and user uniforms, in that order.
+    uniform_slots: [Vec<(usize, Ty)>; 4],
+    geometry_slots: Vec<(usize, Ty)>,
+    instance_slots: Vec<(usize, Ty)>,
+    varying_decl_slots: Vec<(usize, Ty)>,
+    packed_varying_slots: Vec<(usize, Ty)>,
+    packed_varyings_size: usize,
+    vertex_fn: usize,
+    pixel_fn: usize,
+    pixel_uses_derivatives: bool,
+}
+
+impl Program {
+    fn new(shader: ShaderAst) -> Self {
+        let mut program = Program {
+            fn_decls: IdentMap::default(),
+            struct_decls: IdentMap::default(),
+            builtins: BuiltinFn::generate_map(),
+            global_slots: IdentMap::default(),
+            const_slots: Vec::new(),
+            texture_slots: Vec::new(),
+            uniform_slots: Default::default(),
+            geometry_slots: Vec::new(),
+            instance_slots: Vec::new(),
+            varying_decl_slots: Vec::new(),
+            packed_varying_slots: Vec::new(),
+            packed_varyings_size: 0,
+            vertex_fn: 0,
+            pixel_fn: 0,
+            pixel_uses_derivatives: false,
+            shader,
+        };
+
+        for (decl_index, decl) in program.shader.decls.iter().enumerate() {
+            let ty_of = |ty_expr: &crate::ty::TyExpr| ty_expr.ty.borrow().clone().unwrap();
+            let slot = program.global_slots.len();
+            match decl {
+                Decl::Fn(decl) => {
+                    program.fn_decls.insert(decl.ident_path, decl_index);
+                    continue;
+                }
+                Decl::Struct(decl) => {
+                    program.struct_decls.insert(decl.ident, decl_index);
+                    continue;
+                }
+                Decl::Const(decl) => {
+                    program.global_slots.insert(decl.ident, slot);
+                    program.const_slots.push((slot, decl_index));
+                }
+                Decl::Texture(decl) => {
+                    program.global_slots.insert(decl.ident, slot);
+                    program.texture_slots.push((slot, program.texture_slots.len()));
+                }
+                Decl::Uniform(decl) => {
+                    program.global_slots.insert(decl.ident, slot);
+                    let block_index = match decl.block_ident {
+                        Some(block_ident) => block_ident.with(|string| match string {
+                            "pass" => 0,
+                            "view" => 1,
+                            "draw" => 2,
+                            _ => 3,
+                        }),
+                        None => 3,
+                    };
+                    program.uniform_slots[block_index].push((slot, ty_of(&decl.ty_expr)));
+                }
+                Decl::Geometry(decl) => {
+                    program.global_slots.insert(decl.ident, slot);
+                    program.geometry_slots.push((slot, ty_of(&decl.ty_expr)));
+                    if decl.is_used_in_fragment_shader.get().unwrap() {
+                        program.packed_varying_slots.push((slot, ty_of(&decl.ty_expr)));
+                    }
+                }
+                Decl::Instance(decl) => {
+                    program.global_slots.insert(decl.ident, slot);
+                    program.instance_slots.push((slot, ty_of(&decl.ty_expr)));
+                    if decl.is_used_in_fragment_shader.get().unwrap() {
+                        program.packed_varying_slots.push((slot, ty_of(&decl.ty_expr)));
+                    }
+                }
+                Decl::Varying(decl) => {
+                    program.global_slots.insert(decl.ident, slot);
+                    program.varying_decl_slots.push((slot, ty_of(&decl.ty_expr)));
+                    program.packed_varying_slots.push((slot, ty_of(&decl.ty_expr)));
+                }
+            }
+        }
+        program.packed_varyings_size = program.packed_varying_slots.iter().map(|(_, ty)| ty.size()).sum();
+        program.vertex_fn = program.fn_decls[&IdentPath::from_str("vertex")];
+        program.pixel_fn = program.fn_decls[&IdentPath::from_str("pixel")];
+
+        let derivative_idents = [Ident::new("dFdx"), Ident::new("dFdy")];
+        program.pixel_uses_derivatives = program.shader.decls.iter().any(|decl| match decl {
+            Decl::Fn(decl) => {
+                decl.is_used_in_fragment_shader.get().unwrap_or(false) && block_calls_any(&decl.block, &derivative_idents)
+            }
+            _ => false,
+        });
+        program
+    }
+
+    fn fn_decl(&self, decl_index: usize) -> &FnDecl {
+        match &self.shader.decls[decl_index] {
+            Decl::Fn(decl) => decl,
+            _ => panic!(),
+        }
+    }
+
+    fn struct_decl(&self, ident: Ident) -> &StructDecl {
+        match &self.shader.decls[self.struct_decls[&ident]] {
+            Decl::Struct(decl) => decl,
+            _ => panic!(),
+        }
+    }
+
+    fn zero_value(&self, ty: &Ty) -> Value {
+        match ty {
+            Ty::Void => Value::Void,
+            Ty::Bool => Value::Bool(false),
+            Ty::Int => Value::Int(0),
+            Ty::Float => Value::Float(0.0),
+            Ty::Bvec2 => Value::Bvec(2, [false; 4]),
+            Ty::Bvec3 => Value::Bvec(3, [false; 4]),
+            Ty::Bvec4 => Value::Bvec(4, [false; 4]),
+            Ty::Ivec2 => Value::Ivec(2, [0; 4]),
+            Ty::Ivec3 => Value::Ivec(3, [0; 4]),
+            Ty::Ivec4 => Value::Ivec(4, [0; 4]),
+            Ty::Vec2 => Value::Vec(2, [0.0; 4]),
+            Ty::Vec3 => Value::Vec(3, [0.0; 4]),
+            Ty::Vec4 => Value::Vec(4, [0.0; 4]),
+            Ty::Mat2 => Value::Mat(2, [0.0; 16]),
+            Ty::Mat3 => Value::Mat(3, [0.0; 16]),
+            Ty::Mat4 => Value::Mat(4, [0.0; 16]),
+            Ty::Texture2D | Ty::TextureCube | Ty::Texture3D => Value::Texture(0),
+            Ty::Array { elem_ty, len } => Value::Array(vec![self.zero_value(elem_ty); *len]),
+            Ty::Struct { ident } => Value::Struct(
+                self.struct_decl(*ident)
+                    .fields
+                    .iter()
+                    .map(|field| (field.ident, self.zero_value(field.ty_expr.ty.borrow().as_ref().unwrap())))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn block_calls_any(block: &Block, idents: &[Ident]) -> bool {
+    block.stmts.iter().any(|stmt| match stmt {
+        Stmt::Break { .. } | Stmt::Continue { .. } => false,
+        Stmt::For { from_expr, to_expr, step_expr, block, .. } => {
+            expr_calls_any(from_expr, idents)
+                || expr_calls_any(to_expr, idents)
+                || step_expr.as_ref().is_some_and(|expr| expr_calls_any(expr, idents))
+                || block_calls_any(block, idents)
+        }
+        Stmt::If { expr, block_if_true, block_if_false, .. } => {
+            expr_calls_any(expr, idents)
+                || block_calls_any(block_if_true, idents)
+                || block_if_false.as_ref().is_some_and(|block| block_calls_any(block, idents))
+        }
+        Stmt::Let { expr, .. } | Stmt::Return { expr, .. } => expr.as_ref().is_some_and(|expr| expr_calls_any(expr, idents)),
+        Stmt::Loop { block, .. } => block_calls_any(block, idents),
+        Stmt::Match { expr, arms, .. } => {
+            expr_calls_any(expr, idents) || arms.iter().any(|arm| block_calls_any(&arm.block, idents))
+        }
+        Stmt::While { expr, block, .. } => expr_calls_any(expr, idents) || block_calls_any(block, idents),
+        Stmt::Expr { expr, .. } => expr_calls_any(expr, idents),
+    })
+}
+
+fn expr_calls_any(expr: &Expr, idents: &[Ident]) -> bool {
+    match &expr.kind {
+        ExprKind::Cond { expr, expr_if_true, expr_if_false, .. } => {
+            expr_calls_any(expr, idents) || expr_calls_any(expr_if_true, idents) || expr_calls_any(expr_if_false, idents)
+        }
+        ExprKind::Bin { left_expr, right_expr, .. } => expr_calls_any(left_expr, idents) || expr_calls_any(right_expr, idents),
+        ExprKind::Un { expr, .. } | ExprKind::Field { expr, .. } => expr_calls_any(expr, idents),
+        ExprKind::Index { expr, index_expr, .. } => expr_calls_any(expr, idents) || expr_calls_any(index_expr, idents),
+        ExprKind::MethodCall { arg_exprs, .. } | ExprKind::ConsCall { arg_exprs, .. } => {
+            arg_exprs.iter().any(|arg_expr| expr_calls_any(arg_expr, idents))
+        }
+        ExprKind::Call { ident_path, arg_exprs, .. } => {
+            ident_path.get_single().is_some_and(|ident| idents.contains(&ident))
+                || arg_exprs.iter().any(|arg_expr| expr_calls_any(arg_expr, idents))
+        }
+        ExprKind::Var { .. } | ExprKind::Lit { .. } => false,
+    }
+}
+
+/// A runtime value. Vectors and matrices have a fixed-size backing array, with the actual
+/// dimension stored separately, so we don't have to allocate all the time.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Void,
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Bvec(usize, [bool; 4]),
+    Ivec(usize, [i32; 4]),
+    Vec(usize, [f32; 4]),
+    /// Column-major, with the number of columns (and rows) first.
+    Mat(usize, [f32; 16]),
+    Texture(usize),
+    Array(Vec<Value>),
+    Struct(Vec<(Ident, Value)>),
+}
+
+impl Value {
+    fn from_val(val: &Val) -> Value {
+        match *val {
+            Val::Bool(val) => Value::Bool(val),
+            Val::Int(val) => Value::Int(val),
+            Val::Float(val) => Value::Float(val),
+            Val::Vec4(val) => Value::Vec(4, [val.x, val.y, val.z, val.w]),
+        }
+    }
+
+    fn from_components(ty: &Ty, components: &[f32]) -> Value {
+        let mut floats = [0.0; 4];
+        let mut ints = [0; 4];
+        let mut bools = [false; 4];
+        for (index, component) in components.iter().take(4).enumerate() {
+            floats[index] = *component;
+            ints[index] = *component as i32;
+            bools[index] = *component != 0.0;
+        }
+        match ty {
+            Ty::Bool => Value::Bool(bools[0]),
+            Ty::Int => Value::Int(ints[0]),
+            Ty::Float => Value::Float(floats[0]),
+            Ty::Bvec2 => Value::Bvec(2, bools),
+            Ty::Bvec3 => Value::Bvec(3, bools),
+            Ty::Bvec4 => Value::Bvec(4, bools),
+            Ty::Ivec2 => Value::Ivec(2, ints),
+            Ty::Ivec3 => Value::Ivec(3, ints),
+            Ty::Ivec4 => Value::Ivec(4, ints),
+            Ty::Vec2 => Value::Vec(2, floats),
+            Ty::Vec3 => Value::Vec(3, floats),
+            Ty::Vec4 => Value::Vec(4, floats),
+            Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+                let dim = mat_dim(ty);
+                let mut mat = [0.0; 16];
+                mat[..dim * dim].copy_from_slice(&components[..dim * dim]);
+                Value::Mat(dim, mat)
+            }
+            Ty::Array { elem_ty, len } => {
+                let elem_size = elem_ty.size();
+                Value::Array(
+                    (0..*len)
+                        .map(|index| Value::from_components(elem_ty, &components[index * elem_size..(index + 1) * elem_size]))
+                        .collect(),
+                )
+            }
+            _ => panic!("unexpected type for packed data: {}", ty),
+        }
+    }
+
+    /// Writes all components as floats, and returns how many were written.
+    fn write_components(&self, out: &mut [f32]) -> usize {
+        match self {
+            Value::Bool(val) => {
+                out[0] = if *val { 1.0 } else { 0.0 };
+                1
+            }
+            Value::Int(val) => {
+                out[0] = *val as f32;
+                1
+            }
+            Value::Float(val) => {
+                out[0] = *val;
+                1
+            }
+            Value::Bvec(len, vals) => {
+                for index in 0..*len {
+                    out[index] = if vals[index] { 1.0 } else { 0.0 };
+                }
+                *len
+            }
+            Value::Ivec(len, vals) => {
+                for index in 0..*len {
+                    out[index] = vals[index] as f32;
+                }
+                *len
+            }
+            Value::Vec(len, vals) => {
+                out[..*len].copy_from_slice(&vals[..*len]);
+                *len
+            }
+            Value::Mat(dim, vals) => {
+                out[..dim * dim].copy_from_slice(&vals[..dim * dim]);
+                dim * dim
+            }
+            Value::Array(vals) => {
+                let mut offset = 0;
+                for val in vals {
+                    offset += val.write_components(&mut out[offset..]);
+                }
+                offset
+            }
+            Value::Void | Value::Texture(_) | Value::Struct(_) => panic!("value can't be flattened: {:?}", self),
+        }
+    }
+
+    fn push_components(&self, out: &mut Vec<f32>) {
+        let mut buffer = [0.0; 16];
+        let len = self.write_components(&mut buffer);
+        out.extend_from_slice(&buffer[..len]);
+    }
+
+    fn to_bool(&self) -> bool {
+        match self {
+            Value::Bool(val) => *val,
+            _ => panic!("expected bool, got {:?}", self),
+        }
+    }
+
+    fn to_int(&self) -> i32 {
+        match self {
+            Value::Int(val) => *val,
+            _ => panic!("expected int, got {:?}", self),
+        }
+    }
+
+    fn to_float(&self) -> f32 {
+        match self {
+            Value::Float(val) => *val,
+            Value::Int(val) => *val as f32,
+            _ => panic!("expected float, got {:?}", self),
+        }
+    }
+
+    fn to_vec4(&self) -> [f32; 4] {
+        match self {
+            Value::Vec(4, vals) => *vals,
+            _ => panic!("expected vec4, got {:?}", self),
+        }
+    }
+
+    /// Returns the components of a scalar or vector as floats, plus the number of components
+    /// (or `None` for scalars, which can be broadcast to any vector size).
+    fn lanes(&self) -> (Option<usize>, [f32; 4]) {
+        match self {
+            Value::Float(val) => (None, [*val; 4]),
+            Value::Int(val) => (None, [*val as f32; 4]),
+            Value::Bool(val) => (None, [if *val { 1.0 } else { 0.0 }; 4]),
+            Value::Vec(len, vals) => (Some(*len), *vals),
+            Value::Ivec(len, vals) => (Some(*len), [vals[0] as f32, vals[1] as f32, vals[2] as f32, vals[3] as f32]),
+            Value::Bvec(len, vals) => {
+                let mut lanes = [0.0; 4];
+                for index in 0..4 {
+                    lanes[index] = if vals[index] { 1.0 } else { 0.0 };
+                }
+                (Some(*len), lanes)
+            }
+            _ => panic!("expected scalar or vector, got {:?}", self),
+        }
+    }
+}
+
+fn mat_dim(ty: &Ty) -> usize {
+    match ty {
+        Ty::Mat2 => 2,
+        Ty::Mat3 => 3,
+        Ty::Mat4 => 4,
+        _ => panic!(),
+    }
+}
+
+/// Applies `f` component-wise to floats and float vectors.
+fn map_float(value: &Value, f: impl Fn(f32) -> f32) -> Value {
+    match value {
+        Value::Float(val) => Value::Float(f(*val)),
+        Value::Vec(len, vals) => Value::Vec(*len, [f(vals[0]), f(vals[1]), f(vals[2]), f(vals[3])]),
+        Value::Mat(dim, vals) => {
+            let mut out = [0.0; 16];
+            for index in 0..dim * dim {
+                out[index] = f(vals[index]);
+            }
+            Value::Mat(*dim, out)
+        }
+        _ => panic!("expected float type, got {:?}", value),
+    }
+}
+
+/// Applies `f` component-wise to scalars and vectors, broadcasting scalars.
+fn zip_float(a: &Value, b: &Value, f: impl Fn(f32, f32) -> f32) -> Value {
+    match (a, b) {
+        (Value::Mat(dim, a_vals), Value::Mat(_, b_vals)) => {
+            let mut out = [0.0; 16];
+            for index in 0..dim * dim {
+                out[index] = f(a_vals[index], b_vals[index]);
+            }
+            Value::Mat(*dim, out)
+        }
+        (Value::Mat(_, _), _) => {
+            let b = b.to_float();
+            map_float(a, |a| f(a, b))
+        }
+        (_, Value::Mat(_, _)) => {
+            let a = a.to_float();
+            map_float(b, |b| f(a, b))
+        }
+        _ => {
+            let (a_len, a_lanes) = a.lanes();
+            let (b_len, b_lanes) = b.lanes();
+            let mut out = [0.0; 4];
+            for index in 0..4 {
+                out[index] = f(a_lanes[index], b_lanes[index]);
+            }
+            match a_len.or(b_len) {
+                Some(len) => Value::Vec(len, out),
+                None => Value::Float(out[0]),
+            }
+        }
+    }
+}
+
+/// Like [`zip_float`], but with three arguments.
+fn zip3_float(a: &Value, b: &Value, c: &Value, f: impl Fn(f32, f32, f32) -> f32) -> Value {
+    let (a_len, a_lanes) = a.lanes();
+    let (b_len, b_lanes) = b.lanes();
+    let (c_len, c_lanes) = c.lanes();
+    let mut out = [0.0; 4];
+    for index in 0..4 {
+        out[index] = f(a_lanes[index], b_lanes[index], c_lanes[index]);
+    }
+    match a_len.or(b_len).or(c_len) {
+        Some(len) => Value::Vec(len, out),
+        None => Value::Float(out[0]),
+    }
+}
+
+/// Applies `f` component-wise to ints and int vectors, broadcasting scalars.
+fn zip_int(a: &Value, b: &Value, f: impl Fn(i32, i32) -> i32) -> Value {
+    let lanes = |value: &Value| match value {
+        Value::Int(val) => (None, [*val; 4]),
+        Value::Ivec(len, vals) => (Some(*len), *vals),
+        _ => panic!("expected int type, got {:?}", value),
+    };
+    let (a_len, a_lanes) = lanes(a);
+    let (b_len, b_lanes) = lanes(b);
+    let mut out = [0; 4];
+    for index in 0..4 {
+        out[index] = f(a_lanes[index], b_lanes[index]);
+    }
+    match a_len.or(b_len) {
+        Some(len) => Value::Ivec(len, out),
+        None => Value::Int(out[0]),
+    }
+}
+
+/// Component-wise comparison, returning a bool vector.
+fn compare(a: &Value, b: &Value, f: impl Fn(f32, f32) -> bool) -> Value {
+    let (len, a_lanes) = a.lanes();
+    let (_, b_lanes) = b.lanes();
+    let mut out = [false; 4];
+    for index in 0..4 {
+        out[index] = f(a_lanes[index], b_lanes[index]);
+    }
+    Value::Bvec(len.unwrap(), out)
+}
+
+fn dot(a: &Value, b: &Value) -> f32 {
+    let (len, a_lanes) = a.lanes();
+    let (_, b_lanes) = b.lanes();
+    (0..len.unwrap_or(1)).map(|index| a_lanes[index] * b_lanes[index]).sum()
+}
+
+fn mul(a: &Value, b: &Value) -> Value {
+    match (a, b) {
+        (Value::Mat(dim, a_vals), Value::Mat(_, b_vals)) => {
+            let dim = *dim;
+            let mut out = [0.0; 16];
+            for col in 0..dim {
+                for row in 0..dim {
+                    out[col * dim + row] = (0..dim).map(|k| a_vals[k * dim + row] * b_vals[col * dim + k]).sum();
+                }
+            }
+            Value::Mat(dim, out)
+        }
+        (Value::Mat(dim, mat), Value::Vec(_, vec)) => {
+            let dim = *dim;
+            let mut out = [0.0; 4];
+            for (row, out) in out.iter_mut().enumerate().take(dim) {
+                *out = (0..dim).map(|col| mat[col * dim + row] * vec[col]).sum();
+            }
+            Value::Vec(dim, out)
+        }
+        (Value::Vec(_, vec), Value::Mat(dim, mat)) => {
+            let dim = *dim;
+            let mut out = [0.0; 4];
+            for (col, out) in out.iter_mut().enumerate().take(dim) {
+                *out = (0..dim).map(|row| vec[row] * mat[col * dim + row]).sum();
+            }
+            Value::Vec(dim, out)
+        }
+        (Value::Int(_) | Value::Ivec(_, _), _) => zip_int(a, b, i32::wrapping_mul),
+        _ => zip_float(a, b, |a, b| a * b),
+    }
+}
+
+fn arith(op: BinOp, a: &Value, b: &Value) -> Value {
+    let is_int = matches!(a, Value::Int(_) | Value::Ivec(_, _));
+    match op {
+        BinOp::Add | BinOp::AddAssign if is_int => zip_int(a, b, i32::wrapping_add),
+        BinOp::Add | BinOp::AddAssign => zip_float(a, b, |a, b| a + b),
+        BinOp::Sub | BinOp::SubAssign if is_int => zip_int(a, b, i32::wrapping_sub),
+        BinOp::Sub | BinOp::SubAssign => zip_float(a, b, |a, b| a - b),
+        BinOp::Mul | BinOp::MulAssign => mul(a, b),
+        BinOp::Div | BinOp::DivAssign if is_int => zip_int(a, b, |a, b| if b == 0 { 0 } else { a.wrapping_div(b) }),
+        BinOp::Div | BinOp::DivAssign => zip_float(a, b, |a, b| a / b),
+        BinOp::Rem => zip_int(a, b, |a, b| if b == 0 { 0 } else { a.wrapping_rem(b) }),
+        BinOp::BitOr => zip_int(a, b, |a, b| a | b),
+        BinOp::BitXor => zip_int(a, b, |a, b| a ^ b),
+        BinOp::BitAnd => zip_int(a, b, |a, b| a & b),
+        BinOp::Shl => zip_int(a, b, |a, b| a.wrapping_shl(b as u32)),
+        BinOp::Shr => zip_int(a, b, |a, b| a.wrapping_shr(b as u32)),
+        _ => panic!("not an arithmetic operator: {:?}", op),
+    }
+}
+
+fn inverse_mat4(m: &[f32; 16]) -> [f32; 16] {
+    let mut inv = [0.0; 16];
+    inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15] + m[9] * m[7] * m[14] + m[13] * m[6] * m[11]
+        - m[13] * m[7] * m[10];
+    inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15] - m[8] * m[7] * m[14] - m[12] * m[6] * m[11]
+        + m[12] * m[7] * m[10];
+    inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15] + m[8] * m[7] * m[13] + m[12] * m[5] * m[11]
+        - m[12] * m[7] * m[9];
+    inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14] - m[8] * m[6] * m[13] - m[12] * m[5] * m[10]
+        + m[12] * m[6] * m[9];
+    inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15] - m[9] * m[3] * m[14] - m[13] * m[2] * m[11]
+        + m[13] * m[3] * m[10];
+    inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15] + m[8] * m[3] * m[14] + m[12] * m[2] * m[11]
+        - m[12] * m[3] * m[10];
+    inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15] - m[8] * m[3] * m[13] - m[12] * m[1] * m[11]
+        + m[12] * m[3] * m[9];
+    inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14] + m[8] * m[2] * m[13] + m[12] * m[1] * m[10]
+        - m[12] * m[2] * m[9];
+    inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15] + m[5] * m[3] * m[14] + m[13] * m[2] * m[7]
+        - m[13] * m[3] * m[6];
+    inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15] - m[4] * m[3] * m[14] - m[12] * m[2] * m[7]
+        + m[12] * m[3] * m[6];
+    inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15] + m[4] * m[3] * m[13] + m[12] * m[1] * m[7]
+        - m[12] * m[3] * m[5];
+    inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14] - m[4] * m[2] * m[13] - m[12] * m[1] * m[6]
+        + m[12] * m[2] * m[5];
+    inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11] - m[5] * m[3] * m[10] - m[9] * m[2] * m[7]
+        + m[9] * m[3] * m[6];
+    inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11] + m[4] * m[3] * m[10] + m[8] * m[2] * m[7]
+        - m[8] * m[3] * m[6];
+    inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11] - m[4] * m[3] * m[9] - m[8] * m[1] * m[7]
+        + m[8] * m[3] * m[5];
+    inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10] + m[4] * m[2] * m[9] + m[8] * m[1] * m[6]
+        - m[8] * m[2] * m[5];
+    let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+    if det == 0.0 {
+        return inv;
+    }
+    for val in &mut inv {
+        *val /= det;
+    }
+    inv
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BuiltinFn {
+    Abs,
+    Acos,
+    All,
+    Any,
+    Asin,
+    Atan,
+    Ceil,
+    Clamp,
+    Cos,
+    Cross,
+    Degrees,
+    DFdx,
+    DFdy,
+    Distance,
+    Dot,
+    Equal,
+    Exp,
+    Exp2,
+    Faceforward,
+    Floor,
+    Fract,
+    GreaterThan,
+    GreaterThanEqual,
+    Inversesqrt,
+    Inverse,
+    Length,
+    LessThan,
+    LessThanEqual,
+    Log,
+    Log2,
+    MatrixCompMult,
+    Max,
+    Min,
+    Mix,
+    Mod,
+    Normalize,
+    Not,
+    NotEqual,
+    Pow,
+    Radians,
+    Reflect,
+    Refract,
+    Sample2d,
+    Sample3d,
+    SampleCube,
+    Sign,
+    Sin,
+    Smoothstep,
+    Sqrt,
+    Step,
+    Tan,
+    Transpose,
+}
+
+impl BuiltinFn {
+    /// Keep in sync with [`crate::builtin::generate_builtins`].
+    fn generate_map() -> IdentMap<Ident, BuiltinFn> {
+        [
+            ("abs", BuiltinFn::Abs),
+            ("acos", BuiltinFn::Acos),
+            ("all", BuiltinFn::All),
+            ("any", BuiltinFn::Any),
+            ("asin", BuiltinFn::Asin),
+            ("atan", BuiltinFn::Atan),
+            ("ceil", BuiltinFn::Ceil),
+            ("clamp", BuiltinFn::Clamp),
+            ("cos", BuiltinFn::Cos),
+            ("cross", BuiltinFn::Cross),
+            ("degrees", BuiltinFn::Degrees),
+            ("dFdx", BuiltinFn::DFdx),
+            ("dFdy", BuiltinFn::DFdy),
+            ("distance", BuiltinFn::Distance),
+            ("dot", BuiltinFn::Dot),
+            ("equal", BuiltinFn::Equal),
+            ("exp", BuiltinFn::Exp),
+            ("exp2", BuiltinFn::Exp2),
+            ("faceforward", BuiltinFn::Faceforward),
+            ("floor", BuiltinFn::Floor),
+            ("fract", BuiltinFn::Fract),
+            ("greaterThan", BuiltinFn::GreaterThan),
+            ("greaterThanEqual", BuiltinFn::GreaterThanEqual),
+            ("inversesqrt", BuiltinFn::Inversesqrt),
+            ("inverse", BuiltinFn::Inverse),
+            ("length", BuiltinFn::Length),
+            ("lessThan", BuiltinFn::LessThan),
+            ("lessThanEqual", BuiltinFn::LessThanEqual),
+            ("log", BuiltinFn::Log),
+            ("log2", BuiltinFn::Log2),
+            ("matrixCompMult", BuiltinFn::MatrixCompMult),
+            ("max", BuiltinFn::Max),
+            ("min", BuiltinFn::Min),
+            ("mix", BuiltinFn::Mix),
+            ("mod", BuiltinFn::Mod),
+            ("normalize", BuiltinFn::Normalize),
+            ("not", BuiltinFn::Not),
+            ("notEqual", BuiltinFn::NotEqual),
+            ("pow", BuiltinFn::Pow),
+            ("radians", BuiltinFn::Radians),
+            ("reflect", BuiltinFn::Reflect),
+            ("refract", BuiltinFn::Refract),
+            ("sample2d", BuiltinFn::Sample2d),
+            ("sample3d", BuiltinFn::Sample3d),
+            ("sample_cube", BuiltinFn::SampleCube),
+            ("sign", BuiltinFn::Sign),
+            ("sin", BuiltinFn::Sin),
+            ("smoothstep", BuiltinFn::Smoothstep),
+            ("sqrt", BuiltinFn::Sqrt),
+            ("step", BuiltinFn::Step),
+            ("tan", BuiltinFn::Tan),
+            ("transpose", BuiltinFn::Transpose),
+        ]
+        .iter()
+        .map(|(name, builtin)| (Ident::new(*name), *builtin))
+        .collect()
+    }
+}
+
+enum Derivatives<'a> {
+    /// `dFdx` and `dFdy` always return zero.
+    Ignore,
+    /// Record the arguments of all `dFdx` and `dFdy` calls, in order.
+    Record(&'a mut Vec<Value>),
+    /// Subtract the current arguments of `dFdx` and `dFdy` calls from the ones recorded for the
+    /// neighbouring pixels.
+    Replay { dx: &'a [Value], dy: &'a [Value], index: usize },
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+struct Executor<'a> {
+    program: &'a Program,
+    globals: &'a mut Vec<Value>,
+    locals: &'a mut Vec<(Ident, Value)>,
+    /// Index into `locals` where the current function's locals start.
+    frame_base: usize,
+    swizzles: &'a mut IdentMap<Ident, Vec<usize>>,
+    sampler: &'a dyn TextureSampler,
+    derivatives: Derivatives<'a>,
+}
+
+impl<'a> Executor<'a> {
+    /// Returns the return value, and the final values of all parameters (for `inout`).
+    fn call_fn(&mut self, decl_index: usize, args: Vec<Value>) -> (Value, Vec<Value>) {
+        let program = self.program;
+        let decl = program.fn_decl(decl_index);
+        let old_frame_base = self.frame_base;
+        self.frame_base = self.locals.len();
+        for (param, arg) in decl.params.iter().zip(args) {
+            self.locals.push((param.ident, arg));
+        }
+        let ret = match self.exec_block(&decl.block) {
+            Flow::Return(value) => value,
+            _ => Value::Void,
+        };
+        let params = self.locals.drain(self.frame_base..).take(decl.params.len()).map(|(_, value)| value).collect();
+        self.frame_base = old_frame_base;
+        (ret, params)
+    }
+
+    fn exec_block(&mut self, block: &Block) -> Flow {
+        let scope_start = self.locals.len();
+        let mut flow = Flow::Normal;
+        for stmt in &block.stmts {
+            flow = self.exec_stmt(stmt);
+            if !matches!(flow, Flow::Normal) {
+                break;
+            }
+        }
+        self.locals.truncate(scope_start);
+        flow
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Flow {
+        match stmt {
+            Stmt::Break { .. } => Flow::Break,
+            Stmt::Continue { .. } => Flow::Continue,
+            Stmt::For { ident, from_expr, to_expr, step_expr, block, .. } => {
+                self.exec_for_stmt(*ident, from_expr, to_expr, step_expr, block)
+            }
+            Stmt::If { expr, block_if_true, block_if_false, .. } => {
+                if self.eval_expr(expr).to_bool() {
+                    self.exec_block(block_if_true)
+                } else if let Some(block_if_false) = block_if_false {
+                    self.exec_block(block_if_false)
+                } else {
+                    Flow::Normal
+                }
+            }
+            Stmt::Let { ty, ident, expr, .. } => {
+                let value = match expr {
+                    Some(expr) => self.eval_expr(expr),
+                    None => self.program.zero_value(ty.borrow().as_ref().unwrap()),
+                };
+                self.locals.push((*ident, value));
+                Flow::Normal
+            }
+            Stmt::Loop { block, .. } => self.exec_loop_stmt(None, block),
+            Stmt::Match { expr, arms, .. } => {
+                let val = self.eval_expr(expr).to_int();
+                let arm = arms.iter().find(|arm| {
+                    arm.patterns.iter().any(|pattern| match *pattern {
+                        Pattern::Int { val: pattern_val, .. } => pattern_val == val,
+                        Pattern::Wildcard { .. } => true,
+                    })
+                });
+                arm.map_or(Flow::Normal, |arm| self.exec_block(&arm.block))
+            }
+            Stmt::Return { expr, .. } => Flow::Return(expr.as_ref().map_or(Value::Void, |expr| self.eval_expr(expr))),
+            Stmt::While { expr, block, .. } => self.exec_loop_stmt(Some(expr), block),
+            Stmt::Expr { expr, .. } => {
+                self.eval_expr(expr);
+                Flow::Normal
+            }
+        }
+    }
+
+    /// Mirrors the loop that [`crate::generate::BlockGenerator`] writes.
+    fn exec_for_stmt(&mut self, ident: Ident, from_expr: &Expr, to_expr: &Expr, step_expr: &Option<Expr>, block: &Block) -> Flow {
+        let from = self.eval_expr(from_expr).to_int();
+        let to = self.eval_expr(to_expr).to_int();
+        let step = match step_expr {
+            Some(step_expr) => self.eval_expr(step_expr).to_int(),
+            None if from < to => 1,
+            None => -1,
+        };
+        let delta = if step > 0 { step.abs() } else { -step.abs() };
+        let mut counter = if from <= to { from } else { from - 1 };
+        loop {
+            let keep_going = if from <= to { counter < to } else { counter >= to };
+            if !keep_going {
+                return Flow::Normal;
+            }
+            self.locals.push((ident, Value::Int(counter)));
+            let flow = self.exec_block(block);
+            let (_, counter_value) = self.locals.pop().unwrap();
+            counter = counter_value.to_int();
+            match flow {
+                Flow::Break => return Flow::Normal,
+                Flow::Return(value) => return Flow::Return(value),
+                Flow::Normal | Flow::Continue => {}
+            }
+            counter += delta;
+        }
+    }
+
+    /// Runs `while` loops, and `loop` loops if `expr` is `None`.
+    fn exec_loop_stmt(&mut self, expr: Option<&Expr>, block: &Block) -> Flow {
+        while expr.is_none_or(|expr| self.eval_expr(expr).to_bool()) {
+            match self.exec_block(block) {
+                Flow::Break => break,
+                Flow::Return(value) => return Flow::Return(value),
+                Flow::Normal | Flow::Continue => {}
+            }
+        }
+        Flow::Normal
+    }
+
+    fn eval_expr(&mut self, expr: &Expr) -> Value {
+        if let Some(Some(val)) = &*expr.const_val.borrow() {
+            return Value::from_val(val);
+        }
+        match &expr.kind {
+            ExprKind::Cond { expr, expr_if_true, expr_if_false, .. } => {
+                if self.eval_expr(expr).to_bool() {
+                    self.eval_expr(expr_if_true)
+                } else {
+                    self.eval_expr(expr_if_false)
+                }
+            }
+            ExprKind::Bin { op, left_expr, right_expr, .. } => self.eval_bin_expr(*op, left_expr, right_expr),
+            ExprKind::Un { op, expr, .. } => {
+                let value = self.eval_expr(expr);
+                match op {
+                    UnOp::Not => match value {
+                        Value::Int(_) | Value::Ivec(_, _) => zip_int(&value, &Value::Int(0), |a, _| !a),
+                        _ => Value::Bool(!value.to_bool()),
+                    },
+                    UnOp::Neg => match value {
+                        Value::Int(_) | Value::Ivec(_, _) => zip_int(&value, &Value::Int(0), |a, _| a.wrapping_neg()),
+                        _ => map_float(&value, |a| -a),
+                    },
+                }
+            }
+            ExprKind::MethodCall { ident, arg_exprs, .. } => {
+                let struct_ident = match arg_exprs[0].ty.borrow().as_ref().unwrap() {
+                    Ty::Struct { ident } => *ident,
+                    _ => panic!(),
+                };
+                self.eval_call_expr(IdentPath::from_two(struct_ident, *ident), arg_exprs)
+            }
+            ExprKind::Field { expr, field_ident, .. } => {
+                // Avoid cloning whole structs when reading a single field.
+                let value = match self.place(expr) {
+                    Some(Value::Struct(fields)) => return get_struct_field(fields, *field_ident).clone(),
+                    Some(value) => value.clone(),
+                    None => self.eval_expr(expr),
+                };
+                self.get_field(&value, *field_ident)
+            }
+            ExprKind::Index { expr, index_expr, .. } => {
+                let value = self.eval_expr(expr);
+                let index = self.eval_expr(index_expr).to_int() as usize;
+                get_index(&value, index)
+            }
+            ExprKind::Call { ident_path, arg_exprs, .. } => self.eval_call_expr(*ident_path, arg_exprs),
+            ExprKind::ConsCall { ty_lit, arg_exprs, .. } => {
+                let args: Vec<Value> = arg_exprs.iter().map(|arg_expr| self.eval_expr(arg_expr)).collect();
+                construct(*ty_lit, &args)
+            }
+            ExprKind::Var { kind, ident_path, .. } => {
+                let ident = ident_path.get_single().unwrap();
+                match kind.get().unwrap() {
+                    VarKind::Local => self.local_mut(ident).clone(),
+                    _ => self.globals[self.program.global_slots[&ident]].clone(),
+                }
+            }
+            ExprKind::Lit { lit, .. } => match *lit {
+                Lit::Bool(lit) => Value::Bool(lit),
+                Lit::Int(lit) => Value::Int(lit),
+                Lit::Float(lit) => Value::Float(lit),
+                Lit::Vec4(lit) => Value::Vec(4, [lit.x, lit.y, lit.z, lit.w]),
+            },
+        }
+    }
+
+    fn eval_bin_expr(&mut self, op: BinOp, left_expr: &Expr, right_expr: &Expr) -> Value {
+        match op {
+            BinOp::Assign => {
+                let value = self.eval_expr(right_expr);
+                self.assign(left_expr, value.clone());
+                value
+            }
+            BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign => {
+                let left = self.eval_expr(left_expr);
+                let right = self.eval_expr(right_expr);
+                let value = arith(op, &left, &right);
+                self.assign(left_expr, value.clone());
+                value
+            }
+            BinOp::Or => Value::Bool(self.eval_expr(left_expr).to_bool() || self.eval_expr(right_expr).to_bool()),
+            BinOp::And => Value::Bool(self.eval_expr(left_expr).to_bool() && self.eval_expr(right_expr).to_bool()),
+            BinOp::Eq | BinOp::Ne => {
+                let left = self.eval_expr(left_expr);
+                let right = self.eval_expr(right_expr);
+                Value::Bool((left == right) == matches!(op, BinOp::Eq))
+            }
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                let left = self.eval_expr(left_expr).to_float();
+                let right = self.eval_expr(right_expr).to_float();
+                Value::Bool(match op {
+                    BinOp::Lt => left < right,
+                    BinOp::Le => left <= right,
+                    BinOp::Gt => left > right,
+                    _ => left >= right,
+                })
+            }
+            BinOp::Add
+            | BinOp::Sub
+            | BinOp::Mul
+            | BinOp::Div
+            | BinOp::Rem
+            | BinOp::BitOr
+            | BinOp::BitXor
+            | BinOp::BitAnd
+            | BinOp::Shl
+            | BinOp::Shr => {
+                let left = self.eval_expr(left_expr);
+                let right = self.eval_expr(right_expr);
+                arith(op, &left, &right)
+            }
+        }
+    }
+
+    fn eval_call_expr(&mut self, ident_path: IdentPath, arg_exprs: &[Expr]) -> Value {
+        let args: Vec<Value> = arg_exprs.iter().map(|arg_expr| self.eval_expr(arg_expr)).collect();
+        if let Some(&decl_index) = self.program.fn_decls.get(&ident_path) {
+            let (ret, params) = self.call_fn(decl_index, args);
+            let decl = self.program.fn_decl(decl_index);
+            for ((param, arg_expr), value) in decl.params.iter().zip(arg_exprs).zip(params) {
+                if param.is_inout {
+                    self.assign(arg_expr, value);
+                }
+            }
+            ret
+        } else {
+            let builtin = self.program.builtins[&ident_path.get_single().unwrap()];
+            self.call_builtin(builtin, &args)
+        }
+    }
+
+    fn call_builtin(&mut self, builtin: BuiltinFn, args: &[Value]) -> Value {
+        match builtin {
+            BuiltinFn::Abs => match &args[0] {
+                Value::Int(_) | Value::Ivec(_, _) => zip_int(&args[0], &Value::Int(0), |a, _| a.wrapping_abs()),
+                _ => map_float(&args[0], f32::abs),
+            },
+            BuiltinFn::Acos => map_float(&args[0], f32::acos),
+            BuiltinFn::All | BuiltinFn::Any => match &args[0] {
+                Value::Bvec(len, vals) => Value::Bool(if matches!(builtin, BuiltinFn::All) {
+                    vals[..*len].iter().all(|val| *val)
+                } else {
+                    vals[..*len].iter().any(|val| *val)
+                }),
+                _ => panic!(),
+            },
+            BuiltinFn::Asin => map_float(&args[0], f32::asin),
+            BuiltinFn::Atan if args.len() == 2 => zip_float(&args[0], &args[1], f32::atan2),
+            BuiltinFn::Atan => map_float(&args[0], f32::atan),
+            BuiltinFn::Ceil => map_float(&args[0], f32::ceil),
+            BuiltinFn::Clamp => zip3_float(&args[0], &args[1], &args[2], |x, min, max| x.max(min).min(max)),
+            BuiltinFn::Cos => map_float(&args[0], f32::cos),
+            BuiltinFn::Cross => {
+                let (_, a) = args[0].lanes();
+                let (_, b) = args[1].lanes();
+                Value::Vec(3, [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0], 0.0])
+            }
+            BuiltinFn::Degrees => map_float(&args[0], f32::to_degrees),
+            BuiltinFn::DFdx | BuiltinFn::DFdy => self.derivative(builtin, &args[0]),
+            BuiltinFn::Distance => {
+                let diff = zip_float(&args[0], &args[1], |a, b| a - b);
+                Value::Float(dot(&diff, &diff).sqrt())
+            }
+            BuiltinFn::Dot => Value::Float(dot(&args[0], &args[1])),
+            BuiltinFn::Equal => compare(&args[0], &args[1], |a, b| a == b),
+            BuiltinFn::Exp => map_float(&args[0], f32::exp),
+            BuiltinFn::Exp2 => map_float(&args[0], f32::exp2),
+            BuiltinFn::Faceforward => {
+                if dot(&args[2], &args[1]) < 0.0 {
+                    args[0].clone()
+                } else {
+                    map_float(&args[0], |a| -a)
+                }
+            }
+            BuiltinFn::Floor => map_float(&args[0], f32::floor),
+            BuiltinFn::Fract => map_float(&args[0], |a| a - a.floor()),
+            BuiltinFn::GreaterThan => compare(&args[0], &args[1], |a, b| a > b),
+            BuiltinFn::GreaterThanEqual => compare(&args[0], &args[1], |a, b| a >= b),
+            BuiltinFn::Inversesqrt => map_float(&args[0], |a| 1.0 / a.sqrt()),
+            BuiltinFn::Inverse => match &args[0] {
+                Value::Mat(4, vals) => Value::Mat(4, inverse_mat4(vals)),
+                _ => panic!(),
+            },
+            BuiltinFn::Length => Value::Float(dot(&args[0], &args[0]).sqrt()),
+            BuiltinFn::LessThan => compare(&args[0], &args[1], |a, b| a < b),
+            BuiltinFn::LessThanEqual => compare(&args[0], &args[1], |a, b| a <= b),
+            BuiltinFn::Log => map_float(&args[0], f32::ln),
+            BuiltinFn::Log2 => map_float(&args[0], f32::log2),
+            BuiltinFn::MatrixCompMult => zip_float(&args[0], &args[1], |a, b| a * b),
+            BuiltinFn::Max => zip_float(&args[0], &args[1], f32::max),
+            BuiltinFn::Min => zip_float(&args[0], &args[1], f32::min),
+            BuiltinFn::Mix => zip3_float(&args[0], &args[1], &args[2], |x, y, a| x * (1.0 - a) + y * a),
+            BuiltinFn::Mod => zip_float(&args[0], &args[1], |x, y| x - y * (x / y).floor()),
+            BuiltinFn::Normalize => {
+                let length = dot(&args[0], &args[0]).sqrt();
+                map_float(&args[0], |a| a / length)
+            }
+            BuiltinFn::Not => match &args[0] {
+                Value::Bvec(len, vals) => Value::Bvec(*len, [!vals[0], !vals[1], !vals[2], !vals[3]]),
+                _ => panic!(),
+            },
+            BuiltinFn::NotEqual => compare(&args[0], &args[1], |a, b| a != b),
+            BuiltinFn::Pow => zip_float(&args[0], &args[1], f32::powf),
+            BuiltinFn::Radians => map_float(&args[0], f32::to_radians),
+            BuiltinFn::Reflect => {
+                let d = dot(&args[1], &args[0]);
+                zip_float(&args[0], &args[1], |i, n| i - 2.0 * d * n)
+            }
+            BuiltinFn::Refract => {
+                let eta = args[2].to_float();
+                let d = dot(&args[1], &args[0]);
+                let k = 1.0 - eta * eta * (1.0 - d * d);
+                if k < 0.0 {
+                    map_float(&args[0], |_| 0.0)
+                } else {
+                    zip_float(&args[0], &args[1], |i, n| eta * i - (eta * d + k.sqrt()) * n)
+                }
+            }
+            BuiltinFn::Sample2d => {
+                let texture_index = match args[0] {
+                    Value::Texture(texture_index) => texture_index,
+                    _ => panic!(),
+                };
+                let (_, pos) = args[1].lanes();
+                Value::Vec(4, self.sampler.sample_2d(texture_index, pos[0], pos[1]))
+            }
+            BuiltinFn::Sample3d => {
+                let texture_index = match args[0] {
+                    Value::Texture(texture_index) => texture_index,
+                    _ => panic!(),
+                };
+                let (_, pos) = args[1].lanes();
+                Value::Vec(4, self.sampler.sample_3d(texture_index, pos[0], pos[1], pos[2]))
+            }
+            BuiltinFn::SampleCube => {
+                let texture_index = match args[0] {
+                    Value::Texture(texture_index) => texture_index,
+                    _ => panic!(),
+                };
+                let (_, dir) = args[1].lanes();
+                Value::Vec(4, self.sampler.sample_cube(texture_index, dir[0], dir[1], dir[2]))
+            }
+            BuiltinFn::Sign => map_float(&args[0], |a| {
+                if a > 0.0 {
+                    1.0
+                } else if a < 0.0 {
+                    -1.0
+                } else {
+                    0.0
+                }
+            }),
+            BuiltinFn::Sin => map_float(&args[0], f32::sin),
+            BuiltinFn::Smoothstep => zip3_float(&args[0], &args[1], &args[2], |edge0, edge1, x| {
+                let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }),
+            BuiltinFn::Sqrt => map_float(&args[0], f32::sqrt),
+            BuiltinFn::Step => zip_float(&args[0], &args[1], |edge, x| if x < edge { 0.0 } else { 1.0 }),
+            BuiltinFn::Tan => map_float(&args[0], f32::tan),
+            BuiltinFn::Transpose => match &args[0] {
+                Value::Mat(dim, vals) => {
+                    let dim = *dim;
+                    let mut out = [0.0; 16];
+                    for col in 0..dim {
+                        for row in 0..dim {
+                            out[row * dim + col] = vals[col * dim + row];
+                        }
+                    }
+                    Value::Mat(dim, out)
+                }
+                _ => panic!(),
+            },
+        }
+    }
+
+    fn derivative(&mut self, builtin: BuiltinFn, value: &Value) -> Value {
+        match &mut self.derivatives {
+            Derivatives::Ignore => map_float(value, |_| 0.0),
+            Derivatives::Record(recorded) => {
+                recorded.push(value.clone());
+                map_float(value, |_| 0.0)
+            }
+            Derivatives::Replay { dx, dy, index } => {
+                let recorded = if matches!(builtin, BuiltinFn::DFdx) { dx } else { dy };
+                let result = match recorded.get(*index) {
+                    Some(neighbour) => zip_float(neighbour, value, |neighbour, value| neighbour - value),
+                    None => map_float(value, |_| 0.0),
+                };
+                *index += 1;
+                result
+            }
+        }
+    }
+
+    fn local_mut(&mut self, ident: Ident) -> &mut Value {
+        let frame_base = self.frame_base;
+        &mut self.locals[frame_base..].iter_mut().rev().find(|(local_ident, _)| *local_ident == ident).unwrap().1
+    }
+
+    fn swizzle(&mut self, ident: Ident) -> Option<&Vec<usize>> {
+        match self.swizzles.entry(ident) {
+            Entry::Occupied(entry) => Some(entry.into_mut()),
+            Entry::Vacant(entry) => Some(entry.insert(Swizzle::parse(ident)?.iter().cloned().collect())),
+        }
+    }
+
+    fn get_field(&mut self, value: &Value, field_ident: Ident) -> Value {
+        match value {
+            Value::Struct(fields) => get_struct_field(fields, field_ident).clone(),
+            Value::Vec(_, vals) => {
+                let indices = self.swizzle(field_ident).unwrap();
+                if indices.len() == 1 {
+                    Value::Float(vals[indices[0]])
+                } else {
+                    let mut out = [0.0; 4];
+                    for (index, component) in indices.iter().enumerate() {
+                        out[index] = vals[*component];
+                    }
+                    Value::Vec(indices.len(), out)
+                }
+            }
+            Value::Ivec(_, vals) => {
+                let indices = self.swizzle(field_ident).unwrap();
+                if indices.len() == 1 {
+                    Value::Int(vals[indices[0]])
+                } else {
+                    let mut out = [0; 4];
+                    for (index, component) in indices.iter().enumerate() {
+                        out[index] = vals[*component];
+                    }
+                    Value::Ivec(indices.len(), out)
+                }
+            }
+            Value::Bvec(_, vals) => {
+                let indices = self.swizzle(field_ident).unwrap();
+                if indices.len() == 1 {
+                    Value::Bool(vals[indices[0]])
+                } else {
+                    let mut out = [false; 4];
+                    for (index, component) in indices.iter().enumerate() {
+                        out[index] = vals[*component];
+                    }
+                    Value::Bvec(indices.len(), out)
+                }
+            }
+            _ => panic!("unexpected field access on {:?}", value),
+        }
+    }
+
+    /// Returns a reference to the value of an lvalue expression, if it is a variable or a field
+    /// of a struct variable.
+    fn place(&mut self, expr: &Expr) -> Option<&mut Value> {
+        match &expr.kind {
+            ExprKind::Var { kind, ident_path, .. } => {
+                let ident = ident_path.get_single().unwrap();
+                match kind.get().unwrap() {
+                    VarKind::Local => Some(self.local_mut(ident)),
+                    _ => Some(&mut self.globals[self.program.global_slots[&ident]]),
+                }
+            }
+            ExprKind::Field { expr, field_ident, .. } => match self.place(expr)? {
+                Value::Struct(fields) => Some(get_struct_field_mut(fields, *field_ident)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, target: &mut Value, field_ident: Ident, value: Value) {
+        if let Value::Struct(fields) = target {
+            *get_struct_field_mut(fields, field_ident) = value;
+            return;
+        }
+        let indices = self.swizzle(field_ident).unwrap().clone();
+        set_swizzle(target, &indices, value);
+    }
+
+    /// Write a value to an lvalue expression (see [`crate::lhs_check`]).
+    fn assign(&mut self, expr: &Expr, value: Value) {
+        match &expr.kind {
+            ExprKind::Var { .. } => *self.place(expr).unwrap() = value,
+            ExprKind::Field { expr: target_expr, field_ident, .. } => {
+                // Assign in place if possible, to avoid cloning whole structs.
+                if let Some(Value::Struct(fields)) = self.place(target_expr) {
+                    *get_struct_field_mut(fields, *field_ident) = value;
+                    return;
+                }
+                let indices = self.swizzle(*field_ident).cloned();
+                match (self.place(target_expr), indices) {
+                    (Some(target), Some(indices)) => set_swizzle(target, &indices, value),
+                    _ => {
+                        let mut target = self.eval_expr(target_expr);
+                        self.set_field(&mut target, *field_ident, value);
+                        self.assign(target_expr, target);
+                    }
+                }
+            }
+            ExprKind::Index { expr: target_expr, index_expr, .. } => {
+                let mut target = self.eval_expr(target_expr);
+                let index = self.eval_expr(index_expr).to_int() as usize;
+                set_index(&mut target, index, value);
+                self.assign(target_expr, target);
+            }
+            _ => panic!("expression can't be assigned to"),
+        }
+    }
+}
+
+fn get_struct_field(fields: &[(Ident, Value)], field_ident: Ident) -> &Value {
+    &fields.iter().find(|(ident, _)| *ident == field_ident).unwrap().1
+}
+
+fn get_struct_field_mut(fields: &mut [(Ident, Value)], field_ident: Ident) -> &mut Value {
+    &mut fields.iter_mut().find(|(ident, _)| *ident == field_ident).unwrap().1
+}
+
+fn set_swizzle(target: &mut Value, indices: &[usize], value: Value) {
+    let mut components = [0.0; 4];
+    let len = value.write_components(&mut components);
+    assert_eq!(len, indices.len());
+    for (index, component) in indices.iter().enumerate() {
+        match target {
+            Value::Vec(_, vals) => vals[*component] = components[index],
+            Value::Ivec(_, vals) => vals[*component] = components[index] as i32,
+            Value::Bvec(_, vals) => vals[*component] = components[index] != 0.0,
+            _ => panic!("unexpected field assignment on {:?}", target),
+        }
+    }
+}
+
+fn get_index(value: &Value, index: usize) -> Value {
+    match value {
+        Value::Vec(_, vals) => Value::Float(vals[index]),
+        Value::Ivec(_, vals) => Value::Int(vals[index]),
+        Value::Bvec(_, vals) => Value::Bool(vals[index]),
+        Value::Mat(dim, vals) => {
+            let mut column = [0.0; 4];
+            column[..*dim].copy_from_slice(&vals[index * dim..(index + 1) * dim]);
+            Value::Vec(*dim, column)
+        }
+        Value::Array(vals) => vals[index].clone(),
+        _ => panic!("unexpected index on {:?}", value),
+    }
+}
+
+fn set_index(target: &mut Value, index: usize, value: Value) {
+    match target {
+        Value::Vec(_, vals) => vals[index] = value.to_float(),
+        Value::Ivec(_, vals) => vals[index] = value.to_int(),
+        Value::Bvec(_, vals) => vals[index] = value.to_bool(),
+        Value::Mat(dim, vals) => {
+            let (_, column) = value.lanes();
+            vals[index * *dim..(index + 1) * *dim].copy_from_slice(&column[..*dim]);
+        }
+        Value::Array(vals) => vals[index] = value,
+        _ => panic!("unexpected index assignment on {:?}", target),
+    }
+}
+
+/// Constructor calls, like `vec4(vec2(1.0), 0.0, 1.0)`. Mirrors the `mpsc_` constructor
+/// functions that the GPU backends generate.
+fn construct(ty_lit: TyLit, args: &[Value]) -> Value {
+    let ty = ty_lit.to_ty();
+    let size = ty.size();
+    if args.len() == 1 {
+        match (&args[0], &ty) {
+            (Value::Mat(src_dim, src), Ty::Mat2 | Ty::Mat3 | Ty::Mat4) => {
+                let dim = mat_dim(&ty);
+                let mut out = [0.0; 16];
+                for col in 0..dim {
+                    for row in 0..dim {
+                        out[col * dim + row] = if col < *src_dim && row < *src_dim {
+                            src[col * src_dim + row]
+                        } else if col == row {
+                            1.0
+                        } else {
+                            0.0
+                        };
+                    }
+                }
+                return Value::Mat(dim, out);
+            }
+            (Value::Bool(_) | Value::Int(_) | Value::Float(_), Ty::Mat2 | Ty::Mat3 | Ty::Mat4) => {
+                let dim = mat_dim(&ty);
+                let (_, lanes) = args[0].lanes();
+                let mut out = [0.0; 16];
+                for index in 0..dim {
+                    out[index * dim + index] = lanes[0];
+                }
+                return Value::Mat(dim, out);
+            }
+            (Value::Bool(_) | Value::Int(_) | Value::Float(_), _) => {
+                let (_, lanes) = args[0].lanes();
+                return Value::from_components(&ty, &[lanes[0]; 4][..size.min(4)]);
+            }
+            _ => {}
+        }
+    }
+    let mut components = Vec::with_capacity(16);
+    for arg in args {
+        arg.push_components(&mut components);
+    }
+    components.resize(size.max(components.len()), 0.0);
+    Value::from_components(&ty, &components[..size])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_fragment::CodeFragment;
+    use crate::generate_shader_ast::ShaderAstGenerator;
+
+    struct Checkerboard;
+
+    impl TextureSampler for Checkerboard {
+        fn sample_2d(&self, _texture_index: usize, x: f32, y: f32) -> [f32; 4] {
+            if (x < 0.5) == (y < 0.5) {
+                [1.0, 1.0, 1.0, 1.0]
+            } else {
+                [0.0, 0.0, 0.0, 1.0]
+            }
+        }
+    }
+
+    fn interpreter(code: &'static str) -> ShaderInterpreter {
+        let code_fragment = CodeFragment::Static { filename: "test.rs", line: 1, col: 1, code };
+        let shader_ast = ShaderAstGenerator::new().generate_shader_ast(&[code_fragment]).unwrap();
+        ShaderInterpreter::new(shader_ast)
+    }
+
+    #[test]
+    fn vertex_and_pixel() {
+        let mut interpreter = interpreter(
+            r#"
+            struct Scaler {
+                factor: float
+            }
+            impl Scaler {
+                fn apply(self, v: vec2) -> vec2 {
+                    return v * self.factor;
+                }
+            }
+            const OFFSET: float = 0.25;
+            geometry geom: vec2;
+            instance rect_pos: vec2;
+            instance color: vec4;
+            uniform scale: float;
+            uniform camera: mat4 in pass;
+            texture tex: texture2D;
+            varying pos: vec2;
+
+            fn vertex() -> vec4 {
+                let scaler: Scaler;
+                scaler.factor = scale;
+                pos = scaler.apply(geom) + rect_pos;
+                return camera * vec4(pos, 0.0, 1.0);
+            }
+
+            fn pixel() -> vec4 {
+                let total = 0.0;
+                for i from 0 to 4 {
+                    if i == 3 {
+                        break;
+                    }
+                    total += OFFSET;
+                }
+                let tex_color = sample2d(tex, pos);
+                return vec4(color.rgb * total, tex_color.r) + vec4(dFdx(pos.x), 0.0, 0.0, 0.0);
+            }
+            "#,
+        );
+        assert_eq!(interpreter.packed_varyings_size(), 6);
+
+        let camera = [2.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0];
+        interpreter.set_uniforms(&camera, &[], &[], &[0.5]);
+
+        let mut varyings = [0.0; 6];
+        let position = interpreter.vertex(&[1.0, 0.5], &[0.0, 0.1, 1.0, 0.5, 0.0, 1.0], &Checkerboard, &mut varyings);
+        assert_eq!(varyings, [1.0, 0.5, 0.0, 1.0, 0.5, 0.35]);
+        assert_eq!(position, [2.0, 1.7, 0.0, 1.0]);
+
+        let mut varyings_dx = varyings;
+        varyings_dx[4] += 0.125;
+        let color = interpreter.pixel(&varyings, &varyings_dx, &varyings, &Checkerboard);
+        assert_eq!(color, [0.875, 0.375, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn int_ops_and_control_flow() {
+        let mut interpreter = interpreter(
+            r#"
+            const PACKED: int = (5 << 4) | 0x3;
+            instance flags: float;
+
+            fn vertex() -> vec4 {
+                let bits = int(flags);
+                let low = bits & 0xf;
+                let high = (bits >> 4) ^ 1;
+                let i = 0;
+                let sum = 0;
+                while i < 5 {
+                    i += 1;
+                    if i % 2 == 0 {
+                        continue;
+                    }
+                    sum += i;
+                }
+                loop {
+                    sum = sum << 1;
+                    if sum > 100 {
+                        break;
+                    }
+                }
+                let kind = 0;
+                match low {
+                    0 | 1 => {
+                        kind = 1;
+                    }
+                    6 => {
+                        kind = 2;
+                    }
+                    _ => {
+                        kind = 3;
+                    }
+                }
+                return vec4(float(low | (high << 8)), float(sum), float(kind), float(!PACKED % 10));
+            }
+
+            fn pixel() -> vec4 {
+                return vec4(0.0);
+            }
+            "#,
+        );
+        let position = interpreter.vertex(&[], &[182.0], &Checkerboard, &mut []);
+        assert_eq!(position, [2566.0, 144.0, 2.0, -4.0]);
+    }
+}
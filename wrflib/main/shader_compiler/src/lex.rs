@@ -4,7 +4,7 @@
 // found in the LICENSE-APACHE file in the root directory of this source tree.
 // You may not use this file except in compliance with the License.
 
-use crate::error::ParseError;
+use crate::error::{ErrorCode, ParseError};
 use crate::ident::Ident;
 use crate::lit::Lit;
 use crate::math::Vec4;
@@ -108,6 +108,14 @@ where
                 self.skip_two_chars();
                 Token::AndAnd
             }
+            ('&', _) => {
+                self.skip_char();
+                Token::Amp
+            }
+            ('%', _) => {
+                self.skip_char();
+                Token::Percent
+            }
             ('(', _) => {
                 self.skip_char();
                 Token::LeftParen
@@ -160,6 +168,18 @@ where
                     string.push('-');
                 }
                 self.read_chars_while(&mut string, |ch| ch.is_ascii_digit());
+                if string.trim_start_matches('-') == "0" && self.read_char_if(|ch| ch == 'x').is_some() {
+                    let mut digits = String::new();
+                    self.read_chars_while(&mut digits, |ch| ch.is_ascii_hexdigit() || ch == '_');
+                    let digits = digits.replace('_', "");
+                    if digits.is_empty() {
+                        return Err(span.error(self, "missing digits after integer base prefix".into()));
+                    }
+                    let sign = if string.starts_with('-') { "-" } else { "" };
+                    let val = i32::from_str_radix(&format!("{}{}", sign, digits), 16)
+                        .map_err(|_| span.error(self, "overflowing integer literal".into()))?;
+                    return Ok(span.token(self, Token::Lit(Lit::Int(val))));
+                }
                 let has_frac_part = if let Some(ch) = self.read_char_if(|ch| ch == '.') {
                     string.push(ch);
                     self.read_chars_while(&mut string, |ch| ch.is_ascii_digit());
@@ -222,6 +242,10 @@ where
                 self.skip_char();
                 Token::Semi
             }
+            ('<', '<') => {
+                self.skip_two_chars();
+                Token::Shl
+            }
             ('<', '=') => {
                 self.skip_two_chars();
                 Token::LtEq
@@ -234,10 +258,18 @@ where
                 self.skip_two_chars();
                 Token::EqEq
             }
+            ('=', '>') => {
+                self.skip_two_chars();
+                Token::FatArrow
+            }
             ('=', _) => {
                 self.skip_char();
                 Token::Eq
             }
+            ('>', '>') => {
+                self.skip_two_chars();
+                Token::Shr
+            }
             ('>', '=') => {
                 self.skip_two_chars();
                 Token::GtEq
@@ -250,6 +282,10 @@ where
                 self.skip_char();
                 Token::Question
             }
+            ('^', _) => {
+                self.skip_char();
+                Token::Caret
+            }
             (ch, _) if ch.is_ascii_alphabetic() || ch == '_' => {
                 let mut string = String::new();
                 string.push(self.read_char());
@@ -261,6 +297,8 @@ where
                     "bvec3" => Token::TyLit(TyLit::Bvec3),
                     "bvec4" => Token::TyLit(TyLit::Bvec4),
                     "texture2D" => Token::TyLit(TyLit::Texture2D),
+                    "textureCube" => Token::TyLit(TyLit::TextureCube),
+                    "texture3D" => Token::TyLit(TyLit::Texture3D),
                     "const" => Token::Const,
                     "continue" => Token::Continue,
                     "else" => Token::Else,
@@ -278,9 +316,11 @@ where
                     "ivec3" => Token::TyLit(TyLit::Ivec3),
                     "ivec4" => Token::TyLit(TyLit::Ivec4),
                     "let" => Token::Let,
+                    "loop" => Token::Loop,
                     "mat2" => Token::TyLit(TyLit::Mat2),
                     "mat3" => Token::TyLit(TyLit::Mat3),
                     "mat4" => Token::TyLit(TyLit::Mat4),
+                    "match" => Token::Match,
                     "return" => Token::Return,
                     //"self" => Token::Self_,
                     //"crate"=>Token::Crate,
@@ -290,6 +330,12 @@ where
                     "vec3" => Token::TyLit(TyLit::Vec3),
                     "vec4" => Token::TyLit(TyLit::Vec4),
                     "true" => Token::Lit(Lit::Bool(true)),
+                    "while" => Token::While,
+                    // Generated code uses this prefix for its own names, like `mpsc_loop_0` or module items
+                    // (see `write_module_item_ident`), so user names can't clash with them.
+                    _ if string.starts_with("mpsc_") => {
+                        return Err(span.error(self, format!("`{}` starts with `mpsc_`, which is reserved", string)));
+                    }
                     _ => Token::Ident(Ident::new(string)),
                 }
             }
@@ -309,6 +355,10 @@ where
                 self.skip_two_chars();
                 Token::OrOr
             }
+            ('|', _) => {
+                self.skip_char();
+                Token::Pipe
+            }
             ('}', _) => {
                 self.skip_char();
                 Token::RightBrace
@@ -421,6 +471,10 @@ impl SpanTracker {
     }
 
     fn error<C>(&self, lex: &Lex<C>, message: String) -> ParseError {
-        ParseError { span: Span { code_fragment_id: self.code_fragment_id, start: self.start, end: lex.index }, message }
+        ParseError::new(
+            ErrorCode::Lex,
+            Span { code_fragment_id: self.code_fragment_id, start: self.start, end: lex.index },
+            message,
+        )
     }
 }
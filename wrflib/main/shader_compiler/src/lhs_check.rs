@@ -7,7 +7,7 @@
 use {
     crate::{
         env::{Env, Sym, VarKind},
-        error::ParseError,
+        error::{ErrorCode, ParseError},
         ident::{Ident, IdentPath},
         lit::Lit,
         shaderast::*,
@@ -48,19 +48,19 @@ impl<'a> LhsChecker<'a> {
         _expr_if_true: &Expr,
         _expr_if_false: &Expr,
     ) -> Result<(), ParseError> {
-        Err(ParseError { span, message: String::from("expression is not a valid left hand side") })
+        Err(ParseError::new(ErrorCode::InvalidAssignment, span, "expression is not a valid left hand side"))
     }
 
     fn lhs_check_bin_expr(&mut self, span: Span, _op: BinOp, _left_expr: &Expr, _right_expr: &Expr) -> Result<(), ParseError> {
-        Err(ParseError { span, message: String::from("expression is not a valid left hand side") })
+        Err(ParseError::new(ErrorCode::InvalidAssignment, span, "expression is not a valid left hand side"))
     }
 
     fn lhs_check_un_expr(&mut self, span: Span, _op: UnOp, _expr: &Expr) -> Result<(), ParseError> {
-        Err(ParseError { span, message: String::from("expression is not a valid left hand side") })
+        Err(ParseError::new(ErrorCode::InvalidAssignment, span, "expression is not a valid left hand side"))
     }
 
     fn lhs_check_method_call_expr(&mut self, span: Span, _ident: Ident, _arg_exprs: &[Expr]) -> Result<(), ParseError> {
-        Err(ParseError { span, message: String::from("expression is not a valid left hand side") })
+        Err(ParseError::new(ErrorCode::InvalidAssignment, span, "expression is not a valid left hand side"))
     }
 
     fn lhs_check_field_expr(&mut self, _span: Span, expr: &Expr, _field_ident: Ident) -> Result<(), ParseError> {
@@ -72,18 +72,28 @@ impl<'a> LhsChecker<'a> {
     }
 
     fn lhs_check_call_expr(&mut self, span: Span, _ident_path: IdentPath, _arg_exprs: &[Expr]) -> Result<(), ParseError> {
-        Err(ParseError { span, message: String::from("expression is not a valid left hand side") })
+        Err(ParseError::new(ErrorCode::InvalidAssignment, span, "expression is not a valid left hand side"))
     }
 
     fn lhs_check_cons_call_expr(&mut self, span: Span, _ty_lit: TyLit, _arg_exprs: &[Expr]) -> Result<(), ParseError> {
-        Err(ParseError { span, message: String::from("expression is not a valid left hand side") })
+        Err(ParseError::new(ErrorCode::InvalidAssignment, span, "expression is not a valid left hand side"))
     }
 
     fn lhs_check_var_expr(&mut self, span: Span, _kind: &Cell<Option<VarKind>>, ident_path: IdentPath) -> Result<(), ParseError> {
         match self.env.find_sym(ident_path).unwrap() {
-            Sym::Var { is_mut, .. } => {
+            Sym::Var { is_mut, kind, .. } => {
                 if !is_mut {
-                    return Err(ParseError { span, message: String::from("expression is not a valid left hand side") });
+                    let err = ParseError::new(ErrorCode::InvalidAssignment, span, "expression is not a valid left hand side");
+                    let err = match self.env.find_sym_span(ident_path) {
+                        Some(declared_span) => err.with_label(declared_span, format!("`{}` declared here", ident_path)),
+                        None => err,
+                    };
+                    return Err(err.with_help(match kind {
+                        VarKind::Local => {
+                            String::from("loop variables can't be assigned to; copy it into a `let` variable first")
+                        }
+                        _ => format!("{} can't be assigned to; copy it into a `let` variable first", kind.describe_plural()),
+                    }));
                 }
                 Ok(())
             }
@@ -27,11 +27,14 @@ pub mod generate_glsl;
 pub mod generate_hlsl;
 pub mod generate_metal;
 pub mod generate_shader_ast;
+pub mod generate_wgsl;
 mod ident;
+pub mod interpret;
 mod lex;
 mod lhs_check;
 mod lit;
 pub mod math;
+mod resolve;
 mod shaderast;
 mod shaderparser;
 pub mod span;
@@ -41,5 +44,7 @@ pub mod ty;
 mod ty_check;
 mod util;
 mod val;
+#[cfg(feature = "validation")]
+pub mod validate;
 
 pub use shaderast::{Decl, ShaderAst};
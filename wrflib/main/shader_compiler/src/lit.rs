@@ -6,6 +6,7 @@
 
 use crate::math::Vec4;
 use crate::ty::Ty;
+use crate::util::PrettyPrintedFloat;
 use crate::val::Val;
 use std::fmt;
 
@@ -42,13 +43,7 @@ impl fmt::Display for Lit {
         match self {
             Lit::Bool(lit) => write!(f, "{}", lit),
             Lit::Int(lit) => write!(f, "{}", lit),
-            Lit::Float(lit) => {
-                if lit.abs().fract() < 0.00000001 {
-                    write!(f, "{}.0", lit)
-                } else {
-                    write!(f, "{}", lit)
-                }
-            }
+            Lit::Float(lit) => write!(f, "{}", PrettyPrintedFloat(*lit)),
             Lit::Vec4(lit) => {
                 write!(f, "vec4({},{},{},{})", lit.x, lit.y, lit.z, lit.w)
             }
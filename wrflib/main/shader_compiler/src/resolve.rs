@@ -0,0 +1,624 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Resolves names of items in modules, so that the rest of the compiler only needs to deal with full names.
+//!
+//! Items inside a `mod df { .. }` block are called e.g. `df::circle` everywhere after parsing. This pass rewrites
+//! every path in function bodies and types to such full names, by looking names up in this order:
+//! 1. Local variables.
+//! 2. Items in the same module.
+//! 3. Items imported using `use df::circle;`.
+//! 4. Items imported using `use df::*;`.
+//! 5. Items at the top level, which includes all geometries, instances, uniforms, and so on.
+//!
+//! Paths with more than one segment, like `df::circle`, start by looking up their first segment the same way.
+//! Names that can't be found are left alone, so that e.g. builtin functions still work, and undefined names
+//! get reported by [`crate::analyse`].
+
+use crate::error::{ErrorCode, ParseError, ParseErrors};
+use crate::ident::{Ident, IdentPath};
+use crate::shaderast::*;
+use crate::span::Span;
+use crate::ty::{TyExpr, TyExprKind};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+pub(crate) fn resolve_shader(shader: &mut ShaderAst) -> Result<(), ParseErrors> {
+    let mut resolver = Resolver::default();
+    resolver.collect_items(shader).map_err(|err| ParseErrors { errors: vec![err] })?;
+    resolver.collect_imports(shader).map_err(|err| ParseErrors { errors: vec![err] })?;
+    let mut errors = vec![];
+    for decl in &mut shader.decls {
+        if let Err(err) = resolver.resolve_decl(decl) {
+            errors.push(err);
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ParseErrors { errors });
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ItemKind {
+    Module,
+    /// A struct, or the name of an `impl` block.
+    Type,
+    /// A function or a constant.
+    Value,
+}
+
+impl ItemKind {
+    fn is_module_or_type(self) -> bool {
+        matches!(self, ItemKind::Module | ItemKind::Type)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Item {
+    ident: Ident,
+    kind: ItemKind,
+    span: Span,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Import {
+    Item { ident: Ident, item: Item },
+    Glob { module: Ident, span: Span },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Resolved {
+    Item(Item),
+    Method(Ident, Ident),
+}
+
+#[derive(Debug, Default)]
+struct Resolver {
+    /// Every item, by full name.
+    items: HashMap<Ident, Item>,
+    /// The imports of every module, with [`None`] for the top level.
+    imports: HashMap<Option<Ident>, Vec<Import>>,
+    /// The local variables of the function that is being resolved, for each nested block.
+    scopes: Vec<HashSet<Ident>>,
+}
+
+impl Resolver {
+    fn collect_items(&mut self, shader: &ShaderAst) -> Result<(), ParseError> {
+        for decl in &shader.module_decls {
+            self.insert_item(Item { ident: decl.ident, kind: ItemKind::Module, span: decl.span })?;
+        }
+        for decl in &shader.decls {
+            match decl {
+                Decl::Const(decl) => self.insert_item(Item { ident: decl.ident, kind: ItemKind::Value, span: decl.span })?,
+                Decl::Fn(decl) => match *decl.ident_path.segs() {
+                    [ident] => self.insert_item(Item { ident, kind: ItemKind::Value, span: decl.span })?,
+                    [ident, _] => self.insert_type(ident, decl.span)?,
+                    _ => panic!(),
+                },
+                Decl::Struct(decl) => self.insert_type(decl.ident, decl.span)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a struct or the name of an `impl` block. Any number of `impl` blocks can add methods to the same type.
+    fn insert_type(&mut self, ident: Ident, span: Span) -> Result<(), ParseError> {
+        if matches!(self.items.get(&ident), Some(Item { kind: ItemKind::Type, .. })) {
+            return Ok(());
+        }
+        self.insert_item(Item { ident, kind: ItemKind::Type, span })
+    }
+
+    fn insert_item(&mut self, item: Item) -> Result<(), ParseError> {
+        match self.items.entry(item.ident) {
+            Entry::Vacant(entry) => {
+                entry.insert(item);
+            }
+            // Top-level functions and constants could always share names with other items, so only report this
+            // for items in modules, and for modules themselves.
+            Entry::Occupied(mut entry) => {
+                let previous = *entry.get();
+                if item.ident.module().is_some() || item.kind == ItemKind::Module || previous.kind == ItemKind::Module {
+                    return Err(ParseError::new(
+                        ErrorCode::AlreadyDefined,
+                        item.span,
+                        format!("`{}` is already defined in this scope", item.ident),
+                    )
+                    .with_label(previous.span, format!("previous definition of `{}` here", item.ident)));
+                }
+                entry.insert(item);
+            }
+        }
+        Ok(())
+    }
+
+    fn collect_imports(&mut self, shader: &ShaderAst) -> Result<(), ParseError> {
+        for decl in &shader.use_decls {
+            let item = match self.resolve_path(decl.module, decl.span, decl.ident_path, |_| true, false)? {
+                Some(Resolved::Item(item)) => item,
+                _ => {
+                    return Err(ParseError::new(
+                        ErrorCode::UndefinedName,
+                        decl.span,
+                        format!("unresolved import `{}`", decl.ident_path),
+                    ))
+                }
+            };
+            let import = if decl.is_glob {
+                if item.kind != ItemKind::Module {
+                    return Err(ParseError::new(
+                        ErrorCode::UndefinedName,
+                        decl.span,
+                        format!("`{}` is not a module", decl.ident_path),
+                    )
+                    .with_label(item.span, format!("`{}` defined here", item.ident)));
+                }
+                Import::Glob { module: item.ident, span: decl.span }
+            } else {
+                let ident = *decl.ident_path.segs().last().unwrap();
+                if let Some(previous_span) = self.find_local_definition(decl.module, ident) {
+                    return Err(ParseError::new(
+                        ErrorCode::AlreadyDefined,
+                        decl.span,
+                        format!("`{}` is already defined in this scope", ident),
+                    )
+                    .with_label(previous_span, format!("previous definition of `{}` here", ident)));
+                }
+                Import::Item { ident, item: Item { span: decl.span, ..item } }
+            };
+            self.imports.entry(decl.module).or_default().push(import);
+        }
+        Ok(())
+    }
+
+    /// Where `ident` is defined or explicitly imported in `module`, if it is.
+    fn find_local_definition(&self, module: Option<Ident>, ident: Ident) -> Option<Span> {
+        if let Some(item) = self.items.get(&ident.in_module(module)) {
+            return Some(item.span);
+        }
+        self.imports.get(&module)?.iter().find_map(|import| match *import {
+            Import::Item { ident: import_ident, item } if import_ident == ident => Some(item.span),
+            _ => None,
+        })
+    }
+
+    /// Looks up a single name in `module`, only returning items for which `filter` returns true.
+    fn lookup(
+        &self,
+        module: Option<Ident>,
+        span: Span,
+        ident: Ident,
+        filter: fn(ItemKind) -> bool,
+        use_imports: bool,
+    ) -> Result<Option<Item>, ParseError> {
+        if let Some(&item) = self.items.get(&ident.in_module(module)).filter(|item| filter(item.kind)) {
+            return Ok(Some(item));
+        }
+        if use_imports {
+            let imports = self.imports.get(&module).map(Vec::as_slice).unwrap_or_default();
+            for import in imports {
+                match *import {
+                    Import::Item { ident: import_ident, item } if import_ident == ident && filter(item.kind) => {
+                        return Ok(Some(self.items[&item.ident]))
+                    }
+                    _ => {}
+                }
+            }
+            let mut found: Option<(Item, Span)> = None;
+            for import in imports {
+                if let Import::Glob { module: glob_module, span: glob_span } = *import {
+                    if let Some(&item) = self.items.get(&ident.in_module(Some(glob_module))).filter(|item| filter(item.kind)) {
+                        match found {
+                            Some((found_item, found_span)) if found_item.ident != item.ident => {
+                                return Err(ParseError::new(ErrorCode::UndefinedName, span, format!("`{}` is ambiguous", ident))
+                                    .with_label(found_span, format!("`{}` could refer to `{}`", ident, found_item.ident))
+                                    .with_label(glob_span, format!("`{}` could also refer to `{}`", ident, item.ident))
+                                    .with_help(format!("use a full path like `{}`", item.ident)));
+                            }
+                            _ => found = Some((item, glob_span)),
+                        }
+                    }
+                }
+            }
+            if let Some((item, _)) = found {
+                return Ok(Some(item));
+            }
+        }
+        if module.is_some() {
+            return Ok(self.items.get(&ident).filter(|item| filter(item.kind)).copied());
+        }
+        Ok(None)
+    }
+
+    /// Resolves `ident_path` in `module`. Returns [`None`] if its first segment can't be found, which for paths
+    /// with a single segment also happens if `filter` returns false for the item that it refers to.
+    fn resolve_path(
+        &self,
+        module: Option<Ident>,
+        span: Span,
+        ident_path: IdentPath,
+        filter: fn(ItemKind) -> bool,
+        use_imports: bool,
+    ) -> Result<Option<Resolved>, ParseError> {
+        let segs = ident_path.segs();
+        if let [ident] = *segs {
+            let item = self.lookup(module, span, ident, filter, use_imports)?;
+            return Ok(item.map(Resolved::Item));
+        }
+        let mut item = match self.lookup(module, span, segs[0], ItemKind::is_module_or_type, use_imports)? {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        for (index, &ident) in segs.iter().enumerate().skip(1) {
+            match item.kind {
+                ItemKind::Module => {
+                    item = *self.items.get(&ident.in_module(Some(item.ident))).ok_or_else(|| {
+                        ParseError::new(
+                            ErrorCode::UndefinedName,
+                            span,
+                            format!("cannot find `{}` in module `{}`", ident, item.ident),
+                        )
+                    })?;
+                }
+                ItemKind::Type if index == segs.len() - 1 => return Ok(Some(Resolved::Method(item.ident, ident))),
+                _ => {
+                    return Err(ParseError::new(
+                        ErrorCode::UndefinedName,
+                        span,
+                        format!("`{}` is not a module, so it can't contain `{}`", item.ident, ident),
+                    ))
+                }
+            }
+        }
+        Ok(Some(Resolved::Item(item)))
+    }
+
+    fn resolve_decl(&mut self, decl: &mut Decl) -> Result<(), ParseError> {
+        match decl {
+            Decl::Const(decl) => {
+                let module = decl.ident.module();
+                self.resolve_ty_expr(module, &mut decl.ty_expr)?;
+                self.resolve_expr(module, &mut decl.expr)
+            }
+            Decl::Fn(decl) => self.resolve_fn_decl(decl),
+            Decl::Struct(decl) => {
+                let module = decl.ident.module();
+                for field in &mut decl.fields {
+                    self.resolve_ty_expr(module, &mut field.ty_expr)?;
+                }
+                Ok(())
+            }
+            Decl::Varying(decl) => self.resolve_ty_expr(None, &mut decl.ty_expr),
+            Decl::Geometry(_) | Decl::Instance(_) | Decl::Texture(_) | Decl::Uniform(_) => Ok(()),
+        }
+    }
+
+    fn resolve_fn_decl(&mut self, decl: &mut FnDecl) -> Result<(), ParseError> {
+        let module = decl.ident_path.segs()[0].module();
+        for param in &mut decl.params {
+            self.resolve_ty_expr(module, &mut param.ty_expr)?;
+        }
+        if let Some(return_ty_expr) = &mut decl.return_ty_expr {
+            self.resolve_ty_expr(module, return_ty_expr)?;
+        }
+        self.scopes = vec![decl.params.iter().map(|param| param.ident).collect()];
+        self.resolve_block(module, &mut decl.block)
+    }
+
+    fn resolve_ty_expr(&mut self, module: Option<Ident>, ty_expr: &mut TyExpr) -> Result<(), ParseError> {
+        match &mut ty_expr.kind {
+            TyExprKind::Array { elem_ty_expr, .. } => self.resolve_ty_expr(module, elem_ty_expr),
+            TyExprKind::Var { span, ident } => {
+                let mut ident_path = IdentPath::default();
+                for seg in ident.to_string().split("::") {
+                    if !ident_path.push(Ident::new(seg)) {
+                        return Err(ParseError::new(ErrorCode::Syntax, *span, format!("path too long `{}`", ident)));
+                    }
+                }
+                match self.resolve_path(module, *span, ident_path, |kind| kind == ItemKind::Type, true)? {
+                    Some(Resolved::Item(item)) if item.kind == ItemKind::Type => *ident = item.ident,
+                    None if ident_path.get_single().is_some() => {}
+                    _ => return Err(ParseError::new(ErrorCode::UndefinedName, *span, format!("`{}` is not a type", ident))),
+                }
+                Ok(())
+            }
+            TyExprKind::Lit { .. } => Ok(()),
+        }
+    }
+
+    fn resolve_block(&mut self, module: Option<Ident>, block: &mut Block) -> Result<(), ParseError> {
+        self.scopes.push(HashSet::new());
+        for stmt in &mut block.stmts {
+            self.resolve_stmt(module, stmt)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn resolve_stmt(&mut self, module: Option<Ident>, stmt: &mut Stmt) -> Result<(), ParseError> {
+        match stmt {
+            Stmt::Break { .. } | Stmt::Continue { .. } => Ok(()),
+            Stmt::For { ident, from_expr, to_expr, step_expr, block, .. } => {
+                self.resolve_expr(module, from_expr)?;
+                self.resolve_expr(module, to_expr)?;
+                if let Some(step_expr) = step_expr {
+                    self.resolve_expr(module, step_expr)?;
+                }
+                self.scopes.push([*ident].into_iter().collect());
+                self.resolve_block(module, block)?;
+                self.scopes.pop();
+                Ok(())
+            }
+            Stmt::If { expr, block_if_true, block_if_false, .. } => {
+                self.resolve_expr(module, expr)?;
+                self.resolve_block(module, block_if_true)?;
+                if let Some(block_if_false) = block_if_false {
+                    self.resolve_block(module, block_if_false)?;
+                }
+                Ok(())
+            }
+            Stmt::Let { ident, ty_expr, expr, .. } => {
+                if let Some(ty_expr) = ty_expr {
+                    self.resolve_ty_expr(module, ty_expr)?;
+                }
+                if let Some(expr) = expr {
+                    self.resolve_expr(module, expr)?;
+                }
+                self.scopes.last_mut().unwrap().insert(*ident);
+                Ok(())
+            }
+            Stmt::Loop { block, .. } => self.resolve_block(module, block),
+            Stmt::Match { expr, arms, .. } => {
+                self.resolve_expr(module, expr)?;
+                for arm in arms {
+                    self.resolve_block(module, &mut arm.block)?;
+                }
+                Ok(())
+            }
+            Stmt::Return { expr, .. } => {
+                if let Some(expr) = expr {
+                    self.resolve_expr(module, expr)?;
+                }
+                Ok(())
+            }
+            Stmt::While { expr, block, .. } => {
+                self.resolve_expr(module, expr)?;
+                self.resolve_block(module, block)
+            }
+            Stmt::Expr { expr, .. } => self.resolve_expr(module, expr),
+        }
+    }
+
+    fn resolve_expr(&mut self, module: Option<Ident>, expr: &mut Expr) -> Result<(), ParseError> {
+        match &mut expr.kind {
+            ExprKind::Cond { expr, expr_if_true, expr_if_false, .. } => {
+                self.resolve_expr(module, expr)?;
+                self.resolve_expr(module, expr_if_true)?;
+                self.resolve_expr(module, expr_if_false)
+            }
+            ExprKind::Bin { left_expr, right_expr, .. } => {
+                self.resolve_expr(module, left_expr)?;
+                self.resolve_expr(module, right_expr)
+            }
+            ExprKind::Un { expr, .. } | ExprKind::Field { expr, .. } => self.resolve_expr(module, expr),
+            ExprKind::Index { expr, index_expr, .. } => {
+                self.resolve_expr(module, expr)?;
+                self.resolve_expr(module, index_expr)
+            }
+            ExprKind::MethodCall { arg_exprs, .. } | ExprKind::ConsCall { arg_exprs, .. } => {
+                for arg_expr in arg_exprs {
+                    self.resolve_expr(module, arg_expr)?;
+                }
+                Ok(())
+            }
+            ExprKind::Call { span, ident_path, arg_exprs } => {
+                for arg_expr in arg_exprs {
+                    self.resolve_expr(module, arg_expr)?;
+                }
+                self.resolve_expr_path(module, *span, ident_path)
+            }
+            ExprKind::Var { span, ident_path, .. } => {
+                let is_local =
+                    ident_path.get_single().is_some_and(|ident| self.scopes.iter().any(|scope| scope.contains(&ident)));
+                if is_local {
+                    return Ok(());
+                }
+                self.resolve_expr_path(module, *span, ident_path)
+            }
+            ExprKind::Lit { .. } => Ok(()),
+        }
+    }
+
+    fn resolve_expr_path(&self, module: Option<Ident>, span: Span, ident_path: &mut IdentPath) -> Result<(), ParseError> {
+        match self.resolve_path(module, span, *ident_path, |kind| kind == ItemKind::Value, true)? {
+            Some(Resolved::Item(Item { ident, kind: ItemKind::Module, .. })) => {
+                Err(ParseError::new(ErrorCode::UndefinedName, span, format!("expected value, found module `{}`", ident)))
+            }
+            Some(Resolved::Item(item)) => {
+                *ident_path = IdentPath::from_ident(item.ident);
+                Ok(())
+            }
+            Some(Resolved::Method(ident, method_ident)) => {
+                *ident_path = IdentPath::from_two(ident, method_ident);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::code_fragment::CodeFragment;
+    use crate::generate_glsl;
+    use crate::generate_shader_ast::ShaderAstGenerator;
+
+    /// Generates the GLSL vertex and fragment shaders for `code`, which gets appended to some declarations
+    /// that every shader needs.
+    fn generate_glsl(code: &'static str) -> String {
+        let code_fragments = [
+            CodeFragment::Static {
+                filename: "test.rs",
+                line: 1,
+                col: 1,
+                code: "geometry geom: vec2; fn vertex() -> vec4 { return vec4(geom, 0., 1.); }",
+            },
+            CodeFragment::Static { filename: "test.rs", line: 2, col: 1, code },
+        ];
+        let shader = ShaderAstGenerator::new().generate_shader_ast(&code_fragments).unwrap();
+        format!("{}\n{}", generate_glsl::generate_vertex_shader(&shader), generate_glsl::generate_fragment_shader(&shader))
+    }
+
+    #[test]
+    fn use_in_nested_modules() {
+        let glsl = generate_glsl(
+            r#"
+            mod shapes {
+                mod util {
+                    fn scale(v: float) -> float {
+                        return v * 2.;
+                    }
+                }
+                mod circle {
+                    use shapes::util::scale;
+                    fn radius() -> float {
+                        return scale(0.25);
+                    }
+                }
+                mod square {
+                    use shapes::util::*;
+                    fn size() -> float {
+                        return scale(0.5);
+                    }
+                }
+            }
+            use shapes::circle::radius;
+            use shapes::square::{size};
+            fn pixel() -> vec4 {
+                return vec4(radius(), size(), 0., 1.);
+            }
+            "#,
+        );
+        assert!(glsl.contains("float mpsc_m6shapes4util5scale(float v)"), "{}", glsl);
+        assert!(glsl.contains("return mpsc_m6shapes4util5scale(0.25);"), "{}", glsl);
+        assert!(glsl.contains("return mpsc_m6shapes4util5scale(0.5);"), "{}", glsl);
+        assert!(glsl.contains("vec4(mpsc_m6shapes6circle6radius(), mpsc_m6shapes6square4size(), 0.0, 1.0)"), "{}", glsl);
+    }
+
+    #[test]
+    fn glob_import_shadowed_by_local_items() {
+        let glsl = generate_glsl(
+            r#"
+            mod colors {
+                fn red() -> float {
+                    return 1.;
+                }
+                fn green() -> float {
+                    return 0.5;
+                }
+            }
+            mod theme {
+                use colors::*;
+                fn red() -> float {
+                    return 0.75;
+                }
+                fn background() -> vec4 {
+                    return vec4(red(), green(), 0., 1.);
+                }
+            }
+            use colors::*;
+            fn green() -> float {
+                return 0.25;
+            }
+            fn pixel() -> vec4 {
+                return theme::background() + vec4(0., green(), 0., 0.);
+            }
+            "#,
+        );
+        assert!(glsl.contains("vec4(mpsc_m5theme3red(), mpsc_m6colors5green(), 0.0, 1.0)"), "{}", glsl);
+        assert!(glsl.contains("vec4(0.0, green(), 0.0, 0.0)"), "{}", glsl);
+        assert!(!glsl.contains("mpsc_m6colors3red"), "{}", glsl);
+    }
+
+    #[test]
+    fn dead_code_elimination() {
+        let glsl = generate_glsl(
+            r#"
+            mod shapes {
+                struct Circle {
+                    radius: float
+                }
+                struct Unused {
+                    x: float
+                }
+                impl Circle {
+                    fn area(self) -> float {
+                        return 3.14 * self.radius * self.radius;
+                    }
+                    fn unused_method(self) -> float {
+                        return 0.;
+                    }
+                }
+                fn unused() -> Unused {
+                    let unused: Unused;
+                    unused.x = 1.;
+                    return unused;
+                }
+                fn circle() -> Circle {
+                    let circle: Circle;
+                    circle.radius = 0.5;
+                    return circle;
+                }
+            }
+            fn pixel() -> vec4 {
+                return vec4(shapes::circle().area());
+            }
+            "#,
+        );
+        assert!(glsl.contains("struct mpsc_m6shapes6Circle {"), "{}", glsl);
+        assert!(glsl.contains("float mpsc_m6shapes6Circle4area(mpsc_m6shapes6Circle self)"), "{}", glsl);
+        assert!(glsl.contains("mpsc_m6shapes6circle()"), "{}", glsl);
+        assert!(!glsl.contains("Unused"), "{}", glsl);
+        assert!(!glsl.contains("unused"), "{}", glsl);
+    }
+
+    #[test]
+    fn names_of_module_items_and_methods_are_unique() {
+        let glsl = generate_glsl(
+            r#"
+            mod a {
+                fn b_c() -> float {
+                    return 1.;
+                }
+            }
+            mod a_b {
+                fn c() -> float {
+                    return 2.;
+                }
+            }
+            struct A {
+                x: float
+            }
+            impl A {
+                fn b(self) -> float {
+                    return self.x;
+                }
+            }
+            fn A_b() -> float {
+                return 3.;
+            }
+            fn pixel() -> vec4 {
+                let a: A;
+                a.x = 4.;
+                return vec4(a::b_c(), a_b::c(), a.b(), A_b());
+            }
+            "#,
+        );
+        assert!(glsl.contains("vec4(mpsc_m1a3b_c(), mpsc_m3a_b1c(), mpsc_m1A1b(a), A_b())"), "{}", glsl);
+    }
+}
@@ -18,6 +18,9 @@ use std::fmt;
 pub struct ShaderAst {
     pub debug: bool,
     pub decls: Vec<Decl>,
+    /// Items inside modules are part of [`ShaderAst::decls`], so these are only used for resolving names.
+    pub(crate) module_decls: Vec<ModuleDecl>,
+    pub(crate) use_decls: Vec<UseDecl>,
 }
 
 impl ShaderAst {
@@ -94,6 +97,25 @@ pub enum Decl {
     Varying(VaryingDecl),
 }
 
+/// A `mod name { .. }` block. The names of the items in it are prefixed with [`ModuleDecl::ident`], e.g.
+/// `df::circle`.
+#[derive(Clone, Debug)]
+pub(crate) struct ModuleDecl {
+    pub(crate) span: Span,
+    /// The full path of the module, like `shapes::df`.
+    pub(crate) ident: Ident,
+}
+
+/// A `use path;` or `use path::*;` declaration.
+#[derive(Clone, Debug)]
+pub(crate) struct UseDecl {
+    pub(crate) span: Span,
+    /// The module that the declaration is in, or [`None`] for the top level.
+    pub(crate) module: Option<Ident>,
+    pub(crate) ident_path: IdentPath,
+    pub(crate) is_glob: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct GeometryDecl {
     pub(crate) is_used_in_fragment_shader: Cell<Option<bool>>,
@@ -123,6 +145,7 @@ pub struct FnDecl {
     pub(crate) instance_deps: RefCell<Option<BTreeSet<Ident>>>,
     pub(crate) has_varying_deps: Cell<Option<bool>>,
     pub(crate) cons_fn_deps: RefCell<Option<BTreeSet<(TyLit, Vec<Ty>)>>>,
+    pub(crate) struct_deps: RefCell<Option<BTreeSet<Ident>>>,
     pub(crate) ident_path: IdentPath,
     pub(crate) params: Vec<Param>,
     pub(crate) return_ty_expr: Option<TyExpr>,
@@ -140,6 +163,8 @@ pub struct InstanceDecl {
 #[derive(Clone, Debug)]
 pub struct StructDecl {
     pub(crate) span: Span,
+    /// Whether any function that is used in the vertex or fragment shader uses this struct.
+    pub(crate) is_used: Cell<Option<bool>>,
     pub(crate) ident: Ident,
     pub(crate) fields: Vec<Field>,
 }
@@ -198,10 +223,34 @@ pub(crate) enum Stmt {
     For { span: Span, ident: Ident, from_expr: Expr, to_expr: Expr, step_expr: Option<Expr>, block: Box<Block> },
     If { span: Span, expr: Expr, block_if_true: Box<Block>, block_if_false: Option<Box<Block>> },
     Let { span: Span, ty: RefCell<Option<Ty>>, ident: Ident, ty_expr: Option<TyExpr>, expr: Option<Expr> },
+    Loop { span: Span, block: Box<Block> },
+    Match { span: Span, expr: Expr, arms: Vec<MatchArm> },
     Return { span: Span, expr: Option<Expr> },
+    While { span: Span, expr: Expr, block: Box<Block> },
     Expr { span: Span, expr: Expr },
 }
 
+#[derive(Clone, Debug)]
+pub(crate) struct MatchArm {
+    pub(crate) patterns: Vec<Pattern>,
+    pub(crate) block: Block,
+}
+
+/// A pattern in a `match` arm. Several of them can be combined with `|`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Pattern {
+    Int { span: Span, val: i32 },
+    Wildcard { span: Span },
+}
+
+impl Pattern {
+    pub(crate) fn span(&self) -> Span {
+        match *self {
+            Pattern::Int { span, .. } | Pattern::Wildcard { span } => span,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct Expr {
     pub(crate) span: Span,
@@ -244,6 +293,19 @@ pub(crate) enum BinOp {
     Sub,
     Mul,
     Div,
+    Rem,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shl,
+    Shr,
+}
+
+impl BinOp {
+    /// Whether this operator only works on integers.
+    pub(crate) fn is_int_only(self) -> bool {
+        matches!(self, BinOp::Rem | BinOp::BitOr | BinOp::BitXor | BinOp::BitAnd | BinOp::Shl | BinOp::Shr)
+    }
 }
 
 impl fmt::Display for BinOp {
@@ -269,6 +331,12 @@ impl fmt::Display for BinOp {
                 BinOp::Sub => "-",
                 BinOp::Mul => "*",
                 BinOp::Div => "/",
+                BinOp::Rem => "%",
+                BinOp::BitOr => "|",
+                BinOp::BitXor => "^",
+                BinOp::BitAnd => "&",
+                BinOp::Shl => "<<",
+                BinOp::Shr => ">>",
             }
         )
     }
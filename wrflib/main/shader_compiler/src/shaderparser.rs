@@ -17,20 +17,30 @@ use std::cell::{Cell, RefCell};
 impl<'a> DeTokParserImpl<'a> {
     pub(crate) fn parse_shader(&mut self) -> Result<ShaderAst, ParseError> {
         let mut shader_ast = ShaderAst::default();
+        self.parse_items(&mut shader_ast, None)?;
+        Ok(shader_ast)
+    }
 
-        while self.peek_token() != Token::Eof {
+    /// Parses items until the end of `module`, or the end of the shader for [`None`].
+    fn parse_items(&mut self, shader_ast: &mut ShaderAst, module: Option<Ident>) -> Result<(), ParseError> {
+        loop {
             match self.peek_token() {
-                Token::Ident(ident) if ident == Ident::new("geometry") => {
+                Token::Eof if module.is_none() => break,
+                Token::RightBrace if module.is_some() => {
+                    self.skip_token();
+                    break;
+                }
+                Token::Ident(ident) if ident == Ident::new("geometry") && module.is_none() => {
                     self.skip_token();
                     let decl = self.parse_geometry_decl()?;
                     shader_ast.decls.push(Decl::Geometry(decl));
                 }
                 Token::Const => {
-                    let decl = self.parse_const_decl()?;
+                    let decl = self.parse_const_decl(module)?;
                     shader_ast.decls.push(Decl::Const(decl));
                 }
                 Token::Fn => {
-                    let decl = self.parse_fn_decl(None)?;
+                    let decl = self.parse_fn_decl(module, None)?;
                     shader_ast.decls.push(Decl::Fn(decl));
                 }
                 Token::Ident(ident) if ident == Ident::new("impl") => {
@@ -38,48 +48,116 @@ impl<'a> DeTokParserImpl<'a> {
                     let prefix = self.parse_ident()?;
                     self.expect_token(Token::LeftBrace)?;
                     while !self.accept_token(Token::RightBrace) {
-                        let decl = self.parse_fn_decl(Some(prefix))?;
+                        let decl = self.parse_fn_decl(module, Some(prefix))?;
                         shader_ast.decls.push(Decl::Fn(decl));
                     }
                 }
+                Token::Ident(ident) if ident == Ident::new("mod") => {
+                    let span = self.begin_span();
+                    self.skip_token();
+                    let ident = self.parse_ident()?.in_module(module);
+                    let span = span.end(self, |span| span);
+                    shader_ast.module_decls.push(ModuleDecl { span, ident });
+                    self.expect_token(Token::LeftBrace)?;
+                    self.parse_items(shader_ast, Some(ident))?;
+                }
+                Token::Ident(ident) if ident == Ident::new("use") => {
+                    self.skip_token();
+                    self.parse_use_decl(shader_ast, module)?;
+                }
                 Token::Struct => {
-                    let decl = self.parse_struct_decl()?;
+                    let decl = self.parse_struct_decl(module)?;
                     shader_ast.decls.push(Decl::Struct(decl));
                 }
-                Token::Ident(ident) if ident == Ident::new("instance") => {
+                Token::Ident(ident) if ident == Ident::new("instance") && module.is_none() => {
                     self.skip_token();
                     let decl = self.parse_instance_decl()?;
                     shader_ast.decls.push(Decl::Instance(decl));
                 }
-                Token::Ident(ident) if ident == Ident::new("texture") => {
+                Token::Ident(ident) if ident == Ident::new("texture") && module.is_none() => {
                     self.skip_token();
                     let decl = self.parse_texture_decl()?;
                     shader_ast.decls.push(Decl::Texture(decl));
                 }
-                Token::Ident(ident) if ident == Ident::new("uniform") => {
+                Token::Ident(ident) if ident == Ident::new("uniform") && module.is_none() => {
                     self.skip_token();
                     let decl = self.parse_uniform_decl()?;
                     shader_ast.decls.push(Decl::Uniform(decl));
                 }
-                Token::Ident(ident) if ident == Ident::new("varying") => {
+                Token::Ident(ident) if ident == Ident::new("varying") && module.is_none() => {
                     self.skip_token();
                     let decl = self.parse_varying_decl()?;
                     shader_ast.decls.push(Decl::Varying(decl));
                 }
-                Token::Ident(ident) if ident == Ident::new("debug") => {
+                Token::Ident(ident) if ident == Ident::new("debug") && module.is_none() => {
                     self.skip_token();
                     shader_ast.debug = true;
                 }
+                Token::Ident(ident)
+                    if ["geometry", "instance", "texture", "uniform", "varying", "debug"]
+                        .iter()
+                        .any(|keyword| ident == Ident::new(*keyword)) =>
+                {
+                    return Err(self
+                        .error(format!("`{}` declarations are not allowed inside modules", ident))
+                        .with_help("move the declaration to the top level, where every module can use it"));
+                }
                 token => return Err(self.error(format!("unexpected token while parsing shader `{}`", token))),
             }
         }
-        Ok(shader_ast)
+        Ok(())
     }
 
-    fn parse_const_decl(&mut self) -> Result<ConstDecl, ParseError> {
+    /// Parses the rest of `use a::b;`, `use a::*;` or `use a::{b, c};`, after the `use`.
+    fn parse_use_decl(&mut self, shader_ast: &mut ShaderAst, module: Option<Ident>) -> Result<(), ParseError> {
+        let span = self.begin_span();
+        let mut ident_path = IdentPath::from_ident(self.parse_ident()?);
+        loop {
+            if !self.accept_token(Token::PathSep) {
+                let span = span.end(self, |span| span);
+                shader_ast.use_decls.push(UseDecl { span, module, ident_path, is_glob: false });
+                break;
+            }
+            match self.peek_token() {
+                Token::Ident(ident) => {
+                    self.skip_token();
+                    if !ident_path.push(ident) {
+                        return Err(span.error(self, format!("path too long `{}`", ident_path)));
+                    }
+                }
+                Token::Star => {
+                    self.skip_token();
+                    let span = span.end(self, |span| span);
+                    shader_ast.use_decls.push(UseDecl { span, module, ident_path, is_glob: true });
+                    break;
+                }
+                Token::LeftBrace => {
+                    self.skip_token();
+                    loop {
+                        let span = self.begin_span();
+                        let mut ident_path = ident_path;
+                        if !ident_path.push(self.parse_ident()?) {
+                            return Err(span.error(self, format!("path too long `{}`", ident_path)));
+                        }
+                        let span = span.end(self, |span| span);
+                        shader_ast.use_decls.push(UseDecl { span, module, ident_path, is_glob: false });
+                        if !self.accept_token(Token::Comma) || self.peek_token() == Token::RightBrace {
+                            break;
+                        }
+                    }
+                    self.expect_token(Token::RightBrace)?;
+                    break;
+                }
+                token => return Err(self.error(format!("expected ident, `*` or `{{`, unexpected token `{}`", token))),
+            }
+        }
+        self.expect_token(Token::Semi)
+    }
+
+    fn parse_const_decl(&mut self, module: Option<Ident>) -> Result<ConstDecl, ParseError> {
         let span = self.begin_span();
         self.expect_token(Token::Const)?;
-        let ident = self.parse_ident()?;
+        let ident = self.parse_ident()?.in_module(module);
         self.expect_token(Token::Colon)?;
         let ty_expr = self.parse_ty_expr()?;
         self.expect_token(Token::Eq)?;
@@ -88,13 +166,13 @@ impl<'a> DeTokParserImpl<'a> {
         Ok(span.end(self, |span| ConstDecl { span, ident, ty_expr, expr }))
     }
 
-    fn parse_fn_decl(&mut self, prefix: Option<Ident>) -> Result<FnDecl, ParseError> {
+    fn parse_fn_decl(&mut self, module: Option<Ident>, prefix: Option<Ident>) -> Result<FnDecl, ParseError> {
         let span = self.begin_span();
         self.expect_token(Token::Fn)?;
         let ident_path = if let Some(prefix) = prefix {
-            IdentPath::from_two_idents(prefix, self.parse_ident()?)
+            IdentPath::from_two_idents(prefix.in_module(module), self.parse_ident()?)
         } else {
-            IdentPath::from_ident(self.parse_ident()?)
+            IdentPath::from_ident(self.parse_ident()?.in_module(module))
         };
 
         self.expect_token(Token::LeftParen)?;
@@ -142,6 +220,7 @@ impl<'a> DeTokParserImpl<'a> {
             instance_deps: RefCell::new(None),
             has_varying_deps: Cell::new(None),
             cons_fn_deps: RefCell::new(None),
+            struct_deps: RefCell::new(None),
             ident_path,
             params,
             return_ty_expr,
@@ -186,10 +265,10 @@ impl<'a> DeTokParserImpl<'a> {
         Ok(span.end(self, |span| UniformDecl { span, ident, ty_expr, block_ident }))
     }
 
-    fn parse_struct_decl(&mut self) -> Result<StructDecl, ParseError> {
+    fn parse_struct_decl(&mut self, module: Option<Ident>) -> Result<StructDecl, ParseError> {
         let span = self.begin_span();
         self.expect_token(Token::Struct)?;
-        let ident = self.parse_ident()?;
+        let ident = self.parse_ident()?.in_module(module);
         self.expect_token(Token::LeftBrace)?;
         let mut fields = Vec::new();
         loop {
@@ -199,7 +278,7 @@ impl<'a> DeTokParserImpl<'a> {
             }
         }
         self.expect_token(Token::RightBrace)?;
-        Ok(span.end(self, |span| StructDecl { span, ident, fields }))
+        Ok(span.end(self, |span| StructDecl { span, is_used: Cell::new(None), ident, fields }))
     }
 
     fn parse_varying_decl(&mut self) -> Result<VaryingDecl, ParseError> {
@@ -243,7 +322,10 @@ impl<'a> DeTokParserImpl<'a> {
             Token::For => self.parse_for_stmt(),
             Token::If => self.parse_if_stmt(),
             Token::Let => self.parse_let_stmt(),
+            Token::Loop => self.parse_loop_stmt(),
+            Token::Match => self.parse_match_stmt(),
             Token::Return => self.parse_return_stmt(),
+            Token::While => self.parse_while_stmt(),
             _ => self.parse_expr_stmt(),
         }
     }
@@ -292,6 +374,51 @@ impl<'a> DeTokParserImpl<'a> {
         Ok(span.end(self, |span| Stmt::If { span, expr, block_if_true, block_if_false }))
     }
 
+    fn parse_loop_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let span = self.begin_span();
+        self.expect_token(Token::Loop)?;
+        let block = Box::new(self.parse_block()?);
+        Ok(span.end(self, |span| Stmt::Loop { span, block }))
+    }
+
+    fn parse_match_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let span = self.begin_span();
+        self.expect_token(Token::Match)?;
+        let expr = self.parse_expr()?;
+        self.expect_token(Token::LeftBrace)?;
+        let mut arms = Vec::new();
+        while !self.accept_token(Token::RightBrace) {
+            arms.push(self.parse_match_arm()?);
+        }
+        Ok(span.end(self, |span| Stmt::Match { span, expr, arms }))
+    }
+
+    fn parse_match_arm(&mut self) -> Result<MatchArm, ParseError> {
+        let mut patterns = vec![self.parse_pattern()?];
+        while self.accept_token(Token::Pipe) {
+            patterns.push(self.parse_pattern()?);
+        }
+        self.expect_token(Token::FatArrow)?;
+        let block = self.parse_block()?;
+        self.accept_token(Token::Comma);
+        Ok(MatchArm { patterns, block })
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let span = self.begin_span();
+        match self.peek_token() {
+            Token::Lit(Lit::Int(val)) => {
+                self.skip_token();
+                Ok(span.end(self, |span| Pattern::Int { span, val }))
+            }
+            Token::Ident(ident) if ident == Ident::new("_") => {
+                self.skip_token();
+                Ok(span.end(self, |span| Pattern::Wildcard { span }))
+            }
+            token => Err(span.error(self, format!("expected integer literal or `_`, found `{}`", token))),
+        }
+    }
+
     fn parse_let_stmt(&mut self) -> Result<Stmt, ParseError> {
         let span = self.begin_span();
         self.expect_token(Token::Let)?;
@@ -302,6 +429,14 @@ impl<'a> DeTokParserImpl<'a> {
         Ok(span.end(self, |span| Stmt::Let { span, ty: RefCell::new(None), ident, ty_expr, expr }))
     }
 
+    fn parse_while_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let span = self.begin_span();
+        self.expect_token(Token::While)?;
+        let expr = self.parse_expr()?;
+        let block = Box::new(self.parse_block()?);
+        Ok(span.end(self, |span| Stmt::While { span, expr, block }))
+    }
+
     fn parse_return_stmt(&mut self) -> Result<Stmt, ParseError> {
         let span = self.begin_span();
         self.expect_token(Token::Return)?;
@@ -349,8 +484,10 @@ impl<'a> DeTokParserImpl<'a> {
                 self.skip_token();
                 Ok(span.end(self, |span| TyExpr { ty: RefCell::new(None), kind: TyExprKind::Lit { span, ty_lit } }))
             }
-            Token::Ident(ident) => {
-                self.skip_token();
+            Token::Ident(_) => {
+                // Paths to structs in modules are stored like the names of those structs, e.g. `df::Df`.
+                let ident_path = self.parse_ident_path()?;
+                let ident = ident_path.get_single().unwrap_or_else(|| Ident::new(ident_path.to_string()));
                 Ok(span.end(self, |span| TyExpr { ty: RefCell::new(None), kind: TyExprKind::Var { span, ident } }))
             }
             token => Err(span.error(self, format!("unexpected token `{}`", token))),
@@ -456,8 +593,80 @@ impl<'a> DeTokParserImpl<'a> {
 
     fn parse_rel_expr(&mut self) -> Result<Expr, ParseError> {
         let span = self.begin_span();
-        let mut acc = self.parse_add_expr()?;
+        let mut acc = self.parse_bit_or_expr()?;
         while let Some(op) = self.peek_token().to_rel_op() {
+            self.skip_token();
+            let left_expr = Box::new(acc);
+            let right_expr = Box::new(self.parse_bit_or_expr()?);
+            acc = span.end(self, |span| Expr {
+                span,
+                ty: RefCell::new(None),
+                const_val: RefCell::new(None),
+                const_index: Cell::new(None),
+                kind: ExprKind::Bin { span, op, left_expr, right_expr },
+            });
+        }
+        Ok(acc)
+    }
+
+    fn parse_bit_or_expr(&mut self) -> Result<Expr, ParseError> {
+        let span = self.begin_span();
+        let mut acc = self.parse_bit_xor_expr()?;
+        while let Some(op) = self.peek_token().to_bit_or_op() {
+            self.skip_token();
+            let left_expr = Box::new(acc);
+            let right_expr = Box::new(self.parse_bit_xor_expr()?);
+            acc = span.end(self, |span| Expr {
+                span,
+                ty: RefCell::new(None),
+                const_val: RefCell::new(None),
+                const_index: Cell::new(None),
+                kind: ExprKind::Bin { span, op, left_expr, right_expr },
+            });
+        }
+        Ok(acc)
+    }
+
+    fn parse_bit_xor_expr(&mut self) -> Result<Expr, ParseError> {
+        let span = self.begin_span();
+        let mut acc = self.parse_bit_and_expr()?;
+        while let Some(op) = self.peek_token().to_bit_xor_op() {
+            self.skip_token();
+            let left_expr = Box::new(acc);
+            let right_expr = Box::new(self.parse_bit_and_expr()?);
+            acc = span.end(self, |span| Expr {
+                span,
+                ty: RefCell::new(None),
+                const_val: RefCell::new(None),
+                const_index: Cell::new(None),
+                kind: ExprKind::Bin { span, op, left_expr, right_expr },
+            });
+        }
+        Ok(acc)
+    }
+
+    fn parse_bit_and_expr(&mut self) -> Result<Expr, ParseError> {
+        let span = self.begin_span();
+        let mut acc = self.parse_shift_expr()?;
+        while let Some(op) = self.peek_token().to_bit_and_op() {
+            self.skip_token();
+            let left_expr = Box::new(acc);
+            let right_expr = Box::new(self.parse_shift_expr()?);
+            acc = span.end(self, |span| Expr {
+                span,
+                ty: RefCell::new(None),
+                const_val: RefCell::new(None),
+                const_index: Cell::new(None),
+                kind: ExprKind::Bin { span, op, left_expr, right_expr },
+            });
+        }
+        Ok(acc)
+    }
+
+    fn parse_shift_expr(&mut self) -> Result<Expr, ParseError> {
+        let span = self.begin_span();
+        let mut acc = self.parse_add_expr()?;
+        while let Some(op) = self.peek_token().to_shift_op() {
             self.skip_token();
             let left_expr = Box::new(acc);
             let right_expr = Box::new(self.parse_add_expr()?);
@@ -707,6 +916,35 @@ impl Token {
         }
     }
 
+    fn to_bit_or_op(self) -> Option<BinOp> {
+        match self {
+            Token::Pipe => Some(BinOp::BitOr),
+            _ => None,
+        }
+    }
+
+    fn to_bit_xor_op(self) -> Option<BinOp> {
+        match self {
+            Token::Caret => Some(BinOp::BitXor),
+            _ => None,
+        }
+    }
+
+    fn to_bit_and_op(self) -> Option<BinOp> {
+        match self {
+            Token::Amp => Some(BinOp::BitAnd),
+            _ => None,
+        }
+    }
+
+    fn to_shift_op(self) -> Option<BinOp> {
+        match self {
+            Token::Shl => Some(BinOp::Shl),
+            Token::Shr => Some(BinOp::Shr),
+            _ => None,
+        }
+    }
+
     fn to_add_op(self) -> Option<BinOp> {
         match self {
             Token::Plus => Some(BinOp::Add),
@@ -719,6 +957,7 @@ impl Token {
         match self {
             Token::Star => Some(BinOp::Mul),
             Token::Slash => Some(BinOp::Div),
+            Token::Percent => Some(BinOp::Rem),
             _ => None,
         }
     }
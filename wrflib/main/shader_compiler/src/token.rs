@@ -22,6 +22,8 @@ pub(crate) enum Token {
     Not,
     NotEq,
     AndAnd,
+    Amp,
+    Percent,
     LeftParen,
     RightParen,
     Star,
@@ -32,6 +34,7 @@ pub(crate) enum Token {
     Minus,
     MinusEq,
     Arrow,
+    FatArrow,
     Dot,
     Slash,
     SlashEq,
@@ -40,11 +43,14 @@ pub(crate) enum Token {
     Semi,
     Lt,
     LtEq,
+    Shl,
     Eq,
     EqEq,
     Gt,
     GtEq,
+    Shr,
     Question,
+    Caret,
     Break,
     Const,
     Continue,
@@ -56,15 +62,19 @@ pub(crate) enum Token {
     //In,
     Inout,
     Let,
+    Loop,
+    Match,
     Return,
     //Crate,
     Splat,
     //Self_,
     Struct,
     //To,
+    While,
     LeftBracket,
     RightBracket,
     LeftBrace,
+    Pipe,
     OrOr,
     RightBrace,
     String(Ident),
@@ -80,6 +90,8 @@ impl fmt::Display for Token {
             Token::Not => write!(f, "!"),
             Token::NotEq => write!(f, "!="),
             Token::AndAnd => write!(f, "&&"),
+            Token::Amp => write!(f, "&"),
+            Token::Percent => write!(f, "%"),
             Token::LeftParen => write!(f, "("),
             Token::RightParen => write!(f, ")"),
             Token::Star => write!(f, "*"),
@@ -88,7 +100,8 @@ impl fmt::Display for Token {
             Token::PlusEq => write!(f, "+="),
             Token::Comma => write!(f, ","),
             Token::Minus => write!(f, "-"),
-            Token::Arrow => write!(f, "=>"),
+            Token::Arrow => write!(f, "->"),
+            Token::FatArrow => write!(f, "=>"),
             Token::Dot => write!(f, "."),
             Token::Splat => write!(f, ".."),
             Token::MinusEq => write!(f, "-="),
@@ -99,11 +112,14 @@ impl fmt::Display for Token {
             Token::Semi => write!(f, ";"),
             Token::Lt => write!(f, "<"),
             Token::LtEq => write!(f, "<="),
+            Token::Shl => write!(f, "<<"),
             Token::Eq => write!(f, "="),
             Token::EqEq => write!(f, "=="),
             Token::Gt => write!(f, ">"),
             Token::GtEq => write!(f, ">="),
+            Token::Shr => write!(f, ">>"),
             Token::Question => write!(f, "?"),
+            Token::Caret => write!(f, "^"),
             Token::Break => write!(f, "break"),
             Token::Const => write!(f, "const"),
             Token::Continue => write!(f, "continue"),
@@ -113,11 +129,15 @@ impl fmt::Display for Token {
             Token::If => write!(f, "if"),
             Token::Inout => write!(f, "inout"),
             Token::Let => write!(f, "let"),
+            Token::Loop => write!(f, "loop"),
+            Token::Match => write!(f, "match"),
             Token::Return => write!(f, "return"),
             Token::Struct => write!(f, "struct"),
+            Token::While => write!(f, "while"),
             Token::LeftBracket => write!(f, "["),
             Token::RightBracket => write!(f, "]"),
             Token::LeftBrace => write!(f, "{{"),
+            Token::Pipe => write!(f, "|"),
             Token::OrOr => write!(f, "||"),
             Token::RightBrace => write!(f, "}}"),
             Token::Ident(ident) => write!(f, "{}", ident),
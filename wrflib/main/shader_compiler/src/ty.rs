@@ -30,6 +30,8 @@ pub enum Ty {
     Mat3,
     Mat4,
     Texture2D,
+    TextureCube,
+    Texture3D,
     Array { elem_ty: Rc<Ty>, len: usize },
     Struct { ident: Ident },
 }
@@ -53,7 +55,9 @@ impl Ty {
             Ty::Mat2 => Some(TyLit::Mat2),
             Ty::Mat3 => Some(TyLit::Mat3),
             Ty::Mat4 => Some(TyLit::Mat4),
-            Ty::Texture2D => Some(TyLit::Bool),
+            Ty::Texture2D => Some(TyLit::Texture2D),
+            Ty::TextureCube => Some(TyLit::TextureCube),
+            Ty::Texture3D => Some(TyLit::Texture3D),
             Ty::Array { .. } => None,
             Ty::Struct { .. } => None,
         }
@@ -73,6 +77,10 @@ impl Ty {
         }
     }
 
+    pub fn is_texture(&self) -> bool {
+        matches!(self, Ty::Texture2D | Ty::TextureCube | Ty::Texture3D)
+    }
+
     pub fn is_matrix(&self) -> bool {
         match self {
             Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => true,
@@ -89,7 +97,7 @@ impl Ty {
             Ty::Bvec4 | Ty::Ivec4 | Ty::Vec4 | Ty::Mat2 => 4,
             Ty::Mat3 => 9,
             Ty::Mat4 => 16,
-            Ty::Texture2D { .. } => panic!(),
+            Ty::Texture2D | Ty::TextureCube | Ty::Texture3D => panic!(),
             Ty::Array { elem_ty, len } => elem_ty.size() * len,
             Ty::Struct { .. } => panic!(),
         }
@@ -116,6 +124,8 @@ impl fmt::Display for Ty {
             Ty::Mat3 => write!(f, "mat3"),
             Ty::Mat4 => write!(f, "mat4"),
             Ty::Texture2D => write!(f, "texture2D"),
+            Ty::TextureCube => write!(f, "textureCube"),
+            Ty::Texture3D => write!(f, "texture3D"),
             Ty::Array { elem_ty, len } => write!(f, "{}[{}]", elem_ty, len),
             Ty::Struct { ident, .. } => write!(f, "{}", ident),
         }
@@ -128,6 +138,14 @@ pub struct TyExpr {
     pub kind: TyExprKind,
 }
 
+impl TyExpr {
+    pub fn span(&self) -> Span {
+        match self.kind {
+            TyExprKind::Array { span, .. } | TyExprKind::Var { span, .. } | TyExprKind::Lit { span, .. } => span,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum TyExprKind {
     Array { span: Span, elem_ty_expr: Box<TyExpr>, len: u32 },
@@ -153,6 +171,8 @@ pub enum TyLit {
     Mat3,
     Mat4,
     Texture2D,
+    TextureCube,
+    Texture3D,
 }
 
 impl TyLit {
@@ -178,6 +198,8 @@ impl TyLit {
             TyLit::Mat3 => Ty::Mat3,
             TyLit::Mat4 => Ty::Mat4,
             TyLit::Texture2D => Ty::Texture2D,
+            TyLit::TextureCube => Ty::TextureCube,
+            TyLit::Texture3D => Ty::Texture3D,
         }
     }
 
@@ -213,6 +235,12 @@ impl TyLit {
         if ident == Ident::new("Texture2D") {
             return Some(TyLit::Texture2D);
         }
+        if ident == Ident::new("TextureCube") {
+            return Some(TyLit::TextureCube);
+        }
+        if ident == Ident::new("Texture3D") {
+            return Some(TyLit::Texture3D);
+        }
         if ident == Ident::new("BVec2") {
             return Some(TyLit::Bvec2);
         }
@@ -257,6 +285,8 @@ impl fmt::Display for TyLit {
                 TyLit::Mat3 => "mat3",
                 TyLit::Mat4 => "mat4",
                 TyLit::Texture2D => "texture2D",
+                TyLit::TextureCube => "textureCube",
+                TyLit::Texture3D => "texture3D",
             }
         )
     }
@@ -6,7 +6,7 @@
 
 use crate::builtin::Builtin;
 use crate::env::{Env, Sym, VarKind};
-use crate::error::ParseError;
+use crate::error::{ErrorCode, ParseError};
 use crate::ident::{Ident, IdentPath};
 use crate::lhs_check::LhsChecker;
 use crate::lit::Lit;
@@ -52,10 +52,10 @@ impl<'a> TyChecker<'a> {
         match self
             .env
             .find_sym(IdentPath::from_ident(ident))
-            .ok_or_else(|| ParseError { span, message: format!("`{}` is not defined in this scope", ident) })?
+            .ok_or_else(|| ParseError::new(ErrorCode::UndefinedName, span, format!("`{}` is not defined in this scope", ident)))?
         {
             Sym::TyVar { ty } => Ok(ty),
-            _ => Err(ParseError { span, message: format!("`{}` is not a type variable", ident) }),
+            _ => Err(ParseError::new(ErrorCode::UndefinedName, span, format!("`{}` is not a type variable", ident))),
         }
     }
 
@@ -63,12 +63,31 @@ impl<'a> TyChecker<'a> {
         Ok(ty_lit.to_ty())
     }
 
-    pub(crate) fn ty_check_expr_with_expected_ty(&mut self, span: Span, expr: &Expr, expected_ty: &Ty) -> Result<Ty, ParseError> {
+    /// If the types don't match, `expected_because` can point out where `expected_ty` comes from, e.g. a type annotation.
+    pub(crate) fn ty_check_expr_with_expected_ty(
+        &mut self,
+        expr: &Expr,
+        expected_ty: &Ty,
+        expected_because: Option<(Span, &str)>,
+    ) -> Result<Ty, ParseError> {
         let actual_ty = self.ty_check_expr(expr)?;
         if &actual_ty != expected_ty {
-            return Err(ParseError {
-                span,
-                message: format!("can't match expected type `{}` with actual type `{}", expected_ty, actual_ty),
+            let err = ParseError::new(
+                ErrorCode::TypeMismatch,
+                expr.span,
+                format!("can't match expected type `{}` with actual type `{}`", expected_ty, actual_ty),
+            );
+            let err = match expected_because {
+                Some((span, label)) => err.with_label(span, label),
+                None => err,
+            };
+            return Err(match (expected_ty, &actual_ty, &expr.kind) {
+                (Ty::Float, Ty::Int, ExprKind::Lit { lit: Lit::Int(value), .. }) => {
+                    err.with_help(format!("use a float literal instead: `{}.0`", value))
+                }
+                (Ty::Float, Ty::Int, _) => err.with_help("convert the value using `float(...)`"),
+                (Ty::Int, Ty::Float, _) => err.with_help("convert the value using `int(...)`"),
+                _ => err,
             });
         }
         Ok(actual_ty)
@@ -97,14 +116,14 @@ impl<'a> TyChecker<'a> {
 
     fn ty_check_cond_expr(
         &mut self,
-        span: Span,
+        _span: Span,
         expr: &Expr,
         expr_if_true: &Expr,
         expr_if_false: &Expr,
     ) -> Result<Ty, ParseError> {
-        self.ty_check_expr_with_expected_ty(span, expr, &Ty::Bool)?;
+        self.ty_check_expr_with_expected_ty(expr, &Ty::Bool, None)?;
         let ty_if_true = self.ty_check_expr(expr_if_true)?;
-        self.ty_check_expr_with_expected_ty(span, expr_if_false, &ty_if_true)?;
+        self.ty_check_expr_with_expected_ty(expr_if_false, &ty_if_true, Some((expr_if_true.span, "expected because of this")))?;
         Ok(ty_if_true)
     }
 
@@ -262,10 +281,33 @@ impl<'a> TyChecker<'a> {
                 (Ty::Mat4, Ty::Mat4) => Some(Ty::Mat4),
                 _ => None,
             },
+            BinOp::Rem | BinOp::BitOr | BinOp::BitXor | BinOp::BitAnd | BinOp::Shl | BinOp::Shr => match (&left_ty, &right_ty) {
+                (Ty::Int, Ty::Int) => Some(Ty::Int),
+                (Ty::Ivec2, Ty::Int) => Some(Ty::Ivec2),
+                (Ty::Ivec2, Ty::Ivec2) => Some(Ty::Ivec2),
+                (Ty::Ivec3, Ty::Int) => Some(Ty::Ivec3),
+                (Ty::Ivec3, Ty::Ivec3) => Some(Ty::Ivec3),
+                (Ty::Ivec4, Ty::Int) => Some(Ty::Ivec4),
+                (Ty::Ivec4, Ty::Ivec4) => Some(Ty::Ivec4),
+                _ => None,
+            },
         }
-        .ok_or_else(|| ParseError {
-            span,
-            message: format!("can't apply binary operator `{}` to operands of type `{}` and `{}", op, left_ty, right_ty),
+        .ok_or_else(|| {
+            let err = ParseError::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("can't apply binary operator `{}` to operands of type `{}` and `{}`", op, left_ty, right_ty),
+            );
+            match (op, &left_expr.kind) {
+                (BinOp::Assign, ExprKind::Var { ident_path, .. }) => match self.env.find_sym_span(*ident_path) {
+                    Some(declared_span) => {
+                        err.with_label(declared_span, format!("`{}` declared here with type `{}`", ident_path, left_ty))
+                    }
+                    None => err,
+                },
+                _ if op.is_int_only() => err.with_help(format!("`{}` only works on `int` and `ivecN` values", op)),
+                _ => err,
+            }
         })
     }
 
@@ -274,6 +316,10 @@ impl<'a> TyChecker<'a> {
         match op {
             UnOp::Not => match ty {
                 Ty::Bool => Some(Ty::Bool),
+                Ty::Int => Some(Ty::Int),
+                Ty::Ivec2 => Some(Ty::Ivec2),
+                Ty::Ivec3 => Some(Ty::Ivec3),
+                Ty::Ivec4 => Some(Ty::Ivec4),
                 _ => None,
             },
             UnOp::Neg => match ty {
@@ -285,7 +331,13 @@ impl<'a> TyChecker<'a> {
                 _ => None,
             },
         }
-        .ok_or_else(|| ParseError { span, message: format!("can't apply unary operator `{}` to operand of type `{}`", op, ty) })
+        .ok_or_else(|| {
+            ParseError::new(
+                ErrorCode::TypeMismatch,
+                span,
+                format!("can't apply unary operator `{}` to operand of type `{}`", op, ty),
+            )
+        })
     }
 
     fn ty_check_method_call_expr(&mut self, span: Span, ident: Ident, arg_exprs: &[Expr]) -> Result<Ty, ParseError> {
@@ -294,7 +346,11 @@ impl<'a> TyChecker<'a> {
             Ty::Struct { ident: struct_ident } => {
                 self.ty_check_call_expr(span, IdentPath::from_two(struct_ident, ident), arg_exprs)
             }
-            _ => Err(ParseError { span, message: format!("method `{}` is not defined on type `{}`", ident, ty) }),
+            _ => Err(ParseError::new(
+                ErrorCode::UndefinedName,
+                span,
+                format!("method `{}` is not defined on type `{}`", ident, ty),
+            )),
         }
     }
 
@@ -315,9 +371,12 @@ impl<'a> TyChecker<'a> {
                         }
                         true
                     })
-                    .ok_or_else(|| ParseError {
-                        span,
-                        message: format!("field `{}` is not defined on type `{}`", field_ident, ty),
+                    .ok_or_else(|| {
+                        ParseError::new(
+                            ErrorCode::UndefinedName,
+                            span,
+                            format!("field `{}` is not defined on type `{}`", field_ident, ty),
+                        )
                     })?;
                 Ok(match ty {
                     Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 => match swizzle.len() {
@@ -349,14 +408,20 @@ impl<'a> TyChecker<'a> {
                 .find_struct_decl(ident)
                 .unwrap()
                 .find_field(field_ident)
-                .ok_or(ParseError { span, message: format!("field `{}` is not defined on type `{}`", field_ident, ident) })?
+                .ok_or_else(|| {
+                    ParseError::new(
+                        ErrorCode::UndefinedName,
+                        span,
+                        format!("field `{}` is not defined on type `{}`", field_ident, ident),
+                    )
+                })?
                 .ty_expr
                 .ty
                 .borrow()
                 .as_ref()
                 .unwrap()
                 .clone()),
-            _ => Err(ParseError { span, message: format!("can't access field on value of type `{}`", ty) }),
+            _ => Err(ParseError::new(ErrorCode::TypeMismatch, span, format!("can't access field on value of type `{}`", ty))),
         }
     }
 
@@ -371,10 +436,10 @@ impl<'a> TyChecker<'a> {
             Ty::Mat3 => Ty::Vec3,
             Ty::Mat4 => Ty::Vec4,
             Ty::Array { elem_ty, len: _ } => elem_ty.as_ref().clone(),
-            _ => return Err(ParseError { span, message: format!("can't index into value of type `{}`", ty) }),
+            _ => return Err(ParseError::new(ErrorCode::TypeMismatch, span, format!("can't index into value of type `{}`", ty))),
         };
         if index_ty != Ty::Int {
-            return Err(ParseError { span, message: "index is not an integer".into() });
+            return Err(ParseError::new(ErrorCode::TypeMismatch, span, "index is not an integer"));
         }
         Ok(elem_ty)
     }
@@ -387,7 +452,7 @@ impl<'a> TyChecker<'a> {
         match self
             .env
             .find_sym(ident_path)
-            .ok_or_else(|| ParseError { span, message: format!("`{}` is not defined", ident_path) })?
+            .ok_or_else(|| ParseError::new(ErrorCode::UndefinedName, span, format!("`{}` is not defined", ident_path)))?
         {
             Sym::Builtin => {
                 let builtin = self.builtins.get(&ident_path.get_single().expect("unexpected")).unwrap();
@@ -403,33 +468,37 @@ impl<'a> TyChecker<'a> {
                             write!(message, "{}{}", sep, arg_ty).unwrap();
                             sep = ", ";
                         }
-                        ParseError { span, message }
+                        ParseError::new(ErrorCode::TypeMismatch, span, message)
                     })?
                     .clone())
             }
             Sym::Fn => {
                 let fn_decl = self.shader.find_fn_decl(ident_path).unwrap();
                 if arg_exprs.len() < fn_decl.params.len() {
-                    return Err(ParseError {
+                    return Err(ParseError::new(
+                        ErrorCode::TypeMismatch,
                         span,
-                        message: format!(
+                        format!(
                             "not enough arguments for call to function `{}`: expected {}, got {}",
                             ident_path,
                             fn_decl.params.len(),
                             arg_exprs.len(),
                         ),
-                    });
+                    )
+                    .with_label(fn_decl.span, "function defined here"));
                 }
                 if arg_exprs.len() > fn_decl.params.len() {
-                    return Err(ParseError {
+                    return Err(ParseError::new(
+                        ErrorCode::TypeMismatch,
                         span,
-                        message: format!(
+                        format!(
                             "too many arguments for call to function `{}`: expected {}, got {}",
                             ident_path,
                             fn_decl.params.len(),
                             arg_exprs.len()
                         ),
-                    });
+                    )
+                    .with_label(fn_decl.span, "function defined here"));
                 }
                 for (index, (arg_expr, param)) in arg_exprs.iter().zip(fn_decl.params.iter()).enumerate() {
                     let arg_ty = arg_expr.ty.borrow();
@@ -437,16 +506,18 @@ impl<'a> TyChecker<'a> {
                     let param_ty = param.ty_expr.ty.borrow();
                     let param_ty = param_ty.as_ref().unwrap();
                     if arg_ty != param_ty {
-                        return Err(ParseError {
-                            span,
-                            message: format!(
+                        return Err(ParseError::new(
+                            ErrorCode::TypeMismatch,
+                            arg_expr.span,
+                            format!(
                                 "wrong type for argument {} in call to function `{}`: expected `{}`, got `{}`",
                                 index + 1,
                                 ident_path,
                                 param_ty,
                                 arg_ty,
                             ),
-                        });
+                        )
+                        .with_label(param.span, "parameter declared here"));
                     }
                     if param.is_inout {
                         self.lhs_checker().lhs_check_expr(arg_expr)?;
@@ -454,7 +525,7 @@ impl<'a> TyChecker<'a> {
                 }
                 Ok(fn_decl.return_ty.borrow().as_ref().unwrap().clone())
             }
-            _ => Err(ParseError { span, message: format!("`{}` is not a function", ident_path) }),
+            _ => Err(ParseError::new(ErrorCode::UndefinedName, span, format!("`{}` is not a function", ident_path))),
         }
     }
 
@@ -475,43 +546,44 @@ impl<'a> TyChecker<'a> {
                 let expected_size = ty.size();
                 let actual_size = arg_tys.iter().map(|arg_ty| arg_ty.size()).sum::<usize>();
                 if actual_size < expected_size {
-                    return Err(ParseError {
+                    return Err(ParseError::new(
+                        ErrorCode::TypeMismatch,
                         span,
-                        message: format!(
+                        format!(
                             "not enough components for call to constructor `{}`: expected {}, got {}",
                             ty_lit, actual_size, expected_size,
                         ),
-                    });
+                    ));
                 }
                 if actual_size > expected_size {
-                    return Err(ParseError {
+                    return Err(ParseError::new(
+                        ErrorCode::TypeMismatch,
                         span,
-                        message: format!(
+                        format!(
                             "too many components for call to constructor `{}`: expected {}, got {}",
                             ty_lit, expected_size, actual_size,
                         ),
-                    });
+                    ));
                 }
                 Ok(ty.clone())
             }
-            _ => Err(ParseError {
+            _ => Err(ParseError::new(
+                ErrorCode::TypeMismatch,
                 span,
-                message: format!("can't construct value of type `{}` with arguments of types `{}`", ty, CommaSep(&arg_tys)),
-            }),
+                format!("can't construct value of type `{}` with arguments of types `{}`", ty, CommaSep(&arg_tys)),
+            )),
         }
     }
 
     fn ty_check_var_expr(&mut self, span: Span, kind: &Cell<Option<VarKind>>, ident_path: IdentPath) -> Result<Ty, ParseError> {
-        match self
-            .env
-            .find_sym(ident_path)
-            .ok_or_else(|| ParseError { span, message: format!("`{}` is not defined in this scope", ident_path) })?
-        {
+        match self.env.find_sym(ident_path).ok_or_else(|| {
+            ParseError::new(ErrorCode::UndefinedName, span, format!("`{}` is not defined in this scope", ident_path))
+        })? {
             Sym::Var { ref ty, kind: new_kind, .. } => {
                 kind.set(Some(new_kind));
                 Ok(ty.clone())
             }
-            _ => Err(ParseError { span, message: format!("`{}` is not a variable", ident_path) }),
+            _ => Err(ParseError::new(ErrorCode::UndefinedName, span, format!("`{}` is not a variable", ident_path))),
         }
     }
 
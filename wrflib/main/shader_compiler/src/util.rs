@@ -26,10 +26,13 @@ pub(crate) struct PrettyPrintedFloat(pub(crate) f32);
 
 impl fmt::Display for PrettyPrintedFloat {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.0.abs().fract() < 0.00000001 {
-            write!(f, "{}.0", self.0)
+        // Tiny values like `1e-10` have a fractional part too, even though it's close to 0, so check the printed
+        // string instead of using `fract()`.
+        let string = self.0.to_string();
+        if string.contains('.') {
+            write!(f, "{}", string)
         } else {
-            write!(f, "{}", self.0)
+            write!(f, "{}.0", string)
         }
     }
 }
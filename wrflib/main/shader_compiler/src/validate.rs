@@ -0,0 +1,124 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Validates generated shaders offline, so that errors show up in tests instead of at runtime on some user's GPU.
+//!
+//! Every generator gets run, which catches things that generators don't support. The WGSL output also gets
+//! parsed and validated using [naga](https://github.com/gfx-rs/naga), which checks types, control flow, and so
+//! on, and gets compiled to SPIR-V. There are no pure-Rust front-ends for GLSL ES 1.0 (which
+//! [`crate::generate_glsl`] produces), HLSL, or Metal, so their output doesn't get validated beyond that.
+
+use crate::generate_glsl;
+use crate::generate_hlsl;
+use crate::generate_metal;
+use crate::generate_wgsl;
+use crate::shaderast::ShaderAst;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Generates code for some platform, returning an error if the result turns out to be invalid.
+type Generator = fn(&ShaderAst) -> Result<(), String>;
+
+/// Runs all generators on `shader`, and validates their output where possible.
+///
+/// Returns a description of every problem that was found, one per generator.
+pub fn validate_shader(shader: &ShaderAst) -> Result<(), String> {
+    let mut errors = Vec::new();
+    let generators: [(&str, Generator); 4] = [
+        ("GLSL", |shader| {
+            generate_glsl::generate_vertex_shader(shader);
+            generate_glsl::generate_fragment_shader(shader);
+            Ok(())
+        }),
+        ("HLSL", |shader| {
+            generate_hlsl::generate_shader(shader);
+            Ok(())
+        }),
+        ("Metal", |shader| {
+            generate_metal::generate_shader(shader);
+            Ok(())
+        }),
+        ("SPIR-V", |shader| generate_spirv(shader).map(|_| ())),
+    ];
+    for (name, generate) in generators {
+        // The generators panic on anything they don't support.
+        let result = catch_unwind(AssertUnwindSafe(|| generate(shader))).unwrap_or_else(|payload| {
+            Err(payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|message| message.to_string()))
+                .unwrap_or_else(|| "unknown error".to_string()))
+        });
+        if let Err(err) = result {
+            errors.push(format!("{} generation failed: {}", name, err));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// Generates SPIR-V with both the vertex and fragment entry points of [`generate_wgsl`], after validating it.
+pub fn generate_spirv(shader: &ShaderAst) -> Result<Vec<u32>, String> {
+    let wgsl = generate_wgsl::generate_shader(shader);
+    let module = naga::front::wgsl::parse_str(&wgsl).map_err(|err| err.emit_to_string(&wgsl))?;
+    let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+        .validate(&module)
+        .map_err(|err| err.emit_to_string(&wgsl))?;
+    naga::back::spv::write_vec(&module, &info, &naga::back::spv::Options::default(), None).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_fragment::CodeFragment;
+    use crate::generate_shader_ast::ShaderAstGenerator;
+
+    fn shader_ast(code: &'static str) -> ShaderAst {
+        let code_fragment = CodeFragment::Static { filename: "test.rs", line: 1, col: 1, code };
+        ShaderAstGenerator::new().generate_shader_ast(&[code_fragment]).unwrap()
+    }
+
+    #[test]
+    fn valid_shader() {
+        let shader = shader_ast(
+            r#"
+            geometry geom: vec2;
+            instance color: vec4;
+            uniform zoom: float;
+            fn vertex() -> vec4 {
+                return vec4(geom * zoom, 0., 1.);
+            }
+            fn pixel() -> vec4 {
+                return color;
+            }
+            "#,
+        );
+        validate_shader(&shader).unwrap();
+        // SPIR-V magic number.
+        assert_eq!(generate_spirv(&shader).unwrap()[0], 0x07230203);
+    }
+
+    #[test]
+    fn unsupported_uniform_block() {
+        let shader = shader_ast(
+            r#"
+            geometry geom: vec2;
+            uniform zoom: float in custom;
+            fn vertex() -> vec4 {
+                return vec4(geom * zoom, 0., 1.);
+            }
+            fn pixel() -> vec4 {
+                return vec4(1.);
+            }
+            "#,
+        );
+        let err = validate_shader(&shader).unwrap_err();
+        assert!(err.contains("HLSL generation failed: extra uniform blocks not supported"), "{}", err);
+        assert!(err.contains("SPIR-V generation failed: extra uniform blocks not supported"), "{}", err);
+    }
+}
@@ -336,6 +336,9 @@ impl Area {
 
     /// Write a [`Texture`] value into the the [`DrawCall`] associated with this
     /// [`Area::InstanceRange`].
+    ///
+    /// Despite the name, this also works for `textureCube` and `texture3D` declarations, as long as the
+    /// texture was created as such (see [`Texture::from_cube_faces`] and [`Texture::from_voxels`]).
     pub fn write_texture_2d(&self, cx: &mut Cx, name: &str, texture_handle: TextureHandle) {
         if self.is_valid(cx) {
             if let Area::InstanceRange(inst) = self {
@@ -344,12 +347,14 @@ impl Area {
                 let sh = &cx.shaders[draw_call.shader_id];
                 for (index, prop) in sh.mapping.textures.iter().enumerate() {
                     if prop.name == name {
+                        let kind = cx.textures[texture_handle.texture_id as usize].desc.kind;
+                        assert!(kind.matches_ty(&prop.ty), "Texture prop {} is a {}, but got a {:?} texture", name, prop.ty, kind);
                         draw_call.textures_2d[index] = texture_handle.texture_id as u32;
                         return;
                     }
                 }
             }
         }
-        panic!("Cannot find texture2D prop {}", name)
+        panic!("Cannot find texture prop {}", name)
     }
 }
@@ -180,6 +180,9 @@ pub struct Cx {
     /// See also [`Signal`] and [`SignalEvent`].
     pub(crate) signals: HashMap<Signal, BTreeSet<StatusId>>,
 
+    /// Passes whose pixels should be read back after they get painted; see [`Pass::request_readback`].
+    pub(crate) pass_readback_requests: Vec<usize>,
+
     /// A map from profile IDs to [`UniversalInstant`], for keeping track of how long things
     /// take.
     pub(crate) profiles: HashMap<u64, UniversalInstant>,
@@ -201,6 +204,9 @@ pub struct Cx {
     /// The user's event handler. Storing it like this cuts the compile time of an end-user application in half.
     pub(crate) event_handler: Option<*mut dyn FnMut(&mut Cx, &mut Event)>,
 
+    /// See [`Cx::start_event_recording`].
+    pub(crate) event_recorder: Option<EventRecorder>,
+
     /// Fonts specific data
     /// It might be possible for fonts data to be shared between different threads, so
     /// we need to make use of locks.
@@ -289,14 +295,17 @@ pub(crate) const NUM_POINTERS: usize = 10;
 
 impl Cx {
     pub fn new(app_type_id: TypeId) -> Self {
+        #[cfg(feature = "shader-validation")]
+        Shader::validate_registered_once();
+
         let mut pointers = Vec::new();
         pointers.resize(NUM_POINTERS, CxPerPointer::default());
 
         let textures = vec![CxTexture {
-            desc: TextureDesc { format: TextureFormat::ImageRGBA, width: Some(4), height: Some(4), multisample: None },
+            desc: TextureDesc { width: Some(4), height: Some(4), ..TextureDesc::default() },
             image_u32: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
             update_image: true,
-            platform: CxPlatformTexture::default(),
+            ..CxTexture::default()
         }];
 
         // We try to initialize Vecs with some reasonable capacity, to prevent reallocations.
@@ -350,12 +359,16 @@ impl Cx {
 
             signals: HashMap::new(),
 
+            pass_readback_requests: Vec::new(),
+
             panic_redraw: false,
 
             platform: CxPlatform::default(),
 
             event_handler: None,
 
+            event_recorder: None,
+
             temp_default_data: Vec::with_capacity(1000),
 
             debug_flags: Default::default(),
@@ -622,6 +635,7 @@ impl Cx {
     }
 
     pub(crate) fn call_event_handler(&mut self, event: &mut Event) {
+        self.record_event(event);
         let event_handler = self.event_handler.unwrap();
 
         unsafe {
@@ -739,7 +753,11 @@ impl Cx {
             let mut signals = HashMap::new();
             std::mem::swap(&mut self.signals, &mut signals);
 
+            // Signals sent using `send_signal` will be sent again when replaying the events that caused them, so
+            // don't record them.
+            let event_recorder = self.event_recorder.take();
             self.call_event_handler(&mut Event::Signal(SignalEvent { signals }));
+            self.event_recorder = event_recorder;
 
             if counter > 100 {
                 println!("Signal feedback loop detected");
@@ -748,6 +766,14 @@ impl Cx {
         }
     }
 
+    /// Takes the readback requests of passes that have been painted, leaving the others for a later paint.
+    pub(crate) fn take_painted_pass_readback_requests(&mut self) -> Vec<usize> {
+        let (painted, unpainted) =
+            std::mem::take(&mut self.pass_readback_requests).into_iter().partition(|&pass_id| !self.passes[pass_id].paint_dirty);
+        self.pass_readback_requests = unpainted;
+        painted
+    }
+
     pub const STATUS_HTTP_SEND_OK: StatusId = location_hash!();
     pub const STATUS_HTTP_SEND_FAIL: StatusId = location_hash!();
 
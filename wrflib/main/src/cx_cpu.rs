@@ -0,0 +1,618 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Software rasterizer, used by the headless platform (see `cx_headless`).
+//!
+//! It walks the draw tree just like the GPU backends (see e.g. `cx_opengl`), but runs the
+//! shaders using [`ShaderInterpreter`] and rasterizes the triangles itself. It follows OpenGL
+//! conventions where possible: premultiplied alpha blending, `LEQUAL` depth testing, pixel
+//! centers at half-pixel offsets, and a top-left fill rule so that triangles that share an edge
+//! don't paint the same pixel twice.
+//!
+//! Render targets are stored with the top row first, just like the images in
+//! [`CxTexture::image_u32`].
+
+use crate::*;
+use wrflib_shader_compiler::interpret::{ShaderInterpreter, TextureSampler};
+
+/// An in-memory color and depth buffer for a window.
+#[derive(Clone, Default)]
+pub(crate) struct CpuFramebuffer {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) color: Vec<u32>,
+    pub(crate) depth: Vec<f32>,
+}
+
+impl CpuFramebuffer {
+    pub(crate) fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.color.resize(width * height, 0);
+        self.depth.resize(width * height, 1.0);
+    }
+}
+
+/// What we're currently rendering into. When `depth` is [`None`], depth testing is disabled.
+struct CpuRenderTarget<'a> {
+    width: usize,
+    height: usize,
+    color: &'a mut [u32],
+    depth: Option<&'a mut [f32]>,
+    /// Gets increased by `zbias_step` for every [`DrawCall`]; see [`CxPass::zbias_step`].
+    zbias: f32,
+    zbias_step: f32,
+}
+
+impl Cx {
+    fn render_view(&mut self, pass_id: usize, view_id: usize, scroll: Vec2, clip: (Vec2, Vec2), target: &mut CpuRenderTarget) {
+        // tad ugly otherwise the borrow checker locks 'self' and we can't recur
+        let draw_calls_len = self.views[view_id].draw_calls_len;
+        self.views[view_id].parent_scroll = scroll;
+        let local_scroll = self.views[view_id].snapped_scroll;
+        let clip = self.views[view_id].intersect_clip(clip);
+        for draw_call_id in 0..draw_calls_len {
+            let sub_view_id = self.views[view_id].draw_calls[draw_call_id].sub_view_id;
+            if sub_view_id != 0 {
+                self.render_view(
+                    pass_id,
+                    sub_view_id,
+                    Vec2 { x: local_scroll.x + scroll.x, y: local_scroll.y + scroll.y },
+                    clip,
+                    target,
+                );
+            } else {
+                let gpu_geometry_id = GpuGeometry::get_id(self, view_id, draw_call_id);
+
+                let cxview = &mut self.views[view_id];
+                let draw_call = &mut cxview.draw_calls[draw_call_id];
+                let sh = &mut self.shaders[draw_call.shader_id];
+
+                draw_call.set_zbias(target.zbias);
+                draw_call.set_local_scroll(scroll, local_scroll);
+                draw_call.set_clip(clip);
+                target.zbias += target.zbias_step;
+
+                draw_call.instance_dirty = false;
+                draw_call.uniforms_dirty = false;
+
+                let geometry = &mut self.gpu_geometries[gpu_geometry_id];
+                geometry.dirty = false;
+
+                for texture_id in &draw_call.textures_2d {
+                    self.textures[*texture_id as usize].update_image = false;
+                }
+
+                let interpreter = &mut sh.platform.as_mut().unwrap().interpreter;
+                interpreter.set_uniforms(
+                    self.passes[pass_id].pass_uniforms.as_slice(),
+                    cxview.view_uniforms.as_slice(),
+                    draw_call.draw_uniforms.as_slice(),
+                    &draw_call.user_uniforms,
+                );
+
+                let sampler = CpuTextureSampler { textures: &self.textures, texture_ids: &draw_call.textures_2d };
+                let geometry_slots = sh.mapping.geometry_props.total_slots;
+                let instance_slots = sh.mapping.instance_props.total_slots;
+                let vertices = geometry.geometry.vertices_f32_slice();
+                let indices = geometry.geometry.indices_u32_slice();
+                let vertex_count = vertices.len().checked_div(geometry_slots).unwrap_or(0);
+                let varyings_size = interpreter.packed_varyings_size();
+
+                let mut positions = vec![[0.0; 4]; vertex_count];
+                let mut varyings = vec![0.0; vertex_count * varyings_size];
+                let mut scratch = CpuPixelScratch::new(varyings_size);
+                for instance in draw_call.instances.chunks_exact(instance_slots) {
+                    for vertex_index in 0..vertex_count {
+                        positions[vertex_index] = interpreter.vertex(
+                            &vertices[vertex_index * geometry_slots..(vertex_index + 1) * geometry_slots],
+                            instance,
+                            &sampler,
+                            &mut varyings[vertex_index * varyings_size..(vertex_index + 1) * varyings_size],
+                        );
+                    }
+                    for triangle in indices.chunks_exact(3) {
+                        let triangle = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+                        rasterize_triangle(
+                            target,
+                            [positions[triangle[0]], positions[triangle[1]], positions[triangle[2]]],
+                            [
+                                &varyings[triangle[0] * varyings_size..(triangle[0] + 1) * varyings_size],
+                                &varyings[triangle[1] * varyings_size..(triangle[1] + 1) * varyings_size],
+                                &varyings[triangle[2] * varyings_size..(triangle[2] + 1) * varyings_size],
+                            ],
+                            interpreter,
+                            &sampler,
+                            &mut scratch,
+                        );
+                    }
+                }
+            }
+        }
+        self.debug_draw_tree(view_id);
+    }
+
+    pub(crate) fn draw_pass_to_framebuffer(&mut self, pass_id: usize, dpi_factor: f32, framebuffer: &mut CpuFramebuffer) {
+        let view_id = self.passes[pass_id].main_view_id.unwrap();
+        let pass_size = self.passes[pass_id].pass_size;
+        self.passes[pass_id].set_matrix(Vec2::default(), pass_size);
+        self.passes[pass_id].set_dpi_factor(dpi_factor);
+
+        let clear_color = if self.passes[pass_id].color_textures.is_empty() {
+            Vec4::default()
+        } else {
+            match self.passes[pass_id].color_textures[0].clear_color {
+                ClearColor::InitWith(color) => color,
+                ClearColor::ClearWith(color) => color,
+            }
+        };
+        let clear_depth = match self.passes[pass_id].clear_depth {
+            ClearDepth::InitWith(depth) => depth,
+            ClearDepth::ClearWith(depth) => depth,
+        };
+        framebuffer.color.fill(color_to_u32(clear_color));
+        framebuffer.depth.fill(clear_depth as f32);
+
+        let mut target = CpuRenderTarget {
+            width: framebuffer.width,
+            height: framebuffer.height,
+            color: &mut framebuffer.color,
+            depth: Some(&mut framebuffer.depth),
+            zbias: 0.0,
+            zbias_step: self.passes[pass_id].zbias_step,
+        };
+        self.render_view(
+            pass_id,
+            view_id,
+            Vec2::default(),
+            (Vec2 { x: -50000., y: -50000. }, Vec2 { x: 50000., y: 50000. }),
+            &mut target,
+        );
+    }
+
+    pub(crate) fn draw_pass_to_texture(&mut self, pass_id: usize, inherit_dpi_factor: f32) {
+        let pass_size = self.passes[pass_id].pass_size;
+        self.passes[pass_id].set_matrix(Vec2::default(), pass_size);
+        self.passes[pass_id].paint_dirty = false;
+
+        let dpi_factor = if let Some(override_dpi_factor) = self.passes[pass_id].override_dpi_factor {
+            override_dpi_factor
+        } else {
+            inherit_dpi_factor
+        };
+        self.passes[pass_id].set_dpi_factor(dpi_factor);
+
+        // Temporarily take the buffers out of the textures, so we can still sample from other
+        // textures while rendering.
+        let mut depth = None;
+        if let Some(depth_texture_id) = self.passes[pass_id].depth_texture {
+            let cxtexture = &mut self.textures[depth_texture_id as usize];
+            let reallocated = cpu_update_render_target(cxtexture, dpi_factor, pass_size);
+            match self.passes[pass_id].clear_depth {
+                ClearDepth::InitWith(depth_clear) => {
+                    if reallocated {
+                        cxtexture.platform.depth.fill(depth_clear as f32);
+                    }
+                }
+                ClearDepth::ClearWith(depth_clear) => cxtexture.platform.depth.fill(depth_clear as f32),
+            }
+            depth = Some(std::mem::take(&mut cxtexture.platform.depth));
+        }
+
+        for color_texture in &self.passes[pass_id].color_textures {
+            let cxtexture = &mut self.textures[color_texture.texture_id as usize];
+            let reallocated = cpu_update_render_target(cxtexture, dpi_factor, pass_size);
+            match color_texture.clear_color {
+                ClearColor::InitWith(color) => {
+                    if reallocated {
+                        cxtexture.image_u32.fill(color_to_u32(color));
+                    }
+                }
+                ClearColor::ClearWith(color) => cxtexture.image_u32.fill(color_to_u32(color)),
+            }
+        }
+
+        // Shaders only have a single output, so we only ever render into the first color texture.
+        let color_texture_id = self.passes[pass_id].color_textures.first().map(|color_texture| color_texture.texture_id as usize);
+        let (width, height, mut color) = match color_texture_id {
+            Some(texture_id) => {
+                let cxtexture = &mut self.textures[texture_id];
+                (cxtexture.platform.width, cxtexture.platform.height, std::mem::take(&mut cxtexture.image_u32))
+            }
+            None => {
+                let width = (pass_size.x * dpi_factor) as usize;
+                let height = (pass_size.y * dpi_factor) as usize;
+                (width, height, vec![0; width * height])
+            }
+        };
+        if let Some(depth) = &depth {
+            // A depth texture with different dimensions than the color texture would be a
+            // programming error, but let's not crash on it.
+            if depth.len() != width * height {
+                println!("draw_pass_to_texture: depth texture size doesn't match color texture size");
+            }
+        }
+
+        let view_id = self.passes[pass_id].main_view_id.unwrap();
+        let mut target = CpuRenderTarget {
+            width,
+            height,
+            color: &mut color,
+            depth: depth.as_mut().filter(|depth| depth.len() == width * height).map(|depth| depth.as_mut_slice()),
+            zbias: 0.0,
+            zbias_step: self.passes[pass_id].zbias_step,
+        };
+        self.render_view(
+            pass_id,
+            view_id,
+            Vec2::default(),
+            (Vec2 { x: -50000., y: -50000. }, Vec2 { x: 50000., y: 50000. }),
+            &mut target,
+        );
+
+        if let Some(texture_id) = color_texture_id {
+            self.textures[texture_id].image_u32 = color;
+        }
+        if let (Some(depth_texture_id), Some(depth)) = (self.passes[pass_id].depth_texture, depth) {
+            self.textures[depth_texture_id as usize].platform.depth = depth;
+        }
+    }
+
+    pub(crate) fn cpu_compile_shaders(&mut self) {
+        for shader_id in self.shader_recompile_ids.drain(..) {
+            let shader = &mut self.shaders[shader_id];
+            let shader_ast = shader.shader_ast.take().unwrap();
+            shader.platform = Some(CxPlatformShader { interpreter: ShaderInterpreter::new(shader_ast) });
+        }
+    }
+}
+
+/// Make sure the buffer of a render target has the right size. Returns true if it had to be
+/// (re)allocated.
+fn cpu_update_render_target(cxtexture: &mut CxTexture, dpi_factor: f32, size: Vec2) -> bool {
+    let width = cxtexture.desc.width.unwrap_or((size.x * dpi_factor) as usize);
+    let height = cxtexture.desc.height.unwrap_or((size.y * dpi_factor) as usize);
+    let len = width * height;
+    let allocated_len = match cxtexture.desc.format {
+        TextureFormat::Depth32Stencil8 => cxtexture.platform.depth.len(),
+        _ => cxtexture.image_u32.len(),
+    };
+    if cxtexture.platform.width == width && cxtexture.platform.height == height && allocated_len == len {
+        return false;
+    }
+    cxtexture.platform.width = width;
+    cxtexture.platform.height = height;
+    match cxtexture.desc.format {
+        TextureFormat::Depth32Stencil8 => cxtexture.platform.depth.resize(len, 1.0),
+        _ => cxtexture.image_u32.resize(len, 0),
+    }
+    true
+}
+
+fn color_to_u32(color: Vec4) -> u32 {
+    let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u32;
+    to_byte(color.x) | (to_byte(color.y) << 8) | (to_byte(color.z) << 16) | (to_byte(color.w) << 24)
+}
+
+fn u32_to_color(color: u32) -> [f32; 4] {
+    [
+        (color & 0xff) as f32 / 255.0,
+        ((color >> 8) & 0xff) as f32 / 255.0,
+        ((color >> 16) & 0xff) as f32 / 255.0,
+        ((color >> 24) & 0xff) as f32 / 255.0,
+    ]
+}
+
+/// Samples textures with the filtering and wrapping from their [`TextureDesc`], like the GPU platforms.
+/// 3D textures and cube map faces always get clamped to their edges.
+struct CpuTextureSampler<'a> {
+    textures: &'a [CxTexture],
+    texture_ids: &'a [u32],
+}
+
+impl<'a> CpuTextureSampler<'a> {
+    /// Returns the texture and its width and height, if it has any data.
+    fn texture(&self, texture_index: usize) -> Option<(&CxTexture, usize, usize)> {
+        let cxtexture = &self.textures[*self.texture_ids.get(texture_index)? as usize];
+        let width = cxtexture.desc.width.unwrap_or(cxtexture.platform.width);
+        let height = cxtexture.desc.height.unwrap_or(cxtexture.platform.height);
+        let format = cxtexture.desc.format;
+        let len = width * height * cxtexture.desc.kind.layers() * format.channels();
+        let available_len = match format {
+            TextureFormat::R8 | TextureFormat::RG8 => cxtexture.image_u8.len(),
+            TextureFormat::R32F | TextureFormat::RGBA16F | TextureFormat::RGBA32F => cxtexture.image_f32.len(),
+            _ => cxtexture.image_u32.len(),
+        };
+        if width == 0 || height == 0 || available_len < len {
+            return None;
+        }
+        Some((cxtexture, width, height))
+    }
+}
+
+impl<'a> TextureSampler for CpuTextureSampler<'a> {
+    fn sample_2d(&self, texture_index: usize, x: f32, y: f32) -> [f32; 4] {
+        match self.texture(texture_index) {
+            Some((cxtexture, width, height)) if cxtexture.desc.kind == TextureKind::D2 => {
+                sample_image(cxtexture, 0, width, height, x, y, cxtexture.desc.wrap)
+            }
+            _ => [0.0; 4],
+        }
+    }
+
+    fn sample_3d(&self, texture_index: usize, x: f32, y: f32, z: f32) -> [f32; 4] {
+        let (cxtexture, width, height, depth) = match self.texture(texture_index) {
+            Some((cxtexture, width, height)) => match cxtexture.desc.kind {
+                TextureKind::D3 { depth } if depth > 0 => (cxtexture, width, height, depth),
+                _ => return [0.0; 4],
+            },
+            None => return [0.0; 4],
+        };
+        let slice = |z: usize| sample_image(cxtexture, z * width * height, width, height, x, y, TextureWrap::ClampToEdge);
+        if cxtexture.desc.filter == TextureFilter::Nearest {
+            return slice(((z * depth as f32).floor() as i64).clamp(0, depth as i64 - 1) as usize);
+        }
+        let tz = (z * depth as f32 - 0.5).clamp(0.0, (depth - 1) as f32);
+        let z0 = tz.floor() as usize;
+        let z1 = (z0 + 1).min(depth - 1);
+        let fz = tz - z0 as f32;
+        let (c0, c1) = (slice(z0), slice(z1));
+        let mut out = [0.0; 4];
+        for i in 0..4 {
+            out[i] = c0[i] * (1.0 - fz) + c1[i] * fz;
+        }
+        out
+    }
+
+    fn sample_cube(&self, texture_index: usize, x: f32, y: f32, z: f32) -> [f32; 4] {
+        let (cxtexture, size) = match self.texture(texture_index) {
+            Some((cxtexture, width, _)) if cxtexture.desc.kind == TextureKind::Cube => (cxtexture, width),
+            _ => return [0.0; 4],
+        };
+        // Pick the face and coordinates on it like OpenGL does, see "Cube Map Texture Selection" in the spec.
+        let (face, major, s, t) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+            if x >= 0.0 {
+                (0, x, -z, -y)
+            } else {
+                (1, x, z, -y)
+            }
+        } else if y.abs() >= z.abs() {
+            if y >= 0.0 {
+                (2, y, x, z)
+            } else {
+                (3, y, x, -z)
+            }
+        } else if z >= 0.0 {
+            (4, z, x, -y)
+        } else {
+            (5, z, -x, -y)
+        };
+        if major == 0.0 {
+            return [0.0; 4];
+        }
+        let major = major.abs();
+        let (s, t) = ((s / major + 1.0) / 2.0, (t / major + 1.0) / 2.0);
+        sample_image(cxtexture, face * size * size, size, size, s, t, TextureWrap::ClampToEdge)
+    }
+}
+
+/// Returns pixel `index` of a texture as the color that shaders would get, see [`TextureFormat`].
+fn texel(cxtexture: &CxTexture, index: usize) -> [f32; 4] {
+    let (u8s, f32s) = (&cxtexture.image_u8, &cxtexture.image_f32);
+    match cxtexture.desc.format {
+        TextureFormat::ImageRGBA => u32_to_color(cxtexture.image_u32[index]),
+        TextureFormat::R8 => [u8s[index] as f32 / 255.0, 0.0, 0.0, 1.0],
+        TextureFormat::RG8 => [u8s[index * 2] as f32 / 255.0, u8s[index * 2 + 1] as f32 / 255.0, 0.0, 1.0],
+        TextureFormat::R32F => [f32s[index], 0.0, 0.0, 1.0],
+        TextureFormat::RGBA16F | TextureFormat::RGBA32F => {
+            [f32s[index * 4], f32s[index * 4 + 1], f32s[index * 4 + 2], f32s[index * 4 + 3]]
+        }
+        TextureFormat::Depth32Stencil8 => [0.0; 4],
+    }
+}
+
+/// Samples the `width` by `height` image that starts at pixel `offset` of `cxtexture` at normalized
+/// coordinates, with the filtering of the texture.
+fn sample_image(
+    cxtexture: &CxTexture,
+    offset: usize,
+    width: usize,
+    height: usize,
+    x: f32,
+    y: f32,
+    wrap: TextureWrap,
+) -> [f32; 4] {
+    let pixel = |x: f32, y: f32| {
+        let (x, y) = match wrap {
+            TextureWrap::Repeat => ((x as i64).rem_euclid(width as i64) as usize, (y as i64).rem_euclid(height as i64) as usize),
+            TextureWrap::ClampToEdge => {
+                ((x as i64).clamp(0, width as i64 - 1) as usize, (y as i64).clamp(0, height as i64 - 1) as usize)
+            }
+        };
+        texel(cxtexture, offset + y * width + x)
+    };
+    let tx = x * width as f32 - 0.5;
+    let ty = y * height as f32 - 0.5;
+    if cxtexture.desc.filter == TextureFilter::Nearest {
+        return pixel((tx + 0.5).floor(), (ty + 0.5).floor());
+    }
+    let x0 = tx.floor();
+    let y0 = ty.floor();
+    let fx = tx - x0;
+    let fy = ty - y0;
+    let c00 = pixel(x0, y0);
+    let c10 = pixel(x0 + 1.0, y0);
+    let c01 = pixel(x0, y0 + 1.0);
+    let c11 = pixel(x0 + 1.0, y0 + 1.0);
+    let mut out = [0.0; 4];
+    for i in 0..4 {
+        let top = c00[i] * (1.0 - fx) + c10[i] * fx;
+        let bottom = c01[i] * (1.0 - fx) + c11[i] * fx;
+        out[i] = top * (1.0 - fy) + bottom * fy;
+    }
+    out
+}
+
+/// Buffers for interpolated varyings, reused between pixels to avoid allocations.
+struct CpuPixelScratch {
+    varyings: Vec<f32>,
+    varyings_dx: Vec<f32>,
+    varyings_dy: Vec<f32>,
+}
+
+impl CpuPixelScratch {
+    fn new(varyings_size: usize) -> Self {
+        Self { varyings: vec![0.0; varyings_size], varyings_dx: vec![0.0; varyings_size], varyings_dy: vec![0.0; varyings_size] }
+    }
+}
+
+/// Signed area function: positive when `p` lies to the right of the edge from `a` to `b` (with
+/// y pointing down), when walking along the edge.
+fn edge_function(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// Decides which of two triangles sharing an edge gets the pixels exactly on that edge. Shared
+/// edges are walked in opposite directions by the two triangles, so exactly one of them wins.
+fn edge_owns_ties(a: [f32; 2], b: [f32; 2]) -> bool {
+    let dx = b[0] - a[0];
+    let dy = b[1] - a[1];
+    dy < 0.0 || (dy == 0.0 && dx > 0.0)
+}
+
+/// Computes screen space barycentric coordinates for `p`.
+fn barycentrics(screen: &[[f32; 2]; 3], area: f32, p: [f32; 2]) -> [f32; 3] {
+    [
+        edge_function(screen[1], screen[2], p) / area,
+        edge_function(screen[2], screen[0], p) / area,
+        edge_function(screen[0], screen[1], p) / area,
+    ]
+}
+
+/// Perspective-correct interpolation of varyings, given screen space barycentric coordinates.
+fn interpolate(lambdas: [f32; 3], inv_w: &[f32; 3], varyings: &[&[f32]; 3], out: &mut [f32]) {
+    let weights = [lambdas[0] * inv_w[0], lambdas[1] * inv_w[1], lambdas[2] * inv_w[2]];
+    let sum = weights[0] + weights[1] + weights[2];
+    let weights = [weights[0] / sum, weights[1] / sum, weights[2] / sum];
+    for (index, out) in out.iter_mut().enumerate() {
+        *out = varyings[0][index] * weights[0] + varyings[1][index] * weights[1] + varyings[2][index] * weights[2];
+    }
+}
+
+fn rasterize_triangle(
+    target: &mut CpuRenderTarget,
+    clip_positions: [[f32; 4]; 3],
+    varyings: [&[f32]; 3],
+    interpreter: &mut ShaderInterpreter,
+    sampler: &CpuTextureSampler,
+    scratch: &mut CpuPixelScratch,
+) {
+    // We don't do proper clipping against the near plane; triangles that cross it are skipped.
+    if clip_positions.iter().any(|position| position[3] <= 0.0) {
+        return;
+    }
+
+    let mut screen = [[0.0; 2]; 3];
+    let mut depths = [0.0; 3];
+    let mut inv_w = [0.0; 3];
+    for i in 0..3 {
+        let [x, y, z, w] = clip_positions[i];
+        inv_w[i] = 1.0 / w;
+        screen[i] = [(x * inv_w[i] * 0.5 + 0.5) * target.width as f32, (0.5 - y * inv_w[i] * 0.5) * target.height as f32];
+        depths[i] = z * inv_w[i];
+    }
+
+    // Make the winding consistent, so that "inside" always means all edge functions positive.
+    let mut varyings = varyings;
+    let mut area = edge_function(screen[0], screen[1], screen[2]);
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+    if area < 0.0 {
+        screen.swap(1, 2);
+        depths.swap(1, 2);
+        inv_w.swap(1, 2);
+        varyings.swap(1, 2);
+        area = -area;
+    }
+
+    let min_x = screen.iter().map(|p| p[0]).fold(f32::INFINITY, f32::min).floor().max(0.0) as usize;
+    let min_y = screen.iter().map(|p| p[1]).fold(f32::INFINITY, f32::min).floor().max(0.0) as usize;
+    let max_x = (screen.iter().map(|p| p[0]).fold(f32::NEG_INFINITY, f32::max).ceil().max(0.0) as usize).min(target.width);
+    let max_y = (screen.iter().map(|p| p[1]).fold(f32::NEG_INFINITY, f32::max).ceil().max(0.0) as usize).min(target.height);
+
+    let owns_ties =
+        [edge_owns_ties(screen[1], screen[2]), edge_owns_ties(screen[2], screen[0]), edge_owns_ties(screen[0], screen[1])];
+
+    for py in min_y..max_y {
+        for px in min_x..max_x {
+            let p = [px as f32 + 0.5, py as f32 + 0.5];
+            let lambdas = barycentrics(&screen, area, p);
+            let inside = (0..3).all(|i| lambdas[i] > 0.0 || (lambdas[i] == 0.0 && owns_ties[i]));
+            if !inside {
+                continue;
+            }
+
+            let z = lambdas[0] * depths[0] + lambdas[1] * depths[1] + lambdas[2] * depths[2];
+            if !(-1.0..=1.0).contains(&z) {
+                continue;
+            }
+            let depth = z * 0.5 + 0.5;
+            let pixel_index = py * target.width + px;
+            if let Some(depth_buffer) = &target.depth {
+                if depth > depth_buffer[pixel_index] {
+                    continue;
+                }
+            }
+
+            interpolate(lambdas, &inv_w, &varyings, &mut scratch.varyings);
+            interpolate(barycentrics(&screen, area, [p[0] + 1.0, p[1]]), &inv_w, &varyings, &mut scratch.varyings_dx);
+            // The pixel "above" in OpenGL terms, since y points up there.
+            interpolate(barycentrics(&screen, area, [p[0], p[1] - 1.0]), &inv_w, &varyings, &mut scratch.varyings_dy);
+            let color = interpreter.pixel(&scratch.varyings, &scratch.varyings_dx, &scratch.varyings_dy, sampler);
+
+            if let Some(depth_buffer) = &mut target.depth {
+                depth_buffer[pixel_index] = depth;
+            }
+
+            // Premultiplied alpha blending: `src + dst * (1 - src.a)`.
+            let src = color.map(|component| component.clamp(0.0, 1.0));
+            let dst = u32_to_color(target.color[pixel_index]);
+            let blended = Vec4 {
+                x: src[0] + dst[0] * (1.0 - src[3]),
+                y: src[1] + dst[1] * (1.0 - src[3]),
+                z: src[2] + dst[2] * (1.0 - src[3]),
+                w: src[3] + dst[3] * (1.0 - src[3]),
+            };
+            target.color[pixel_index] = color_to_u32(blended);
+        }
+    }
+}
+
+pub(crate) struct CxPlatformShader {
+    pub(crate) interpreter: ShaderInterpreter,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct CxPlatformGpuGeometry {}
+
+#[derive(Clone, Default)]
+pub(crate) struct CxPlatformView {}
+
+#[derive(Default, Clone)]
+pub(crate) struct CxPlatformDrawCall {}
+
+#[derive(Default, Clone)]
+pub(crate) struct CxPlatformTexture {
+    /// The size of the render target, once allocated.
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// Only used for depth textures.
+    pub(crate) depth: Vec<f32>,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct CxPlatformPass {}
@@ -115,6 +115,7 @@ impl CxDesktopVsWasmCommon for Cx {
 }
 
 impl Cx {
+    #[cfg_attr(feature = "headless", allow(dead_code))]
     pub(crate) fn process_desktop_paint_callbacks(&mut self) -> bool {
         let mut vsync = false; //self.platform.desktop.repaint_via_scroll_event;
         self.platform.desktop.repaint_via_scroll_event = false;
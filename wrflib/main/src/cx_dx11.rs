@@ -127,20 +127,17 @@ impl Cx {
                 for (i, texture_id) in draw_call.textures_2d.iter().enumerate() {
                     let cxtexture = &mut self.textures[*texture_id as usize];
                     match cxtexture.desc.format {
-                        // we only allocate Image and Mapped textures.
-                        TextureFormat::ImageRGBA => {
+                        TextureFormat::Depth32Stencil8 => (),
+                        _ => {
                             if cxtexture.update_image {
                                 cxtexture.update_image = false;
-                                d3d11_cx.update_platform_texture_image_rgba(
-                                    &mut cxtexture.platform,
-                                    cxtexture.desc.width.unwrap(),
-                                    cxtexture.desc.height.unwrap(),
-                                    &cxtexture.image_u32,
-                                );
+                                d3d11_cx.update_platform_texture_image(cxtexture);
+                            }
+                            if cxtexture.desc.kind.matches_ty(&sh.mapping.textures[i].ty) {
+                                d3d11_cx.set_shader_resource(i, &cxtexture.platform.shader_resource);
+                                d3d11_cx.set_sampler_state(i, &cxtexture.desc);
                             }
-                            d3d11_cx.set_shader_resource(i, &cxtexture.platform.shader_resource);
                         }
-                        _ => (),
                     }
                 }
 
@@ -288,6 +285,56 @@ impl Cx {
         );
     }
 
+    /// Reads back the first color texture of a pass; see [`Pass::request_readback`]. Render targets can't be
+    /// mapped directly, so we copy them into a staging texture first.
+    pub(crate) fn d3d11_read_pass_pixels(&self, pass_id: usize, d3d11_cx: &D3d11Cx) -> Option<PassReadbackEvent> {
+        let color_texture = self.passes[pass_id].color_textures.first()?;
+        let platform = &self.textures[color_texture.texture_id as usize].platform;
+        let texture = platform.texture.as_ref()?;
+        let (width, height) = (platform.width, platform.height);
+        if width * height == 0 {
+            return None;
+        }
+
+        let texture_desc = d3d11::D3D11_TEXTURE2D_DESC {
+            Width: width as u32,
+            Height: height as u32,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM,
+            SampleDesc: dxgitype::DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
+            Usage: d3d11::D3D11_USAGE_STAGING,
+            BindFlags: 0,
+            CPUAccessFlags: d3d11::D3D11_CPU_ACCESS_READ,
+            MiscFlags: 0,
+        };
+        let mut staging = ptr::null_mut();
+        let hr = unsafe { d3d11_cx.device.CreateTexture2D(&texture_desc, ptr::null(), &mut staging as *mut *mut _) };
+        if !winerror::SUCCEEDED(hr) {
+            println!("d3d11_read_pass_pixels: cannot create staging texture");
+            return None;
+        }
+        let staging: ComPtr<d3d11::ID3D11Texture2D> = unsafe { ComPtr::from_raw(staging) };
+
+        let mut pixels = vec![0u32; width * height];
+        unsafe {
+            d3d11_cx.context.CopyResource(staging.as_raw() as *mut _, texture.as_raw() as *mut _);
+            let mut mapped: d3d11::D3D11_MAPPED_SUBRESOURCE = mem::zeroed();
+            let hr = d3d11_cx.context.Map(staging.as_raw() as *mut _, 0, d3d11::D3D11_MAP_READ, 0, &mut mapped);
+            if !winerror::SUCCEEDED(hr) {
+                println!("d3d11_read_pass_pixels: cannot map staging texture");
+                return None;
+            }
+            // Rows can be padded, so copy them one by one.
+            for (y, row) in pixels.chunks_exact_mut(width).enumerate() {
+                let src = (mapped.pData as *const u8).add(y * mapped.RowPitch as usize) as *const u32;
+                ptr::copy_nonoverlapping(src, row.as_mut_ptr(), width);
+            }
+            d3d11_cx.context.Unmap(staging.as_raw() as *mut _, 0);
+        }
+        Some(PassReadbackEvent { pass_id, width, height, pixels })
+    }
+
     pub(crate) fn hlsl_compile_shaders(&mut self, d3d11_cx: &D3d11Cx) {
         for shader_id in self.shader_recompile_ids.drain(..) {
             let shader = unsafe { self.shaders.get_unchecked_mut(shader_id) };
@@ -520,6 +567,9 @@ pub(crate) struct D3d11Cx {
     pub(crate) device: ComPtr<d3d11::ID3D11Device>,
     pub(crate) context: ComPtr<d3d11::ID3D11DeviceContext>,
     pub(crate) factory: ComPtr<dxgi1_2::IDXGIFactory2>,
+    /// Sampler states for every combination of [`TextureFilter`] and [`TextureWrap`], see
+    /// [`D3d11Cx::set_sampler_state`].
+    sampler_states: Vec<ComPtr<d3d11::ID3D11SamplerState>>,
     //    pub(crate) d2d1_factory: ComPtr<d2d1::ID2D1Factory>
 }
 
@@ -529,10 +579,18 @@ impl D3d11Cx {
         let adapter = D3d11Cx::enum_adapters(&factory).expect("cannot enum_adapters");
         let (device, context) = D3d11Cx::create_d3d11_device(&adapter).expect("cannot create_d3d11_device");
         // let d2d1_factory = D3d11Cx::create_d2d1_factory().expect("cannot create_d2d1_factory");
+        let mut sampler_states = Vec::new();
+        for filter in [d3d11::D3D11_FILTER_MIN_MAG_MIP_POINT, d3d11::D3D11_FILTER_MIN_MAG_MIP_LINEAR] {
+            for address_mode in [d3d11::D3D11_TEXTURE_ADDRESS_CLAMP, d3d11::D3D11_TEXTURE_ADDRESS_WRAP] {
+                sampler_states
+                    .push(D3d11Cx::create_sampler_state(&device, filter, address_mode).expect("cannot create_sampler_state"));
+            }
+        }
         D3d11Cx {
             device,
             context,
             factory,
+            sampler_states,
             //    d2d1_factory: d2d1_factory
         }
     }
@@ -599,6 +657,47 @@ impl D3d11Cx {
         }
     }
 
+    /// Sets the sampler state for the filtering and wrapping of a texture. Only 2D textures can repeat.
+    pub(crate) fn set_sampler_state(&self, index: usize, desc: &TextureDesc) {
+        let filter_index = match desc.filter {
+            TextureFilter::Nearest => 0,
+            TextureFilter::Linear => 1,
+        };
+        let wrap_index = match desc.wrap {
+            TextureWrap::Repeat if desc.kind == TextureKind::D2 => 1,
+            _ => 0,
+        };
+        let raw = [self.sampler_states[filter_index * 2 + wrap_index].as_raw() as *const std::ffi::c_void];
+        unsafe { self.context.PSSetSamplers(index as u32, 1, raw.as_ptr() as *const *mut _) }
+        unsafe { self.context.VSSetSamplers(index as u32, 1, raw.as_ptr() as *const *mut _) }
+    }
+
+    fn create_sampler_state(
+        device: &ComPtr<d3d11::ID3D11Device>,
+        filter: d3d11::D3D11_FILTER,
+        address_mode: d3d11::D3D11_TEXTURE_ADDRESS_MODE,
+    ) -> Result<ComPtr<d3d11::ID3D11SamplerState>, winerror::HRESULT> {
+        let sampler_desc = d3d11::D3D11_SAMPLER_DESC {
+            Filter: filter,
+            AddressU: address_mode,
+            AddressV: address_mode,
+            AddressW: address_mode,
+            MipLODBias: 0.0,
+            MaxAnisotropy: 1,
+            ComparisonFunc: d3d11::D3D11_COMPARISON_NEVER,
+            BorderColor: [0.0; 4],
+            MinLOD: 0.0,
+            MaxLOD: d3d11::D3D11_FLOAT32_MAX,
+        };
+        let mut sampler_state = ptr::null_mut();
+        let hr = unsafe { device.CreateSamplerState(&sampler_desc, &mut sampler_state as *mut *mut _) };
+        if winerror::SUCCEEDED(hr) {
+            Ok(unsafe { ComPtr::from_raw(sampler_state as *mut _) })
+        } else {
+            Err(hr)
+        }
+    }
+
     //fn set_raster_state(&self, d3d11_window: &D3d11Window) {
     //    unsafe {self.context.RSSetState(d3d11_window.raster_state.as_raw() as *mut _)};
     // }
@@ -1085,47 +1184,130 @@ impl D3d11Cx {
         true
     }
 
-    pub(crate) fn update_platform_texture_image_rgba(
+    pub(crate) fn update_platform_texture_image(&self, cxtexture: &mut CxTexture) {
+        let width = cxtexture.desc.width.unwrap();
+        let height = cxtexture.desc.height.unwrap();
+        let kind = cxtexture.desc.kind;
+        let (image, bytes_per_pixel) = cxtexture.gpu_image();
+        if image.len() != width * height * kind.layers() * bytes_per_pixel {
+            println!("update_platform_texture_image with wrong image size!");
+            return;
+        }
+        let format = match cxtexture.desc.format {
+            TextureFormat::ImageRGBA => dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM,
+            TextureFormat::R8 => dxgiformat::DXGI_FORMAT_R8_UNORM,
+            TextureFormat::RG8 => dxgiformat::DXGI_FORMAT_R8G8_UNORM,
+            TextureFormat::R32F => dxgiformat::DXGI_FORMAT_R32_FLOAT,
+            TextureFormat::RGBA16F => dxgiformat::DXGI_FORMAT_R16G16B16A16_FLOAT,
+            TextureFormat::RGBA32F => dxgiformat::DXGI_FORMAT_R32G32B32A32_FLOAT,
+            TextureFormat::Depth32Stencil8 => panic!("update_platform_texture_image with unsupported format"),
+        };
+        let row_pitch = width * bytes_per_pixel;
+
+        if let TextureKind::D3 { depth } = kind {
+            let (texture, shader_resource) = self.create_texture_3d(width, height, depth, format, &image, row_pitch);
+            cxtexture.platform.texture = None;
+            cxtexture.platform.d3d11_resource = Some(texture);
+            cxtexture.platform.shader_resource = Some(shader_resource);
+        } else {
+            let (texture, shader_resource) = self.create_texture_2d(width, height, kind, format, &image, row_pitch);
+            cxtexture.platform.texture = Some(texture);
+            cxtexture.platform.shader_resource = Some(shader_resource);
+        }
+        cxtexture.platform.width = width;
+        cxtexture.platform.height = height;
+    }
+
+    fn create_texture_2d(
         &self,
-        res: &mut CxPlatformTexture,
         width: usize,
         height: usize,
-        image_u32: &Vec<u32>,
-    ) {
-        if image_u32.len() != width * height {
-            println!("update_platform_texture_image_rgba with wrong buffer_u32 size!");
-            return;
+        kind: TextureKind,
+        format: dxgiformat::DXGI_FORMAT,
+        image: &[u8],
+        row_pitch: usize,
+    ) -> (ComPtr<d3d11::ID3D11Texture2D>, ComPtr<d3d11::ID3D11ShaderResourceView>) {
+        // Cube maps are a 2D texture array with one subresource per face.
+        let sub_data: Vec<d3d11::D3D11_SUBRESOURCE_DATA> = image
+            .chunks_exact(row_pitch * height)
+            .map(|pixels| d3d11::D3D11_SUBRESOURCE_DATA {
+                pSysMem: pixels.as_ptr() as *const _,
+                SysMemPitch: row_pitch as u32,
+                SysMemSlicePitch: 0,
+            })
+            .collect();
+
+        let texture_desc = d3d11::D3D11_TEXTURE2D_DESC {
+            Width: width as u32,
+            Height: height as u32,
+            MipLevels: 1,
+            ArraySize: kind.layers() as u32,
+            Format: format,
+            SampleDesc: dxgitype::DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
+            Usage: d3d11::D3D11_USAGE_DEFAULT,
+            BindFlags: d3d11::D3D11_BIND_SHADER_RESOURCE,
+            CPUAccessFlags: 0,
+            MiscFlags: if kind == TextureKind::Cube { d3d11::D3D11_RESOURCE_MISC_TEXTURECUBE } else { 0 },
+        };
+        let mut texture = ptr::null_mut();
+        let hr = unsafe { self.device.CreateTexture2D(&texture_desc, sub_data.as_ptr(), &mut texture as *mut *mut _) };
+        if winerror::SUCCEEDED(hr) {
+            let mut shader_resource = ptr::null_mut();
+            if kind == TextureKind::Cube {
+                let mut view_desc = d3d11::D3D11_SHADER_RESOURCE_VIEW_DESC {
+                    Format: format,
+                    ViewDimension: d3dcommon::D3D11_SRV_DIMENSION_TEXTURECUBE,
+                    u: unsafe { mem::zeroed() },
+                };
+                unsafe {
+                    *view_desc.u.TextureCube_mut() = d3d11::D3D11_TEXCUBE_SRV { MostDetailedMip: 0, MipLevels: 1 };
+                    self.device.CreateShaderResourceView(texture as *mut _, &view_desc, &mut shader_resource as *mut *mut _)
+                };
+            } else {
+                unsafe {
+                    self.device.CreateShaderResourceView(texture as *mut _, ptr::null(), &mut shader_resource as *mut *mut _)
+                };
+            }
+            unsafe { (ComPtr::from_raw(texture as *mut _), ComPtr::from_raw(shader_resource as *mut _)) }
+        } else {
+            panic!("create_texture_2d failed");
         }
+    }
 
+    fn create_texture_3d(
+        &self,
+        width: usize,
+        height: usize,
+        depth: usize,
+        format: dxgiformat::DXGI_FORMAT,
+        image: &[u8],
+        row_pitch: usize,
+    ) -> (ComPtr<d3d11::ID3D11Resource>, ComPtr<d3d11::ID3D11ShaderResourceView>) {
         let sub_data = d3d11::D3D11_SUBRESOURCE_DATA {
-            pSysMem: image_u32.as_ptr() as *const _,
-            SysMemPitch: (width * 4) as u32,
-            SysMemSlicePitch: 0,
+            pSysMem: image.as_ptr() as *const _,
+            SysMemPitch: row_pitch as u32,
+            SysMemSlicePitch: (row_pitch * height) as u32,
         };
 
-        let texture_desc = d3d11::D3D11_TEXTURE2D_DESC {
+        let texture_desc = d3d11::D3D11_TEXTURE3D_DESC {
             Width: width as u32,
             Height: height as u32,
+            Depth: depth as u32,
             MipLevels: 1,
-            ArraySize: 1,
-            Format: dxgiformat::DXGI_FORMAT_R8G8B8A8_UNORM,
-            SampleDesc: dxgitype::DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
+            Format: format,
             Usage: d3d11::D3D11_USAGE_DEFAULT,
             BindFlags: d3d11::D3D11_BIND_SHADER_RESOURCE,
             CPUAccessFlags: 0,
             MiscFlags: 0,
         };
-        let mut texture = ptr::null_mut();
-        let hr = unsafe { self.device.CreateTexture2D(&texture_desc, &sub_data, &mut texture as *mut *mut _) };
+        let mut texture: *mut d3d11::ID3D11Texture3D = ptr::null_mut();
+        let hr = unsafe { self.device.CreateTexture3D(&texture_desc, &sub_data, &mut texture as *mut *mut _) };
         if winerror::SUCCEEDED(hr) {
             let mut shader_resource = ptr::null_mut();
             unsafe { self.device.CreateShaderResourceView(texture as *mut _, ptr::null(), &mut shader_resource as *mut *mut _) };
-            res.width = width;
-            res.height = height;
-            res.texture = Some(unsafe { ComPtr::from_raw(texture as *mut _) });
-            res.shader_resource = Some(unsafe { ComPtr::from_raw(shader_resource as *mut _) });
+            unsafe { (ComPtr::from_raw(texture as *mut _), ComPtr::from_raw(shader_resource as *mut _)) }
         } else {
-            panic!("update_platform_texture_image_rgba failed");
+            panic!("create_texture_3d failed");
         }
     }
 }
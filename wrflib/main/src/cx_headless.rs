@@ -0,0 +1,408 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Headless platform, enabled using the `headless` feature. Instead of opening native windows,
+//! every window gets painted into an in-memory framebuffer by the software rasterizer in
+//! `cx_cpu`. This makes it possible to run apps in tests and to take screenshots, without
+//! needing a GPU or a display server.
+//!
+//! Time is virtual: it only advances when calling [`Cx::headless_advance_time`], so that
+//! [`Timer`]s and animations are deterministic.
+
+use crate::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Signals posted using [`Cx::post_signal`], possibly from other threads.
+static POSTED_SIGNALS: Mutex<Vec<(Signal, StatusId)>> = Mutex::new(Vec::new());
+
+/// Events sent using [`Cx::send_event_from_any_thread`], possibly from other threads.
+static POSTED_EVENTS: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+
+impl Cx {
+    /// Runs the app until there is nothing left to do: it gets constructed, drawn, and painted
+    /// until no more draws or frames are requested. Since there are no native windows in the
+    /// headless platform, this returns instead of waiting for more events.
+    pub fn event_loop<F>(&mut self, mut event_handler: F)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        self.headless_process_events(&mut event_handler, Vec::new());
+    }
+
+    /// Sends `events` to the app (constructing it first if that hasn't happened yet), and then
+    /// draws and paints until there is nothing left to do.
+    ///
+    /// Use this instead of [`Cx::event_loop`] to drive an app step by step, e.g. in tests. Use
+    /// [`Cx::headless_window_pixels`] to read back what got painted.
+    pub fn headless_process_events<F>(&mut self, event_handler: &mut F, events: Vec<Event>)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        let event_handler: *mut (dyn FnMut(&mut Cx, &mut Event) + '_) = event_handler;
+        // The handler only gets called from within this function, so it's fine to erase its lifetime.
+        self.event_handler = Some(unsafe {
+            std::mem::transmute::<*mut (dyn FnMut(&mut Cx, &mut Event) + '_), *mut dyn FnMut(&mut Cx, &mut Event)>(event_handler)
+        });
+        self.headless_process_events_core(events);
+        self.event_handler = None;
+    }
+
+    /// Advances the virtual clock by `seconds`, and fires all [`Timer`]s that expire along
+    /// the way. Afterwards draws and paints until there is nothing left to do, just like
+    /// [`Cx::headless_process_events`].
+    pub fn headless_advance_time<F>(&mut self, event_handler: &mut F, seconds: f64)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        self.start_pending_timers();
+        let end_time = self.platform.time + seconds;
+        let mut events = Vec::new();
+        loop {
+            let next_timer = self
+                .platform
+                .timers
+                .iter()
+                .enumerate()
+                .filter(|(_, timer)| timer.fire_time <= end_time)
+                .min_by(|(_, a), (_, b)| a.fire_time.partial_cmp(&b.fire_time).unwrap())
+                .map(|(index, _)| index);
+            let index = match next_timer {
+                Some(index) => index,
+                None => break,
+            };
+            let timer = &mut self.platform.timers[index];
+            self.platform.time = self.platform.time.max(timer.fire_time);
+            events.push(Event::Timer(TimerEvent { timer_id: timer.timer_id }));
+            if timer.repeats && timer.interval > 0.0 {
+                timer.fire_time += timer.interval;
+            } else {
+                self.platform.timers.remove(index);
+            }
+        }
+        self.platform.time = end_time;
+        self.headless_process_events(event_handler, events);
+    }
+
+    /// Replays an [`EventRecording`] (made using [`Cx::start_event_recording`]), advancing the
+    /// virtual clock to the time of each event before sending it. Afterwards draws and paints
+    /// until there is nothing left to do, just like [`Cx::headless_process_events`].
+    ///
+    /// [`Timer`]s don't fire by themselves while replaying, since their [`Event::Timer`]s are
+    /// already part of the recording. This does require the app to start its timers in the same
+    /// order as when recording, so they get the same [`Timer::timer_id`]s.
+    pub fn headless_replay_events<F>(&mut self, event_handler: &mut F, recording: &EventRecording)
+    where
+        F: FnMut(&mut Cx, &mut Event),
+    {
+        let start_time = self.platform.time;
+        let mut recorded_events = recording.events.iter().peekable();
+        while let Some(recorded_event) = recorded_events.next() {
+            let mut events = vec![recorded_event.event.clone()];
+            while let Some(next) = recorded_events.next_if(|next| next.time == recorded_event.time) {
+                events.push(next.event.clone());
+            }
+            self.platform.time = self.platform.time.max(start_time + recorded_event.time);
+            self.skip_timers();
+            self.headless_process_events(event_handler, events);
+        }
+        self.skip_timers();
+    }
+
+    /// The pixels that got painted into a [`Window`] during the last paint, as `(width, height,
+    /// pixels)`. Pixels are stored row by row starting at the top left, in the same RGBA format
+    /// as [`TextureHandle::get_image_mut`]. The size is the inner size of the window multiplied
+    /// by its `dpi_factor`.
+    ///
+    /// Returns [`None`] if the window hasn't been painted yet.
+    pub fn headless_window_pixels(&self, window_id: usize) -> Option<(usize, usize, &[u32])> {
+        self.platform
+            .window_framebuffers
+            .get(&window_id)
+            .map(|framebuffer| (framebuffer.width, framebuffer.height, framebuffer.color.as_slice()))
+    }
+
+    /// The pixels of the first color texture of `pass`, as of the last time it got painted, in the same
+    /// format as [`Cx::headless_window_pixels`]. This is the synchronous variant of
+    /// [`Pass::request_readback`], meant for tests.
+    ///
+    /// Returns [`None`] if the [`Pass`] doesn't have a color texture or hasn't been painted yet.
+    pub fn headless_pass_pixels(&self, pass: &Pass) -> Option<(usize, usize, &[u32])> {
+        self.headless_pass_pixels_by_id(pass.pass_id?)
+    }
+
+    fn headless_pass_pixels_by_id(&self, pass_id: usize) -> Option<(usize, usize, &[u32])> {
+        let color_texture = self.passes[pass_id].color_textures.first()?;
+        let cxtexture = &self.textures[color_texture.texture_id as usize];
+        let (width, height) = (cxtexture.platform.width, cxtexture.platform.height);
+        if width * height == 0 || cxtexture.image_u32.len() != width * height {
+            return None;
+        }
+        Some((width, height, cxtexture.image_u32.as_slice()))
+    }
+
+    fn headless_process_events_core(&mut self, mut events: Vec<Event>) {
+        if !self.platform.constructed {
+            self.platform.constructed = true;
+            self.platform_type = PlatformType::Unknown;
+            self.load_fonts();
+            self.call_event_handler(&mut Event::Construct);
+            self.request_draw();
+        }
+
+        self.headless_call_events(&mut events);
+
+        // Keep painting while there is work to do, but don't spin forever on apps that request
+        // a new draw on every draw (e.g. while fading something in). Since time doesn't advance
+        // here, those extra draws wouldn't change anything anyway.
+        for _ in 0..Self::HEADLESS_MAX_PAINTS_PER_STEP {
+            self.call_posted_events();
+            self.call_posted_signals();
+            let paint_dirty = self.headless_paint();
+            if !(paint_dirty || self.requested_draw || Self::has_posted_signals() || Self::has_posted_events()) {
+                break;
+            }
+        }
+    }
+
+    fn headless_call_events(&mut self, events: &mut [Event]) {
+        self.last_event_time = self.platform.time;
+        for event in events {
+            self.process_pre_event(event);
+            match event {
+                Event::WindowGeomChange(re) => {
+                    self.windows[re.window_id].window_geom = re.new_geom.clone();
+                    if re.old_geom.inner_size != re.new_geom.inner_size {
+                        self.request_draw();
+                    }
+                    self.call_event_handler(event);
+                }
+                Event::Signal(_) => {
+                    self.call_event_handler(event);
+                    self.call_signals();
+                }
+                Event::None => {}
+                _ => {
+                    self.call_event_handler(event);
+                }
+            }
+            self.process_post_event(event);
+        }
+    }
+
+    const HEADLESS_MAX_PAINTS_PER_STEP: usize = 10;
+
+    /// Like [`Cx::process_desktop_paint_callbacks`], but an [`Event::NextFrame`] only gets sent
+    /// once (virtual) time has advanced past the moment it was requested, just like it would
+    /// wait for the next vsync on the native platforms. Otherwise e.g. [`Animator`] would see a
+    /// frame without any time passing, and stop animating.
+    fn headless_paint_callbacks(&mut self) {
+        let time = self.platform.time;
+        if self.requested_next_frame && self.platform.next_frame_requested_time.is_some_and(|requested| requested < time) {
+            self.platform.next_frame_requested_time = None;
+            self.call_next_frame_event();
+        }
+
+        self.call_signals();
+
+        if self.requested_draw {
+            self.call_draw_event();
+        }
+
+        self.call_signals();
+
+        if self.requested_next_frame && self.platform.next_frame_requested_time.is_none() {
+            self.platform.next_frame_requested_time = Some(time);
+        }
+    }
+
+    /// Mirrors what happens on a [`SystemEvent::Paint`] on the native platforms. Returns whether
+    /// any of the passes still needs painting.
+    fn headless_paint(&mut self) -> bool {
+        let mut event = Event::SystemEvent(SystemEvent::Paint);
+        self.process_pre_event(&mut event);
+
+        self.headless_paint_callbacks();
+
+        for window in &mut self.windows {
+            window.window_state = match &window.window_state {
+                CxWindowState::Create { inner_size, position, .. } => {
+                    window.window_geom = WindowGeom {
+                        dpi_factor: self.default_dpi_factor,
+                        inner_size: *inner_size,
+                        outer_size: *inner_size,
+                        position: position.unwrap_or_default(),
+                        ..WindowGeom::default()
+                    };
+                    CxWindowState::Created
+                }
+                CxWindowState::Close => CxWindowState::Closed,
+                CxWindowState::Created => CxWindowState::Created,
+                CxWindowState::Closed => CxWindowState::Closed,
+            };
+            window.window_command = CxWindowCmd::None;
+        }
+        for (window_id, window) in self.windows.iter().enumerate() {
+            if let CxWindowState::Closed = window.window_state {
+                self.platform.window_framebuffers.remove(&window_id);
+            }
+        }
+
+        self.start_pending_timers();
+
+        let mut passes_todo = Vec::new();
+        let mut windows_need_repaint = 0;
+        self.compute_passes_to_repaint(&mut passes_todo, &mut windows_need_repaint);
+
+        if !passes_todo.is_empty() {
+            self.cpu_compile_shaders();
+            for pass_id in &passes_todo {
+                match self.passes[*pass_id].dep_of.clone() {
+                    CxPassDepOf::Window(window_id) => {
+                        let dpi_factor = self.windows[window_id].window_geom.dpi_factor;
+                        let inner_size = self.windows[window_id].window_geom.inner_size;
+                        let mut framebuffer = self.platform.window_framebuffers.remove(&window_id).unwrap_or_default();
+                        framebuffer.resize((inner_size.x * dpi_factor) as usize, (inner_size.y * dpi_factor) as usize);
+                        self.passes[*pass_id].paint_dirty = false;
+                        self.draw_pass_to_framebuffer(*pass_id, dpi_factor, &mut framebuffer);
+                        self.platform.window_framebuffers.insert(window_id, framebuffer);
+                    }
+                    CxPassDepOf::Pass(parent_pass_id) => {
+                        let dpi_factor = self.get_delegated_dpi_factor(parent_pass_id);
+                        self.draw_pass_to_texture(*pass_id, dpi_factor);
+                    }
+                    CxPassDepOf::None => {
+                        self.draw_pass_to_texture(*pass_id, 1.0);
+                    }
+                }
+            }
+        }
+
+        for pass_id in self.take_painted_pass_readback_requests() {
+            if let Some((width, height, pixels)) = self.headless_pass_pixels_by_id(pass_id) {
+                let pixels = pixels.to_vec();
+                self.call_event_handler(&mut Event::PassReadback(PassReadbackEvent { pass_id, width, height, pixels }));
+            }
+        }
+
+        self.process_post_event(&mut event);
+        self.passes.iter().any(|pass| pass.paint_dirty)
+    }
+
+    fn start_pending_timers(&mut self) {
+        for timer_id in std::mem::take(&mut self.platform.stop_timer) {
+            self.platform.timers.retain(|timer| timer.timer_id != timer_id);
+        }
+        for (timer_id, interval, repeats) in std::mem::take(&mut self.platform.start_timer) {
+            self.platform.timers.push(HeadlessTimer { timer_id, interval, repeats, fire_time: self.platform.time + interval });
+        }
+    }
+
+    /// Skip firing all [`Timer`]s up until the current virtual time. Used when replaying, since
+    /// the [`Event::Timer`]s are part of the recording.
+    fn skip_timers(&mut self) {
+        self.start_pending_timers();
+        let time = self.platform.time;
+        self.platform.timers.retain_mut(|timer| {
+            if timer.fire_time > time {
+                true
+            } else if timer.repeats && timer.interval > 0.0 {
+                timer.fire_time += ((time - timer.fire_time) / timer.interval).floor() * timer.interval + timer.interval;
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    fn has_posted_signals() -> bool {
+        !POSTED_SIGNALS.lock().unwrap().is_empty()
+    }
+
+    fn has_posted_events() -> bool {
+        !POSTED_EVENTS.lock().unwrap().is_empty()
+    }
+
+    fn call_posted_events(&mut self) {
+        let mut posted_events = std::mem::take(&mut *POSTED_EVENTS.lock().unwrap());
+        if !posted_events.is_empty() {
+            self.headless_call_events(&mut posted_events);
+        }
+    }
+
+    fn call_posted_signals(&mut self) {
+        let posted_signals = std::mem::take(&mut *POSTED_SIGNALS.lock().unwrap());
+        if posted_signals.is_empty() {
+            return;
+        }
+        for (signal, status) in posted_signals {
+            self.send_signal(signal, status);
+        }
+        self.call_signals();
+    }
+}
+
+impl CxPlatformCommon for Cx {
+    /// See [`CxPlatformCommon::show_text_ime`] for documentation.
+    fn show_text_ime(&mut self, _x: f32, _y: f32) {}
+
+    /// See [`CxPlatformCommon::hide_text_ime`] for documentation.
+    fn hide_text_ime(&mut self) {}
+
+    /// See [`CxPlatformCommon::start_timer`] for documentation.
+    fn start_timer(&mut self, interval: f64, repeats: bool) -> Timer {
+        self.last_timer_id += 1;
+        self.platform.start_timer.push((self.last_timer_id, interval, repeats));
+        Timer { timer_id: self.last_timer_id }
+    }
+
+    /// See [`CxPlatformCommon::stop_timer`] for documentation.
+    fn stop_timer(&mut self, timer: &mut Timer) {
+        if timer.timer_id != 0 {
+            self.platform.stop_timer.push(timer.timer_id);
+            timer.timer_id = 0;
+        }
+    }
+
+    /// See [`CxPlatformCommon::post_signal`] for documentation.
+    fn post_signal(signal: Signal, status: StatusId) {
+        POSTED_SIGNALS.lock().unwrap().push((signal, status));
+    }
+
+    /// See [`CxPlatformCommon::update_menu`] for documentation.
+    fn update_menu(&mut self, _menu: &Menu) {}
+
+    /// See [`CxPlatformCommon::copy_text_to_clipboard`] for documentation.
+    fn copy_text_to_clipboard(&mut self, _text: &str) {}
+
+    /// See [`CxPlatformCommon::send_event_from_any_thread`] for documentation.
+    fn send_event_from_any_thread(event: Event) {
+        POSTED_EVENTS.lock().unwrap().push(event);
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct HeadlessTimer {
+    timer_id: u64,
+    interval: f64,
+    repeats: bool,
+    /// In virtual time; see [`CxPlatform::time`].
+    fire_time: f64,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct CxPlatform {
+    pub(crate) constructed: bool,
+    /// Virtual time in seconds; see [`Cx::headless_advance_time`].
+    pub(crate) time: f64,
+    /// The virtual time at which the pending [`Event::NextFrame`] got requested.
+    pub(crate) next_frame_requested_time: Option<f64>,
+    pub(crate) start_timer: Vec<(u64, f64, bool)>,
+    pub(crate) stop_timer: Vec<u64>,
+    pub(crate) timers: Vec<HeadlessTimer>,
+    pub(crate) window_framebuffers: HashMap<usize, CpuFramebuffer>,
+    pub(crate) desktop: CxDesktop,
+}
@@ -15,7 +15,7 @@ impl Cx {
         F: FnMut(&mut Cx, &mut Event),
     {
         self.event_handler =
-            Some(&mut event_handler as *const dyn FnMut(&mut Cx, &mut Event) as *mut dyn FnMut(&mut Cx, &mut Event));
+            Some(unsafe { std::mem::transmute(&mut event_handler as *mut dyn FnMut(&mut Cx, &mut Event)) }); /*TMPFIX*/
         self.event_loop_core();
         self.event_handler = None;
     }
@@ -231,6 +231,12 @@ impl Cx {
                                         }
                                     }
                                 }
+
+                                for pass_id in self.take_painted_pass_readback_requests() {
+                                    if let Some(readback) = self.opengl_read_pass_pixels(pass_id) {
+                                        self.call_event_handler(&mut Event::PassReadback(readback));
+                                    }
+                                }
                             }
                             _ => {
                                 self.call_event_handler(event);
@@ -300,6 +300,12 @@ impl Cx {
                                         }
                                     }
                                 }
+
+                                for pass_id in self.take_painted_pass_readback_requests() {
+                                    if let Some(readback) = self.metal_read_pass_pixels(pass_id, &metal_cx) {
+                                        self.call_event_handler(&mut Event::PassReadback(readback));
+                                    }
+                                }
                             }
                             #[cfg(feature = "cef")]
                             SystemEvent::CefDoMessageLoopWork => {
@@ -157,7 +157,11 @@ impl Cx {
                     if cxtexture.update_image {
                         metal_cx.update_platform_texture_image2d(cxtexture);
                     }
+                    if !cxtexture.desc.kind.matches_ty(&sh.mapping.textures[i].ty) {
+                        continue;
+                    }
                     if let Some(inner) = cxtexture.platform.inner.as_ref() {
+                        let sampler_state = metal_cx.sampler_state(&cxtexture.desc);
                         let () = unsafe {
                             msg_send![
                                 encoder,
@@ -172,6 +176,20 @@ impl Cx {
                                 atIndex: i as u64
                             ]
                         };
+                        let () = unsafe {
+                            msg_send![
+                                encoder,
+                                setFragmentSamplerState: sampler_state
+                                atIndex: i as u64
+                            ]
+                        };
+                        let () = unsafe {
+                            msg_send![
+                                encoder,
+                                setVertexSamplerState: sampler_state
+                                atIndex: i as u64
+                            ]
+                        };
                     }
                 }
                 self.platform.draw_calls_done += 1;
@@ -427,6 +445,58 @@ impl Cx {
         let () = unsafe { msg_send![pool, release] };
     }
 
+    /// Reads back the first color texture of a pass; see [`Pass::request_readback`]. Render targets are
+    /// private to the GPU, so we copy them into a shared buffer and wait for that to finish.
+    pub(crate) fn metal_read_pass_pixels(&self, pass_id: usize, metal_cx: &MetalCx) -> Option<PassReadbackEvent> {
+        let color_texture = self.passes[pass_id].color_textures.first()?;
+        let inner = self.textures[color_texture.texture_id as usize].platform.inner.as_ref()?;
+        let (width, height) = (inner.width, inner.height);
+        if width * height == 0 {
+            return None;
+        }
+        let bytes_per_row = width * 4;
+
+        let pool: id = unsafe { msg_send![class!(NSAutoreleasePool), new] };
+        let buffer = RcObjcId::from_owned(
+            NonNull::new(unsafe {
+                msg_send![
+                    metal_cx.device,
+                    newBufferWithLength: bytes_per_row * height
+                    options: MTLResourceOptions::StorageModeShared
+                ]
+            })
+            .unwrap(),
+        );
+        let command_buffer: id = unsafe { msg_send![metal_cx.command_queue, commandBuffer] };
+        let encoder: id = unsafe { msg_send![command_buffer, blitCommandEncoder] };
+        let () = unsafe {
+            msg_send![
+                encoder,
+                copyFromTexture: inner.texture.as_id()
+                sourceSlice: 0u64
+                sourceLevel: 0u64
+                sourceOrigin: MTLOrigin { x: 0, y: 0, z: 0 }
+                sourceSize: MTLSize { width, height, depth: 1 }
+                toBuffer: buffer.as_id()
+                destinationOffset: 0u64
+                destinationBytesPerRow: bytes_per_row
+                destinationBytesPerImage: bytes_per_row * height
+            ]
+        };
+        let () = unsafe { msg_send![encoder, endEncoding] };
+        let () = unsafe { msg_send![command_buffer, commit] };
+        let () = unsafe { msg_send![command_buffer, waitUntilCompleted] };
+
+        let len = (width * height) as usize;
+        let mut pixels = vec![0u32; len];
+        unsafe {
+            let contents: *const u32 = msg_send![buffer.as_id(), contents];
+            std::ptr::copy_nonoverlapping(contents, pixels.as_mut_ptr(), len);
+        }
+        let () = unsafe { msg_send![pool, release] };
+        Some(PassReadbackEvent { pass_id, width: width as usize, height: height as usize, pixels })
+    }
+
     fn commit_command_buffer(&mut self, command_buffer: id, gpu_read_guards: Vec<MetalRwLockGpuReadGuard>) {
         #[repr(C)]
         struct BlockDescriptor {
@@ -488,6 +558,9 @@ impl Cx {
 pub(crate) struct MetalCx {
     pub(crate) device: id,
     pub(crate) command_queue: id,
+    /// Sampler states for every combination of [`TextureFilter`] and [`TextureWrap`], see
+    /// [`MetalCx::sampler_state`].
+    sampler_states: Vec<RcObjcId>,
 }
 
 #[derive(Clone)]
@@ -612,7 +685,38 @@ impl MetalCx {
         }
         */
         let device = get_default_metal_device().expect("Cannot get default metal device");
-        MetalCx { command_queue: unsafe { msg_send![device, newCommandQueue] }, device }
+        let mut sampler_states = Vec::new();
+        for filter in [MTLSamplerMinMagFilter::Nearest, MTLSamplerMinMagFilter::Linear] {
+            for address_mode in [MTLSamplerAddressMode::ClampToEdge, MTLSamplerAddressMode::Repeat] {
+                let descriptor =
+                    RcObjcId::from_owned(NonNull::new(unsafe { msg_send![class!(MTLSamplerDescriptor), new] }).unwrap());
+                sampler_states.push(RcObjcId::from_owned(
+                    NonNull::new(unsafe {
+                        let _: () = msg_send![descriptor.as_id(), setMinFilter: filter];
+                        let _: () = msg_send![descriptor.as_id(), setMagFilter: filter];
+                        let _: () = msg_send![descriptor.as_id(), setSAddressMode: address_mode];
+                        let _: () = msg_send![descriptor.as_id(), setTAddressMode: address_mode];
+                        let _: () = msg_send![descriptor.as_id(), setRAddressMode: address_mode];
+                        msg_send![device, newSamplerStateWithDescriptor: descriptor]
+                    })
+                    .unwrap(),
+                ));
+            }
+        }
+        MetalCx { command_queue: unsafe { msg_send![device, newCommandQueue] }, device, sampler_states }
+    }
+
+    /// The sampler state for the filtering and wrapping of a texture. Only 2D textures can repeat.
+    fn sampler_state(&self, desc: &TextureDesc) -> id {
+        let filter_index = match desc.filter {
+            TextureFilter::Nearest => 0,
+            TextureFilter::Linear => 1,
+        };
+        let wrap_index = match desc.wrap {
+            TextureWrap::Repeat if desc.kind == TextureKind::D2 => 1,
+            _ => 0,
+        };
+        self.sampler_states[filter_index * 2 + wrap_index].as_id()
     }
 
     pub(crate) fn update_platform_texture_image2d(&self, cxtexture: &mut CxTexture) {
@@ -626,7 +730,8 @@ impl MetalCx {
 
         let mut desc_changed = true;
         if let Some(inner) = &cxtexture.platform.inner {
-            desc_changed = inner.format != cxtexture.desc.format
+            desc_changed = inner.kind != cxtexture.desc.kind
+                || inner.format != cxtexture.desc.format
                 || inner.width != width
                 || inner.height != height
                 || inner.multisample != cxtexture.desc.multisample;
@@ -637,19 +742,36 @@ impl MetalCx {
             let descriptor = RcObjcId::from_owned(NonNull::new(unsafe { msg_send![class!(MTLTextureDescriptor), new] }).unwrap());
             let texture = RcObjcId::from_owned(
                 NonNull::new(unsafe {
-                    let _: () = msg_send![descriptor.as_id(), setTextureType: MTLTextureType::D2];
+                    match cxtexture.desc.kind {
+                        TextureKind::D2 => {
+                            let _: () = msg_send![descriptor.as_id(), setTextureType: MTLTextureType::D2];
+                            let _: () = msg_send![descriptor.as_id(), setUsage: MTLTextureUsage::RenderTarget];
+                        }
+                        TextureKind::Cube => {
+                            let _: () = msg_send![descriptor.as_id(), setTextureType: MTLTextureType::Cube];
+                            let _: () = msg_send![descriptor.as_id(), setUsage: MTLTextureUsage::ShaderRead];
+                        }
+                        TextureKind::D3 { depth } => {
+                            let _: () = msg_send![descriptor.as_id(), setTextureType: MTLTextureType::D3];
+                            let _: () = msg_send![descriptor.as_id(), setDepth: depth as u64];
+                            let _: () = msg_send![descriptor.as_id(), setUsage: MTLTextureUsage::ShaderRead];
+                        }
+                    }
                     let _: () = msg_send![descriptor.as_id(), setWidth: width as u64];
                     let _: () = msg_send![descriptor.as_id(), setHeight: height as u64];
                     let _: () = msg_send![descriptor.as_id(), setStorageMode: MTLStorageMode::Managed];
-                    let _: () = msg_send![descriptor.as_id(), setUsage: MTLTextureUsage::RenderTarget];
-                    match cxtexture.desc.format {
-                        TextureFormat::ImageRGBA => {
-                            let _: () = msg_send![descriptor.as_id(), setPixelFormat: MTLPixelFormat::RGBA8Unorm];
-                        }
-                        _ => {
+                    let pixel_format = match cxtexture.desc.format {
+                        TextureFormat::ImageRGBA => MTLPixelFormat::RGBA8Unorm,
+                        TextureFormat::R8 => MTLPixelFormat::R8Unorm,
+                        TextureFormat::RG8 => MTLPixelFormat::RG8Unorm,
+                        TextureFormat::R32F => MTLPixelFormat::R32Float,
+                        TextureFormat::RGBA16F => MTLPixelFormat::RGBA16Float,
+                        TextureFormat::RGBA32F => MTLPixelFormat::RGBA32Float,
+                        TextureFormat::Depth32Stencil8 => {
                             panic!("update_platform_texture_image2d with unsupported format");
                         }
-                    }
+                    };
+                    let _: () = msg_send![descriptor.as_id(), setPixelFormat: pixel_format];
                     msg_send![self.device, newTextureWithDescriptor: descriptor]
                 })
                 .unwrap(),
@@ -659,6 +781,7 @@ impl MetalCx {
                 is_inited: false,
                 width,
                 height,
+                kind: cxtexture.desc.kind,
                 format: cxtexture.desc.format,
                 multisample: cxtexture.desc.multisample,
                 texture,
@@ -667,29 +790,43 @@ impl MetalCx {
 
         // always allocate new image
         let inner = cxtexture.platform.inner.as_ref().unwrap();
-        match cxtexture.desc.format {
-            TextureFormat::ImageRGBA => {
-                if cxtexture.image_u32.len() as u64 != width * height {
-                    panic!("update_platform_texture_image2d with wrong buffer_u32 size!");
-                }
-                let region = MTLRegion {
-                    origin: MTLOrigin { x: 0, y: 0, z: 0 },
-                    size: MTLSize { width: width as u64, height: height as u64, depth: 1 },
-                };
-                let mtl_texture = inner.texture.as_id();
+        let layers = cxtexture.desc.kind.layers() as u64;
+        let (image, bytes_per_pixel) = cxtexture.gpu_image();
+        if image.len() as u64 != width * height * layers * bytes_per_pixel as u64 {
+            panic!("update_platform_texture_image2d with wrong image size!");
+        }
+        let bytes_per_row = width * bytes_per_pixel as u64;
+        let mtl_texture = inner.texture.as_id();
+        match cxtexture.desc.kind {
+            TextureKind::D2 | TextureKind::D3 { .. } => {
+                let region = MTLRegion { origin: MTLOrigin { x: 0, y: 0, z: 0 }, size: MTLSize { width, height, depth: layers } };
                 let () = unsafe {
                     msg_send![
                         mtl_texture,
                         replaceRegion: region
                         mipmapLevel: 0
-                        withBytes: cxtexture.image_u32.as_ptr() as *const std::ffi::c_void
-                        bytesPerRow: (width as usize * std::mem::size_of::<u32>()) as u64
+                        slice: 0
+                        withBytes: image.as_ptr() as *const std::ffi::c_void
+                        bytesPerRow: bytes_per_row
+                        bytesPerImage: bytes_per_row * height
                     ]
                 };
             }
-            _ => {
-                println!("update_platform_texture_image2d with unsupported format");
-                return;
+            TextureKind::Cube => {
+                let region = MTLRegion { origin: MTLOrigin { x: 0, y: 0, z: 0 }, size: MTLSize { width, height, depth: 1 } };
+                for (face, pixels) in image.chunks_exact((bytes_per_row * height) as usize).enumerate() {
+                    let () = unsafe {
+                        msg_send![
+                            mtl_texture,
+                            replaceRegion: region
+                            mipmapLevel: 0
+                            slice: face as u64
+                            withBytes: pixels.as_ptr() as *const std::ffi::c_void
+                            bytesPerRow: bytes_per_row
+                            bytesPerImage: bytes_per_row * height
+                        ]
+                    };
+                }
             }
         }
         cxtexture.update_image = false;
@@ -892,6 +1029,7 @@ impl CxPlatformTexture {
             is_inited: false,
             width,
             height,
+            kind: desc.kind,
             format: desc.format,
             multisample: desc.multisample,
             texture,
@@ -903,6 +1041,7 @@ struct CxPlatformTextureInner {
     is_inited: bool,
     width: u64,
     height: u64,
+    kind: TextureKind,
     format: TextureFormat,
     multisample: Option<usize>,
     texture: RcObjcId,
@@ -167,10 +167,15 @@ impl Cx {
                         }
                         // get the loc
                         gl::ActiveTexture(gl::TEXTURE0 + i as u32);
-                        if let Some(texture) = cxtexture.platform.gl_texture {
-                            gl::BindTexture(gl::TEXTURE_2D, texture);
-                        } else {
-                            gl::BindTexture(gl::TEXTURE_2D, 0);
+                        let ty = &sh.mapping.textures[i].ty;
+                        let target = if *ty == Ty::TextureCube { gl::TEXTURE_CUBE_MAP } else { gl::TEXTURE_2D };
+                        match cxtexture.platform.gl_texture {
+                            Some(texture) if cxtexture.desc.kind.matches_ty(ty) => gl::BindTexture(target, texture),
+                            _ => gl::BindTexture(target, 0),
+                        }
+                        gl::Uniform1i(shp.textures[i].loc, i as i32);
+                        if let TextureKind::D3 { depth } = cxtexture.desc.kind {
+                            gl::Uniform2f(shp.texture_3d_sizes[i].loc, cxtexture.platform.height as f32, depth as f32);
                         }
                     }
 
@@ -460,6 +465,28 @@ impl Cx {
     // commit
     //}
 
+    /// Reads back the first color texture of a pass; see [`Pass::request_readback`].
+    pub(crate) fn opengl_read_pass_pixels(&self, pass_id: usize) -> Option<PassReadbackEvent> {
+        let color_texture = self.passes[pass_id].color_textures.first()?;
+        let gl_framebuffer = self.passes[pass_id].platform.gl_framebuffer?;
+        let cxtexture = &self.textures[color_texture.texture_id as usize];
+        cxtexture.platform.gl_texture?;
+        let width = cxtexture.platform.width as usize;
+        let height = cxtexture.platform.height as usize;
+        if width * height == 0 {
+            return None;
+        }
+        let mut pixels = vec![0u32; width * height];
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, gl_framebuffer);
+            gl::ReadPixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut c_void);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        // OpenGL returns the bottom row first.
+        let pixels = pixels.chunks_exact(width).rev().flatten().copied().collect();
+        Some(PassReadbackEvent { pass_id, width, height, pixels })
+    }
+
     pub(crate) fn opengl_get_info_log(compile: bool, shader: usize, source: &str) -> String {
         unsafe {
             let mut length = 0;
@@ -578,6 +605,7 @@ impl Cx {
                 precision highp float;
                 precision highp int;
                 vec4 sample2d(sampler2D sampler, vec2 pos){{return texture2D(sampler, vec2(pos.x, 1.0-pos.y));}}
+                vec4 sample_cube(samplerCube sampler, vec3 dir){{return textureCube(sampler, dir);}}
                 {}\0",
                 vertex
             );
@@ -588,6 +616,7 @@ impl Cx {
                 precision highp float;
                 precision highp int;
                 vec4 sample2d(sampler2D sampler, vec2 pos){{return texture2D(sampler, vec2(pos.x, 1.0-pos.y));}}
+                vec4 sample_cube(samplerCube sampler, vec3 dir){{return textureCube(sampler, dir);}}
                 {}\0",
                 fragment
             );
@@ -637,6 +666,20 @@ impl Cx {
                     view_uniforms: Self::opengl_get_uniforms(program, &shader.mapping.view_uniforms),
                     draw_uniforms: Self::opengl_get_uniforms(program, &shader.mapping.draw_uniforms),
                     user_uniforms: Self::opengl_get_uniforms(program, &shader.mapping.user_uniforms),
+                    textures: shader
+                        .mapping
+                        .textures
+                        .iter()
+                        .map(|texture| Self::opengl_get_uniform(program, &generate_glsl::texture_uniform_name(&texture.name), 0))
+                        .collect(),
+                    texture_3d_sizes: shader
+                        .mapping
+                        .textures
+                        .iter()
+                        .map(|texture| {
+                            Self::opengl_get_uniform(program, &generate_glsl::texture_3d_size_uniform_name(&texture.name), 2)
+                        })
+                        .collect(),
                 });
                 shader.shader_ast = None;
             }
@@ -810,41 +853,84 @@ impl OpenglCx {
         let width = cxtexture.desc.width.unwrap();
         let height = cxtexture.desc.height.unwrap();
 
-        // allocate new texture if descriptor change
-        // TODO(Paras): Change this so that we still update image_u32 data
-        // whenever this function is called, not just when the descriptor changes.
-        if cxtexture.platform.alloc_desc != cxtexture.desc {
-            cxtexture.platform.alloc_desc = cxtexture.desc.clone();
-            cxtexture.platform.width = width as u64;
-            cxtexture.platform.height = height as u64;
-
-            let gl_texture = match cxtexture.platform.gl_texture {
-                None => unsafe {
-                    let mut gl_texture = std::mem::MaybeUninit::uninit();
-                    gl::GenTextures(1, gl_texture.as_mut_ptr());
-                    let gl_texture = gl_texture.assume_init();
-                    cxtexture.platform.gl_texture = Some(gl_texture);
-                    gl_texture
-                },
-                Some(gl_texture_old) => gl_texture_old,
-            };
-            unsafe {
-                gl::BindTexture(gl::TEXTURE_2D, gl_texture);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-                gl::TexImage2D(
-                    gl::TEXTURE_2D,
-                    0,
-                    gl::RGBA as i32,
-                    width as i32,
-                    height as i32,
-                    0,
-                    gl::RGBA,
-                    gl::UNSIGNED_BYTE,
-                    cxtexture.image_u32.as_ptr() as *const _,
-                );
-                gl::BindTexture(gl::TEXTURE_2D, 0);
+        cxtexture.platform.alloc_desc = cxtexture.desc.clone();
+        cxtexture.platform.width = width as u64;
+        cxtexture.platform.height = height as u64;
+
+        let gl_texture = match cxtexture.platform.gl_texture {
+            None => unsafe {
+                let mut gl_texture = std::mem::MaybeUninit::uninit();
+                gl::GenTextures(1, gl_texture.as_mut_ptr());
+                let gl_texture = gl_texture.assume_init();
+                cxtexture.platform.gl_texture = Some(gl_texture);
+                gl_texture
+            },
+            Some(gl_texture_old) => gl_texture_old,
+        };
+
+        let (internal_format, format, ty) = match cxtexture.desc.format {
+            TextureFormat::ImageRGBA => (gl::RGBA, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::R8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+            TextureFormat::RG8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+            TextureFormat::R32F => (gl::R32F, gl::RED, gl::FLOAT),
+            TextureFormat::RGBA16F => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+            TextureFormat::RGBA32F => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+            TextureFormat::Depth32Stencil8 => panic!("depth textures can't be uploaded"),
+        };
+        let (image, bytes_per_pixel) = cxtexture.gpu_image();
+        let filter = match cxtexture.desc.filter {
+            TextureFilter::Linear => gl::LINEAR,
+            TextureFilter::Nearest => gl::NEAREST,
+        };
+        let wrap = match cxtexture.desc.wrap {
+            // 3D textures get their slices stacked, so repeating would sample neighbouring slices.
+            _ if cxtexture.desc.kind != TextureKind::D2 => gl::CLAMP_TO_EDGE,
+            TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            TextureWrap::Repeat => gl::REPEAT,
+        };
+        unsafe {
+            // Rows of 1 or 2 byte pixels aren't necessarily aligned to 4 bytes.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            let target = if cxtexture.desc.kind == TextureKind::Cube { gl::TEXTURE_CUBE_MAP } else { gl::TEXTURE_2D };
+            gl::BindTexture(target, gl_texture);
+            gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, filter as i32);
+            gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, filter as i32);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_S, wrap as i32);
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_T, wrap as i32);
+            match cxtexture.desc.kind {
+                TextureKind::D2 | TextureKind::D3 { .. } => {
+                    // 3D textures get their slices stacked vertically, see `generate_glsl::generate_sample_3d_fn`.
+                    let height = height * cxtexture.desc.kind.layers();
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        internal_format as i32,
+                        width as i32,
+                        height as i32,
+                        0,
+                        format,
+                        ty,
+                        image.as_ptr() as *const _,
+                    );
+                }
+                TextureKind::Cube => {
+                    for (face, pixels) in image.chunks_exact(width * height * bytes_per_pixel).take(6).enumerate() {
+                        gl::TexImage2D(
+                            gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                            0,
+                            internal_format as i32,
+                            width as i32,
+                            height as i32,
+                            0,
+                            format,
+                            ty,
+                            pixels.as_ptr() as *const _,
+                        );
+                    }
+                }
             }
+            gl::BindTexture(target, 0);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
         }
 
         cxtexture.update_image = false;
@@ -941,6 +1027,11 @@ pub(crate) struct CxPlatformShader {
     pub(crate) view_uniforms: Vec<OpenglUniform>,
     pub(crate) draw_uniforms: Vec<OpenglUniform>,
     pub(crate) user_uniforms: Vec<OpenglUniform>,
+    /// The sampler uniform of every texture in [`CxShaderMapping::textures`].
+    pub(crate) textures: Vec<OpenglUniform>,
+    /// For every texture in [`CxShaderMapping::textures`], the size uniform that `texture3D`s need, see
+    /// [`generate_glsl::texture_3d_size_uniform_name`].
+    pub(crate) texture_3d_sizes: Vec<OpenglUniform>,
 }
 
 #[derive(Clone)]
@@ -145,6 +145,10 @@ impl Cx {
                 }
                 MSG_TYPE_ANIMATION_FRAME => {
                     is_animation_frame = true;
+                    // The renderer has processed the previous frame by now, so pixels that we asked for got filled in.
+                    for readback in std::mem::take(&mut self.platform.pass_readbacks) {
+                        self.wasm_event_handler(Event::PassReadback(readback));
+                    }
                     if self.requested_next_frame {
                         self.call_next_frame_event();
                     }
@@ -488,13 +492,19 @@ impl Cx {
                     }
                 }
             }
+            for pass_id in self.take_painted_pass_readback_requests() {
+                if let Some(readback) = self.webgl_read_pass_pixels(pass_id, &mut zerde_webgl) {
+                    self.platform.pass_readbacks.push(readback);
+                }
+            }
             zerde_webgl.end();
             self.platform.zerde_eventloop_msgs.run_webgl(zerde_webgl.take_ptr());
         }
 
         // request animation frame if still need to redraw, or repaint
         // we use request animation frame for that.
-        if passes_todo.len() != 0 || self.requested_draw || self.requested_next_frame {
+        if passes_todo.len() != 0 || self.requested_draw || self.requested_next_frame || !self.platform.pass_readbacks.is_empty()
+        {
             self.platform.zerde_eventloop_msgs.request_animation_frame();
         }
 
@@ -646,6 +656,8 @@ pub(crate) struct CxPlatform {
     pub(crate) index_buffers: usize,
     pub(crate) vaos: usize,
     pub(crate) pointers_down: Vec<bool>,
+    /// Readbacks that the renderer fills in, which get sent on the next animation frame.
+    pub(crate) pass_readbacks: Vec<PassReadbackEvent>,
     call_rust_in_same_thread_sync_fn: RwLock<Option<CallRustInSameThreadSyncFn>>,
     // pub(crate) xr_last_left_input: XRInput,
     // pub(crate) xr_last_right_input: XRInput,
@@ -661,6 +673,7 @@ impl Default for CxPlatform {
             index_buffers: 0,
             vaos: 0,
             pointers_down: Vec::new(),
+            pass_readbacks: Vec::new(),
             call_rust_in_same_thread_sync_fn: RwLock::new(None),
             // xr_last_left_input: XRInput::default(),
             // xr_last_right_input: XRInput::default(),
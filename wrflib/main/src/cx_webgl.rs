@@ -202,7 +202,9 @@ impl Cx {
 
         self.setup_render_pass(pass_id, dpi_factor);
 
-        zerde_webgl.begin_render_targets(pass_id, (pass_size.x * dpi_factor) as usize, (pass_size.y * dpi_factor) as usize);
+        let (width, height) = ((pass_size.x * dpi_factor) as usize, (pass_size.y * dpi_factor) as usize);
+        self.passes[pass_id].platform.render_target_size = (width, height);
+        zerde_webgl.begin_render_targets(pass_id, width, height);
 
         for color_texture in &self.passes[pass_id].color_textures {
             match color_texture.clear_color {
@@ -245,6 +247,23 @@ impl Cx {
         );
     }
 
+    /// Asks the renderer to read back the first color texture of a pass into the pixels of the returned event,
+    /// which are only filled in once the renderer has processed `zerde_webgl`. See [`Pass::request_readback`].
+    pub(crate) fn webgl_read_pass_pixels(
+        &self,
+        pass_id: usize,
+        zerde_webgl: &mut ZerdeWebGLMessages,
+    ) -> Option<PassReadbackEvent> {
+        self.passes[pass_id].color_textures.first()?;
+        let (width, height) = self.passes[pass_id].platform.render_target_size;
+        if width * height == 0 {
+            return None;
+        }
+        let mut pixels = vec![0u32; width * height];
+        zerde_webgl.read_pixels(pass_id, width, height, pixels.as_mut_ptr() as u32);
+        Some(PassReadbackEvent { pass_id, width, height, pixels })
+    }
+
     pub(crate) fn webgl_compile_shaders(&mut self, zerde_webgl: &mut ZerdeWebGLMessages) {
         for shader_id in self.shader_recompile_ids.drain(..) {
             let shader = unsafe { self.shaders.get_unchecked_mut(shader_id) };
@@ -258,6 +277,7 @@ impl Cx {
                 precision highp float;
                 precision highp int;
                 vec4 sample2d(sampler2D sampler, vec2 pos){{return texture2D(sampler, vec2(pos.x, 1.0-pos.y));}}
+                vec4 sample_cube(samplerCube sampler, vec3 dir){{return textureCube(sampler, dir);}}
                 mat4 transpose(mat4 m){{return \
                  mat4(m[0][0],m[1][0],m[2][0],m[3][0],m[0][1],m[1][1],m[2][1],m[3][1],m[0][2],m[1][2],m[2][2],m[3][3], m[3][0], \
                  m[3][1], m[3][2], m[3][3]);}}
@@ -272,6 +292,7 @@ impl Cx {
                 precision highp float;
                 precision highp int;
                 vec4 sample2d(sampler2D sampler, vec2 pos){{return texture2D(sampler, vec2(pos.x, 1.0-pos.y));}}
+                vec4 sample_cube(samplerCube sampler, vec3 dir){{return textureCube(sampler, dir);}}
                 mat4 transpose(mat4 m){{return \
                  mat4(m[0][0],m[1][0],m[2][0],m[3][0],m[0][1],m[1][1],m[2][1],m[3][1],m[0][2],m[1][2],m[2][2],m[3][3], m[3][0], \
                  m[3][1], m[3][2], m[3][3]);}}
@@ -300,7 +321,10 @@ impl Cx {
 }
 
 #[derive(Default, Clone)]
-pub(crate) struct CxPlatformPass {}
+pub(crate) struct CxPlatformPass {
+    /// The size of the render targets in the last paint.
+    render_target_size: (usize, usize),
+}
 
 #[derive(Clone, Default)]
 pub(crate) struct CxPlatformView {}
@@ -324,7 +348,11 @@ pub(crate) struct CxPlatformDrawCall {
 pub(crate) struct CxPlatformShader {}
 
 #[derive(Clone, Default)]
-pub(crate) struct CxPlatformTexture {}
+pub(crate) struct CxPlatformTexture {
+    /// Pixels converted to RGBA, for formats that WebGL 1 doesn't support. See [`webgl_texture_data`].
+    converted_u8: Vec<u8>,
+    converted_f32: Vec<f32>,
+}
 
 #[derive(Clone, Default)]
 pub(crate) struct CxPlatformGpuGeometry {
@@ -360,13 +388,27 @@ impl ZerdeWebGLMessages {
                 Ty::Vec2 => "vec2",
                 Ty::Float => "float",
                 Ty::Mat4 => "mat4",
-                Ty::Texture2D => "sampler2D",
                 _ => panic!("unexpected type in send_propdefvec"),
             });
             self.builder.send_string(&prop_def.name);
         }
     }
 
+    fn send_texture_slots(&mut self, textures: &[PropDef]) {
+        self.builder.send_u32(textures.len() as u32);
+        for texture in textures {
+            self.builder.send_string(match texture.ty {
+                Ty::Texture2D => "sampler2D",
+                Ty::TextureCube => "samplerCube",
+                // Not a GLSL type, since these are 2D textures in GLSL. See `generate_glsl::generate_sample_3d_fn`.
+                Ty::Texture3D => "sampler3D",
+                _ => panic!("unexpected type in send_texture_slots"),
+            });
+            self.builder.send_string(&generate_glsl::texture_uniform_name(&texture.name));
+            self.builder.send_string(&generate_glsl::texture_3d_size_uniform_name(&texture.name));
+        }
+    }
+
     pub(crate) fn compile_webgl_shader(&mut self, shader_id: usize, vertex: &str, fragment: &str, mapping: &CxShaderMapping) {
         self.builder.send_u32(1);
         self.builder.send_u32(shader_id as u32);
@@ -378,7 +420,7 @@ impl ZerdeWebGLMessages {
         self.send_propdefvec(&mapping.view_uniforms);
         self.send_propdefvec(&mapping.draw_uniforms);
         self.send_propdefvec(&mapping.user_uniforms);
-        self.send_propdefvec(&mapping.textures);
+        self.send_texture_slots(&mapping.textures);
     }
 
     pub(crate) fn alloc_array_buffer(&mut self, buffer_id: usize, len: usize, data: *const f32) {
@@ -425,12 +467,28 @@ impl ZerdeWebGLMessages {
     }
 
     pub(crate) fn update_texture_image2d(&mut self, texture_id: usize, texture: &mut CxTexture) {
-        //usize, width: usize, height: usize, data: &Vec<u32>
         self.builder.send_u32(6);
         self.builder.send_u32(texture_id as u32);
         self.builder.send_u32(texture.desc.width.unwrap() as u32);
         self.builder.send_u32(texture.desc.height.unwrap() as u32);
-        self.builder.send_u32(texture.image_u32.as_ptr() as u32)
+        let (data_ptr, is_float) = webgl_texture_data(texture);
+        self.builder.send_u32(data_ptr);
+        let (kind, depth) = match texture.desc.kind {
+            TextureKind::D2 => (0, 1),
+            TextureKind::Cube => (1, 6),
+            TextureKind::D3 { depth } => (2, depth),
+        };
+        self.builder.send_u32(kind);
+        self.builder.send_u32(depth as u32);
+        self.builder.send_u32(if is_float { 1 } else { 0 });
+        self.builder.send_u32(match texture.desc.filter {
+            TextureFilter::Nearest => 0,
+            TextureFilter::Linear => 1,
+        });
+        self.builder.send_u32(match texture.desc.wrap {
+            TextureWrap::Repeat if texture.desc.kind == TextureKind::D2 => 1,
+            _ => 0,
+        });
     }
 
     pub(crate) fn begin_render_targets(&mut self, pass_id: usize, width: usize, height: usize) {
@@ -473,4 +531,36 @@ impl ZerdeWebGLMessages {
         self.builder.send_f32(color.w);
         self.builder.send_f32(depth);
     }
+
+    pub(crate) fn read_pixels(&mut self, pass_id: usize, width: usize, height: usize, pixels_ptr: u32) {
+        self.builder.send_u32(13);
+        self.builder.send_u32(pass_id as u32);
+        self.builder.send_u32(width as u32);
+        self.builder.send_u32(height as u32);
+        self.builder.send_u32(pixels_ptr);
+    }
+}
+
+/// Returns a pointer to the pixels of `texture` as RGBA, and whether they are floats. WebGL 1 only supports
+/// RGBA textures of bytes, or of floats with `OES_texture_float`, so other formats get converted into
+/// [`CxPlatformTexture`], which keeps them around until the renderer has uploaded them.
+fn webgl_texture_data(texture: &mut CxTexture) -> (u32, bool) {
+    let platform = &mut texture.platform;
+    match texture.desc.format {
+        TextureFormat::ImageRGBA => (texture.image_u32.as_ptr() as u32, false),
+        TextureFormat::R8 => {
+            platform.converted_u8 = texture.image_u8.iter().flat_map(|&r| [r, 0, 0, 255]).collect();
+            (platform.converted_u8.as_ptr() as u32, false)
+        }
+        TextureFormat::RG8 => {
+            platform.converted_u8 = texture.image_u8.chunks_exact(2).flat_map(|rg| [rg[0], rg[1], 0, 255]).collect();
+            (platform.converted_u8.as_ptr() as u32, false)
+        }
+        TextureFormat::R32F => {
+            platform.converted_f32 = texture.image_f32.iter().flat_map(|&r| [r, 0.0, 0.0, 1.0]).collect();
+            (platform.converted_f32.as_ptr() as u32, true)
+        }
+        TextureFormat::RGBA16F | TextureFormat::RGBA32F => (texture.image_f32.as_ptr() as u32, true),
+        TextureFormat::Depth32Stencil8 => panic!("depth textures can't be uploaded"),
+    }
 }
@@ -254,6 +254,12 @@ impl Cx {
                                         }
                                     }
                                 }
+
+                                for pass_id in self.take_painted_pass_readback_requests() {
+                                    if let Some(readback) = self.d3d11_read_pass_pixels(pass_id, &d3d11_cx) {
+                                        self.call_event_handler(&mut Event::PassReadback(readback));
+                                    }
+                                }
                             }
                             _ => {
                                 self.call_event_handler(event);
@@ -183,8 +183,7 @@ impl XlibApp {
     {
         unsafe {
             self.event_callback = Some(
-                &mut event_handler as *const dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool
-                    as *mut dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool,
+                std::mem::transmute(&mut event_handler as *mut dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool), /*TMPFIX*/
             );
 
             self.do_callback(&mut vec![Event::SystemEvent(SystemEvent::Paint)]);
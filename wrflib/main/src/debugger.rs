@@ -14,7 +14,7 @@ struct BorderIns {
 }
 
 /// Draws small border around the provided rect with transparent background
-static BORDER_SHADER: Shader = Shader {
+pub(crate) static BORDER_SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -35,6 +35,7 @@ static BORDER_SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(BORDER_SHADER);
 
 #[derive(Default, Clone)]
 pub struct Debugger {
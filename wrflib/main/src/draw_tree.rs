@@ -619,6 +619,7 @@ pub struct DrawCall {
     /// Hardcoded set of uniforms that are present on every [`DrawCall`].
     pub(crate) draw_uniforms: DrawUniforms,
     /// Platform-specific data for use during painting.
+    #[cfg_attr(feature = "headless", allow(dead_code))]
     pub(crate) platform: CxPlatformDrawCall,
     pub(crate) props: DrawCallProps,
 }
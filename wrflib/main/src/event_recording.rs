@@ -0,0 +1,763 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Recording [`Event`]s that get delivered to an app, so they can be replayed later, e.g. to
+//! reproduce a bug report or to turn an interaction into a regression test.
+//!
+//! Start recording with [`Cx::start_event_recording`], and get the result using
+//! [`Cx::stop_event_recording`]. Only events that come from the outside world get recorded
+//! (pointer, key, text input, timer, signal, and window geometry events), since all other events
+//! (like [`Event::Construct`], [`Event::NextFrame`], or draws) follow from those.
+//!
+//! An [`EventRecording`] can be stored as text, with one event per line:
+//!
+//! ```text
+//! 0.5 PointerDown window_id=0 abs=20,30 button=Left tap_count=1 time=12.5
+//! 0.6 TextInput input="hello \"world\"" replace_last=false was_paste=false
+//! ```
+//!
+//! The first number is the time in seconds since the recording started. Fields that are left
+//! out get their default value, which makes it easy to write (or edit) these by hand.
+//!
+//! Use `Cx::headless_replay_events` (with the `headless` feature) to replay a recording.
+
+use crate::*;
+use std::collections::{BTreeSet, HashMap};
+
+/// An [`Event`] in an [`EventRecording`].
+#[derive(Clone, Debug)]
+pub struct RecordedEvent {
+    /// Time in seconds since the start of the recording.
+    pub time: f64,
+    pub event: Event,
+}
+
+/// A list of [`Event`]s along with when they happened. See the [module-level docs](self).
+#[derive(Clone, Debug, Default)]
+pub struct EventRecording {
+    pub events: Vec<RecordedEvent>,
+}
+
+/// An [`EventRecording`] that is currently in progress. See [`Cx::start_event_recording`].
+pub(crate) struct EventRecorder {
+    /// The [`Cx::last_event_time`] at which the recording started.
+    start_time: f64,
+    recording: EventRecording,
+}
+
+impl Cx {
+    /// Start recording all [`Event`]s that come from the outside world. Get the recorded events
+    /// using [`Cx::stop_event_recording`].
+    ///
+    /// If a recording was already in progress, it will be thrown away.
+    pub fn start_event_recording(&mut self) {
+        self.event_recorder = Some(EventRecorder { start_time: self.last_event_time, recording: EventRecording::default() });
+    }
+
+    /// Stop recording [`Event`]s, and return what has been recorded since
+    /// [`Cx::start_event_recording`]. Returns an empty [`EventRecording`] if no recording was in
+    /// progress.
+    pub fn stop_event_recording(&mut self) -> EventRecording {
+        self.event_recorder.take().map(|recorder| recorder.recording).unwrap_or_default()
+    }
+
+    /// Add `event` to the current recording, if any. Called from [`Cx::call_event_handler`].
+    pub(crate) fn record_event(&mut self, event: &Event) {
+        if let Some(recorder) = &mut self.event_recorder {
+            if is_recordable(event) {
+                recorder
+                    .recording
+                    .events
+                    .push(RecordedEvent { time: self.last_event_time - recorder.start_time, event: event.clone() });
+            }
+        }
+    }
+}
+
+fn is_recordable(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::AppFocus
+            | Event::AppFocusLost
+            | Event::WindowGeomChange(_)
+            | Event::PointerDown(_)
+            | Event::PointerMove(_)
+            | Event::PointerHover(_)
+            | Event::PointerUp(_)
+            | Event::PointerScroll(_)
+            | Event::Timer(_)
+            | Event::Signal(_)
+            | Event::KeyDown(_)
+            | Event::KeyUp(_)
+            | Event::TextInput(_)
+            | Event::TextCopy
+    )
+}
+
+impl EventRecording {
+    /// Serialize to the text format described in the [module-level docs](self).
+    ///
+    /// Panics if [`EventRecording::events`] contains events that don't get recorded, like
+    /// [`Event::NextFrame`].
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for recorded_event in &self.events {
+            text.push_str(&recorded_event.time.to_string());
+            Line { text: &mut text }.write_event(&recorded_event.event);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parse the text format described in the [module-level docs](self). Empty lines and lines
+    /// starting with `#` are ignored.
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut events = Vec::new();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let recorded_event = parse_line(line).map_err(|err| format!("Line {}: {}", line_index + 1, err))?;
+            events.push(recorded_event);
+        }
+        Ok(Self { events })
+    }
+
+    /// Write the text format described in the [module-level docs](self) to a file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write_to_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    /// Read a file written using [`EventRecording::write_to_file`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_from_file(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        Self::from_text(&text)
+    }
+}
+
+/// A value in a `key=value` field. Values only contain whitespace within quoted strings.
+trait FieldValue: Sized {
+    fn write(&self, text: &mut String);
+    fn parse(value: &str) -> Result<Self, String>;
+}
+
+macro_rules! impl_field_value_from_str {
+    ($($ty:ty),*) => {
+        $(
+            impl FieldValue for $ty {
+                fn write(&self, text: &mut String) {
+                    text.push_str(&self.to_string());
+                }
+                fn parse(value: &str) -> Result<Self, String> {
+                    value.parse().map_err(|_| format!("Invalid {}: {:?}", stringify!($ty), value))
+                }
+            }
+        )*
+    };
+}
+impl_field_value_from_str!(bool, u32, u64, usize, f32, f64);
+
+impl FieldValue for Vec2 {
+    fn write(&self, text: &mut String) {
+        text.push_str(&format!("{},{}", self.x, self.y));
+    }
+    fn parse(value: &str) -> Result<Self, String> {
+        match parse_floats(value)?.as_slice() {
+            [x, y] => Ok(vec2(*x, *y)),
+            _ => Err(format!("Expected 2 numbers: {:?}", value)),
+        }
+    }
+}
+
+impl FieldValue for Rect {
+    fn write(&self, text: &mut String) {
+        text.push_str(&format!("{},{},{},{}", self.pos.x, self.pos.y, self.size.x, self.size.y));
+    }
+    fn parse(value: &str) -> Result<Self, String> {
+        match parse_floats(value)?.as_slice() {
+            [x, y, w, h] => Ok(Rect { pos: vec2(*x, *y), size: vec2(*w, *h) }),
+            _ => Err(format!("Expected 4 numbers: {:?}", value)),
+        }
+    }
+}
+
+fn parse_floats(value: &str) -> Result<Vec<f32>, String> {
+    value.split(',').map(f32::parse).collect()
+}
+
+impl FieldValue for String {
+    fn write(&self, text: &mut String) {
+        text.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => text.push_str("\\\""),
+                '\\' => text.push_str("\\\\"),
+                '\n' => text.push_str("\\n"),
+                '\r' => text.push_str("\\r"),
+                '\t' => text.push_str("\\t"),
+                c => text.push(c),
+            }
+        }
+        text.push('"');
+    }
+    fn parse(value: &str) -> Result<Self, String> {
+        let inner = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .ok_or_else(|| format!("Expected a quoted string: {}", value))?;
+        let mut string = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                string.push(match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    other => return Err(format!("Invalid escape sequence: \\{}", other.map(String::from).unwrap_or_default())),
+                });
+            } else {
+                string.push(c);
+            }
+        }
+        Ok(string)
+    }
+}
+
+impl FieldValue for KeyModifiers {
+    fn write(&self, text: &mut String) {
+        let names = [(self.shift, "shift"), (self.control, "control"), (self.alt, "alt"), (self.logo, "logo")];
+        let names: Vec<&str> = names.iter().filter(|(enabled, _)| *enabled).map(|(_, name)| *name).collect();
+        text.push_str(&names.join("+"));
+    }
+    fn parse(value: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::default();
+        for name in value.split('+').filter(|name| !name.is_empty()) {
+            match name {
+                "shift" => modifiers.shift = true,
+                "control" => modifiers.control = true,
+                "alt" => modifiers.alt = true,
+                "logo" => modifiers.logo = true,
+                _ => return Err(format!("Invalid modifier: {:?}", name)),
+            }
+        }
+        Ok(modifiers)
+    }
+}
+
+/// Implement [`FieldValue`] for fieldless enums, using their [`std::fmt::Debug`] names.
+macro_rules! impl_field_value_for_enum {
+    ($ty:ty, [$($variant:ident),* $(,)?]) => {
+        impl FieldValue for $ty {
+            fn write(&self, text: &mut String) {
+                text.push_str(&format!("{:?}", self));
+            }
+            fn parse(value: &str) -> Result<Self, String> {
+                match value {
+                    $(stringify!($variant) => Ok(<$ty>::$variant),)*
+                    _ => Err(format!("Invalid {}: {:?}", stringify!($ty), value)),
+                }
+            }
+        }
+    };
+}
+impl_field_value_for_enum!(MouseButton, [Left, Right, Other]);
+impl_field_value_for_enum!(PointerInputType, [Mouse, Touch, XR]);
+impl_field_value_for_enum!(HoverState, [In, Over, Out]);
+#[rustfmt::skip]
+impl_field_value_for_enum!(KeyCode, [
+    Escape, Backtick, Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Minus, Equals, Backspace, Tab, KeyQ,
+    KeyW, KeyE, KeyR, KeyT, KeyY, KeyU, KeyI, KeyO, KeyP, LBracket, RBracket, Return, KeyA, KeyS, KeyD, KeyF, KeyG, KeyH,
+    KeyJ, KeyK, KeyL, Semicolon, Quote, Backslash, KeyZ, KeyX, KeyC, KeyV, KeyB, KeyN, KeyM, Comma, Period, Slash, Control,
+    Alt, Shift, Logo, Space, Capslock, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, PrintScreen, Scrolllock, Pause,
+    Insert, Delete, Home, End, PageUp, PageDown, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7,
+    Numpad8, Numpad9, NumpadEquals, NumpadSubtract, NumpadAdd, NumpadDecimal, NumpadMultiply, NumpadDivide, Numlock,
+    NumpadEnter, ArrowUp, ArrowDown, ArrowLeft, ArrowRight, Unknown,
+]);
+
+/// Signals are written as `signal_id:status|status|...`, separated by `;`.
+impl FieldValue for HashMap<Signal, BTreeSet<StatusId>> {
+    fn write(&self, text: &mut String) {
+        // Sort so that the output is deterministic.
+        let mut signals: Vec<_> = self.iter().collect();
+        signals.sort_by_key(|(signal, _)| signal.signal_id);
+        let signals: Vec<String> = signals
+            .iter()
+            .map(|(signal, statusses)| {
+                let statusses: Vec<String> = statusses.iter().map(|status| status.0.to_string()).collect();
+                format!("{}:{}", signal.signal_id, statusses.join("|"))
+            })
+            .collect();
+        text.push_str(&signals.join(";"));
+    }
+    fn parse(value: &str) -> Result<Self, String> {
+        let mut signals = HashMap::new();
+        for signal in value.split(';').filter(|signal| !signal.is_empty()) {
+            let (signal_id, statusses) = signal.split_once(':').ok_or_else(|| format!("Invalid signal: {:?}", signal))?;
+            let statusses = statusses
+                .split('|')
+                .filter(|status| !status.is_empty())
+                .map(|status| Ok(LocationHash(u64::parse(status)?)))
+                .collect::<Result<BTreeSet<StatusId>, String>>()?;
+            signals.insert(Signal { signal_id: usize::parse(signal_id)? }, statusses);
+        }
+        Ok(signals)
+    }
+}
+
+/// Helper for writing a single line of an [`EventRecording`].
+struct Line<'a> {
+    text: &'a mut String,
+}
+
+impl Line<'_> {
+    fn field(&mut self, key: &str, value: &impl FieldValue) -> &mut Self {
+        self.text.push(' ');
+        self.text.push_str(key);
+        self.text.push('=');
+        value.write(self.text);
+        self
+    }
+
+    fn window_geom(&mut self, prefix: &str, geom: &WindowGeom) -> &mut Self {
+        self.field(&format!("{}.dpi_factor", prefix), &geom.dpi_factor)
+            .field(&format!("{}.can_fullscreen", prefix), &geom.can_fullscreen)
+            .field(&format!("{}.xr_can_present", prefix), &geom.xr_can_present)
+            .field(&format!("{}.xr_is_presenting", prefix), &geom.xr_is_presenting)
+            .field(&format!("{}.is_fullscreen", prefix), &geom.is_fullscreen)
+            .field(&format!("{}.is_topmost", prefix), &geom.is_topmost)
+            .field(&format!("{}.position", prefix), &geom.position)
+            .field(&format!("{}.inner_size", prefix), &geom.inner_size)
+            .field(&format!("{}.outer_size", prefix), &geom.outer_size)
+    }
+
+    fn kind(&mut self, kind: &str) -> &mut Self {
+        self.text.push(' ');
+        self.text.push_str(kind);
+        self
+    }
+
+    fn write_event(&mut self, event: &Event) {
+        match event {
+            Event::AppFocus => {
+                self.kind("AppFocus");
+            }
+            Event::AppFocusLost => {
+                self.kind("AppFocusLost");
+            }
+            Event::WindowGeomChange(wg) => {
+                self.kind("WindowGeomChange")
+                    .field("window_id", &wg.window_id)
+                    .window_geom("old_geom", &wg.old_geom)
+                    .window_geom("new_geom", &wg.new_geom);
+            }
+            Event::PointerDown(pe) => {
+                self.kind("PointerDown")
+                    .field("window_id", &pe.window_id)
+                    .field("abs", &pe.abs)
+                    .field("rel", &pe.rel)
+                    .field("rect", &pe.rect)
+                    .field("digit", &pe.digit)
+                    .field("button", &pe.button)
+                    .field("tap_count", &pe.tap_count)
+                    .field("input_type", &pe.input_type)
+                    .field("modifiers", &pe.modifiers)
+                    .field("time", &pe.time);
+            }
+            Event::PointerMove(pe) => {
+                self.kind("PointerMove")
+                    .field("window_id", &pe.window_id)
+                    .field("abs", &pe.abs)
+                    .field("abs_start", &pe.abs_start)
+                    .field("rel", &pe.rel)
+                    .field("rel_start", &pe.rel_start)
+                    .field("rect", &pe.rect)
+                    .field("is_over", &pe.is_over)
+                    .field("digit", &pe.digit)
+                    .field("input_type", &pe.input_type)
+                    .field("modifiers", &pe.modifiers)
+                    .field("time", &pe.time);
+            }
+            Event::PointerHover(pe) => {
+                self.kind("PointerHover")
+                    .field("window_id", &pe.window_id)
+                    .field("digit", &pe.digit)
+                    .field("abs", &pe.abs)
+                    .field("rel", &pe.rel)
+                    .field("rect", &pe.rect)
+                    .field("any_down", &pe.any_down)
+                    .field("hover_state", &pe.hover_state)
+                    .field("modifiers", &pe.modifiers)
+                    .field("time", &pe.time);
+            }
+            Event::PointerUp(pe) => {
+                self.kind("PointerUp")
+                    .field("window_id", &pe.window_id)
+                    .field("abs", &pe.abs)
+                    .field("abs_start", &pe.abs_start)
+                    .field("rel", &pe.rel)
+                    .field("rel_start", &pe.rel_start)
+                    .field("rect", &pe.rect)
+                    .field("digit", &pe.digit)
+                    .field("button", &pe.button)
+                    .field("is_over", &pe.is_over)
+                    .field("input_type", &pe.input_type)
+                    .field("modifiers", &pe.modifiers)
+                    .field("time", &pe.time);
+            }
+            Event::PointerScroll(pe) => {
+                self.kind("PointerScroll")
+                    .field("window_id", &pe.window_id)
+                    .field("digit", &pe.digit)
+                    .field("abs", &pe.abs)
+                    .field("rel", &pe.rel)
+                    .field("rect", &pe.rect)
+                    .field("scroll", &pe.scroll)
+                    .field("input_type", &pe.input_type)
+                    .field("handled_x", &pe.handled_x)
+                    .field("handled_y", &pe.handled_y)
+                    .field("modifiers", &pe.modifiers)
+                    .field("time", &pe.time);
+            }
+            Event::Timer(te) => {
+                self.kind("Timer").field("timer_id", &te.timer_id);
+            }
+            Event::Signal(se) => {
+                self.kind("Signal").field("signals", &se.signals);
+            }
+            Event::KeyDown(ke) | Event::KeyUp(ke) => {
+                self.kind(if let Event::KeyDown(_) = event { "KeyDown" } else { "KeyUp" })
+                    .field("key_code", &ke.key_code)
+                    .field("is_repeat", &ke.is_repeat)
+                    .field("modifiers", &ke.modifiers)
+                    .field("time", &ke.time);
+            }
+            Event::TextInput(te) => {
+                self.kind("TextInput")
+                    .field("input", &te.input)
+                    .field("replace_last", &te.replace_last)
+                    .field("was_paste", &te.was_paste);
+            }
+            Event::TextCopy => {
+                self.kind("TextCopy");
+            }
+            _ => panic!("Event can't be recorded: {:?}", event),
+        }
+    }
+}
+
+/// Split a line into whitespace-separated tokens, except for whitespace in quoted strings.
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut token_start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c.is_whitespace() {
+            if let Some(start) = token_start.take() {
+                tokens.push(&line[start..index]);
+            }
+        } else {
+            token_start.get_or_insert(index);
+            in_string = c == '"';
+        }
+    }
+    if let Some(start) = token_start {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
+
+fn parse_line(line: &str) -> Result<RecordedEvent, String> {
+    let mut tokens = tokenize(line).into_iter();
+    let time = f64::parse(tokens.next().unwrap_or_default())?;
+    let kind = tokens.next().ok_or("Missing event type")?;
+    let mut fields = Fields { fields: HashMap::new() };
+    for token in tokens {
+        let (key, value) = token.split_once('=').ok_or_else(|| format!("Expected key=value: {:?}", token))?;
+        fields.fields.insert(key, value);
+    }
+
+    let event = match kind {
+        "AppFocus" => Event::AppFocus,
+        "AppFocusLost" => Event::AppFocusLost,
+        "WindowGeomChange" => Event::WindowGeomChange(WindowGeomChangeEvent {
+            window_id: fields.get("window_id")?,
+            old_geom: fields.window_geom("old_geom")?,
+            new_geom: fields.window_geom("new_geom")?,
+        }),
+        "PointerDown" => Event::PointerDown(PointerDownEvent {
+            window_id: fields.get("window_id")?,
+            abs: fields.get("abs")?,
+            rel: fields.get("rel")?,
+            rect: fields.get("rect")?,
+            digit: fields.get("digit")?,
+            button: fields.get("button")?,
+            tap_count: fields.get("tap_count")?,
+            handled: false,
+            input_type: fields.get("input_type")?,
+            modifiers: fields.get("modifiers")?,
+            time: fields.get("time")?,
+        }),
+        "PointerMove" => Event::PointerMove(PointerMoveEvent {
+            window_id: fields.get("window_id")?,
+            abs: fields.get("abs")?,
+            abs_start: fields.get("abs_start")?,
+            rel: fields.get("rel")?,
+            rel_start: fields.get("rel_start")?,
+            rect: fields.get("rect")?,
+            is_over: fields.get("is_over")?,
+            digit: fields.get("digit")?,
+            input_type: fields.get("input_type")?,
+            modifiers: fields.get("modifiers")?,
+            time: fields.get("time")?,
+        }),
+        "PointerHover" => Event::PointerHover(PointerHoverEvent {
+            window_id: fields.get("window_id")?,
+            digit: fields.get("digit")?,
+            abs: fields.get("abs")?,
+            rel: fields.get("rel")?,
+            rect: fields.get("rect")?,
+            any_down: fields.get("any_down")?,
+            handled: false,
+            hover_state: fields.get("hover_state")?,
+            modifiers: fields.get("modifiers")?,
+            time: fields.get("time")?,
+        }),
+        "PointerUp" => Event::PointerUp(PointerUpEvent {
+            window_id: fields.get("window_id")?,
+            abs: fields.get("abs")?,
+            abs_start: fields.get("abs_start")?,
+            rel: fields.get("rel")?,
+            rel_start: fields.get("rel_start")?,
+            rect: fields.get("rect")?,
+            digit: fields.get("digit")?,
+            button: fields.get("button")?,
+            is_over: fields.get("is_over")?,
+            input_type: fields.get("input_type")?,
+            modifiers: fields.get("modifiers")?,
+            time: fields.get("time")?,
+        }),
+        "PointerScroll" => Event::PointerScroll(PointerScrollEvent {
+            window_id: fields.get("window_id")?,
+            digit: fields.get("digit")?,
+            abs: fields.get("abs")?,
+            rel: fields.get("rel")?,
+            rect: fields.get("rect")?,
+            scroll: fields.get("scroll")?,
+            input_type: fields.get("input_type")?,
+            handled_x: fields.get("handled_x")?,
+            handled_y: fields.get("handled_y")?,
+            modifiers: fields.get("modifiers")?,
+            time: fields.get("time")?,
+        }),
+        "Timer" => Event::Timer(TimerEvent { timer_id: fields.get("timer_id")? }),
+        "Signal" => Event::Signal(SignalEvent { signals: fields.get("signals")? }),
+        "KeyDown" | "KeyUp" => {
+            let key_event = KeyEvent {
+                key_code: fields.get("key_code")?,
+                is_repeat: fields.get("is_repeat")?,
+                modifiers: fields.get("modifiers")?,
+                time: fields.get("time")?,
+            };
+            if kind == "KeyDown" {
+                Event::KeyDown(key_event)
+            } else {
+                Event::KeyUp(key_event)
+            }
+        }
+        "TextInput" => Event::TextInput(TextInputEvent {
+            input: fields.get("input")?,
+            replace_last: fields.get("replace_last")?,
+            was_paste: fields.get("was_paste")?,
+        }),
+        "TextCopy" => Event::TextCopy,
+        _ => return Err(format!("Unknown event type: {:?}", kind)),
+    };
+    fields.check_all_used()?;
+    Ok(RecordedEvent { time, event })
+}
+
+/// The `key=value` fields of a single line. Gets emptied out while parsing, so we can detect
+/// unknown fields.
+struct Fields<'a> {
+    fields: HashMap<&'a str, &'a str>,
+}
+
+impl Fields<'_> {
+    /// Parse a field, or return the default value if it doesn't exist.
+    fn get<T: FieldValue + Default>(&mut self, key: &str) -> Result<T, String> {
+        match self.fields.remove(key) {
+            Some(value) => T::parse(value).map_err(|err| format!("Field {:?}: {}", key, err)),
+            None => Ok(T::default()),
+        }
+    }
+
+    fn window_geom(&mut self, prefix: &str) -> Result<WindowGeom, String> {
+        Ok(WindowGeom {
+            dpi_factor: self.get(&format!("{}.dpi_factor", prefix))?,
+            can_fullscreen: self.get(&format!("{}.can_fullscreen", prefix))?,
+            xr_can_present: self.get(&format!("{}.xr_can_present", prefix))?,
+            xr_is_presenting: self.get(&format!("{}.xr_is_presenting", prefix))?,
+            is_fullscreen: self.get(&format!("{}.is_fullscreen", prefix))?,
+            is_topmost: self.get(&format!("{}.is_topmost", prefix))?,
+            position: self.get(&format!("{}.position", prefix))?,
+            inner_size: self.get(&format!("{}.inner_size", prefix))?,
+            outer_size: self.get(&format!("{}.outer_size", prefix))?,
+        })
+    }
+
+    fn check_all_used(&self) -> Result<(), String> {
+        match self.fields.keys().next() {
+            Some(key) => Err(format!("Unknown field: {:?}", key)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::any::TypeId;
+
+    #[test]
+    fn recordings_survive_a_round_trip_through_text() {
+        let modifiers = KeyModifiers { shift: true, control: false, alt: true, logo: true };
+        let rect = Rect { pos: vec2(1., 2.), size: vec2(30., 40.5) };
+        let geom = |dpi_factor| WindowGeom {
+            dpi_factor,
+            can_fullscreen: true,
+            xr_can_present: true,
+            xr_is_presenting: false,
+            is_fullscreen: true,
+            is_topmost: true,
+            position: vec2(-5., 6.),
+            inner_size: vec2(800., 600.),
+            outer_size: vec2(810., 640.),
+        };
+        let signals = HashMap::from([
+            (Signal { signal_id: 3 }, BTreeSet::from([LocationHash(7), LocationHash(u64::MAX)])),
+            (Signal { signal_id: 1 }, BTreeSet::new()),
+        ]);
+        let events = [
+            Event::AppFocus,
+            Event::AppFocusLost,
+            Event::WindowGeomChange(WindowGeomChangeEvent { window_id: 1, old_geom: geom(1.), new_geom: geom(2.) }),
+            Event::PointerDown(PointerDownEvent {
+                window_id: 1,
+                abs: vec2(10.5, 20.25),
+                rel: vec2(0.5, 0.25),
+                rect,
+                digit: 2,
+                button: MouseButton::Right,
+                tap_count: 2,
+                handled: false,
+                input_type: PointerInputType::Touch,
+                modifiers: modifiers.clone(),
+                time: 12.5,
+            }),
+            Event::PointerMove(PointerMoveEvent {
+                window_id: 1,
+                abs: vec2(11., 21.),
+                abs_start: vec2(10.5, 20.25),
+                rel: vec2(1., 1.),
+                rel_start: vec2(0.5, 0.25),
+                rect,
+                is_over: true,
+                digit: 2,
+                input_type: PointerInputType::Touch,
+                modifiers: modifiers.clone(),
+                time: 12.75,
+            }),
+            Event::PointerHover(PointerHoverEvent {
+                window_id: 1,
+                digit: 1,
+                abs: vec2(3., 4.),
+                rel: vec2(2., 2.),
+                rect,
+                any_down: true,
+                handled: false,
+                hover_state: HoverState::Out,
+                modifiers: modifiers.clone(),
+                time: 13.,
+            }),
+            Event::PointerUp(PointerUpEvent {
+                window_id: 1,
+                abs: vec2(11., 21.),
+                abs_start: vec2(10.5, 20.25),
+                rel: vec2(1., 1.),
+                rel_start: vec2(0.5, 0.25),
+                rect,
+                digit: 2,
+                button: MouseButton::Other,
+                is_over: true,
+                input_type: PointerInputType::XR,
+                modifiers: modifiers.clone(),
+                time: 14.,
+            }),
+            Event::PointerScroll(PointerScrollEvent {
+                window_id: 1,
+                digit: 0,
+                abs: vec2(5., 6.),
+                rel: vec2(1., 2.),
+                rect,
+                scroll: vec2(-0.125, 3.),
+                input_type: PointerInputType::Mouse,
+                handled_x: true,
+                handled_y: false,
+                modifiers: modifiers.clone(),
+                time: 15.,
+            }),
+            Event::Timer(TimerEvent { timer_id: 42 }),
+            Event::Signal(SignalEvent { signals }),
+            Event::KeyDown(KeyEvent { key_code: KeyCode::NumpadEnter, is_repeat: true, modifiers: modifiers.clone(), time: 16. }),
+            Event::KeyUp(KeyEvent { key_code: KeyCode::KeyQ, is_repeat: false, modifiers, time: 17. }),
+            Event::TextInput(TextInputEvent {
+                input: "say \"hi\"\\\n\tthere\r = # ok".to_string(),
+                replace_last: true,
+                was_paste: true,
+            }),
+            Event::TextCopy,
+        ];
+
+        let mut cx = Cx::new(TypeId::of::<()>());
+        cx.last_event_time = 100.;
+        cx.start_event_recording();
+        for (index, event) in events.iter().enumerate() {
+            cx.last_event_time = 100. + index as f64 * 0.25;
+            cx.record_event(event);
+            // Events that follow from recorded ones don't get recorded themselves.
+            cx.record_event(&Event::NextFrame);
+        }
+        let recording = cx.stop_event_recording();
+        assert!(cx.stop_event_recording().events.is_empty());
+
+        let text = recording.to_text();
+        let parsed = EventRecording::from_text(&text).unwrap();
+        assert_eq!(parsed.events.len(), events.len());
+        for (index, (recorded_event, event)) in parsed.events.iter().zip(&events).enumerate() {
+            assert_eq!(recorded_event.time, index as f64 * 0.25);
+            match (&recorded_event.event, event) {
+                // The order of a `HashMap` isn't stable, so its `Debug` output can't be compared.
+                (Event::Signal(recorded), Event::Signal(expected)) => assert_eq!(recorded, expected),
+                _ => assert_eq!(format!("{:?}", recorded_event.event), format!("{:?}", event)),
+            }
+        }
+        assert_eq!(parsed.to_text(), text);
+    }
+}
@@ -265,6 +265,18 @@ pub struct WebSocketMessageEvent {
     pub result: Result<Vec<u8>, String>,
 }
 
+/// See [`Event::PassReadback`].
+#[derive(Clone, Debug)]
+pub struct PassReadbackEvent {
+    /// The [`Pass::pass_id`] of the [`Pass`] that got read back.
+    pub pass_id: usize,
+    pub width: usize,
+    pub height: usize,
+    /// Pixels stored row by row starting at the top left, in the same RGBA format as
+    /// [`TextureHandle::get_image_mut`].
+    pub pixels: Vec<u32>,
+}
+
 /// A file that was supplied by a user, as opposed to by the application itself (like font resources
 /// and such).
 #[derive(Clone, Debug)]
@@ -385,6 +397,8 @@ pub enum Event {
     FileDragUpdate(FileDragUpdateEvent),
     /// When a file is being dragged and the mouse moves out of the window
     FileDragCancel,
+    /// The pixels of a [`Pass`] got read back, as requested using [`Pass::request_readback`].
+    PassReadback(PassReadbackEvent),
     /// Events that are handled internally and are not propagated to an application `handle` method.
     SystemEvent(SystemEvent),
 }
@@ -6,6 +6,7 @@
 
 //! Font drawing primitives.
 
+use std::io::Read;
 use std::sync::RwLock;
 use std::sync::RwLockReadGuard;
 
@@ -28,12 +29,15 @@ const FONTS_BYTES: &[&[u8]] =
 /// The default [`TextStyle`].
 pub const TEXT_STYLE_NORMAL: TextStyle = TextStyle {
     font: FONT_UBUNTU_REGULAR,
+    font_fallbacks: FontFallbacks::NONE,
     font_size: 8.0,
     brightness: 1.0,
     curve: 0.6,
     line_spacing: 1.4,
     top_drop: 1.2,
     height_factor: 1.3,
+    kerning: false,
+    ligatures: false,
 };
 
 /// A monospace [`TextStyle`].
@@ -47,51 +51,158 @@ pub const TEXT_STYLE_MONO: TextStyle = TextStyle {
 };
 
 /// A pointer to a [`CxFont`] (indexed in [`CxFontsData::fonts`] using [`Font::font_id`]),
+///
+/// Besides the built-in fonts used by [`TEXT_STYLE_NORMAL`] and [`TEXT_STYLE_MONO`], fonts can be registered
+/// at runtime using [`Cx::load_font`].
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Font {
     pub font_id: usize,
 }
 
+/// A list of fallback fonts for [`TextStyle::font_fallbacks`], registered using [`Cx::add_font_fallbacks`]
+/// (indexed in [`CxFontsData::font_fallbacks`] using [`FontFallbacks::fallbacks_id`]).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FontFallbacks {
+    pub fallbacks_id: usize,
+}
+
+impl FontFallbacks {
+    /// No fallback fonts.
+    pub const NONE: FontFallbacks = FontFallbacks { fallbacks_id: 0 };
+}
+
+/// The styles of a single typeface, e.g. registered using [`Cx::load_font`] for each font file.
+///
+/// Use [`FontFamily::get`] to pick the [`Font`] for a [`TextStyle`], e.g.
+/// `TextStyle { font: family.get(true, false), ..TEXT_STYLE_NORMAL }` for bold text.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FontFamily {
+    pub regular: Font,
+    pub bold: Option<Font>,
+    pub italic: Option<Font>,
+    pub bold_italic: Option<Font>,
+}
+
+impl FontFamily {
+    /// Returns the closest available style; e.g. bold if there is no bold italic font, and regular if there
+    /// is no bold font either.
+    pub fn get(&self, bold: bool, italic: bool) -> Font {
+        let candidates = match (bold, italic) {
+            (false, false) => [None, None, None],
+            (true, false) => [self.bold, None, None],
+            (false, true) => [self.italic, None, None],
+            (true, true) => [self.bold_italic, self.bold, self.italic],
+        };
+        candidates.into_iter().flatten().next().unwrap_or(self.regular)
+    }
+}
+
 /// Style for how to render text.
 /// TODO(hernan): Should we include color and font scaling as part of the text style?
 #[derive(Clone, Debug, Copy)]
 pub struct TextStyle {
     pub font: Font,
+    /// Fonts to use, in order, for characters that [`TextStyle::font`] doesn't have a glyph for; e.g. for
+    /// symbols or other scripts. Each glyph is scaled using its own font's metrics, and all glyphs share the
+    /// same baseline. Characters that none of the fonts have are drawn using the missing glyph of
+    /// [`TextStyle::font`], or skipped by [`TextIns::draw_walk`].
+    pub font_fallbacks: FontFallbacks,
     pub font_size: f32,
     pub brightness: f32,
     pub curve: f32,
     pub line_spacing: f32,
     pub top_drop: f32,
     pub height_factor: f32,
+    /// Whether to adjust the spacing between pairs of characters using the font's kerning, like in "AV". Makes
+    /// proportional text look better, but don't use this if you rely on every character having a fixed width.
+    pub kerning: bool,
+    /// Whether to replace sequences of characters with the font's ligatures, like "fi". A ligature gets drawn as
+    /// a single glyph for all its characters; callbacks of [`TextIns::generate_2d_glyphs`] still get called for
+    /// each character, with an equal share of the ligature's width.
+    pub ligatures: bool,
 }
 
 impl Default for TextStyle {
     fn default() -> Self {
         TextStyle {
             font: Font { font_id: 0 },
+            font_fallbacks: FontFallbacks::NONE,
             font_size: 8.0,
             brightness: 1.0,
             curve: 0.6,
             line_spacing: 1.4,
             top_drop: 1.1,
             height_factor: 1.3,
+            kerning: false,
+            ligatures: false,
         }
     }
 }
 
 impl Cx {
+    /// Loads the built-in fonts, unless that already happened. They have to come first, so this also gets called
+    /// by [`Cx::load_font`].
     pub(crate) fn load_fonts(&mut self) {
         #[cfg(not(feature = "disable-fonts"))]
         {
             let mut write_fonts_data = self.fonts_data.write().unwrap();
-            write_fonts_data.fonts = Iterator::map(FONTS_BYTES.iter(), |bytes| {
-                let font = wrflib_vector::ttf_parser::parse_ttf(bytes).expect("Error loading font");
-                CxFont { font_loaded: Some(font), atlas_pages: vec![] }
-            })
-            .collect();
+            if write_fonts_data.fonts.is_empty() {
+                write_fonts_data.fonts = Iterator::map(FONTS_BYTES.iter(), |bytes| {
+                    let font = wrflib_vector::ttf_parser::parse_ttf(bytes).expect("Error loading font");
+                    CxFont { font_loaded: Some(font), atlas_pages: vec![] }
+                })
+                .collect();
+            }
         }
     }
 
+    /// Registers a TrueType (`.ttf`) or OpenType (`.otf`) font, so it can be used in a [`TextStyle`]. Fonts stay
+    /// loaded for the lifetime of the [`Cx`], so only call this once per font, e.g. when constructing your app. Use
+    /// a [`FontFamily`] to group bold and italic variants.
+    ///
+    /// Returns an error if the font can't be parsed.
+    pub fn load_font(&mut self, bytes: &[u8]) -> Result<Font, String> {
+        self.load_font_from_collection(bytes, 0)
+    }
+
+    /// Registers a font from a TrueType or OpenType Collection (`.ttc` or `.otc`), like [`Cx::load_font`].
+    /// `index` is the index of the font in the collection.
+    pub fn load_font_from_collection(&mut self, bytes: &[u8], index: usize) -> Result<Font, String> {
+        let font = wrflib_vector::ttf_parser::parse_ttc(bytes, index).map_err(|_| "Invalid or unsupported font".to_string())?;
+        self.load_fonts();
+        let mut write_fonts_data = self.fonts_data.write().unwrap();
+        write_fonts_data.fonts.push(CxFont { font_loaded: Some(font), atlas_pages: vec![] });
+        Ok(Font { font_id: write_fonts_data.fonts.len() - 1 })
+    }
+
+    /// Registers a list of fallback fonts for [`TextStyle::font_fallbacks`]. Registering the same list again
+    /// returns the same [`FontFallbacks`].
+    pub fn add_font_fallbacks(&mut self, fonts: &[Font]) -> FontFallbacks {
+        if fonts.is_empty() {
+            return FontFallbacks::NONE;
+        }
+        let mut write_fonts_data = self.fonts_data.write().unwrap();
+        if write_fonts_data.font_fallbacks.is_empty() {
+            // Reserve the id of `FontFallbacks::NONE`.
+            write_fonts_data.font_fallbacks.push(vec![]);
+        }
+        let fallbacks_id = match write_fonts_data.font_fallbacks.iter().position(|fallbacks| fallbacks == fonts) {
+            Some(fallbacks_id) => fallbacks_id,
+            None => {
+                write_fonts_data.font_fallbacks.push(fonts.to_vec());
+                write_fonts_data.font_fallbacks.len() - 1
+            }
+        };
+        FontFallbacks { fallbacks_id }
+    }
+
+    /// Reads and registers a font using [`Cx::load_font`], e.g. from a file opened using [`UniversalFile::open`].
+    pub fn load_font_file(&mut self, file: &mut UniversalFile) -> Result<Font, String> {
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes).map_err(|err| err.to_string())?;
+        self.load_font(&bytes)
+    }
+
     pub fn reset_font_atlas_and_redraw(&mut self) {
         {
             // Use a block here to constraint the lifetime of locks
@@ -112,7 +223,7 @@ impl Cx {
     }
 }
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -216,6 +327,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 #[derive(Clone, Default)]
 pub(crate) struct TrapezoidText {
@@ -292,9 +404,9 @@ impl TrapezoidText {
                 let atlas_page = &cxfont.atlas_pages[todo.atlas_page_id];
                 let glyph = &font.glyphs[todo.glyph_id];
 
-                if todo.glyph_id == font.char_code_to_glyph_index_map[10]
-                    || todo.glyph_id == font.char_code_to_glyph_index_map[9]
-                    || todo.glyph_id == font.char_code_to_glyph_index_map[13]
+                if todo.glyph_id == font.char_to_glyph_index('\n')
+                    || todo.glyph_id == font.char_to_glyph_index('\t')
+                    || todo.glyph_id == font.char_to_glyph_index('\r')
                 {
                     return;
                 }
@@ -520,12 +632,26 @@ impl CxFontsAtlas {
     }
 }
 
+/// A glyph to draw for one or more characters, see [`CxFontsData::shape_glyphs`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ShapedGlyph {
+    pub(crate) font_id: usize,
+    pub(crate) glyph_id: usize,
+    /// Number of characters that this glyph represents; more than 1 for ligatures.
+    pub(crate) char_count: usize,
+    /// Advance width in font units, including kerning with the next glyph.
+    pub(crate) advance_width: f32,
+}
+
 /// A context object containing everything font releated. This is used in different places to render text
 /// and also
 #[derive(Debug, Default)]
 pub struct CxFontsData {
     /// List of actual [`CxFont`] objects. [`Font::font_id`] represents an index in this list.
     pub(crate) fonts: Vec<CxFont>,
+    /// Lists of fonts registered using [`Cx::add_font_fallbacks`]. [`FontFallbacks::fallbacks_id`] represents an
+    /// index in this list, with the first one being [`FontFallbacks::NONE`] once there are any.
+    pub(crate) font_fallbacks: Vec<Vec<Font>>,
     /// See [`CxFontsAtlas`].
     pub(crate) fonts_atlas: CxFontsAtlas,
 }
@@ -535,6 +661,55 @@ impl CxFontsData {
         self.fonts_atlas.texture_handle.unwrap()
     }
 
+    /// Finds the font to draw a character with, by going through [`TextStyle::font`] and then
+    /// [`TextStyle::font_fallbacks`]. Returns the font id and glyph index, which is 0 (the "missing glyph" of
+    /// [`TextStyle::font`]) if none of the fonts have the character.
+    pub(crate) fn get_glyph(&self, text_style: &TextStyle, c: char) -> (usize, usize) {
+        let font_fallbacks = self.font_fallbacks.get(text_style.font_fallbacks.fallbacks_id).map_or(&[][..], Vec::as_slice);
+        std::iter::once(&text_style.font)
+            .chain(font_fallbacks)
+            .find_map(|font| {
+                let glyph_id = self.fonts[font.font_id].font_loaded.as_ref().unwrap().char_to_glyph_index(c);
+                (glyph_id != 0).then_some((font.font_id, glyph_id))
+            })
+            .unwrap_or((text_style.font.font_id, 0))
+    }
+
+    /// Finds the glyphs to draw for some characters, using [`CxFontsData::get_glyph`], and applying ligatures and
+    /// kerning if [`TextStyle::ligatures`] and [`TextStyle::kerning`] are set. Ligatures and kerning only apply
+    /// between characters that are drawn using the same font.
+    pub(crate) fn shape_glyphs(&self, text_style: &TextStyle, chars: &[char]) -> Vec<ShapedGlyph> {
+        let glyphs: Vec<(usize, usize)> = Iterator::map(chars.iter(), |&c| self.get_glyph(text_style, c)).collect();
+        let mut shaped_glyphs: Vec<ShapedGlyph> = Vec::with_capacity(glyphs.len());
+        for run in glyphs.chunk_by(|(font_id, _), (next_font_id, _)| font_id == next_font_id) {
+            let font_id = run[0].0;
+            let font = self.fonts[font_id].font_loaded.as_ref().unwrap();
+            let glyph_ids: Vec<usize> = Iterator::map(run.iter(), |&(_, glyph_id)| glyph_id).collect();
+            let run_glyphs = if text_style.ligatures {
+                font.apply_ligatures(&glyph_ids)
+            } else {
+                Iterator::map(glyph_ids.into_iter(), |glyph_id| (glyph_id, 1)).collect()
+            };
+            let first_run_glyph_index = shaped_glyphs.len();
+            for (glyph_id, char_count) in run_glyphs {
+                let advance_width = font.glyphs.get(glyph_id).map_or(0., |glyph| glyph.horizontal_metrics.advance_width);
+                shaped_glyphs.push(ShapedGlyph { font_id, glyph_id, char_count, advance_width });
+            }
+            if text_style.kerning {
+                for index in first_run_glyph_index..(shaped_glyphs.len() - 1) {
+                    shaped_glyphs[index].advance_width +=
+                        font.kerning(shaped_glyphs[index].glyph_id, shaped_glyphs[index + 1].glyph_id);
+                }
+            }
+        }
+        shaped_glyphs
+    }
+
+    /// Converts a font size in points to logical pixels per font unit, for the font with this id.
+    pub(crate) fn get_font_size_logical(&self, font_id: usize, font_size: f32) -> f32 {
+        font_size * 96.0 / (72.0 * self.fonts[font_id].font_loaded.as_ref().unwrap().units_per_em)
+    }
+
     pub fn new_dummy_for_tests() -> Self {
         CxFontsData::default()
     }
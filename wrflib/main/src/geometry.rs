@@ -101,6 +101,7 @@ pub(crate) struct CxGpuGeometry {
     pub(crate) geometry: Geometry,
     pub(crate) dirty: bool,
     usage_count: Rc<()>,
+    #[cfg_attr(feature = "headless", allow(dead_code))]
     pub(crate) platform: CxPlatformGpuGeometry,
 }
 
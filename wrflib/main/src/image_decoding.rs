@@ -0,0 +1,83 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Decoding PNG and JPEG images into pixels that can be uploaded to a [`crate::Texture`].
+
+/// Pixels of a decoded image, stored row by row starting at the top left, in the same RGBA
+/// format as [`crate::TextureHandle::get_image_mut`] (so red is in the lowest byte).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const JPEG_SIGNATURE: &[u8] = &[0xff, 0xd8, 0xff];
+
+/// Decodes a PNG or JPEG image, based on its first bytes.
+///
+/// Images with 16 bits per channel get reduced to 8 bits per channel, and images without an
+/// alpha channel become fully opaque.
+pub fn decode_image(bytes: &[u8]) -> Result<DecodedImage, String> {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        decode_png(bytes)
+    } else if bytes.starts_with(JPEG_SIGNATURE) {
+        decode_jpeg(bytes)
+    } else {
+        Err("Unsupported image format; only PNG and JPEG are supported".to_string())
+    }
+}
+
+fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    u32::from_le_bytes([r, g, b, a])
+}
+
+fn decode_png(bytes: &[u8]) -> Result<DecodedImage, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    // Turn palettes into RGB, expand grayscale to 8 bits, and reduce 16 bit channels to 8 bits.
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|err| err.to_string())?;
+    let bytes = &buf[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => bytes.chunks_exact(4).map(|p| rgba(p[0], p[1], p[2], p[3])).collect(),
+        png::ColorType::Rgb => bytes.chunks_exact(3).map(|p| rgba(p[0], p[1], p[2], 255)).collect(),
+        png::ColorType::GrayscaleAlpha => bytes.chunks_exact(2).map(|p| rgba(p[0], p[0], p[0], p[1])).collect(),
+        png::ColorType::Grayscale => bytes.iter().map(|&l| rgba(l, l, l, 255)).collect(),
+        png::ColorType::Indexed => return Err("PNG palette did not get expanded".to_string()),
+    };
+    Ok(DecodedImage { width: info.width as usize, height: info.height as usize, pixels })
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<DecodedImage, String> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let data = decoder.decode().map_err(|err| err.to_string())?;
+    let info = decoder.info().ok_or("JPEG has no image info")?;
+
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => data.chunks_exact(3).map(|p| rgba(p[0], p[1], p[2], 255)).collect(),
+        jpeg_decoder::PixelFormat::L8 => data.iter().map(|&l| rgba(l, l, l, 255)).collect(),
+        jpeg_decoder::PixelFormat::L16 => data
+            .chunks_exact(2)
+            .map(|p| {
+                let l = (u16::from_ne_bytes([p[0], p[1]]) >> 8) as u8;
+                rgba(l, l, l, 255)
+            })
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => data
+            .chunks_exact(4)
+            .map(|p| {
+                let k = 255 - p[3] as u32;
+                let channel = |value: u8| ((255 - value as u32) * k / 255) as u8;
+                rgba(channel(p[0]), channel(p[1]), channel(p[2]), 255)
+            })
+            .collect(),
+    };
+    Ok(DecodedImage { width: info.width as usize, height: info.height as usize, pixels })
+}
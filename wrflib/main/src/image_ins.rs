@@ -20,7 +20,7 @@ pub struct ImageIns {
     alpha: f32,
 }
 
-static SHADER: Shader = Shader {
+pub(crate) static SHADER: Shader = Shader {
     build_geom: Some(QuadIns::build_geom),
     code_to_concatenate: &[
         Cx::STD_SHADER,
@@ -57,6 +57,7 @@ static SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(SHADER);
 
 impl Default for ImageIns {
     fn default() -> Self {
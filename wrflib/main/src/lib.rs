@@ -20,39 +20,39 @@
 #[macro_use]
 mod macros;
 
-#[cfg(any(target_os = "linux"))]
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
 mod cx_linux;
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
 mod cx_opengl;
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
 mod cx_xlib;
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
 pub(crate) use cx_linux::*;
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
 pub(crate) use cx_opengl::*;
 
-#[cfg(any(target_os = "macos"))]
+#[cfg(all(target_os = "macos", not(feature = "headless")))]
 mod cx_apple;
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "headless")))]
 mod cx_cocoa;
-#[cfg(any(target_os = "macos"))]
+#[cfg(all(target_os = "macos", not(feature = "headless")))]
 mod cx_macos;
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "headless")))]
 mod cx_metal;
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "headless")))]
 pub(crate) use cx_macos::*;
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "headless")))]
 pub(crate) use cx_metal::*;
 
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 mod cx_dx11;
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 mod cx_win32;
-#[cfg(any(target_os = "windows"))]
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 mod cx_windows;
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 pub(crate) use cx_dx11::*;
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 pub(crate) use cx_windows::*;
 
 #[cfg(target_arch = "wasm32")]
@@ -60,6 +60,15 @@ mod cx_wasm32;
 #[cfg(target_arch = "wasm32")]
 mod cx_webgl;
 
+#[cfg(feature = "headless")]
+mod cx_cpu;
+#[cfg(feature = "headless")]
+mod cx_headless;
+#[cfg(feature = "headless")]
+pub(crate) use cx_cpu::*;
+#[cfg(feature = "headless")]
+pub(crate) use cx_headless::*;
+
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
 mod cx_desktop;
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
@@ -91,10 +100,12 @@ mod cx;
 pub mod debug_log;
 mod debugger;
 mod draw_tree;
+mod event_recording;
 mod events;
 mod fonts;
 mod geometry;
 mod hash;
+mod image_decoding;
 mod layout;
 mod layout_api;
 mod layout_internal;
@@ -125,7 +136,9 @@ pub use cube_ins::*;
 pub use cursor::*;
 pub use cx::*;
 pub use debugger::*;
+pub use event_recording::*;
 pub use events::*;
+pub use image_decoding::*;
 pub use image_ins::*;
 pub use param::*;
 pub use quad_ins::*;
@@ -136,6 +149,10 @@ pub use window::*;
 pub use wrflib_shader_compiler::code_fragment::CodeFragment;
 pub use wrflib_shader_compiler::math::*;
 pub use wrflib_shader_compiler::ty::Ty;
+/// Used by [`register_shader!`].
+#[doc(hidden)]
+#[cfg(feature = "shader-validation")]
+pub use inventory;
 
 pub use animator::*;
 pub use colors::*;
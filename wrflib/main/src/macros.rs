@@ -175,6 +175,29 @@ macro_rules! code_fragment {
     };
 }
 
+/// Register a static [`Shader`], so that it gets validated by [`Shader::validate_registered`], even if it doesn't
+/// get drawn in any test. Does nothing without the `shader-validation` feature.
+///
+/// ```ignore
+/// static SHADER: Shader = Shader { ... };
+/// register_shader!(SHADER);
+/// ```
+#[cfg(feature = "shader-validation")]
+#[macro_export]
+macro_rules! register_shader {
+    ( $ shader: path ) => {
+        $crate::inventory::submit! { $crate::RegisteredShader(&$shader) }
+    };
+}
+
+/// Register a static [`Shader`], so that it gets validated by `Shader::validate_registered`, even if it doesn't
+/// get drawn in any test. Does nothing without the `shader-validation` feature.
+#[cfg(not(feature = "shader-validation"))]
+#[macro_export]
+macro_rules! register_shader {
+    ( $ shader: path ) => {};
+}
+
 /// Logging helper that works both on native and WebAssembly targets.
 #[cfg(not(target_arch = "wasm32"))]
 #[macro_export]
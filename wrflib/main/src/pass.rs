@@ -126,6 +126,22 @@ impl Pass {
         }
     }
 
+    /// Requests the pixels of the first color texture of this [`Pass`]. The [`Pass`] gets painted again, and
+    /// afterwards the pixels get sent as an [`Event::PassReadback`]. Requesting it multiple times before
+    /// the next paint results in a single event.
+    ///
+    /// Reading pixels back from the GPU is slow, so only use this occasionally, e.g. for screenshots or
+    /// thumbnails. On WebGL the event arrives an animation frame later than on other platforms.
+    ///
+    /// For tests, [`Cx::headless_pass_pixels`] returns the pixels right away.
+    pub fn request_readback(&self, cx: &mut Cx) {
+        let pass_id = self.pass_id.expect("Please call request_readback after begin_pass");
+        cx.passes[pass_id].paint_dirty = true;
+        if !cx.pass_readback_requests.contains(&pass_id) {
+            cx.pass_readback_requests.push(pass_id);
+        }
+    }
+
     pub fn end_pass(&mut self, cx: &mut Cx) {
         cx.pass_stack.pop();
         if !cx.pass_stack.is_empty() {
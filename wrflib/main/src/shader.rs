@@ -8,7 +8,9 @@
 
 use crate::*;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use wrflib_shader_compiler::error::ParseError;
+use wrflib_shader_compiler::error::{ErrorCode, ParseError, ParseErrors};
+#[cfg(feature = "shader-validation")]
+use wrflib_shader_compiler::generate_shader_ast::ShaderAstGenerator;
 use wrflib_shader_compiler::span::{CodeFragmentId, Span};
 use wrflib_shader_compiler::ty::Ty;
 use wrflib_shader_compiler::{Decl, ShaderAst};
@@ -32,7 +34,8 @@ pub struct Shader {
     pub build_geom: Option<fn() -> Geometry>,
     /// A bunch of [`CodeFragment`]s that will get concatenated.
     pub code_to_concatenate: &'static [CodeFragment],
-    /// The id of the shader (index into [`Cx::shaders`]), or [`Shader::UNCOMPILED_SHADER_ID`] if uninitialized.
+    /// The id of the shader (index into [`Cx::shaders`]) in the [`Cx`] that last used it, or
+    /// [`Shader::UNCOMPILED_SHADER_ID`] if uninitialized.
     /// You should never read or modify this manually (see TODO below).
     ///
     /// TODO(JP): This shouldn't be public, but right now that's necessary for using [`Shader::DEFAULT`]. We might want to
@@ -54,24 +57,84 @@ impl Shader {
 
     const UNCOMPILED_SHADER_ID: usize = usize::MAX;
 
-    pub fn update(&'static self, cx: &mut Cx, new_code_to_concatenate: &[CodeFragment]) -> Result<(), ParseError> {
+    pub fn update(&'static self, cx: &mut Cx, new_code_to_concatenate: &[CodeFragment]) -> Result<(), ParseErrors> {
         let shader_id = cx.get_shader_id(self);
 
         let shader = &mut cx.shaders[shader_id];
         let shader_ast = cx.shader_ast_generator.generate_shader_ast(new_code_to_concatenate)?;
         if shader.mapping != CxShaderMapping::from_shader_ast(shader_ast.clone()) {
-            return Err(ParseError {
-                span: Span { code_fragment_id: CodeFragmentId(0), start: 0, end: 0 },
-                message: "Mismatch in shader mapping".to_string(),
-            });
+            return Err(ParseError::new(
+                ErrorCode::InvalidDeclaration,
+                Span { code_fragment_id: CodeFragmentId(0), start: 0, end: 0 },
+                "Mismatch in shader mapping",
+            )
+            .with_help("the geometries, instances, uniforms and textures of a shader can't change when updating it")
+            .into());
         }
         shader.shader_ast = Some(shader_ast);
         cx.shader_recompile_ids.push(shader_id);
 
         Ok(())
     }
+
+    /// Compiles this shader for every platform and validates the results, without needing a GPU. See
+    /// [`wrflib_shader_compiler::validate`] for what gets checked.
+    ///
+    /// With the `shader-validation` feature, this also happens for every [`Shader`] when it first gets used, so
+    /// running tests (e.g. using `cargo wrflib check-shaders`) checks every shader that gets drawn in them. Shaders
+    /// that don't get drawn can be registered using [`register_shader!`]; see [`Shader::validate_registered`].
+    #[cfg(feature = "shader-validation")]
+    pub fn validate(&self) -> Result<(), String> {
+        let shader_ast = ShaderAstGenerator::new()
+            .generate_shader_ast(self.code_to_concatenate)
+            .map_err(|err| err.format_for_console(self.code_to_concatenate))?;
+        self.validate_shader_ast(&shader_ast)
+    }
+
+    /// Validates every [`Shader`] that was registered using [`register_shader!`] in any crate that is linked
+    /// into the current binary, and returns the errors of all invalid shaders.
+    ///
+    /// This happens automatically when the first [`Cx`] of a process gets created, so running tests (e.g. using
+    /// `cargo wrflib check-shaders`) checks every registered shader, as long as some test creates a [`Cx`].
+    #[cfg(feature = "shader-validation")]
+    pub fn validate_registered() -> Result<(), String> {
+        let errors: Vec<String> = inventory::iter::<RegisteredShader>
+            .into_iter()
+            .filter_map(|registered_shader| registered_shader.0.validate().err())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Runs [`Shader::validate_registered`] once per process, and panics on every call if it failed.
+    #[cfg(feature = "shader-validation")]
+    pub(crate) fn validate_registered_once() {
+        static RESULT: std::sync::OnceLock<Result<(), String>> = std::sync::OnceLock::new();
+        if let Err(err) = RESULT.get_or_init(Self::validate_registered) {
+            panic!("{}", err);
+        }
+    }
+
+    #[cfg(feature = "shader-validation")]
+    fn validate_shader_ast(&self, shader_ast: &ShaderAst) -> Result<(), String> {
+        wrflib_shader_compiler::validate::validate_shader(shader_ast).map_err(|err| {
+            let main_code_fragment = self.code_to_concatenate.last().expect("No code fragments found");
+            format!("Invalid shader at {}:\n{}", main_code_fragment.name_line_col_at_offset(0), err)
+        })
+    }
 }
 
+/// A [`Shader`] that was registered using [`register_shader!`].
+#[doc(hidden)]
+#[cfg(feature = "shader-validation")]
+pub struct RegisteredShader(pub &'static Shader);
+
+#[cfg(feature = "shader-validation")]
+inventory::collect!(RegisteredShader);
+
 /// Contains information of a [`CxShader`] of what instances, instances, textures
 /// and so on it contains. That information can then be used to modify a [`Shader`
 /// or [`DrawCall`].
@@ -85,27 +148,27 @@ pub(crate) struct CxShaderMapping {
     /// Special structure for reading/editing instance properties.
     pub(crate) instance_props: InstanceProps,
     /// Special structure for reading/editing geometry properties.
-    #[cfg(any(target_arch = "wasm32", target_os = "linux", target_os = "windows"))]
+    #[cfg(any(target_arch = "wasm32", target_os = "linux", target_os = "windows", feature = "headless"))]
     pub(crate) geometry_props: InstanceProps,
     /// Raw definition of all textures.
     pub(crate) textures: Vec<PropDef>,
     /// Raw definition of all geometries.
-    #[cfg(target_os = "windows")]
+    #[cfg(all(target_os = "windows", not(feature = "headless")))]
     pub(crate) geometries: Vec<PropDef>,
     /// Raw definition of all instances.
-    #[cfg(target_os = "windows")]
+    #[cfg(all(target_os = "windows", not(feature = "headless")))]
     pub(crate) instances: Vec<PropDef>,
     /// Raw definition of all user-level uniforms.
-    #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+    #[cfg(any(target_arch = "wasm32", all(target_os = "linux", not(feature = "headless"))))]
     pub(crate) user_uniforms: Vec<PropDef>,
     /// Raw definition of all framework-level uniforms that get set per [`DrawCall`].
-    #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+    #[cfg(any(target_arch = "wasm32", all(target_os = "linux", not(feature = "headless"))))]
     pub(crate) draw_uniforms: Vec<PropDef>,
     /// Raw definition of all framework-level uniforms that get set per [`View`].
-    #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+    #[cfg(any(target_arch = "wasm32", all(target_os = "linux", not(feature = "headless"))))]
     pub(crate) view_uniforms: Vec<PropDef>,
     /// Raw definition of all framework-level uniforms that get set per [`Pass`].
-    #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+    #[cfg(any(target_arch = "wasm32", all(target_os = "linux", not(feature = "headless"))))]
     pub(crate) pass_uniforms: Vec<PropDef>,
 }
 
@@ -158,20 +221,20 @@ impl CxShaderMapping {
             rect_instance_props: RectInstanceProps::construct(&instances),
             user_uniform_props: UniformProps::construct(&user_uniforms),
             instance_props: InstanceProps::construct(&instances),
-            #[cfg(any(target_arch = "wasm32", target_os = "linux", target_os = "windows"))]
+            #[cfg(any(target_arch = "wasm32", target_os = "linux", target_os = "windows", feature = "headless"))]
             geometry_props: InstanceProps::construct(&geometries),
             textures,
-            #[cfg(target_os = "windows")]
+            #[cfg(all(target_os = "windows", not(feature = "headless")))]
             instances,
-            #[cfg(target_os = "windows")]
+            #[cfg(all(target_os = "windows", not(feature = "headless")))]
             geometries,
-            #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+            #[cfg(any(target_arch = "wasm32", all(target_os = "linux", not(feature = "headless"))))]
             pass_uniforms,
-            #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+            #[cfg(any(target_arch = "wasm32", all(target_os = "linux", not(feature = "headless"))))]
             view_uniforms,
-            #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+            #[cfg(any(target_arch = "wasm32", all(target_os = "linux", not(feature = "headless"))))]
             draw_uniforms,
-            #[cfg(any(target_arch = "wasm32", target_os = "linux"))]
+            #[cfg(any(target_arch = "wasm32", all(target_os = "linux", not(feature = "headless"))))]
             user_uniforms,
         }
     }
@@ -239,7 +302,7 @@ pub(crate) struct UniformProps {
 }
 
 /// A generic representation of any kind of [`Shader`] input (instance/uniform/geometry).
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 #[derive(Debug, Clone)]
 pub(crate) struct NamedProp {
     pub(crate) offset: usize,
@@ -247,13 +310,13 @@ pub(crate) struct NamedProp {
 }
 
 /// A generic representation of a list of [`Shader`] inputs (instance/uniform/geometry).
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 #[derive(Debug, Default, Clone)]
 pub(crate) struct NamedProps {
     pub(crate) props: Vec<NamedProp>,
 }
 
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 impl NamedProps {
     pub(crate) fn construct(in_props: &[PropDef]) -> NamedProps {
         let mut offset = 0;
@@ -295,8 +358,9 @@ impl UniformProps {
 /// The actual shader information, which gets stored on [`Cx`]. Once compiled the
 /// [`ShaderAst`] will be removed, and the [`CxPlatformShader`] (platform-specific
 /// part of the compiled shader) gets set.
-#[derive(Default)]
 pub(crate) struct CxShader {
+    /// The static [`Shader`] that this got created from.
+    pub(crate) shader: &'static Shader,
     pub(crate) name: String,
     pub(crate) gpu_geometry: Option<GpuGeometry>,
     pub(crate) platform: Option<CxPlatformShader>,
@@ -310,7 +374,13 @@ impl Cx {
     /// For more information on what [`LocationHash`] is used for here, see [`Shader`].
     pub(crate) fn get_shader_id(&mut self, shader: &'static Shader) -> usize {
         let shader_id = shader.shader_id.load(Ordering::Relaxed);
-        if shader_id != Shader::UNCOMPILED_SHADER_ID {
+        if self.shaders.get(shader_id).is_some_and(|cx_shader| std::ptr::eq(cx_shader.shader, shader)) {
+            return shader_id;
+        }
+        // The cached id might have been set by a different `Cx`, e.g. when running multiple (headless) tests in
+        // the same process. In that case we fall back to looking it up.
+        if let Some(shader_id) = self.shaders.iter().position(|cx_shader| std::ptr::eq(cx_shader.shader, shader)) {
+            shader.shader_id.store(shader_id, Ordering::Relaxed);
             shader_id
         } else {
             // Use the last code fragment as the shader name.
@@ -318,10 +388,16 @@ impl Cx {
             match self.shader_ast_generator.generate_shader_ast(shader.code_to_concatenate) {
                 Err(err) => panic!("{}", err.format_for_console(shader.code_to_concatenate)),
                 Ok(shader_ast) => {
+                    #[cfg(feature = "shader-validation")]
+                    if let Err(err) = shader.validate_shader_ast(&shader_ast) {
+                        panic!("{}", err);
+                    }
+
                     let gpu_geometry = shader.build_geom.map(|build_geom| GpuGeometry::new(self, (build_geom)()));
 
                     let shader_id = self.shaders.len();
                     self.shaders.push(CxShader {
+                        shader,
                         name: main_code_fragment.name_line_col_at_offset(0),
                         gpu_geometry,
                         mapping: CxShaderMapping::from_shader_ast(shader_ast.clone()),
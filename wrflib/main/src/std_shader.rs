@@ -308,3 +308,71 @@ impl Cx {
     "#
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wrflib_shader_compiler::generate_shader_ast::ShaderAstGenerator;
+    use wrflib_shader_compiler::generate_wgsl;
+
+    /// Uses all the functions in [`Cx::STD_SHADER`], so they all get generated.
+    static ALL_STD_FUNCTIONS_SHADER: Shader = Shader {
+        build_geom: Some(QuadIns::build_geom),
+        code_to_concatenate: &[
+            Cx::STD_SHADER,
+            QuadIns::SHADER,
+            code_fragment!(
+                r#"
+                fn pixel() -> vec4 {
+                    let df = Df::viewport(pos * rect_size);
+                    df.translate(vec2(1., 2.));
+                    df.rotate(PI, vec2(3., 4.));
+                    df.scale(2., vec2(3., 4.));
+                    df.clear(hsv2rgb(rgb2hsv(vec4(0.5, 0.5, 1., 1.))));
+                    df.circle(vec2(10., 10.), 5.);
+                    df.arc(vec2(10., 10.), 5., 0., PI);
+                    df.union();
+                    df.box(vec2(0., 0.), vec2(10., 10.), 2.);
+                    df.intersect();
+                    df.rect(vec2(0., 0.), vec2(10., 10.));
+                    df.subtract();
+                    df.triangle(vec2(0., 0.), vec2(10., 0.), vec2(0., 10.));
+                    df.blend(0.5);
+                    df.hexagon(vec2(10., 10.), 5.);
+                    df.move_to(vec2(0., 0.));
+                    df.line_to(vec2(10., 10.));
+                    df.close_path();
+                    df.add_clip(1.);
+                    df.fill(vec4(1., 0., 0., 1.));
+                    df.stroke(vec4(0., 1., 0., 1.), 2.);
+                    df.glow(vec4(0., 0., 1., 1.), 2.);
+                    let df2 = Df::viewport_px(pos);
+                    df2.new_path();
+                    return df.result + vec4(Math::rotate_2d(pos, 1.), 0., 0.) + df2.result;
+                }"#
+            ),
+        ],
+        ..Shader::DEFAULT
+    };
+
+    #[test]
+    fn std_shaders_generate_valid_wgsl() {
+        for shader in [
+            &ALL_STD_FUNCTIONS_SHADER,
+            &crate::image_ins::SHADER,
+            &TEXT_INS_SHADER,
+            &crate::fonts::SHADER,
+            &crate::debugger::BORDER_SHADER,
+        ] {
+            let shader_ast = ShaderAstGenerator::new()
+                .generate_shader_ast(shader.code_to_concatenate)
+                .unwrap_or_else(|err| panic!("{}", err.format_for_console(shader.code_to_concatenate)));
+            let wgsl = generate_wgsl::generate_shader(&shader_ast);
+            let module =
+                naga::front::wgsl::parse_str(&wgsl).unwrap_or_else(|err| panic!("{}\n{}", err.emit_to_string(&wgsl), wgsl));
+            naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::empty())
+                .validate(&module)
+                .unwrap_or_else(|err| panic!("{:?}\n{}", err, wgsl));
+        }
+    }
+}
@@ -8,6 +8,9 @@
 
 use std::sync::RwLock;
 
+use unicode_bidi::{bidi_class, BidiClass, BidiInfo, Level};
+use unicode_segmentation::UnicodeSegmentation;
+
 use crate::*;
 
 #[derive(Clone, Debug)]
@@ -27,6 +30,10 @@ pub struct TextIns {
     pub char_depth: f32,
     /// Position used in [`TextIns::closest_offset`].
     pub base: Vec2,
+    /// Distance from the caret position before the glyph's characters to the caret position after them, in view
+    /// space. Negative for glyphs in right-to-left text, where the caret moves to the left, and zero for glyphs
+    /// that don't move the caret, like combining marks.
+    pub advance: f32,
     /// Font size in pixels
     pub font_size: f32,
     /// Character index in the text string
@@ -59,6 +66,7 @@ pub static TEXT_INS_SHADER: Shader = Shader {
             instance rect_size: vec2;
             instance char_depth: float;
             instance base: vec2;
+            instance advance: float;
             instance font_size: float;
             instance char_offset: float;
             instance marker: float;
@@ -150,6 +158,7 @@ pub static TEXT_INS_SHADER: Shader = Shader {
     ],
     ..Shader::DEFAULT
 };
+register_shader!(TEXT_INS_SHADER);
 
 // Some constants for text anchoring
 // Addition can be used to combine them together: LEFT + TOP
@@ -179,6 +188,8 @@ pub struct TextInsProps {
     pub position_anchoring: Vec2,
     /// See [`Padding`].
     pub padding: Padding,
+    /// Direction of paragraphs in [`TextIns::draw_walk`], see [`TextDirection`].
+    pub direction: TextDirection,
 }
 impl TextInsProps {
     /// TODO(JP): Replace these with TextInsProps::default() when
@@ -191,6 +202,7 @@ impl TextInsProps {
         color: COLOR_WHITE,
         position_anchoring: vec2(0., 0.),
         padding: Padding::DEFAULT,
+        direction: TextDirection::DEFAULT,
     };
 }
 impl Default for TextInsProps {
@@ -224,6 +236,27 @@ impl Default for Wrapping {
     }
 }
 
+/// The base direction of paragraphs, which determines how text with both left-to-right characters (like Latin)
+/// and right-to-left characters (like Arabic or Hebrew) gets ordered, following the Unicode Bidirectional
+/// Algorithm. Lines of right-to-left paragraphs are aligned to the right.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextDirection {
+    /// Use the direction of the first letter in each paragraph, or left-to-right if there are none.
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+impl TextDirection {
+    /// TODO(JP): Replace these with TextDirection::default() when
+    /// <https://github.com/rust-lang/rust/issues/67792> gets done
+    pub const DEFAULT: TextDirection = TextDirection::Auto;
+}
+impl Default for TextDirection {
+    fn default() -> Self {
+        TextDirection::DEFAULT
+    }
+}
+
 #[derive(Default)]
 pub struct DrawGlyphsProps {
     pub text_style: TextStyle,
@@ -241,39 +274,95 @@ impl TextIns {
         pos: Vec2,
         char_offset: usize,
         chunk: &[char],
+        char_callback: F,
+    ) -> Vec<TextIns>
+    where
+        F: FnMut(char, usize, f32, f32) -> f32,
+    {
+        Self::generate_glyphs(
+            text_style,
+            fonts_data,
+            dpi_factor,
+            font_scale,
+            draw_depth,
+            color,
+            pos,
+            char_offset,
+            GlyphChunk { chars: chunk, cluster_starts: None, is_rtl: false },
+            char_callback,
+        )
+    }
+
+    /// Like [`TextIns::generate_2d_glyphs`], but with the options of [`GlyphChunk`], which are used for the
+    /// grapheme clusters and right-to-left runs in [`TextIns::draw_walk`].
+    fn generate_glyphs<F>(
+        text_style: &TextStyle,
+        fonts_data: &RwLock<CxFontsData>,
+        dpi_factor: f32,
+        font_scale: f32,
+        draw_depth: f32,
+        color: Vec4,
+        pos: Vec2,
+        char_offset: usize,
+        glyph_chunk: GlyphChunk,
         mut char_callback: F,
     ) -> Vec<TextIns>
     where
         F: FnMut(char, usize, f32, f32) -> f32,
     {
+        let chunk = glyph_chunk.chars;
         let mut ret = Vec::with_capacity(chunk.len());
 
-        let font_id = text_style.font.font_id;
-
-        let (atlas_page_id, mut read_lock) = get_font_atlas_page_id(fonts_data, font_id, dpi_factor, text_style.font_size);
+        let mut font_id = text_style.font.font_id;
 
-        let font_size_logical =
-            text_style.font_size * 96.0 / (72.0 * read_lock.fonts[font_id].font_loaded.as_ref().unwrap().units_per_em);
-        let font_size_pixels = font_size_logical * dpi_factor;
+        let (mut atlas_page_id, mut read_lock) = get_font_atlas_page_id(fonts_data, font_id, dpi_factor, text_style.font_size);
 
         let mut x = pos.x;
         let mut char_offset = char_offset;
+        let mut char_index = 0;
+        // Position and advance of the first glyph of the current grapheme cluster.
+        let mut cluster_x = x;
+        let mut cluster_advance = 0.0;
+
+        for ShapedGlyph { font_id: glyph_font_id, glyph_id, char_count, advance_width } in
+            read_lock.shape_glyphs(text_style, chunk)
+        {
+            let glyph_chars = &chunk[char_index..(char_index + char_count)];
+            let is_cluster_start = glyph_chunk.cluster_starts.as_ref().is_none_or(|cluster_starts| cluster_starts[char_index]);
+            char_index += char_count;
+            if glyph_font_id != font_id {
+                // Switch to the atlas page of a fallback font (or back to the main font).
+                drop(read_lock);
+                font_id = glyph_font_id;
+                (atlas_page_id, read_lock) = get_font_atlas_page_id(fonts_data, font_id, dpi_factor, text_style.font_size);
+            }
 
-        for wc in chunk {
-            let unicode = *wc as usize;
+            // Every font has its own units per em, but they all share the same baseline.
+            let font_size_logical = read_lock.get_font_size_logical(font_id, text_style.font_size);
+            let font_size_pixels = font_size_logical * dpi_factor;
 
             // Scope the `cxfont` borrow to these variables.
-            let (glyph_id, advance, w, h, min_pos_x, subpixel_x_fract, subpixel_y_fract, scaled_min_pos_x, scaled_min_pos_y) = {
+            let (advance, w, h, min_pos_x, subpixel_x_fract, subpixel_y_fract, scaled_min_pos_x, scaled_min_pos_y) = {
                 let cxfont = read_lock.fonts[font_id].font_loaded.as_ref().unwrap();
-                let glyph_id = cxfont.char_code_to_glyph_index_map[unicode];
                 if glyph_id >= cxfont.glyphs.len() {
-                    println!("GLYPHID OUT OF BOUNDS {} {} len is {}", unicode, glyph_id, cxfont.glyphs.len());
+                    println!("GLYPHID OUT OF BOUNDS {} {} len is {}", glyph_chars[0] as usize, glyph_id, cxfont.glyphs.len());
                     continue;
                 }
 
                 let glyph = &cxfont.glyphs[glyph_id];
 
-                let advance = glyph.horizontal_metrics.advance_width * font_size_logical * font_scale;
+                let advance = advance_width * font_size_logical * font_scale;
+
+                // Draw the other characters of a grapheme cluster, like combining marks, on top of the first
+                // character. Fonts without mark positioning usually give them either no advance (in which case
+                // they are drawn to the left of their origin) or the full advance (in which case they are
+                // centered), so center them on the first character in that case.
+                if is_cluster_start {
+                    cluster_x = x;
+                    cluster_advance = advance;
+                } else if advance != 0.0 {
+                    x = cluster_x + (cluster_advance - advance) * 0.5;
+                }
 
                 // snap width/height to pixel granularity
                 let w = ((glyph.bounds.p_max.x - glyph.bounds.p_min.x) * font_size_pixels).ceil() + 1.0;
@@ -293,7 +382,7 @@ impl TextIns {
                     + text_style.font_size * font_scale * text_style.top_drop
                     - subpixel_y_fract;
 
-                (glyph_id, advance, w, h, min_pos_x, subpixel_x_fract, subpixel_y_fract, scaled_min_pos_x, scaled_min_pos_y)
+                (advance, w, h, min_pos_x, subpixel_x_fract, subpixel_y_fract, scaled_min_pos_x, scaled_min_pos_y)
             };
 
             // only use a subpixel id for small fonts
@@ -329,6 +418,18 @@ impl TextIns {
                 read_lock.fonts[font_id].atlas_pages[atlas_page_id].atlas_glyphs[glyph_id][subpixel_id].unwrap()
             };
 
+            let advance = if is_cluster_start { advance } else { 0.0 };
+
+            // Give the callback a chance to do things, for every character of a ligature.
+            let char_advance = advance / char_count as f32;
+            let mut marker = 0.0;
+            for (index, &c) in glyph_chars.iter().enumerate() {
+                let char_marker = char_callback(c, char_offset + index, x + char_advance * index as f32, char_advance);
+                if index == 0 {
+                    marker = char_marker;
+                }
+            }
+
             ret.push(TextIns {
                 font_t1: vec2(tc.tx1, tc.ty1),
                 font_t2: vec2(tc.tx2, tc.ty2),
@@ -337,15 +438,14 @@ impl TextIns {
                 rect_size: vec2(w * font_scale / dpi_factor, h * font_scale / dpi_factor),
                 char_depth: draw_depth + 0.00001 * min_pos_x,
                 base: vec2(x, pos.y),
+                advance: if glyph_chunk.is_rtl { -advance } else { advance },
                 font_size: text_style.font_size,
                 char_offset: char_offset as f32,
-
-                // give the callback a chance to do things
-                marker: char_callback(*wc, char_offset, x, advance),
+                marker,
             });
 
-            x += advance;
-            char_offset += 1;
+            x = if is_cluster_start { x + advance } else { cluster_x + cluster_advance };
+            char_offset += char_count;
         }
 
         ret
@@ -431,6 +531,10 @@ impl TextIns {
     /// that matter). Not a high priority but might good to be aware of.
     ///
     /// [`TextInsProps::position_anchoring`] is ignored by this function.
+    ///
+    /// Text is laid out per grapheme cluster (so wrapping never splits characters like "é" written with a
+    /// combining accent), and reordered using the Unicode Bidirectional Algorithm, see [`TextDirection`]. The
+    /// [`TextIns::char_offset`] of each glyph is the index of its character in `text`, counted in chars.
     pub fn draw_walk(cx: &mut Cx, text: &str, props: &TextInsProps) -> Area {
         let mut width = 0.0;
         let mut elipct = 0;
@@ -439,93 +543,94 @@ impl TextIns {
         let font_size = text_style.font_size;
         let line_spacing = text_style.line_spacing;
         let height_factor = text_style.height_factor;
-        let mut iter = text.chars().peekable();
-
-        let font_id = text_style.font.font_id;
-        let font_size_logical = text_style.font_size * 96.0
-            / (72.0 * cx.fonts_data.read().unwrap().fonts[font_id].font_loaded.as_ref().unwrap().units_per_em);
+        let mut iter = text.grapheme_indices(true).peekable();
+        let mut char_offset = 0;
 
-        let mut buf = Vec::with_capacity(text.len());
-        let mut glyphs: Vec<TextIns> = Vec::with_capacity(text.len());
+        let mut buf: Vec<WalkChar> = Vec::with_capacity(text.len());
+        let mut chunks: Vec<WalkChunk> = Vec::new();
 
         cx.begin_row(Width::Compute, Height::Compute);
         cx.begin_padding_box(props.padding);
         cx.begin_wrapping_box();
 
-        while let Some(c) = iter.next() {
+        while let Some((byte_index, cluster)) = iter.next() {
             let last = iter.peek().is_none();
+            let cluster_char_offset = char_offset;
+            char_offset += cluster.chars().count();
 
+            let c = cluster.chars().next().unwrap();
             let mut emit = last;
             let mut newline = false;
-            let slot = if c < '\u{10000}' {
-                cx.fonts_data.read().unwrap().fonts[font_id].font_loaded.as_ref().unwrap().char_code_to_glyph_index_map
-                    [c as usize]
-            } else {
-                0
-            };
-            if c == '\n' {
+            let (font_id, slot) = cx.fonts_data.read().unwrap().get_glyph(text_style, c);
+            if cluster.ends_with('\n') {
                 emit = true;
                 newline = true;
             }
             if slot != 0 {
-                let read_fonts = &cx.fonts_data.read().unwrap().fonts;
-                let glyph = &read_fonts[font_id].font_loaded.as_ref().unwrap().glyphs[slot];
+                let read_fonts_data = cx.fonts_data.read().unwrap();
+                let glyph = &read_fonts_data.fonts[font_id].font_loaded.as_ref().unwrap().glyphs[slot];
+                let font_size_logical = read_fonts_data.get_font_size_logical(font_id, font_size);
                 width += glyph.horizontal_metrics.advance_width * font_size_logical * props.font_scale;
+                // Skip the other characters of the cluster that we don't have a glyph for, like zero width joiners.
+                let cluster_chars: Vec<WalkChar> = cluster
+                    .char_indices()
+                    .enumerate()
+                    .filter(|&(index, (_, c))| index == 0 || read_fonts_data.get_glyph(text_style, c).1 != 0)
+                    .map(|(index, (cluster_byte_index, c))| WalkChar {
+                        c,
+                        char_offset: cluster_char_offset + index,
+                        byte_index: byte_index + cluster_byte_index,
+                        is_cluster_start: index == 0,
+                    })
+                    .collect();
                 match props.wrapping {
                     Wrapping::Char => {
-                        buf.push(c);
+                        buf.extend(cluster_chars);
                         emit = true
                     }
                     Wrapping::Word => {
-                        buf.push(c);
+                        buf.extend(cluster_chars);
                         if c == ' ' || c == '\t' || c == ',' || c == '\n' {
                             emit = true;
                         }
                     }
                     Wrapping::Line => {
-                        buf.push(c);
+                        buf.extend(cluster_chars);
                         if c == 10 as char || c == 13 as char {
                             emit = true;
                         }
                         newline = true;
                     }
                     Wrapping::None => {
-                        buf.push(c);
+                        buf.extend(cluster_chars);
                     }
                     Wrapping::Ellipsis(ellipsis_width) => {
                         if width > ellipsis_width {
                             // output ...
                             if elipct < 3 {
-                                buf.push('.');
+                                buf.push(WalkChar {
+                                    c: '.',
+                                    char_offset: cluster_char_offset,
+                                    byte_index,
+                                    is_cluster_start: true,
+                                });
                                 elipct += 1;
                             }
                         } else {
-                            buf.push(c)
+                            buf.extend(cluster_chars)
                         }
                     }
                 }
             }
             if emit {
+                // Kerning, ligatures, and grapheme clusters depend on the characters around each character, so
+                // measure them together.
+                width = Self::walk_chars_width(&cx.fonts_data.read().unwrap(), props, &buf);
                 let height = font_size * height_factor * props.font_scale;
                 let rect = cx.add_box(LayoutSize { width: Width::Fix(width), height: Height::Fix(height) });
-
-                if !rect.pos.x.is_nan() && !rect.pos.y.is_nan() {
-                    glyphs.extend(Self::generate_2d_glyphs(
-                        &props.text_style,
-                        &cx.fonts_data,
-                        cx.current_dpi_factor,
-                        props.font_scale,
-                        props.draw_depth,
-                        props.color,
-                        rect.pos,
-                        0,
-                        &buf,
-                        |_, _, _, _| 0.0,
-                    ));
-                }
+                chunks.push(WalkChunk { rect, chars: std::mem::take(&mut buf) });
 
                 width = 0.0;
-                buf.truncate(0);
                 if newline {
                     cx.draw_new_line_min_height(font_size * line_spacing * props.font_scale);
                 }
@@ -536,6 +641,7 @@ impl TextIns {
         cx.end_padding_box();
         cx.end_row();
 
+        let glyphs = Self::generate_walk_glyphs(cx, text, props, &chunks);
         Self::draw_glyphs(
             cx,
             &glyphs,
@@ -547,7 +653,132 @@ impl TextIns {
         )
     }
 
+    /// Width of some characters in [`TextIns::draw_walk`], the same as how [`TextIns::generate_glyphs`] would
+    /// lay them out.
+    fn walk_chars_width(fonts_data: &CxFontsData, props: &TextInsProps, chars: &[WalkChar]) -> f32 {
+        let mut char_index = 0;
+        fonts_data
+            .shape_glyphs(&props.text_style, &chars.iter().map(|walk_char| walk_char.c).collect::<Vec<char>>())
+            .iter()
+            .map(|glyph| {
+                let is_cluster_start = chars[char_index].is_cluster_start;
+                char_index += glyph.char_count;
+                if is_cluster_start {
+                    glyph.advance_width
+                        * fonts_data.get_font_size_logical(glyph.font_id, props.text_style.font_size)
+                        * props.font_scale
+                } else {
+                    0.0
+                }
+            })
+            .sum()
+    }
+
+    /// Generates the glyphs for the chunks of [`TextIns::draw_walk`]. Lines with right-to-left text get reordered
+    /// into runs of the same direction, which get drawn from left to right, across the boxes of the chunks of that
+    /// line.
+    fn generate_walk_glyphs(cx: &Cx, text: &str, props: &TextInsProps, chunks: &[WalkChunk]) -> Vec<TextIns> {
+        let drawn_chunks =
+            chunks.iter().filter(|chunk| !chunk.chars.is_empty() && !chunk.rect.pos.x.is_nan() && !chunk.rect.pos.y.is_nan());
+        if props.direction != TextDirection::RightToLeft && !text.chars().any(is_rtl_char) {
+            // Without right-to-left characters there is nothing to reorder, so skip running the bidi algorithm.
+            return drawn_chunks
+                .flat_map(|chunk| Self::generate_walk_chunk_glyphs(cx, props, chunk.rect.pos, &chunk.chars, false))
+                .collect();
+        }
+
+        let default_para_level = match props.direction {
+            TextDirection::Auto => None,
+            TextDirection::LeftToRight => Some(Level::ltr()),
+            TextDirection::RightToLeft => Some(Level::rtl()),
+        };
+        let bidi_info = BidiInfo::new(text, default_para_level);
+        let has_rtl = bidi_info.has_rtl() || bidi_info.paragraphs.iter().any(|para| para.level.is_rtl());
+        // Lines of right-to-left paragraphs are aligned to the end of the longest line.
+        let right = chunks
+            .iter()
+            .filter(|chunk| !chunk.rect.pos.x.is_nan())
+            .map(|chunk| chunk.rect.pos.x + chunk.rect.size.x)
+            .fold(f32::NAN, f32::max);
+
+        let mut glyphs = Vec::with_capacity(text.len());
+        let chunks: Vec<&WalkChunk> = drawn_chunks.collect();
+        for line in chunks.chunk_by(|chunk, next_chunk| chunk.rect.pos.y == next_chunk.rect.pos.y) {
+            let line_chars: Vec<WalkChar> = line.iter().flat_map(|chunk| chunk.chars.iter().copied()).collect();
+            let first_byte_index = line_chars[0].byte_index;
+            let last_byte_index = line_chars[line_chars.len() - 1].byte_index;
+            let line_range = first_byte_index..(last_byte_index + text[last_byte_index..].chars().next().unwrap().len_utf8());
+            let para = bidi_info.paragraphs.iter().find(|para| para.range.contains(&first_byte_index)).unwrap();
+            let levels = match has_rtl.then(|| bidi_info.reordered_levels(para, line_range)) {
+                Some(levels)
+                    if para.level.is_rtl() || line_chars.iter().any(|walk_char| levels[walk_char.byte_index].is_rtl()) =>
+                {
+                    levels
+                }
+                _ => {
+                    // Nothing to reorder, so draw every chunk in its own box.
+                    for chunk in line {
+                        glyphs.extend(Self::generate_walk_chunk_glyphs(cx, props, chunk.rect.pos, &chunk.chars, false));
+                    }
+                    continue;
+                }
+            };
+
+            // Reorder grapheme clusters, so that their characters stay together.
+            let clusters: Vec<&[WalkChar]> = line_chars.chunk_by(|_, next_walk_char| !next_walk_char.is_cluster_start).collect();
+            let cluster_levels: Vec<Level> = clusters.iter().map(|cluster| levels[cluster[0].byte_index]).collect();
+            let visual_clusters: Vec<usize> = BidiInfo::reorder_visual(&cluster_levels);
+
+            let line_width: f32 = line.iter().map(|chunk| chunk.rect.size.x).sum();
+            let mut pos = line[0].rect.pos;
+            if para.level.is_rtl() {
+                pos.x = pos.x.max(right - line_width);
+            }
+            for run in visual_clusters.chunk_by(|&index, &next_index| cluster_levels[index] == cluster_levels[next_index]) {
+                let is_rtl = cluster_levels[run[0]].is_rtl();
+                let run_chars: Vec<WalkChar> = run
+                    .iter()
+                    .flat_map(|&index| clusters[index].iter())
+                    .map(|&walk_char| if is_rtl { WalkChar { c: mirror_char(walk_char.c), ..walk_char } } else { walk_char })
+                    .collect();
+                let run_glyphs = Self::generate_walk_chunk_glyphs(cx, props, pos, &run_chars, is_rtl);
+                pos.x += run_glyphs.iter().map(|glyph| glyph.advance.abs()).sum::<f32>();
+                glyphs.extend(run_glyphs);
+            }
+        }
+        glyphs
+    }
+
+    /// Generates glyphs for characters of [`TextIns::draw_walk`] that are in visual order, starting at `pos`.
+    fn generate_walk_chunk_glyphs(cx: &Cx, props: &TextInsProps, pos: Vec2, chars: &[WalkChar], is_rtl: bool) -> Vec<TextIns> {
+        let mut glyphs = Self::generate_glyphs(
+            &props.text_style,
+            &cx.fonts_data,
+            cx.current_dpi_factor,
+            props.font_scale,
+            props.draw_depth,
+            props.color,
+            pos,
+            0,
+            GlyphChunk {
+                chars: &chars.iter().map(|walk_char| walk_char.c).collect::<Vec<char>>(),
+                cluster_starts: Some(chars.iter().map(|walk_char| walk_char.is_cluster_start).collect()),
+                is_rtl,
+            },
+            |_, _, _, _| 0.0,
+        );
+        for glyph in &mut glyphs {
+            glyph.char_offset = chars[glyph.char_offset as usize].char_offset as f32;
+        }
+        glyphs
+    }
+
     /// Looks up text with the behavior of a text selection mouse cursor.
+    ///
+    /// Returns the offset of the caret position closest to `pos` on the line at `pos`, where a caret position is
+    /// before the characters of a glyph: to the left of glyphs in left-to-right text, and to the right of glyphs
+    /// in right-to-left text (see [`TextIns::advance`]). Glyphs that don't move the caret, like combining marks,
+    /// are skipped, so this never returns an offset within a grapheme cluster.
     pub fn closest_offset(cx: &Cx, area: &Area, pos: Vec2, line_spacing: f32) -> Option<usize> {
         if let Area::InstanceRange(instance) = area {
             if instance.instance_count == 0 {
@@ -559,40 +790,120 @@ impl TextIns {
         let spos = Vec2 { x: pos.x + scroll_pos.x, y: pos.y + scroll_pos.y };
 
         let glyphs = area.get_slice::<TextIns>(cx);
-        let mut i = 0;
-        let len = glyphs.len();
-        while i < len {
-            let glyph = &glyphs[i];
-            if glyph.base.y + glyph.font_size * line_spacing > spos.y {
-                // Find a matching character within this line.
-                while i < len {
-                    let glyph = &glyphs[i];
-                    let width = glyph.rect_size.x;
-                    if glyph.base.x > spos.x + width * 0.5 || glyph.base.y > spos.y {
-                        let prev_glyph = &glyphs[if i == 0 { 0 } else { i - 1 }];
-                        let prev_width = prev_glyph.rect_size.x;
-                        if i < len - 1 && prev_glyph.base.x > spos.x + prev_width {
-                            // fix newline jump-back
-                            return Some(glyph.char_offset as usize);
-                        }
-                        return Some(prev_glyph.char_offset as usize);
-                    }
-                    i += 1;
-                }
+        if let Some(line_start) = glyphs.iter().position(|glyph| glyph.base.y + glyph.font_size * line_spacing > spos.y) {
+            // Find the closest caret position within this line.
+            let line_y = glyphs[line_start].base.y;
+            let closest_glyph = glyphs[line_start..]
+                .iter()
+                .take_while(|glyph| glyph.base.y == line_y)
+                .filter(|glyph| glyph.advance != 0.0)
+                .min_by(|glyph, other_glyph| (caret_x(glyph) - spos.x).abs().total_cmp(&(caret_x(other_glyph) - spos.x).abs()))
+                .unwrap_or(&glyphs[line_start]);
+            return Some(closest_glyph.char_offset as usize);
+        }
+        // Below the last line, so use the end of the text.
+        glyphs.iter().map(|glyph| glyph.char_offset as usize).max()
+    }
+
+    /// The position of the caret before the character at `char_offset`, in the same coordinates as the `pos` of
+    /// [`TextIns::closest_offset`], at the top of its line. For offsets after the last glyph, the caret is after
+    /// that glyph. Takes right-to-left text into account; see [`TextIns::closest_offset`].
+    pub fn caret_position(cx: &Cx, area: &Area, char_offset: usize) -> Option<Vec2> {
+        if let Area::InstanceRange(instance) = area {
+            if instance.instance_count == 0 {
+                return None;
             }
-            i += 1;
         }
-        Some(glyphs[len - 1].char_offset as usize)
+
+        let scroll_pos = area.get_scroll_pos(cx);
+        let glyphs = area.get_slice::<TextIns>(cx);
+        let caret_glyphs = glyphs.iter().filter(|glyph| glyph.advance != 0.0);
+        let pos = if let Some(glyph) = caret_glyphs
+            .clone()
+            .filter(|glyph| glyph.char_offset as usize >= char_offset)
+            .min_by(|glyph, other_glyph| glyph.char_offset.total_cmp(&other_glyph.char_offset))
+        {
+            vec2(caret_x(glyph), glyph.base.y)
+        } else {
+            let glyph = caret_glyphs.max_by(|glyph, other_glyph| glyph.char_offset.total_cmp(&other_glyph.char_offset))?;
+            vec2(caret_x(glyph) + glyph.advance, glyph.base.y)
+        };
+        Some(pos - scroll_pos)
     }
 
     pub fn get_monospace_base(cx: &Cx, text_style: &TextStyle) -> Vec2 {
         let font_id = text_style.font.font_id;
         let read_fonts = &cx.fonts_data.read().unwrap().fonts;
         let font = read_fonts[font_id].font_loaded.as_ref().unwrap();
-        let slot = font.char_code_to_glyph_index_map[33];
+        let slot = font.char_to_glyph_index('!');
         let glyph = &font.glyphs[slot];
 
         //let font_size = if let Some(font_size) = font_size{font_size}else{self.font_size};
         Vec2 { x: glyph.horizontal_metrics.advance_width * (96.0 / (72.0 * font.units_per_em)), y: text_style.line_spacing }
     }
 }
+
+/// A character that [`TextIns::draw_walk`] has laid out, but not generated glyphs for yet.
+#[derive(Clone, Copy, Debug)]
+struct WalkChar {
+    c: char,
+    /// Index of the character in the text, in chars.
+    char_offset: usize,
+    /// Index of the character in the text, in bytes, to look up its bidi level.
+    byte_index: usize,
+    is_cluster_start: bool,
+}
+
+/// Characters that [`TextIns::draw_walk`] has added a box for.
+struct WalkChunk {
+    rect: Rect,
+    chars: Vec<WalkChar>,
+}
+
+/// Characters for [`TextIns::generate_glyphs`], with how to lay them out.
+struct GlyphChunk<'a> {
+    chars: &'a [char],
+    /// For every character, whether it starts a grapheme cluster. If `None`, every character is laid out on its
+    /// own, as in [`TextIns::generate_2d_glyphs`].
+    cluster_starts: Option<Vec<bool>>,
+    /// Whether the characters are a right-to-left run, in which case they are in visual order, but the characters
+    /// of each grapheme cluster are still in logical order.
+    is_rtl: bool,
+}
+
+/// The x position of the caret before the characters of a glyph, see [`TextIns::advance`].
+fn caret_x(glyph: &TextIns) -> f32 {
+    glyph.base.x - glyph.advance.min(0.0)
+}
+
+/// Whether a character can start right-to-left text: right-to-left letters, Arabic numbers, and explicit
+/// right-to-left embeddings, overrides and isolates. No ASCII characters can.
+fn is_rtl_char(c: char) -> bool {
+    !c.is_ascii()
+        && matches!(
+            bidi_class(c),
+            BidiClass::R | BidiClass::AL | BidiClass::AN | BidiClass::RLE | BidiClass::RLO | BidiClass::RLI
+        )
+}
+
+/// Mirrors characters like brackets in right-to-left text, as in rule L4 of the Unicode Bidirectional Algorithm,
+/// for the most common characters with the Bidi_Mirrored property.
+fn mirror_char(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '<' => '>',
+        '>' => '<',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        '≤' => '≥',
+        '≥' => '≤',
+        _ => c,
+    }
+}
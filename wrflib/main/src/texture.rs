@@ -6,6 +6,10 @@
 
 //! Managing GPU textures.
 
+use std::borrow::Cow;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
 use crate::*;
 
 /// A persistent reference to a GPU texture.
@@ -72,9 +76,96 @@ impl Texture {
         }
     }
 
+    /// Creates a texture containing `image`, e.g. one returned by [`decode_image`].
+    pub fn from_decoded_image(cx: &mut Cx, image: DecodedImage) -> Self {
+        cx.textures.push(CxTexture {
+            desc: TextureDesc { width: Some(image.width), height: Some(image.height), ..TextureDesc::default() },
+            image_u32: image.pixels,
+            update_image: true,
+            ..CxTexture::default()
+        });
+        Self { handle: Some(TextureHandle { texture_id: (cx.textures.len() - 1) as u32 }) }
+    }
+
+    /// Creates a cube map texture, for `textureCube` declarations in shaders, from the faces in the order
+    /// +X, -X, +Y, -Y, +Z, -Z. All faces need to be square and of the same size.
+    ///
+    /// In shaders, `sample_cube(texture, direction)` returns the color in `direction` as seen from the center
+    /// of the cube, using the OpenGL conventions for which face and where on it that is.
+    pub fn from_cube_faces(cx: &mut Cx, faces: [DecodedImage; 6]) -> Result<Self, String> {
+        let size = faces[0].width;
+        if faces.iter().any(|face| face.width != size || face.height != size) {
+            return Err("cube map faces must be square and all have the same size".to_string());
+        }
+        cx.textures.push(CxTexture {
+            desc: TextureDesc { kind: TextureKind::Cube, width: Some(size), height: Some(size), ..TextureDesc::default() },
+            image_u32: faces.iter().flat_map(|face| face.pixels.iter().copied()).collect(),
+            update_image: true,
+            ..CxTexture::default()
+        });
+        Ok(Self { handle: Some(TextureHandle { texture_id: (cx.textures.len() - 1) as u32 }) })
+    }
+
+    /// Creates a 3D texture, for `texture3D` declarations in shaders. `voxels` are in the same RGBA format as
+    /// [`TextureHandle::get_image_mut`], stored slice by slice, and within a slice row by row.
+    ///
+    /// In shaders, `sample3d(texture, pos)` samples at normalized coordinates, where `(0, 0, 0)` is the first
+    /// voxel. Coordinates outside of the texture are clamped to the edges.
+    ///
+    /// OpenGL and WebGL don't support 3D textures, so there the slices get stacked into a 2D texture of
+    /// `width` by `height * depth`, which has to fit within the maximum texture size of the GPU.
+    pub fn from_voxels(cx: &mut Cx, width: usize, height: usize, depth: usize, voxels: Vec<u32>) -> Self {
+        assert_eq!(voxels.len(), width * height * depth, "expected width * height * depth voxels");
+        cx.textures.push(CxTexture {
+            desc: TextureDesc {
+                kind: TextureKind::D3 { depth },
+                width: Some(width),
+                height: Some(height),
+                ..TextureDesc::default()
+            },
+            image_u32: voxels,
+            update_image: true,
+            ..CxTexture::default()
+        });
+        Self { handle: Some(TextureHandle { texture_id: (cx.textures.len() - 1) as u32 }) }
+    }
+
+    /// Creates an empty 2D texture that stores its pixels in `format`, which can be filled using
+    /// [`TextureHandle::upload_u8`], [`TextureHandle::upload_u32`], or [`TextureHandle::upload_f32`],
+    /// depending on the format.
+    ///
+    /// For RGBA images you can also use [`Texture::get_with_dimensions`] or [`Texture::from_decoded_image`].
+    pub fn new_2d(cx: &mut Cx, format: TextureFormat, options: TextureOptions) -> Self {
+        assert!(format != TextureFormat::Depth32Stencil8, "use Texture::get_depth for depth textures");
+        cx.textures.push(CxTexture {
+            desc: TextureDesc { format, filter: options.filter, wrap: options.wrap, ..TextureDesc::default() },
+            ..CxTexture::default()
+        });
+        Self { handle: Some(TextureHandle { texture_id: (cx.textures.len() - 1) as u32 }) }
+    }
+
+    /// Decodes a PNG or JPEG image into a new texture. See [`decode_image`].
+    ///
+    /// Decoding big images can take a while, so consider using [`TextureLoader`] instead.
+    pub fn from_image_bytes(cx: &mut Cx, bytes: &[u8]) -> Result<Self, String> {
+        Ok(Self::from_decoded_image(cx, decode_image(bytes)?))
+    }
+
+    /// Reads a PNG or JPEG image from `file` and decodes it into a new texture.
+    ///
+    /// Reading and decoding can take a while, so consider using [`TextureLoader`] instead.
+    pub fn from_image_file(cx: &mut Cx, file: &mut UniversalFile) -> Result<Self, String> {
+        Ok(Self::from_decoded_image(cx, read_and_decode_image(file)?))
+    }
+
     pub fn unwrap_texture_handle(&self) -> TextureHandle {
         self.handle.unwrap()
     }
+
+    /// The [`TextureHandle`], if the texture has been created already.
+    pub fn get_handle(&self) -> Option<TextureHandle> {
+        self.handle
+    }
 }
 
 /// A pointer to a [`CxTexture`] (indexed in [`Cx::textures`] using [`TextureHandle::texture_id`]),
@@ -89,19 +180,227 @@ impl TextureHandle {
         cx_texture.update_image = true;
         &mut cx_texture.image_u32
     }
+
+    /// Replaces the pixels of a [`TextureFormat::R8`] or [`TextureFormat::RG8`] texture, with
+    /// [`TextureFormat::channels`] bytes per pixel, row by row.
+    pub fn upload_u8(&self, cx: &mut Cx, width: usize, height: usize, data: &[u8]) {
+        let cx_texture = self.prepare_upload(cx, width, height, data.len(), &[TextureFormat::R8, TextureFormat::RG8]);
+        cx_texture.image_u8.clear();
+        cx_texture.image_u8.extend_from_slice(data);
+    }
+
+    /// Replaces the pixels of a [`TextureFormat::ImageRGBA`] texture, row by row.
+    pub fn upload_u32(&self, cx: &mut Cx, width: usize, height: usize, data: &[u32]) {
+        let cx_texture = self.prepare_upload(cx, width, height, data.len(), &[TextureFormat::ImageRGBA]);
+        cx_texture.image_u32.clear();
+        cx_texture.image_u32.extend_from_slice(data);
+    }
+
+    /// Replaces the pixels of a [`TextureFormat::R32F`], [`TextureFormat::RGBA16F`], or
+    /// [`TextureFormat::RGBA32F`] texture, with [`TextureFormat::channels`] values per pixel, row by row.
+    pub fn upload_f32(&self, cx: &mut Cx, width: usize, height: usize, data: &[f32]) {
+        let cx_texture = self.prepare_upload(
+            cx,
+            width,
+            height,
+            data.len(),
+            &[TextureFormat::R32F, TextureFormat::RGBA16F, TextureFormat::RGBA32F],
+        );
+        cx_texture.image_f32.clear();
+        cx_texture.image_f32.extend_from_slice(data);
+    }
+
+    fn prepare_upload<'a>(
+        &self,
+        cx: &'a mut Cx,
+        width: usize,
+        height: usize,
+        len: usize,
+        formats: &[TextureFormat],
+    ) -> &'a mut CxTexture {
+        let cx_texture = &mut cx.textures[self.texture_id as usize];
+        let format = cx_texture.desc.format;
+        assert!(formats.contains(&format), "cannot upload this type of data to a {:?} texture", format);
+        assert!(cx_texture.desc.kind == TextureKind::D2, "can only upload to 2D textures");
+        assert_eq!(len, width * height * format.channels(), "expected width * height * {} values", format.channels());
+        cx_texture.desc.width = Some(width);
+        cx_texture.desc.height = Some(height);
+        cx_texture.update_image = true;
+        cx_texture
+    }
+
+    /// Changes how the texture gets sampled in shaders.
+    pub fn set_options(&self, cx: &mut Cx, options: TextureOptions) {
+        let cx_texture = &mut cx.textures[self.texture_id as usize];
+        if cx_texture.desc.filter != options.filter || cx_texture.desc.wrap != options.wrap {
+            cx_texture.desc.filter = options.filter;
+            cx_texture.desc.wrap = options.wrap;
+            cx_texture.update_image = true;
+        }
+    }
 }
 
-// TODO(Paras): Standardize and test all platforms on RGBA.
-// TODO(Paras): Make image_u32 updating work on Linux.
-#[derive(Copy, Clone, PartialEq)]
-pub(crate) enum TextureFormat {
+fn read_and_decode_image(file: &mut UniversalFile) -> Result<DecodedImage, String> {
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes).map_err(|err| err.to_string())?;
+    decode_image(&bytes)
+}
+
+const TEXTURE_LOADED: StatusId = location_hash!();
+
+/// Reads and decodes a PNG or JPEG image on a separate thread (see [`universal_thread`]), and
+/// sends a [`SignalEvent`] when it's done. Pass events to [`TextureLoader::handle`] to get the
+/// resulting [`Texture`].
+///
+/// ```ignore
+/// if let Some(result) = self.texture_loader.handle(cx, event) {
+///     match result {
+///         Ok(texture) => self.texture = Some(texture),
+///         Err(err) => log!("Could not load image: {}", err),
+///     }
+///     cx.request_draw();
+/// }
+/// ```
+pub struct TextureLoader {
+    signal: Signal,
+    result: Arc<Mutex<Option<Result<DecodedImage, String>>>>,
+}
+
+impl TextureLoader {
+    /// Starts loading `file`, e.g. one opened using [`UniversalFile::open_url`].
+    pub fn new(cx: &mut Cx, mut file: UniversalFile) -> Self {
+        let signal = cx.new_signal();
+        let result = Arc::new(Mutex::new(None));
+        let thread_result = Arc::clone(&result);
+        universal_thread::spawn(move || {
+            *thread_result.lock().unwrap() = Some(read_and_decode_image(&mut file));
+            Cx::post_signal(signal, TEXTURE_LOADED);
+        });
+        Self { signal, result }
+    }
+
+    /// Returns the texture when loading is done, or an error if the image couldn't be read or
+    /// decoded. Only returns [`Some`] once.
+    pub fn handle(&mut self, cx: &mut Cx, event: &mut Event) -> Option<Result<Texture, String>> {
+        if let Event::Signal(signal_event) = event {
+            if signal_event.signals.contains_key(&self.signal) {
+                let result = self.result.lock().unwrap().take()?;
+                return Some(result.map(|image| Texture::from_decoded_image(cx, image)));
+            }
+        }
+        None
+    }
+}
+
+/// How the pixels of a texture are stored. In shaders all of them get sampled as `vec4`s; formats
+/// with fewer channels return 0 for the missing color channels and 1 for alpha.
+///
+/// Not all GPUs support linear filtering of 32-bit float textures; use [`TextureFilter::Nearest`]
+/// for those to be safe.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TextureFormat {
+    /// 8-bit RGBA, stored as one `u32` per pixel with red in the lowest byte. See
+    /// [`TextureHandle::get_image_mut`].
     ImageRGBA,
+    /// A single 8-bit channel, normalized to 0..1.
+    R8,
+    /// Two 8-bit channels, normalized to 0..1.
+    RG8,
+    /// A single 32-bit float channel.
+    R32F,
+    /// Four 16-bit float channels. Uploaded as `f32`s, which lose precision when converted.
+    RGBA16F,
+    /// Four 32-bit float channels.
+    RGBA32F,
+    /// Depth and stencil buffer of a [`crate::Pass`], see [`Texture::get_depth`]. Can't be uploaded to.
     Depth32Stencil8,
 }
 
+impl TextureFormat {
+    /// The number of values per pixel that [`TextureHandle::upload_u8`], [`TextureHandle::upload_u32`],
+    /// or [`TextureHandle::upload_f32`] expect.
+    pub fn channels(self) -> usize {
+        match self {
+            TextureFormat::ImageRGBA | TextureFormat::R8 | TextureFormat::R32F => 1,
+            TextureFormat::RG8 => 2,
+            TextureFormat::RGBA16F | TextureFormat::RGBA32F => 4,
+            TextureFormat::Depth32Stencil8 => 1,
+        }
+    }
+}
+
+/// How to sample in between pixels.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TextureFilter {
+    /// Interpolate between the nearest pixels.
+    Linear,
+    /// Use the nearest pixel.
+    Nearest,
+}
+
+/// What to sample outside of the 0..1 range of texture coordinates.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TextureWrap {
+    /// Use the pixels at the edges.
+    ClampToEdge,
+    /// Repeat the texture. WebGL only supports this for textures with power-of-two sizes.
+    Repeat,
+}
+
+/// Sampling options for [`Texture::new_2d`] and [`TextureHandle::set_options`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TextureOptions {
+    pub filter: TextureFilter,
+    pub wrap: TextureWrap,
+}
+
+impl TextureOptions {
+    pub const DEFAULT: Self = Self { filter: TextureFilter::Linear, wrap: TextureWrap::ClampToEdge };
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Which kind of `texture` declaration in a shader a texture can be used for.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum TextureKind {
+    /// `texture2D`.
+    D2,
+    /// `textureCube`. [`CxTexture::image_u32`] contains the 6 faces after each other, see
+    /// [`Texture::from_cube_faces`].
+    Cube,
+    /// `texture3D`. [`CxTexture::image_u32`] contains `depth` slices after each other.
+    D3 { depth: usize },
+}
+
+impl TextureKind {
+    /// The number of 2D images in [`CxTexture::image_u32`].
+    pub(crate) fn layers(self) -> usize {
+        match self {
+            TextureKind::D2 => 1,
+            TextureKind::Cube => 6,
+            TextureKind::D3 { depth } => depth,
+        }
+    }
+
+    /// Whether a texture of this kind can be bound to a texture declaration of type `ty`.
+    pub(crate) fn matches_ty(self, ty: &Ty) -> bool {
+        matches!(
+            (self, ty),
+            (TextureKind::D2, Ty::Texture2D) | (TextureKind::Cube, Ty::TextureCube) | (TextureKind::D3 { .. }, Ty::Texture3D)
+        )
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub(crate) struct TextureDesc {
+    pub(crate) kind: TextureKind,
     pub(crate) format: TextureFormat,
+    pub(crate) filter: TextureFilter,
+    pub(crate) wrap: TextureWrap,
     pub(crate) width: Option<usize>,
     pub(crate) height: Option<usize>,
     pub(crate) multisample: Option<usize>,
@@ -109,7 +408,15 @@ pub(crate) struct TextureDesc {
 
 impl Default for TextureDesc {
     fn default() -> Self {
-        TextureDesc { format: TextureFormat::ImageRGBA, width: None, height: None, multisample: None }
+        TextureDesc {
+            kind: TextureKind::D2,
+            format: TextureFormat::ImageRGBA,
+            filter: TextureOptions::DEFAULT.filter,
+            wrap: TextureOptions::DEFAULT.wrap,
+            width: None,
+            height: None,
+            multisample: None,
+        }
     }
 }
 
@@ -117,9 +424,85 @@ impl Default for TextureDesc {
 #[derive(Default)]
 pub(crate) struct CxTexture {
     pub(crate) desc: TextureDesc,
+    /// Pixels of [`TextureFormat::ImageRGBA`] textures.
     pub(crate) image_u32: Vec<u32>,
+    /// Pixels of [`TextureFormat::R8`] and [`TextureFormat::RG8`] textures.
+    pub(crate) image_u8: Vec<u8>,
+    /// Pixels of [`TextureFormat::R32F`], [`TextureFormat::RGBA16F`], and [`TextureFormat::RGBA32F`] textures.
+    pub(crate) image_f32: Vec<f32>,
     pub(crate) update_image: bool,
-    // Not used on wasm
-    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
     pub(crate) platform: CxPlatformTexture,
 }
+
+impl CxTexture {
+    /// The pixels as GPUs expect them for [`TextureDesc::format`], and the number of bytes per pixel.
+    /// [`TextureFormat::RGBA16F`] gets converted to half floats.
+    #[cfg_attr(any(target_arch = "wasm32", feature = "headless"), allow(dead_code))]
+    pub(crate) fn gpu_image(&self) -> (Cow<'_, [u8]>, usize) {
+        fn as_bytes<T>(values: &[T]) -> &[u8] {
+            unsafe { std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values)) }
+        }
+        match self.desc.format {
+            TextureFormat::ImageRGBA => (Cow::Borrowed(as_bytes(&self.image_u32)), 4),
+            TextureFormat::R8 => (Cow::Borrowed(&self.image_u8), 1),
+            TextureFormat::RG8 => (Cow::Borrowed(&self.image_u8), 2),
+            TextureFormat::R32F => (Cow::Borrowed(as_bytes(&self.image_f32)), 4),
+            TextureFormat::RGBA16F => {
+                (Cow::Owned(self.image_f32.iter().flat_map(|&value| f32_to_f16(value).to_ne_bytes()).collect()), 8)
+            }
+            TextureFormat::RGBA32F => (Cow::Borrowed(as_bytes(&self.image_f32)), 16),
+            TextureFormat::Depth32Stencil8 => panic!("depth textures can't be uploaded"),
+        }
+    }
+}
+
+/// Converts to the bits of an IEEE 754 half-precision float, rounding to nearest.
+#[cfg_attr(any(target_arch = "wasm32", feature = "headless"), allow(dead_code))]
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        // Infinity or NaN.
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // Subnormal: shift the mantissa, including its implicit leading 1, into place.
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let rounded = (mantissa + (1 << (shift - 1))) >> shift;
+        return sign | rounded as u16;
+    }
+    // Rounding can carry into the exponent, which still gives the right result (up to infinity).
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    sign | (half + ((mantissa >> 12) & 1)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f32_to_f16_conversion() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        assert_eq!(f32_to_f16(0.5), 0x3800);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7e00, 0x7e00);
+        // Smallest subnormal.
+        assert_eq!(f32_to_f16(5.960_464_5e-8), 0x0001);
+        assert_eq!(f32_to_f16(1e-10), 0x0000);
+    }
+}
@@ -220,6 +220,7 @@ pub struct WindowGeom {
 #[derive(Clone)]
 pub(crate) enum CxWindowState {
     Create {
+        #[cfg_attr(feature = "headless", allow(dead_code))]
         title: String,
         inner_size: Vec2,
         position: Option<Vec2>,
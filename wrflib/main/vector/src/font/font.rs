@@ -4,7 +4,7 @@
 // found in the LICENSE-APACHE file in the root directory of this source tree.
 // You may not use this file except in compliance with the License.
 
-use crate::font::Glyph;
+use crate::font::{Glyph, LigatureLookup, PairAdjustmentLookup};
 use crate::geometry::Rectangle;
 
 /// A font.
@@ -15,6 +15,79 @@ pub struct VectorFont {
     pub(crate) descender: f32,
     pub(crate) line_gap: f32,
     pub(crate) bounds: Rectangle,
+    /// Glyph indices for characters in the Basic Multilingual Plane (U+0000 to U+FFFF).
     pub char_code_to_glyph_index_map: Vec<usize>,
+    /// Sorted, non-overlapping ranges of glyph indices for characters outside of the Basic Multilingual Plane.
+    pub(crate) char_code_ranges: Vec<CharCodeRange>,
     pub glyphs: Vec<Glyph>,
+    /// Kerning, from the `kern` feature of the `GPOS` table, or else from the `kern` table.
+    pub(crate) pair_adjustment_lookups: Vec<PairAdjustmentLookup>,
+    /// Ligatures, from the `liga` and `rlig` features of the `GSUB` table.
+    pub(crate) ligature_lookups: Vec<LigatureLookup>,
+}
+
+/// A range of characters that map to glyphs, from `cmap` subtable formats 12 and 13.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct CharCodeRange {
+    pub(crate) start: u32,
+    /// Inclusive.
+    pub(crate) end: u32,
+    pub(crate) glyph_index: usize,
+    /// Whether all characters map to `glyph_index`, instead of consecutive glyphs starting at `glyph_index`.
+    pub(crate) is_constant: bool,
+}
+
+impl VectorFont {
+    /// Index into [`VectorFont::glyphs`] for a character, or 0 (the "missing glyph") if the font doesn't have it.
+    pub fn char_to_glyph_index(&self, c: char) -> usize {
+        let char_code = c as u32;
+        let glyph_index = if char_code <= 0xFFFF {
+            self.char_code_to_glyph_index_map.get(char_code as usize).copied().unwrap_or(0)
+        } else {
+            let range_index = self.char_code_ranges.partition_point(|range| range.end < char_code);
+            match self.char_code_ranges.get(range_index) {
+                Some(range) if range.start <= char_code && range.is_constant => range.glyph_index,
+                Some(range) if range.start <= char_code => range.glyph_index + (char_code - range.start) as usize,
+                _ => 0,
+            }
+        };
+        if glyph_index < self.glyphs.len() {
+            glyph_index
+        } else {
+            0
+        }
+    }
+
+    /// Adjustment of the advance width of glyph `left` when it's followed by glyph `right`, in font units. Usually
+    /// negative, to move glyphs closer together, e.g. in "AV".
+    pub fn kerning(&self, left: usize, right: usize) -> f32 {
+        self.pair_adjustment_lookups.iter().filter_map(|lookup| lookup.get(left, right)).sum()
+    }
+
+    /// Replaces sequences of glyphs with ligatures, e.g. "f" and "i" with "fi". Returns the resulting glyph
+    /// indices, each with the number of glyphs of `glyph_indices` that it replaces.
+    pub fn apply_ligatures(&self, glyph_indices: &[usize]) -> Vec<(usize, usize)> {
+        let mut glyphs: Vec<(usize, usize)> = glyph_indices.iter().map(|&glyph_index| (glyph_index, 1)).collect();
+        // Lookups apply one after the other, so ligatures can be made from the results of earlier lookups.
+        for lookup in &self.ligature_lookups {
+            let glyph_indices: Vec<usize> = glyphs.iter().map(|&(glyph_index, _)| glyph_index).collect();
+            let mut substituted = Vec::with_capacity(glyphs.len());
+            let mut index = 0;
+            while index < glyphs.len() {
+                match lookup.get(&glyph_indices[index..]) {
+                    Some((ligature_glyph_index, component_count)) => {
+                        let count = glyphs[index..(index + component_count)].iter().map(|&(_, count)| count).sum();
+                        substituted.push((ligature_glyph_index, count));
+                        index += component_count;
+                    }
+                    None => {
+                        substituted.push(glyphs[index]);
+                        index += 1;
+                    }
+                }
+            }
+            glyphs = substituted;
+        }
+        glyphs
+    }
 }
@@ -13,9 +13,12 @@ mod font;
 mod glyph;
 mod horizontal_metrics;
 mod outline_point;
+mod shaping;
 
+pub(crate) use self::font::CharCodeRange;
 pub use self::font::VectorFont;
 pub use self::glyph::Glyph;
 pub use self::horizontal_metrics::HorizontalMetrics;
 pub use self::outline::Outline;
 pub(crate) use self::outline_point::OutlinePoint;
+pub(crate) use self::shaping::{Ligature, LigatureLookup, PairAdjustmentLookup, PairAdjustmentSubtable};
@@ -0,0 +1,79 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+use std::collections::{HashMap, HashSet};
+
+/// Adjustments of the advance width of glyphs based on the glyph that follows, from a `GPOS` pair adjustment
+/// lookup or a `kern` subtable.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct PairAdjustmentLookup {
+    pub(crate) subtables: Vec<PairAdjustmentSubtable>,
+}
+
+impl PairAdjustmentLookup {
+    /// Returns the adjustment of the first subtable that applies to this pair of glyphs, if any.
+    pub(crate) fn get(&self, left: usize, right: usize) -> Option<f32> {
+        self.subtables.iter().find_map(|subtable| subtable.get(left, right))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum PairAdjustmentSubtable {
+    /// Adjustments for individual pairs of glyphs.
+    Pairs(HashMap<(usize, usize), f32>),
+    /// Adjustments for pairs of classes of glyphs, which apply to all first glyphs in `coverage`.
+    Classes {
+        coverage: HashSet<usize>,
+        first_classes: HashMap<usize, usize>,
+        second_classes: HashMap<usize, usize>,
+        second_class_count: usize,
+        /// Indexed by `first_class * second_class_count + second_class`.
+        adjustments: Vec<f32>,
+    },
+}
+
+impl PairAdjustmentSubtable {
+    fn get(&self, left: usize, right: usize) -> Option<f32> {
+        match self {
+            PairAdjustmentSubtable::Pairs(pairs) => pairs.get(&(left, right)).copied(),
+            PairAdjustmentSubtable::Classes { coverage, first_classes, second_classes, second_class_count, adjustments } => {
+                if !coverage.contains(&left) {
+                    return None;
+                }
+                // Glyphs that aren't in a class definition are in class 0.
+                let first_class = first_classes.get(&left).copied().unwrap_or(0);
+                let second_class = second_classes.get(&right).copied().unwrap_or(0);
+                Some(adjustments.get(first_class * second_class_count + second_class).copied().unwrap_or(0.))
+            }
+        }
+    }
+}
+
+/// Ligatures from a `GSUB` ligature substitution lookup, by their first glyph. Ligatures with the same first
+/// glyph are in order of preference.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct LigatureLookup {
+    pub(crate) ligatures: HashMap<usize, Vec<Ligature>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Ligature {
+    /// The glyphs that follow the first glyph.
+    pub(crate) components: Vec<usize>,
+    pub(crate) glyph_index: usize,
+}
+
+impl LigatureLookup {
+    /// Returns the ligature for the glyphs at the start of `glyphs`, if any, and the number of glyphs it replaces.
+    pub(crate) fn get(&self, glyphs: &[usize]) -> Option<(usize, usize)> {
+        let (first, rest) = glyphs.split_first()?;
+        self.ligatures
+            .get(first)?
+            .iter()
+            .find(|ligature| rest.starts_with(&ligature.components))
+            .map(|ligature| (ligature.glyph_index, ligature.components.len() + 1))
+    }
+}
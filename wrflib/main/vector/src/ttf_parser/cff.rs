@@ -0,0 +1,725 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Glyph outlines from the `CFF ` and `CFF2` tables of OpenType fonts (.otf), which describe glyphs using
+//! Type 2 charstrings with cubic curves.
+//!
+//! Only what's needed for outlines is parsed: hints are skipped, the `FontMatrix` is assumed to map
+//! charstring units to font units (as required by OpenType), variable fonts are drawn using their default
+//! instance, and the deprecated `seac` accents and arithmetic operators are not supported.
+
+use super::{Error, Reader, Result};
+use crate::font::{Outline, OutlinePoint};
+use crate::geometry::Point;
+
+/// Maximum distance in font units between a cubic curve and the quadratic curves that approximate it.
+const CUBIC_TO_QUADRATIC_TOLERANCE: f32 = 0.5;
+/// Maximum number of quadratic curves to approximate a single cubic curve with.
+const MAX_QUADRATICS_PER_CUBIC: usize = 16;
+/// Maximum nesting of subroutine calls; the spec allows 10.
+const MAX_SUBR_DEPTH: usize = 10;
+/// Maximum number of operands on the charstring stack; 48 for CFF, 513 for CFF2.
+const MAX_STACK_LEN: usize = 513;
+
+/// Parses the outlines of all glyphs in a `CFF ` table.
+pub(crate) fn parse_cff_outlines(bytes: &[u8]) -> Result<Vec<Outline>> {
+    let mut reader = Reader::new(bytes);
+    let major_version = reader.read_u8()?;
+    reader.skip(1)?;
+    let header_size = reader.read_u8()? as usize;
+    if major_version != 1 {
+        return Err(Error);
+    }
+    let mut reader = Reader::new(bytes.get(header_size..).ok_or(Error)?);
+    let _names = read_index(&mut reader, false)?;
+    let top_dicts = read_index(&mut reader, false)?;
+    let _strings = read_index(&mut reader, false)?;
+    let global_subrs = read_index(&mut reader, false)?;
+    // OpenType fonts contain exactly one font, so we use the first Top DICT.
+    let top_dict = parse_dict(top_dicts.first().ok_or(Error)?)?;
+    if top_dict.get(CHARSTRING_TYPE).is_some_and(|operands| operands != [2.]) {
+        return Err(Error);
+    }
+    let charstrings = read_index_at(bytes, top_dict.get_offset(CHARSTRINGS)?, false)?;
+
+    let (local_subrs_per_font_dict, font_dict_indices) = if top_dict.get(ROS).is_some() {
+        // CID-keyed fonts have a Font DICT (with a Private DICT) per group of glyphs.
+        let font_dicts = read_index_at(bytes, top_dict.get_offset(FD_ARRAY)?, false)?;
+        let local_subrs_per_font_dict = parse_local_subrs_per_font_dict(bytes, &font_dicts, false)?;
+        let font_dict_indices = parse_fd_select(bytes, top_dict.get_offset(FD_SELECT)?, charstrings.len())?;
+        (local_subrs_per_font_dict, font_dict_indices)
+    } else {
+        let local_subrs = match top_dict.get(PRIVATE) {
+            Some(operands) => parse_local_subrs(bytes, operands, false)?,
+            None => vec![],
+        };
+        (vec![local_subrs], vec![0; charstrings.len()])
+    };
+
+    parse_charstrings(&charstrings, &global_subrs, &local_subrs_per_font_dict, &font_dict_indices, false, &[])
+}
+
+/// Parses the outlines of all glyphs in a `CFF2` table.
+pub(crate) fn parse_cff2_outlines(bytes: &[u8]) -> Result<Vec<Outline>> {
+    let mut reader = Reader::new(bytes);
+    let major_version = reader.read_u8()?;
+    reader.skip(1)?;
+    let header_size = reader.read_u8()? as usize;
+    let top_dict_length = reader.read_u16()? as usize;
+    if major_version != 2 {
+        return Err(Error);
+    }
+    let top_dict_bytes = bytes.get(header_size..).and_then(|bytes| bytes.get(..top_dict_length)).ok_or(Error)?;
+    let top_dict = parse_dict(top_dict_bytes)?;
+    let global_subrs = read_index(&mut Reader::new(&bytes[header_size + top_dict_length..]), true)?;
+    let charstrings = read_index_at(bytes, top_dict.get_offset(CHARSTRINGS)?, true)?;
+
+    let font_dicts = read_index_at(bytes, top_dict.get_offset(FD_ARRAY)?, true)?;
+    let local_subrs_per_font_dict = parse_local_subrs_per_font_dict(bytes, &font_dicts, true)?;
+    // With a single Font DICT the FDSelect is optional.
+    let font_dict_indices = match top_dict.get(FD_SELECT) {
+        Some(_) => parse_fd_select(bytes, top_dict.get_offset(FD_SELECT)?, charstrings.len())?,
+        None => vec![0; charstrings.len()],
+    };
+    let region_counts = match top_dict.get(VSTORE) {
+        Some(_) => parse_region_counts(bytes, top_dict.get_offset(VSTORE)?)?,
+        None => vec![],
+    };
+
+    parse_charstrings(&charstrings, &global_subrs, &local_subrs_per_font_dict, &font_dict_indices, true, &region_counts)
+}
+
+fn parse_charstrings(
+    charstrings: &[&[u8]],
+    global_subrs: &[&[u8]],
+    local_subrs_per_font_dict: &[Vec<&[u8]>],
+    font_dict_indices: &[usize],
+    is_cff2: bool,
+    region_counts: &[usize],
+) -> Result<Vec<Outline>> {
+    charstrings
+        .iter()
+        .zip(font_dict_indices)
+        .map(|(charstring, &font_dict_index)| {
+            let context = CharstringContext {
+                global_subrs,
+                local_subrs: local_subrs_per_font_dict.get(font_dict_index).ok_or(Error)?,
+                is_cff2,
+                region_counts,
+            };
+            let mut interpreter = CharstringInterpreter::new(context);
+            interpreter.run(charstring, 0)?;
+            Ok(interpreter.outline_builder.finish())
+        })
+        .collect()
+}
+
+// Operators in Top DICTs and Private DICTs. Two-byte operators are `1200 + second byte`.
+const CHARSTRINGS: u16 = 17;
+const PRIVATE: u16 = 18;
+const SUBRS: u16 = 19;
+const VSTORE: u16 = 24;
+const CHARSTRING_TYPE: u16 = 1206;
+const ROS: u16 = 1230;
+const FD_ARRAY: u16 = 1236;
+const FD_SELECT: u16 = 1237;
+
+/// The operators and operands in a DICT.
+struct Dict(Vec<(u16, Vec<f64>)>);
+
+impl Dict {
+    fn get(&self, operator: u16) -> Option<&[f64]> {
+        self.0.iter().find(|(entry_operator, _)| *entry_operator == operator).map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Gets an operator with a single operand that is an offset.
+    fn get_offset(&self, operator: u16) -> Result<usize> {
+        match self.get(operator) {
+            Some(&[offset]) if offset >= 0. => Ok(offset as usize),
+            _ => Err(Error),
+        }
+    }
+}
+
+fn parse_dict(bytes: &[u8]) -> Result<Dict> {
+    let mut reader = Reader::new(bytes);
+    let mut entries = vec![];
+    let mut operands = vec![];
+    while !reader.bytes.is_empty() {
+        let b0 = reader.read_u8()?;
+        match b0 {
+            0..=21 => {
+                let operator = if b0 == 12 { 1200 + reader.read_u8()? as u16 } else { b0 as u16 };
+                entries.push((operator, std::mem::take(&mut operands)));
+            }
+            // `blend` in a CFF2 Private DICT leaves the default values on the stack for the next operator, so
+            // that operator just gets some extra operands (none of which we use).
+            23 => {}
+            22 | 24 => {
+                entries.push((b0 as u16, std::mem::take(&mut operands)));
+            }
+            28 => operands.push(reader.read_i16()? as f64),
+            29 => operands.push(reader.read_i32()? as f64),
+            30 => operands.push(read_real(&mut reader)?),
+            32..=246 => operands.push(b0 as f64 - 139.),
+            247..=250 => operands.push((b0 as f64 - 247.) * 256. + reader.read_u8()? as f64 + 108.),
+            251..=254 => operands.push(-(b0 as f64 - 251.) * 256. - reader.read_u8()? as f64 - 108.),
+            _ => return Err(Error),
+        }
+    }
+    Ok(Dict(entries))
+}
+
+/// Reads a real number operand, which is encoded as a string of nibbles.
+fn read_real(reader: &mut Reader) -> Result<f64> {
+    let mut string = String::new();
+    loop {
+        let byte = reader.read_u8()?;
+        for nibble in [byte >> 4, byte & 0xF] {
+            match nibble {
+                0..=9 => string.push((b'0' + nibble) as char),
+                0xA => string.push('.'),
+                0xB => string.push('E'),
+                0xC => string.push_str("E-"),
+                0xE => string.push('-'),
+                0xF => return string.parse().map_err(|_| Error),
+                _ => return Err(Error),
+            }
+        }
+    }
+}
+
+/// Reads an INDEX, which is an array of variable-sized objects. In CFF2 the count has 32 instead of 16 bits.
+fn read_index<'a>(reader: &mut Reader<'a>, is_cff2: bool) -> Result<Vec<&'a [u8]>> {
+    let count = if is_cff2 { reader.read_u32()? as usize } else { reader.read_u16()? as usize };
+    if count == 0 {
+        return Ok(vec![]);
+    }
+    let offset_size = reader.read_u8()?;
+    if !(1..=4).contains(&offset_size) {
+        return Err(Error);
+    }
+    let offsets = (0..=count).map(|_| reader.read_uint(offset_size as usize)).collect::<Result<Vec<_>>>()?;
+    // Offsets are relative to the byte before the object data.
+    let data = reader.bytes;
+    let objects = offsets
+        .windows(2)
+        .map(|window| match window {
+            &[start, end] if 1 <= start && start <= end => data.get((start - 1)..(end - 1)).ok_or(Error),
+            _ => Err(Error),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    reader.skip(offsets[count] - 1)?;
+    Ok(objects)
+}
+
+fn read_index_at(bytes: &[u8], offset: usize, is_cff2: bool) -> Result<Vec<&[u8]>> {
+    read_index(&mut Reader::new(bytes.get(offset..).ok_or(Error)?), is_cff2)
+}
+
+/// Parses the local subroutines of a Private DICT, given the operands of the `Private` operator (its size and
+/// offset).
+fn parse_local_subrs<'a>(bytes: &'a [u8], private_operands: &[f64], is_cff2: bool) -> Result<Vec<&'a [u8]>> {
+    let (size, offset) = match *private_operands {
+        [size, offset] if size >= 0. && offset >= 0. => (size as usize, offset as usize),
+        _ => return Err(Error),
+    };
+    let private_dict = parse_dict(bytes.get(offset..).and_then(|bytes| bytes.get(..size)).ok_or(Error)?)?;
+    match private_dict.get(SUBRS) {
+        // The offset of the subroutines is relative to the Private DICT.
+        Some(_) => read_index_at(bytes, offset + private_dict.get_offset(SUBRS)?, is_cff2),
+        None => Ok(vec![]),
+    }
+}
+
+fn parse_local_subrs_per_font_dict<'a>(bytes: &'a [u8], font_dicts: &[&[u8]], is_cff2: bool) -> Result<Vec<Vec<&'a [u8]>>> {
+    font_dicts
+        .iter()
+        .map(|font_dict| match parse_dict(font_dict)?.get(PRIVATE) {
+            Some(operands) => parse_local_subrs(bytes, operands, is_cff2),
+            None => Ok(vec![]),
+        })
+        .collect()
+}
+
+/// Parses an FDSelect, which maps glyph indices to Font DICT indices.
+fn parse_fd_select(bytes: &[u8], offset: usize, glyph_count: usize) -> Result<Vec<usize>> {
+    let mut reader = Reader::new(bytes.get(offset..).ok_or(Error)?);
+    let format = reader.read_u8()?;
+    if format == 0 {
+        return (0..glyph_count).map(|_| Ok(reader.read_u8()? as usize)).collect();
+    }
+    // Formats 3 and 4 consist of ranges of glyphs, with 16 and 32 bit glyph indices respectively.
+    let index_size = match format {
+        3 => 2,
+        4 => 4,
+        _ => return Err(Error),
+    };
+    let font_dict_index_size = if format == 3 { 1 } else { 2 };
+    let range_count = reader.read_uint(index_size)?;
+    let mut font_dict_indices = Vec::with_capacity(glyph_count);
+    let mut first = reader.read_uint(index_size)?;
+    for _ in 0..range_count {
+        let font_dict_index = reader.read_uint(font_dict_index_size)?;
+        let next = reader.read_uint(index_size)?;
+        if first != font_dict_indices.len() || next < first {
+            return Err(Error);
+        }
+        font_dict_indices.resize(next.min(glyph_count), font_dict_index);
+        first = next;
+    }
+    if font_dict_indices.len() != glyph_count {
+        return Err(Error);
+    }
+    Ok(font_dict_indices)
+}
+
+/// Parses the number of regions of each item variation data in a CFF2 variation store, which determines the
+/// number of operands of the `blend` operator.
+fn parse_region_counts(bytes: &[u8], offset: usize) -> Result<Vec<usize>> {
+    // The variation store starts with its length.
+    let store_bytes = bytes.get((offset + 2)..).ok_or(Error)?;
+    let mut reader = Reader::new(store_bytes);
+    reader.skip(6)?;
+    let data_count = reader.read_u16()? as usize;
+    (0..data_count)
+        .map(|_| {
+            let mut data_reader = Reader::new(store_bytes.get(reader.read_u32()? as usize..).ok_or(Error)?);
+            data_reader.skip(4)?;
+            Ok(data_reader.read_u16()? as usize)
+        })
+        .collect()
+}
+
+/// Returns the number to add to subroutine numbers in charstrings to get the subroutine index.
+fn subr_bias(subr_count: usize) -> i32 {
+    if subr_count < 1240 {
+        107
+    } else if subr_count < 33900 {
+        1131
+    } else {
+        32768
+    }
+}
+
+#[derive(Clone, Copy)]
+struct CharstringContext<'a> {
+    global_subrs: &'a [&'a [u8]],
+    local_subrs: &'a [&'a [u8]],
+    is_cff2: bool,
+    region_counts: &'a [usize],
+}
+
+struct CharstringInterpreter<'a> {
+    context: CharstringContext<'a>,
+    stack: Vec<f64>,
+    stem_count: usize,
+    /// In CFF the first stack-clearing operator can have an extra operand for the advance width, which we
+    /// ignore since we use the `hmtx` table.
+    has_width: bool,
+    vsindex: usize,
+    /// The current point, in double precision so that adding up relative coordinates doesn't lose precision.
+    x: f64,
+    y: f64,
+    outline_builder: OutlineBuilder,
+    is_done: bool,
+}
+
+impl<'a> CharstringInterpreter<'a> {
+    fn new(context: CharstringContext<'a>) -> Self {
+        Self {
+            context,
+            stack: vec![],
+            stem_count: 0,
+            has_width: context.is_cff2,
+            vsindex: 0,
+            x: 0.,
+            y: 0.,
+            outline_builder: OutlineBuilder::default(),
+            is_done: false,
+        }
+    }
+
+    /// Removes the advance width from the stack, if there is one; `has_width_if` tells if that's the case
+    /// based on the number of operands.
+    fn handle_width(&mut self, has_width_if: impl Fn(usize) -> bool) {
+        if !self.has_width {
+            self.has_width = true;
+            if has_width_if(self.stack.len()) {
+                self.stack.remove(0);
+            }
+        }
+    }
+
+    fn stems(&mut self) {
+        self.handle_width(|len| len % 2 == 1);
+        self.stem_count += self.stack.len() / 2;
+        self.stack.clear();
+    }
+
+    /// Moves the current point, and returns it.
+    fn advance(&mut self, dx: f64, dy: f64) -> Point {
+        self.x += dx;
+        self.y += dy;
+        Point::new(self.x as f32, self.y as f32)
+    }
+
+    fn move_to(&mut self, dx: f64, dy: f64) {
+        let point = self.advance(dx, dy);
+        self.outline_builder.move_to(point);
+    }
+
+    fn line_to(&mut self, dx: f64, dy: f64) {
+        let point = self.advance(dx, dy);
+        self.outline_builder.line_to(point);
+    }
+
+    fn curve_to(&mut self, dxa: f64, dya: f64, dxb: f64, dyb: f64, dxc: f64, dyc: f64) {
+        let control_1 = self.advance(dxa, dya);
+        let control_2 = self.advance(dxb, dyb);
+        let point = self.advance(dxc, dyc);
+        self.outline_builder.curve_to(control_1, control_2, point);
+    }
+
+    fn run(&mut self, charstring: &[u8], depth: usize) -> Result<()> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err(Error);
+        }
+        let mut reader = Reader::new(charstring);
+        while !reader.bytes.is_empty() && !self.is_done {
+            if self.stack.len() > MAX_STACK_LEN {
+                return Err(Error);
+            }
+            let b0 = reader.read_u8()?;
+            let s = &self.stack;
+            match b0 {
+                // hstem, vstem, hstemhm, vstemhm
+                1 | 3 | 18 | 23 => self.stems(),
+                // hintmask, cntrmask
+                19 | 20 => {
+                    // Operands are an implicit vstem.
+                    self.stems();
+                    reader.skip(self.stem_count.div_ceil(8))?;
+                }
+                // rmoveto
+                21 => {
+                    self.handle_width(|len| len > 2);
+                    match *self.stack.as_slice() {
+                        [dx, dy] => self.move_to(dx, dy),
+                        _ => return Err(Error),
+                    }
+                    self.stack.clear();
+                }
+                // hmoveto, vmoveto
+                22 | 4 => {
+                    self.handle_width(|len| len > 1);
+                    match *self.stack.as_slice() {
+                        [d] if b0 == 22 => self.move_to(d, 0.),
+                        [d] => self.move_to(0., d),
+                        _ => return Err(Error),
+                    }
+                    self.stack.clear();
+                }
+                // rlineto
+                5 => {
+                    if s.is_empty() || !s.len().is_multiple_of(2) {
+                        return Err(Error);
+                    }
+                    for i in (0..s.len()).step_by(2) {
+                        self.line_to(self.stack[i], self.stack[i + 1]);
+                    }
+                    self.stack.clear();
+                }
+                // hlineto, vlineto: alternating horizontal and vertical lines.
+                6 | 7 => {
+                    if s.is_empty() {
+                        return Err(Error);
+                    }
+                    for i in 0..s.len() {
+                        let d = self.stack[i];
+                        if (i % 2 == 0) == (b0 == 6) {
+                            self.line_to(d, 0.);
+                        } else {
+                            self.line_to(0., d);
+                        }
+                    }
+                    self.stack.clear();
+                }
+                // rrcurveto
+                8 => {
+                    if s.is_empty() || !s.len().is_multiple_of(6) {
+                        return Err(Error);
+                    }
+                    for i in (0..s.len()).step_by(6) {
+                        let s = &self.stack;
+                        let (dxa, dya, dxb, dyb, dxc, dyc) = (s[i], s[i + 1], s[i + 2], s[i + 3], s[i + 4], s[i + 5]);
+                        self.curve_to(dxa, dya, dxb, dyb, dxc, dyc);
+                    }
+                    self.stack.clear();
+                }
+                // rcurveline: curves followed by a line.
+                24 => {
+                    if s.len() < 8 || !(s.len() - 2).is_multiple_of(6) {
+                        return Err(Error);
+                    }
+                    let curves_len = s.len() - 2;
+                    for i in (0..curves_len).step_by(6) {
+                        let s = &self.stack;
+                        let (dxa, dya, dxb, dyb, dxc, dyc) = (s[i], s[i + 1], s[i + 2], s[i + 3], s[i + 4], s[i + 5]);
+                        self.curve_to(dxa, dya, dxb, dyb, dxc, dyc);
+                    }
+                    self.line_to(self.stack[curves_len], self.stack[curves_len + 1]);
+                    self.stack.clear();
+                }
+                // rlinecurve: lines followed by a curve.
+                25 => {
+                    if s.len() < 8 || !(s.len() - 6).is_multiple_of(2) {
+                        return Err(Error);
+                    }
+                    let lines_len = s.len() - 6;
+                    for i in (0..lines_len).step_by(2) {
+                        self.line_to(self.stack[i], self.stack[i + 1]);
+                    }
+                    let s = &self.stack[lines_len..];
+                    let (dxa, dya, dxb, dyb, dxc, dyc) = (s[0], s[1], s[2], s[3], s[4], s[5]);
+                    self.curve_to(dxa, dya, dxb, dyb, dxc, dyc);
+                    self.stack.clear();
+                }
+                // vvcurveto, hhcurveto: curves starting and ending vertically or horizontally respectively, with
+                // an optional extra first operand for the other direction.
+                26 | 27 => {
+                    if s.len() < 4 || s.len() % 4 > 1 {
+                        return Err(Error);
+                    }
+                    let mut extra = if s.len() % 4 == 1 { self.stack[0] } else { 0. };
+                    for i in ((s.len() % 4)..s.len()).step_by(4) {
+                        let s = &self.stack;
+                        if b0 == 26 {
+                            self.curve_to(extra, s[i], s[i + 1], s[i + 2], 0., s[i + 3]);
+                        } else {
+                            self.curve_to(s[i], extra, s[i + 1], s[i + 2], s[i + 3], 0.);
+                        }
+                        extra = 0.;
+                    }
+                    self.stack.clear();
+                }
+                // vhcurveto, hvcurveto: curves alternating between starting vertically and horizontally, with
+                // an optional extra last operand.
+                30 | 31 => {
+                    if s.len() < 4 || s.len() % 4 > 1 {
+                        return Err(Error);
+                    }
+                    let mut horizontal = b0 == 31;
+                    let mut i = 0;
+                    while i + 4 <= self.stack.len() {
+                        let s = &self.stack;
+                        let extra = if i + 5 == s.len() { s[i + 4] } else { 0. };
+                        if horizontal {
+                            self.curve_to(s[i], 0., s[i + 1], s[i + 2], extra, s[i + 3]);
+                        } else {
+                            self.curve_to(0., s[i], s[i + 1], s[i + 2], s[i + 3], extra);
+                        }
+                        horizontal = !horizontal;
+                        i += 4;
+                    }
+                    self.stack.clear();
+                }
+                // callsubr, callgsubr
+                10 | 29 => {
+                    let subrs = if b0 == 10 { self.context.local_subrs } else { self.context.global_subrs };
+                    let subr_number = self.stack.pop().ok_or(Error)? as i32;
+                    let subr = usize::try_from(subr_number + subr_bias(subrs.len())).ok().and_then(|index| subrs.get(index));
+                    self.run(subr.ok_or(Error)?, depth + 1)?;
+                }
+                // return
+                11 if !self.context.is_cff2 => return Ok(()),
+                // endchar
+                14 if !self.context.is_cff2 => {
+                    // 4 extra operands would be a deprecated `seac` accented character, which we don't draw.
+                    self.handle_width(|len| len == 1 || len == 5);
+                    self.stack.clear();
+                    self.is_done = true;
+                }
+                // vsindex
+                15 if self.context.is_cff2 => {
+                    self.vsindex = self.stack.pop().ok_or(Error)? as usize;
+                    self.stack.clear();
+                }
+                // blend: keep the default values, and drop the deltas for the regions.
+                16 if self.context.is_cff2 => {
+                    let value_count = self.stack.pop().ok_or(Error)? as usize;
+                    let region_count = *self.context.region_counts.get(self.vsindex).ok_or(Error)?;
+                    let operand_count = value_count * (region_count + 1);
+                    let start = self.stack.len().checked_sub(operand_count).ok_or(Error)?;
+                    self.stack.truncate(start + value_count);
+                }
+                12 => self.run_escaped_operator(reader.read_u8()?)?,
+                28 => self.stack.push(reader.read_i16()? as f64),
+                32..=246 => self.stack.push(b0 as f64 - 139.),
+                247..=250 => self.stack.push((b0 as f64 - 247.) * 256. + reader.read_u8()? as f64 + 108.),
+                251..=254 => self.stack.push(-(b0 as f64 - 251.) * 256. - reader.read_u8()? as f64 - 108.),
+                // 16.16 fixed point number.
+                255 => self.stack.push(reader.read_i32()? as f64 / 65536.),
+                _ => return Err(Error),
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the flex operators, which draw two curves. They are prefixed by byte 12.
+    fn run_escaped_operator(&mut self, b1: u8) -> Result<()> {
+        let s = self.stack.clone();
+        match (b1, s.as_slice()) {
+            // flex
+            (35, &[dx1, dy1, dx2, dy2, dx3, dy3, dx4, dy4, dx5, dy5, dx6, dy6, _fd]) => {
+                self.curve_to(dx1, dy1, dx2, dy2, dx3, dy3);
+                self.curve_to(dx4, dy4, dx5, dy5, dx6, dy6);
+            }
+            // hflex
+            (34, &[dx1, dx2, dy2, dx3, dx4, dx5, dx6]) => {
+                self.curve_to(dx1, 0., dx2, dy2, dx3, 0.);
+                self.curve_to(dx4, 0., dx5, -dy2, dx6, 0.);
+            }
+            // hflex1
+            (36, &[dx1, dy1, dx2, dy2, dx3, dx4, dx5, dy5, dx6]) => {
+                self.curve_to(dx1, dy1, dx2, dy2, dx3, 0.);
+                self.curve_to(dx4, 0., dx5, dy5, dx6, -(dy1 + dy2 + dy5));
+            }
+            // flex1: the last point is on the same horizontal or vertical line as the start.
+            (37, &[dx1, dy1, dx2, dy2, dx3, dy3, dx4, dy4, dx5, dy5, d6]) => {
+                let dx = dx1 + dx2 + dx3 + dx4 + dx5;
+                let dy = dy1 + dy2 + dy3 + dy4 + dy5;
+                let (dx6, dy6) = if dx.abs() > dy.abs() { (d6, -dy) } else { (-dx, d6) };
+                self.curve_to(dx1, dy1, dx2, dy2, dx3, dy3);
+                self.curve_to(dx4, dy4, dx5, dy5, dx6, dy6);
+            }
+            _ => return Err(Error),
+        }
+        self.stack.clear();
+        Ok(())
+    }
+}
+
+/// Builds an [`Outline`] from path commands, approximating cubic curves with quadratic ones.
+#[derive(Default)]
+struct OutlineBuilder {
+    outline: Outline,
+    contour: Vec<OutlinePoint>,
+}
+
+impl OutlineBuilder {
+    fn move_to(&mut self, point: Point) {
+        self.close_contour();
+        self.contour.push(OutlinePoint { is_on_curve: true, point });
+    }
+
+    fn line_to(&mut self, point: Point) {
+        self.contour.push(OutlinePoint { is_on_curve: true, point });
+    }
+
+    fn curve_to(&mut self, control_1: Point, control_2: Point, point: Point) {
+        let start = match self.contour.last() {
+            Some(last) => last.point,
+            // A path has to start with a moveto.
+            None => return,
+        };
+        // Split the curve into pieces that are each close enough to a quadratic curve. The error of
+        // approximating a cubic curve by a single quadratic one is at most sqrt(3)/36 times the length of this
+        // vector, and splitting the curve into n pieces divides that by n^3.
+        let third_derivative = (point - start) - (control_2 - control_1) * 3.;
+        let error = third_derivative.length() * 3f32.sqrt() / 36.;
+        let piece_count = ((error / CUBIC_TO_QUADRATIC_TOLERANCE).cbrt().ceil() as usize).clamp(1, MAX_QUADRATICS_PER_CUBIC);
+
+        let evaluate = |t: f32| {
+            let a = start.lerp(control_1, t);
+            let b = control_1.lerp(control_2, t);
+            let c = control_2.lerp(point, t);
+            a.lerp(b, t).lerp(b.lerp(c, t), t)
+        };
+        let derivative = |t: f32| {
+            let a = (control_1 - start) * ((1. - t) * (1. - t));
+            let b = (control_2 - control_1) * (2. * (1. - t) * t);
+            let c = (point - control_2) * (t * t);
+            (a + b + c) * 3.
+        };
+        for piece in 0..piece_count {
+            let (t0, t1) = (piece as f32 / piece_count as f32, (piece + 1) as f32 / piece_count as f32);
+            let piece_start = evaluate(t0);
+            let piece_end = if piece + 1 == piece_count { point } else { evaluate(t1) };
+            let piece_control_1 = piece_start + derivative(t0) * ((t1 - t0) / 3.);
+            let piece_control_2 = piece_end - derivative(t1) * ((t1 - t0) / 3.);
+            // The quadratic control point that matches the cubic curve best at the middle.
+            let control = piece_start
+                + ((piece_control_1 - piece_start) * 3. + (piece_control_2 - piece_start) * 3. - (piece_end - piece_start)) / 4.;
+            self.contour.push(OutlinePoint { is_on_curve: false, point: control });
+            self.contour.push(OutlinePoint { is_on_curve: true, point: piece_end });
+        }
+    }
+
+    fn close_contour(&mut self) {
+        // Contours are closed implicitly, so drop the last point if it's the same as the first one.
+        if self.contour.len() > 1 && self.contour.first() == self.contour.last() {
+            self.contour.pop();
+        }
+        if !self.contour.is_empty() {
+            let mut contour = self.outline.begin_contour();
+            for point in self.contour.drain(..) {
+                contour.push(point);
+            }
+            contour.end();
+        }
+    }
+
+    fn finish(mut self) -> Outline {
+        self.close_contour();
+        self.outline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutlineBuilder, CUBIC_TO_QUADRATIC_TOLERANCE};
+    use crate::geometry::Point;
+    use crate::internal_iter::InternalIterator;
+    use crate::path::PathCommand;
+
+    #[test]
+    fn it_approximates_cubic_curves() {
+        // A quarter circle, which can't be drawn with a single quadratic curve.
+        let (start, control_1, control_2, end) =
+            (Point::new(1000., 0.), Point::new(1000., 552.), Point::new(552., 1000.), Point::new(0., 1000.));
+        let mut outline_builder = OutlineBuilder::default();
+        outline_builder.move_to(start);
+        outline_builder.curve_to(control_1, control_2, end);
+        let outline = outline_builder.finish();
+
+        let cubic_points: Vec<Point> = Iterator::map(0..=1000, |i| {
+            let t = i as f32 / 1000.;
+            let (a, b, c) = (start.lerp(control_1, t), control_1.lerp(control_2, t), control_2.lerp(end, t));
+            a.lerp(b, t).lerp(b.lerp(c, t), t)
+        })
+        .collect();
+        let mut quadratic_count = 0;
+        let mut last_point = start;
+        outline.commands().for_each(&mut |command| {
+            if let PathCommand::QuadraticTo(control, point) = command {
+                quadratic_count += 1;
+                for i in 0..=100 {
+                    let t = i as f32 / 100.;
+                    let quadratic_point = last_point.lerp(control, t).lerp(control.lerp(point, t), t);
+                    let distance = Iterator::map(cubic_points.iter(), |&cubic_point| (cubic_point - quadratic_point).length())
+                        .fold(f32::MAX, f32::min);
+                    // Allow for the distance between the samples of the cubic curve.
+                    assert!(distance < CUBIC_TO_QUADRATIC_TOLERANCE + 1.);
+                }
+                last_point = point;
+            }
+            true
+        });
+        assert!(quadratic_count > 1);
+        assert_eq!(last_point, end);
+    }
+}
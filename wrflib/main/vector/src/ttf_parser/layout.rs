@@ -0,0 +1,332 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Kerning from the `kern` and `GPOS` tables, and ligatures from the `GSUB` table.
+//!
+//! This is only basic shaping: features are collected from the default language system of all scripts, only
+//! horizontal advances of pair adjustments are used, and lookup flags (like ignoring marks) are ignored.
+
+use super::{Error, Reader, Result};
+use crate::font::{Ligature, LigatureLookup, PairAdjustmentLookup, PairAdjustmentSubtable};
+use std::collections::{HashMap, HashSet};
+
+/// GPOS lookup types.
+const PAIR_ADJUSTMENT: u16 = 2;
+const GPOS_EXTENSION: u16 = 9;
+/// GSUB lookup types.
+const LIGATURE_SUBSTITUTION: u16 = 4;
+const GSUB_EXTENSION: u16 = 7;
+
+/// Parses the horizontal kerning pairs of a `kern` table (in the format used by Windows), as one lookup per
+/// subtable, since the adjustments of all subtables add up.
+pub(crate) fn parse_kern_table(bytes: &[u8]) -> Result<Vec<PairAdjustmentLookup>> {
+    let mut reader = Reader::new(bytes);
+    if reader.read_u16()? != 0 {
+        return Err(Error);
+    }
+    let subtable_count = reader.read_u16()? as usize;
+    let mut lookups = vec![];
+    for index in 0..subtable_count {
+        let subtable_bytes = reader.bytes;
+        reader.skip(2)?;
+        let length = reader.read_u16()? as usize;
+        let coverage = reader.read_u16()?;
+        let (format, is_horizontal, has_minimum_values, is_cross_stream) =
+            (coverage >> 8, coverage & 0x1 != 0, coverage & 0x2 != 0, coverage & 0x4 != 0);
+        if format == 0 && is_horizontal && !has_minimum_values && !is_cross_stream {
+            let pair_count = reader.read_u16()? as usize;
+            reader.skip(6)?;
+            let mut pairs = HashMap::new();
+            for _ in 0..pair_count {
+                let left = reader.read_u16()? as usize;
+                let right = reader.read_u16()? as usize;
+                pairs.insert((left, right), reader.read_i16()? as f32);
+            }
+            lookups.push(PairAdjustmentLookup { subtables: vec![PairAdjustmentSubtable::Pairs(pairs)] });
+        }
+        // The length of a large last subtable might not fit in 16 bits, so only use it to get to the next one.
+        if index + 1 < subtable_count {
+            reader = Reader::new(subtable_bytes.get(length..).ok_or(Error)?);
+        }
+    }
+    Ok(lookups)
+}
+
+/// Parses the pair adjustment lookups of the `kern` feature of a `GPOS` table.
+pub(crate) fn parse_gpos_table(bytes: &[u8]) -> Result<Vec<PairAdjustmentLookup>> {
+    let lookups = parse_lookups(bytes, &[*b"kern"], PAIR_ADJUSTMENT, GPOS_EXTENSION, parse_pair_adjustment_subtable)?;
+    Ok(lookups.into_iter().map(|subtables| PairAdjustmentLookup { subtables }).collect())
+}
+
+/// Parses the ligature substitution lookups of the `liga` (standard ligatures) and `rlig` (required
+/// ligatures) features of a `GSUB` table.
+pub(crate) fn parse_gsub_table(bytes: &[u8]) -> Result<Vec<LigatureLookup>> {
+    let lookups = parse_lookups(bytes, &[*b"liga", *b"rlig"], LIGATURE_SUBSTITUTION, GSUB_EXTENSION, parse_ligature_subtable)?;
+    Ok(lookups
+        .into_iter()
+        .map(|subtables| {
+            let mut ligatures: HashMap<usize, Vec<Ligature>> = HashMap::new();
+            // Subtables are tried in order, so the ligatures of earlier subtables come first.
+            for subtable in subtables {
+                for (first_glyph_index, subtable_ligatures) in subtable {
+                    ligatures.entry(first_glyph_index).or_default().extend(subtable_ligatures);
+                }
+            }
+            LigatureLookup { ligatures }
+        })
+        .collect())
+}
+
+/// Parses the subtables of the lookups of type `lookup_type` that are used by `feature_tags`, in the order in
+/// which they should be applied.
+fn parse_lookups<T>(
+    bytes: &[u8],
+    feature_tags: &[[u8; 4]],
+    lookup_type: u16,
+    extension_lookup_type: u16,
+    parse_subtable: fn(&[u8]) -> Result<T>,
+) -> Result<Vec<Vec<T>>> {
+    let mut reader = Reader::new(bytes);
+    if reader.read_u16()? != 1 {
+        return Err(Error);
+    }
+    reader.skip(2)?;
+    let script_list_bytes = bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+    let feature_list_bytes = bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+    let lookup_list_bytes = bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+
+    let mut lookup_indices = vec![];
+    for feature_index in parse_default_feature_indices(script_list_bytes)? {
+        let mut reader = Reader::new(feature_list_bytes);
+        let feature_count = reader.read_u16()? as usize;
+        if feature_index >= feature_count {
+            return Err(Error);
+        }
+        reader.skip(feature_index * 6)?;
+        let mut feature_tag = [0; 4];
+        reader.read(&mut feature_tag)?;
+        if !feature_tags.contains(&feature_tag) {
+            continue;
+        }
+        let mut reader = Reader::new(feature_list_bytes.get(reader.read_u16()? as usize..).ok_or(Error)?);
+        reader.skip(2)?;
+        let lookup_index_count = reader.read_u16()? as usize;
+        for _ in 0..lookup_index_count {
+            lookup_indices.push(reader.read_u16()? as usize);
+        }
+    }
+    // Lookups get applied in the order of the lookup list.
+    lookup_indices.sort_unstable();
+    lookup_indices.dedup();
+
+    let mut lookups = vec![];
+    for lookup_index in lookup_indices {
+        let mut reader = Reader::new(lookup_list_bytes);
+        let lookup_count = reader.read_u16()? as usize;
+        if lookup_index >= lookup_count {
+            return Err(Error);
+        }
+        reader.skip(lookup_index * 2)?;
+        let lookup_bytes = lookup_list_bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+        let mut reader = Reader::new(lookup_bytes);
+        let this_lookup_type = reader.read_u16()?;
+        reader.skip(2)?;
+        let subtable_count = reader.read_u16()? as usize;
+        let mut subtables = vec![];
+        for _ in 0..subtable_count {
+            let subtable_bytes = lookup_bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+            if this_lookup_type == lookup_type {
+                subtables.push(parse_subtable(subtable_bytes)?);
+            } else if this_lookup_type == extension_lookup_type {
+                // Extension subtables point to a subtable of another type, using a 32 bit offset.
+                let mut reader = Reader::new(subtable_bytes);
+                reader.skip(2)?;
+                if reader.read_u16()? == lookup_type {
+                    subtables.push(parse_subtable(subtable_bytes.get(reader.read_u32()? as usize..).ok_or(Error)?)?);
+                }
+            }
+        }
+        if !subtables.is_empty() {
+            lookups.push(subtables);
+        }
+    }
+    Ok(lookups)
+}
+
+/// Returns the indices of the features of the default language system of all scripts.
+fn parse_default_feature_indices(script_list_bytes: &[u8]) -> Result<Vec<usize>> {
+    let mut reader = Reader::new(script_list_bytes);
+    let script_count = reader.read_u16()? as usize;
+    let mut feature_indices = vec![];
+    for _ in 0..script_count {
+        reader.skip(4)?;
+        let script_bytes = script_list_bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+        let default_lang_sys_offset = Reader::new(script_bytes).read_u16()? as usize;
+        if default_lang_sys_offset == 0 {
+            continue;
+        }
+        let mut reader = Reader::new(script_bytes.get(default_lang_sys_offset..).ok_or(Error)?);
+        reader.skip(2)?;
+        let required_feature_index = reader.read_u16()?;
+        if required_feature_index != 0xFFFF {
+            feature_indices.push(required_feature_index as usize);
+        }
+        let feature_index_count = reader.read_u16()? as usize;
+        for _ in 0..feature_index_count {
+            feature_indices.push(reader.read_u16()? as usize);
+        }
+    }
+    feature_indices.sort_unstable();
+    feature_indices.dedup();
+    Ok(feature_indices)
+}
+
+/// Parses a coverage table into glyph indices, in coverage index order.
+fn parse_coverage(bytes: &[u8]) -> Result<Vec<usize>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    let count = reader.read_u16()? as usize;
+    let mut glyph_indices = vec![];
+    match format {
+        1 => {
+            for _ in 0..count {
+                glyph_indices.push(reader.read_u16()? as usize);
+            }
+        }
+        2 => {
+            for _ in 0..count {
+                let start = reader.read_u16()? as usize;
+                let end = reader.read_u16()? as usize;
+                if reader.read_u16()? as usize != glyph_indices.len() {
+                    return Err(Error);
+                }
+                glyph_indices.extend(start..=end);
+            }
+        }
+        _ => return Err(Error),
+    }
+    Ok(glyph_indices)
+}
+
+/// Parses a class definition table into a map from glyph indices to classes. Other glyphs are in class 0.
+fn parse_class_def(bytes: &[u8]) -> Result<HashMap<usize, usize>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    let mut classes = HashMap::new();
+    match format {
+        1 => {
+            let start = reader.read_u16()? as usize;
+            let count = reader.read_u16()? as usize;
+            for glyph_index in start..(start + count) {
+                classes.insert(glyph_index, reader.read_u16()? as usize);
+            }
+        }
+        2 => {
+            let range_count = reader.read_u16()? as usize;
+            for _ in 0..range_count {
+                let start = reader.read_u16()? as usize;
+                let end = reader.read_u16()? as usize;
+                let class = reader.read_u16()? as usize;
+                classes.extend((start..=end).map(|glyph_index| (glyph_index, class)));
+            }
+        }
+        _ => return Err(Error),
+    }
+    Ok(classes)
+}
+
+/// Reads a value record, which has a field for each bit in `value_format`, and returns its horizontal advance.
+fn read_x_advance(reader: &mut Reader, value_format: u16) -> Result<f32> {
+    let mut x_advance = 0.;
+    for bit in 0..8 {
+        if value_format & (1 << bit) != 0 {
+            let value = reader.read_i16()?;
+            if bit == 2 {
+                x_advance = value as f32;
+            }
+        }
+    }
+    Ok(x_advance)
+}
+
+fn parse_pair_adjustment_subtable(bytes: &[u8]) -> Result<PairAdjustmentSubtable> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    let coverage = parse_coverage(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+    let first_value_format = reader.read_u16()?;
+    let second_value_format = reader.read_u16()?;
+    match format {
+        1 => {
+            let pair_set_count = reader.read_u16()? as usize;
+            if pair_set_count != coverage.len() {
+                return Err(Error);
+            }
+            let mut pairs = HashMap::new();
+            for &left in &coverage {
+                let mut pair_set_reader = Reader::new(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?);
+                let pair_count = pair_set_reader.read_u16()? as usize;
+                for _ in 0..pair_count {
+                    let right = pair_set_reader.read_u16()? as usize;
+                    let x_advance = read_x_advance(&mut pair_set_reader, first_value_format)?;
+                    read_x_advance(&mut pair_set_reader, second_value_format)?;
+                    // The first pair wins if there are duplicates.
+                    pairs.entry((left, right)).or_insert(x_advance);
+                }
+            }
+            Ok(PairAdjustmentSubtable::Pairs(pairs))
+        }
+        2 => {
+            let first_classes = parse_class_def(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+            let second_classes = parse_class_def(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+            let first_class_count = reader.read_u16()? as usize;
+            let second_class_count = reader.read_u16()? as usize;
+            let mut adjustments = Vec::new();
+            for _ in 0..(first_class_count * second_class_count) {
+                adjustments.push(read_x_advance(&mut reader, first_value_format)?);
+                read_x_advance(&mut reader, second_value_format)?;
+            }
+            Ok(PairAdjustmentSubtable::Classes {
+                coverage: coverage.into_iter().collect::<HashSet<_>>(),
+                first_classes,
+                second_classes,
+                second_class_count,
+                adjustments,
+            })
+        }
+        _ => Err(Error),
+    }
+}
+
+/// Parses a ligature substitution subtable into ligatures by first glyph.
+fn parse_ligature_subtable(bytes: &[u8]) -> Result<Vec<(usize, Vec<Ligature>)>> {
+    let mut reader = Reader::new(bytes);
+    if reader.read_u16()? != 1 {
+        return Err(Error);
+    }
+    let coverage = parse_coverage(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+    let ligature_set_count = reader.read_u16()? as usize;
+    if ligature_set_count != coverage.len() {
+        return Err(Error);
+    }
+    let mut ligatures_by_first_glyph = vec![];
+    for first_glyph_index in coverage {
+        let ligature_set_bytes = bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+        let mut ligature_set_reader = Reader::new(ligature_set_bytes);
+        let ligature_count = ligature_set_reader.read_u16()? as usize;
+        let mut ligatures = vec![];
+        for _ in 0..ligature_count {
+            let mut ligature_reader =
+                Reader::new(ligature_set_bytes.get(ligature_set_reader.read_u16()? as usize..).ok_or(Error)?);
+            let glyph_index = ligature_reader.read_u16()? as usize;
+            let component_count = ligature_reader.read_u16()? as usize;
+            let components =
+                (1..component_count).map(|_| Ok(ligature_reader.read_u16()? as usize)).collect::<Result<Vec<_>>>()?;
+            ligatures.push(Ligature { components, glyph_index });
+        }
+        ligatures_by_first_glyph.push((first_glyph_index, ligatures));
+    }
+    Ok(ligatures_by_first_glyph)
+}
@@ -4,7 +4,10 @@
 // found in the LICENSE-APACHE file in the root directory of this source tree.
 // You may not use this file except in compliance with the License.
 
-use crate::font::{Glyph, HorizontalMetrics, Outline, OutlinePoint, VectorFont};
+mod cff;
+mod layout;
+
+use crate::font::{CharCodeRange, Glyph, HorizontalMetrics, Outline, OutlinePoint, VectorFont};
 use crate::geometry::{AffineTransformation, LinearTransformation, Point, Rectangle, Transform, Vector};
 use std::{mem, result};
 
@@ -55,8 +58,8 @@ impl<'a> GlyphsParser<'a> {
     fn parse_glyph(&mut self, index: usize) -> Result<Glyph> {
         let start = self.parse_offset(index)?;
         let end = self.parse_offset(index + 1)?;
-        let bytes = &self.glyf_table_bytes[start..end];
-        let horizontal_metrics = self.parse_horizontal_metrics(index)?;
+        let bytes = self.glyf_table_bytes.get(start..end).ok_or(Error)?;
+        let horizontal_metrics = parse_horizontal_metrics(self.hmtx_table_bytes, self.advance_width_count, index)?;
         Ok(if bytes.is_empty() {
             Glyph { horizontal_metrics, bounds: Rectangle::default(), outline: Outline::default() }
         } else {
@@ -89,20 +92,6 @@ impl<'a> GlyphsParser<'a> {
         })
     }
 
-    fn parse_horizontal_metrics(&self, index: usize) -> Result<HorizontalMetrics> {
-        let mut reader = Reader::new(self.hmtx_table_bytes);
-        if index < self.advance_width_count {
-            reader.skip(index * 4)?;
-            Ok(HorizontalMetrics { advance_width: reader.read_u16()? as f32, left_side_bearing: reader.read_i16()? as f32 })
-        } else {
-            reader.skip((self.advance_width_count - 1) * 4)?;
-            let advance_width = reader.read_u16()? as f32;
-            reader.skip(2)?;
-            reader.skip((index - self.advance_width_count) * 2)?;
-            Ok(HorizontalMetrics { advance_width, left_side_bearing: reader.read_i16()? as f32 })
-        }
-    }
-
     fn parse_simple_glyph(
         bytes: &'a [u8],
         horizontal_metrics: HorizontalMetrics,
@@ -228,6 +217,41 @@ impl<'a> GlyphsParser<'a> {
     }
 }
 
+fn parse_horizontal_metrics(hmtx_table_bytes: &[u8], advance_width_count: usize, index: usize) -> Result<HorizontalMetrics> {
+    let mut reader = Reader::new(hmtx_table_bytes);
+    if index < advance_width_count {
+        reader.skip(index * 4)?;
+        Ok(HorizontalMetrics { advance_width: reader.read_u16()? as f32, left_side_bearing: reader.read_i16()? as f32 })
+    } else {
+        reader.skip(advance_width_count.checked_sub(1).ok_or(Error)? * 4)?;
+        let advance_width = reader.read_u16()? as f32;
+        reader.skip(2)?;
+        reader.skip((index - advance_width_count) * 2)?;
+        Ok(HorizontalMetrics { advance_width, left_side_bearing: reader.read_i16()? as f32 })
+    }
+}
+
+/// Creates glyphs from outlines that don't come with bounds, like those in `CFF ` and `CFF2` tables.
+fn glyphs_from_outlines(outlines: Vec<Outline>, hmtx_table_bytes: &[u8], advance_width_count: usize) -> Result<Vec<Glyph>> {
+    outlines
+        .into_iter()
+        .enumerate()
+        .map(|(index, outline)| {
+            let horizontal_metrics = parse_horizontal_metrics(hmtx_table_bytes, advance_width_count, index)?;
+            let bounds = match outline.points().first() {
+                Some(first) => outline.points().iter().fold(Rectangle::new(first.point, first.point), |bounds, point| {
+                    Rectangle::new(
+                        Point::new(bounds.p_min.x.min(point.point.x), bounds.p_min.y.min(point.point.y)),
+                        Point::new(bounds.p_max.x.max(point.point.x), bounds.p_max.y.max(point.point.y)),
+                    )
+                }),
+                None => Rectangle::default(),
+            };
+            Ok(Glyph { horizontal_metrics, bounds, outline })
+        })
+        .collect()
+}
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq)]
 enum IndexToLocFormat {
     Short,
@@ -440,6 +464,19 @@ impl<'a> Reader<'a> {
         Ok(u32::from_be_bytes(bytes))
     }
 
+    fn read_i32(&mut self) -> Result<i32> {
+        let mut bytes = [0; mem::size_of::<i32>()];
+        self.read(&mut bytes)?;
+        Ok(i32::from_be_bytes(bytes))
+    }
+
+    /// Reads an unsigned integer of 1 to 4 bytes.
+    fn read_uint(&mut self, size: usize) -> Result<usize> {
+        let mut bytes = [0; mem::size_of::<u32>()];
+        self.read(bytes.get_mut(mem::size_of::<u32>().checked_sub(size).ok_or(Error)?..).ok_or(Error)?)?;
+        Ok(u32::from_be_bytes(bytes) as usize)
+    }
+
     fn read_f2dot14(&mut self) -> Result<f32> {
         Ok(self.read_i16()? as f32 / (1 << 14) as f32)
     }
@@ -450,45 +487,80 @@ pub type Result<T> = result::Result<T, Error>;
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Error;
 
+/// Parses a TrueType (.ttf) or OpenType (.otf) font.
 pub fn parse_ttf(bytes: &[u8]) -> Result<VectorFont> {
-    let mut reader = Reader::new(&bytes[0..12]);
+    parse_ttc(bytes, 0)
+}
+
+/// Parses a font from a TrueType or OpenType Collection (.ttc or .otc), which bundles multiple fonts that can
+/// share tables. `index` is the index of the font in the collection. Also accepts a single font, with index 0.
+pub fn parse_ttc(bytes: &[u8], index: usize) -> Result<VectorFont> {
+    let mut reader = Reader::new(bytes);
+    let offset = if reader.read_u32()? == u32::from_be_bytes(*b"ttcf") {
+        reader.skip(4)?;
+        let font_count = reader.read_u32()? as usize;
+        if index >= font_count {
+            return Err(Error);
+        }
+        reader.skip(index * 4)?;
+        reader.read_u32()? as usize
+    } else if index == 0 {
+        0
+    } else {
+        return Err(Error);
+    };
+    parse_font(bytes, offset)
+}
+
+/// Parses the font with the table directory at `offset`. Table offsets are relative to the start of `bytes`,
+/// also in collections.
+fn parse_font(bytes: &[u8], offset: usize) -> Result<VectorFont> {
+    let mut reader = Reader::new(bytes.get(offset..).and_then(|bytes| bytes.get(..12)).ok_or(Error)?);
     let sfnt_version = reader.read_u32()?;
-    if ![0x00010000, u32::from_be_bytes(*b"true")].contains(&sfnt_version) {
+    if ![0x00010000, u32::from_be_bytes(*b"true"), u32::from_be_bytes(*b"OTTO")].contains(&sfnt_version) {
         return Err(Error);
     }
     let table_count = reader.read_u16()? as usize;
     reader.skip(6)?;
+    let mut cff_table_bytes = None;
+    let mut cff2_table_bytes = None;
     let mut cmap_table_bytes = None;
     let mut glyf_table_bytes = None;
+    let mut gpos_table_bytes = None;
+    let mut gsub_table_bytes = None;
     let mut head_table_bytes = None;
     let mut hhea_table_bytes = None;
     let mut hmtx_table_bytes = None;
+    let mut kern_table_bytes = None;
     let mut loca_table_bytes = None;
     let mut maxp_table_bytes = None;
     for index in 0..table_count {
-        let mut reader = Reader::new(&bytes[(12 + index * 16)..][..16]);
+        let mut reader = Reader::new(bytes.get((offset + 12 + index * 16)..).and_then(|bytes| bytes.get(..16)).ok_or(Error)?);
         let table_tag = reader.read_u32()?;
         reader.skip(4)?;
         let offset = reader.read_u32()? as usize;
         let length = reader.read_u32()? as usize;
-        let table_bytes = &bytes[offset..][..length];
+        let table_bytes = bytes.get(offset..).and_then(|bytes| bytes.get(..length)).ok_or(Error)?;
         match &table_tag.to_be_bytes() {
+            b"CFF " => cff_table_bytes = Some(table_bytes),
+            b"CFF2" => cff2_table_bytes = Some(table_bytes),
             b"cmap" => cmap_table_bytes = Some(table_bytes),
             b"glyf" => glyf_table_bytes = Some(table_bytes),
+            b"GPOS" => gpos_table_bytes = Some(table_bytes),
+            b"GSUB" => gsub_table_bytes = Some(table_bytes),
             b"head" => head_table_bytes = Some(table_bytes),
             b"hhea" => hhea_table_bytes = Some(table_bytes),
             b"hmtx" => hmtx_table_bytes = Some(table_bytes),
+            b"kern" => kern_table_bytes = Some(table_bytes),
             b"loca" => loca_table_bytes = Some(table_bytes),
             b"maxp" => maxp_table_bytes = Some(table_bytes),
             _ => {}
         }
     }
     let cmap_table_bytes = cmap_table_bytes.ok_or(Error)?;
-    let glyf_table_bytes = glyf_table_bytes.ok_or(Error)?;
     let head_table_bytes = head_table_bytes.ok_or(Error)?;
     let hhea_table_bytes = hhea_table_bytes.ok_or(Error)?;
     let hmtx_table_bytes = hmtx_table_bytes.ok_or(Error)?;
-    let loca_table_bytes = loca_table_bytes.ok_or(Error)?;
     let maxp_table_bytes = maxp_table_bytes.ok_or(Error)?;
     let mut reader = Reader::new(hhea_table_bytes);
     reader.skip(4)?;
@@ -500,7 +572,6 @@ pub fn parse_ttf(bytes: &[u8]) -> Result<VectorFont> {
     let mut reader = Reader::new(maxp_table_bytes);
     reader.skip(4)?;
     let glyph_count = reader.read_u16()? as usize;
-    reader.skip(26)?;
     let mut reader = Reader::new(head_table_bytes);
     reader.skip(18)?;
     let units_per_em = reader.read_u16()? as f32;
@@ -510,48 +581,84 @@ pub fn parse_ttf(bytes: &[u8]) -> Result<VectorFont> {
         Point::new(reader.read_i16()? as f32, reader.read_i16()? as f32),
     );
     reader.skip(6)?;
-    let index_to_loc_format = IndexToLocFormat::from_i16(reader.read_i16()?).ok_or(Error)?;
+    let index_to_loc_format = reader.read_i16()?;
     reader.skip(2)?;
-    Ok(VectorFont {
-        units_per_em,
-        ascender,
-        descender,
-        line_gap,
-        bounds,
-        char_code_to_glyph_index_map: parse_char_code_to_glyph_index_map(cmap_table_bytes)?,
-        glyphs: GlyphsParser::new(
+    // Glyph outlines are either TrueType outlines (in the `glyf` table) or CFF outlines (in the `CFF ` or `CFF2`
+    // table).
+    let glyphs = match (glyf_table_bytes, loca_table_bytes, cff_table_bytes, cff2_table_bytes) {
+        (Some(glyf_table_bytes), Some(loca_table_bytes), _, _) => GlyphsParser::new(
             glyph_count,
             advance_width_count,
             hmtx_table_bytes,
-            index_to_loc_format,
+            IndexToLocFormat::from_i16(index_to_loc_format).ok_or(Error)?,
             loca_table_bytes,
             glyf_table_bytes,
         )
         .parse_glyphs()?,
+        (_, _, Some(cff_table_bytes), _) => {
+            glyphs_from_outlines(cff::parse_cff_outlines(cff_table_bytes)?, hmtx_table_bytes, advance_width_count)?
+        }
+        (_, _, _, Some(cff2_table_bytes)) => {
+            glyphs_from_outlines(cff::parse_cff2_outlines(cff2_table_bytes)?, hmtx_table_bytes, advance_width_count)?
+        }
+        _ => return Err(Error),
+    };
+    if glyphs.len() != glyph_count {
+        return Err(Error);
+    }
+    let (char_code_to_glyph_index_map, char_code_ranges) = parse_char_code_to_glyph_index_map(cmap_table_bytes)?;
+    // Kerning and ligatures are optional, so ignore tables that we can't parse. Like browsers, prefer the `GPOS`
+    // table over the older `kern` table.
+    let pair_adjustment_lookups = gpos_table_bytes
+        .and_then(|bytes| layout::parse_gpos_table(bytes).ok())
+        .filter(|lookups| !lookups.is_empty())
+        .or_else(|| kern_table_bytes.and_then(|bytes| layout::parse_kern_table(bytes).ok()))
+        .unwrap_or_default();
+    let ligature_lookups = gsub_table_bytes.and_then(|bytes| layout::parse_gsub_table(bytes).ok()).unwrap_or_default();
+    Ok(VectorFont {
+        units_per_em,
+        ascender,
+        descender,
+        line_gap,
+        bounds,
+        char_code_to_glyph_index_map,
+        char_code_ranges,
+        glyphs,
+        pair_adjustment_lookups,
+        ligature_lookups,
     })
 }
 
-fn parse_char_code_to_glyph_index_map(bytes: &[u8]) -> Result<Vec<usize>> {
+/// Parses the `cmap` table into a map for the Basic Multilingual Plane and ranges for other characters.
+fn parse_char_code_to_glyph_index_map(bytes: &[u8]) -> Result<(Vec<usize>, Vec<CharCodeRange>)> {
     let mut reader = Reader::new(bytes);
     reader.skip(2)?;
-    let mut subtable_bytes = None;
+    // Pick a Unicode subtable, preferring formats 12 and 13 (which cover all of Unicode) over format 4 (which
+    // only covers the Basic Multilingual Plane).
+    let mut best_subtable = None;
     let subtable_count = reader.read_u16()? as usize;
     for _ in 0..subtable_count {
         let platform_id = reader.read_u16()?;
         let encoding_id = reader.read_u16()?;
         let offset = reader.read_u32()? as usize;
         if let (0, _) | (3, 1) | (3, 10) = (platform_id, encoding_id) {
-            subtable_bytes = Some(&bytes[offset..]);
-            break;
+            let subtable_bytes = bytes.get(offset..).ok_or(Error)?;
+            let format = Reader::new(subtable_bytes).read_u16()?;
+            let priority = match format {
+                12 | 13 => 2,
+                4 => 1,
+                _ => continue,
+            };
+            if best_subtable.map_or(true, |(best_priority, _, _)| priority > best_priority) {
+                best_subtable = Some((priority, format, subtable_bytes));
+            }
         }
     }
-    let subtable_bytes = subtable_bytes.ok_or(Error)?;
-    let mut reader = Reader::new(subtable_bytes);
-    let format = reader.read_u16()?;
+    let (_, format, subtable_bytes) = best_subtable.ok_or(Error)?;
     let bytes = &subtable_bytes[2..];
     match format {
-        4 => parse_char_code_to_glyph_index_map_format_4(bytes),
-        _ => Err(Error),
+        4 => Ok((parse_char_code_to_glyph_index_map_format_4(bytes)?, vec![])),
+        _ => parse_char_code_to_glyph_index_map_format_12_or_13(bytes, format == 13),
     }
 }
 
@@ -564,10 +671,10 @@ fn parse_char_code_to_glyph_index_map_format_4(bytes: &[u8]) -> Result<Vec<usize
     let start_code_bytes_start = end_code_bytes_end + 2;
     let id_delta_bytes_start = start_code_bytes_start + seg_count * 2;
     let id_range_offset_bytes_start = id_delta_bytes_start + seg_count * 2;
-    let end_code_bytes = &bytes[end_code_bytes_start..end_code_bytes_end];
-    let start_code_bytes = &bytes[start_code_bytes_start..id_delta_bytes_start];
-    let id_delta_bytes = &bytes[id_delta_bytes_start..id_range_offset_bytes_start];
-    let id_range_offset_bytes = &bytes[id_range_offset_bytes_start..];
+    let end_code_bytes = bytes.get(end_code_bytes_start..end_code_bytes_end).ok_or(Error)?;
+    let start_code_bytes = bytes.get(start_code_bytes_start..id_delta_bytes_start).ok_or(Error)?;
+    let id_delta_bytes = bytes.get(id_delta_bytes_start..id_range_offset_bytes_start).ok_or(Error)?;
+    let id_range_offset_bytes = bytes.get(id_range_offset_bytes_start..).ok_or(Error)?;
     let mut end_code_reader = Reader::new(end_code_bytes);
     let mut start_code_reader = Reader::new(start_code_bytes);
     let mut id_delta_reader = Reader::new(id_delta_bytes);
@@ -578,43 +685,321 @@ fn parse_char_code_to_glyph_index_map_format_4(bytes: &[u8]) -> Result<Vec<usize
         let start_code = start_code_reader.read_u16()?;
         let id_delta = id_delta_reader.read_u16()? as usize;
         let id_range_offset = id_range_offset_reader.read_u16()? as usize;
-        for code in start_code..end_code {
-            let mut id = if id_range_offset == 0 {
-                code
+        for code in start_code..=end_code {
+            let id = if id_range_offset == 0 {
+                (code as usize + id_delta) % 65536
             } else {
                 let id_range_bytes = &id_range_offset_bytes[(seg_index * 2)..];
                 let mut reader = Reader::new(id_range_bytes);
                 reader.skip(id_range_offset + (code - start_code) as usize * 2)?;
-
-                reader.read_u16()?
-            } as usize;
-            if id != 0 {
-                id = (id + id_delta) % 65536;
-            }
-            char_code_to_glyph_index_map.resize(code as usize + 1, 0);
+                match reader.read_u16()? as usize {
+                    0 => 0,
+                    id => (id + id_delta) % 65536,
+                }
+            };
+            char_code_to_glyph_index_map.resize(char_code_to_glyph_index_map.len().max(code as usize + 1), 0);
             char_code_to_glyph_index_map[code as usize] = id;
         }
     }
     Ok(char_code_to_glyph_index_map)
 }
 
+/// Parses subtable format 12 (segmented coverage) or 13 (many-to-one range mappings), which consist of groups
+/// of consecutive characters that map to consecutive glyphs, or all to the same glyph for format 13.
+fn parse_char_code_to_glyph_index_map_format_12_or_13(
+    bytes: &[u8],
+    is_constant: bool,
+) -> Result<(Vec<usize>, Vec<CharCodeRange>)> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(10)?;
+    let group_count = reader.read_u32()? as usize;
+    let mut char_code_to_glyph_index_map = Vec::new();
+    let mut char_code_ranges: Vec<CharCodeRange> = Vec::new();
+    let mut next_char_code = 0;
+    for _ in 0..group_count {
+        let start = reader.read_u32()?;
+        let end = reader.read_u32()?;
+        let glyph_index = reader.read_u32()? as usize;
+        // Groups have to be sorted and can't overlap.
+        if start < next_char_code || end < start {
+            return Err(Error);
+        }
+        next_char_code = end.saturating_add(1);
+        let glyph_index_for = |code: u32| if is_constant { glyph_index } else { glyph_index + (code - start) as usize };
+        if start <= 0xFFFF {
+            let bmp_end = end.min(0xFFFF);
+            char_code_to_glyph_index_map.resize(bmp_end as usize + 1, 0);
+            for code in start..=bmp_end {
+                char_code_to_glyph_index_map[code as usize] = glyph_index_for(code);
+            }
+        }
+        if end > 0xFFFF {
+            let range_start = start.max(0x10000);
+            char_code_ranges.push(CharCodeRange {
+                start: range_start,
+                end,
+                glyph_index: glyph_index_for(range_start),
+                is_constant,
+            });
+        }
+    }
+    Ok((char_code_to_glyph_index_map, char_code_ranges))
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::font::VectorFont;
+    use crate::geometry::Point;
+    use crate::path::PathCommand;
+    use crate::ttf_parser::{parse_ttc, parse_ttf};
     use std::fs;
     use std::path::Path;
 
+    fn read_resource(name: &str) -> Vec<u8> {
+        fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources").join(name)).unwrap()
+    }
+
+    /// Finds a table in a font.
+    fn table_bytes(bytes: &[u8], tag: &[u8; 4]) -> Vec<u8> {
+        let table_count = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        let record = (0..table_count)
+            .map(|index| &bytes[(12 + index * 16)..(28 + index * 16)])
+            .find(|record| &record[..4] == tag)
+            .unwrap();
+        let offset = u32::from_be_bytes(record[8..12].try_into().unwrap()) as usize;
+        let length = u32::from_be_bytes(record[12..16].try_into().unwrap()) as usize;
+        bytes[offset..(offset + length)].to_vec()
+    }
+
+    /// The path commands of a glyph, without lines that don't go anywhere.
+    fn glyph_commands(font: &VectorFont, glyph_index: usize) -> Vec<PathCommand> {
+        use crate::internal_iter::InternalIterator;
+
+        let mut commands = vec![];
+        let mut last_point = None;
+        font.glyphs[glyph_index].outline.commands().for_each(&mut |command| {
+            match command {
+                PathCommand::LineTo(point) if Some(point) == last_point => {}
+                PathCommand::MoveTo(point) | PathCommand::LineTo(point) | PathCommand::QuadraticTo(_, point) => {
+                    last_point = Some(point);
+                    commands.push(command);
+                }
+                PathCommand::Close => commands.push(command),
+            }
+            true
+        });
+        commands
+    }
+
+    /// Builds a TrueType Collection with copies of the tables of each font.
+    ///
+    /// TODO: Also test a real .ttc or .otc, which shares tables between its fonts.
+    fn make_collection(fonts: &[&[u8]]) -> Vec<u8> {
+        let read_u16 = |bytes: &[u8], offset: usize| u16::from_be_bytes([bytes[offset], bytes[offset + 1]]) as usize;
+        let read_u32 = |bytes: &[u8], offset: usize| u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        let mut header = b"ttcf\x00\x01\x00\x00".to_vec();
+        header.extend((fonts.len() as u32).to_be_bytes());
+        let mut table_directories = vec![];
+        let mut tables = vec![];
+        let table_directories_start = header.len() + fonts.len() * 4;
+        let table_directories_len: usize = fonts.iter().map(|font| 12 + read_u16(font, 4) * 16).sum();
+        for font in fonts {
+            header.extend(((table_directories_start + table_directories.len()) as u32).to_be_bytes());
+            table_directories.extend(&font[..12]);
+            for index in 0..read_u16(font, 4) {
+                let record = &font[(12 + index * 16)..(12 + index * 16 + 16)];
+                let (offset, length) = (read_u32(record, 8), read_u32(record, 12));
+                table_directories.extend(&record[..8]);
+                table_directories.extend(((table_directories_start + table_directories_len + tables.len()) as u32).to_be_bytes());
+                table_directories.extend(&record[12..]);
+                tables.extend(&font[offset..(offset + length)]);
+                tables.resize(tables.len().div_ceil(4) * 4, 0);
+            }
+        }
+        [header, table_directories, tables].concat()
+    }
+
     #[test]
     fn main() {
         for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources")).unwrap() {
             let path = entry.unwrap().path();
-            if path.extension().unwrap() != "ttf" {
+            if !["ttf", "otf"].contains(&path.extension().unwrap().to_str().unwrap()) {
                 continue;
             }
             println!("{}", path.file_stem().unwrap().to_str().unwrap());
-            let font = crate::ttf_parser::parse_ttf(&fs::read(path).unwrap()).unwrap();
+            let font = parse_ttf(&fs::read(path).unwrap()).unwrap();
             for char_code in 0..font.char_code_to_glyph_index_map.len() {
                 assert!(font.char_code_to_glyph_index_map[char_code] <= font.glyphs.len());
             }
         }
     }
+
+    #[test]
+    fn it_rejects_invalid_fonts() {
+        let bytes = read_resource("Ubuntu-R.ttf");
+        assert!(parse_ttf(b"").is_err());
+        assert!(parse_ttf(b"not a font at all").is_err());
+        for len in [12, 100, 1000, bytes.len() / 2] {
+            assert!(parse_ttf(&bytes[..len]).is_err());
+        }
+        for name in ["DejaVuSansMono-Subset-CFF.otf", "DejaVuSansMono-Subset-CFF2.otf"] {
+            let bytes = read_resource(name);
+            for len in [1000, bytes.len() / 2, bytes.len() - 100] {
+                assert!(parse_ttf(&bytes[..len]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_out_of_range_glyph_offsets() {
+        let mut bytes = read_resource("Ubuntu-R.ttf");
+        let table_count = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        let record = (0..table_count).map(|index| 12 + index * 16).find(|&record| &bytes[record..(record + 4)] == b"loca");
+        let record = record.unwrap();
+        let offset = u32::from_be_bytes(bytes[(record + 8)..(record + 12)].try_into().unwrap()) as usize;
+        let length = u32::from_be_bytes(bytes[(record + 12)..(record + 16)].try_into().unwrap()) as usize;
+        // Make the last glyphs end before they start.
+        bytes[(offset + length - 4)..(offset + length)].fill(0);
+        assert!(parse_ttf(&bytes).is_err());
+    }
+
+    #[test]
+    fn it_maps_out_of_range_glyph_indices_to_the_missing_glyph() {
+        let mut font = parse_ttf(&read_resource("DejaVuSansMono.ttf")).unwrap();
+        let glyph_index = font.char_to_glyph_index('A').min(font.char_to_glyph_index('\u{1D670}'));
+        assert_ne!(glyph_index, 0);
+        font.glyphs.truncate(glyph_index);
+        assert_eq!(font.char_to_glyph_index('A'), 0);
+        assert_eq!(font.char_to_glyph_index('\u{1D670}'), 0);
+    }
+
+    #[test]
+    fn it_maps_the_ends_of_cmap_format_4_segments() {
+        // '~' is the last character of a segment in these fonts.
+        for name in ["Ubuntu-R.ttf", "LiberationMono-Regular.ttf"] {
+            let font = parse_ttf(&read_resource(name)).unwrap();
+            assert_ne!(font.char_to_glyph_index('~'), 0);
+            assert_ne!(font.char_to_glyph_index('}'), font.char_to_glyph_index('~'));
+        }
+    }
+
+    #[test]
+    fn it_maps_characters_outside_the_basic_multilingual_plane() {
+        // DejaVu Sans Mono has a format 12 subtable, which includes mathematical monospace letters and digits.
+        let font = parse_ttf(&read_resource("DejaVuSansMono.ttf")).unwrap();
+        let monospace_a = font.char_to_glyph_index('\u{1D670}');
+        assert_ne!(monospace_a, 0);
+        assert_eq!(font.char_to_glyph_index('\u{1D671}'), monospace_a + 1);
+        assert_ne!(font.char_to_glyph_index('\u{1D7F6}'), 0);
+        assert_ne!(font.char_to_glyph_index('A'), 0);
+        assert_ne!(font.char_to_glyph_index('\u{1D670}'), font.char_to_glyph_index('A'));
+        // Characters in between groups, or past the last one, don't have glyphs.
+        assert_eq!(font.char_to_glyph_index('\u{1D6A4}'), 0);
+        assert_eq!(font.char_to_glyph_index('\u{1F600}'), 0);
+        assert_eq!(font.char_to_glyph_index('\u{10FFFF}'), 0);
+    }
+
+    #[test]
+    fn it_parses_cff_outlines() {
+        // These fonts are subsets of DejaVuSansMono.ttf with the same outlines as cubic curves, made by
+        // `scripts/make_cff_test_fonts.py`.
+        //
+        // TODO: Since that script and this parser could share the same misreading of the spec, replace these
+        // with real CFF and CFF2 (variable) fonts made by standard font tools, and remove the script.
+        let ttf_font = parse_ttf(&read_resource("DejaVuSansMono.ttf")).unwrap();
+        let chars = ('\u{20}'..='\u{7E}').chain('\u{1D670}'..='\u{1D6A3}').chain('\u{1D7F6}'..='\u{1D7FF}');
+        for name in ["DejaVuSansMono-Subset-CFF.otf", "DejaVuSansMono-Subset-CFF2.otf"] {
+            let font = parse_ttf(&read_resource(name)).unwrap_or_else(|err| panic!("{}: {:?}", name, err));
+            assert_eq!(font.glyphs.len(), 158, "{}", name);
+            assert_eq!(font.units_per_em, ttf_font.units_per_em, "{}", name);
+            for c in chars.clone() {
+                let (glyph_index, ttf_glyph_index) = (font.char_to_glyph_index(c), ttf_font.char_to_glyph_index(c));
+                assert_ne!(glyph_index, 0, "{}: {:?}", name, c);
+                let (glyph, ttf_glyph) = (&font.glyphs[glyph_index], &ttf_font.glyphs[ttf_glyph_index]);
+                assert_eq!(glyph.horizontal_metrics, ttf_glyph.horizontal_metrics, "{}: {:?}", name, c);
+                let (commands, ttf_commands) = (glyph_commands(&font, glyph_index), glyph_commands(&ttf_font, ttf_glyph_index));
+                assert_eq!(commands.len(), ttf_commands.len(), "{}: {:?}", name, c);
+                let is_close = |a: Point, b: Point| (a - b).length() < 0.01;
+                for (command, ttf_command) in commands.into_iter().zip(ttf_commands) {
+                    assert!(
+                        match (command, ttf_command) {
+                            (PathCommand::MoveTo(p), PathCommand::MoveTo(ttf_p)) => is_close(p, ttf_p),
+                            (PathCommand::LineTo(p), PathCommand::LineTo(ttf_p)) => is_close(p, ttf_p),
+                            (PathCommand::QuadraticTo(p1, p), PathCommand::QuadraticTo(ttf_p1, ttf_p)) => {
+                                is_close(p1, ttf_p1) && is_close(p, ttf_p)
+                            }
+                            (PathCommand::Close, PathCommand::Close) => true,
+                            _ => false,
+                        },
+                        "{}: {:?}: {:?} != {:?}",
+                        name,
+                        c,
+                        command,
+                        ttf_command
+                    );
+                }
+                let bounds = (glyph.bounds.p_min - ttf_glyph.bounds.p_min, glyph.bounds.p_max - ttf_glyph.bounds.p_max);
+                assert!(bounds.0.length() <= 1. && bounds.1.length() <= 1., "{}: {:?}", name, c);
+            }
+        }
+    }
+
+    #[test]
+    fn it_parses_kerning() {
+        let bytes = read_resource("Ubuntu-R.ttf");
+        let font = parse_ttf(&bytes).unwrap();
+        let kerning = |pair: &str| {
+            let glyph_indices: Vec<usize> = pair.chars().map(|c| font.char_to_glyph_index(c)).collect();
+            font.kerning(glyph_indices[0], glyph_indices[1])
+        };
+        // From the `GPOS` table, both from pair adjustments for individual glyphs and for classes of glyphs.
+        assert_eq!(kerning("AV"), -62.);
+        assert_eq!(kerning("LT"), -115.);
+        assert_eq!(kerning("r."), -55.);
+        assert_eq!(kerning("HH"), 0.);
+        // The `kern` table of this font has the same values, but is only used if there is no `GPOS` table.
+        let kern_lookups = crate::ttf_parser::layout::parse_kern_table(&table_bytes(&bytes, b"kern")).unwrap();
+        let (a, v) = (font.char_to_glyph_index('A'), font.char_to_glyph_index('V'));
+        assert_eq!(kern_lookups.iter().filter_map(|lookup| lookup.get(a, v)).sum::<f32>(), -62.);
+        // Fonts without kerning.
+        let font = parse_ttf(&read_resource("LiberationMono-Regular.ttf")).unwrap();
+        assert_eq!(font.kerning(font.char_to_glyph_index('A'), font.char_to_glyph_index('V')), 0.);
+    }
+
+    #[test]
+    fn it_parses_ligatures() {
+        let font = parse_ttf(&read_resource("Ubuntu-R.ttf")).unwrap();
+        let glyph_indices = |text: &str| text.chars().map(|c| font.char_to_glyph_index(c)).collect::<Vec<_>>();
+        let fi = font.apply_ligatures(&glyph_indices("fi"));
+        assert_eq!(fi.len(), 1);
+        assert_eq!(fi[0].1, 2);
+        assert!(!glyph_indices("fi").contains(&fi[0].0));
+        // The longest ligature comes first.
+        let ffix = font.apply_ligatures(&glyph_indices("ffix"));
+        assert_eq!(ffix.iter().map(|&(_, count)| count).collect::<Vec<_>>(), vec![3, 1]);
+        assert_eq!(
+            font.apply_ligatures(&glyph_indices("hello")),
+            glyph_indices("hello").into_iter().map(|glyph_index| (glyph_index, 1)).collect::<Vec<_>>()
+        );
+
+        // DejaVu Sans Mono has no Latin ligatures, but it does have required Arabic ligatures, like lam-alef.
+        let font = parse_ttf(&read_resource("DejaVuSansMono.ttf")).unwrap();
+        assert_eq!(font.apply_ligatures(&glyph_indices("fi")).len(), 2);
+        assert!(!font.ligature_lookups.is_empty());
+        let font = parse_ttf(&read_resource("LiberationMono-Regular.ttf")).unwrap();
+        assert!(font.ligature_lookups.is_empty());
+    }
+
+    #[test]
+    fn it_parses_collections() {
+        let ubuntu_bytes = read_resource("Ubuntu-R.ttf");
+        let liberation_mono_bytes = read_resource("LiberationMono-Regular.ttf");
+        let collection_bytes = make_collection(&[&ubuntu_bytes, &liberation_mono_bytes]);
+        assert_eq!(parse_ttc(&collection_bytes, 0).unwrap(), parse_ttf(&ubuntu_bytes).unwrap());
+        assert_eq!(parse_ttc(&collection_bytes, 1).unwrap(), parse_ttf(&liberation_mono_bytes).unwrap());
+        assert!(parse_ttc(&collection_bytes, 2).is_err());
+        // A single font is like a collection with one font.
+        assert_eq!(parse_ttc(&ubuntu_bytes, 0).unwrap(), parse_ttf(&ubuntu_bytes).unwrap());
+        assert!(parse_ttc(&ubuntu_bytes, 1).is_err());
+    }
 }
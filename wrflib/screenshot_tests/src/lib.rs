@@ -0,0 +1,326 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Golden-image ("screenshot") tests for Wrflib components.
+//!
+//! A [`ScreenshotTest`] renders a [`ScreenshotApp`] using the `headless` platform of Wrflib (which
+//! uses a software renderer, so no GPU or display server is needed), optionally sends it some
+//! scripted [`Event`]s (or an [`EventRecording`], like the ones under `recordings/`), and then
+//! compares the final contents of the window with a PNG image that is checked in under
+//! `screenshots/`.
+//!
+//! When a test fails, the actual image and a diff image (mismatching pixels in red) are written
+//! to `screenshots/failures/`. To accept the new images as the golden images, run the tests with
+//! `WRFLIB_UPDATE_SCREENSHOTS=1`.
+
+use std::any::TypeId;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use wrflib::*;
+
+/// Set this environment variable to overwrite the golden images with the actual images.
+const UPDATE_SCREENSHOTS_ENV_VAR: &str = "WRFLIB_UPDATE_SCREENSHOTS";
+
+/// An app that can be rendered by a [`ScreenshotTest`].
+///
+/// The [`Window`], [`Pass`], and [`View`] are set up by the test, so [`ScreenshotApp::draw`]
+/// only has to draw its components into the current [`View`].
+pub trait ScreenshotApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event);
+    fn draw(&mut self, cx: &mut Cx);
+}
+
+/// A step in the script of a [`ScreenshotTest`].
+#[derive(Clone, Debug)]
+pub enum ScreenshotStep {
+    /// Send events to the app, and draw and paint until there is nothing left to do.
+    Events(Vec<Event>),
+    /// Advance time by this many seconds, e.g. to let animations finish.
+    AdvanceTime(f64),
+    /// Replay an [`EventRecording`], e.g. one loaded using [`EventRecording::read_from_file`].
+    Replay(EventRecording),
+}
+
+/// Describes how to render an app, and which golden image to compare it with.
+pub struct ScreenshotTest {
+    /// Name of the golden image, without the `.png` extension.
+    pub name: &'static str,
+    /// Inner size of the window.
+    pub size: Vec2,
+    pub background_color: Vec4,
+    /// Script that gets run after the app has been drawn for the first time.
+    pub steps: Vec<ScreenshotStep>,
+    /// Maximum difference per color channel (0-255) for a pixel to still be considered equal.
+    pub tolerance: u8,
+    /// Maximum number of pixels that can exceed [`ScreenshotTest::tolerance`] for the test to
+    /// still pass.
+    pub max_mismatched_pixels: usize,
+}
+
+impl Default for ScreenshotTest {
+    fn default() -> Self {
+        Self {
+            name: "",
+            size: vec2(400., 300.),
+            background_color: Vec4::color("333"),
+            steps: vec![],
+            tolerance: 2,
+            max_mismatched_pixels: 0,
+        }
+    }
+}
+
+impl ScreenshotTest {
+    /// Renders `app`, and returns the final contents of the window.
+    pub fn render<A: ScreenshotApp + 'static>(&self, app: &mut A) -> Image {
+        let mut cx = Cx::new(TypeId::of::<A>());
+        let mut window = Window { create_inner_size: Some(self.size), ..Window::default() };
+        let mut pass = Pass::default();
+        let mut view = View::default();
+        let mut cxafterdraw = CxAfterDraw::new(&mut cx);
+        let background_color = self.background_color;
+
+        let mut event_handler = |cx: &mut Cx, event: &mut Event| match event {
+            Event::SystemEvent(SystemEvent::Draw) => {
+                window.begin_window(cx);
+                pass.begin_pass(cx, background_color);
+                view.begin_view(cx, LayoutSize::FILL);
+                app.draw(cx);
+                view.end_view(cx);
+                pass.end_pass(cx);
+                window.end_window(cx);
+                cxafterdraw.after_draw(cx);
+            }
+            Event::SystemEvent(_) => {}
+            _ => app.handle(cx, event),
+        };
+
+        cx.headless_process_events(&mut event_handler, vec![]);
+        for step in &self.steps {
+            match step {
+                ScreenshotStep::Events(events) => cx.headless_process_events(&mut event_handler, events.clone()),
+                ScreenshotStep::AdvanceTime(seconds) => cx.headless_advance_time(&mut event_handler, *seconds),
+                ScreenshotStep::Replay(recording) => cx.headless_replay_events(&mut event_handler, recording),
+            }
+        }
+
+        let window_id = window.window_id.expect("Window was never drawn");
+        let (width, height, pixels) = cx.headless_window_pixels(window_id).expect("Window was never painted");
+        Image { width, height, pixels: pixels.to_vec() }
+    }
+
+    /// Renders `app` and compares it with the golden image, panicking if they don't match.
+    pub fn run<A: ScreenshotApp + 'static>(&self, app: &mut A) {
+        let actual = self.render(app);
+        let golden_path = screenshots_dir().join(format!("{}.png", self.name));
+
+        if std::env::var_os(UPDATE_SCREENSHOTS_ENV_VAR).is_some() {
+            actual.write_png(&golden_path).unwrap();
+            return;
+        }
+
+        let failures_dir = screenshots_dir().join("failures");
+        let actual_path = failures_dir.join(format!("{}.actual.png", self.name));
+        let diff_path = failures_dir.join(format!("{}.diff.png", self.name));
+
+        let expected = match Image::read_png(&golden_path) {
+            Ok(expected) => expected,
+            Err(err) => {
+                actual.write_png(&actual_path).unwrap();
+                panic!(
+                    "Could not read golden image {:?} ({}); wrote actual image to {:?}. Run with {}=1 to create it.",
+                    golden_path, err, actual_path, UPDATE_SCREENSHOTS_ENV_VAR
+                );
+            }
+        };
+
+        let comparison = compare_images(&expected, &actual, self.tolerance);
+        if comparison.mismatched_pixels > self.max_mismatched_pixels {
+            actual.write_png(&actual_path).unwrap();
+            comparison.diff.write_png(&diff_path).unwrap();
+            panic!(
+                "Screenshot {:?} has {} mismatched pixels (max allowed: {}, max channel difference: {}); wrote actual image to \
+                 {:?} and diff image to {:?}. Run with {}=1 to accept the new image.",
+                self.name,
+                comparison.mismatched_pixels,
+                self.max_mismatched_pixels,
+                comparison.max_difference,
+                actual_path,
+                diff_path,
+                UPDATE_SCREENSHOTS_ENV_VAR
+            );
+        }
+    }
+}
+
+fn screenshots_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("screenshots")
+}
+
+/// An RGBA image, stored row by row starting at the top left. Pixels are packed in the same way
+/// as in [`TextureHandle::get_image_mut`], so red is in the lowest byte.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Image {
+    pub fn read_png(path: &Path) -> Result<Image, String> {
+        let file = File::open(path).map_err(|err| err.to_string())?;
+        Self::decode_png(BufReader::new(file))
+    }
+
+    /// Decodes a PNG file, e.g. one from [`ChartEvent::Png`](wrflib_components::ChartEvent::Png).
+    pub fn decode_png<R: Read>(reader: R) -> Result<Image, String> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(|err| err.to_string())?;
+        if info.bit_depth != png::BitDepth::Eight {
+            return Err(format!("Unsupported bit depth: {:?}", info.bit_depth));
+        }
+        let bytes = &buf[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => bytes.chunks_exact(4).map(|p| u32::from_le_bytes([p[0], p[1], p[2], p[3]])).collect(),
+            png::ColorType::Rgb => bytes.chunks_exact(3).map(|p| u32::from_le_bytes([p[0], p[1], p[2], 255])).collect(),
+            color_type => return Err(format!("Unsupported color type: {:?}", color_type)),
+        };
+        Ok(Image { width: info.width as usize, height: info.height as usize, pixels })
+    }
+
+    /// The average position of the pixels with exactly `color` (e.g. `"f00"`, see [`Vec4::color`]), in pixels
+    /// from the top left, measured to the pixel centers. Useful for clicking on something that was drawn in that
+    /// color. Panics if no pixel has that color.
+    pub fn center_of_color(&self, color: &str) -> Vec2 {
+        let rgba = Vec4::color(color);
+        let [r, g, b] = [rgba.x, rgba.y, rgba.z].map(|channel| (channel * 255.).round() as u8);
+        let positions: Vec<Vec2> = (0..self.pixels.len())
+            .filter(|&index| self.pixels[index].to_le_bytes()[..3] == [r, g, b])
+            .map(|index| vec2((index % self.width) as f32 + 0.5, (index / self.width) as f32 + 0.5))
+            .collect();
+        assert!(!positions.is_empty(), "No pixels with color {}", color);
+        positions.iter().fold(Vec2::default(), |sum, &pos| sum + pos) / positions.len() as f32
+    }
+
+    pub fn write_png(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let file = File::create(path).map_err(|err| err.to_string())?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+        let bytes: Vec<u8> = self.pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+        writer.write_image_data(&bytes).map_err(|err| err.to_string())
+    }
+
+    fn get(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[y * self.width + x].to_le_bytes())
+        } else {
+            None
+        }
+    }
+}
+
+/// Result of [`compare_images`].
+pub struct ImageComparison {
+    /// Number of pixels that have a channel that differs more than the tolerance.
+    pub mismatched_pixels: usize,
+    /// Largest difference in any channel of any pixel.
+    pub max_difference: u8,
+    /// Mismatched pixels in red, on top of a faded grayscale version of the expected image.
+    pub diff: Image,
+}
+
+/// Compares two images pixel by pixel. If the sizes are different, then pixels that only exist in
+/// one of the images count as mismatched.
+pub fn compare_images(expected: &Image, actual: &Image, tolerance: u8) -> ImageComparison {
+    let width = expected.width.max(actual.width);
+    let height = expected.height.max(actual.height);
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let mut diff = Image { width, height, pixels: Vec::with_capacity(width * height) };
+
+    for y in 0..height {
+        for x in 0..width {
+            let (difference, faded) = match (expected.get(x, y), actual.get(x, y)) {
+                (Some(e), Some(a)) => {
+                    let difference = e.iter().zip(a.iter()).map(|(e, a)| e.abs_diff(*a)).max().unwrap();
+                    let luma = (e[0] as u32 * 3 + e[1] as u32 * 6 + e[2] as u32) / 10;
+                    (difference, 64 + luma / 4)
+                }
+                _ => (255, 0),
+            };
+            max_difference = max_difference.max(difference);
+            let pixel = if difference > tolerance {
+                mismatched_pixels += 1;
+                [255, 0, 0, 255]
+            } else {
+                [faded as u8, faded as u8, faded as u8, 255]
+            };
+            diff.pixels.push(u32::from_le_bytes(pixel));
+        }
+    }
+
+    ImageComparison { mismatched_pixels, max_difference, diff }
+}
+
+/// A [`Event::PointerHover`] at `pos` (in logical pixels relative to the window).
+pub fn pointer_hover(pos: Vec2) -> Event {
+    // Can't use struct update syntax here, since `PointerHoverEvent` has private fields.
+    let mut pointer_hover = PointerHoverEvent::default();
+    pointer_hover.abs = pos;
+    pointer_hover.rel = pos;
+    Event::PointerHover(pointer_hover)
+}
+
+/// A [`Event::PointerDown`] and [`Event::PointerUp`] at `pos` (in logical pixels relative to the
+/// window), with the left mouse button.
+pub fn click(pos: Vec2) -> Vec<Event> {
+    let mut pointer_down = PointerDownEvent::default();
+    pointer_down.abs = pos;
+    pointer_down.rel = pos;
+    pointer_down.button = MouseButton::Left;
+    pointer_down.tap_count = 1;
+    vec![
+        Event::PointerDown(pointer_down),
+        Event::PointerUp(PointerUpEvent {
+            abs: pos,
+            abs_start: pos,
+            rel: pos,
+            rel_start: pos,
+            button: MouseButton::Left,
+            is_over: true,
+            ..PointerUpEvent::default()
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_images_with_tolerance() {
+        let expected = Image { width: 2, height: 1, pixels: vec![0xff000000, 0xff102030] };
+        let actual = Image { width: 2, height: 1, pixels: vec![0xff000002, 0xff102050] };
+
+        let comparison = compare_images(&expected, &actual, 2);
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.max_difference, 0x20);
+        assert_eq!(comparison.diff.pixels[1], 0xff0000ff);
+
+        let comparison = compare_images(&expected, &Image { width: 1, height: 1, pixels: vec![0xff000000] }, 0);
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!((comparison.diff.width, comparison.diff.height), (2, 1));
+    }
+}
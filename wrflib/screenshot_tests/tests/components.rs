@@ -0,0 +1,732 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+use screenshot_tests::*;
+use wrflib::*;
+use wrflib_components::*;
+
+#[derive(Default)]
+struct ButtonApp {
+    button: Button,
+}
+
+impl ScreenshotApp for ButtonApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        self.button.handle(cx, event);
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        self.button.draw(cx, "Hello world");
+    }
+}
+
+#[test]
+fn button() {
+    ScreenshotTest { name: "button", size: vec2(160., 60.), ..ScreenshotTest::default() }.run(&mut ButtonApp::default());
+}
+
+#[test]
+fn button_hover() {
+    ScreenshotTest {
+        name: "button_hover",
+        size: vec2(160., 60.),
+        steps: vec![ScreenshotStep::Events(vec![pointer_hover(vec2(20., 20.))]), ScreenshotStep::AdvanceTime(1.)],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut ButtonApp::default());
+}
+
+#[derive(Default)]
+struct CheckboxApp {
+    checkboxes: Vec<(Checkbox, bool)>,
+}
+
+impl ScreenshotApp for CheckboxApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        for (checkbox, checked) in &mut self.checkboxes {
+            if let CheckboxEvent::Toggled = checkbox.handle(cx, event) {
+                *checked = !*checked;
+                cx.request_draw();
+            }
+        }
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        cx.begin_column(Width::Fill, Height::Fill);
+        for (index, (checkbox, checked)) in self.checkboxes.iter_mut().enumerate() {
+            checkbox.draw(cx, *checked, true, false, &format!("Checkbox {}", index), 0.);
+        }
+        cx.end_column();
+    }
+}
+
+#[test]
+fn checkbox() {
+    let mut app = CheckboxApp { checkboxes: vec![(Checkbox::default(), false), (Checkbox::default(), true)] };
+    ScreenshotTest {
+        name: "checkbox",
+        size: vec2(200., 80.),
+        // Let the labels fade in.
+        steps: vec![ScreenshotStep::AdvanceTime(1.)],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut app);
+}
+
+#[test]
+fn checkbox_toggle() {
+    let mut app = CheckboxApp { checkboxes: vec![(Checkbox::default(), false), (Checkbox::default(), true)] };
+    ScreenshotTest {
+        name: "checkbox_toggle",
+        size: vec2(200., 80.),
+        steps: vec![
+            ScreenshotStep::AdvanceTime(1.),
+            ScreenshotStep::Events(click(vec2(15., 15.))),
+            ScreenshotStep::AdvanceTime(1.),
+        ],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut app);
+}
+
+#[test]
+fn checkbox_replay() {
+    let recording = EventRecording::from_text(include_str!("../recordings/checkbox_toggle.txt")).unwrap();
+    let mut app = CheckboxApp { checkboxes: vec![(Checkbox::default(), false), (Checkbox::default(), true)] };
+    ScreenshotTest {
+        name: "checkbox_replay",
+        size: vec2(200., 80.),
+        steps: vec![ScreenshotStep::AdvanceTime(1.), ScreenshotStep::Replay(recording)],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut app);
+}
+
+/// Records its events from [`Event::Construct`] until [`Event::AppFocusLost`].
+struct CheckboxRecordingApp {
+    app: CheckboxApp,
+    recording: Option<EventRecording>,
+}
+
+impl ScreenshotApp for CheckboxRecordingApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        match event {
+            Event::Construct => cx.start_event_recording(),
+            Event::AppFocusLost => self.recording = Some(cx.stop_event_recording()),
+            _ => {}
+        }
+        self.app.handle(cx, event);
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        self.app.draw(cx);
+    }
+}
+
+#[test]
+fn checkbox_record_and_replay() {
+    let checkboxes = || vec![(Checkbox::default(), false), (Checkbox::default(), true)];
+    let mut recording_app = CheckboxRecordingApp { app: CheckboxApp { checkboxes: checkboxes() }, recording: None };
+    ScreenshotTest {
+        size: vec2(200., 80.),
+        steps: vec![
+            ScreenshotStep::AdvanceTime(1.),
+            ScreenshotStep::Events(click(vec2(15., 15.))),
+            ScreenshotStep::AdvanceTime(1.),
+            ScreenshotStep::Events(vec![Event::AppFocusLost]),
+        ],
+        ..ScreenshotTest::default()
+    }
+    .render(&mut recording_app);
+    let text = recording_app.recording.unwrap().to_text();
+    assert!(text.contains("PointerDown") && text.contains("PointerUp"), "{}", text);
+
+    // Replaying the recording should end up in the same state as `checkbox_toggle`.
+    let mut app = CheckboxApp { checkboxes: checkboxes() };
+    ScreenshotTest {
+        name: "checkbox_toggle",
+        size: vec2(200., 80.),
+        steps: vec![ScreenshotStep::Replay(EventRecording::from_text(&text).unwrap()), ScreenshotStep::AdvanceTime(1.)],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut app);
+    assert_eq!(app.checkboxes.iter().map(|&(_, checked)| checked).collect::<Vec<_>>(), vec![true, true]);
+}
+
+/// Clicks the checkboxes from another thread, using [`Cx::send_event_from_any_thread`], whenever it gets hovered.
+struct CheckboxThreadApp {
+    app: CheckboxApp,
+}
+
+impl ScreenshotApp for CheckboxThreadApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Event::PointerHover(pe) = event {
+            let pos = pe.abs;
+            std::thread::spawn(move || click(pos).into_iter().for_each(Cx::send_event_from_any_thread)).join().unwrap();
+        }
+        self.app.handle(cx, event);
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        self.app.draw(cx);
+    }
+}
+
+#[test]
+fn checkbox_toggle_from_thread() {
+    let mut app =
+        CheckboxThreadApp { app: CheckboxApp { checkboxes: vec![(Checkbox::default(), false), (Checkbox::default(), true)] } };
+    let test = ScreenshotTest {
+        size: vec2(200., 80.),
+        steps: vec![
+            ScreenshotStep::AdvanceTime(1.),
+            ScreenshotStep::Events(vec![pointer_hover(vec2(15., 15.))]),
+            ScreenshotStep::AdvanceTime(1.),
+        ],
+        ..ScreenshotTest::default()
+    };
+    test.render(&mut app);
+    assert_eq!(app.app.checkboxes.iter().map(|&(_, checked)| checked).collect::<Vec<_>>(), vec![true, true]);
+}
+
+struct TabControlApp {
+    tab_control: TabControl,
+    selected: usize,
+}
+
+impl ScreenshotApp for TabControlApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let TabControlEvent::TabSelect { tab_id } = self.tab_control.handle_tab_control(cx, event) {
+            self.selected = tab_id;
+            cx.request_draw();
+        }
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        cx.begin_row(Width::Fill, Height::Fill);
+        self.tab_control.begin_tabs(cx);
+        for (index, label) in ["First", "Second", "Third"].iter().enumerate() {
+            self.tab_control.draw_tab(cx, label, index == self.selected, index == 2);
+        }
+        self.tab_control.end_tabs(cx);
+        self.tab_control.begin_tab_page(cx);
+        self.tab_control.end_tab_page(cx);
+        cx.end_row();
+    }
+}
+
+#[test]
+fn tab_control() {
+    ScreenshotTest {
+        name: "tab_control",
+        size: vec2(300., 80.),
+        steps: vec![ScreenshotStep::Events(click(vec2(90., 10.))), ScreenshotStep::AdvanceTime(1.)],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut TabControlApp { tab_control: TabControl::new(), selected: 0 });
+}
+
+#[derive(Default)]
+struct ChartApp {
+    chart: Chart,
+    chart_type: ChartType,
+    stacked: bool,
+}
+
+impl ChartApp {
+    fn new(chart_type: ChartType, stacked: bool) -> Self {
+        Self { chart: Chart::default(), chart_type, stacked }
+    }
+}
+
+impl ScreenshotApp for ChartApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        self.chart.handle(cx, event);
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let values = [2., 5., 3., 8., 6., 9., 4.];
+        let pairs = [vec2(0., 7.), vec2(2., 1.), vec2(4., 6.), vec2(6., 2.)];
+        // Datasets are stacked by datum index, so use values that line up with the first dataset.
+        let stacked_values = [3., 1., 4., 1., 5., 2., 6.];
+        let pair_colors = [COLOR_BLUE, COLOR_GREEN, COLOR_PURPLE, COLOR_ORANGE];
+        let pair_radii = [6., 10., 14., 18.];
+        let config = ChartConfig {
+            chart_type: self.chart_type,
+            stacked: self.stacked,
+            labels: (0..values.len()).map(|index| format!("{}", index)).collect(),
+            datasets: vec![
+                ChartDataset {
+                    label: "Values".to_string(),
+                    data: ChartData::from_values(&values),
+                    background_color: vec4(1., 0., 0., 0.5),
+                    border_color: COLOR_RED,
+                    point_background_color: COLOR_RED,
+                    point_radius: 6.,
+                    ..ChartDataset::default()
+                },
+                ChartDataset {
+                    label: "Pairs".to_string(),
+                    data: if self.stacked { ChartData::from_values(&stacked_values) } else { ChartData::from_pairs(&pairs) },
+                    background_color: vec4(0., 0., 1., 0.5),
+                    border_color: COLOR_BLUE,
+                    point_background_color: COLOR_BLUE,
+                    point_background_colors: if self.chart_type == ChartType::Scatter { &pair_colors } else { &[] },
+                    point_radius: 6.,
+                    point_radii: if self.chart_type == ChartType::Scatter { &pair_radii } else { &[] },
+                    ..ChartDataset::default()
+                },
+            ],
+            style: CHART_STYLE_LIGHT,
+            ..ChartConfig::default()
+        };
+        self.chart.draw(cx, &config);
+    }
+}
+
+#[test]
+fn chart() {
+    ScreenshotTest { name: "chart", size: vec2(400., 300.), tolerance: 4, ..ScreenshotTest::default() }
+        .run(&mut ChartApp::default());
+}
+
+#[test]
+fn chart_bar() {
+    ScreenshotTest { name: "chart_bar", size: vec2(400., 300.), tolerance: 4, ..ScreenshotTest::default() }
+        .run(&mut ChartApp::new(ChartType::Bar, false));
+}
+
+#[test]
+fn chart_bar_stacked() {
+    ScreenshotTest { name: "chart_bar_stacked", size: vec2(400., 300.), tolerance: 4, ..ScreenshotTest::default() }
+        .run(&mut ChartApp::new(ChartType::Bar, true));
+}
+
+#[test]
+fn chart_scatter() {
+    ScreenshotTest { name: "chart_scatter", size: vec2(400., 300.), tolerance: 4, ..ScreenshotTest::default() }
+        .run(&mut ChartApp::new(ChartType::Scatter, false));
+}
+
+#[test]
+fn chart_area_stacked() {
+    ScreenshotTest { name: "chart_area_stacked", size: vec2(400., 300.), tolerance: 4, ..ScreenshotTest::default() }
+        .run(&mut ChartApp::new(ChartType::Area, true));
+}
+
+#[derive(Default)]
+struct TimeSeriesChartApp {
+    chart: Chart,
+}
+
+impl ScreenshotApp for TimeSeriesChartApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        self.chart.handle(cx, event);
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        // Nanosecond timestamps every 250ms, starting at 2021-10-12 01:46:40 UTC.
+        let times: Vec<f64> = (0..12).map(|index| 1_634_003_200e9 + index as f64 * 250e6).collect();
+        let values = [2., 5., 3., 8., 6., 9., 4., 7., 5., 6., 3., 4.];
+        let config = ChartConfig {
+            datasets: vec![ChartDataset {
+                label: "Values".to_string(),
+                data: ChartData::from_time_series(&times, &values),
+                border_color: COLOR_RED,
+                point_background_color: COLOR_RED,
+                point_radius: 6.,
+                ..ChartDataset::default()
+            }],
+            time_axis: Some(ChartTimeAxis { unit: ChartTimeUnit::Nanoseconds, ..ChartTimeAxis::default() }),
+            style: CHART_STYLE_LIGHT,
+            ..ChartConfig::default()
+        };
+        self.chart.draw(cx, &config);
+    }
+}
+
+#[test]
+fn chart_time_series() {
+    ScreenshotTest {
+        name: "chart_time_series",
+        size: vec2(400., 300.),
+        tolerance: 4,
+        steps: vec![ScreenshotStep::Events(vec![pointer_hover(vec2(230., 150.))])],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut TimeSeriesChartApp::default());
+}
+
+struct LargeChartApp {
+    chart: Chart,
+    values: Vec<f32>,
+}
+
+impl ScreenshotApp for LargeChartApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        self.chart.handle(cx, event);
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let config = ChartConfig {
+            datasets: vec![ChartDataset {
+                label: "Values".to_string(),
+                data: ChartData::from_values(&self.values),
+                border_color: COLOR_RED,
+                point_radius: 0.,
+                ..ChartDataset::default()
+            }],
+            style: CHART_STYLE_LIGHT,
+            ..ChartConfig::default()
+        };
+        self.chart.draw(cx, &config);
+    }
+}
+
+#[test]
+fn chart_large_dataset() {
+    // Noisy sine wave, with way more values than pixels so it gets decimated.
+    let values = (0..1_000_000_u64).map(|index| 40. * (index as f32 * 1e-5).sin() + (index * 7919 % 100) as f32 * 0.2).collect();
+    ScreenshotTest {
+        name: "chart_large_dataset",
+        size: vec2(400., 300.),
+        tolerance: 4,
+        steps: vec![ScreenshotStep::Events(vec![pointer_hover(vec2(200., 100.))])],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut LargeChartApp { chart: Chart::default(), values });
+}
+
+#[derive(Default)]
+struct MultipleAxesChartApp {
+    chart: Chart,
+}
+
+impl ScreenshotApp for MultipleAxesChartApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        self.chart.handle(cx, event);
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let velocity = [0., 4., 9., 15., 22., 28., 31., 32., 30., 25.];
+        let acceleration = [4., 5., 6., 7., 6., 3., 1., -2., -5., -6.];
+        let config = ChartConfig {
+            datasets: vec![
+                ChartDataset {
+                    label: "Velocity".to_string(),
+                    data: ChartData::from_values(&velocity),
+                    border_color: COLOR_RED,
+                    point_background_color: COLOR_RED,
+                    point_radius: 6.,
+                    y_axis_id: "velocity",
+                    ..ChartDataset::default()
+                },
+                ChartDataset {
+                    label: "Acceleration".to_string(),
+                    data: ChartData::from_values(&acceleration),
+                    border_color: COLOR_BLUE,
+                    point_background_color: COLOR_BLUE,
+                    point_radius: 6.,
+                    y_axis_id: "acceleration",
+                    ..ChartDataset::default()
+                },
+            ],
+            style: CHART_STYLE_LIGHT,
+            zoom_enabled: true,
+            ..ChartConfig::default()
+        };
+        self.chart.draw(cx, &config);
+    }
+}
+
+#[test]
+fn chart_multiple_axes() {
+    ScreenshotTest { name: "chart_multiple_axes", size: vec2(400., 300.), tolerance: 4, ..ScreenshotTest::default() }
+        .run(&mut MultipleAxesChartApp::default());
+}
+
+#[test]
+fn chart_multiple_axes_zoom() {
+    // Scrolling over the labels of the right axis only zooms that axis.
+    let pos = vec2(360., 130.);
+    ScreenshotTest {
+        name: "chart_multiple_axes_zoom",
+        size: vec2(400., 300.),
+        tolerance: 4,
+        steps: vec![ScreenshotStep::Events(vec![Event::PointerScroll(PointerScrollEvent {
+            abs: pos,
+            rel: pos,
+            scroll: vec2(0., -100.),
+            ..PointerScrollEvent::default()
+        })])],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut MultipleAxesChartApp::default());
+}
+
+struct LegendChartApp {
+    chart: Chart,
+    position: ChartLegendPosition,
+}
+
+impl ScreenshotApp for LegendChartApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        self.chart.handle(cx, event);
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let values = [[2., 5., 3., 8., 6., 9.], [4., 3., 6., 5., 8., 7.], [1., 2., 2., 4., 3., 5.]];
+        let colors = [COLOR_RED, COLOR_GREEN, COLOR_BLUE];
+        let config = ChartConfig {
+            datasets: values
+                .iter()
+                .zip(colors)
+                .enumerate()
+                .map(|(index, (values, color))| ChartDataset {
+                    label: format!("Dataset {}", index),
+                    data: ChartData::from_values(values),
+                    border_color: color,
+                    point_background_color: color,
+                    ..ChartDataset::default()
+                })
+                .collect(),
+            legend: ChartLegendConfig { display: true, position: self.position },
+            style: CHART_STYLE_LIGHT,
+            ..ChartConfig::default()
+        };
+        self.chart.draw(cx, &config);
+    }
+}
+
+#[test]
+fn chart_legend() {
+    ScreenshotTest { name: "chart_legend", size: vec2(400., 300.), tolerance: 4, ..ScreenshotTest::default() }
+        .run(&mut LegendChartApp { chart: Chart::default(), position: ChartLegendPosition::Top });
+}
+
+#[test]
+fn chart_legend_right() {
+    ScreenshotTest { name: "chart_legend_right", size: vec2(400., 300.), tolerance: 4, ..ScreenshotTest::default() }
+        .run(&mut LegendChartApp { chart: Chart::default(), position: ChartLegendPosition::Right });
+}
+
+#[test]
+fn chart_legend_toggle() {
+    // Hide the second dataset, and then highlight the first one.
+    let mut events = click(vec2(200., 18.));
+    events.push(pointer_hover(vec2(120., 18.)));
+    ScreenshotTest {
+        name: "chart_legend_toggle",
+        size: vec2(400., 300.),
+        tolerance: 4,
+        steps: vec![ScreenshotStep::Events(events)],
+        ..ScreenshotTest::default()
+    }
+    .run(&mut LegendChartApp { chart: Chart::default(), position: ChartLegendPosition::Top });
+}
+
+/// Exports the chart on the first pointer event, after it has been drawn. The PNG file arrives
+/// as a [`ChartEvent::Png`] after the chart's pass gets read back.
+#[derive(Default)]
+struct ExportChartApp {
+    chart: ChartApp,
+    svg: Option<String>,
+    png: Option<Result<Vec<u8>, String>>,
+}
+
+impl ScreenshotApp for ExportChartApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Event::PointerHover(_) = event {
+            if self.svg.is_none() {
+                self.svg = Some(self.chart.chart.to_svg());
+                self.chart.chart.request_png(cx);
+            }
+        }
+        if let ChartEvent::Png(png) = self.chart.chart.handle(cx, event) {
+            assert!(self.png.is_none());
+            self.png = Some(png);
+        }
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        self.chart.draw(cx);
+    }
+}
+
+#[test]
+fn chart_export() {
+    // Pointer events only get sent after the chart has been drawn.
+    let mut app = ExportChartApp { chart: ChartApp::new(ChartType::Area, false), ..ExportChartApp::default() };
+    let test = ScreenshotTest {
+        size: vec2(400., 300.),
+        steps: vec![ScreenshotStep::Events(vec![pointer_hover(vec2(5., 5.))])],
+        ..ScreenshotTest::default()
+    };
+    test.render(&mut app);
+
+    let svg = app.svg.unwrap();
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300""#));
+    assert!(svg.contains("<polygon points=") && svg.contains(r##"fill="#ff0000" fill-opacity="0.5""##));
+    assert!(svg.contains(r#"<path d="M"#) && svg.contains(r##"stroke="#0000ff""##));
+    assert!(svg.contains(">10</text>"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+
+    // The chart fills the whole window, so it should look the same as the window after the hover.
+    let window = test.render(&mut ChartApp::new(ChartType::Area, false));
+    let png = Image::decode_png(app.png.unwrap().unwrap().as_slice()).unwrap();
+    assert_eq!((png.width, png.height), (window.width, window.height));
+    assert_eq!(compare_images(&window, &png, 4).mismatched_pixels, 0);
+}
+
+/// Draws two sets of points into a [`Viewport3D`] with picking enabled, and collects the picks of every pointer down.
+#[derive(Default)]
+struct ViewportPickingApp {
+    viewport: Viewport3D,
+    areas: Vec<Area>,
+    picks: Vec<Option<Viewport3DPick>>,
+}
+
+impl ViewportPickingApp {
+    const POINTS: [&'static [(Vec3, &'static str)]; 2] =
+        [&[(vec3(-10., 0., 0.), "f00"), (vec3(10., 0., 0.), "0f0")], &[(vec3(0., 0., -10.), "00f")]];
+}
+
+impl ScreenshotApp for ViewportPickingApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Viewport3DEvent::Pick(picks) = self.viewport.handle(cx, event) {
+            self.picks.extend(picks.iter().map(|pointer_pick| pointer_pick.pick));
+        }
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let options = DrawPoints3dOptions { point_style: DrawPoints3dStyle::Circle, ..DrawPoints3dOptions::default() };
+        let data: Vec<Vec<DrawPoints3dInstance>> = Self::POINTS
+            .iter()
+            .map(|points| {
+                points
+                    .iter()
+                    .map(|&(position, color)| DrawPoints3dInstance {
+                        position,
+                        color: Vec4::color(color).to_vec3(),
+                        size: 8.,
+                        user_info: Vec2::default(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        self.viewport.begin_draw(cx, Viewport3DProps { picking_enabled: true, ..Viewport3DProps::DEFAULT });
+        self.areas = data.iter().map(|points| DrawPoints3d::draw(cx, points, options.clone())).collect();
+        self.viewport.end_draw(cx);
+
+        if self.viewport.begin_picking_draw(cx) {
+            for (points, area) in data.iter().zip(&self.areas) {
+                DrawPoints3d::draw_picking(cx, &mut self.viewport, *area, points, options.clone());
+            }
+            self.viewport.end_picking_draw(cx);
+        }
+    }
+}
+
+#[test]
+fn viewport_picking() {
+    let test = ScreenshotTest { name: "viewport_picking", size: vec2(100., 100.), ..ScreenshotTest::default() };
+    let image = test.render(&mut ViewportPickingApp::default());
+
+    // Click the center of each point, and then the background.
+    let clicks = [image.center_of_color("f00"), image.center_of_color("0f0"), image.center_of_color("00f"), vec2(2., 2.)];
+
+    let steps = clicks.iter().map(|&pos| ScreenshotStep::Events(click(pos))).collect();
+    let mut app = ViewportPickingApp::default();
+    ScreenshotTest { steps, ..test }.run(&mut app);
+
+    let pick = |points: usize, instance_index: usize| Some(Viewport3DPick { area: app.areas[points], instance_index });
+    assert_eq!(app.picks, vec![pick(0, 0), pick(0, 1), pick(1, 0), None]);
+}
+
+#[repr(C)]
+struct MeshInstance {
+    transform: Mat4,
+    color: Vec3,
+}
+
+static MESH_SHADER: Shader = Shader {
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        Geometry3d::SHADER,
+        code_fragment!(
+            r#"
+            instance in_transform: mat4;
+            instance in_color: vec3;
+
+            fn vertex() -> vec4 {
+                return camera_projection * camera_view * in_transform * vec4(geom_pos, 1.);
+            }
+
+            fn pixel() -> vec4 {
+                return vec4(in_color, 1.);
+            }
+            "#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+register_shader!(MESH_SHADER);
+
+#[derive(Default)]
+struct ViewportMeshPickingApp {
+    viewport: Viewport3D,
+    cube: Option<GpuGeometry>,
+    area: Area,
+    picks: Vec<Option<Viewport3DPick>>,
+}
+
+impl ViewportMeshPickingApp {
+    const CUBES: [(Vec3, &'static str); 2] = [(vec3(-10., 0., 0.), "f00"), (vec3(10., 0., 5.), "0f0")];
+}
+
+impl ScreenshotApp for ViewportMeshPickingApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Viewport3DEvent::Pick(picks) = self.viewport.handle(cx, event) {
+            self.picks.extend(picks.iter().map(|pointer_pick| pointer_pick.pick));
+        }
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let cube = self.cube.get_or_insert_with(|| GpuGeometry::new(cx, Geometry3d::cube(8., 8., 8., 1, 1, 1))).clone();
+        let transforms: Vec<Mat4> =
+            Self::CUBES.iter().map(|&(position, _)| Mat4::translation(position.x, position.y, position.z)).collect();
+        let instances: Vec<MeshInstance> = Self::CUBES
+            .iter()
+            .zip(&transforms)
+            .map(|(&(_, color), &transform)| MeshInstance { transform, color: Vec4::color(color).to_vec3() })
+            .collect();
+
+        self.viewport.begin_draw(cx, Viewport3DProps { picking_enabled: true, ..Viewport3DProps::DEFAULT });
+        self.area = cx.add_mesh_instances(&MESH_SHADER, &instances, cube.clone());
+        self.viewport.end_draw(cx);
+
+        if self.viewport.begin_picking_draw(cx) {
+            Geometry3d::draw_picking(cx, &mut self.viewport, self.area, cube, &transforms);
+            self.viewport.end_picking_draw(cx);
+        }
+    }
+}
+
+#[test]
+fn viewport_mesh_picking() {
+    let test = ScreenshotTest { name: "viewport_mesh_picking", size: vec2(100., 100.), ..ScreenshotTest::default() };
+    let image = test.render(&mut ViewportMeshPickingApp::default());
+
+    let clicks = [image.center_of_color("f00"), image.center_of_color("0f0"), vec2(2., 2.)];
+
+    let steps = clicks.iter().map(|&pos| ScreenshotStep::Events(click(pos))).collect();
+    let mut app = ViewportMeshPickingApp::default();
+    ScreenshotTest { steps, ..test }.run(&mut app);
+
+    let pick = |instance_index: usize| Some(Viewport3DPick { area: app.area, instance_index });
+    assert_eq!(app.picks, vec![pick(0), pick(1), None]);
+}
@@ -0,0 +1,352 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+use std::any::TypeId;
+
+use screenshot_tests::*;
+use wrflib::*;
+
+const UBUNTU_REGULAR_BYTES: &[u8] = include_bytes!("../../main/resources/Ubuntu-R.ttf");
+const LIBERATION_MONO_PATH: &str = "../main/resources/LiberationMono-Regular.ttf";
+const DEJAVU_SANS_MONO_CFF_BYTES: &[u8] = include_bytes!("../../main/resources/DejaVuSansMono-Subset-CFF.otf");
+const DEJAVU_SANS_MONO_CFF2_BYTES: &[u8] = include_bytes!("../../main/resources/DejaVuSansMono-Subset-CFF2.otf");
+const DEJAVU_SANS_MONO_BYTES: &[u8] = include_bytes!("../../main/resources/DejaVuSansMono.ttf");
+
+/// Mathematical monospace letters and digits are outside of the Basic Multilingual Plane.
+const MONOSPACE_TEXT: &str = "Hi \u{1D670}\u{1D671}\u{1D672} \u{1D7F7}\u{1D7F8}\u{1D7F9}";
+
+/// The Ubuntu font doesn't have arrows, card suits, or block elements, but Liberation Mono does; and neither has
+/// emoji, which [`TextIns::draw_walk`] should skip.
+const FALLBACK_TEXT: &str = "Go \u{2192} \u{2665} \u{2591}\u{2592}\u{2593} \u{1F600}!";
+
+/// Creates the [`TextStyle`] for a [`FontApp`], e.g. by loading fonts.
+type LoadTextStyle = fn(&mut Cx) -> Result<TextStyle, String>;
+
+/// Draws a line of text, optionally with a [`TextStyle`] that uses fonts loaded at runtime on the first draw.
+struct FontApp {
+    text: &'static str,
+    text_style: TextStyle,
+    load: Option<LoadTextStyle>,
+    direction: TextDirection,
+}
+
+impl FontApp {
+    fn new(text: &'static str, text_style: TextStyle) -> Self {
+        Self { text, text_style, load: None, direction: TextDirection::DEFAULT }
+    }
+
+    fn new_with_load(text: &'static str, load: LoadTextStyle) -> Self {
+        Self { text, text_style: TEXT_STYLE_NORMAL, load: Some(load), direction: TextDirection::DEFAULT }
+    }
+}
+
+impl ScreenshotApp for FontApp {
+    fn handle(&mut self, _cx: &mut Cx, _event: &mut Event) {}
+
+    fn draw(&mut self, cx: &mut Cx) {
+        if let Some(load) = self.load.take() {
+            self.text_style = load(cx).unwrap();
+        }
+        TextIns::draw_walk(
+            cx,
+            self.text,
+            &TextInsProps { text_style: self.text_style, direction: self.direction, ..TextInsProps::DEFAULT },
+        );
+    }
+}
+
+#[test]
+fn font_loaded() {
+    ScreenshotTest { name: "font_loaded", size: vec2(120., 30.), ..ScreenshotTest::default() }
+        .run(&mut FontApp::new_with_load("Hello, world!", |cx| {
+            Ok(TextStyle { font: cx.load_font(UBUNTU_REGULAR_BYTES)?, ..TEXT_STYLE_NORMAL })
+        }));
+}
+
+#[test]
+fn font_loaded_matches_builtin() {
+    // Loading the same file as a built-in font should render the same text.
+    let test = ScreenshotTest { size: vec2(120., 30.), ..ScreenshotTest::default() };
+    let builtin = test.render(&mut FontApp::new("Hello, world!", TEXT_STYLE_NORMAL));
+    let loaded = test.render(&mut FontApp::new_with_load("Hello, world!", |cx| {
+        Ok(TextStyle { font: cx.load_font(UBUNTU_REGULAR_BYTES)?, ..TEXT_STYLE_NORMAL })
+    }));
+    assert_eq!(builtin, loaded);
+}
+
+#[test]
+fn font_loaded_from_file() {
+    let test = ScreenshotTest { size: vec2(120., 30.), ..ScreenshotTest::default() };
+    let builtin = test.render(&mut FontApp::new("Hello, world!", TEXT_STYLE_MONO));
+    let loaded = test.render(&mut FontApp::new_with_load("Hello, world!", |cx| {
+        let font = cx.load_font_file(&mut UniversalFile::open(LIBERATION_MONO_PATH).unwrap())?;
+        Ok(TextStyle { font, ..TEXT_STYLE_MONO })
+    }));
+    assert_eq!(builtin, loaded);
+}
+
+#[test]
+fn font_loading_errors() {
+    let mut cx = Cx::new(TypeId::of::<FontApp>());
+    assert_eq!(cx.load_font(b"not a font"), Err("Invalid or unsupported font".to_string()));
+    assert_eq!(cx.load_font(&UBUNTU_REGULAR_BYTES[..1000]), Err("Invalid or unsupported font".to_string()));
+    // Loaded fonts come after the built-in ones.
+    assert_eq!(cx.load_font(UBUNTU_REGULAR_BYTES), Ok(Font { font_id: 2 }));
+    assert_eq!(cx.load_font(UBUNTU_REGULAR_BYTES), Ok(Font { font_id: 3 }));
+}
+
+#[test]
+fn font_loaded_opentype() {
+    ScreenshotTest { name: "font_loaded_opentype", size: vec2(120., 30.), ..ScreenshotTest::default() }.run(
+        &mut FontApp::new_with_load(MONOSPACE_TEXT, |cx| {
+            Ok(TextStyle { font: cx.load_font(DEJAVU_SANS_MONO_CFF_BYTES)?, ..TEXT_STYLE_MONO })
+        }),
+    );
+}
+
+#[test]
+fn font_loaded_opentype_cff2() {
+    // The same outlines in a `CFF2` table should render the same.
+    let test = ScreenshotTest { size: vec2(120., 30.), ..ScreenshotTest::default() };
+    let cff = test.render(&mut FontApp::new_with_load(MONOSPACE_TEXT, |cx| {
+        Ok(TextStyle { font: cx.load_font(DEJAVU_SANS_MONO_CFF_BYTES)?, ..TEXT_STYLE_MONO })
+    }));
+    let cff2 = test.render(&mut FontApp::new_with_load(MONOSPACE_TEXT, |cx| {
+        Ok(TextStyle { font: cx.load_font(DEJAVU_SANS_MONO_CFF2_BYTES)?, ..TEXT_STYLE_MONO })
+    }));
+    assert_eq!(cff, cff2);
+}
+
+#[test]
+fn font_loaded_from_collection() {
+    let mut cx = Cx::new(TypeId::of::<FontApp>());
+    // A single font works like a collection with one font.
+    assert_eq!(cx.load_font_from_collection(UBUNTU_REGULAR_BYTES, 0), Ok(Font { font_id: 2 }));
+    assert_eq!(cx.load_font_from_collection(UBUNTU_REGULAR_BYTES, 1), Err("Invalid or unsupported font".to_string()));
+}
+
+/// Records the calls of the character callback of [`TextIns::generate_2d_glyphs`].
+#[derive(Default)]
+struct CharCallbackApp {
+    text_style: TextStyle,
+    glyph_count: usize,
+    /// Character, offset, x position, and width.
+    calls: Vec<(char, usize, f32, f32)>,
+}
+
+impl ScreenshotApp for CharCallbackApp {
+    fn handle(&mut self, _cx: &mut Cx, _event: &mut Event) {}
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let chars: Vec<char> = "office".chars().collect();
+        self.calls.clear();
+        let calls = &mut self.calls;
+        let glyphs = TextIns::generate_2d_glyphs(
+            &self.text_style,
+            &cx.fonts_data,
+            cx.current_dpi_factor,
+            1.0,
+            0.0,
+            COLOR_WHITE,
+            vec2(0., 0.),
+            10,
+            &chars,
+            |c, offset, x, width| {
+                calls.push((c, offset, x, width));
+                0.0
+            },
+        );
+        self.glyph_count = glyphs.len();
+    }
+}
+
+#[test]
+fn font_kerning_and_ligatures() {
+    ScreenshotTest { name: "font_kerning_and_ligatures", size: vec2(120., 60.), ..ScreenshotTest::default() }.run(
+        &mut FontApp::new(
+            "AVATAR Tokyo\nofficial flow",
+            TextStyle { kerning: true, ligatures: true, font_size: 10.0, ..TEXT_STYLE_NORMAL },
+        ),
+    );
+}
+
+#[test]
+fn font_kerning_and_ligatures_are_optional() {
+    let test = ScreenshotTest { size: vec2(120., 30.), ..ScreenshotTest::default() };
+    let plain = test.render(&mut FontApp::new("AVATAR", TEXT_STYLE_NORMAL));
+    let kerned = test.render(&mut FontApp::new("AVATAR", TextStyle { kerning: true, ..TEXT_STYLE_NORMAL }));
+    assert_ne!(plain, kerned);
+    let plain = test.render(&mut FontApp::new("office", TEXT_STYLE_NORMAL));
+    let ligatures = test.render(&mut FontApp::new("office", TextStyle { ligatures: true, ..TEXT_STYLE_NORMAL }));
+    assert_ne!(plain, ligatures);
+    // Liberation Mono has neither, so nothing changes.
+    let plain = test.render(&mut FontApp::new("AVATAR office", TEXT_STYLE_MONO));
+    let shaped = test.render(&mut FontApp::new("AVATAR office", TextStyle { kerning: true, ligatures: true, ..TEXT_STYLE_MONO }));
+    assert_eq!(plain, shaped);
+}
+
+#[test]
+fn font_ligature_char_callbacks() {
+    let test = ScreenshotTest { size: vec2(120., 30.), ..ScreenshotTest::default() };
+    let mut app = CharCallbackApp { text_style: TEXT_STYLE_NORMAL, ..CharCallbackApp::default() };
+    test.render(&mut app);
+    assert_eq!(app.glyph_count, 6);
+    let plain_calls = app.calls.clone();
+
+    // "ffi" is a single glyph, but the callback still gets called for every character.
+    let mut app =
+        CharCallbackApp { text_style: TextStyle { ligatures: true, ..TEXT_STYLE_NORMAL }, ..CharCallbackApp::default() };
+    test.render(&mut app);
+    assert_eq!(app.glyph_count, 4);
+    assert_eq!(
+        app.calls.iter().map(|&(c, offset, _, _)| (c, offset)).collect::<Vec<_>>(),
+        plain_calls.iter().map(|&(c, offset, _, _)| (c, offset)).collect::<Vec<_>>()
+    );
+    let (_, _, ffi_x, ffi_width) = app.calls[1];
+    for (index, &(_, _, x, width)) in app.calls[1..4].iter().enumerate() {
+        assert_eq!(width, ffi_width);
+        assert!((x - (ffi_x + ffi_width * index as f32)).abs() < 0.001);
+    }
+    assert!((app.calls[4].2 - (ffi_x + ffi_width * 3.)).abs() < 0.001);
+}
+
+#[test]
+fn font_family() {
+    let family = FontFamily { regular: Font { font_id: 2 }, bold: Some(Font { font_id: 3 }), italic: None, bold_italic: None };
+    assert_eq!(family.get(false, false), Font { font_id: 2 });
+    assert_eq!(family.get(true, false), Font { font_id: 3 });
+    assert_eq!(family.get(false, true), Font { font_id: 2 });
+    assert_eq!(family.get(true, true), Font { font_id: 3 });
+}
+
+fn load_builtin_fallbacks(cx: &mut Cx) -> Result<TextStyle, String> {
+    Ok(TextStyle { font_fallbacks: cx.add_font_fallbacks(&[TEXT_STYLE_MONO.font]), ..TEXT_STYLE_NORMAL })
+}
+
+#[test]
+fn font_fallbacks() {
+    ScreenshotTest { name: "font_fallbacks", size: vec2(120., 30.), ..ScreenshotTest::default() }
+        .run(&mut FontApp::new_with_load(FALLBACK_TEXT, load_builtin_fallbacks));
+}
+
+#[test]
+fn font_fallbacks_loaded() {
+    // The same, but with a fallback font that gets loaded at runtime.
+    let test = ScreenshotTest { size: vec2(120., 30.), ..ScreenshotTest::default() };
+    let builtin = test.render(&mut FontApp::new_with_load(FALLBACK_TEXT, load_builtin_fallbacks));
+    let loaded = test.render(&mut FontApp::new_with_load(FALLBACK_TEXT, |cx| {
+        let font = cx.load_font_file(&mut UniversalFile::open(LIBERATION_MONO_PATH).unwrap())?;
+        let font_fallbacks = cx.add_font_fallbacks(&[font]);
+        // Registering the same fonts again doesn't make a new list.
+        assert_eq!(cx.add_font_fallbacks(&[font]), font_fallbacks);
+        assert_ne!(font_fallbacks, FontFallbacks::NONE);
+        assert_eq!(cx.add_font_fallbacks(&[]), FontFallbacks::NONE);
+        Ok(TextStyle { font_fallbacks, ..TEXT_STYLE_NORMAL })
+    }));
+    assert_eq!(builtin, loaded);
+}
+
+fn load_dejavu_sans_mono(cx: &mut Cx) -> Result<TextStyle, String> {
+    Ok(TextStyle { font: cx.load_font(DEJAVU_SANS_MONO_BYTES)?, ..TEXT_STYLE_MONO })
+}
+
+#[test]
+fn font_bidi() {
+    // A left-to-right paragraph with an Arabic word, a right-to-left paragraph (which is aligned to the right and
+    // has mirrored parentheses), and an "e" with a combining accent.
+    ScreenshotTest { name: "font_bidi", size: vec2(120., 60.), ..ScreenshotTest::default() }.run(&mut FontApp::new_with_load(
+        "Exit (\u{645}\u{62E}\u{631}\u{62C}) 42\n\u{645}\u{62E}\u{631}\u{62C} (42)\ncafe\u{301}",
+        load_dejavu_sans_mono,
+    ));
+}
+
+#[test]
+fn font_bidi_direction() {
+    let test = ScreenshotTest { size: vec2(120., 30.), ..ScreenshotTest::default() };
+    // Text without right-to-left characters looks the same in a right-to-left paragraph, except for neutral
+    // characters at the ends.
+    let ltr = test.render(&mut FontApp::new("Hello world", TEXT_STYLE_NORMAL));
+    let rtl =
+        test.render(&mut FontApp { direction: TextDirection::RightToLeft, ..FontApp::new("Hello world", TEXT_STYLE_NORMAL) });
+    assert_eq!(ltr, rtl);
+    let ltr = test.render(&mut FontApp::new("Hello world!", TEXT_STYLE_NORMAL));
+    let rtl =
+        test.render(&mut FontApp { direction: TextDirection::RightToLeft, ..FontApp::new("Hello world!", TEXT_STYLE_NORMAL) });
+    assert_ne!(ltr, rtl);
+    // The direction of the first letter gets used by default.
+    let auto = test.render(&mut FontApp::new_with_load("\u{645}\u{62E}\u{631}\u{62C}!", load_dejavu_sans_mono));
+    let rtl = test.render(&mut FontApp {
+        direction: TextDirection::RightToLeft,
+        ..FontApp::new_with_load("\u{645}\u{62E}\u{631}\u{62C}!", load_dejavu_sans_mono)
+    });
+    let ltr = test.render(&mut FontApp {
+        direction: TextDirection::LeftToRight,
+        ..FontApp::new_with_load("\u{645}\u{62E}\u{631}\u{62C}!", load_dejavu_sans_mono)
+    });
+    assert_eq!(auto, rtl);
+    assert_ne!(auto, ltr);
+}
+
+/// Draws text using [`TextIns::draw_walk`], and records caret positions and the offsets that
+/// [`TextIns::closest_offset`] finds for them.
+struct CaretApp {
+    text: &'static str,
+    /// The position of the first glyph, and its advance.
+    origin: Vec2,
+    advance: f32,
+    caret_positions: Vec<Vec2>,
+    closest_offsets: Vec<Option<usize>>,
+}
+
+impl CaretApp {
+    fn new(text: &'static str) -> Self {
+        Self { text, origin: Vec2::default(), advance: 0.0, caret_positions: vec![], closest_offsets: vec![] }
+    }
+}
+
+impl ScreenshotApp for CaretApp {
+    fn handle(&mut self, _cx: &mut Cx, _event: &mut Event) {}
+
+    fn draw(&mut self, cx: &mut Cx) {
+        let text_style = load_dejavu_sans_mono(cx).unwrap();
+        let area = TextIns::draw_walk(cx, self.text, &TextInsProps { text_style, ..TextInsProps::DEFAULT });
+        let first_glyph = &area.get_slice::<TextIns>(cx)[0];
+        self.origin = first_glyph.base;
+        self.advance = first_glyph.advance;
+        self.caret_positions =
+            (0..=self.text.chars().count()).map(|offset| TextIns::caret_position(cx, &area, offset).unwrap()).collect();
+        self.closest_offsets = (0..=10)
+            .map(|index| {
+                let pos = self.origin + vec2(self.advance * (index as f32 * 0.5 + 0.2), 5.);
+                TextIns::closest_offset(cx, &area, pos, text_style.line_spacing)
+            })
+            .collect();
+    }
+}
+
+#[test]
+fn font_bidi_caret_positions() {
+    let test = ScreenshotTest { size: vec2(120., 30.), ..ScreenshotTest::default() };
+
+    // "ab" and two Arabic letters, which are drawn from right to left.
+    let mut app = CaretApp::new("ab \u{645}\u{62E}");
+    test.render(&mut app);
+    let caret_x: Vec<f32> = app.caret_positions.iter().map(|pos| (pos.x - app.origin.x) / app.advance).collect();
+    assert_eq!(caret_x, vec![0., 1., 2., 5., 4., 3.]);
+    assert!(app.caret_positions.iter().all(|pos| pos.y == app.origin.y));
+    // Positions every half glyph, from left to right, go to the closest caret.
+    assert_eq!(
+        app.closest_offsets,
+        vec![Some(0), Some(1), Some(1), Some(2), Some(2), Some(2), Some(4), Some(4), Some(4), Some(3), Some(3)]
+    );
+
+    // The combining accent is drawn on top of the "e", and carets don't go between them.
+    let mut app = CaretApp::new("e\u{301}x");
+    test.render(&mut app);
+    let caret_x: Vec<f32> = app.caret_positions.iter().map(|pos| (pos.x - app.origin.x) / app.advance).collect();
+    assert_eq!(caret_x, vec![0., 1., 1., 2.]);
+    assert_eq!(&app.closest_offsets[..3], &[Some(0), Some(2), Some(2)]);
+}
@@ -0,0 +1,86 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+//! Note that with the `shader-validation` feature every shader also gets validated when it first gets drawn,
+//! and every shader that is registered using `register_shader!` when the first `Cx` gets created, so the other
+//! tests check all the shaders that they use or register.
+
+use wrflib::*;
+
+#[test]
+fn valid_shader() {
+    TEXT_INS_SHADER.validate().unwrap();
+}
+
+static CUSTOM_UNIFORM_BLOCK_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            uniform color: vec4 in custom;
+            fn pixel() -> vec4 {
+                return color;
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+#[test]
+fn invalid_shader() {
+    let err = CUSTOM_UNIFORM_BLOCK_SHADER.validate().unwrap_err();
+    assert!(err.starts_with("Invalid shader at tests/shaders.rs:"), "{}", err);
+    assert!(err.contains("HLSL generation failed: extra uniform blocks not supported"), "{}", err);
+}
+
+static PARSE_ERROR_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                return undefined_color;
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+
+#[test]
+fn shader_parse_error() {
+    let err = PARSE_ERROR_SHADER.validate().unwrap_err();
+    assert!(err.contains("`undefined_color` is not defined"), "{}", err);
+}
+
+static REGISTERED_SHADER: Shader = Shader {
+    build_geom: Some(QuadIns::build_geom),
+    code_to_concatenate: &[
+        Cx::STD_SHADER,
+        QuadIns::SHADER,
+        code_fragment!(
+            r#"
+            fn pixel() -> vec4 {
+                return vec4(1., 0., 0., 1.);
+            }"#
+        ),
+    ],
+    ..Shader::DEFAULT
+};
+register_shader!(REGISTERED_SHADER);
+
+#[test]
+fn registered_shaders() {
+    let is_registered =
+        |shader: &Shader| inventory::iter::<RegisteredShader>.into_iter().any(|registered| std::ptr::eq(registered.0, shader));
+    assert!(is_registered(&TEXT_INS_SHADER));
+    assert!(is_registered(&REGISTERED_SHADER));
+    assert!(!is_registered(&PARSE_ERROR_SHADER));
+    Shader::validate_registered().unwrap();
+}
@@ -0,0 +1,185 @@
+// Copyright (c) 2021-present, Cruise LLC
+//
+// This source code is licensed under the Apache License, Version 2.0,
+// found in the LICENSE-APACHE file in the root directory of this source tree.
+// You may not use this file except in compliance with the License.
+
+use std::any::TypeId;
+use std::path::Path;
+use std::time::Duration;
+
+use screenshot_tests::*;
+use wrflib::*;
+
+/// Draws an encoded image so it fills the whole window.
+struct ImageApp {
+    bytes: &'static [u8],
+    texture: Option<Texture>,
+}
+
+impl ScreenshotApp for ImageApp {
+    fn handle(&mut self, _cx: &mut Cx, _event: &mut Event) {}
+
+    fn draw(&mut self, cx: &mut Cx) {
+        if self.texture.is_none() {
+            self.texture = Some(Texture::from_image_bytes(cx, self.bytes).unwrap());
+        }
+        ImageIns::draw(cx, cx.get_box_rect(), self.texture.as_ref().unwrap().unwrap_texture_handle());
+    }
+}
+
+#[test]
+fn image_png() {
+    // Drawing a golden image at its original size should give back the same image.
+    let bytes = include_bytes!("../screenshots/tab_control.png");
+    ScreenshotTest { name: "tab_control", size: vec2(300., 80.), ..ScreenshotTest::default() }
+        .run(&mut ImageApp { bytes, texture: None });
+}
+
+#[test]
+fn image_jpeg() {
+    // Red on the left, blue on the top right, and green on the bottom right.
+    let bytes = include_bytes!("../images/quadrants.jpg");
+    let image = decode_image(bytes).unwrap();
+    assert_eq!((image.width, image.height), (64, 32));
+    for (x, y, expected) in [(8, 16, [255, 0, 0]), (56, 4, [0, 0, 255]), (56, 28, [0, 255, 0])] {
+        let pixel = image.pixels[y * image.width + x].to_le_bytes();
+        for channel in 0..3 {
+            assert!((pixel[channel] as i32 - expected[channel]).abs() < 8, "{:?} at {}, {}", pixel, x, y);
+        }
+        assert_eq!(pixel[3], 255);
+    }
+
+    ScreenshotTest { name: "image_jpeg", size: vec2(64., 32.), ..ScreenshotTest::default() }
+        .run(&mut ImageApp { bytes, texture: None });
+}
+
+/// Draws a 4x2 single-channel texture with nearest filtering, so every texel becomes a sharp block.
+struct FormatApp {
+    format: TextureFormat,
+    texture: Option<Texture>,
+}
+
+impl ScreenshotApp for FormatApp {
+    fn handle(&mut self, _cx: &mut Cx, _event: &mut Event) {}
+
+    fn draw(&mut self, cx: &mut Cx) {
+        if self.texture.is_none() {
+            let options = TextureOptions { filter: TextureFilter::Nearest, ..TextureOptions::default() };
+            let texture = Texture::new_2d(cx, self.format, options);
+            let handle = texture.unwrap_texture_handle();
+            match self.format {
+                TextureFormat::R8 => handle.upload_u8(cx, 4, 2, &[0, 64, 128, 255, 255, 128, 64, 0]),
+                TextureFormat::R32F => handle.upload_f32(cx, 4, 2, &[0., 0.25, 0.5, 1., 1., 0.5, 0.25, 0.]),
+                _ => unreachable!(),
+            }
+            self.texture = Some(texture);
+        }
+        ImageIns::draw(cx, cx.get_box_rect(), self.texture.as_ref().unwrap().unwrap_texture_handle());
+    }
+}
+
+#[test]
+fn texture_format_r8() {
+    ScreenshotTest { name: "texture_format_r8", size: vec2(64., 32.), ..ScreenshotTest::default() }
+        .run(&mut FormatApp { format: TextureFormat::R8, texture: None });
+}
+
+#[test]
+fn texture_format_r32f() {
+    // Same values as `texture_format_r8`, so it should render the same image.
+    ScreenshotTest { name: "texture_format_r8", size: vec2(64., 32.), ..ScreenshotTest::default() }
+        .run(&mut FormatApp { format: TextureFormat::R32F, texture: None });
+}
+
+/// Draws an image into the color texture of a nested [`Pass`], shows that texture in the window, and reads the
+/// [`Pass`] back.
+#[derive(Default)]
+struct ReadbackApp {
+    image: Option<Texture>,
+    pass: Pass,
+    view: View,
+    color_texture: Texture,
+    readbacks: Vec<PassReadbackEvent>,
+}
+
+impl ScreenshotApp for ReadbackApp {
+    fn handle(&mut self, cx: &mut Cx, event: &mut Event) {
+        if let Event::PassReadback(readback) = event {
+            assert_eq!(Some(readback.pass_id), self.pass.pass_id);
+            let (width, height, pixels) = cx.headless_pass_pixels(&self.pass).unwrap();
+            assert_eq!((width, height, pixels), (readback.width, readback.height, readback.pixels.as_slice()));
+            self.readbacks.push(readback.clone());
+        }
+    }
+
+    fn draw(&mut self, cx: &mut Cx) {
+        if self.image.is_none() {
+            self.image = Some(Texture::from_image_bytes(cx, include_bytes!("../images/quadrants.jpg")).unwrap());
+        }
+        let rect = cx.get_box_rect();
+        let color_texture_handle = self.color_texture.get_color(cx);
+        ImageIns::draw(cx, rect, color_texture_handle);
+
+        self.pass.begin_pass_without_textures(cx);
+        self.pass.set_size(cx, rect.size);
+        self.pass.add_color_texture(cx, color_texture_handle, ClearColor::ClearWith(Vec4::default()));
+        self.view.begin_view(cx, LayoutSize::FILL);
+        ImageIns::draw(cx, cx.get_box_rect(), self.image.as_ref().unwrap().unwrap_texture_handle());
+        self.view.end_view(cx);
+        self.pass.end_pass(cx);
+
+        if self.readbacks.is_empty() {
+            self.pass.request_readback(cx);
+        }
+    }
+}
+
+#[test]
+fn pass_readback() {
+    let mut app = ReadbackApp::default();
+    let image = ScreenshotTest { name: "pass_readback", size: vec2(64., 32.), ..ScreenshotTest::default() }.render(&mut app);
+
+    assert_eq!(app.readbacks.len(), 1);
+    let readback = &app.readbacks[0];
+    assert_eq!((readback.width, readback.height), (64, 32));
+    // Blue should be on the top right, so the rows start at the top.
+    let pixel = readback.pixels[4 * readback.width + 56].to_le_bytes();
+    assert!(pixel[2] > 200 && pixel[0] < 50, "{:?}", pixel);
+    // The window shows the color texture 1:1.
+    assert_eq!(readback.pixels, image.pixels);
+}
+
+#[test]
+fn image_decoding_errors() {
+    assert!(decode_image(b"GIF89a").is_err());
+    assert!(decode_image(&include_bytes!("../images/quadrants.jpg")[..100]).is_err());
+}
+
+#[test]
+fn texture_loader() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("screenshots/tab_control.png");
+    let file = UniversalFile::open(path.to_str().unwrap()).unwrap();
+
+    let mut cx = Cx::new(TypeId::of::<()>());
+    let mut loader = TextureLoader::new(&mut cx, file);
+    let mut texture = None;
+    for _ in 0..500 {
+        cx.headless_process_events(
+            &mut |cx: &mut Cx, event: &mut Event| {
+                if let Some(result) = loader.handle(cx, event) {
+                    texture = Some(result.unwrap());
+                }
+            },
+            vec![],
+        );
+        if texture.is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let texture_handle = texture.expect("Texture didn't load in time").unwrap_texture_handle();
+    let expected = Image::read_png(&path).unwrap();
+    assert_eq!(texture_handle.get_image_mut(&mut cx), expected.pixels.as_slice());
+}
@@ -15,3 +15,4 @@ wrflib/scripts/ci/common.sh
 
 export RUSTFLAGS="-D warnings"
 cargo test --all-targets --workspace # runs all types of tests for the entire workspace
+cargo test --all-targets --manifest-path wrflib/screenshot_tests/Cargo.toml # golden-image tests, which have their own workspace
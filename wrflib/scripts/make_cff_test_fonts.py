@@ -0,0 +1,585 @@
+#!/usr/bin/env python3
+
+# Copyright (c) 2021-present, Cruise LLC
+#
+# This source code is licensed under the Apache License, Version 2.0,
+# found in the LICENSE-APACHE file in the root directory of this source tree.
+# You may not use this file except in compliance with the License.
+
+"""Generates the CFF and CFF2 test fonts in main/resources from DejaVuSansMono.ttf.
+
+The outlines of a subset of the glyphs get converted to cubic curves (which represent the quadratic
+curves exactly), so the tests in main/vector/src/ttf_parser can compare the parsed CFF/CFF2 outlines
+with the TrueType ones. The charstrings use a mix of operators, hints, and subroutines, and the CFF2
+font uses blends and multiple font dicts, to cover more of the parser.
+
+Usage: scripts/make_cff_test_fonts.py (from the wrflib directory; only uses the standard library)
+"""
+
+import struct
+from fractions import Fraction
+
+RESOURCES_DIR = "main/resources"
+SOURCE_FONT = RESOURCES_DIR + "/DejaVuSansMono.ttf"
+CHAR_CODES = list(range(0x20, 0x7F)) + list(range(0x1D670, 0x1D6A4)) + list(range(0x1D7F6, 0x1D800))
+
+
+# Reading the TrueType font.
+
+
+def read_tables(data):
+    table_count = struct.unpack(">H", data[4:6])[0]
+    tables = {}
+    for index in range(table_count):
+        tag, _, offset, length = struct.unpack(">4sIII", data[12 + 16 * index : 28 + 16 * index])
+        tables[tag.decode()] = data[offset : offset + length]
+    return tables
+
+
+def read_cmap(cmap):
+    subtable_count = struct.unpack(">H", cmap[2:4])[0]
+    for index in range(subtable_count):
+        platform_id, encoding_id, offset = struct.unpack(">HHI", cmap[4 + 8 * index : 12 + 8 * index])
+        subtable = cmap[offset:]
+        if (platform_id, encoding_id) == (3, 10) and struct.unpack(">H", subtable[:2])[0] == 12:
+            group_count = struct.unpack(">I", subtable[12:16])[0]
+            char_code_to_glyph_index = {}
+            for group in range(group_count):
+                start, end, glyph_index = struct.unpack(">III", subtable[16 + 12 * group : 28 + 12 * group])
+                for char_code in range(start, end + 1):
+                    char_code_to_glyph_index[char_code] = glyph_index + char_code - start
+            return char_code_to_glyph_index
+    raise Exception("No format 12 cmap subtable")
+
+
+def read_glyph_offsets(tables, glyph_count):
+    index_to_loc_format = struct.unpack(">h", tables["head"][50:52])[0]
+    if index_to_loc_format == 0:
+        return [offset * 2 for offset in struct.unpack(">%dH" % (glyph_count + 1), tables["loca"][: 2 * (glyph_count + 1)])]
+    return list(struct.unpack(">%dI" % (glyph_count + 1), tables["loca"][: 4 * (glyph_count + 1)]))
+
+
+def read_contours(tables, offsets, glyph_index):
+    """Returns the contours of a glyph as lists of (x, y, is_on_curve), with composite glyphs flattened."""
+    glyph = tables["glyf"][offsets[glyph_index] : offsets[glyph_index + 1]]
+    if not glyph:
+        return []
+    contour_count = struct.unpack(">h", glyph[:2])[0]
+    if contour_count >= 0:
+        end_points = struct.unpack(">%dH" % contour_count, glyph[10 : 10 + 2 * contour_count])
+        point_count = end_points[-1] + 1 if end_points else 0
+        position = 10 + 2 * contour_count
+        instruction_length = struct.unpack(">H", glyph[position : position + 2])[0]
+        position += 2 + instruction_length
+        flags = []
+        while len(flags) < point_count:
+            flag = glyph[position]
+            position += 1
+            repeat = 1
+            if flag & 8:
+                repeat += glyph[position]
+                position += 1
+            flags += [flag] * repeat
+        coordinates = []
+        for short_bit, same_bit in [(2, 16), (4, 32)]:
+            value = 0
+            values = []
+            for flag in flags:
+                if flag & short_bit:
+                    delta = glyph[position]
+                    position += 1
+                    value += delta if flag & same_bit else -delta
+                elif not flag & same_bit:
+                    value += struct.unpack(">h", glyph[position : position + 2])[0]
+                    position += 2
+                values.append(value)
+            coordinates.append(values)
+        points = [(x, y, bool(flag & 1)) for x, y, flag in zip(coordinates[0], coordinates[1], flags)]
+        contours = []
+        start = 0
+        for end in end_points:
+            contours.append(points[start : end + 1])
+            start = end + 1
+        return contours
+
+    contours = []
+    position = 10
+    while True:
+        flags, component = struct.unpack(">HH", glyph[position : position + 4])
+        position += 4
+        if not flags & 2:
+            raise Exception("Point matching in composite glyphs is not supported")
+        if flags & 1:
+            dx, dy = struct.unpack(">hh", glyph[position : position + 4])
+            position += 4
+        else:
+            dx, dy = struct.unpack(">bb", glyph[position : position + 2])
+            position += 2
+        if flags & (8 | 64 | 128):
+            raise Exception("Scaled components are not supported")
+        for contour in read_contours(tables, offsets, component):
+            contours.append([(x + dx, y + dy, is_on_curve) for x, y, is_on_curve in contour])
+        if not flags & 32:
+            return contours
+
+
+def contour_to_commands(contour):
+    """Converts a TrueType contour to ("M", p), ("L", p) and ("Q", control, p) commands, in the same way as
+    `Outline::commands` in main/vector/src/font/outline.rs, and without the command that closes the contour
+    (which becomes implicit in CFF), unless that's a curve."""
+    points = [(Fraction(x), Fraction(y), is_on_curve) for x, y, is_on_curve in contour]
+    commands = []
+    first_off = first_on = last_off = None
+
+    def midpoint(a, b):
+        return ((a[0] + b[0]) / 2, (a[1] + b[1]) / 2)
+
+    for x, y, is_on_curve in points:
+        point = (x, y)
+        if first_on is None:
+            if is_on_curve:
+                commands.append(("M", point))
+                first_on = point
+            elif first_off is not None:
+                first_on = midpoint(first_off, point)
+                commands.append(("M", first_on))
+                last_off = point
+            else:
+                first_off = point
+        elif last_off is None:
+            if is_on_curve:
+                commands.append(("L", point))
+            else:
+                last_off = point
+        elif is_on_curve:
+            commands.append(("Q", last_off, point))
+            last_off = None
+        else:
+            commands.append(("Q", last_off, midpoint(last_off, point)))
+            last_off = point
+    if first_on is None:
+        return []
+    if last_off is not None and first_off is not None:
+        commands.append(("Q", last_off, midpoint(last_off, first_off)))
+        commands.append(("Q", first_off, first_on))
+    elif last_off is not None:
+        commands.append(("Q", last_off, first_on))
+    elif first_off is not None:
+        commands.append(("Q", first_off, first_on))
+    return commands
+
+
+# Writing CFF data.
+
+
+def encode_dict_int(value, fixed_size=False):
+    if fixed_size or not -32768 <= value <= 32767:
+        return bytes([29]) + struct.pack(">i", value)
+    return bytes([28]) + struct.pack(">h", value)
+
+
+def encode_dict_real(string):
+    nibbles = [0xA if char == "." else int(char) for char in string] + [0xF]
+    if len(nibbles) % 2:
+        nibbles.append(0xF)
+    return bytes([30] + [nibbles[i] << 4 | nibbles[i + 1] for i in range(0, len(nibbles), 2)])
+
+
+def encode_operator(operator):
+    return bytes(operator) if isinstance(operator, tuple) else bytes([operator])
+
+
+def encode_dict(entries):
+    """Encodes (operands, operator) pairs; int operands are always 5 bytes so offsets can be filled in later."""
+    data = b""
+    for operands, operator in entries:
+        for operand in operands:
+            data += operand if isinstance(operand, bytes) else encode_dict_int(operand, fixed_size=True)
+        data += encode_operator(operator)
+    return data
+
+
+def encode_index(items, count_format=">H"):
+    if not items:
+        return struct.pack(count_format, 0)
+    offsets = [1]
+    for item in items:
+        offsets.append(offsets[-1] + len(item))
+    offset_size = 1 if offsets[-1] < 0x100 else 2 if offsets[-1] < 0x10000 else 3 if offsets[-1] < 0x1000000 else 4
+    data = struct.pack(count_format, len(items)) + bytes([offset_size])
+    for offset in offsets:
+        data += offset.to_bytes(offset_size, "big")
+    return data + b"".join(items)
+
+
+def encode_charstring_number(value):
+    """Encodes a number in 1/65536 units."""
+    if value % 65536 == 0:
+        value //= 65536
+        if -107 <= value <= 107:
+            return bytes([value + 139])
+        if 108 <= value <= 1131:
+            return bytes([(value - 108) // 256 + 247, (value - 108) % 256])
+        if -1131 <= value <= -108:
+            return bytes([(-value - 108) // 256 + 251, (-value - 108) % 256])
+        return bytes([28]) + struct.pack(">h", value)
+    return bytes([255]) + struct.pack(">i", value)
+
+
+def to_fixed(value):
+    return round(value * 65536)
+
+
+class CharstringWriter:
+    """Writes path commands as Type 2 charstring operators, tracking the position in 16.16 units so
+    rounding errors don't accumulate."""
+
+    def __init__(self, blend):
+        self.data = b""
+        self.position = (0, 0)
+        self.blend = blend
+
+    def numbers(self, operands):
+        self.data += b"".join(encode_charstring_number(operand) for operand in operands)
+
+    def op(self, operands, operator):
+        self.numbers(operands)
+        self.data += encode_operator(operator)
+
+    def deltas(self, point):
+        target = (to_fixed(point[0]), to_fixed(point[1]))
+        deltas = (target[0] - self.position[0], target[1] - self.position[1])
+        self.position = target
+        return deltas
+
+    def move_to(self, point):
+        dx, dy = self.deltas(point)
+        if self.blend:
+            # Blend in an arbitrary variation, which should get ignored for the default instance.
+            self.op([dx, dy, 10 * 65536, -10 * 65536, 2 * 65536], 16)
+            self.op([], 21)
+        elif dy == 0:
+            self.op([dx], 22)
+        elif dx == 0:
+            self.op([dy], 4)
+        else:
+            self.op([dx, dy], 21)
+
+    def line_to(self, point):
+        dx, dy = self.deltas(point)
+        if dy == 0 and dx != 0:
+            self.op([dx], 6)
+        elif dx == 0 and dy != 0:
+            self.op([dy], 7)
+        else:
+            self.op([dx, dy], 5)
+
+    def curve_to(self, control_1, control_2, point):
+        dxa, dya = self.deltas(control_1)
+        dxb, dyb = self.deltas(control_2)
+        dxc, dyc = self.deltas(point)
+        if dya == 0 and dxc == 0:
+            self.op([dxa, dxb, dyb, dyc], 31)
+        elif dxa == 0 and dyc == 0:
+            self.op([dya, dxb, dyb, dxc], 30)
+        else:
+            self.op([dxa, dya, dxb, dyb, dxc, dyc], 8)
+
+    def write_commands(self, commands):
+        current = None
+        for command in commands:
+            if command[0] == "M":
+                self.move_to(command[1])
+                current = command[1]
+            elif command[0] == "L":
+                self.line_to(command[1])
+                current = command[1]
+            else:
+                # A quadratic curve is exactly representable as a cubic one.
+                control, point = command[1], command[2]
+                control_1 = (current[0] + (control[0] - current[0]) * 2 / 3, current[1] + (control[1] - current[1]) * 2 / 3)
+                control_2 = (point[0] + (control[0] - point[0]) * 2 / 3, point[1] + (control[1] - point[1]) * 2 / 3)
+                self.curve_to(control_1, control_2, point)
+                current = point
+
+
+def subr_bias(count):
+    return 107 if count < 1240 else 1131 if count < 33900 else 32768
+
+
+def make_charstrings(glyphs, is_cff2):
+    """Returns the charstrings and the global and local subroutines. Every third glyph has its path in a global
+    subroutine, and every third in a local one."""
+    charstrings = []
+    global_subrs = []
+    local_subrs = []
+    for glyph_index, (advance_width, bounds, commands) in enumerate(glyphs):
+        header = CharstringWriter(False)
+        if not is_cff2:
+            header.numbers([advance_width * 65536])
+        if commands:
+            x_min, y_min, x_max, y_max = bounds
+            header.op([y_min * 65536, (y_max - y_min) * 65536], 1)
+            header.op([x_min * 65536, (x_max - x_min) * 65536], 3)
+            # Both stems are active.
+            header.data += bytes([19, 0b11000000])
+        body = CharstringWriter(is_cff2 and glyph_index % 2 == 0)
+        body.write_commands(commands)
+        footer = b"" if is_cff2 else bytes([14])
+        subr_end = b"" if is_cff2 else bytes([11])
+        if glyph_index % 3 == 0 and commands:
+            global_subrs.append(body.data + subr_end)
+            charstrings.append((header.data, 29, len(global_subrs) - 1, footer))
+        elif glyph_index % 3 == 1 and commands:
+            local_subrs.append(body.data + subr_end)
+            charstrings.append((header.data, 10, len(local_subrs) - 1, footer))
+        else:
+            charstrings.append((header.data + body.data, None, None, footer))
+
+    def finish(charstring):
+        data, call_operator, subr_index, footer = charstring
+        if call_operator is not None:
+            bias = subr_bias(len(global_subrs) if call_operator == 29 else len(local_subrs))
+            data += encode_charstring_number((subr_index - bias) * 65536) + bytes([call_operator])
+        return data + footer
+
+    return [finish(charstring) for charstring in charstrings], global_subrs, local_subrs
+
+
+FONT_MATRIX = ([encode_dict_real("0.00048828125"), 0, 0, encode_dict_real("0.00048828125"), 0, 0], (12, 7))
+
+
+def make_cff(glyphs, glyph_names, font_name, font_bbox):
+    charstrings, global_subrs, local_subrs = make_charstrings(glyphs, False)
+    strings = [name.encode() for name in glyph_names[1:]]
+    charset = bytes([0]) + b"".join(struct.pack(">H", 391 + index) for index in range(len(strings)))
+    # The local subroutines come right after the private dict.
+    private_dict_length = len(encode_dict([([0], 20), ([0], 21), ([0], 19)]))
+    private_dict = encode_dict([([0], 20), ([0], 21), ([private_dict_length], 19)])
+    local_subrs_index = encode_index(local_subrs)
+
+    def top_dict(charset_offset, charstrings_offset, private_offset):
+        return encode_dict(
+            [
+                FONT_MATRIX,
+                (font_bbox, 5),
+                ([charset_offset], 15),
+                ([charstrings_offset], 17),
+                ([len(private_dict), private_offset], 18),
+            ]
+        )
+
+    header = bytes([1, 0, 4, 4])
+    name_index = encode_index([font_name.encode()])
+    start = len(header) + len(name_index) + len(encode_index([top_dict(0, 0, 0)]))
+    string_index = encode_index(strings)
+    global_subrs_index = encode_index(global_subrs)
+    charset_offset = start + len(string_index) + len(global_subrs_index)
+    charstrings_index = encode_index(charstrings)
+    charstrings_offset = charset_offset + len(charset)
+    private_offset = charstrings_offset + len(charstrings_index)
+    top_dict_index = encode_index([top_dict(charset_offset, charstrings_offset, private_offset)])
+    return (
+        header
+        + name_index
+        + top_dict_index
+        + string_index
+        + global_subrs_index
+        + charset
+        + charstrings_index
+        + private_dict
+        + local_subrs_index
+    )
+
+
+def make_cff2(glyphs):
+    charstrings, global_subrs, local_subrs = make_charstrings(glyphs, True)
+    # Two font dicts: glyphs calling local subroutines use the first one, and all others the second one, which
+    # has no subroutines.
+    font_dict_indices = [0 if glyph_index % 3 == 1 else 1 for glyph_index in range(len(glyphs))]
+    fd_select = bytes([3])
+    ranges = []
+    for glyph_index, font_dict_index in enumerate(font_dict_indices):
+        if not ranges or ranges[-1][1] != font_dict_index:
+            ranges.append((glyph_index, font_dict_index))
+    fd_select += struct.pack(">H", len(ranges))
+    for first, font_dict_index in ranges:
+        fd_select += struct.pack(">HB", first, font_dict_index)
+    fd_select += struct.pack(">H", len(glyphs))
+
+    # A variation store with a single region, for the blends.
+    region_list = struct.pack(">HH", 1, 1) + struct.pack(">hhh", 0, 1 << 14, 1 << 14)
+    variation_data = struct.pack(">HHHH", 0, 0, 1, 0)
+    item_variation_store = struct.pack(">HIHI", 1, 12, 1, 12 + len(region_list)) + region_list + variation_data
+    variation_store = struct.pack(">H", len(item_variation_store)) + item_variation_store
+
+    def top_dict(charstrings_offset, fd_array_offset, fd_select_offset, variation_store_offset):
+        return encode_dict(
+            [
+                FONT_MATRIX,
+                ([charstrings_offset], 17),
+                ([fd_array_offset], (12, 36)),
+                ([fd_select_offset], (12, 37)),
+                ([variation_store_offset], 24),
+            ]
+        )
+
+    # The local subroutines come right after the private dict.
+    private_dict_with_subrs = encode_dict([([len(encode_dict([([0], 19)]))], 19)])
+    private_dict_without_subrs = b""
+    local_subrs_index = encode_index(local_subrs, ">I")
+
+    def font_dicts(private_offset):
+        return encode_index(
+            [
+                encode_dict([([len(private_dict_with_subrs), private_offset], 18)]),
+                encode_dict([([len(private_dict_without_subrs), private_offset], 18)]),
+            ],
+            ">I",
+        )
+
+    top_dict_length = len(top_dict(0, 0, 0, 0))
+    global_subrs_index = encode_index(global_subrs, ">I")
+    variation_store_offset = 5 + top_dict_length + len(global_subrs_index)
+    charstrings_offset = variation_store_offset + len(variation_store)
+    charstrings_index = encode_index(charstrings, ">I")
+    fd_select_offset = charstrings_offset + len(charstrings_index)
+    fd_array_offset = fd_select_offset + len(fd_select)
+    private_offset = fd_array_offset + len(font_dicts(0))
+    return (
+        struct.pack(">BBBH", 2, 0, 5, top_dict_length)
+        + top_dict(charstrings_offset, fd_array_offset, fd_select_offset, variation_store_offset)
+        + global_subrs_index
+        + variation_store
+        + charstrings_index
+        + fd_select
+        + font_dicts(private_offset)
+        + private_dict_with_subrs
+        + local_subrs_index
+    )
+
+
+# Writing the OpenType font.
+
+
+def make_cmap(char_code_to_glyph_index):
+    groups = []
+    for char_code, glyph_index in sorted(char_code_to_glyph_index.items()):
+        if groups and groups[-1][1] == char_code - 1 and groups[-1][2] + char_code - groups[-1][0] == glyph_index:
+            groups[-1][1] = char_code
+        else:
+            groups.append([char_code, char_code, glyph_index])
+    format_12 = struct.pack(">HHIII", 12, 0, 16 + 12 * len(groups), 0, len(groups))
+    for start, end, glyph_index in groups:
+        format_12 += struct.pack(">III", start, end, glyph_index)
+
+    segments = [(start, end, glyph_index - start) for start, end, glyph_index in groups if end < 0xFFFF]
+    segments.append((0xFFFF, 0xFFFF, 1))
+    segment_count = len(segments)
+    search_range = 2 * 2 ** (segment_count.bit_length() - 1)
+    format_4 = struct.pack(
+        ">HHHHHHH",
+        4,
+        16 + 8 * segment_count,
+        0,
+        2 * segment_count,
+        search_range,
+        segment_count.bit_length() - 1,
+        2 * segment_count - search_range,
+    )
+    format_4 += b"".join(struct.pack(">H", end) for _, end, _ in segments) + struct.pack(">H", 0)
+    format_4 += b"".join(struct.pack(">H", start) for start, _, _ in segments)
+    format_4 += b"".join(struct.pack(">H", delta % 65536) for _, _, delta in segments)
+    format_4 += struct.pack(">%dH" % segment_count, *([0] * segment_count))
+
+    header = struct.pack(">HH", 0, 3)
+    format_4_offset = 4 + 3 * 8
+    format_12_offset = format_4_offset + len(format_4)
+    header += struct.pack(">HHI", 0, 3, format_4_offset)
+    header += struct.pack(">HHI", 3, 1, format_4_offset)
+    header += struct.pack(">HHI", 3, 10, format_12_offset)
+    return header + format_4 + format_12
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def make_otf(tables):
+    tags = sorted(tables)
+    offset = 12 + 16 * len(tags)
+    directory = b""
+    data = b""
+    head_offset = None
+    for tag in tags:
+        table = tables[tag]
+        if tag == "head":
+            head_offset = offset + len(data)
+        directory += struct.pack(">4sIII", tag.encode(), checksum(table), offset + len(data), len(table))
+        data += table + b"\0" * (-len(table) % 4)
+    entry_selector = len(tags).bit_length() - 1
+    search_range = 16 * 2**entry_selector
+    font = (
+        struct.pack(">4sHHHH", b"OTTO", len(tags), search_range, entry_selector, 16 * len(tags) - search_range)
+        + directory
+        + data
+    )
+    adjustment = (0xB1B0AFBA - checksum(font)) & 0xFFFFFFFF
+    return font[: head_offset + 8] + struct.pack(">I", adjustment) + font[head_offset + 12 :]
+
+
+def main():
+    data = open(SOURCE_FONT, "rb").read()
+    tables = read_tables(data)
+    glyph_count = struct.unpack(">H", tables["maxp"][4:6])[0]
+    advance_width_count = struct.unpack(">H", tables["hhea"][34:36])[0]
+    offsets = read_glyph_offsets(tables, glyph_count)
+    cmap = read_cmap(tables["cmap"])
+
+    old_glyph_indices = [0] + sorted({cmap[char_code] for char_code in CHAR_CODES} - {0})
+    glyphs = []
+    for old_glyph_index in old_glyph_indices:
+        metrics_index = min(old_glyph_index, advance_width_count - 1)
+        advance_width = struct.unpack(">H", tables["hmtx"][4 * metrics_index : 4 * metrics_index + 2])[0]
+        contours = read_contours(tables, offsets, old_glyph_index)
+        points = [point for contour in contours for point in contour]
+        bounds = (
+            min(x for x, _, _ in points),
+            min(y for _, y, _ in points),
+            max(x for x, _, _ in points),
+            max(y for _, y, _ in points),
+        ) if points else (0, 0, 0, 0)
+        commands = [command for contour in contours for command in contour_to_commands(contour)]
+        glyphs.append((advance_width, bounds, commands))
+    glyph_names = [".notdef"] + ["glyph%d" % old_glyph_index for old_glyph_index in old_glyph_indices[1:]]
+    char_code_to_glyph_index = {char_code: old_glyph_indices.index(cmap[char_code]) for char_code in CHAR_CODES}
+
+    hmtx = b""
+    for advance_width, bounds, _ in glyphs:
+        hmtx += struct.pack(">Hh", advance_width, bounds[0])
+    hhea = tables["hhea"][:34] + struct.pack(">H", len(glyphs))
+    head = tables["head"][:8] + b"\0\0\0\0" + tables["head"][12:50] + struct.pack(">h", 0) + tables["head"][52:]
+    maxp = struct.pack(">IH", 0x00005000, len(glyphs))
+    post = struct.pack(">I", 0x00030000) + tables["post"][4:32]
+    font_bbox = list(struct.unpack(">4h", tables["head"][36:44]))
+    shared_tables = {
+        "OS/2": tables["OS/2"],
+        "cmap": make_cmap(char_code_to_glyph_index),
+        "head": head,
+        "hhea": hhea,
+        "hmtx": hmtx,
+        "maxp": maxp,
+        "name": tables["name"],
+        "post": post,
+    }
+
+    cff = make_cff(glyphs, glyph_names, "DejaVuSansMono-Subset", font_bbox)
+    with open(RESOURCES_DIR + "/DejaVuSansMono-Subset-CFF.otf", "wb") as file:
+        file.write(make_otf({**shared_tables, "CFF ": cff}))
+    with open(RESOURCES_DIR + "/DejaVuSansMono-Subset-CFF2.otf", "wb") as file:
+        file.write(make_otf({**shared_tables, "CFF2": make_cff2(glyphs)}))
+
+
+if __name__ == "__main__":
+    main()
@@ -19,6 +19,14 @@ export type UniformType =
   | "mat3"
   | "mat4";
 export type Uniform = { ty: UniformType; name: string };
+// "sampler3D" is not an actual GLSL type, since 3D textures are emulated using 2D textures.
+export type TextureSlotType = "sampler2D" | "samplerCube" | "sampler3D";
+export type TextureSlot = {
+  ty: TextureSlotType;
+  name: string;
+  // Name of the uniform that holds the height and depth of a 3D texture.
+  sizeName: string;
+};
 
 export type ShaderAttributes = {
   shaderId: number;
@@ -30,12 +38,15 @@ export type ShaderAttributes = {
   viewUniforms: Uniform[];
   drawUniforms: Uniform[];
   userUniforms: Uniform[];
-  textureSlots: Uniform[];
+  textureSlots: TextureSlot[];
 };
 
 export type Texture = WebGLTexture & {
   mpWidth: number;
   mpHeight: number;
+  // Kind of texture, as in `TextureKind` in Rust: 0 for 2D, 1 for cube maps, and 2 for 3D.
+  mpKind?: number;
+  mpDepth?: number;
 };
 
 export type FileHandle = {
@@ -3,6 +3,8 @@ import {
   ShaderAttributes,
   SizingData,
   Texture,
+  TextureSlot,
+  TextureSlotType,
   Uniform,
   UniformType,
 } from "./types";
@@ -27,7 +29,7 @@ export class WebGLRenderer {
     viewUniforms: ReturnType<WebGLRenderer["getUniformLocations"]>;
     drawUniforms: ReturnType<WebGLRenderer["getUniformLocations"]>;
     userUniforms: ReturnType<WebGLRenderer["getUniformLocations"]>;
-    textureSlots: ReturnType<WebGLRenderer["getUniformLocations"]>;
+    textureSlots: ReturnType<WebGLRenderer["getTextureSlotLocations"]>;
     instanceSlots: number;
     program: WebGLProgram;
     ash: ShaderAttributes;
@@ -113,6 +115,9 @@ export class WebGLRenderer {
     );
     this.gl.getExtension("OES_standard_derivatives");
     this.gl.getExtension("OES_element_index_uint");
+    // For float texture formats, which get converted to RGBA floats.
+    this.gl.getExtension("OES_texture_float");
+    this.gl.getExtension("OES_texture_float_linear");
     this.resize(sizingData);
   }
 
@@ -192,6 +197,25 @@ export class WebGLRenderer {
     return uniformLocs;
   }
 
+  private getTextureSlotLocations(
+    program: WebGLProgram,
+    textureSlots: TextureSlot[]
+  ): {
+    ty: TextureSlotType;
+    loc: WebGLUniformLocation | null;
+    sizeLoc: WebGLUniformLocation | null;
+  }[] {
+    const gl = this.gl;
+    return textureSlots.map((slot) => ({
+      ty: slot.ty,
+      loc: gl.getUniformLocation(program, slot.name),
+      sizeLoc:
+        slot.ty === "sampler3D"
+          ? gl.getUniformLocation(program, slot.sizeName)
+          : null,
+    }));
+  }
+
   private compileWebGLShader(ash: ShaderAttributes): void {
     const gl = this.gl;
     const vsh = assertNotNull(gl.createShader(gl.VERTEX_SHADER));
@@ -240,7 +264,7 @@ export class WebGLRenderer {
       viewUniforms: this.getUniformLocations(program, ash.viewUniforms),
       drawUniforms: this.getUniformLocations(program, ash.drawUniforms),
       userUniforms: this.getUniformLocations(program, ash.userUniforms),
-      textureSlots: this.getUniformLocations(program, ash.textureSlots),
+      textureSlots: this.getTextureSlotLocations(program, ash.textureSlots),
       instanceSlots: ash.instanceSlots,
       program: program,
       ash: ash,
@@ -386,9 +410,18 @@ export class WebGLRenderer {
       const texSlot = textureSlots[i];
       const texId = this.baseu32[(texturesPtr >> 2) + i];
       const texObj = this.textures[texId];
+      const kind = { sampler2D: 0, samplerCube: 1, sampler3D: 2 }[texSlot.ty];
+      // Textures that haven't been uploaded yet are 2D (e.g. render targets).
+      const matches = texObj !== undefined && (texObj.mpKind ?? 0) === kind;
       gl.activeTexture(gl.TEXTURE0 + i);
-      gl.bindTexture(gl.TEXTURE_2D, texObj);
+      gl.bindTexture(
+        kind === 1 ? gl.TEXTURE_CUBE_MAP : gl.TEXTURE_2D,
+        matches ? texObj : null
+      );
       gl.uniform1i(texSlot.loc, i);
+      if (texSlot.sizeLoc && matches) {
+        gl.uniform2f(texSlot.sizeLoc, texObj.mpHeight, texObj.mpDepth ?? 1);
+      }
     }
     const indices = indexBuffer.length;
     const instances = instanceBuffer.length / shader.instanceSlots;
@@ -431,34 +464,74 @@ export class WebGLRenderer {
     textureId: number,
     width: number,
     height: number,
-    dataPtr: number
+    dataPtr: number,
+    kind: number,
+    depth: number,
+    isFloat: boolean,
+    linear: boolean,
+    repeat: boolean
   ): void {
     const gl = this.gl;
-    const glTex = this.textures[textureId] || gl.createTexture();
-
-    gl.bindTexture(gl.TEXTURE_2D, glTex);
-    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.LINEAR);
-    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR);
-    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
-    gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
-
-    const data = new Uint8Array(
-      this.memory.buffer,
-      dataPtr,
-      width * height * 4
-    );
-    gl.texImage2D(
-      gl.TEXTURE_2D,
-      0,
-      gl.RGBA,
-      width,
-      height,
-      0,
-      gl.RGBA,
-      gl.UNSIGNED_BYTE,
-      data
-    );
-    this.textures[textureId] = glTex as Texture;
+    const glTex = (this.textures[textureId] || gl.createTexture()) as Texture;
+
+    // 2D and 3D textures are both 2D textures in WebGL 1; 3D textures get
+    // their slices stacked vertically.
+    const target = kind === 1 ? gl.TEXTURE_CUBE_MAP : gl.TEXTURE_2D;
+    const filter = linear ? gl.LINEAR : gl.NEAREST;
+    const wrap = repeat ? gl.REPEAT : gl.CLAMP_TO_EDGE;
+    gl.bindTexture(target, glTex);
+    gl.texParameteri(target, gl.TEXTURE_MAG_FILTER, filter);
+    gl.texParameteri(target, gl.TEXTURE_MIN_FILTER, filter);
+    gl.texParameteri(target, gl.TEXTURE_WRAP_S, wrap);
+    gl.texParameteri(target, gl.TEXTURE_WRAP_T, wrap);
+
+    // Pixels are always RGBA, either as bytes or as floats; see
+    // `webgl_texture_data` in `cx_webgl.rs`.
+    const type = isFloat ? gl.FLOAT : gl.UNSIGNED_BYTE;
+    const getData = (offset: number, pixels: number) =>
+      isFloat
+        ? new Float32Array(
+            this.memory.buffer,
+            dataPtr + offset * 16,
+            pixels * 4
+          )
+        : new Uint8Array(
+            this.memory.buffer,
+            dataPtr + offset * 4,
+            pixels * 4
+          );
+    if (kind === 1) {
+      for (let face = 0; face < 6; face++) {
+        gl.texImage2D(
+          gl.TEXTURE_CUBE_MAP_POSITIVE_X + face,
+          0,
+          gl.RGBA,
+          width,
+          height,
+          0,
+          gl.RGBA,
+          type,
+          getData(face * width * height, width * height)
+        );
+      }
+    } else {
+      gl.texImage2D(
+        gl.TEXTURE_2D,
+        0,
+        gl.RGBA,
+        width,
+        height * depth,
+        0,
+        gl.RGBA,
+        type,
+        getData(0, width * height * depth)
+      );
+    }
+    glTex.mpKind = kind;
+    glTex.mpWidth = width;
+    glTex.mpHeight = height;
+    glTex.mpDepth = depth;
+    this.textures[textureId] = glTex;
   }
 
   private beginRenderTargets(
@@ -602,6 +675,33 @@ export class WebGLRenderer {
     gl.enable(gl.BLEND);
   }
 
+  // Reads the color texture of a pass into wasm memory. WebGL returns the bottom
+  // row first, so we flip the rows to start at the top.
+  private readPixels(
+    passId: number,
+    width: number,
+    height: number,
+    pixelsPtr: number
+  ): void {
+    const gl = this.gl;
+    const pixels = new Uint8Array(
+      this.memory.buffer,
+      pixelsPtr,
+      width * height * 4
+    );
+    gl.bindFramebuffer(gl.FRAMEBUFFER, this.framebuffers[passId]);
+    gl.readPixels(0, 0, width, height, gl.RGBA, gl.UNSIGNED_BYTE, pixels);
+    const rowSize = width * 4;
+    const row = new Uint8Array(rowSize);
+    for (let y = 0; y < height >> 1; y++) {
+      const top = y * rowSize;
+      const bottom = (height - 1 - y) * rowSize;
+      row.set(pixels.subarray(top, top + rowSize));
+      pixels.copyWithin(top, bottom, bottom + rowSize);
+      pixels.set(row, bottom);
+    }
+  }
+
   private beginMainCanvas(
     r: number,
     g: number,
@@ -719,6 +819,19 @@ export class WebGLRenderer {
         return vars;
       }
 
+      function parseTextureSlots(): TextureSlot[] {
+        const len = self.zerdeParser.parseU32();
+        const slots: TextureSlot[] = [];
+        for (let i = 0; i < len; i++) {
+          slots.push({
+            ty: self.zerdeParser.parseString() as TextureSlotType,
+            name: self.zerdeParser.parseString(),
+            sizeName: self.zerdeParser.parseString(),
+          });
+        }
+        return slots;
+      }
+
       const ash = {
         shaderId: self.zerdeParser.parseU32(),
         fragment: self.zerdeParser.parseString(),
@@ -729,7 +842,7 @@ export class WebGLRenderer {
         viewUniforms: parseShvarvec(),
         drawUniforms: parseShvarvec(),
         userUniforms: parseShvarvec(),
-        textureSlots: parseShvarvec(),
+        textureSlots: parseTextureSlots(),
       };
       self.compileWebGLShader(ash);
     },
@@ -783,7 +896,22 @@ export class WebGLRenderer {
       const width = self.zerdeParser.parseU32();
       const height = self.zerdeParser.parseU32();
       const dataPtr = self.zerdeParser.parseU32();
-      self.allocTexture(textureId, width, height, dataPtr);
+      const kind = self.zerdeParser.parseU32();
+      const depth = self.zerdeParser.parseU32();
+      const isFloat = self.zerdeParser.parseU32() === 1;
+      const linear = self.zerdeParser.parseU32() === 1;
+      const repeat = self.zerdeParser.parseU32() === 1;
+      self.allocTexture(
+        textureId,
+        width,
+        height,
+        dataPtr,
+        kind,
+        depth,
+        isFloat,
+        linear,
+        repeat
+      );
     },
     // begin_render_targets
     function beginRenderTargets7(self) {
@@ -826,6 +954,14 @@ export class WebGLRenderer {
       const depth = self.zerdeParser.parseF32();
       self.beginMainCanvas(r, g, b, a, depth);
     },
+    // read_pixels
+    function readPixels13(self) {
+      const passId = self.zerdeParser.parseU32();
+      const width = self.zerdeParser.parseU32();
+      const height = self.zerdeParser.parseU32();
+      const pixelsPtr = self.zerdeParser.parseU32();
+      self.readPixels(passId, width, height, pixelsPtr);
+    },
   ];
 }
 